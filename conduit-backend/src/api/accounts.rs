@@ -0,0 +1,1125 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use axum::{
+    extract::{Path, Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
+    Extension, Json, Router,
+};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+
+use crate::memory::{init_store_dir, Election, MemoryStore, PassphraseHash, Principal, StoreOptions, WriterGuard};
+use crate::util::write_atomic;
+use super::auth::{Caller, KeyAuth};
+use super::connectors::ConnectorsConfig;
+use super::error::{ApiError, OpenAiErrorResponse};
+use super::inbox::InboxConfig;
+use super::scheduler::SchedulerConfig;
+use super::server::{self, ServerOptions};
+use super::state::ServerState;
+use super::sync::SyncConfig;
+use super::validation::ValidJson;
+use super::webhooks::WebhookConfig;
+use super::workspaces::{dispatch, is_valid_workspace_name};
+
+/// Folder inside the store holding the accounts file and each account's store
+const ACCOUNTS_DIR: &str = ".accounts";
+
+/// File in the accounts folder holding the accounts and the session signing key
+const ACCOUNTS_FILE: &str = "accounts.json";
+
+/// Folder in the accounts folder that the stores of removed accounts are moved to
+const DELETED_DIR: &str = ".deleted";
+
+/// Shortest password accepted, in characters
+const MIN_PASSWORD_CHARS: usize = 8;
+
+/// Name of the admin account created from `CONDUIT_ADMIN_PASSWORD`
+const BOOTSTRAP_ADMIN: &str = "admin";
+
+/// Header of every session token; no other algorithm is accepted
+const SESSION_HEADER: &str = r#"{"alg":"HS256","typ":"JWT"}"#;
+
+/// Failed logins to a name before each further attempt has to wait
+const FREE_LOGIN_FAILURES: u32 = 5;
+
+/// Longest wait between login attempts to a name, in seconds
+const MAX_LOGIN_BACKOFF_SECS: i64 = 300;
+
+/// Time without failed logins after which a name's failures are forgotten, in seconds
+const LOGIN_FAILURE_WINDOW_SECS: i64 = 900;
+
+/// Accounts that log in with a password and each keep their memories in a
+/// store of their own, set under `[accounts]`
+///
+/// ```toml
+/// [accounts]
+/// enabled = true
+/// dir = "/srv/conduit/accounts"
+/// session_ttl_secs = 43200
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccountsConfig {
+    pub enabled: bool,
+    /// Folder holding the accounts file and a store per account; `.accounts`
+    /// inside the `[storage]` store when unset
+    pub dir: Option<PathBuf>,
+    /// How long a session token from `/api/auth/login` is valid
+    pub session_ttl_secs: u64,
+    /// Password of an `admin` account created when there are no accounts yet,
+    /// only read from `CONDUIT_ADMIN_PASSWORD`
+    #[serde(skip)]
+    pub admin_password: Option<String>,
+}
+
+impl Default for AccountsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: None,
+            session_ttl_secs: 12 * 3600,
+            admin_password: None,
+        }
+    }
+}
+
+impl AccountsConfig {
+    /// Override the settings with `CONDUIT_ACCOUNTS` (`true` or `false`) and
+    /// `CONDUIT_ACCOUNTS_DIR`, and read `CONDUIT_ADMIN_PASSWORD`, if set
+    pub fn apply_env(&mut self) {
+        if let Ok(enabled) = std::env::var("CONDUIT_ACCOUNTS") {
+            self.enabled = matches!(enabled.trim().to_lowercase().as_str(), "true" | "1" | "yes");
+        }
+        if let Ok(dir) = std::env::var("CONDUIT_ACCOUNTS_DIR") {
+            self.dir = Some(PathBuf::from(dir)).filter(|dir| !dir.as_os_str().is_empty());
+        }
+        if let Ok(password) = std::env::var("CONDUIT_ADMIN_PASSWORD") {
+            self.admin_password = Some(password).filter(|password| !password.is_empty());
+        }
+    }
+}
+
+/// An account of a multi-user server
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Account {
+    pub name: String,
+    /// Admins manage the accounts of the server
+    pub admin: bool,
+    /// Groups memories can be shared with
+    pub groups: Vec<String>,
+    /// Disabled accounts cannot log in, and their sessions stop working
+    pub disabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub last_login_at: Option<DateTime<Utc>>,
+}
+
+impl Account {
+    fn principal(&self) -> Principal {
+        Principal { user: self.name.clone(), groups: self.groups.clone() }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredAccount {
+    #[serde(flatten)]
+    info: Account,
+    password: PassphraseHash,
+    /// Raised to end every session of the account at once
+    session_version: u32,
+}
+
+// Recent failed logins to one account name
+#[derive(Debug, Clone, Copy)]
+struct LoginFailures {
+    count: u32,
+    last: DateTime<Utc>,
+}
+
+impl LoginFailures {
+    // When the next attempt may be made
+    fn next_attempt(&self) -> DateTime<Utc> {
+        if self.count < FREE_LOGIN_FAILURES {
+            return self.last;
+        }
+        let backoff = 1i64.checked_shl(self.count - FREE_LOGIN_FAILURES).unwrap_or(i64::MAX);
+        self.last + Duration::seconds(backoff.clamp(1, MAX_LOGIN_BACKOFF_SECS))
+    }
+}
+
+// Checked for unknown names, so they take as long to reject as wrong passwords
+fn unknown_account_hash() -> &'static PassphraseHash {
+    static HASH: OnceLock<PassphraseHash> = OnceLock::new();
+    HASH.get_or_init(|| PassphraseHash::new(&rand::thread_rng().gen::<u64>().to_string()))
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct AccountsFile {
+    /// Key session tokens are signed with, hex-encoded
+    secret: String,
+    accounts: Vec<StoredAccount>,
+}
+
+/// A logged-in session
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Session {
+    /// Send as `Authorization: Bearer <token>`
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+    pub account: Account,
+}
+
+// Claims of a session token
+#[derive(Debug, Serialize, Deserialize)]
+struct SessionClaims {
+    sub: String,
+    ver: u32,
+    iat: i64,
+    exp: i64,
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct LoginRequest {
+    pub name: String,
+    pub password: String,
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct CreateAccountRequest {
+    pub name: String,
+    pub password: String,
+    #[serde(default)]
+    pub admin: bool,
+    #[serde(default)]
+    pub groups: Vec<String>,
+}
+
+/// Changes to an account; fields left out stay as they are
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct UpdateAccountRequest {
+    pub admin: Option<bool>,
+    pub groups: Option<Vec<String>>,
+    pub disabled: Option<bool>,
+    /// New password, which also ends the account's sessions
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+/// Set on requests authenticated with a session token, holding the account name
+#[derive(Debug, Clone)]
+pub struct AccountAuth(pub String);
+
+#[derive(Debug, thiserror::Error)]
+pub enum AccountError {
+    #[error("Invalid account name {0:?}: use letters, digits, `-` and `_`")]
+    InvalidName(String),
+
+    #[error("The password must have at least {} characters", MIN_PASSWORD_CHARS)]
+    WeakPassword,
+
+    #[error("Account {0} already exists")]
+    Exists(String),
+
+    #[error("Account not found: {0}")]
+    NotFound(String),
+
+    #[error("Wrong account name or password")]
+    InvalidCredentials,
+
+    #[error("The current password is wrong")]
+    WrongPassword,
+
+    #[error("Account {0} is disabled")]
+    Disabled(String),
+
+    #[error("Too many failed logins; retry in {0} seconds")]
+    TooManyAttempts(i64),
+
+    #[error("At least one enabled admin account must remain")]
+    LastAdmin,
+
+    #[error("Failed to save accounts: {0}")]
+    Storage(String),
+}
+
+impl AccountError {
+    fn status(&self) -> StatusCode {
+        match self {
+            Self::InvalidName(_) | Self::WeakPassword => StatusCode::BAD_REQUEST,
+            Self::InvalidCredentials => StatusCode::UNAUTHORIZED,
+            Self::WrongPassword | Self::Disabled(_) => StatusCode::FORBIDDEN,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::Exists(_) | Self::LastAdmin => StatusCode::CONFLICT,
+            Self::TooManyAttempts(_) => StatusCode::TOO_MANY_REQUESTS,
+            Self::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<AccountError> for ApiError {
+    fn from(err: AccountError) -> Self {
+        ApiError::status(err.status(), err.to_string())
+    }
+}
+
+fn check_password(password: &str) -> Result<(), AccountError> {
+    if password.chars().count() < MIN_PASSWORD_CHARS {
+        return Err(AccountError::WeakPassword);
+    }
+    Ok(())
+}
+
+// Whether any enabled account is an admin
+fn has_admin(accounts: &[StoredAccount]) -> bool {
+    accounts.iter().any(|stored| stored.info.admin && !stored.info.disabled)
+}
+
+/// Whether a bearer credential is a session token rather than a key
+pub fn is_session_token(key: &str) -> bool {
+    key.starts_with("eyJ") && key.matches('.').count() == 2
+}
+
+fn sign(secret: &[u8], message: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(message.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn encode_session(secret: &[u8], claims: &SessionClaims) -> String {
+    let claims = serde_json::to_vec(claims).expect("session claims serialize");
+    let body = format!("{}.{}", URL_SAFE_NO_PAD.encode(SESSION_HEADER), URL_SAFE_NO_PAD.encode(claims));
+    let signature = URL_SAFE_NO_PAD.encode(sign(secret, &body));
+    format!("{}.{}", body, signature)
+}
+
+// Claims of a token signed with `secret`, whatever its expiry
+fn decode_session(secret: &[u8], token: &str) -> Option<SessionClaims> {
+    let (body, signature) = token.rsplit_once('.')?;
+    let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(body.as_bytes());
+    mac.verify_slice(&signature).ok()?;
+
+    let (header, claims) = body.split_once('.')?;
+    if URL_SAFE_NO_PAD.decode(header).ok()? != SESSION_HEADER.as_bytes() {
+        return None;
+    }
+    serde_json::from_slice(&URL_SAFE_NO_PAD.decode(claims).ok()?).ok()
+}
+
+// The store of an account, opened on its first request
+struct AccountStore {
+    state: Arc<ServerState>,
+    router: Router,
+    // Released when the store is closed, handing it to the next instance
+    _writer: Option<WriterGuard>,
+}
+
+/// Accounts of a multi-user server, saved next to their stores
+///
+/// Only salted hashes of the passwords are saved. Requests with a session
+/// token go to the account's own store, with its own indexes and background
+/// tasks, so accounts never see each other's memories. Scheduled tasks, git
+/// sync, webhooks, connectors and inbound webhooks only run for the server's
+/// own store, since their settings name one store.
+pub struct Accounts {
+    root: PathBuf,
+    path: PathBuf,
+    session_ttl: Duration,
+    file: Mutex<AccountsFile>,
+    store_options: StoreOptions,
+    server_options: ServerOptions,
+    stores: Mutex<BTreeMap<String, AccountStore>>,
+    // Held while a store opens, so two requests never open the same one
+    opening: tokio::sync::Mutex<()>,
+    // Failed logins by account name, whether or not the account exists
+    login_failures: Mutex<HashMap<String, LoginFailures>>,
+}
+
+impl Accounts {
+    /// Load the accounts saved for the server of `store`, creating the
+    /// bootstrap admin when there are none and `CONDUIT_ADMIN_PASSWORD` is set
+    pub fn open(config: &AccountsConfig, store: &MemoryStore, options: &ServerOptions) -> Result<Self, String> {
+        let root = config.dir.clone().unwrap_or_else(|| store.base_path.join(ACCOUNTS_DIR));
+        let path = root.join(ACCOUNTS_FILE);
+        let mut file: AccountsFile = if path.is_file() {
+            let json = fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read accounts file {}: {}", path.display(), e))?;
+            serde_json::from_str(&json)
+                .map_err(|e| format!("Invalid accounts file {}: {}", path.display(), e))?
+        } else {
+            AccountsFile::default()
+        };
+        if !hex::decode(&file.secret).is_ok_and(|secret| secret.len() >= 32) {
+            let secret: [u8; 32] = rand::thread_rng().gen();
+            file.secret = hex::encode(secret);
+        }
+
+        let accounts = Self {
+            root,
+            path,
+            session_ttl: Duration::seconds(config.session_ttl_secs.clamp(60, 365 * 86400) as i64),
+            file: Mutex::new(file),
+            store_options: store.options.clone(),
+            server_options: options.clone(),
+            stores: Mutex::new(BTreeMap::new()),
+            opening: tokio::sync::Mutex::new(()),
+            login_failures: Mutex::new(HashMap::new()),
+        };
+        if let Some(password) = &config.admin_password {
+            if accounts.list().is_empty() {
+                let request = CreateAccountRequest {
+                    name: BOOTSTRAP_ADMIN.to_string(),
+                    password: password.clone(),
+                    admin: true,
+                    groups: Vec::new(),
+                };
+                accounts.create(request).map_err(|e| format!("Failed to create the admin account: {}", e))?;
+                info!("[ACCOUNTS] Created the {} account from CONDUIT_ADMIN_PASSWORD", BOOTSTRAP_ADMIN);
+            }
+        }
+        Ok(accounts)
+    }
+
+    /// Every account, by name
+    pub fn list(&self) -> Vec<Account> {
+        let file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        let mut accounts: Vec<Account> = file.accounts.iter().map(|stored| stored.info.clone()).collect();
+        accounts.sort_by(|a, b| a.name.cmp(&b.name));
+        accounts
+    }
+
+    /// The account named `name`, if it exists
+    pub fn get(&self, name: &str) -> Option<Account> {
+        let file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        file.accounts.iter().find(|stored| stored.info.name == name).map(|stored| stored.info.clone())
+    }
+
+    pub fn create(&self, request: CreateAccountRequest) -> Result<Account, AccountError> {
+        let name = request.name.trim();
+        if !is_valid_workspace_name(name) {
+            return Err(AccountError::InvalidName(name.to_string()));
+        }
+        check_password(&request.password)?;
+        let account = Account {
+            name: name.to_string(),
+            admin: request.admin,
+            groups: request.groups,
+            disabled: false,
+            created_at: Utc::now(),
+            last_login_at: None,
+        };
+        let password = PassphraseHash::new(&request.password);
+        self.update(|accounts| {
+            if accounts.iter().any(|stored| stored.info.name == account.name) {
+                return Err(AccountError::Exists(account.name.clone()));
+            }
+            // A store left behind by an account of the same name is not handed to the new one
+            self.archive_store(&account.name)?;
+            accounts.push(StoredAccount { info: account.clone(), password, session_version: 0 });
+            Ok(account.clone())
+        })
+    }
+
+    /// Change an account; a new password or disabling it ends its sessions
+    pub fn modify(&self, name: &str, request: UpdateAccountRequest) -> Result<Account, AccountError> {
+        if let Some(password) = &request.password {
+            check_password(password)?;
+        }
+        let password = request.password.as_deref().map(PassphraseHash::new);
+        let account = self.update(|accounts| {
+            let stored = accounts.iter_mut()
+                .find(|stored| stored.info.name == name)
+                .ok_or_else(|| AccountError::NotFound(name.to_string()))?;
+            if let Some(admin) = request.admin {
+                stored.info.admin = admin;
+            }
+            if let Some(groups) = request.groups {
+                stored.info.groups = groups;
+            }
+            if let Some(disabled) = request.disabled {
+                if disabled && !stored.info.disabled {
+                    stored.session_version += 1;
+                }
+                stored.info.disabled = disabled;
+            }
+            if let Some(password) = password {
+                stored.password = password;
+                stored.session_version += 1;
+            }
+            Ok(stored.info.clone())
+        })?;
+        if account.disabled {
+            self.close_store(name);
+        }
+        Ok(account)
+    }
+
+    /// Remove an account and close its store, which is moved to the
+    /// `.deleted` folder so an account created later with the same name
+    /// starts empty
+    pub fn delete(&self, name: &str) -> Result<Account, AccountError> {
+        let account = self.update(|accounts| {
+            let index = accounts.iter()
+                .position(|stored| stored.info.name == name)
+                .ok_or_else(|| AccountError::NotFound(name.to_string()))?;
+            Ok(accounts.remove(index).info)
+        })?;
+        self.close_store(name);
+        self.archive_store(name)?;
+        Ok(account)
+    }
+
+    // Move the store of an account to `.deleted/<name>-<time>`, if it has one
+    fn archive_store(&self, name: &str) -> Result<(), AccountError> {
+        let path = self.root.join(name);
+        if !path.exists() {
+            return Ok(());
+        }
+        let deleted = self.root.join(DELETED_DIR);
+        let archived = deleted.join(format!("{}-{}", name, Utc::now().format("%Y%m%dT%H%M%S%.3f")));
+        fs::create_dir_all(&deleted)
+            .and_then(|_| fs::rename(&path, &archived))
+            .map_err(|e| AccountError::Storage(format!("Failed to move the store of account {} aside: {}", name, e)))?;
+        info!("[ACCOUNTS] Moved the store of removed account {} to {}", name, archived.display());
+        Ok(())
+    }
+
+    /// Check a password and start a session
+    ///
+    /// After a few failed logins to a name, each further attempt has to wait
+    /// twice as long as the one before, up to five minutes.
+    pub fn login(&self, name: &str, password: &str) -> Result<Session, AccountError> {
+        let now = Utc::now();
+        self.begin_login(name, now)?;
+        let stored = {
+            let file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+            file.accounts.iter().find(|stored| stored.info.name == name).cloned()
+        };
+        let verified = match &stored {
+            Some(stored) => stored.password.verify(password),
+            None => {
+                unknown_account_hash().verify(password);
+                false
+            }
+        };
+        let mut failures = self.login_failures.lock().unwrap_or_else(|e| e.into_inner());
+        if !verified {
+            // The wait starts once the attempt has failed, not when hashing began
+            if let Some(failure) = failures.get_mut(name) {
+                failure.last = Utc::now();
+            }
+            return Err(AccountError::InvalidCredentials);
+        }
+        failures.remove(name);
+        drop(failures);
+        let stored = stored.ok_or(AccountError::InvalidCredentials)?;
+        if stored.info.disabled {
+            return Err(AccountError::Disabled(stored.info.name));
+        }
+
+        let account = self.update(|accounts| {
+            let stored = accounts.iter_mut()
+                .find(|stored| stored.info.name == name)
+                .ok_or_else(|| AccountError::NotFound(name.to_string()))?;
+            stored.info.last_login_at = Some(now);
+            Ok(stored.info.clone())
+        })?;
+        Ok(self.session(account, stored.session_version))
+    }
+
+    // Count a login attempt as failed until it succeeds, refusing it while
+    // the name has to wait; counting up front also holds back parallel attempts
+    fn begin_login(&self, name: &str, now: DateTime<Utc>) -> Result<(), AccountError> {
+        let mut failures = self.login_failures.lock().unwrap_or_else(|e| e.into_inner());
+        let window = Duration::seconds(LOGIN_FAILURE_WINDOW_SECS);
+        failures.retain(|_, failure| now - failure.last < window);
+
+        let failure = failures.entry(name.to_string()).or_insert(LoginFailures { count: 0, last: now });
+        let next = failure.next_attempt();
+        if now < next {
+            let wait = (next - now).num_milliseconds();
+            return Err(AccountError::TooManyAttempts((wait + 999) / 1000));
+        }
+        failure.count += 1;
+        failure.last = now;
+        Ok(())
+    }
+
+    /// Check the current password, set a new one and start a new session,
+    /// ending the account's other sessions
+    pub fn change_password(&self, name: &str, request: &ChangePasswordRequest) -> Result<Session, AccountError> {
+        check_password(&request.new_password)?;
+        let password = PassphraseHash::new(&request.new_password);
+        let (account, version) = self.update(|accounts| {
+            let stored = accounts.iter_mut()
+                .find(|stored| stored.info.name == name)
+                .ok_or_else(|| AccountError::NotFound(name.to_string()))?;
+            if !stored.password.verify(&request.current_password) {
+                return Err(AccountError::WrongPassword);
+            }
+            stored.password = password;
+            stored.session_version += 1;
+            Ok((stored.info.clone(), stored.session_version))
+        })?;
+        Ok(self.session(account, version))
+    }
+
+    /// End every session of an account
+    pub fn end_sessions(&self, name: &str) -> Result<(), AccountError> {
+        self.update(|accounts| {
+            let stored = accounts.iter_mut()
+                .find(|stored| stored.info.name == name)
+                .ok_or_else(|| AccountError::NotFound(name.to_string()))?;
+            stored.session_version += 1;
+            Ok(())
+        })
+    }
+
+    /// The account a session token belongs to, if it is valid, unexpired and
+    /// its account is enabled
+    pub fn verify_session(&self, token: &str) -> Option<Account> {
+        let file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        let secret = hex::decode(&file.secret).ok()?;
+        let claims = decode_session(&secret, token).filter(|claims| claims.exp > Utc::now().timestamp())?;
+        file.accounts.iter()
+            .find(|stored| stored.info.name == claims.sub && stored.session_version == claims.ver)
+            .map(|stored| stored.info.clone())
+            .filter(|account| !account.disabled)
+    }
+
+    fn session(&self, account: Account, version: u32) -> Session {
+        let now = Utc::now();
+        let expires_at = now + self.session_ttl;
+        let claims = SessionClaims {
+            sub: account.name.clone(),
+            ver: version,
+            iat: now.timestamp(),
+            exp: expires_at.timestamp(),
+        };
+        let secret = {
+            let file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+            hex::decode(&file.secret).unwrap_or_default()
+        };
+        Session { token: encode_session(&secret, &claims), expires_at, account }
+    }
+
+    // Change the accounts and save them, leaving them unchanged if saving fails
+    fn update<T>(&self, change: impl FnOnce(&mut Vec<StoredAccount>) -> Result<T, AccountError>) -> Result<T, AccountError> {
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        let mut changed = file.clone();
+        let result = change(&mut changed.accounts)?;
+        if has_admin(&file.accounts) && !has_admin(&changed.accounts) {
+            return Err(AccountError::LastAdmin);
+        }
+
+        let json = serde_json::to_string_pretty(&changed).map_err(|e| AccountError::Storage(e.to_string()))?;
+        fs::create_dir_all(&self.root)
+            .and_then(|_| write_atomic(&self.path, json))
+            .map_err(|e| AccountError::Storage(e.to_string()))?;
+
+        *file = changed;
+        Ok(result)
+    }
+
+    /// Routes of an account's store, opening it and starting its background
+    /// tasks on first use
+    ///
+    /// A store already written to by another process is served read-only.
+    pub async fn store_router(self: &Arc<Self>, name: &str) -> Result<Router, String> {
+        if let Some(store) = self.stores.lock().unwrap_or_else(|e| e.into_inner()).get(name) {
+            return Ok(store.router.clone());
+        }
+        let _opening = self.opening.lock().await;
+        if let Some(store) = self.stores.lock().unwrap_or_else(|e| e.into_inner()).get(name) {
+            return Ok(store.router.clone());
+        }
+
+        let path = self.root.join(name);
+        init_store_dir(&path).map_err(|e| format!("Failed to create the store of account {}: {}", name, e))?;
+        let store = Arc::new(MemoryStore::with_options(&path, self.store_options.clone()));
+        let mut writer = None;
+        if !store.is_read_only() {
+            let election_store = store.clone();
+            let election = tokio::task::spawn_blocking(move || election_store.elect_writer())
+                .await
+                .map_err(|e| format!("Writer election for account {} failed: {}", name, e))?;
+            match election {
+                Ok(Election::Won(guard)) => writer = Some(guard),
+                Ok(Election::Lost(_)) => {
+                    warn!("[ACCOUNTS] Store of account {} is already served by another Conduit process, serving it read-only", name);
+                    store.set_read_only(true);
+                }
+                Err(e) => warn!("[ACCOUNTS] Could not elect a writer for account {}, continuing without the lock: {}", name, e),
+            }
+        }
+
+        let options = ServerOptions {
+            workspace: None,
+            workspaces: BTreeMap::new(),
+            scheduler: SchedulerConfig::default(),
+            sync: SyncConfig::default(),
+            webhooks: WebhookConfig::default(),
+            connectors: ConnectorsConfig::default(),
+            inbox: InboxConfig::default(),
+            ..self.server_options.clone()
+        };
+        let state = ServerState::start_for_account(store, &options, self.clone(), name)
+            .map_err(|e| format!("Failed to open the store of account {}: {}", name, e))?;
+        info!("[ACCOUNTS] Serving account {} from {}", name, path.display());
+        let router = server::router(state.clone());
+        self.stores.lock().unwrap_or_else(|e| e.into_inner())
+            .insert(name.to_string(), AccountStore { state, router: router.clone(), _writer: writer });
+        Ok(router)
+    }
+
+    // Stop the background tasks of an account's store and release it
+    fn close_store(&self, name: &str) {
+        if let Some(store) = self.stores.lock().unwrap_or_else(|e| e.into_inner()).remove(name) {
+            store.state.stop();
+        }
+    }
+
+    /// Stop the background tasks of every open account store and release them
+    pub fn stop(&self) {
+        let stores = std::mem::take(&mut *self.stores.lock().unwrap_or_else(|e| e.into_inner()));
+        for store in stores.values() {
+            store.state.stop();
+        }
+    }
+}
+
+/// Authenticate a request carrying a session token, sending it to the routes
+/// of the account's own store unless `state` already serves that store
+pub async fn authenticate_session(
+    state: &ServerState,
+    accounts: &Arc<Accounts>,
+    token: &str,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let Some(account) = accounts.verify_session(token) else {
+        warn!("[AUTH] Rejected request to {} with an invalid or expired session", request.uri().path());
+        let err = ApiError::authentication("Invalid or expired session; log in again").code("invalid_session");
+        return ([(header::WWW_AUTHENTICATE, "Bearer")], err).into_response();
+    };
+    if state.account.as_deref() == Some(account.name.as_str()) {
+        request.extensions_mut().insert(Caller(Some(account.principal())));
+        request.extensions_mut().insert(AccountAuth(account.name));
+        return next.run(request).await;
+    }
+    match accounts.store_router(&account.name).await {
+        Ok(router) => dispatch(router, request).await,
+        Err(e) => {
+            error!("[ACCOUNTS] {}", e);
+            ApiError::store_unavailable(e).into_response()
+        }
+    }
+}
+
+/// Routes of the signed-in account and, for admins, every account
+pub fn router() -> Router<Arc<ServerState>> {
+    Router::new()
+        .route("/api/auth/me", get(current_account_handler))
+        .route("/api/auth/logout", post(logout_handler))
+        .route("/api/auth/password", put(change_password_handler))
+        .route("/api/accounts", get(list_accounts_handler).post(create_account_handler))
+        .route(
+            "/api/accounts/:name",
+            get(get_account_handler).patch(update_account_handler).delete(delete_account_handler),
+        )
+}
+
+/// The login route, which has to be reachable without credentials
+pub fn login_router() -> Router<Arc<ServerState>> {
+    Router::new().route("/api/auth/login", post(login_handler))
+}
+
+fn accounts_of(state: &ServerState) -> Result<&Arc<Accounts>, ApiError> {
+    state.accounts.as_ref().ok_or_else(|| {
+        ApiError::not_found("Accounts are not enabled; set `enabled = true` under [accounts]").code("accounts_disabled")
+    })
+}
+
+// The accounts, if the request may manage them: an admin's session or one of the `api_keys`
+fn admin_accounts<'a>(
+    state: &'a ServerState,
+    account: Option<&AccountAuth>,
+    key: Option<&KeyAuth>,
+) -> Result<&'a Arc<Accounts>, ApiError> {
+    let accounts = accounts_of(state)?;
+    if !is_admin(accounts, account, key) {
+        return Err(ApiError::permission("Only admins can manage accounts"));
+    }
+    Ok(accounts)
+}
+
+fn is_admin(accounts: &Accounts, account: Option<&AccountAuth>, key: Option<&KeyAuth>) -> bool {
+    match account {
+        Some(AccountAuth(name)) => accounts.get(name).is_some_and(|account| account.admin),
+        None => key.is_some(),
+    }
+}
+
+/// Refuse requests that are not from an admin or one of the `api_keys`, when
+/// accounts are enabled
+pub(crate) fn require_admin(
+    state: &ServerState,
+    account: Option<&AccountAuth>,
+    key: Option<&KeyAuth>,
+    action: &str,
+) -> Result<(), ApiError> {
+    match &state.accounts {
+        Some(accounts) if !is_admin(accounts, account, key) => {
+            Err(ApiError::permission(format!("Only admins can {}", action)))
+        }
+        _ => Ok(()),
+    }
+}
+
+// The account of a session request
+fn session_account(account: Option<Extension<AccountAuth>>) -> Result<String, ApiError> {
+    account
+        .map(|Extension(AccountAuth(name))| name)
+        .ok_or_else(|| ApiError::invalid_request("Only requests with a session token from /api/auth/login have an account"))
+}
+
+async fn login(
+    State(state): State<Arc<ServerState>>,
+    request: LoginRequest,
+) -> Response {
+    info!("[SERVER] Handling login request for {}", request.name);
+    let accounts = match accounts_of(&state) {
+        Ok(accounts) => accounts.clone(),
+        Err(err) => return err.into_response(),
+    };
+    // Hashing takes a while, so it stays off the async workers
+    let result = tokio::task::spawn_blocking(move || accounts.login(request.name.trim(), &request.password)).await;
+    match result {
+        Ok(Ok(session)) => (StatusCode::OK, Json(session)).into_response(),
+        Ok(Err(err)) => {
+            warn!("[AUTH] Failed login: {}", err);
+            let retry_after = match &err {
+                AccountError::TooManyAttempts(secs) => HeaderValue::from_str(&secs.to_string()).ok(),
+                _ => None,
+            };
+            let mut response = ApiError::from(err).into_response();
+            if let Some(retry_after) = retry_after {
+                response.headers_mut().insert(header::RETRY_AFTER, retry_after);
+            }
+            response
+        }
+        Err(e) => ApiError::server(format!("Login failed: {}", e)).into_response(),
+    }
+}
+
+async fn current_account(
+    State(state): State<Arc<ServerState>>,
+    account: Option<Extension<AccountAuth>>,
+) -> Response {
+    info!("[SERVER] Handling current_account request");
+    let result = accounts_of(&state).and_then(|accounts| {
+        let name = session_account(account)?;
+        accounts.get(&name).ok_or_else(|| AccountError::NotFound(name).into())
+    });
+    match result {
+        Ok(account) => (StatusCode::OK, Json(account)).into_response(),
+        Err(err) => err.into_response(),
+    }
+}
+
+async fn logout(
+    State(state): State<Arc<ServerState>>,
+    account: Option<Extension<AccountAuth>>,
+) -> Response {
+    info!("[SERVER] Handling logout request");
+    let result = accounts_of(&state).and_then(|accounts| {
+        let name = session_account(account)?;
+        accounts.end_sessions(&name).map_err(ApiError::from)
+    });
+    match result {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(err) => err.into_response(),
+    }
+}
+
+async fn change_password(
+    State(state): State<Arc<ServerState>>,
+    account: Option<Extension<AccountAuth>>,
+    request: ChangePasswordRequest,
+) -> Response {
+    info!("[SERVER] Handling change_password request");
+    let (accounts, name) = match accounts_of(&state).and_then(|accounts| Ok((accounts.clone(), session_account(account)?))) {
+        Ok(found) => found,
+        Err(err) => return err.into_response(),
+    };
+    let result = tokio::task::spawn_blocking(move || accounts.change_password(&name, &request)).await;
+    match result {
+        Ok(Ok(session)) => (StatusCode::OK, Json(session)).into_response(),
+        Ok(Err(err)) => ApiError::from(err).into_response(),
+        Err(e) => ApiError::server(format!("Changing the password failed: {}", e)).into_response(),
+    }
+}
+
+async fn list_accounts(
+    State(state): State<Arc<ServerState>>,
+    account: Option<Extension<AccountAuth>>,
+    key: Option<Extension<KeyAuth>>,
+) -> Response {
+    info!("[SERVER] Handling list_accounts request");
+    match admin_accounts(&state, account.as_deref(), key.as_deref()) {
+        Ok(accounts) => (StatusCode::OK, Json(accounts.list())).into_response(),
+        Err(err) => err.into_response(),
+    }
+}
+
+async fn create_account(
+    State(state): State<Arc<ServerState>>,
+    account: Option<Extension<AccountAuth>>,
+    key: Option<Extension<KeyAuth>>,
+    request: CreateAccountRequest,
+) -> Response {
+    info!("[SERVER] Handling create_account request for {}", request.name);
+    let accounts = match admin_accounts(&state, account.as_deref(), key.as_deref()) {
+        Ok(accounts) => accounts.clone(),
+        Err(err) => return err.into_response(),
+    };
+    match tokio::task::spawn_blocking(move || accounts.create(request)).await {
+        Ok(Ok(account)) => (StatusCode::CREATED, Json(account)).into_response(),
+        Ok(Err(err)) => {
+            error!("[SERVER] Failed to create account: {}", err);
+            ApiError::from(err).into_response()
+        }
+        Err(e) => ApiError::server(format!("Creating the account failed: {}", e)).into_response(),
+    }
+}
+
+async fn get_account(
+    State(state): State<Arc<ServerState>>,
+    account: Option<Extension<AccountAuth>>,
+    key: Option<Extension<KeyAuth>>,
+    Path(name): Path<String>,
+) -> Response {
+    info!("[SERVER] Handling get_account request for {}", name);
+    let result = admin_accounts(&state, account.as_deref(), key.as_deref())
+        .and_then(|accounts| accounts.get(&name).ok_or_else(|| AccountError::NotFound(name).into()));
+    match result {
+        Ok(account) => (StatusCode::OK, Json(account)).into_response(),
+        Err(err) => err.into_response(),
+    }
+}
+
+async fn update_account(
+    State(state): State<Arc<ServerState>>,
+    account: Option<Extension<AccountAuth>>,
+    key: Option<Extension<KeyAuth>>,
+    Path(name): Path<String>,
+    request: UpdateAccountRequest,
+) -> Response {
+    info!("[SERVER] Handling update_account request for {}", name);
+    let accounts = match admin_accounts(&state, account.as_deref(), key.as_deref()) {
+        Ok(accounts) => accounts.clone(),
+        Err(err) => return err.into_response(),
+    };
+    match tokio::task::spawn_blocking(move || accounts.modify(&name, request)).await {
+        Ok(Ok(account)) => (StatusCode::OK, Json(account)).into_response(),
+        Ok(Err(err)) => ApiError::from(err).into_response(),
+        Err(e) => ApiError::server(format!("Updating the account failed: {}", e)).into_response(),
+    }
+}
+
+async fn delete_account(
+    State(state): State<Arc<ServerState>>,
+    account: Option<Extension<AccountAuth>>,
+    key: Option<Extension<KeyAuth>>,
+    Path(name): Path<String>,
+) -> Response {
+    info!("[SERVER] Handling delete_account request for {}", name);
+    let result = admin_accounts(&state, account.as_deref(), key.as_deref())
+        .and_then(|accounts| accounts.delete(&name).map_err(ApiError::from));
+    match result {
+        Ok(account) => (StatusCode::OK, Json(account)).into_response(),
+        Err(err) => err.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post, path = "/api/auth/login", tag = "accounts", operation_id = "login",
+    request_body = LoginRequest,
+    responses(
+        (status = 200, description = "A session token for the account's own store", body = Session),
+        (status = 401, description = "Wrong account name or password", body = OpenAiErrorResponse),
+        (status = 403, description = "The account is disabled", body = OpenAiErrorResponse),
+        (status = 429, description = "Too many failed logins to this name; retry after `Retry-After` seconds", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn login_handler(
+    state: State<Arc<ServerState>>,
+    ValidJson(request): ValidJson<LoginRequest>,
+) -> Response {
+    login(state, request).await
+}
+
+#[utoipa::path(
+    get, path = "/api/auth/me", tag = "accounts", operation_id = "current_account",
+    responses(
+        (status = 200, description = "The account of the session", body = Account),
+        (status = 400, description = "Not called with a session token", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn current_account_handler(
+    state: State<Arc<ServerState>>,
+    account: Option<Extension<AccountAuth>>,
+) -> Response {
+    current_account(state, account).await
+}
+
+#[utoipa::path(
+    post, path = "/api/auth/logout", tag = "accounts", operation_id = "logout",
+    responses(
+        (status = 204, description = "Every session of the account has ended"),
+        (status = 400, description = "Not called with a session token", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn logout_handler(
+    state: State<Arc<ServerState>>,
+    account: Option<Extension<AccountAuth>>,
+) -> Response {
+    logout(state, account).await
+}
+
+#[utoipa::path(
+    put, path = "/api/auth/password", tag = "accounts", operation_id = "change_password",
+    request_body = ChangePasswordRequest,
+    responses(
+        (status = 200, description = "A new session; the account's other sessions have ended", body = Session),
+        (status = 400, description = "The new password is too short, or not called with a session token", body = OpenAiErrorResponse),
+        (status = 403, description = "The current password is wrong", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn change_password_handler(
+    state: State<Arc<ServerState>>,
+    account: Option<Extension<AccountAuth>>,
+    ValidJson(request): ValidJson<ChangePasswordRequest>,
+) -> Response {
+    change_password(state, account, request).await
+}
+
+#[utoipa::path(
+    get, path = "/api/accounts", tag = "accounts", operation_id = "list_accounts",
+    responses(
+        (status = 200, description = "Every account, without passwords", body = [Account]),
+        (status = 403, description = "Not called by an admin or with an API key", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn list_accounts_handler(
+    state: State<Arc<ServerState>>,
+    account: Option<Extension<AccountAuth>>,
+    key: Option<Extension<KeyAuth>>,
+) -> Response {
+    list_accounts(state, account, key).await
+}
+
+#[utoipa::path(
+    post, path = "/api/accounts", tag = "accounts", operation_id = "create_account",
+    request_body = CreateAccountRequest,
+    responses(
+        (status = 201, description = "The account", body = Account),
+        (status = 400, description = "Invalid name or too short a password", body = OpenAiErrorResponse),
+        (status = 403, description = "Not called by an admin or with an API key", body = OpenAiErrorResponse),
+        (status = 409, description = "An account with the name exists", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn create_account_handler(
+    state: State<Arc<ServerState>>,
+    account: Option<Extension<AccountAuth>>,
+    key: Option<Extension<KeyAuth>>,
+    ValidJson(request): ValidJson<CreateAccountRequest>,
+) -> Response {
+    create_account(state, account, key, request).await
+}
+
+#[utoipa::path(
+    get, path = "/api/accounts/{name}", tag = "accounts", operation_id = "get_account",
+    params(("name" = String, Path, description = "Account name")),
+    responses(
+        (status = 200, description = "The account", body = Account),
+        (status = 403, description = "Not called by an admin or with an API key", body = OpenAiErrorResponse),
+        (status = 404, description = "Account not found", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn get_account_handler(
+    state: State<Arc<ServerState>>,
+    account: Option<Extension<AccountAuth>>,
+    key: Option<Extension<KeyAuth>>,
+    path: Path<String>,
+) -> Response {
+    get_account(state, account, key, path).await
+}
+
+#[utoipa::path(
+    patch, path = "/api/accounts/{name}", tag = "accounts", operation_id = "update_account",
+    params(("name" = String, Path, description = "Account name")),
+    request_body = UpdateAccountRequest,
+    responses(
+        (status = 200, description = "The changed account", body = Account),
+        (status = 400, description = "Too short a password", body = OpenAiErrorResponse),
+        (status = 403, description = "Not called by an admin or with an API key", body = OpenAiErrorResponse),
+        (status = 404, description = "Account not found", body = OpenAiErrorResponse),
+        (status = 409, description = "The change would leave no enabled admin", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn update_account_handler(
+    state: State<Arc<ServerState>>,
+    account: Option<Extension<AccountAuth>>,
+    key: Option<Extension<KeyAuth>>,
+    path: Path<String>,
+    ValidJson(request): ValidJson<UpdateAccountRequest>,
+) -> Response {
+    update_account(state, account, key, path, request).await
+}
+
+#[utoipa::path(
+    delete, path = "/api/accounts/{name}", tag = "accounts", operation_id = "delete_account",
+    params(("name" = String, Path, description = "Account name")),
+    responses(
+        (status = 200, description = "The removed account; its store is moved to `.deleted`", body = Account),
+        (status = 403, description = "Not called by an admin or with an API key", body = OpenAiErrorResponse),
+        (status = 404, description = "Account not found", body = OpenAiErrorResponse),
+        (status = 409, description = "It is the last enabled admin", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn delete_account_handler(
+    state: State<Arc<ServerState>>,
+    account: Option<Extension<AccountAuth>>,
+    key: Option<Extension<KeyAuth>>,
+    path: Path<String>,
+) -> Response {
+    delete_account(state, account, key, path).await
+}
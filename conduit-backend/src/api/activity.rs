@@ -0,0 +1,400 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Json, Router,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{error, info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::memory::{
+    ImportAction, ImportReport, Memory, MemoryAccess, MemoryError, MemoryEvent, MemoryEventKind, MemoryStore, Principal, SyncReport,
+};
+use super::error::{ApiError, OpenAiErrorResponse};
+use super::auth::Caller;
+use super::cache;
+use super::scheduler::{RunStatus, TaskRun};
+use super::state::ServerState;
+
+/// Number of recorded entries kept when none is configured
+const DEFAULT_HISTORY_SIZE: usize = 1000;
+
+/// Entries returned when the request does not say
+const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// Most entries returned for one request
+const MAX_PAGE_SIZE: usize = 500;
+
+/// Changes this soon after a memory was created count as part of creating it
+const EDIT_GRACE_SECS: i64 = 1;
+
+/// How much activity is kept, set under `[activity]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ActivityConfig {
+    /// Number of entries recorded while the server runs that are kept, oldest dropped first
+    pub history_size: usize,
+}
+
+impl Default for ActivityConfig {
+    fn default() -> Self {
+        Self { history_size: DEFAULT_HISTORY_SIZE }
+    }
+}
+
+impl ActivityConfig {
+    /// Override the history size with `CONDUIT_ACTIVITY_HISTORY_SIZE`, if set
+    pub fn apply_env(&mut self) {
+        if let Some(size) = std::env::var("CONDUIT_ACTIVITY_HISTORY_SIZE").ok().and_then(|v| v.parse().ok()) {
+            self.history_size = size;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityKind {
+    Created,
+    Updated,
+    Deleted,
+    /// A memory's `remind_at` time passed
+    Reminder,
+    Import,
+    Sync,
+    /// A scheduled task ran
+    Task,
+}
+
+impl ActivityKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Created => "created",
+            Self::Updated => "updated",
+            Self::Deleted => "deleted",
+            Self::Reminder => "reminder",
+            Self::Import => "import",
+            Self::Sync => "sync",
+            Self::Task => "task",
+        }
+    }
+
+    fn is_memory(self) -> bool {
+        matches!(self, Self::Created | Self::Updated | Self::Deleted | Self::Reminder)
+    }
+}
+
+// Summary of a memory change or reminder, naming the memory by title or ID
+fn memory_summary(kind: ActivityKind, name: &str) -> String {
+    match kind {
+        ActivityKind::Created => format!("Created \"{}\"", name),
+        ActivityKind::Updated => format!("Edited \"{}\"", name),
+        ActivityKind::Deleted => format!("Moved \"{}\" to the trash", name),
+        _ => format!("Reminder for \"{}\"", name),
+    }
+}
+
+/// Something that happened in the store
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ActivityEntry {
+    pub id: String,
+    pub kind: ActivityKind,
+    pub timestamp: DateTime<Utc>,
+    /// The memory the entry is about, for memory changes and reminders
+    pub memory_id: Option<String>,
+    /// Title of the memory, or name of the scheduled task
+    pub title: Option<String>,
+    /// What happened, in a sentence
+    pub summary: String,
+    /// Why an import, sync or task failed
+    pub error: Option<String>,
+}
+
+impl ActivityEntry {
+    fn new(kind: ActivityKind, timestamp: DateTime<Utc>, summary: String) -> Self {
+        Self {
+            id: ulid::Ulid::new().to_string(),
+            kind,
+            timestamp,
+            memory_id: None,
+            title: None,
+            summary,
+            error: None,
+        }
+    }
+
+    // An entry read from a memory's dates, with an ID that stays the same between requests
+    fn derived(kind: ActivityKind, memory: &Memory, timestamp: DateTime<Utc>) -> Self {
+        Self {
+            id: format!("{}-{}-{}", memory.id, kind.as_str(), timestamp.timestamp_millis()),
+            memory_id: Some(memory.id.clone()),
+            title: Some(memory.title.clone()),
+            ..Self::new(kind, timestamp, memory_summary(kind, &memory.title))
+        }
+    }
+}
+
+/// Filters and page of the activity feed
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ActivityQuery {
+    /// Most entries to return, 50 by default and at most 500
+    pub limit: Option<usize>,
+    /// Number of entries to skip
+    pub offset: Option<usize>,
+    /// Only entries of this kind
+    pub kind: Option<ActivityKind>,
+    /// Only entries about this memory
+    pub memory_id: Option<String>,
+    /// Only entries at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only entries at or before this time
+    pub until: Option<DateTime<Utc>>,
+}
+
+/// A page of the activity feed, newest first, with the number of matching entries
+pub struct ActivityPage {
+    pub items: Vec<ActivityEntry>,
+    pub total: usize,
+}
+
+/// Activity recorded while the server runs
+///
+/// Memory changes from before the server started are read from the dates of
+/// the memories and the trash instead, so the feed reaches back past a
+/// restart. Reminders are read from the memories whenever the feed is built.
+pub struct ActivityLog {
+    config: ActivityConfig,
+    started_at: DateTime<Utc>,
+    entries: Mutex<VecDeque<ActivityEntry>>,
+}
+
+impl ActivityLog {
+    pub fn new(config: ActivityConfig) -> Self {
+        Self {
+            config,
+            started_at: Utc::now(),
+            entries: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Add an entry, dropping the oldest once the history is full
+    pub fn record(&self, entry: ActivityEntry) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        while entries.len() >= self.config.history_size.max(1) {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    fn record_event(&self, event: &MemoryEvent) {
+        let kind = match event.kind {
+            MemoryEventKind::Created => ActivityKind::Created,
+            MemoryEventKind::Updated => ActivityKind::Updated,
+            MemoryEventKind::Deleted => ActivityKind::Deleted,
+        };
+        let title = event.memory.as_ref().map(|memory| memory.title.clone());
+        let summary = memory_summary(kind, title.as_deref().unwrap_or(&event.id));
+        self.record(ActivityEntry {
+            memory_id: Some(event.id.clone()),
+            title,
+            ..ActivityEntry::new(kind, event.timestamp, summary)
+        });
+    }
+
+    /// Record the outcome of an import that was not a dry run
+    pub fn record_import(&self, source: &str, result: &Result<ImportReport, MemoryError>) {
+        let entry = match result {
+            Ok(report) if report.dry_run => return,
+            Ok(report) => {
+                let summary = format!(
+                    "Imported {} from {}: {} created, {} updated, {} skipped, {} failed",
+                    report.items.len(),
+                    source,
+                    report.count(ImportAction::Created) + report.count(ImportAction::Duplicated),
+                    report.count(ImportAction::Overwritten) + report.count(ImportAction::Merged),
+                    report.count(ImportAction::Skipped),
+                    report.count(ImportAction::Failed),
+                );
+                ActivityEntry::new(ActivityKind::Import, report.completed_at, summary)
+            }
+            Err(err) => ActivityEntry {
+                error: Some(err.to_string()),
+                ..ActivityEntry::new(ActivityKind::Import, Utc::now(), format!("Import from {} failed", source))
+            },
+        };
+        self.record(entry);
+    }
+
+    /// Record the outcome of a sync with the remote
+    pub fn record_sync(&self, result: &Result<SyncReport, MemoryError>) {
+        let entry = match result {
+            Ok(report) => {
+                let summary = format!(
+                    "Synced {}: {} memories pulled, {} conflicts",
+                    report.branch,
+                    report.pulled.len(),
+                    report.conflicts.len(),
+                );
+                ActivityEntry::new(ActivityKind::Sync, report.completed_at, summary)
+            }
+            Err(err) => ActivityEntry {
+                error: Some(err.to_string()),
+                ..ActivityEntry::new(ActivityKind::Sync, Utc::now(), "Sync failed".to_string())
+            },
+        };
+        self.record(entry);
+    }
+
+    /// Record a finished run of a scheduled task
+    pub fn record_task(&self, run: &TaskRun) {
+        let summary = match (run.status, &run.output) {
+            (RunStatus::Failed, _) => format!("Task {} failed", run.task),
+            (_, Some(output)) => format!("Task {} ran: {}", run.task, output),
+            (_, None) => format!("Task {} ran", run.task),
+        };
+        self.record(ActivityEntry {
+            title: Some(run.task.clone()),
+            error: run.error.clone(),
+            ..ActivityEntry::new(ActivityKind::Task, run.finished_at.unwrap_or(run.started_at), summary)
+        });
+    }
+
+    /// Recorded and derived entries matching `query`, newest first
+    ///
+    /// With a `principal`, only entries about memories it can read are
+    /// included; imports, syncs and tasks are left out.
+    pub fn feed(&self, store: &MemoryStore, query: &ActivityQuery, principal: Option<&Principal>) -> Result<ActivityPage, MemoryError> {
+        let now = Utc::now();
+        let memories = store.list()?;
+        let trash = store.list_trash()?;
+        let known: HashMap<&str, &Memory> = memories.iter()
+            .chain(trash.iter().map(|trashed| &trashed.memory))
+            .map(|memory| (memory.id.as_str(), memory))
+            .collect();
+
+        let mut entries: Vec<ActivityEntry> = self.entries.lock().unwrap_or_else(|e| e.into_inner()).iter().cloned().collect();
+        for memory in &memories {
+            if memory.created_at < self.started_at {
+                entries.push(ActivityEntry::derived(ActivityKind::Created, memory, memory.created_at));
+            }
+            if memory.updated_at < self.started_at && memory.updated_at - memory.created_at > Duration::seconds(EDIT_GRACE_SECS) {
+                entries.push(ActivityEntry::derived(ActivityKind::Updated, memory, memory.updated_at));
+            }
+            if let Some(remind_at) = memory.remind_at.filter(|remind_at| *remind_at <= now) {
+                entries.push(ActivityEntry::derived(ActivityKind::Reminder, memory, remind_at));
+            }
+        }
+        for trashed in &trash {
+            if trashed.memory.created_at < self.started_at {
+                entries.push(ActivityEntry::derived(ActivityKind::Created, &trashed.memory, trashed.memory.created_at));
+            }
+            if trashed.deleted_at < self.started_at {
+                entries.push(ActivityEntry::derived(ActivityKind::Deleted, &trashed.memory, trashed.deleted_at));
+            }
+        }
+
+        entries.retain(|entry| {
+            let visible = match (&entry.memory_id, principal) {
+                (_, None) => true,
+                (Some(id), Some(_)) => entry.kind.is_memory() && known.get(id.as_str()).is_some_and(|memory| memory.is_visible_to(principal)),
+                (None, Some(_)) => false,
+            };
+            visible
+                && query.kind.is_none_or(|kind| entry.kind == kind)
+                && query.memory_id.as_ref().is_none_or(|id| entry.memory_id.as_ref() == Some(id))
+                && query.since.is_none_or(|since| entry.timestamp >= since)
+                && query.until.is_none_or(|until| entry.timestamp <= until)
+        });
+        // Deletions are recorded without a title, which the trash still has
+        for entry in entries.iter_mut().filter(|entry| entry.title.is_none() && entry.kind.is_memory()) {
+            if let Some(memory) = entry.memory_id.as_deref().and_then(|id| known.get(id)) {
+                entry.title = Some(memory.title.clone());
+                entry.summary = memory_summary(entry.kind, &memory.title);
+            }
+        }
+        entries.sort_by(|a, b| b.timestamp.cmp(&a.timestamp).then_with(|| b.id.cmp(&a.id)));
+
+        let total = entries.len();
+        let items = entries.into_iter()
+            .skip(query.offset.unwrap_or(0))
+            .take(query.limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE))
+            .collect();
+        Ok(ActivityPage { items, total })
+    }
+}
+
+/// Record memory changes until the server shuts down
+pub fn spawn(activity: Arc<ActivityLog>, state: &ServerState) {
+    let mut receiver = state.events.subscribe();
+    let mut shutdown = state.shutdown.clone();
+
+    tokio::spawn(async move {
+        loop {
+            let event = tokio::select! {
+                event = receiver.recv() => event,
+                _ = shutdown.wait_for(|stopped| *stopped) => break,
+            };
+            match event {
+                Ok(event) => activity.record_event(&event),
+                Err(RecvError::Lagged(missed)) => warn!("[ACTIVITY] Fell behind, {} memory changes not recorded", missed),
+                Err(RecvError::Closed) => break,
+            }
+        }
+        info!("[ACTIVITY] Server stopped, ending activity recording");
+    });
+}
+
+pub fn router() -> Router<Arc<ServerState>> {
+    Router::new()
+        .route("/api/activity", get(activity_handler))
+}
+
+async fn activity(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Query(query): Query<ActivityQuery>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling activity request");
+    let store = state.memory_store.clone();
+    let principal = caller.principal().cloned();
+    let state = state.clone();
+    match tokio::task::spawn_blocking(move || state.activity.feed(&store, &query, principal.as_ref())).await {
+        Ok(Ok(page)) => {
+            let mut headers = HeaderMap::new();
+            headers.insert(cache::TOTAL_COUNT, page.total.into());
+            (StatusCode::OK, headers, Json(page.items)).into_response()
+        }
+        Ok(Err(err)) => {
+            error!("Error building activity feed: {:?}", err);
+            ApiError::from(err).into_response()
+        }
+        Err(err) => {
+            error!("Activity task failed: {:?}", err);
+            ApiError::server(err.to_string()).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    get, path = "/api/activity", tag = "memories", operation_id = "activity",
+    params(ActivityQuery),
+    responses(
+        (status = 200, description = "Memory changes, reminders, imports, syncs and task runs, newest first; \
+            the total count is in `X-Total-Count`", body = [ActivityEntry]),
+        (status = 503, description = "The store is unavailable", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn activity_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    query: Query<ActivityQuery>,
+) -> impl IntoResponse {
+    activity(state, caller, query).await
+}
@@ -0,0 +1,269 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::get,
+    Json, Router,
+};
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::memory::{Memory, MemoryError, MemoryStore};
+use crate::util::write_atomic;
+use super::error::{ApiError, OpenAiErrorResponse};
+use super::auth::Caller;
+use super::state::ServerState;
+
+/// File in the store directory holding one snapshot of the store per day
+const STATS_FILE: &str = ".stats.json";
+
+/// How often today's snapshot is refreshed when not configured
+const DEFAULT_SNAPSHOT_INTERVAL_SECS: u64 = 60 * 60;
+
+/// Days of snapshots kept when not configured, about two years
+const DEFAULT_RETENTION_DAYS: u32 = 730;
+
+/// How often the store is measured for the trends report, set under `[analytics]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnalyticsConfig {
+    /// Seconds between snapshots, each replacing the one taken earlier the same day; `0` disables them
+    pub snapshot_interval_secs: u64,
+    /// Snapshots older than this many days are dropped; `0` keeps them all
+    pub retention_days: u32,
+}
+
+impl Default for AnalyticsConfig {
+    fn default() -> Self {
+        Self {
+            snapshot_interval_secs: DEFAULT_SNAPSHOT_INTERVAL_SECS,
+            retention_days: DEFAULT_RETENTION_DAYS,
+        }
+    }
+}
+
+impl AnalyticsConfig {
+    /// Override the snapshot interval with `CONDUIT_STATS_INTERVAL_SECS`, if set
+    pub fn apply_env(&mut self) {
+        if let Some(secs) = std::env::var("CONDUIT_STATS_INTERVAL_SECS").ok().and_then(|v| v.parse().ok()) {
+            self.snapshot_interval_secs = secs;
+        }
+    }
+}
+
+/// Size of the store at the end of a day, or when it was last measured that day
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DailyStats {
+    /// Day the snapshot was taken, in UTC
+    pub date: NaiveDate,
+    pub memories: usize,
+    /// Bytes of title and content across all memories
+    pub bytes: u64,
+    pub attachments: usize,
+    /// Memories carrying each tag
+    pub tags: BTreeMap<String, usize>,
+}
+
+impl DailyStats {
+    fn measure(date: NaiveDate, memories: &[Memory]) -> Self {
+        let mut tags = BTreeMap::new();
+        for tag in memories.iter().flat_map(|memory| &memory.tags) {
+            *tags.entry(tag.clone()).or_insert(0) += 1;
+        }
+        Self {
+            date,
+            memories: memories.len(),
+            bytes: memories.iter().map(|memory| (memory.title.len() + memory.content.len()) as u64).sum(),
+            attachments: memories.iter().map(|memory| memory.attachments.len()).sum(),
+            tags,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(default)]
+pub struct TrendsQuery {
+    /// First day to report, as `YYYY-MM-DD`
+    pub from: Option<NaiveDate>,
+    /// Last day to report, as `YYYY-MM-DD`
+    pub to: Option<NaiveDate>,
+    /// Only report this many of each day's most used tags
+    pub top_tags: Option<usize>,
+}
+
+/// Daily snapshots in a range of days, with the change across it
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TrendsReport {
+    /// One snapshot per day the server ran, oldest first
+    pub days: Vec<DailyStats>,
+    /// Memories added since the first day reported, negative when the store shrank
+    pub memories_change: i64,
+    /// Bytes added since the first day reported
+    pub bytes_change: i64,
+}
+
+/// Daily snapshots of the store, saved in the store directory
+///
+/// Snapshots are only kept in memory while the store is read-only.
+pub struct StatsHistory {
+    days: Mutex<Vec<DailyStats>>,
+    path: PathBuf,
+    store: Arc<MemoryStore>,
+    retention_days: u32,
+}
+
+impl StatsHistory {
+    pub fn open(store: &Arc<MemoryStore>, config: &AnalyticsConfig) -> Result<Self, String> {
+        let path = store.base_path.join(STATS_FILE);
+        let days = if path.is_file() {
+            let json = fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read stats file {}: {}", path.display(), e))?;
+            serde_json::from_str(&json)
+                .map_err(|e| format!("Invalid stats file {}: {}", path.display(), e))?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self { days: Mutex::new(days), path, store: store.clone(), retention_days: config.retention_days })
+    }
+
+    /// Measure the store and keep it as today's snapshot
+    pub fn snapshot(&self) -> Result<DailyStats, MemoryError> {
+        let today = Utc::now().date_naive();
+        let stats = DailyStats::measure(today, &self.store.list()?);
+
+        let mut days = self.days.lock().unwrap_or_else(|e| e.into_inner());
+        days.retain(|day| day.date != today);
+        if self.retention_days > 0 {
+            let oldest = today - chrono::Duration::days(i64::from(self.retention_days));
+            days.retain(|day| day.date > oldest);
+        }
+        days.push(stats.clone());
+        days.sort_by_key(|day| day.date);
+
+        if self.store.check_writable().is_err() {
+            return Ok(stats);
+        }
+        let saved = serde_json::to_string_pretty(&*days)
+            .map_err(|e| e.to_string())
+            .and_then(|json| write_atomic(&self.path, json).map_err(|e| e.to_string()));
+        if let Err(err) = saved {
+            error!("[ANALYTICS] Failed to save store stats: {}", err);
+        }
+        Ok(stats)
+    }
+
+    /// Snapshots in the query's range, oldest first
+    pub fn trends(&self, query: &TrendsQuery) -> TrendsReport {
+        let days = self.days.lock().unwrap_or_else(|e| e.into_inner());
+        let mut days: Vec<DailyStats> = days.iter()
+            .filter(|day| query.from.is_none_or(|from| day.date >= from))
+            .filter(|day| query.to.is_none_or(|to| day.date <= to))
+            .cloned()
+            .collect();
+        if let Some(top) = query.top_tags {
+            for day in &mut days {
+                let mut tags: Vec<(String, usize)> = std::mem::take(&mut day.tags).into_iter().collect();
+                tags.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+                day.tags = tags.into_iter().take(top).collect();
+            }
+        }
+
+        let (memories_change, bytes_change) = match (days.first(), days.last()) {
+            (Some(first), Some(last)) => (
+                last.memories as i64 - first.memories as i64,
+                last.bytes as i64 - first.bytes as i64,
+            ),
+            _ => (0, 0),
+        };
+        TrendsReport { days, memories_change, bytes_change }
+    }
+}
+
+/// Take a snapshot when the server starts and then every `interval`, so days
+/// nobody asks for the report are recorded too
+///
+/// Runs until the server state is dropped. No snapshot is taken while the
+/// workspace is locked.
+pub fn spawn(state: Weak<ServerState>, interval: Duration) {
+    tokio::spawn(async move {
+        loop {
+            let Some(state) = state.upgrade() else {
+                break;
+            };
+            if state.memory_store.workspace_lock().is_locked() {
+                drop(state);
+                tokio::time::sleep(interval).await;
+                continue;
+            }
+
+            let snapshot_state = state.clone();
+            match tokio::task::spawn_blocking(move || snapshot_state.stats.snapshot()).await {
+                Ok(Ok(stats)) => info!("[ANALYTICS] Recorded {} memories, {} bytes for {}", stats.memories, stats.bytes, stats.date),
+                Ok(Err(e)) => warn!("[ANALYTICS] Failed to measure the store: {}", e),
+                Err(e) => warn!("[ANALYTICS] Snapshot panicked: {}", e),
+            }
+
+            drop(state);
+            tokio::time::sleep(interval).await;
+        }
+
+        info!("[ANALYTICS] Server stopped, ending store snapshots");
+    });
+}
+
+/// Routes for store statistics over time
+pub fn router() -> Router<Arc<ServerState>> {
+    Router::new().route("/api/analytics/trends", get(trends_handler))
+}
+
+async fn trends(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Query(query): Query<TrendsQuery>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling trends request (from: {:?}, to: {:?})", query.from, query.to);
+    // Counts and tags cover every memory, including ones the caller cannot see
+    if caller.principal().is_some() {
+        return ApiError::permission("Store trends require an API key with access to every memory".to_string()).into_response();
+    }
+    if let Err(err) = state.memory_store.check_available() {
+        return ApiError::from(err).into_response();
+    }
+
+    // Today's snapshot may be an interval old, so measure again
+    let snapshot_state = state.clone();
+    let measured = tokio::task::spawn_blocking(move || snapshot_state.stats.snapshot())
+        .await
+        .unwrap_or_else(|e| Err(MemoryError::Io(std::io::Error::other(format!("Snapshot panicked: {}", e)))));
+    if let Err(err) = measured {
+        error!("[ANALYTICS] Failed to measure the store: {}", err);
+        return ApiError::from(err).into_response();
+    }
+    (StatusCode::OK, Json(state.stats.trends(&query))).into_response()
+}
+
+#[utoipa::path(
+    get, path = "/api/analytics/trends", tag = "analytics", operation_id = "store_trends",
+    params(TrendsQuery),
+    responses(
+        (status = 200, description = "Memory count, size and tag counts per day", body = TrendsReport),
+        (status = 403, description = "Caller acts as a user", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn trends_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    query: Query<TrendsQuery>,
+) -> impl IntoResponse {
+    trends(state, caller, query).await
+}
@@ -0,0 +1,119 @@
+use std::sync::Weak;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::memory::MemoryStore;
+use super::state::ServerState;
+
+/// How attachment text is extracted for full-text search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AttachmentTextConfig {
+    /// Look for new and changed attachments every this many seconds; `0` disables extraction
+    pub interval_secs: u64,
+    /// Attachments larger than this many bytes are not searched
+    pub max_size: u64,
+}
+
+impl Default for AttachmentTextConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 60,
+            max_size: 50 * 1024 * 1024,
+        }
+    }
+}
+
+impl AttachmentTextConfig {
+    /// Override settings with `CONDUIT_ATTACHMENT_TEXT_INTERVAL_SECS` and
+    /// `CONDUIT_ATTACHMENT_TEXT_MAX_SIZE`, if set
+    pub fn apply_env(&mut self) {
+        if let Some(secs) = std::env::var("CONDUIT_ATTACHMENT_TEXT_INTERVAL_SECS").ok().and_then(|secs| secs.parse().ok()) {
+            self.interval_secs = secs;
+        }
+        if let Some(size) = std::env::var("CONDUIT_ATTACHMENT_TEXT_MAX_SIZE").ok().and_then(|size| size.parse().ok()) {
+            self.max_size = size;
+        }
+    }
+}
+
+/// Extract the text of attachments so searches find the memories they belong to
+///
+/// Runs until the server state is dropped. Every `interval` the job checks whether
+/// the store changed and, if so, extracts the text of attachments added or replaced
+/// since the last run. The first check backfills attachments uploaded before text
+/// was extracted. Nothing is extracted while the store is read-only or the
+/// workspace is locked.
+pub fn spawn(state: Weak<ServerState>, config: AttachmentTextConfig) {
+    tokio::spawn(async move {
+        let mut extracted_at: Option<DateTime<Utc>> = None;
+
+        loop {
+            let Some(state) = state.upgrade() else {
+                break;
+            };
+
+            let store = state.memory_store.clone();
+            let stamp = tokio::task::spawn_blocking(move || store.last_modified()).await;
+            match stamp {
+                // Text is written into the store, so wait until it accepts changes
+                _ if state.memory_store.is_read_only() || state.memory_store.workspace_lock().is_locked() => {}
+                Ok(Ok(stamp)) if extracted_at != Some(stamp) => {
+                    let store = state.memory_store.clone();
+                    let max_size = config.max_size;
+                    let running = state.indexing.attachment_text_run();
+                    let extracted = tokio::task::spawn_blocking(move || extract(&store, max_size)).await;
+                    drop(running);
+                    match extracted {
+                        Ok(Ok(())) => extracted_at = Some(stamp),
+                        Ok(Err(e)) => warn!("[ATTACHMENT_TEXT] Extraction failed, retrying later: {}", e),
+                        Err(e) => warn!("[ATTACHMENT_TEXT] Extraction panicked: {}", e),
+                    }
+                }
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => warn!("[ATTACHMENT_TEXT] Failed to check the store for changes: {}", e),
+                Err(e) => warn!("[ATTACHMENT_TEXT] Change check panicked: {}", e),
+            }
+
+            drop(state);
+            tokio::time::sleep(Duration::from_secs(config.interval_secs)).await;
+        }
+
+        info!("[ATTACHMENT_TEXT] Server stopped, ending attachment text extraction");
+    });
+}
+
+/// Extract the text of an attachment as it is stored, so the next search finds it
+///
+/// Searches only see text once it is extracted, so a stored attachment is not
+/// left for the background job to catch up with. Failures are only logged:
+/// the attachment is stored, and the background job tries again.
+pub fn index_stored(store: &MemoryStore, config: &AttachmentTextConfig, id: &str, name: &str) {
+    if config.interval_secs == 0 {
+        return;
+    }
+    if let Err(e) = store.index_attachment_text(id, name, config.max_size) {
+        warn!("[ATTACHMENT_TEXT] Failed to extract text from {}/{}, retrying later: {}", id, name, e);
+    }
+}
+
+// Extract the text of every attachment whose stored text is missing or out of date
+fn extract(store: &MemoryStore, max_size: u64) -> Result<(), String> {
+    let stale = store.stale_attachment_text().map_err(|e| e.to_string())?;
+    if stale.is_empty() {
+        return Ok(());
+    }
+    info!("[ATTACHMENT_TEXT] Extracting text from {} attachments", stale.len());
+
+    let mut found = 0;
+    for (id, name) in &stale {
+        if store.index_attachment_text(id, name, max_size).map_err(|e| e.to_string())? {
+            found += 1;
+        }
+    }
+
+    info!("[ATTACHMENT_TEXT] Found text in {} of {} attachments", found, stale.len());
+    Ok(())
+}
@@ -0,0 +1,211 @@
+use std::convert::Infallible;
+use std::sync::Arc;
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request, State},
+    http::{header, request::Parts},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use tracing::warn;
+
+use super::accounts::{authenticate_session, is_session_token};
+use super::error::ApiError;
+use super::state::ServerState;
+use super::tokens::{TokenAuth, TOKEN_PREFIX};
+use crate::memory::Principal;
+
+/// A user of a multi-user server
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserConfig {
+    pub name: String,
+    /// Key the user sends as `Authorization: Bearer <key>`
+    pub api_key: String,
+    /// Groups memories can be shared with
+    pub groups: Vec<String>,
+}
+
+/// API keys accepted by the server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    /// Keys clients send as `Authorization: Bearer <key>`; empty leaves the API open
+    pub api_keys: Vec<String>,
+    /// Users with their own keys, whose requests only reach the memories they can access
+    pub users: Vec<UserConfig>,
+    /// Lifetime of service tokens minted without one
+    pub token_ttl_secs: u64,
+    /// Longest lifetime a service token can be minted or renewed with
+    pub token_max_ttl_secs: u64,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            api_keys: Vec::new(),
+            users: Vec::new(),
+            token_ttl_secs: 3600,
+            token_max_ttl_secs: 86400,
+        }
+    }
+}
+
+impl AuthConfig {
+    /// Whether requests must carry an API key
+    pub fn is_enabled(&self) -> bool {
+        !self.api_keys.is_empty() || self.is_multi_user()
+    }
+
+    /// Whether memories have owners and are only shared through grants
+    pub fn is_multi_user(&self) -> bool {
+        !self.users.is_empty()
+    }
+
+    /// Override the keys with the comma-separated `CONDUIT_API_KEYS` variable,
+    /// and the token lifetime with `CONDUIT_TOKEN_TTL_SECS`, if set
+    pub fn apply_env(&mut self) {
+        if let Ok(keys) = std::env::var("CONDUIT_API_KEYS") {
+            self.api_keys = keys.split(',')
+                .map(|key| key.trim().to_string())
+                .filter(|key| !key.is_empty())
+                .collect();
+        }
+        if let Some(secs) = std::env::var("CONDUIT_TOKEN_TTL_SECS").ok().and_then(|secs| secs.parse().ok()) {
+            self.token_ttl_secs = secs;
+        }
+    }
+
+    // Principal of a configured user, with their current groups
+    fn principal(&self, user: &UserConfig) -> Principal {
+        Principal { user: user.name.clone(), groups: user.groups.clone() }
+    }
+
+    /// Principal of the user named `name`, with the groups configured for them
+    pub fn user_principal(&self, name: &str) -> Principal {
+        match self.users.iter().find(|user| user.name == name) {
+            Some(user) => self.principal(user),
+            None => Principal { user: name.to_string(), groups: Vec::new() },
+        }
+    }
+}
+
+/// Who a request acts as, for access checks on memories
+///
+/// Requests with a user's key act as that user. Requests with one of the
+/// `api_keys`, or to a server without users, can access every memory.
+#[derive(Debug, Clone, Default)]
+pub struct Caller(pub Option<Principal>);
+
+impl Caller {
+    pub fn principal(&self) -> Option<&Principal> {
+        self.0.as_ref()
+    }
+
+    /// Name of the user, if the request acts as one
+    pub fn user(&self) -> Option<&str> {
+        self.0.as_ref().map(|principal| principal.user.as_str())
+    }
+}
+
+/// Set on requests authenticated with one of the `api_keys`, holding a
+/// fingerprint of the key that is safe to show
+#[derive(Debug, Clone)]
+pub struct KeyAuth(pub String);
+
+impl KeyAuth {
+    fn new(key: &str) -> Self {
+        Self(hex::encode(&Sha256::digest(key.as_bytes())[..4]))
+    }
+}
+
+// Whether a presented key is the configured one, taking the same time
+// wherever they differ; hashing first hides their lengths too
+fn key_matches(given: &str, expected: &str) -> bool {
+    Sha256::digest(given.as_bytes()).ct_eq(&Sha256::digest(expected.as_bytes())).into()
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Caller {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<Caller>().cloned().unwrap_or_default())
+    }
+}
+
+/// Reject requests that do not carry one of the configured API keys, an
+/// active service token or, with accounts enabled, a session token
+///
+/// A service token acts as whoever minted it. Tokens of users that were
+/// removed from the configuration stop working. Session requests go to the
+/// account's own store.
+///
+/// The `401` is an OpenAI-style error on every route, so client libraries
+/// raise their authentication error.
+pub async fn require_api_key(
+    State(state): State<Arc<ServerState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let config = &state.auth;
+    let key = request.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim);
+
+    if let (Some(accounts), Some(token)) = (&state.accounts, key.filter(|key| is_session_token(key))) {
+        let token = token.to_string();
+        return authenticate_session(&state, accounts, &token, request, next).await;
+    }
+
+    let user = key.and_then(|key| {
+        config.users.iter()
+            .filter(|user| !user.api_key.is_empty())
+            .fold(None, |found, user| {
+                // Keep comparing after a match, and keep the first one as before
+                let matched = key_matches(key, &user.api_key);
+                found.or(matched.then_some(user))
+            })
+    });
+    if let Some(user) = user {
+        let mut request = request;
+        request.extensions_mut().insert(Caller(Some(config.principal(user))));
+        return next.run(request).await;
+    }
+    // Every key is compared, so the time taken does not reveal which one matched
+    let api_key = key.filter(|key| config.api_keys.iter().fold(false, |found, allowed| found | key_matches(key, allowed)));
+    if let Some(key) = api_key {
+        let auth = KeyAuth::new(key);
+        let mut request = request;
+        request.extensions_mut().insert(auth);
+        return next.run(request).await;
+    }
+
+    let token = key
+        .filter(|key| key.starts_with(TOKEN_PREFIX))
+        .and_then(|key| state.tokens.verify(key));
+    if let Some(token) = token {
+        let caller = match &token.user {
+            Some(name) => config.users.iter().find(|user| user.name == *name).map(|user| Caller(Some(config.principal(user)))),
+            None => Some(Caller(None)),
+        };
+        if let Some(caller) = caller {
+            let mut request = request;
+            request.extensions_mut().insert(caller);
+            request.extensions_mut().insert(TokenAuth(token.id));
+            return next.run(request).await;
+        }
+    }
+
+    warn!("[AUTH] Rejected request to {} without a valid API key", request.uri().path());
+    let message = match key {
+        Some(_) => "Invalid API key",
+        None => "Missing API key; send it as `Authorization: Bearer <key>`",
+    };
+    ([(header::WWW_AUTHENTICATE, "Bearer")], ApiError::authentication(message)).into_response()
+}
@@ -0,0 +1,444 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::memory::{Memory, MemoryAccess, MemoryError, Permission, Principal};
+use super::error::{ApiError, OpenAiErrorResponse};
+use super::auth::Caller;
+use super::openai::{ChatCompletionRequest, ChatMessage};
+use super::state::ServerState;
+
+/// Tags suggested for a memory when none is configured
+const DEFAULT_MAX_TAGS: usize = 3;
+
+/// Most content sent to the model when suggesting tags, in characters
+const MAX_PROMPT_CHARS: usize = 2000;
+
+/// Most existing tags offered to the model, the most used first
+const MAX_PROMPT_TAGS: usize = 300;
+
+/// What happens to the tags the model suggests for new memories
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AutoTagMode {
+    /// New memories are not tagged by the model
+    #[default]
+    Off,
+    /// Return the tags as `suggested_tags` for the caller to confirm
+    Suggest,
+    /// Add the tags to the memory before it is saved
+    Apply,
+}
+
+impl std::str::FromStr for AutoTagMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "off" | "none" => Ok(Self::Off),
+            "suggest" => Ok(Self::Suggest),
+            "apply" => Ok(Self::Apply),
+            other => Err(format!("Unknown auto-tag mode: {}", other)),
+        }
+    }
+}
+
+/// Settings for tagging memories with the upstream model, set under `[auto_tag]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoTagConfig {
+    pub mode: AutoTagMode,
+    /// Model asked for tags; without one memories are never tagged by the model
+    pub model: Option<String>,
+    /// Most tags suggested for one memory
+    pub max_tags: usize,
+}
+
+impl Default for AutoTagConfig {
+    fn default() -> Self {
+        Self {
+            mode: AutoTagMode::Off,
+            model: None,
+            max_tags: DEFAULT_MAX_TAGS,
+        }
+    }
+}
+
+impl AutoTagConfig {
+    /// Override settings with `CONDUIT_AUTO_TAG`, `CONDUIT_AUTO_TAG_MODEL` and
+    /// `CONDUIT_AUTO_TAG_MAX_TAGS`, if set
+    pub fn apply_env(&mut self) {
+        if let Some(mode) = std::env::var("CONDUIT_AUTO_TAG").ok().and_then(|v| v.parse().ok()) {
+            self.mode = mode;
+        }
+        if let Some(model) = std::env::var("CONDUIT_AUTO_TAG_MODEL").ok().filter(|model| !model.is_empty()) {
+            self.model = Some(model);
+        }
+        if let Some(max_tags) = std::env::var("CONDUIT_AUTO_TAG_MAX_TAGS").ok().and_then(|v| v.parse().ok()) {
+            self.max_tags = max_tags;
+        }
+    }
+}
+
+/// Routes for tagging memories with the upstream model
+pub fn router() -> Router<Arc<ServerState>> {
+    Router::new()
+        .route("/api/memories/:id/auto-tag", post(auto_tag_memory_handler))
+        .route("/api/tags/auto", post(auto_tag_store_handler))
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(default)]
+pub struct AutoTagQuery {
+    /// Add the suggested tags to the memory instead of only returning them
+    pub apply: bool,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(default)]
+pub struct AutoTagStoreQuery {
+    /// Add the suggested tags to the memories instead of only returning them
+    pub apply: bool,
+    /// Only tag memories that have no tags yet
+    pub untagged_only: bool,
+    /// Most memories to ask the model about
+    pub limit: Option<usize>,
+}
+
+/// Tags the model suggested for a memory
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AutoTagResult {
+    pub id: String,
+    pub title: String,
+    /// Existing tags the memory does not have yet, the most fitting first
+    pub suggested_tags: Vec<String>,
+    /// Whether the suggested tags were added to the memory
+    pub applied: bool,
+}
+
+/// Outcome of tagging the existing store
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AutoTagReport {
+    /// Whether the suggested tags were added to the memories
+    pub applied: bool,
+    /// Memories the model was asked about
+    pub checked: usize,
+    /// Memories the model suggested tags for
+    pub items: Vec<AutoTagResult>,
+    /// Memories that could not be tagged, by ID, with why
+    pub failed: Vec<AutoTagFailure>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AutoTagFailure {
+    pub id: String,
+    pub error: String,
+}
+
+// The tags in use, the most used first, each spelled as most memories spell it
+fn vocabulary<'a>(memories: impl IntoIterator<Item = &'a Memory>) -> Vec<String> {
+    let mut spellings: HashMap<String, HashMap<&str, usize>> = HashMap::new();
+    for memory in memories {
+        for tag in memory.tags.iter().filter(|tag| !tag.trim().is_empty()) {
+            *spellings.entry(tag.to_lowercase()).or_default().entry(tag).or_insert(0) += 1;
+        }
+    }
+
+    let mut tags: Vec<(usize, String)> = spellings.into_values()
+        .filter_map(|spellings| {
+            let total = spellings.values().sum();
+            let (tag, _) = spellings.into_iter().max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then_with(|| b.cmp(a)))?;
+            Some((total, tag.to_string()))
+        })
+        .collect();
+    tags.sort_by(|(a_count, a), (b_count, b)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+    tags.into_iter().map(|(_, tag)| tag).collect()
+}
+
+// Tags in use on the memories the caller can read
+fn caller_vocabulary(state: &ServerState, principal: Option<&Principal>) -> Result<Vec<String>, MemoryError> {
+    let memories = state.memory_store.list()?;
+    Ok(vocabulary(memories.iter().filter(|memory| memory.is_visible_to(principal))))
+}
+
+// Ask the upstream model which of the existing tags fit a memory
+async fn suggest_tags(state: &ServerState, model: &str, memory: &Memory, vocabulary: &[String]) -> Result<Vec<String>, String> {
+    let known: HashMap<String, &String> = vocabulary.iter().map(|tag| (tag.to_lowercase(), tag)).collect();
+    let has_tag = |tag: &str| memory.tags.iter().any(|existing| existing.eq_ignore_ascii_case(tag));
+    let candidates: Vec<&str> = vocabulary.iter()
+        .filter(|tag| !has_tag(tag))
+        .take(MAX_PROMPT_TAGS)
+        .map(String::as_str)
+        .collect();
+    let max_tags = state.auto_tag.max_tags;
+    if candidates.is_empty() || max_tags == 0 {
+        return Ok(Vec::new());
+    }
+    let provider = state.provider.as_ref().ok_or("No upstream provider is configured")?;
+    let content: String = memory.content.chars().take(MAX_PROMPT_CHARS).collect();
+
+    let req = ChatCompletionRequest {
+        model: model.to_string(),
+        messages: vec![
+            ChatMessage {
+                role: "system".to_string(),
+                content: format!(
+                    "You tag the notes of a note-taking app. Choose at most {} tags for the user's note, the most fitting first, \
+                    from this list only: {}. Reply with a JSON array of the tags only, and [] when none fit.",
+                    max_tags,
+                    candidates.join(", "),
+                ).into(),
+                ..Default::default()
+            },
+            ChatMessage {
+                role: "user".to_string(),
+                content: format!("{}\n\n{}", memory.title, content).into(),
+                ..Default::default()
+            },
+        ],
+        temperature: Some(0.0),
+        max_tokens: Some(128),
+        stream: None,
+        response_format: None,
+        tools: Vec::new(),
+        tool_choice: None,
+        conversation_id: None,
+        conduit: None,
+    };
+
+    let completion = provider.chat_completion(&req).await.map_err(|err| err.to_string())?;
+    let reply = completion.choices.into_iter().next().map(|choice| choice.message.content.text()).unwrap_or_default();
+    // Models often wrap the array in prose or a code fence
+    let tags: Vec<String> = match (reply.find('['), reply.rfind(']')) {
+        (Some(start), Some(end)) if start < end => serde_json::from_str(&reply[start..=end])
+            .map_err(|err| format!("Could not read the tags suggested by {}: {}", model, err))?,
+        _ => return Err(format!("{} did not reply with a list of tags", model)),
+    };
+
+    // Only tags already in use are kept, spelled as the store spells them
+    let mut suggested: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().trim_start_matches('#').to_lowercase();
+        if let Some(known) = known.get(&tag).or_else(|| known.get(&format!("#{}", tag))) {
+            if !has_tag(known) && !suggested.contains(known) {
+                suggested.push((*known).clone());
+            }
+        }
+    }
+    suggested.truncate(max_tags);
+    Ok(suggested)
+}
+
+/// Tag a memory that is about to be created
+///
+/// In `apply` mode the tags the model suggests are added to `memory`; in
+/// `suggest` mode they are returned for the caller to confirm. Returns no
+/// tags when auto-tagging is off, no model is configured or the model fails,
+/// so creating the memory never depends on it.
+pub async fn tag_new_memory(state: &ServerState, memory: &mut Memory, principal: Option<&Principal>) -> Vec<String> {
+    let (mode, Some(model)) = (state.auto_tag.mode, &state.auto_tag.model) else {
+        return Vec::new();
+    };
+    if mode == AutoTagMode::Off {
+        return Vec::new();
+    }
+
+    let suggested = match caller_vocabulary(state, principal) {
+        Ok(vocabulary) => suggest_tags(state, model, memory, &vocabulary).await,
+        Err(err) => Err(err.to_string()),
+    };
+    let suggested = match suggested {
+        Ok(suggested) => suggested,
+        Err(err) => {
+            warn!("[AUTO_TAG] Tagging the new memory failed, saving it as given: {}", err);
+            return Vec::new();
+        }
+    };
+    info!("[AUTO_TAG] Suggested tags for new memory {}: {:?}", memory.id, suggested);
+
+    match mode {
+        AutoTagMode::Apply => {
+            memory.tags.retain(|tag| !tag.trim().is_empty());
+            memory.tags.extend(suggested);
+            Vec::new()
+        }
+        _ => suggested,
+    }
+}
+
+// Add tags to a memory as it is now, which may have changed while the model was asked
+fn add_tags(state: &ServerState, id: &str, tags: &[String]) -> Result<Memory, MemoryError> {
+    let mut memory = state.memory_store.get(id)?;
+    if memory.read_only {
+        return Err(MemoryError::ReadOnly(id.to_string()));
+    }
+    memory.tags.retain(|tag| !tag.trim().is_empty());
+    for tag in tags {
+        if !memory.tags.iter().any(|existing| existing.eq_ignore_ascii_case(tag)) {
+            memory.tags.push(tag.clone());
+        }
+    }
+    state.memory_store.save(&memory)?;
+    Ok(memory)
+}
+
+// The configured model, or why tags cannot be suggested
+fn configured_model(state: &ServerState) -> Result<String, &'static str> {
+    match (&state.auto_tag.model, &state.provider) {
+        (Some(model), Some(_)) => Ok(model.clone()),
+        (None, _) => Err("No auto-tag model is configured"),
+        (_, None) => Err("No upstream provider is configured"),
+    }
+}
+
+fn error_response(context: &str, err: MemoryError) -> Response {
+    error!("Error {}: {:?}", context, err);
+    ApiError::from(err).into_response()
+}
+
+async fn auto_tag_memory(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+    Query(query): Query<AutoTagQuery>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling auto_tag_memory request for id: {} (apply: {})", id, query.apply);
+    let model = match configured_model(&state) {
+        Ok(model) => model,
+        Err(err) => return ApiError::status(StatusCode::SERVICE_UNAVAILABLE, err.to_string()).into_response(),
+    };
+    let permission = if query.apply { Permission::Write } else { Permission::Read };
+    let memory = match state.memory_store.authorize(&id, caller.principal(), permission) {
+        Ok(memory) => memory,
+        Err(err) => return error_response("authorizing auto-tag", err),
+    };
+    let vocabulary = match caller_vocabulary(&state, caller.principal()) {
+        Ok(vocabulary) => vocabulary,
+        Err(err) => return error_response("listing tags", err),
+    };
+
+    let suggested_tags = match suggest_tags(&state, &model, &memory, &vocabulary).await {
+        Ok(tags) => tags,
+        Err(err) => {
+            error!("[AUTO_TAG] Suggesting tags for {} failed: {}", id, err);
+            return ApiError::status(StatusCode::BAD_GATEWAY, err).into_response();
+        }
+    };
+    let applied = query.apply && !suggested_tags.is_empty();
+    if applied {
+        if let Err(err) = add_tags(&state, &id, &suggested_tags) {
+            return error_response("adding suggested tags", err);
+        }
+    }
+
+    (StatusCode::OK, Json(AutoTagResult { id, title: memory.title, suggested_tags, applied })).into_response()
+}
+
+async fn auto_tag_store(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Query(query): Query<AutoTagStoreQuery>,
+) -> impl IntoResponse {
+    info!(
+        "[SERVER] Handling auto_tag_store request (apply: {}, untagged_only: {}, limit: {:?})",
+        query.apply, query.untagged_only, query.limit,
+    );
+    // Tagging the store changes every user's memories
+    if caller.principal().is_some() {
+        return ApiError::permission("Tagging the store requires an API key with access to every memory".to_string()).into_response();
+    }
+    let model = match configured_model(&state) {
+        Ok(model) => model,
+        Err(err) => return ApiError::status(StatusCode::SERVICE_UNAVAILABLE, err.to_string()).into_response(),
+    };
+    let memories = match state.memory_store.list() {
+        Ok(memories) => memories,
+        Err(err) => return error_response("listing memories", err),
+    };
+    let vocabulary = vocabulary(&memories);
+
+    let mut report = AutoTagReport { applied: query.apply, checked: 0, items: Vec::new(), failed: Vec::new() };
+    let candidates = memories.iter()
+        .filter(|memory| !memory.read_only && (!query.untagged_only || memory.tags.iter().all(|tag| tag.trim().is_empty())))
+        .take(query.limit.unwrap_or(usize::MAX));
+    for memory in candidates {
+        report.checked += 1;
+        let suggested_tags = match suggest_tags(&state, &model, memory, &vocabulary).await {
+            Ok(tags) if tags.is_empty() => continue,
+            Ok(tags) => tags,
+            Err(err) => {
+                warn!("[AUTO_TAG] Suggesting tags for {} failed: {}", memory.id, err);
+                report.failed.push(AutoTagFailure { id: memory.id.clone(), error: err });
+                continue;
+            }
+        };
+        if query.apply {
+            if let Err(err) = add_tags(&state, &memory.id, &suggested_tags) {
+                warn!("[AUTO_TAG] Adding tags to {} failed: {}", memory.id, err);
+                report.failed.push(AutoTagFailure { id: memory.id.clone(), error: err.to_string() });
+                continue;
+            }
+        }
+        report.items.push(AutoTagResult {
+            id: memory.id.clone(),
+            title: memory.title.clone(),
+            suggested_tags,
+            applied: query.apply,
+        });
+    }
+
+    info!(
+        "[AUTO_TAG] Checked {} memories, {} with suggestions, {} failed",
+        report.checked, report.items.len(), report.failed.len(),
+    );
+    (StatusCode::OK, Json(report)).into_response()
+}
+
+#[utoipa::path(
+    post, path = "/api/memories/{id}/auto-tag", tag = "memories", operation_id = "auto_tag_memory",
+    params(("id" = String, Path, description = "Memory ID"), AutoTagQuery),
+    responses(
+        (status = 200, description = "Existing tags the model suggests for the memory", body = AutoTagResult),
+        (status = 404, description = "Memory not found", body = OpenAiErrorResponse),
+        (status = 502, description = "The model failed or gave an unreadable answer", body = OpenAiErrorResponse),
+        (status = 503, description = "No auto-tag model or upstream provider is configured", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn auto_tag_memory_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+    query: Query<AutoTagQuery>,
+) -> impl IntoResponse {
+    auto_tag_memory(state, caller, path, query).await
+}
+
+#[utoipa::path(
+    post, path = "/api/tags/auto", tag = "maintenance", operation_id = "auto_tag_store",
+    params(AutoTagStoreQuery),
+    responses(
+        (status = 200, description = "Tags suggested for, or added to, the existing memories", body = AutoTagReport),
+        (status = 403, description = "The API key cannot access every memory", body = OpenAiErrorResponse),
+        (status = 503, description = "No auto-tag model or upstream provider is configured", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn auto_tag_store_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    query: Query<AutoTagStoreQuery>,
+) -> impl IntoResponse {
+    auto_tag_store(state, caller, query).await
+}
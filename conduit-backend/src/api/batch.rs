@@ -0,0 +1,93 @@
+use std::sync::Arc;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use serde::Deserialize;
+use tracing::{error, info};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::memory::{BatchOperation, BatchReport};
+use super::error::{ApiError, OpenAiErrorResponse};
+use super::auth::Caller;
+use super::state::ServerState;
+use super::validation::ValidJson;
+
+/// Most operations accepted in one batch
+const MAX_OPERATIONS: usize = 1000;
+
+/// Routes for changing many memories at once
+pub fn router() -> Router<Arc<ServerState>> {
+    Router::new()
+        .route("/api/memories/batch", post(apply_batch_handler))
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BatchRequest {
+    /// Operations applied in order; each one succeeds or fails on its own
+    pub operations: Vec<BatchOperation>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(default)]
+pub struct BatchQuery {
+    /// Only report what each operation would change
+    pub dry_run: bool,
+}
+
+async fn apply_batch(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Query(query): Query<BatchQuery>,
+    ValidJson(req): ValidJson<BatchRequest>,
+) -> Response {
+    info!("[SERVER] Handling apply_batch request with {} operations (dry_run: {})", req.operations.len(), query.dry_run);
+    // Retagging every memory and merging need a view of the whole store
+    if caller.principal().is_some() {
+        return ApiError::permission("Batch changes require an API key with access to every memory".to_string()).into_response();
+    }
+    if req.operations.len() > MAX_OPERATIONS {
+        return ApiError::invalid_request(format!("At most {} operations are accepted in one batch", MAX_OPERATIONS)).into_response();
+    }
+
+    let store = state.memory_store.clone();
+    match tokio::task::spawn_blocking(move || store.apply_batch(&req.operations, query.dry_run)).await {
+        Ok(Ok(report)) => {
+            let failed = report.results.iter().filter(|result| result.error.is_some()).count();
+            info!("[SERVER] Applied batch: {} operations, {} failed", report.results.len(), failed);
+            (StatusCode::OK, Json(report)).into_response()
+        }
+        Ok(Err(err)) => {
+            error!("Error applying batch: {:?}", err);
+            ApiError::from(err).into_response()
+        }
+        Err(err) => {
+            error!("Batch task failed: {:?}", err);
+            ApiError::server(err.to_string()).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    post, path = "/api/memories/batch", tag = "memories", operation_id = "apply_batch",
+    params(BatchQuery),
+    request_body = BatchRequest,
+    responses(
+        (status = 200, description = "What each operation changed, or the error that stopped it", body = BatchReport),
+        (status = 400, description = "Too many operations", body = OpenAiErrorResponse),
+        (status = 403, description = "The caller cannot access every memory, or the store is read-only", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn apply_batch_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    query: Query<BatchQuery>,
+    req: ValidJson<BatchRequest>,
+) -> Response {
+    apply_batch(state, caller, query, req).await
+}
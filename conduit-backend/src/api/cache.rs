@@ -0,0 +1,38 @@
+use axum::http::{header, HeaderMap, HeaderValue};
+use chrono::{DateTime, Utc};
+
+const HTTP_DATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// Header carrying the total number of results before pagination
+pub const TOTAL_COUNT: &str = "x-total-count";
+
+/// Responses may be cached, but clients must revalidate before reusing them
+const CACHE_CONTROL: &str = "no-cache";
+
+/// Whether the client's `If-Modified-Since` header shows it already holds a response
+/// at least as recent as `last_modified`
+///
+/// HTTP dates only have second precision, so a mutation within the current second
+/// never produces a 304; another write could still land in that same second.
+pub fn is_not_modified(headers: &HeaderMap, last_modified: DateTime<Utc>) -> bool {
+    if Utc::now().timestamp() <= last_modified.timestamp() {
+        return false;
+    }
+    
+    headers.get(header::IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| DateTime::parse_from_rfc2822(value).ok())
+        .is_some_and(|since| last_modified.timestamp() <= since.timestamp())
+}
+
+/// `Last-Modified` and `Cache-Control` headers for a response derived from the store
+pub fn cache_headers(last_modified: DateTime<Utc>) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    
+    if let Ok(value) = HeaderValue::from_str(&last_modified.format(HTTP_DATE_FORMAT).to_string()) {
+        headers.insert(header::LAST_MODIFIED, value);
+    }
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static(CACHE_CONTROL));
+    
+    headers
+}
@@ -0,0 +1,26 @@
+use crate::memory::{CancelToken, MemoryError};
+
+// Cancels the token when the request future is dropped, which happens when the
+// client disconnects or the request times out
+struct CancelOnDrop(CancelToken);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
+}
+
+/// Run a store operation on the blocking thread pool, cancelling it if the
+/// request that started it is abandoned
+pub async fn run_cancellable<T, F>(operation: F) -> Result<T, MemoryError>
+where
+    F: FnOnce(&CancelToken) -> Result<T, MemoryError> + Send + 'static,
+    T: Send + 'static,
+{
+    let token = CancelToken::new();
+    let _guard = CancelOnDrop(token.clone());
+
+    tokio::task::spawn_blocking(move || operation(&token))
+        .await
+        .map_err(|e| MemoryError::Io(std::io::Error::other(e)))?
+}
@@ -0,0 +1,32 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::memory::find_local_date;
+
+/// How quickly captured memories are filled in from their text
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptureConfig {
+    /// Set the reminder of new memories from a date mentioned in their title or
+    /// content, such as "next Tuesday" or "2024-07-01", when none is given
+    pub parse_dates: bool,
+}
+
+impl CaptureConfig {
+    /// Override settings with `CONDUIT_PARSE_DATES`, if set
+    pub fn apply_env(&mut self) {
+        if let Some(enabled) = std::env::var("CONDUIT_PARSE_DATES").ok().and_then(|enabled| enabled.parse().ok()) {
+            self.parse_dates = enabled;
+        }
+    }
+
+    /// The reminder for a new memory: the one given, or else the first date
+    /// mentioned in the title or content when date parsing is enabled
+    pub fn remind_at(&self, given: Option<DateTime<Utc>>, title: &str, content: &str) -> Option<DateTime<Utc>> {
+        given.or_else(|| {
+            self.parse_dates
+                .then(|| find_local_date(title).or_else(|| find_local_date(content)))
+                .flatten()
+        })
+    }
+}
@@ -0,0 +1,330 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::memory::{filter_visible, has_any_tag, summary_from_content, Memory, MemoryError, Permission};
+use crate::provider::{ProviderError, UpstreamProvider};
+use super::auth::Caller;
+use super::auto_tag::tag_new_memory;
+use super::openai::{ChatCompletionRequest, ChatCompletionResponse, ChatCompletionUsage, ChatMessage, Tool, ToolCall};
+use super::state::ServerState;
+use super::titles::resolve_title;
+
+const CREATE_MEMORY: &str = "create_memory";
+const SEARCH_MEMORIES: &str = "search_memories";
+const UPDATE_MEMORY: &str = "update_memory";
+
+/// Rounds of tool calls run for one chat request when none is configured
+const DEFAULT_MAX_ROUNDS: usize = 5;
+
+/// Memories found by `search_memories` when the model does not say
+const DEFAULT_SEARCH_LIMIT: usize = 5;
+
+/// Most memories `search_memories` returns
+const MAX_SEARCH_LIMIT: usize = 20;
+
+/// Most content of each found memory given to the model, in characters
+const MAX_RESULT_CHARS: usize = 1000;
+
+/// Whether chat models can read and write memories with tools, set under `[chat_tools]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChatToolsConfig {
+    /// Offer the memory tools with every chat request that does not turn them
+    /// off in its `conduit` options
+    pub enabled: bool,
+    /// Most rounds of tool calls run for one request before the model must reply
+    pub max_rounds: usize,
+}
+
+impl Default for ChatToolsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_rounds: DEFAULT_MAX_ROUNDS,
+        }
+    }
+}
+
+impl ChatToolsConfig {
+    /// Override settings with `CONDUIT_CHAT_TOOLS` and `CONDUIT_CHAT_TOOLS_MAX_ROUNDS`, if set
+    pub fn apply_env(&mut self) {
+        if let Some(enabled) = std::env::var("CONDUIT_CHAT_TOOLS").ok().and_then(|v| v.parse().ok()) {
+            self.enabled = enabled;
+        }
+        if let Some(rounds) = std::env::var("CONDUIT_CHAT_TOOLS_MAX_ROUNDS").ok().and_then(|v| v.parse().ok()) {
+            self.max_rounds = rounds;
+        }
+    }
+}
+
+// The tools Conduit runs itself, in the OpenAI function-calling format
+fn memory_tools() -> Vec<Tool> {
+    let tags = |description: &str| json!({ "type": "array", "items": { "type": "string" }, "description": description });
+    vec![
+        Tool::function(
+            CREATE_MEMORY,
+            "Save a new note in the user's memory store, such as something the user asks to remember.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "title": { "type": "string", "description": "Short title; generated from the content when empty" },
+                    "content": { "type": "string", "description": "Text of the note, in markdown" },
+                    "tags": tags("Tags without a leading #"),
+                },
+                "required": ["content"],
+            }),
+        ),
+        Tool::function(
+            SEARCH_MEMORIES,
+            "Search the user's notes by words, phrases and filters, returning the best matches.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "Words to search for" },
+                    "tag": { "type": "string", "description": "Only return notes with this tag" },
+                    "limit": { "type": "integer", "description": "Most notes to return, at most 20" },
+                },
+                "required": ["query"],
+            }),
+        ),
+        Tool::function(
+            UPDATE_MEMORY,
+            "Change a note found with search_memories or created earlier, by its ID.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "id": { "type": "string", "description": "ID of the note" },
+                    "title": { "type": "string", "description": "New title" },
+                    "content": { "type": "string", "description": "New text, replacing the whole content" },
+                    "append": { "type": "string", "description": "Text added at the end of the content" },
+                    "tags": tags("New tags, replacing the existing ones"),
+                    "add_tags": tags("Tags added to the existing ones"),
+                },
+                "required": ["id"],
+            }),
+        ),
+    ]
+}
+
+/// Add the memory tools to a chat request, if they are enabled for it
+///
+/// Returns the names of the tools added. Tools of the client with the same
+/// name are kept in their place, and their calls left to the client.
+pub fn offer(state: &ServerState, req: &mut ChatCompletionRequest) -> Vec<String> {
+    let enabled = req.conduit.as_ref().and_then(|options| options.memory_tools).unwrap_or(state.chat_tools.enabled);
+    if !enabled {
+        return Vec::new();
+    }
+    let mut offered = Vec::new();
+    for tool in memory_tools() {
+        if req.tools.iter().any(|own| own.function.name == tool.function.name) {
+            continue;
+        }
+        offered.push(tool.function.name.clone());
+        req.tools.push(tool);
+    }
+    offered
+}
+
+/// Ask the model for a chat completion, running its calls of the memory tools
+///
+/// While the model only calls tools in `offered`, each call is run against
+/// the store as `caller` and the results are sent back, for at most
+/// `max_rounds` rounds; the last round offers no memory tools so the model
+/// has to reply. The completion returned is the first that does not call a
+/// memory tool, with the tokens of every round, and `req` ends with the tool
+/// calls and results exchanged on the way.
+pub async fn complete(
+    state: &ServerState,
+    provider: &UpstreamProvider,
+    req: &mut ChatCompletionRequest,
+    offered: &[String],
+    caller: &Caller,
+) -> Result<ChatCompletionResponse, ProviderError> {
+    let mut usage = ChatCompletionUsage::default();
+    let mut round = 0;
+    loop {
+        if round == state.chat_tools.max_rounds && !offered.is_empty() {
+            req.tools.retain(|tool| !offered.contains(&tool.function.name));
+            if req.tools.is_empty() {
+                req.tool_choice = None;
+            }
+        }
+        let mut completion = provider.chat_completion(req).await?;
+        usage.prompt_tokens += completion.usage.prompt_tokens;
+        usage.completion_tokens += completion.usage.completion_tokens;
+        usage.total_tokens += completion.usage.total_tokens;
+
+        let message = match completion.choices.first() {
+            Some(choice) if round < state.chat_tools.max_rounds && runs_here(&choice.message.tool_calls, offered) => choice.message.clone(),
+            _ => {
+                completion.usage = usage;
+                return Ok(completion);
+            }
+        };
+        round += 1;
+
+        let calls = message.tool_calls.clone();
+        req.messages.push(message);
+        for call in calls {
+            let result = run(state, &call, caller).await;
+            req.messages.push(ChatMessage {
+                role: "tool".to_string(),
+                content: result.to_string().into(),
+                tool_call_id: Some(call.id),
+                ..Default::default()
+            });
+        }
+    }
+}
+
+// Whether the model only asks for memory tools, which are then run here
+fn runs_here(calls: &[ToolCall], offered: &[String]) -> bool {
+    !calls.is_empty() && calls.iter().all(|call| offered.contains(&call.function.name))
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateArgs {
+    #[serde(default)]
+    title: String,
+    content: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchArgs {
+    query: String,
+    #[serde(default)]
+    tag: Option<String>,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UpdateArgs {
+    id: String,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    append: Option<String>,
+    #[serde(default)]
+    tags: Option<Vec<String>>,
+    #[serde(default)]
+    add_tags: Vec<String>,
+}
+
+// Models often write tags as hashtags
+fn clean_tags(tags: Vec<String>) -> Vec<String> {
+    tags.into_iter()
+        .map(|tag| tag.trim().trim_start_matches('#').to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+fn memory_result(memory: &Memory) -> Value {
+    json!({ "id": memory.id, "title": memory.title, "tags": memory.tags })
+}
+
+// Run one tool call, returning the JSON sent back to the model; failures are
+// reported to the model as `{"error": ...}` so it can tell the user
+async fn run(state: &ServerState, call: &ToolCall, caller: &Caller) -> Value {
+    info!("[CHAT_TOOLS] Running {} with {}", call.function.name, call.function.arguments);
+    let result = match call.function.name.as_str() {
+        CREATE_MEMORY => match serde_json::from_str(&call.function.arguments) {
+            Ok(args) => create_memory(state, args, caller).await,
+            Err(err) => Err(format!("Invalid arguments: {}", err)),
+        },
+        SEARCH_MEMORIES => match serde_json::from_str(&call.function.arguments) {
+            Ok(args) => search_memories(state, args, caller).map_err(|err| err.to_string()),
+            Err(err) => Err(format!("Invalid arguments: {}", err)),
+        },
+        UPDATE_MEMORY => match serde_json::from_str(&call.function.arguments) {
+            Ok(args) => update_memory(state, args, caller).map_err(|err| err.to_string()),
+            Err(err) => Err(format!("Invalid arguments: {}", err)),
+        },
+        other => Err(format!("Unknown tool: {}", other)),
+    };
+    result.unwrap_or_else(|err| {
+        warn!("[CHAT_TOOLS] {} failed: {}", call.function.name, err);
+        json!({ "error": err })
+    })
+}
+
+async fn create_memory(state: &ServerState, args: CreateArgs, caller: &Caller) -> Result<Value, String> {
+    state.memory_store.check_available().map_err(|err| err.to_string())?;
+    let title = resolve_title(state, args.title, &args.content).await?;
+    let remind_at = state.capture.remind_at(None, &title, &args.content);
+    let mut memory = state.memory_store.new_memory(title, args.content, clean_tags(args.tags)).map_err(|err| err.to_string())?;
+    memory.remind_at = remind_at;
+    memory.owner = caller.user().map(str::to_string);
+    tag_new_memory(state, &mut memory, caller.principal()).await;
+    state.memory_store.save(&memory).map_err(|err| err.to_string())?;
+    info!("[CHAT_TOOLS] Created memory {} from chat", memory.id);
+    Ok(memory_result(&memory))
+}
+
+fn search_memories(state: &ServerState, args: SearchArgs, caller: &Caller) -> Result<Value, MemoryError> {
+    let mut memories = filter_visible(state.memory_store.search(&args.query)?, caller.principal());
+    if let Some(tag) = args.tag.as_deref().map(|tag| tag.trim_start_matches('#')).filter(|tag| !tag.is_empty()) {
+        let synonyms = state.memory_store.tag_synonyms(tag)?;
+        memories.retain(|memory| has_any_tag(memory, &synonyms));
+    }
+    let limit = args.limit.unwrap_or(DEFAULT_SEARCH_LIMIT).clamp(1, MAX_SEARCH_LIMIT);
+    let results: Vec<Value> = memories.iter()
+        .take(limit)
+        .map(|memory| {
+            let content = match memory.content.chars().count() > MAX_RESULT_CHARS {
+                true => memory.summary.clone().unwrap_or_else(|| summary_from_content(&memory.content)),
+                false => memory.content.clone(),
+            };
+            json!({
+                "id": memory.id,
+                "title": memory.title,
+                "tags": memory.tags,
+                "updated_at": memory.updated_at,
+                "content": content,
+            })
+        })
+        .collect();
+    Ok(json!({ "memories": results }))
+}
+
+fn update_memory(state: &ServerState, args: UpdateArgs, caller: &Caller) -> Result<Value, MemoryError> {
+    let mut memory = state.memory_store.authorize(&args.id, caller.principal(), Permission::Write)?;
+    if memory.read_only {
+        return Err(MemoryError::ReadOnly(args.id));
+    }
+
+    if let Some(title) = args.title.filter(|title| !title.trim().is_empty()) {
+        memory.title = title;
+    }
+    if let Some(content) = args.content {
+        memory.content = content;
+    }
+    if let Some(append) = args.append.filter(|append| !append.is_empty()) {
+        if !memory.content.is_empty() && !memory.content.ends_with('\n') {
+            memory.content.push('\n');
+        }
+        memory.content.push_str(&append);
+    }
+    if let Some(tags) = args.tags {
+        memory.tags = clean_tags(tags);
+    }
+    memory.tags.retain(|tag| !tag.trim().is_empty());
+    for tag in clean_tags(args.add_tags) {
+        if !memory.tags.iter().any(|existing| existing.eq_ignore_ascii_case(&tag)) {
+            memory.tags.push(tag);
+        }
+    }
+    memory.tags = state.memory_store.normalize_tags(&memory.tags)?;
+    memory.updated_at = Utc::now();
+
+    state.memory_store.save(&memory)?;
+    info!("[CHAT_TOOLS] Updated memory {} from chat", memory.id);
+    Ok(memory_result(&memory))
+}
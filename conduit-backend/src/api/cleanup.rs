@@ -0,0 +1,430 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::memory::{cosine_similarity, BatchOperation, Memory, MemoryEmbedding};
+use super::error::{ApiError, OpenAiErrorResponse};
+use super::auth::Caller;
+use super::openai::{ChatCompletionRequest, ChatMessage};
+use super::state::ServerState;
+
+/// Memories embedded per request when filling in missing embeddings
+const EMBED_BATCH_SIZE: usize = 32;
+
+/// Most tags sent to the model when asking for consolidations
+const MAX_PROMPT_TAGS: usize = 500;
+
+/// Settings for the cleanup assistant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CleanupConfig {
+    /// Model asked to group tags that mean the same thing; without one only
+    /// spelling variants are grouped
+    pub model: Option<String>,
+    /// Cosine similarity at or above which two memories count as duplicates
+    pub duplicate_threshold: f32,
+}
+
+impl Default for CleanupConfig {
+    fn default() -> Self {
+        Self {
+            model: None,
+            duplicate_threshold: 0.9,
+        }
+    }
+}
+
+impl CleanupConfig {
+    /// Override settings with `CONDUIT_CLEANUP_MODEL` and
+    /// `CONDUIT_DUPLICATE_THRESHOLD`, if set
+    pub fn apply_env(&mut self) {
+        if let Some(model) = std::env::var("CONDUIT_CLEANUP_MODEL").ok().filter(|model| !model.is_empty()) {
+            self.model = Some(model);
+        }
+        if let Some(threshold) = std::env::var("CONDUIT_DUPLICATE_THRESHOLD").ok().and_then(|v| v.parse().ok()) {
+            self.duplicate_threshold = threshold;
+        }
+    }
+}
+
+/// Routes for the cleanup assistant
+pub fn router() -> Router<Arc<ServerState>> {
+    Router::new()
+        .route("/api/maintenance/cleanup", post(plan_cleanup_handler))
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(default)]
+pub struct CleanupQuery {
+    /// Similarity at or above which memories count as duplicates, overriding the server setting
+    pub threshold: Option<f32>,
+    /// Only group spelling variants of tags, without asking the model
+    pub skip_llm: bool,
+}
+
+/// How a tag suggestion was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SuggestionSource {
+    /// The tags differ only in case, separators, a leading `#` or a plural `s`
+    Heuristic,
+    /// The model judged the tags to mean the same thing
+    Llm,
+}
+
+/// Tags that could be folded into one
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TagSuggestion {
+    /// Every tag of the group, including the canonical one
+    pub tags: Vec<String>,
+    /// The tag the others would be replaced by
+    pub canonical: String,
+    /// Memories tagged with any tag of the group
+    pub memory_count: usize,
+    pub source: SuggestionSource,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub operation: BatchOperation,
+}
+
+/// Memories similar enough to be merged
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DuplicateGroup {
+    pub memory_ids: Vec<String>,
+    pub titles: Vec<String>,
+    /// Highest similarity between two memories of the group
+    pub similarity: f32,
+    /// The oldest memory, which the others would be merged into
+    pub keep: String,
+    pub operation: BatchOperation,
+}
+
+/// Proposed changes for review, applied by posting `operations` (or a
+/// subset of them) to `/api/memories/batch`
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CleanupPlan {
+    pub generated_at: DateTime<Utc>,
+    /// Embedding model the duplicates were found with
+    pub model: String,
+    pub tag_suggestions: Vec<TagSuggestion>,
+    pub duplicates: Vec<DuplicateGroup>,
+    /// Every suggested operation, tag consolidations first
+    pub operations: Vec<BatchOperation>,
+}
+
+// The form spelling variants of a tag share, such as `devop` for `#Dev_Ops`
+fn tag_key(tag: &str) -> String {
+    let key: String = tag.trim().trim_start_matches('#').to_lowercase()
+        .chars()
+        .filter(|c| !matches!(c, '-' | '_') && !c.is_whitespace())
+        .collect();
+    match key.strip_suffix('s') {
+        Some(stem) if stem.len() >= 3 && !stem.ends_with('s') => stem.to_string(),
+        _ => key,
+    }
+}
+
+// How many memories use each tag
+fn tag_counts(memories: &[Memory]) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for memory in memories {
+        for tag in memory.tags.iter().filter(|tag| !tag.is_empty()) {
+            *counts.entry(tag.clone()).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+// A suggestion to fold `tags` into the most used of them, unless `canonical` is given
+fn suggestion(
+    tags: Vec<String>,
+    canonical: Option<String>,
+    counts: &BTreeMap<String, usize>,
+    memories: &[Memory],
+    source: SuggestionSource,
+    reason: Option<String>,
+) -> TagSuggestion {
+    // Prefer the most used tag, then plain lowercase ones without a `#`
+    let canonical = canonical.unwrap_or_else(|| {
+        tags.iter()
+            .min_by_key(|tag| {
+                let plain = !tag.starts_with('#') && **tag == tag.to_lowercase();
+                (std::cmp::Reverse(counts.get(*tag).copied().unwrap_or(0)), !plain, tag.len(), (*tag).clone())
+            })
+            .cloned()
+            .unwrap_or_default()
+    });
+    let memory_count = memories.iter().filter(|memory| memory.tags.iter().any(|tag| tags.contains(tag))).count();
+    let from = tags.iter().filter(|tag| **tag != canonical).cloned().collect();
+    TagSuggestion {
+        operation: BatchOperation::Retag { from, to: canonical.clone(), memory_ids: Vec::new() },
+        tags,
+        canonical,
+        memory_count,
+        source,
+        reason,
+    }
+}
+
+// Group tags that differ only in spelling
+fn heuristic_tag_suggestions(counts: &BTreeMap<String, usize>, memories: &[Memory]) -> Vec<TagSuggestion> {
+    let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for tag in counts.keys() {
+        groups.entry(tag_key(tag)).or_default().push(tag.clone());
+    }
+    groups.into_values()
+        .filter(|tags| tags.len() > 1)
+        .map(|tags| suggestion(tags, None, counts, memories, SuggestionSource::Heuristic, None))
+        .collect()
+}
+
+#[derive(Debug, Deserialize)]
+struct LlmTagGroup {
+    tags: Vec<String>,
+    canonical: Option<String>,
+    reason: Option<String>,
+}
+
+// Ask the upstream model which of the remaining tags mean the same thing
+async fn llm_tag_suggestions(
+    state: &ServerState,
+    model: &str,
+    counts: &BTreeMap<String, usize>,
+    memories: &[Memory],
+    taken: &[String],
+) -> Vec<TagSuggestion> {
+    let Some(provider) = state.provider.as_ref() else {
+        return Vec::new();
+    };
+    let tags: Vec<String> = counts.iter()
+        .filter(|(tag, _)| !taken.contains(tag))
+        .take(MAX_PROMPT_TAGS)
+        .map(|(tag, count)| format!("{} ({})", tag, count))
+        .collect();
+    if tags.len() < 2 {
+        return Vec::new();
+    }
+
+    let req = ChatCompletionRequest {
+        model: model.to_string(),
+        messages: vec![
+            ChatMessage {
+                role: "system".to_string(),
+                content: "You tidy the tags of a note-taking app. The user lists tags with how many notes use each. \
+                    Find groups of tags that mean the same thing, such as synonyms or abbreviations. \
+                    Reply with a JSON array only, where each item is {\"tags\": [...], \"canonical\": \"...\", \"reason\": \"...\"}. \
+                    Use only the tags given, and reply [] when no tags should be merged.".into(),
+                ..Default::default()
+            },
+            ChatMessage {
+                role: "user".to_string(),
+                content: tags.join("\n").into(),
+                ..Default::default()
+            },
+        ],
+        temperature: Some(0.0),
+        max_tokens: Some(1024),
+        stream: None,
+        response_format: None,
+        tools: Vec::new(),
+        tool_choice: None,
+        conversation_id: None,
+        conduit: None,
+    };
+
+    let reply = match provider.chat_completion(&req).await {
+        Ok(completion) => completion.choices.into_iter().next().map(|choice| choice.message.content.text()).unwrap_or_default(),
+        Err(err) => {
+            warn!("[CLEANUP] Tag suggestions from {} failed: {}", model, err);
+            return Vec::new();
+        }
+    };
+    // Models often wrap the array in prose or a code fence
+    let groups: Vec<LlmTagGroup> = match (reply.find('['), reply.rfind(']')) {
+        (Some(start), Some(end)) if start < end => serde_json::from_str(&reply[start..=end]).unwrap_or_else(|err| {
+            warn!("[CLEANUP] Could not read tag suggestions from {}: {}", model, err);
+            Vec::new()
+        }),
+        _ => Vec::new(),
+    };
+
+    let mut taken = taken.to_vec();
+    let mut suggestions = Vec::new();
+    for group in groups {
+        let mut tags: Vec<String> = Vec::new();
+        for tag in group.tags {
+            if counts.contains_key(&tag) && !taken.contains(&tag) && !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        if tags.len() < 2 {
+            continue;
+        }
+        let canonical = group.canonical.filter(|canonical| tags.contains(canonical));
+        taken.extend(tags.iter().cloned());
+        suggestions.push(suggestion(tags, canonical, counts, memories, SuggestionSource::Llm, group.reason));
+    }
+    suggestions
+}
+
+// Embeddings of the memories for `model`, using stored ones that are up to
+// date and computing the rest; memories that cannot be embedded are left out
+async fn memory_vectors(state: &ServerState, model: &str, memories: &[Memory]) -> Vec<(usize, Vec<f32>)> {
+    let mut vectors = Vec::new();
+    let mut missing = Vec::new();
+    for (index, memory) in memories.iter().enumerate() {
+        match state.memory_store.get_embedding(&memory.id) {
+            Ok(Some(stored)) if stored.model == model && stored.content_hash == memory.content_hash() => {
+                vectors.push((index, stored.vector));
+            }
+            _ => missing.push(index),
+        }
+    }
+
+    let (provider, target) = state.embeddings.select(model);
+    for batch in missing.chunks(EMBED_BATCH_SIZE) {
+        let texts: Vec<String> = batch.iter().map(|&index| memories[index].embedding_text()).collect();
+        match provider.embed(&target, &texts).await {
+            Ok(embeddings) => vectors.extend(batch.iter().copied().zip(embeddings.vectors)),
+            Err(err) => warn!("[CLEANUP] Skipping {} memories that could not be embedded: {}", batch.len(), err),
+        }
+    }
+    vectors
+}
+
+// Group memories whose embeddings are at least `threshold` alike, directly or
+// through other memories of the group
+fn duplicate_groups(memories: &[Memory], vectors: &[(usize, Vec<f32>)], threshold: f32) -> Vec<DuplicateGroup> {
+    fn root(parents: &mut [usize], mut node: usize) -> usize {
+        while parents[node] != node {
+            parents[node] = parents[parents[node]];
+            node = parents[node];
+        }
+        node
+    }
+
+    let mut parents: Vec<usize> = (0..vectors.len()).collect();
+    let mut best = vec![0.0f32; vectors.len()];
+    for i in 0..vectors.len() {
+        for j in i + 1..vectors.len() {
+            let similarity = cosine_similarity(&vectors[i].1, &vectors[j].1);
+            if similarity >= threshold {
+                let (a, b) = (root(&mut parents, i), root(&mut parents, j));
+                parents[b] = a;
+                best[a] = best[a].max(best[b]).max(similarity);
+            }
+        }
+    }
+
+    let mut members: HashMap<usize, Vec<&Memory>> = HashMap::new();
+    for node in 0..vectors.len() {
+        let group = root(&mut parents, node);
+        members.entry(group).or_default().push(&memories[vectors[node].0]);
+    }
+
+    let mut groups: Vec<DuplicateGroup> = members.into_iter()
+        .filter(|(_, group)| group.len() > 1)
+        .map(|(root, mut group)| {
+            group.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+            let keep = group[0].id.clone();
+            let others: Vec<String> = group[1..].iter().map(|memory| memory.id.clone()).collect();
+            DuplicateGroup {
+                memory_ids: group.iter().map(|memory| memory.id.clone()).collect(),
+                titles: group.iter().map(|memory| memory.title.clone()).collect(),
+                similarity: best[root],
+                operation: BatchOperation::Merge { into: keep.clone(), memory_ids: others },
+                keep,
+            }
+        })
+        .collect();
+    groups.sort_by(|a, b| b.similarity.total_cmp(&a.similarity).then_with(|| a.keep.cmp(&b.keep)));
+    groups
+}
+
+async fn plan_cleanup(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Query(query): Query<CleanupQuery>,
+) -> Response {
+    info!("[SERVER] Handling plan_cleanup request");
+    // The plan covers every memory, so it is only shown to full-access keys
+    if caller.principal().is_some() {
+        return ApiError::permission("Planning a cleanup requires an API key with access to every memory".to_string()).into_response();
+    }
+    let threshold = query.threshold.unwrap_or(state.cleanup.duplicate_threshold);
+    if !(0.0..=1.0).contains(&threshold) {
+        return ApiError::invalid_request("The duplicate threshold must be between 0 and 1".to_string()).into_response();
+    }
+
+    let store = state.memory_store.clone();
+    let memories: Vec<Memory> = match tokio::task::spawn_blocking(move || store.list()).await {
+        Ok(Ok(memories)) => memories.into_iter().filter(|memory| !memory.read_only).collect(),
+        Ok(Err(err)) => {
+            error!("Error listing memories for cleanup: {:?}", err);
+            return ApiError::from(err).into_response();
+        }
+        Err(err) => {
+            error!("Cleanup task failed: {:?}", err);
+            return ApiError::server(err.to_string()).into_response();
+        }
+    };
+
+    let counts = tag_counts(&memories);
+    let mut tag_suggestions = heuristic_tag_suggestions(&counts, &memories);
+    if let (Some(model), false) = (&state.cleanup.model, query.skip_llm) {
+        let taken: Vec<String> = tag_suggestions.iter().flat_map(|suggestion| suggestion.tags.iter().cloned()).collect();
+        tag_suggestions.extend(llm_tag_suggestions(&state, model, &counts, &memories, &taken).await);
+    }
+
+    let model = state.embeddings.memory_model().to_string();
+    let vectors = memory_vectors(&state, &model, &memories).await;
+    let duplicates = match tokio::task::spawn_blocking(move || duplicate_groups(&memories, &vectors, threshold)).await {
+        Ok(duplicates) => duplicates,
+        Err(err) => {
+            error!("Cleanup task failed: {:?}", err);
+            return ApiError::server(err.to_string()).into_response();
+        }
+    };
+
+    let operations = tag_suggestions.iter().map(|suggestion| suggestion.operation.clone())
+        .chain(duplicates.iter().map(|group| group.operation.clone()))
+        .collect();
+    info!("[SERVER] Planned cleanup: {} tag suggestions, {} duplicate groups", tag_suggestions.len(), duplicates.len());
+    let plan = CleanupPlan {
+        generated_at: Utc::now(),
+        model,
+        tag_suggestions,
+        duplicates,
+        operations,
+    };
+    (StatusCode::OK, Json(plan)).into_response()
+}
+
+#[utoipa::path(
+    post, path = "/api/maintenance/cleanup", tag = "maintenance", operation_id = "plan_cleanup",
+    params(CleanupQuery),
+    responses(
+        (status = 200, description = "Suggested tag consolidations and merges; nothing is changed", body = CleanupPlan),
+        (status = 400, description = "Threshold outside 0 to 1", body = OpenAiErrorResponse),
+        (status = 403, description = "The caller cannot access every memory", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn plan_cleanup_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    query: Query<CleanupQuery>,
+) -> Response {
+    plan_cleanup(state, caller, query).await
+}
@@ -0,0 +1,522 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use axum::{
+    extract::{Query, State},
+    response::{IntoResponse, Response},
+    routing::get,
+    Extension, Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::watch;
+use tracing::{error, info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::memory::{matches_query, Memory, MemoryEvent, MemoryEventKind, MemoryStore};
+use super::accounts::{require_admin, AccountAuth};
+use super::auth::KeyAuth;
+use super::error::OpenAiErrorResponse;
+use super::lock::wait_until_unlocked;
+use super::state::ServerState;
+use super::webhooks::DeliveryStatus;
+
+/// Slack Web API method used when a Slack connector has a bot token
+const SLACK_POST_MESSAGE_URL: &str = "https://slack.com/api/chat.postMessage";
+
+/// Delivery attempts made when none is configured
+const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// Upstream timeout for a single delivery attempt when none is configured
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+
+/// Number of deliveries kept in the log when none is configured
+const DEFAULT_LOG_SIZE: usize = 200;
+
+/// Wait before the first retry; doubled after every failed attempt
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(2);
+
+/// Longest characters of content included in a Slack message
+const SLACK_EXCERPT_CHARS: usize = 300;
+
+/// External service a connector posts to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectorKind {
+    /// POST the `memory.created` event as JSON to `url`
+    #[default]
+    Http,
+    /// Post a message with the title and an excerpt to Slack
+    Slack,
+}
+
+/// An external service new memories can be forwarded to
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConnectorConfig {
+    /// Referenced by rules
+    pub name: String,
+    pub kind: ConnectorKind,
+    /// Endpoint to post to; for Slack, an incoming webhook URL when there is no credential
+    pub url: Option<String>,
+    /// Slack channel to post in when using a bot token
+    pub channel: Option<String>,
+    /// Sent as `Authorization: Bearer <credential>`; a Slack bot token for Slack
+    pub credential: Option<String>,
+    /// Environment variable holding the credential, to keep it out of the config file
+    pub credential_env: Option<String>,
+}
+
+/// Forwards new memories that match every given condition to a connector
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct ForwardRule {
+    /// Name of the connector
+    pub connector: String,
+    /// Tags the memory must all have, ignoring case
+    pub tags: Vec<String>,
+    /// Collection ID or folder the memory must be in
+    pub collection: Option<String>,
+    /// Text the title, content or tags must contain, ignoring case and, per the store's collation, accents
+    pub query: Option<String>,
+}
+
+impl ForwardRule {
+    fn matches(&self, memory: &Memory, store: &MemoryStore) -> bool {
+        let tags = self.tags.iter()
+            .all(|wanted| memory.tags.iter().any(|tag| tag.eq_ignore_ascii_case(wanted)));
+        let query = self.query.as_ref()
+            .is_none_or(|query| matches_query(memory, &store.options.collation.fold(query), &store.options.collation));
+        let collection = self.collection.as_ref().is_none_or(|collection| {
+            store.filter_by_collection(vec![memory.clone()], collection)
+                .is_ok_and(|kept| !kept.is_empty())
+        });
+        tags && query && collection
+    }
+}
+
+/// Settings for forwarding new memories to external services
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConnectorsConfig {
+    pub endpoints: Vec<ConnectorConfig>,
+    pub rules: Vec<ForwardRule>,
+    /// Attempts made per delivery before giving up
+    pub max_attempts: u32,
+    /// Seconds to wait for a service to answer
+    pub timeout_secs: u64,
+    /// Number of recent deliveries listed by `/api/connectors/deliveries`
+    pub log_size: usize,
+}
+
+impl Default for ConnectorsConfig {
+    fn default() -> Self {
+        Self {
+            endpoints: Vec::new(),
+            rules: Vec::new(),
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            timeout_secs: DEFAULT_TIMEOUT_SECS,
+            log_size: DEFAULT_LOG_SIZE,
+        }
+    }
+}
+
+/// A connector as listed by the API, without its credential
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Connector {
+    pub name: String,
+    pub kind: ConnectorKind,
+    /// Scheme and host of the URL, whose path can itself be the credential
+    pub url: String,
+    pub channel: Option<String>,
+    /// Whether requests carry a credential, in a header or in the URL
+    pub authenticated: bool,
+}
+
+/// Configured connectors and the rules forwarding memories to them
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ConnectorList {
+    pub connectors: Vec<Connector>,
+    pub rules: Vec<ForwardRule>,
+}
+
+/// A memory forwarded, or being forwarded, to a connector
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ConnectorDelivery {
+    pub id: String,
+    pub connector: String,
+    pub memory_id: String,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    /// Why the last attempt failed
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Filters for the delivery log
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ConnectorDeliveryQuery {
+    /// Only deliveries to this connector
+    pub connector: Option<String>,
+    /// Only deliveries with this status
+    pub status: Option<DeliveryStatus>,
+}
+
+// A connector with its credential resolved
+#[derive(Debug, Clone)]
+struct Target {
+    name: String,
+    kind: ConnectorKind,
+    url: String,
+    channel: Option<String>,
+    credential: Option<String>,
+}
+
+impl Target {
+    fn resolve(config: &ConnectorConfig) -> Result<Self, String> {
+        let credential = match &config.credential_env {
+            Some(var) => Some(std::env::var(var).map_err(|_| format!("Connector {}: environment variable {} is not set", config.name, var))?),
+            None => config.credential.clone(),
+        }.filter(|credential| !credential.is_empty());
+
+        let url = match (config.kind, &config.url, &credential) {
+            (_, Some(url), _) => url.clone(),
+            (ConnectorKind::Slack, None, Some(_)) => SLACK_POST_MESSAGE_URL.to_string(),
+            (ConnectorKind::Slack, None, None) => {
+                return Err(format!("Connector {} needs an incoming webhook `url` or a bot token `credential`", config.name));
+            }
+            (ConnectorKind::Http, None, _) => return Err(format!("Connector {} needs a `url`", config.name)),
+        };
+        let parsed = reqwest::Url::parse(&url).map_err(|e| format!("Connector {}: invalid URL {}: {}", config.name, url, e))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(format!("Connector {}: only http and https URLs are supported", config.name));
+        }
+        if config.kind == ConnectorKind::Slack && credential.is_some() && config.channel.is_none() {
+            return Err(format!("Connector {} needs a `channel` to post with a bot token", config.name));
+        }
+
+        Ok(Self {
+            name: config.name.clone(),
+            kind: config.kind,
+            url,
+            channel: config.channel.clone(),
+            credential,
+        })
+    }
+
+    fn to_connector(&self) -> Connector {
+        Connector {
+            name: self.name.clone(),
+            kind: self.kind,
+            url: reqwest::Url::parse(&self.url)
+                .map(|url| url.origin().ascii_serialization())
+                .unwrap_or_default(),
+            channel: self.channel.clone(),
+            // Slack incoming webhooks carry their secret in the path
+            authenticated: self.credential.is_some() || self.kind == ConnectorKind::Slack,
+        }
+    }
+
+    fn body(&self, event: &MemoryEvent, memory: &Memory) -> serde_json::Value {
+        match self.kind {
+            ConnectorKind::Http => serde_json::to_value(event).unwrap_or_default(),
+            ConnectorKind::Slack => {
+                let mut excerpt: String = memory.content.chars().take(SLACK_EXCERPT_CHARS).collect();
+                if memory.content.chars().count() > SLACK_EXCERPT_CHARS {
+                    excerpt.push('…');
+                }
+                let mut text = format!("*{}*\n{}", memory.title, excerpt);
+                if !memory.tags.is_empty() {
+                    text.push_str(&format!("\n_{}_", memory.tags.join(", ")));
+                }
+                match &self.channel {
+                    Some(channel) if self.credential.is_some() => serde_json::json!({ "channel": channel, "text": text }),
+                    _ => serde_json::json!({ "text": text }),
+                }
+            }
+        }
+    }
+}
+
+/// Connectors, the rules forwarding to them and the log of their deliveries
+pub struct Connectors {
+    config: ConnectorsConfig,
+    targets: Vec<Target>,
+    deliveries: Mutex<VecDeque<ConnectorDelivery>>,
+    client: reqwest::Client,
+}
+
+impl Connectors {
+    /// Resolve the connectors' credentials and check that every rule names one
+    pub fn new(config: ConnectorsConfig) -> Result<Self, String> {
+        let mut targets: Vec<Target> = Vec::new();
+        for endpoint in &config.endpoints {
+            if targets.iter().any(|target| target.name == endpoint.name) {
+                return Err(format!("Connector {} is configured twice", endpoint.name));
+            }
+            targets.push(Target::resolve(endpoint)?);
+        }
+        if let Some(rule) = config.rules.iter().find(|rule| !targets.iter().any(|target| target.name == rule.connector)) {
+            return Err(format!("Forward rule names unknown connector: {}", rule.connector));
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .user_agent(concat!("conduit-connectors/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(|e| format!("Failed to create connector client: {}", e))?;
+
+        Ok(Self {
+            config,
+            targets,
+            deliveries: Mutex::new(VecDeque::new()),
+            client,
+        })
+    }
+
+    pub fn list(&self) -> ConnectorList {
+        ConnectorList {
+            connectors: self.targets.iter().map(Target::to_connector).collect(),
+            rules: self.config.rules.clone(),
+        }
+    }
+
+    /// Recent deliveries matching `query`, newest first
+    pub fn deliveries(&self, query: &ConnectorDeliveryQuery) -> Vec<ConnectorDelivery> {
+        let deliveries = self.deliveries.lock().unwrap_or_else(|e| e.into_inner());
+        deliveries.iter()
+            .rev()
+            .filter(|delivery| query.connector.as_ref().is_none_or(|name| &delivery.connector == name))
+            .filter(|delivery| query.status.is_none_or(|status| delivery.status == status))
+            .cloned()
+            .collect()
+    }
+
+    // Connectors with a rule matching the memory, each at most once
+    fn targets_for(&self, memory: &Memory, store: &MemoryStore) -> Vec<Target> {
+        self.targets.iter()
+            .filter(|target| {
+                self.config.rules.iter()
+                    .any(|rule| rule.connector == target.name && rule.matches(memory, store))
+            })
+            .cloned()
+            .collect()
+    }
+
+    // Add or replace a delivery in the log, dropping the oldest once it is full
+    fn record(&self, delivery: &ConnectorDelivery) {
+        let mut deliveries = self.deliveries.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(logged) = deliveries.iter_mut().rev().find(|logged| logged.id == delivery.id) {
+            *logged = delivery.clone();
+            return;
+        }
+        while deliveries.len() >= self.config.log_size.max(1) {
+            deliveries.pop_front();
+        }
+        deliveries.push_back(delivery.clone());
+    }
+}
+
+/// Forward new memories matching the rules until the server shuts down
+///
+/// While the workspace is locked, memories wait to be forwarded until it is unlocked.
+pub fn spawn(connectors: Arc<Connectors>, state: &ServerState) {
+    if connectors.config.rules.is_empty() {
+        return;
+    }
+    let mut receiver = state.events.subscribe();
+    let mut shutdown = state.shutdown.clone();
+    let store = state.memory_store.clone();
+
+    tokio::spawn(async move {
+        loop {
+            let event = tokio::select! {
+                event = receiver.recv() => event,
+                _ = shutdown.wait_for(|stopped| *stopped) => break,
+            };
+            match event {
+                Ok(event) if event.kind == MemoryEventKind::Created => {
+                    let Some(memory) = event.memory.clone() else {
+                        continue;
+                    };
+                    if !wait_until_unlocked(&store, &mut shutdown).await {
+                        break;
+                    }
+                    for target in connectors.targets_for(&memory, &store) {
+                        tokio::spawn(deliver(connectors.clone(), target, event.clone(), memory.clone(), shutdown.clone()));
+                    }
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(missed)) => warn!("[CONNECTORS] Fell behind, {} events not forwarded", missed),
+                Err(RecvError::Closed) => break,
+            }
+        }
+        info!("[CONNECTORS] Server stopped, ending forwarding");
+    });
+}
+
+// Post one memory to one connector, retrying with exponential backoff
+async fn deliver(connectors: Arc<Connectors>, target: Target, event: MemoryEvent, memory: Memory, mut shutdown: watch::Receiver<bool>) {
+    let body = target.body(&event, &memory);
+    let mut delivery = ConnectorDelivery {
+        id: ulid::Ulid::new().to_string(),
+        connector: target.name.clone(),
+        memory_id: memory.id.clone(),
+        status: DeliveryStatus::Pending,
+        attempts: 0,
+        error: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    };
+    connectors.record(&delivery);
+
+    let max_attempts = connectors.config.max_attempts.max(1);
+    let mut delay = RETRY_BASE_DELAY;
+    loop {
+        delivery.attempts += 1;
+        let mut request = connectors.client.post(&target.url).json(&body);
+        if let Some(credential) = &target.credential {
+            request = request.bearer_auth(credential);
+        }
+
+        // Retry network errors, server errors and rate limits; other answers are final
+        let retry = match request.send().await {
+            Ok(response) if response.status().is_success() => match target.kind {
+                // The Slack Web API answers 200 with `ok: false` on errors
+                ConnectorKind::Slack if target.credential.is_some() => {
+                    let answer: serde_json::Value = response.json().await.unwrap_or_default();
+                    match answer.get("ok").and_then(|ok| ok.as_bool()) {
+                        Some(true) => {
+                            delivery.status = DeliveryStatus::Succeeded;
+                            false
+                        }
+                        _ => {
+                            let reason = answer.get("error").and_then(|e| e.as_str()).unwrap_or("unknown error");
+                            delivery.error = Some(format!("Slack answered: {}", reason));
+                            reason == "ratelimited"
+                        }
+                    }
+                }
+                _ => {
+                    delivery.status = DeliveryStatus::Succeeded;
+                    false
+                }
+            },
+            Ok(response) => {
+                let status = response.status();
+                delivery.error = Some(format!("Service answered {}", status));
+                status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            }
+            Err(e) => {
+                // The URL of a Slack incoming webhook is its credential
+                delivery.error = Some(e.without_url().to_string());
+                true
+            }
+        };
+
+        if delivery.status == DeliveryStatus::Succeeded {
+            delivery.error = None;
+        } else if !retry || delivery.attempts >= max_attempts {
+            delivery.status = DeliveryStatus::Failed;
+        }
+        delivery.updated_at = Utc::now();
+        connectors.record(&delivery);
+
+        match delivery.status {
+            DeliveryStatus::Succeeded => {
+                info!("[CONNECTORS] Forwarded memory {} to {}", memory.id, target.name);
+                return;
+            }
+            DeliveryStatus::Failed => {
+                error!(
+                    "[CONNECTORS] Giving up on forwarding memory {} to {} after {} attempt(s): {}",
+                    memory.id, target.name, delivery.attempts, delivery.error.as_deref().unwrap_or_default(),
+                );
+                return;
+            }
+            DeliveryStatus::Pending => {
+                warn!("[CONNECTORS] Attempt {} of {} for {} failed, retrying in {:?}", delivery.attempts, max_attempts, target.name, delay);
+            }
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = shutdown.wait_for(|stopped| *stopped) => {
+                delivery.status = DeliveryStatus::Failed;
+                delivery.error = Some("Server stopped before the delivery succeeded".to_string());
+                delivery.updated_at = Utc::now();
+                connectors.record(&delivery);
+                return;
+            }
+        }
+        delay *= 2;
+    }
+}
+
+pub fn router() -> Router<Arc<ServerState>> {
+    Router::new()
+        .route("/api/connectors", get(list_connectors_handler))
+        .route("/api/connectors/deliveries", get(list_connector_deliveries_handler))
+}
+
+async fn list_connectors(
+    State(state): State<Arc<ServerState>>,
+    account: Option<Extension<AccountAuth>>,
+    key: Option<Extension<KeyAuth>>,
+) -> Response {
+    info!("[SERVER] Handling list_connectors request");
+    if let Err(err) = require_admin(&state, account.as_deref(), key.as_deref(), "list connectors") {
+        return err.into_response();
+    }
+    Json(state.connectors.list()).into_response()
+}
+
+async fn list_connector_deliveries(
+    State(state): State<Arc<ServerState>>,
+    account: Option<Extension<AccountAuth>>,
+    key: Option<Extension<KeyAuth>>,
+    Query(query): Query<ConnectorDeliveryQuery>,
+) -> Response {
+    info!("[SERVER] Handling list_connector_deliveries request");
+    if let Err(err) = require_admin(&state, account.as_deref(), key.as_deref(), "list connector deliveries") {
+        return err.into_response();
+    }
+    Json(state.connectors.deliveries(&query)).into_response()
+}
+
+#[utoipa::path(
+    get, path = "/api/connectors", tag = "connectors", operation_id = "list_connectors",
+    responses(
+        (status = 200, description = "Configured connectors, without credentials, and forwarding rules", body = ConnectorList),
+        (status = 403, description = "Accounts are enabled and the caller is not an admin", body = OpenAiErrorResponse)
+    )
+)]
+#[axum::debug_handler]
+async fn list_connectors_handler(
+    state: State<Arc<ServerState>>,
+    account: Option<Extension<AccountAuth>>,
+    key: Option<Extension<KeyAuth>>,
+) -> Response {
+    list_connectors(state, account, key).await
+}
+
+#[utoipa::path(
+    get, path = "/api/connectors/deliveries", tag = "connectors", operation_id = "list_connector_deliveries",
+    params(ConnectorDeliveryQuery),
+    responses(
+        (status = 200, description = "Recent deliveries, newest first", body = [ConnectorDelivery]),
+        (status = 403, description = "Accounts are enabled and the caller is not an admin", body = OpenAiErrorResponse)
+    )
+)]
+#[axum::debug_handler]
+async fn list_connector_deliveries_handler(
+    state: State<Arc<ServerState>>,
+    account: Option<Extension<AccountAuth>>,
+    key: Option<Extension<KeyAuth>>,
+    query: Query<ConnectorDeliveryQuery>,
+) -> Response {
+    list_connector_deliveries(state, account, key, query).await
+}
@@ -0,0 +1,227 @@
+use std::sync::{Arc, Mutex};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use futures::stream::StreamExt;
+use tracing::{error, info};
+
+use crate::memory::{
+    Conversation, ConversationInfo, ConversationToMemoryRequest, CreateConversationRequest, Memory, MemoryError,
+};
+use crate::provider::ChatCompletionStream;
+use super::error::{ApiError, OpenAiErrorResponse};
+use super::auth::Caller;
+use super::openai::{ChatCompletionRequest, ChatCompletionResponse, ChatMessage};
+use super::state::ServerState;
+use super::validation::ValidJson;
+
+/// Routes for chat sessions kept in the store
+pub fn router() -> Router<Arc<ServerState>> {
+    Router::new()
+        .route("/api/conversations", get(list_conversations_handler).post(create_conversation_handler))
+        .route("/api/conversations/:id", get(get_conversation_handler))
+        .route("/api/conversations/:id/memory", post(conversation_to_memory_handler))
+}
+
+/// Messages of a chat request continuing a conversation, added to it with the reply
+pub(super) struct PendingExchange {
+    id: String,
+    owner: Option<String>,
+    messages: Vec<ChatMessage>,
+}
+
+/// Send the history of the conversation a chat request continues before its messages
+///
+/// Returns the exchange to record once the model has replied, or `None` when
+/// the request names no conversation.
+pub(super) fn continue_conversation(
+    state: &ServerState,
+    caller: &Caller,
+    req: &mut ChatCompletionRequest,
+) -> Result<Option<PendingExchange>, MemoryError> {
+    let Some(id) = req.conversation_id.clone() else {
+        return Ok(None);
+    };
+    let conversation = state.memory_store.get_conversation(&id, caller.user())?;
+    info!("[CONVERSATION] Continuing {} after {} messages", id, conversation.messages.len());
+
+    let messages = std::mem::replace(&mut req.messages, conversation.messages);
+    req.messages.extend(messages.iter().cloned());
+    Ok(Some(PendingExchange { id, owner: caller.user().map(str::to_string), messages }))
+}
+
+impl PendingExchange {
+    /// Add the request's messages and the model's reply to the conversation
+    ///
+    /// The reply has already been sent, so failures are only logged.
+    pub(super) fn record(mut self, state: &ServerState, model: &str, reply: ChatMessage) {
+        self.messages.push(reply);
+        let count = self.messages.len();
+        match state.memory_store.append_to_conversation(&self.id, self.owner.as_deref(), Some(model), self.messages) {
+            Ok(_) => info!("[CONVERSATION] Added {} messages to {}", count, self.id),
+            Err(err) => error!("[CONVERSATION] Failed to add messages to {}: {}", self.id, err),
+        }
+    }
+
+    /// Record the first choice of a completion as the reply
+    pub(super) fn record_completion(self, state: &ServerState, completion: &ChatCompletionResponse) {
+        if let Some(choice) = completion.choices.first() {
+            self.record(state, &completion.model, choice.message.clone());
+        }
+    }
+
+    /// Pass chunks through, recording the streamed reply once it is complete
+    ///
+    /// Nothing is recorded when the stream fails.
+    pub(super) fn record_stream(self, state: Arc<ServerState>, model: String, chunks: ChatCompletionStream) -> ChatCompletionStream {
+        let reply = Arc::new(Mutex::new(Some(String::new())));
+
+        let collected = reply.clone();
+        let chunks = chunks.inspect(move |chunk| {
+            let mut collected = collected.lock().unwrap_or_else(|e| e.into_inner());
+            match chunk {
+                Ok(chunk) => {
+                    let content = chunk.choices.first().and_then(|choice| choice.delta.content.as_deref());
+                    if let (Some(collected), Some(content)) = (collected.as_mut(), content) {
+                        collected.push_str(content);
+                    }
+                }
+                Err(_) => *collected = None,
+            }
+        });
+
+        let done = futures::stream::once(async move {
+            let content = reply.lock().unwrap_or_else(|e| e.into_inner()).take();
+            if let Some(content) = content {
+                let message = ChatMessage { role: "assistant".to_string(), content: content.into(), ..Default::default() };
+                self.record(&state, &model, message);
+            }
+            futures::stream::empty()
+        }).flatten();
+
+        chunks.chain(done).boxed()
+    }
+}
+
+fn error_response(context: &str, err: MemoryError) -> Response {
+    error!("Error {}: {:?}", context, err);
+    ApiError::from(err).into_response()
+}
+
+async fn list_conversations(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling list_conversations request");
+    match state.memory_store.list_conversations(caller.user()) {
+        Ok(conversations) => (StatusCode::OK, Json(conversations)).into_response(),
+        Err(err) => error_response("listing conversations", err),
+    }
+}
+
+async fn create_conversation(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    ValidJson(req): ValidJson<CreateConversationRequest>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling create_conversation request with {} messages", req.messages.len());
+    match state.memory_store.create_conversation(req, caller.user()) {
+        Ok(conversation) => (StatusCode::CREATED, Json(conversation)).into_response(),
+        Err(err) => error_response("creating conversation", err),
+    }
+}
+
+async fn get_conversation(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling get_conversation request for id: {}", id);
+    match state.memory_store.get_conversation(&id, caller.user()) {
+        Ok(conversation) => (StatusCode::OK, Json(conversation)).into_response(),
+        Err(err) => error_response("getting conversation", err),
+    }
+}
+
+async fn conversation_to_memory(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+    req: Option<Json<ConversationToMemoryRequest>>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling conversation_to_memory request for id: {}", id);
+    let req = req.map(|Json(req)| req).unwrap_or_default();
+    match state.memory_store.conversation_to_memory(&id, caller.user(), req) {
+        Ok(memory) => (StatusCode::CREATED, Json(memory)).into_response(),
+        Err(err) => error_response("saving conversation as memory", err),
+    }
+}
+
+#[utoipa::path(
+    get, path = "/api/conversations", tag = "conversations", operation_id = "list_conversations",
+    responses((status = 200, description = "Conversations the caller can see, most recently updated first", body = Vec<ConversationInfo>))
+)]
+#[axum::debug_handler]
+async fn list_conversations_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+) -> impl IntoResponse {
+    list_conversations(state, caller).await
+}
+
+#[utoipa::path(
+    post, path = "/api/conversations", tag = "conversations", operation_id = "create_conversation",
+    request_body = CreateConversationRequest,
+    responses(
+        (status = 201, description = "Conversation started", body = Conversation),
+        (status = 403, description = "The store is read-only", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn create_conversation_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    json: ValidJson<CreateConversationRequest>,
+) -> impl IntoResponse {
+    create_conversation(state, caller, json).await
+}
+
+#[utoipa::path(
+    get, path = "/api/conversations/{id}", tag = "conversations", operation_id = "get_conversation",
+    params(("id" = String, Path, description = "Conversation ID")),
+    responses(
+        (status = 200, body = Conversation),
+        (status = 404, description = "Conversation not found", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn get_conversation_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+) -> impl IntoResponse {
+    get_conversation(state, caller, path).await
+}
+
+#[utoipa::path(
+    post, path = "/api/conversations/{id}/memory", tag = "conversations", operation_id = "conversation_to_memory",
+    params(("id" = String, Path, description = "Conversation ID")),
+    request_body(content = Option<ConversationToMemoryRequest>, description = "Title and tags of the memory; optional"),
+    responses(
+        (status = 201, description = "Memory holding the conversation's transcript, with its images attached", body = Memory),
+        (status = 404, description = "Conversation not found", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn conversation_to_memory_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+    json: Option<Json<ConversationToMemoryRequest>>,
+) -> impl IntoResponse {
+    conversation_to_memory(state, caller, path, json).await
+}
@@ -0,0 +1,147 @@
+use axum::http::{HeaderName, HeaderValue, Method};
+use serde::{Deserialize, Serialize};
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, Any, CorsLayer};
+use tracing::warn;
+
+/// Origin entry matching any loopback origin on any port, including the Tauri webview
+pub const LOCALHOST_ORIGINS: &str = "localhost";
+
+/// Entry allowing any origin, method or header
+const WILDCARD: &str = "*";
+
+/// Cross-origin settings for browser clients
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CorsConfig {
+    /// Whether to send CORS headers at all; disable when the API is only used by
+    /// the embedding application or by non-browser clients
+    pub enabled: bool,
+    /// Origins allowed to call the API, e.g. `http://localhost:1420`;
+    /// `localhost` allows every loopback origin and `*` allows any origin
+    pub origins: Vec<String>,
+    /// Methods allowed in cross-origin requests; `*` allows any
+    pub methods: Vec<String>,
+    /// Request headers allowed in cross-origin requests; `*` allows any
+    pub headers: Vec<String>,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            origins: vec![LOCALHOST_ORIGINS.to_string()],
+            methods: ["GET", "POST", "PUT", "PATCH", "DELETE"].map(String::from).to_vec(),
+            headers: ["authorization", "content-type", "x-conduit-workspace"].map(String::from).to_vec(),
+        }
+    }
+}
+
+// Split a comma-separated variable into its non-empty entries
+fn env_list(name: &str) -> Option<Vec<String>> {
+    std::env::var(name).ok().map(|list| {
+        list.split(',')
+            .map(|entry| entry.trim().to_string())
+            .filter(|entry| !entry.is_empty())
+            .collect()
+    })
+}
+
+// Whether an origin is served from the local machine
+fn is_loopback_origin(origin: &HeaderValue) -> bool {
+    let Ok(origin) = origin.to_str() else {
+        return false;
+    };
+    if matches!(origin, "tauri://localhost" | "http://tauri.localhost" | "https://tauri.localhost") {
+        return true;
+    }
+
+    let Some(host) = origin.strip_prefix("http://").or_else(|| origin.strip_prefix("https://")) else {
+        return false;
+    };
+    let host = match host.strip_prefix('[') {
+        // IPv6 literal such as `[::1]:3000`
+        Some(rest) => rest.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "::1")
+}
+
+// Parse entries, warning about and skipping the ones that are not valid
+fn parse_entries<T: std::str::FromStr>(entries: &[String], what: &str) -> Vec<T> {
+    entries.iter()
+        .filter_map(|entry| match entry.parse() {
+            Ok(value) => Some(value),
+            Err(_) => {
+                warn!("[CORS] Ignoring invalid {}: {}", what, entry);
+                None
+            }
+        })
+        .collect()
+}
+
+impl CorsConfig {
+    /// Override settings with the CORS variables that are set
+    ///
+    /// `CONDUIT_CORS_ORIGINS`, `CONDUIT_CORS_METHODS` and `CONDUIT_CORS_HEADERS`
+    /// are comma-separated lists; `CONDUIT_CORS=false` disables CORS.
+    pub fn apply_env(&mut self) {
+        if let Ok(enabled) = std::env::var("CONDUIT_CORS") {
+            self.enabled = !matches!(enabled.to_lowercase().as_str(), "0" | "false" | "no" | "off");
+        }
+        if let Some(origins) = env_list("CONDUIT_CORS_ORIGINS") {
+            self.origins = origins;
+        }
+        if let Some(methods) = env_list("CONDUIT_CORS_METHODS") {
+            self.methods = methods;
+        }
+        if let Some(headers) = env_list("CONDUIT_CORS_HEADERS") {
+            self.headers = headers;
+        }
+    }
+
+    fn allow_origin(&self) -> AllowOrigin {
+        if self.origins.iter().any(|origin| origin == WILDCARD) {
+            return AllowOrigin::any();
+        }
+
+        let localhost = self.origins.iter().any(|origin| origin == LOCALHOST_ORIGINS);
+        let origins: Vec<HeaderValue> = parse_entries(
+            &self.origins.iter().filter(|origin| *origin != LOCALHOST_ORIGINS).cloned().collect::<Vec<_>>(),
+            "origin",
+        );
+        AllowOrigin::predicate(move |origin, _| {
+            (localhost && is_loopback_origin(origin)) || origins.contains(origin)
+        })
+    }
+
+    fn allow_methods(&self) -> AllowMethods {
+        if self.methods.iter().any(|method| method == WILDCARD) {
+            return Any.into();
+        }
+        let methods: Vec<Method> = parse_entries(
+            &self.methods.iter().map(|method| method.to_uppercase()).collect::<Vec<_>>(),
+            "method",
+        );
+        methods.into()
+    }
+
+    fn allow_headers(&self) -> AllowHeaders {
+        if self.headers.iter().any(|header| header == WILDCARD) {
+            return Any.into();
+        }
+        let headers: Vec<HeaderName> = parse_entries(&self.headers, "header");
+        headers.into()
+    }
+
+    /// Build the CORS layer for the router, or `None` when CORS is disabled
+    pub fn layer(&self) -> Option<CorsLayer> {
+        if !self.enabled {
+            return None;
+        }
+
+        Some(CorsLayer::new()
+            .allow_origin(self.allow_origin())
+            .allow_methods(self.allow_methods())
+            .allow_headers(self.allow_headers()))
+    }
+}
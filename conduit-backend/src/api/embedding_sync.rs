@@ -0,0 +1,100 @@
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use tracing::{info, warn};
+
+use crate::memory::{MemoryEmbedding, MemoryError, StoredEmbedding};
+use super::state::ServerState;
+
+/// Number of memories sent to the embedding provider at once
+const BATCH_SIZE: usize = 32;
+
+/// Keep stored memory embeddings in sync with memory content
+///
+/// Runs until the server state is dropped. Every `interval` the job checks whether
+/// the store changed and, if so, embeds memories that have no embedding yet or
+/// whose content changed since they were embedded. The first check backfills
+/// memories created before embeddings were stored. Nothing is embedded while
+/// the store is read-only or the workspace is locked.
+pub fn spawn(state: Weak<ServerState>, interval: Duration) {
+    tokio::spawn(async move {
+        let mut synced_at: Option<DateTime<Utc>> = None;
+
+        loop {
+            let Some(state) = state.upgrade() else {
+                break;
+            };
+
+            let store = state.memory_store.clone();
+            let stamp = tokio::task::spawn_blocking(move || store.last_modified()).await;
+            match stamp {
+                // Embeddings are written into the store, so wait until it accepts changes
+                _ if state.memory_store.is_read_only() => {}
+                // Content is sent to the embedding provider, which waits for the owner
+                _ if state.memory_store.workspace_lock().is_locked() => {}
+                Ok(Ok(stamp)) if synced_at != Some(stamp) => {
+                    // Only remember the stamp once every memory is embedded, so failures are retried
+                    let running = state.indexing.embeddings_run();
+                    let synced = sync(&state).await;
+                    drop(running);
+                    match synced {
+                        Ok(()) => synced_at = Some(stamp),
+                        Err(e) => warn!("[EMBEDDINGS] Sync failed, retrying later: {}", e),
+                    }
+                }
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => warn!("[EMBEDDINGS] Failed to check the store for changes: {}", e),
+                Err(e) => warn!("[EMBEDDINGS] Change check panicked: {}", e),
+            }
+
+            drop(state);
+            tokio::time::sleep(interval).await;
+        }
+
+        info!("[EMBEDDINGS] Server stopped, ending embedding sync");
+    });
+}
+
+// Embed every memory whose stored embedding is missing or out of date
+async fn sync(state: &Arc<ServerState>) -> Result<(), String> {
+    let model = state.embeddings.memory_model().to_string();
+    let store = state.memory_store.clone();
+    let stale_model = model.clone();
+    let stale = tokio::task::spawn_blocking(move || store.stale_embeddings(&stale_model))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+
+    if stale.is_empty() {
+        return Ok(());
+    }
+    info!("[EMBEDDINGS] Embedding {} memories with {}", stale.len(), model);
+
+    let (provider, target) = state.embeddings.select(&model);
+    for batch in stale.chunks(BATCH_SIZE) {
+        let texts: Vec<String> = batch.iter().map(|memory| memory.embedding_text()).collect();
+        let embeddings = provider.embed(&target, &texts).await.map_err(|e| e.to_string())?;
+
+        let store = state.memory_store.clone();
+        let entries: Vec<(String, StoredEmbedding)> = batch.iter()
+            .zip(embeddings.vectors)
+            .map(|(memory, vector)| {
+                (memory.id.clone(), StoredEmbedding {
+                    model: model.clone(),
+                    content_hash: memory.content_hash(),
+                    vector,
+                    updated_at: Utc::now(),
+                })
+            })
+            .collect();
+        tokio::task::spawn_blocking(move || {
+            entries.iter().try_for_each(|(id, embedding)| store.save_embedding(id, embedding))
+        })
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e: MemoryError| e.to_string())?;
+    }
+
+    info!("[EMBEDDINGS] Embedded {} memories", stale.len());
+    Ok(())
+}
@@ -0,0 +1,278 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{
+        rejection::{JsonRejection, PathRejection, QueryRejection},
+        Request,
+    },
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use tracing::error;
+use uuid::Uuid;
+
+use crate::memory::MemoryError;
+
+pub use conduit_types::openai::{OpenAiErrorBody, OpenAiErrorResponse, OpenAiErrorType};
+
+/// Header carrying the ID of a request, sent by the client or generated
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest plain-text error body that is wrapped; longer ones are cut off
+const MAX_WRAPPED_BODY: usize = 64 * 1024;
+
+/// An error response of any route
+///
+/// Serializes as `{"error": {"message", "type", "param", "code", "request_id"}}`
+/// with a matching HTTP status. `/v1` clients get the shape OpenAI client
+/// libraries use to pick the exception they raise, and `/api` clients the same.
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub body: OpenAiErrorBody,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, error_type: OpenAiErrorType, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: OpenAiErrorBody {
+                message: message.into(),
+                error_type,
+                param: None,
+                code: None,
+                request_id: None,
+            },
+        }
+    }
+
+    /// Any status, with the error type that goes with it
+    pub fn status(status: StatusCode, message: impl Into<String>) -> Self {
+        let error_type = match status {
+            StatusCode::UNAUTHORIZED => OpenAiErrorType::AuthenticationError,
+            StatusCode::FORBIDDEN | StatusCode::LOCKED => OpenAiErrorType::PermissionError,
+            StatusCode::TOO_MANY_REQUESTS => OpenAiErrorType::RateLimitError,
+            status if status.is_server_error() => OpenAiErrorType::ServerError,
+            _ => OpenAiErrorType::InvalidRequestError,
+        };
+        Self::new(status, error_type, message)
+    }
+
+    /// 400 for malformed or invalid requests
+    pub fn invalid_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, OpenAiErrorType::InvalidRequestError, message)
+    }
+
+    /// 404 for unknown resources
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, OpenAiErrorType::InvalidRequestError, message)
+            .code("not_found")
+    }
+
+    /// 409 for requests that clash with the current state
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::CONFLICT, OpenAiErrorType::InvalidRequestError, message)
+            .code("conflict")
+    }
+
+    /// 401 for requests without a valid API key
+    pub fn authentication(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, OpenAiErrorType::AuthenticationError, message)
+            .code("invalid_api_key")
+    }
+
+    /// 403 for resources that cannot be modified
+    pub fn permission(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::FORBIDDEN, OpenAiErrorType::PermissionError, message)
+    }
+
+    /// 429 when too many requests are in flight
+    pub fn rate_limit(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::TOO_MANY_REQUESTS, OpenAiErrorType::RateLimitError, message)
+            .code("concurrency_limit_exceeded")
+    }
+
+    /// 500 for failures inside the server
+    pub fn server(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, OpenAiErrorType::ServerError, message)
+    }
+
+    /// 502 for failures of the upstream provider
+    pub fn upstream(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_GATEWAY, OpenAiErrorType::ServerError, message)
+            .code("upstream_error")
+    }
+
+    /// 503 when the upstream provider is failing and requests are not forwarded
+    pub fn unavailable(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::SERVICE_UNAVAILABLE, OpenAiErrorType::ServerError, message)
+            .code("upstream_unavailable")
+    }
+
+    /// 503 when the memory store cannot be reached
+    pub fn store_unavailable(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::SERVICE_UNAVAILABLE, OpenAiErrorType::ServerError, message)
+            .code("store_unavailable")
+    }
+
+    /// Map a memory store error, prefixing the message with what was being done
+    pub fn memory(context: &str, err: &MemoryError) -> Self {
+        Self::memory_message(format!("{}: {}", context, err), err)
+    }
+
+    fn memory_message(message: String, err: &MemoryError) -> Self {
+        match err {
+            MemoryError::NotFound(_) => Self::not_found(message),
+            MemoryError::ReadOnly(_) | MemoryError::AccessDenied(_) => Self::permission(message),
+            MemoryError::StoreReadOnly(_) => Self::permission(message).code("store_read_only"),
+            MemoryError::InvalidId(_) | MemoryError::InvalidAttachmentName(_) => {
+                Self::invalid_request(message).code("invalid_id")
+            }
+            MemoryError::InvalidCollection(_)
+            | MemoryError::InvalidReference(_)
+            | MemoryError::InvalidGrant(_)
+            | MemoryError::InvalidExportPath(_)
+            | MemoryError::InvalidImportPath(_)
+            | MemoryError::InvalidTagAlias(_)
+            | MemoryError::InvalidImportance(_)
+            | MemoryError::InvalidQuery(_)
+            | MemoryError::GitDisabled => {
+                Self::invalid_request(message)
+            }
+            MemoryError::AlreadyExists(_) => Self::conflict(message).code("already_exists"),
+            MemoryError::AttachmentTooLarge(_) => {
+                Self::new(StatusCode::PAYLOAD_TOO_LARGE, OpenAiErrorType::InvalidRequestError, message)
+                    .code("attachment_too_large")
+            }
+            MemoryError::AttachmentRejected(_) => {
+                Self::new(StatusCode::UNSUPPORTED_MEDIA_TYPE, OpenAiErrorType::InvalidRequestError, message)
+                    .code("attachment_rejected")
+            }
+            MemoryError::StoreUnavailable(_) => Self::store_unavailable(message),
+            MemoryError::Locked => {
+                Self::new(StatusCode::LOCKED, OpenAiErrorType::PermissionError, message).code("workspace_locked")
+            }
+            MemoryError::WrongPassphrase => Self::permission(message).code("wrong_passphrase"),
+            MemoryError::InvalidPassphrase(_) => Self::invalid_request(message),
+            _ => Self::server(message),
+        }
+    }
+
+    pub fn param(mut self, param: impl Into<String>) -> Self {
+        self.body.param = Some(param.into());
+        self
+    }
+
+    pub fn code(mut self, code: impl Into<String>) -> Self {
+        self.body.code = Some(code.into());
+        self
+    }
+}
+
+/// Status a memory store error is reported with
+pub fn memory_error_status(err: &MemoryError) -> StatusCode {
+    ApiError::memory_message(String::new(), err).status
+}
+
+impl From<MemoryError> for ApiError {
+    fn from(err: MemoryError) -> Self {
+        Self::memory_message(err.to_string(), &err)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        // Kept on the response so the request ID can be filled in on the way out
+        let mut response = (self.status, Json(OpenAiErrorResponse { error: self.body.clone() })).into_response();
+        response.extensions_mut().insert(self.body);
+        response
+    }
+}
+
+// Extractor rejections keep their status but use the error shape of every route
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::new(rejection.status(), OpenAiErrorType::InvalidRequestError, rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        Self::new(rejection.status(), OpenAiErrorType::InvalidRequestError, rejection.body_text())
+    }
+}
+
+impl From<PathRejection> for ApiError {
+    fn from(rejection: PathRejection) -> Self {
+        Self::new(rejection.status(), OpenAiErrorType::InvalidRequestError, rejection.body_text())
+    }
+}
+
+// A client's request ID is kept when it is short and printable
+fn client_request_id(value: &HeaderValue) -> Option<String> {
+    let id = value.to_str().ok()?.trim();
+    (!id.is_empty() && id.len() <= 128 && id.chars().all(|c| c.is_ascii_graphic())).then(|| id.to_string())
+}
+
+/// Give every request an ID and every error response the same JSON shape
+///
+/// The ID comes from the client's `X-Request-Id` header or is generated, and
+/// is returned in the same header and in the `request_id` of errors, and
+/// logged with server errors. Errors that extractors and layers answer with
+/// in plain text, or without a body, are wrapped in the error object too.
+/// Event streams are left alone.
+pub async fn error_envelope(mut request: Request, next: Next) -> Response {
+    let request_id = request.headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(client_request_id)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        request.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+
+    let mut response = next.run(request).await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    let status = response.status();
+    if !status.is_client_error() && !status.is_server_error() {
+        return response;
+    }
+
+    let body = match response.extensions_mut().remove::<OpenAiErrorBody>() {
+        Some(body) => body,
+        None => {
+            let is_json_or_stream = response.headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| value.starts_with("application/json") || value.starts_with("text/event-stream"));
+            if is_json_or_stream {
+                return response;
+            }
+            let (parts, body) = response.into_parts();
+            let text = to_bytes(body, MAX_WRAPPED_BODY).await.unwrap_or_default();
+            let text = String::from_utf8_lossy(&text).trim().to_string();
+            let message = if text.is_empty() {
+                status.canonical_reason().unwrap_or("Request failed").to_string()
+            } else {
+                text
+            };
+            response = Response::from_parts(parts, Body::empty());
+            ApiError::status(status, message).body
+        }
+    };
+
+    if status.is_server_error() {
+        error!("[SERVER] {} {} failed with {} (request {}): {}", method, path, status, request_id, body.message);
+    }
+    let mut body = body;
+    body.request_id = Some(request_id);
+    let (mut parts, _) = response.into_parts();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    let (json_parts, json_body) = Json(OpenAiErrorResponse { error: body }).into_response().into_parts();
+    parts.headers.extend(json_parts.headers);
+    Response::from_parts(parts, json_body)
+}
@@ -0,0 +1,318 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{Local, Utc};
+use futures::stream;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncReadExt;
+use tracing::{error, info};
+use utoipa::ToSchema;
+
+use crate::memory::{ExportFormat, ExportOptions, ExportReport, Memory, MemoryError, MemoryStore};
+use super::error::{ApiError, OpenAiErrorResponse};
+use super::auth::Caller;
+use super::state::ServerState;
+use super::templates::{check_template, render};
+
+/// Size of the chunks a zip export is streamed in
+const CHUNK_SIZE: usize = 64 * 1024;
+
+fn default_true() -> bool {
+    true
+}
+
+/// A named set of export options, run from the API, the CLI or a scheduled task
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ExportProfile {
+    pub name: String,
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// Directory for markdown, otherwise the file to write; a template where
+    /// `{{date}}`, `{{time}}`, `{{weekday}}` and `{{date:<strftime>}}` are
+    /// replaced as in scheduled tasks
+    pub path: String,
+    /// Only memories with at least one of these tags; every memory when empty
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Leave out memories with any of these tags
+    #[serde(default)]
+    pub exclude_tags: Vec<String>,
+    /// Regular expressions whose matches are replaced with `[REDACTED]` in titles and content
+    #[serde(default)]
+    pub redact: Vec<String>,
+    /// Whether attachments are copied; they are never redacted
+    #[serde(default = "default_true")]
+    pub attachments: bool,
+}
+
+/// Export profiles, set as `[[export.profiles]]` in the config file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportConfig {
+    pub profiles: Vec<ExportProfile>,
+}
+
+/// The configured export profiles, checked and ready to run
+#[derive(Debug, Clone, Default)]
+pub struct ExportProfiles {
+    config: ExportConfig,
+    /// Options of each profile, in the order of `config.profiles`
+    options: Vec<ExportOptions>,
+}
+
+impl ExportProfiles {
+    /// Check the names, path templates and redaction patterns of the profiles
+    pub fn new(config: ExportConfig) -> Result<Self, String> {
+        let mut options = Vec::new();
+        for (index, profile) in config.profiles.iter().enumerate() {
+            if profile.name.is_empty() || config.profiles[..index].iter().any(|other| other.name == profile.name) {
+                return Err(format!("Export profile names must be unique and not empty: {:?}", profile.name));
+            }
+            if profile.path.trim().is_empty() {
+                return Err(format!("Export profile {}: path is empty", profile.name));
+            }
+            check_template(&profile.path).map_err(|e| format!("Export profile {}: {}", profile.name, e))?;
+            let redactions = profile.redact.iter()
+                .map(|pattern| Regex::new(pattern).map_err(|e| format!("Export profile {}: invalid redaction pattern {:?}: {}", profile.name, pattern, e)))
+                .collect::<Result<_, _>>()?;
+            options.push(ExportOptions {
+                tags: profile.tags.clone(),
+                exclude_tags: profile.exclude_tags.clone(),
+                redactions,
+                attachments: profile.attachments,
+            });
+        }
+        Ok(Self { config, options })
+    }
+
+    pub fn list(&self) -> &[ExportProfile] {
+        &self.config.profiles
+    }
+
+    /// The profile named `name` with its options, to run on a blocking thread
+    pub fn get(&self, name: &str) -> Option<(ExportProfile, ExportOptions)> {
+        let index = self.config.profiles.iter().position(|profile| profile.name == name)?;
+        Some((self.config.profiles[index].clone(), self.options[index].clone()))
+    }
+}
+
+/// Run an export profile now, writing to its path rendered for the local time
+///
+/// Blocks while the export is written.
+pub fn run_profile(store: &MemoryStore, profile: &ExportProfile, options: &ExportOptions) -> Result<ExportReport, MemoryError> {
+    let path = PathBuf::from(render(&profile.path, Local::now()));
+    let report = store.export_as(profile.format, &path, options)?;
+    info!("[EXPORT] Profile {} exported {} memories to {}", profile.name, report.memories, report.path.display());
+    Ok(report)
+}
+
+/// Routes for downloading backups of the store and running export profiles
+pub fn router() -> Router<Arc<ServerState>> {
+    Router::new()
+        .route("/api/export", get(export_zip_handler))
+        .route("/api/export/profiles", get(list_profiles_handler))
+        .route("/api/export/profiles/:name/run", post(run_profile_handler))
+        .route("/api/memories/export.json", get(export_json_handler))
+}
+
+// A zip built for a download, removed once it has been sent or the client goes away
+struct TempZip(PathBuf);
+
+impl Drop for TempZip {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.0) {
+            error!("[SERVER] Failed to remove export {:?}: {}", self.0, e);
+        }
+    }
+}
+
+async fn export_zip(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling export_zip request");
+
+    // Built in the temp directory first, since a zip cannot be written front to back
+    let zip = TempZip(std::env::temp_dir().join(format!("conduit-export-{}.zip", ulid::Ulid::new())));
+    let store = state.memory_store.clone();
+    let path = zip.0.clone();
+    let principal = caller.principal().cloned();
+    let exported = tokio::task::spawn_blocking(move || store.export_zip(&path, principal.as_ref()))
+        .await
+        .unwrap_or_else(|e| Err(MemoryError::Io(std::io::Error::other(format!("Export panicked: {}", e)))));
+    let report = match exported {
+        Ok(report) => report,
+        Err(err) => {
+            error!("Error exporting memories: {:?}", err);
+            return ApiError::from(err).into_response();
+        }
+    };
+
+    let file = match tokio::fs::File::open(&zip.0).await {
+        Ok(file) => file,
+        Err(err) => {
+            error!("Error opening export {:?}: {:?}", zip.0, err);
+            return ApiError::server(err.to_string()).into_response();
+        }
+    };
+    let length = file.metadata().await.map(|metadata| metadata.len()).unwrap_or_default();
+    info!("[SERVER] Streaming export of {} memories ({} bytes)", report.memories, length);
+
+    // The file is dropped before the zip is removed, which Windows requires
+    let chunks = stream::unfold((file, zip), |(mut file, zip)| async move {
+        let mut chunk = vec![0; CHUNK_SIZE];
+        match file.read(&mut chunk).await {
+            Ok(0) => None,
+            Ok(read) => {
+                chunk.truncate(read);
+                Some((Ok(chunk), (file, zip)))
+            }
+            Err(err) => Some((Err(err), (file, zip))),
+        }
+    });
+
+    let name = format!("conduit-export-{}.zip", Utc::now().format("%Y-%m-%d"));
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (header::CONTENT_LENGTH, length.to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", name)),
+        ],
+        Body::from_stream(chunks),
+    ).into_response()
+}
+
+async fn export_json(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling export_json request");
+    let store = state.memory_store.clone();
+    let principal = caller.principal().cloned();
+    match tokio::task::spawn_blocking(move || store.export_memories(principal.as_ref())).await {
+        Ok(Ok(memories)) => {
+            info!("[SERVER] Exporting {} memories as JSON", memories.len());
+            let name = format!("conduit-export-{}.json", Utc::now().format("%Y-%m-%d"));
+            (
+                StatusCode::OK,
+                [(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", name))],
+                Json(memories),
+            ).into_response()
+        }
+        Ok(Err(err)) => {
+            error!("Error exporting memories: {:?}", err);
+            ApiError::from(err).into_response()
+        }
+        Err(err) => {
+            error!("Export task failed: {:?}", err);
+            ApiError::server(err.to_string()).into_response()
+        }
+    }
+}
+
+async fn list_profiles(
+    State(state): State<Arc<ServerState>>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling list_export_profiles request");
+    Json(state.export_profiles.list().to_vec())
+}
+
+async fn run_export_profile(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling run_export_profile request for profile: {}", name);
+    // Profiles write every memory to a folder on the server
+    if caller.principal().is_some() {
+        return ApiError::permission("Running an export profile requires an API key with access to every memory".to_string()).into_response();
+    }
+    let Some((profile, options)) = state.export_profiles.get(&name) else {
+        return ApiError::not_found(format!("Export profile not found: {}", name)).into_response();
+    };
+
+    let store = state.memory_store.clone();
+    match tokio::task::spawn_blocking(move || run_profile(&store, &profile, &options)).await {
+        Ok(Ok(report)) => Json(report).into_response(),
+        Ok(Err(err)) => {
+            error!("Error running export profile {}: {:?}", name, err);
+            ApiError::from(err).into_response()
+        }
+        Err(err) => {
+            error!("Export task failed: {:?}", err);
+            ApiError::server(err.to_string()).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    get, path = "/api/export", tag = "maintenance", operation_id = "export_zip",
+    responses(
+        (status = 200, description = "Zip of the memories the caller can read, their attachments \
+            and an `index.json` manifest", content_type = "application/zip", body = Vec<u8>),
+        (status = 503, description = "The store is unavailable", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn export_zip_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+) -> impl IntoResponse {
+    export_zip(state, caller).await
+}
+
+#[utoipa::path(
+    get, path = "/api/memories/export.json", tag = "maintenance", operation_id = "export_json",
+    responses(
+        (status = 200, description = "The memories the caller can read, oldest first, \
+            in the form `POST /api/memories/import.json` accepts", body = [Memory]),
+        (status = 503, description = "The store is unavailable", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn export_json_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+) -> impl IntoResponse {
+    export_json(state, caller).await
+}
+
+#[utoipa::path(
+    get, path = "/api/export/profiles", tag = "maintenance", operation_id = "list_export_profiles",
+    responses((status = 200, description = "Export profiles from the config file", body = [ExportProfile]))
+)]
+#[axum::debug_handler]
+async fn list_profiles_handler(
+    state: State<Arc<ServerState>>,
+) -> impl IntoResponse {
+    list_profiles(state).await
+}
+
+#[utoipa::path(
+    post, path = "/api/export/profiles/{name}/run", tag = "maintenance", operation_id = "run_export_profile",
+    params(("name" = String, Path, description = "Profile name from the config file")),
+    responses(
+        (status = 200, description = "Where the export was written", body = ExportReport),
+        (status = 400, description = "The profile's path is inside the store or not usable", body = OpenAiErrorResponse),
+        (status = 403, description = "The API key only has access to some memories", body = OpenAiErrorResponse),
+        (status = 404, description = "No profile with this name", body = OpenAiErrorResponse),
+        (status = 503, description = "The store is unavailable", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn run_profile_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+) -> impl IntoResponse {
+    run_export_profile(state, caller, path).await
+}
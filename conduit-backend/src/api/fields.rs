@@ -0,0 +1,106 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Query, Request},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use tracing::error;
+use utoipa::IntoParams;
+
+use crate::memory::summary_from_content;
+use super::error::ApiError;
+
+/// Fields a memory can be shaped to, in the order they are listed in errors
+const MEMORY_FIELDS: &[&str] = &[
+    "id", "title", "content", "tags", "created_at", "updated_at", "last_viewed_at", "remind_at",
+    "collection", "read_only", "attachments", "references", "owner", "shared", SUMMARY,
+];
+
+/// Field holding the stored summary, or else the start of the content as plain text
+const SUMMARY: &str = "summary";
+
+/// Field of search results listing where the query matched
+const MATCHES: &str = "matches";
+
+/// Largest memory response that is shaped, in bytes
+const MAX_SHAPED_BODY: usize = 256 * 1024 * 1024;
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(default)]
+pub struct FieldsQuery {
+    /// Comma-separated memory fields to return, such as `id,title,tags,summary`;
+    /// `summary` is the summary stored by `/summarize`, or else the start of
+    /// the content as plain text. All fields are returned when left out
+    pub fields: Option<String>,
+}
+
+// The requested fields, or an error naming the first unknown one
+fn parse_fields(fields: &str) -> Result<Vec<&str>, String> {
+    let fields: Vec<&str> = fields.split(',').map(str::trim).filter(|field| !field.is_empty()).collect();
+    match fields.iter().find(|field| !MEMORY_FIELDS.contains(field)) {
+        Some(unknown) => Err(format!("Unknown field `{}`; expected some of {}", unknown, MEMORY_FIELDS.join(", "))),
+        None => Ok(fields),
+    }
+}
+
+// Keep only the requested fields of a memory object, computing a missing summary if asked for
+fn shape_memory(memory: Value, fields: &[&str]) -> Value {
+    let Value::Object(mut memory) = memory else {
+        return memory;
+    };
+    if fields.contains(&SUMMARY) && memory.get(SUMMARY).is_none_or(Value::is_null) {
+        let summary = memory.get("content").and_then(Value::as_str).map(summary_from_content).unwrap_or_default();
+        memory.insert(SUMMARY.to_string(), Value::String(summary));
+    }
+    let mut shaped: Map<String, Value> = fields.iter()
+        .filter_map(|field| memory.remove_entry(*field))
+        .collect();
+    // Search results keep where they matched whatever fields are asked for
+    if let Some(matches) = memory.remove(MATCHES) {
+        shaped.insert(MATCHES.to_string(), matches);
+    }
+    Value::Object(shaped)
+}
+
+/// Shape memory responses to the fields named in the `fields` query parameter
+///
+/// Applied to routes returning a memory or a list of memories. Responses
+/// other than a successful JSON body are passed through unchanged, and
+/// unknown fields are rejected with `400 Bad Request`.
+pub async fn shape_fields(Query(query): Query<FieldsQuery>, request: Request, next: Next) -> Response {
+    let fields = match query.fields.as_deref().map(parse_fields) {
+        None => return next.run(request).await,
+        Some(Ok(fields)) if fields.is_empty() => return next.run(request).await,
+        Some(Ok(fields)) => fields,
+        Some(Err(err)) => return ApiError::invalid_request(err).into_response(),
+    };
+
+    let response = next.run(request).await;
+    let is_json = response.headers().get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    if response.status() != StatusCode::OK || !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, MAX_SHAPED_BODY).await {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            error!("[SERVER] Failed to read response for shaping: {:?}", err);
+            return ApiError::server(err.to_string()).into_response();
+        }
+    };
+    let shaped = match serde_json::from_slice::<Value>(&bytes) {
+        Ok(Value::Array(memories)) => Value::Array(memories.into_iter().map(|memory| shape_memory(memory, &fields)).collect()),
+        Ok(memory) => shape_memory(memory, &fields),
+        Err(_) => return Response::from_parts(parts, Body::from(bytes)),
+    };
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(shaped.to_string()))
+}
@@ -0,0 +1,209 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use axum::{
+    body::Bytes,
+    extract::{DefaultBodyLimit, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use serde::Deserialize;
+use tracing::{error, info};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::import::{self as importers, json::JsonMemory, ImportFormat};
+use crate::memory::{ImportAction, ImportJob, ImportReport, ImportStrategy, MemoryError};
+use super::error::{ApiError, OpenAiErrorResponse};
+use super::auth::Caller;
+use super::state::ServerState;
+
+/// Largest zip accepted by an import
+const IMPORT_BODY_LIMIT: usize = 512 * 1024 * 1024;
+
+/// Routes for importing markdown, Notion and Evernote exports and JSON memories
+pub fn router() -> Router<Arc<ServerState>> {
+    Router::new()
+        .route(
+            "/api/import",
+            post(import_handler).layer(DefaultBodyLimit::max(IMPORT_BODY_LIMIT)),
+        )
+        .route(
+            "/api/memories/import.json",
+            post(import_json_handler).layer(DefaultBodyLimit::max(IMPORT_BODY_LIMIT)),
+        )
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(default)]
+pub struct ImportQuery {
+    /// Kind of export: `markdown`, `notion` or `enex`
+    pub format: ImportFormat,
+    /// Collection for the notes of a single uploaded `.enex` file
+    pub notebook: Option<String>,
+    /// What to do with files matching an existing memory by ID, title or content
+    pub strategy: ImportStrategy,
+    /// Only report what would be imported
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(default)]
+pub struct ImportJsonQuery {
+    /// What to do with memories matching an existing memory by ID, title or content
+    pub strategy: ImportStrategy,
+    /// Only report what would be imported
+    pub dry_run: bool,
+}
+
+/// A folder, or an export file, on the server to import
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ImportPathRequest {
+    #[schema(value_type = String)]
+    pub path: PathBuf,
+}
+
+// The report of a finished import, or the reason it failed, recorded in the activity feed
+fn import_response(state: &ServerState, source: &str, imported: Result<Result<ImportReport, MemoryError>, tokio::task::JoinError>) -> Response {
+    if let Ok(result) = &imported {
+        state.activity.record_import(source, result);
+    }
+    match imported {
+        Ok(Ok(report)) => {
+            info!(
+                "[SERVER] Imported {} items, {} failed",
+                report.items.len(),
+                report.count(ImportAction::Failed),
+            );
+            (StatusCode::OK, Json(report)).into_response()
+        }
+        // An upload that is not a valid export is the client's mistake, not a broken store
+        Ok(Err(err @ MemoryError::InvalidFormat(_))) => ApiError::invalid_request(err.to_string()).into_response(),
+        Ok(Err(err)) => {
+            error!("Error importing memories: {:?}", err);
+            ApiError::from(err).into_response()
+        }
+        Err(err) => {
+            error!("Import task failed: {:?}", err);
+            ApiError::server(err.to_string()).into_response()
+        }
+    }
+}
+
+async fn import(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Query(query): Query<ImportQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    info!(
+        "[SERVER] Handling import request (format: {:?}, strategy: {:?}, dry_run: {})",
+        query.format, query.strategy, query.dry_run,
+    );
+
+    // Imports read server folders and match against every memory
+    if caller.principal().is_some() {
+        return ApiError::permission("Importing requires an API key with access to every memory".to_string()).into_response();
+    }
+
+    let is_json = headers.get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    let job = ImportJob { strategy: query.strategy, dry_run: query.dry_run };
+    let store = state.memory_store.clone();
+
+    let (source, imported) = if is_json {
+        let request: ImportPathRequest = match serde_json::from_slice(&body) {
+            Ok(request) => request,
+            Err(err) => return ApiError::invalid_request(format!("Invalid import request: {}", err)).into_response(),
+        };
+        let source = request.path.display().to_string();
+        (source, tokio::task::spawn_blocking(move || importers::import_path(&store, query.format, &request.path, &job)).await)
+    } else if body.is_empty() {
+        return ApiError::invalid_request("Send an export, or JSON with the path of a folder".to_string()).into_response();
+    } else {
+        ("an upload".to_string(), tokio::task::spawn_blocking(move || {
+            importers::import_bytes(&store, query.format, &body, query.notebook.as_deref(), &job)
+        }).await)
+    };
+
+    import_response(&state, &source, imported)
+}
+
+async fn import_json(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Query(query): Query<ImportJsonQuery>,
+    body: Bytes,
+) -> impl IntoResponse {
+    info!(
+        "[SERVER] Handling import_json request (strategy: {:?}, dry_run: {})",
+        query.strategy, query.dry_run,
+    );
+    if caller.principal().is_some() {
+        return ApiError::permission("Importing requires an API key with access to every memory".to_string()).into_response();
+    }
+
+    let memories = match importers::json::parse(&body) {
+        Ok(memories) => memories,
+        Err(err) => return ApiError::invalid_request(err.to_string()).into_response(),
+    };
+    let job = ImportJob { strategy: query.strategy, dry_run: query.dry_run };
+    let store = state.memory_store.clone();
+    import_response(&state, "JSON", tokio::task::spawn_blocking(move || importers::json::import_json(&store, memories, &job)).await)
+}
+
+#[utoipa::path(
+    post, path = "/api/import", tag = "maintenance", operation_id = "import",
+    params(ImportQuery),
+    request_body(
+        description = "A zip of markdown files, as written by `GET /api/export`, a Notion export zip, an `.enex` file or a zip of them, \
+            or the path of a folder or export file on the server",
+        content(
+            (Vec<u8> = "application/zip"),
+            (Vec<u8> = "application/xml"),
+            (ImportPathRequest = "application/json"),
+        ),
+    ),
+    responses(
+        (status = 200, description = "What was done with each file; failed files do not stop the import", body = ImportReport),
+        (status = 400, description = "Not a valid export, or the folder is missing or overlaps the store", body = OpenAiErrorResponse),
+        (status = 403, description = "The caller cannot access every memory, or the store is read-only", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn import_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    query: Query<ImportQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    import(state, caller, query, headers, body).await
+}
+
+#[utoipa::path(
+    post, path = "/api/memories/import.json", tag = "maintenance", operation_id = "import_json",
+    params(ImportJsonQuery),
+    request_body(
+        description = "Memories as returned by `GET /api/memories/export.json`; only a title or content is required",
+        content = [JsonMemory],
+    ),
+    responses(
+        (status = 200, description = "What was done with each memory, reported as `memories[<index>]`", body = ImportReport),
+        (status = 400, description = "The body is not an array of memory objects", body = OpenAiErrorResponse),
+        (status = 403, description = "The caller cannot access every memory, or the store is read-only", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn import_json_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    query: Query<ImportJsonQuery>,
+    body: Bytes,
+) -> impl IntoResponse {
+    import_json(state, caller, query, body).await
+}
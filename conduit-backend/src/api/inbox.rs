@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::post,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tracing::{error, info, warn};
+use utoipa::IntoParams;
+
+use crate::memory::Memory;
+use super::error::{ApiError, OpenAiErrorResponse};
+use super::state::ServerState;
+use super::titles::resolve_title;
+
+/// Header carrying the hook secret, for senders that cannot set `Authorization`
+const SECRET_HEADER: &str = "x-conduit-hook-secret";
+
+/// An inbound webhook that turns JSON payloads into memories
+///
+/// The title, content and tags are templates: `{{ path }}` is replaced with
+/// the payload value at `path`, a dot-separated list of object keys and array
+/// indexes such as `{{ issue.labels.0.name }}`. `{{ path | text }}` falls back
+/// to `text` when the value is missing or null, and `{{ . }}` is the whole
+/// payload. Strings are inserted as they are; other values as JSON.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InboundHook {
+    /// Receives payloads at `POST /api/hooks/<name>`
+    pub name: String,
+    /// Senders must pass this as `Authorization: Bearer <secret>`, the
+    /// `X-Conduit-Hook-Secret` header or a `secret` query parameter
+    pub secret: Option<String>,
+    /// Generated from the content when it renders blank
+    pub title: String,
+    pub content: String,
+    /// A tag that is only a placeholder for an array adds one tag per element;
+    /// tags rendering blank are left out
+    pub tags: Vec<String>,
+    /// User who owns the memories, on servers with users
+    pub owner: Option<String>,
+}
+
+/// Settings for inbound webhooks
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InboxConfig {
+    pub hooks: Vec<InboundHook>,
+}
+
+// A parsed template: literal text and placeholders, in order
+#[derive(Debug, Clone)]
+enum Part {
+    Text(String),
+    Value { path: Vec<String>, fallback: Option<String> },
+}
+
+#[derive(Debug, Clone)]
+struct Template(Vec<Part>);
+
+impl Template {
+    fn parse(template: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                parts.push(Part::Text(rest[..start].to_string()));
+            }
+            let end = rest[start..].find("}}")
+                .ok_or_else(|| format!("unclosed `{{{{` in template {:?}", template))?;
+            let placeholder = &rest[start + 2..start + end];
+            let (path, fallback) = match placeholder.split_once('|') {
+                Some((path, fallback)) => (path.trim(), Some(fallback.trim().to_string())),
+                None => (placeholder.trim(), None),
+            };
+            if path.is_empty() {
+                return Err(format!("empty placeholder in template {:?}", template));
+            }
+            let path = match path {
+                "." => Vec::new(),
+                path => path.split('.').map(|key| key.trim().to_string()).collect(),
+            };
+            parts.push(Part::Value { path, fallback });
+            rest = &rest[start + end + 2..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest.to_string()));
+        }
+        Ok(Self(parts))
+    }
+
+    fn render(&self, payload: &Value) -> String {
+        self.0.iter()
+            .map(|part| match part {
+                Part::Text(text) => text.clone(),
+                Part::Value { path, fallback } => match lookup(payload, path) {
+                    Some(value) => value_text(value),
+                    None => fallback.clone().unwrap_or_default(),
+                },
+            })
+            .collect()
+    }
+
+    // The array a template made of a single placeholder points at, if any
+    fn as_array<'a>(&self, payload: &'a Value) -> Option<&'a Vec<Value>> {
+        match self.0.as_slice() {
+            [Part::Value { path, .. }] => lookup(payload, path)?.as_array(),
+            _ => None,
+        }
+    }
+}
+
+fn lookup<'a>(payload: &'a Value, path: &[String]) -> Option<&'a Value> {
+    let value = path.iter().try_fold(payload, |value, key| match value {
+        Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+        value => value.get(key),
+    })?;
+    (!value.is_null()).then_some(value)
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+// A hook with its templates parsed
+struct CompiledHook {
+    hook: InboundHook,
+    title: Template,
+    content: Template,
+    tags: Vec<Template>,
+}
+
+impl CompiledHook {
+    fn compile(hook: InboundHook) -> Result<Self, String> {
+        let parse = |template: &str| Template::parse(template).map_err(|e| format!("Inbound hook {}: {}", hook.name, e));
+        Ok(Self {
+            title: parse(&hook.title)?,
+            content: parse(&hook.content)?,
+            tags: hook.tags.iter().map(|tag| parse(tag)).collect::<Result<_, _>>()?,
+            hook,
+        })
+    }
+
+    fn tags(&self, payload: &Value) -> Vec<String> {
+        let mut tags: Vec<String> = Vec::new();
+        for template in &self.tags {
+            let rendered = match template.as_array(payload) {
+                Some(items) => items.iter().filter(|item| !item.is_null()).map(value_text).collect(),
+                None => vec![template.render(payload)],
+            };
+            for tag in rendered {
+                let tag = tag.trim().to_string();
+                if !tag.is_empty() && !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+        }
+        tags
+    }
+
+    fn accepts(&self, secret: Option<&str>) -> bool {
+        match (&self.hook.secret, secret) {
+            (None, _) => true,
+            // Compare digests so the time taken does not reveal the secret
+            (Some(expected), Some(secret)) => Sha256::digest(expected.as_bytes()) == Sha256::digest(secret.as_bytes()),
+            (Some(_), None) => false,
+        }
+    }
+}
+
+/// The configured inbound webhooks
+pub struct Inbox {
+    hooks: HashMap<String, CompiledHook>,
+}
+
+impl Inbox {
+    /// Check the hooks and parse their templates
+    ///
+    /// Hooks without a secret are refused when `require_secret` is set, since
+    /// the hook routes do not take API keys.
+    pub fn new(config: InboxConfig, require_secret: bool) -> Result<Self, String> {
+        let mut hooks = HashMap::new();
+        for hook in config.hooks {
+            let name = hook.name.clone();
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_')) {
+                return Err(format!("Invalid inbound hook name: {:?}", name));
+            }
+            if require_secret && hook.secret.as_deref().is_none_or(str::is_empty) {
+                return Err(format!("Inbound hook {} needs a secret when API keys are configured", name));
+            }
+            if hooks.insert(name.clone(), CompiledHook::compile(hook)?).is_some() {
+                return Err(format!("Inbound hook {} is configured twice", name));
+            }
+        }
+        Ok(Self { hooks })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+}
+
+/// Routes for the inbound webhooks, which check hook secrets instead of API keys
+pub fn router() -> Router<Arc<ServerState>> {
+    Router::new().route("/api/hooks/:name", post(receive_hook_handler))
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HookQuery {
+    /// The hook secret, for senders that cannot set headers
+    pub secret: Option<String>,
+}
+
+fn request_secret<'a>(headers: &'a HeaderMap, query: &'a HookQuery) -> Option<&'a str> {
+    let bearer = headers.get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let header = headers.get(SECRET_HEADER).and_then(|value| value.to_str().ok());
+    bearer.or(header).or(query.secret.as_deref()).map(str::trim)
+}
+
+async fn receive_hook(
+    State(state): State<Arc<ServerState>>,
+    Path(name): Path<String>,
+    Query(query): Query<HookQuery>,
+    headers: HeaderMap,
+    Json(payload): Json<Value>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling receive_hook request for hook: {}", name);
+
+    let Some(hook) = state.inbox.hooks.get(&name) else {
+        return ApiError::not_found(format!("Inbound hook not found: {}", name)).into_response();
+    };
+    if !hook.accepts(request_secret(&headers, &query)) {
+        warn!("[INBOX] Rejected payload for hook {} with a missing or wrong secret", name);
+        return ApiError::status(StatusCode::UNAUTHORIZED, "Missing or invalid hook secret".to_string()).into_response();
+    }
+
+    if let Err(err) = state.memory_store.check_available() {
+        error!("[SERVER] {}", err);
+        return ApiError::from(err).into_response();
+    }
+
+    let content = hook.content.render(&payload);
+    let title = match resolve_title(&state, hook.title.render(&payload), &content).await {
+        Ok(title) => title,
+        Err(err) => return ApiError::invalid_request(err).into_response(),
+    };
+
+    let mut memory = match state.memory_store.new_memory(title, content, hook.tags(&payload)) {
+        Ok(memory) => memory,
+        Err(err) => return ApiError::from(err).into_response(),
+    };
+    memory.owner = hook.hook.owner.clone();
+
+    match state.memory_store.save(&memory) {
+        Ok(_) => {
+            info!("[INBOX] Hook {} created memory {}", name, memory.id);
+            (StatusCode::CREATED, Json(memory)).into_response()
+        }
+        Err(err) => {
+            error!("[INBOX] Hook {} failed to save memory: {}", name, err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    post, path = "/api/hooks/{name}", tag = "inbox", operation_id = "receive_hook",
+    params(("name" = String, Path, description = "Hook name from the config file"), HookQuery),
+    request_body(content = Object, description = "Any JSON payload, mapped to a memory by the hook's templates"),
+    responses(
+        (status = 201, description = "Memory created from the payload", body = Memory),
+        (status = 401, description = "Missing or invalid hook secret", body = OpenAiErrorResponse),
+        (status = 403, description = "The store is read-only", body = OpenAiErrorResponse),
+        (status = 404, description = "No hook with this name", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn receive_hook_handler(
+    state: State<Arc<ServerState>>,
+    path: Path<String>,
+    query: Query<HookQuery>,
+    headers: HeaderMap,
+    json: Json<Value>,
+) -> impl IntoResponse {
+    receive_hook(state, path, query, headers, json).await
+}
@@ -0,0 +1,76 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::IntoResponse,
+    routing::get,
+    Json, Router,
+};
+use tracing::{error, info};
+
+use crate::memory::IndexStatus;
+use super::error::{ApiError, OpenAiErrorResponse};
+use super::state::ServerState;
+
+/// Which background jobs are updating the store's indexes right now
+#[derive(Debug, Default)]
+pub struct IndexJobs {
+    embeddings: AtomicBool,
+    attachment_text: AtomicBool,
+}
+
+/// Marks a job as running until dropped
+pub struct JobRun<'a>(&'a AtomicBool);
+
+impl Drop for JobRun<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+impl IndexJobs {
+    /// Mark the embedding sync as running
+    pub fn embeddings_run(&self) -> JobRun<'_> {
+        self.embeddings.store(true, Ordering::Relaxed);
+        JobRun(&self.embeddings)
+    }
+
+    /// Mark attachment text extraction as running
+    pub fn attachment_text_run(&self) -> JobRun<'_> {
+        self.attachment_text.store(true, Ordering::Relaxed);
+        JobRun(&self.attachment_text)
+    }
+}
+
+/// Routes reporting how far the indexes have caught up with the store
+pub fn router() -> Router<Arc<ServerState>> {
+    Router::new().route("/api/index/status", get(index_status_handler))
+}
+
+async fn index_status(State(state): State<Arc<ServerState>>) -> impl IntoResponse {
+    info!("[SERVER] Handling index_status request");
+    match state.memory_store.index_status(state.embeddings.memory_model()) {
+        Ok(mut status) => {
+            status.full_text.rebuilding = state.indexing.attachment_text.load(Ordering::Relaxed);
+            status.vector.rebuilding = state.indexing.embeddings.load(Ordering::Relaxed);
+            (StatusCode::OK, Json(status)).into_response()
+        }
+        Err(err) => {
+            error!("Error reading index status: {:?}", err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    get, path = "/api/index/status", tag = "maintenance", operation_id = "index_status",
+    responses(
+        (status = 200, description = "Documents, pending changes and last update of the metadata, full-text and vector indexes", body = IndexStatus),
+        (status = 503, description = "The store is unavailable", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn index_status_handler(state: State<Arc<ServerState>>) -> impl IntoResponse {
+    index_status(state).await
+}
@@ -0,0 +1,128 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use super::error::ApiError;
+
+/// What a server does when another Conduit process already writes to its store
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Fail to start, naming the process that serves the store
+    #[default]
+    Refuse,
+    /// Forward every request to the running instance
+    Proxy,
+    /// Serve the store without changing it
+    ReadOnly,
+}
+
+impl std::str::FromStr for ConflictPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "refuse" => Ok(Self::Refuse),
+            "proxy" => Ok(Self::Proxy),
+            "read_only" | "read-only" | "readonly" => Ok(Self::ReadOnly),
+            other => Err(format!("Unknown conflict policy: {}", other)),
+        }
+    }
+}
+
+/// How a server shares its store with other Conduit processes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InstanceConfig {
+    /// What to do when another process already writes to the store
+    pub on_conflict: ConflictPolicy,
+}
+
+impl InstanceConfig {
+    /// Override the policy with `CONDUIT_ON_CONFLICT` (`refuse`, `proxy` or `read_only`), if set
+    pub fn apply_env(&mut self) {
+        if let Some(policy) = std::env::var("CONDUIT_ON_CONFLICT").ok().and_then(|policy| policy.parse().ok()) {
+            self.on_conflict = policy;
+        }
+    }
+}
+
+// Headers that apply to a single connection and are not forwarded
+const HOP_BY_HOP: &[header::HeaderName] = &[
+    header::CONNECTION,
+    header::HOST,
+    header::PROXY_AUTHENTICATE,
+    header::PROXY_AUTHORIZATION,
+    header::TE,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+];
+
+fn forwarded_headers(headers: &HeaderMap) -> HeaderMap {
+    let mut headers = headers.clone();
+    for name in HOP_BY_HOP {
+        headers.remove(name);
+    }
+    headers
+}
+
+struct ProxyTarget {
+    addr: SocketAddr,
+    client: reqwest::Client,
+}
+
+/// Router forwarding every request to the instance listening at `addr`
+///
+/// Request and response bodies are streamed, so server-sent events and chat
+/// streams pass through unchanged. Authentication and CORS are left to the
+/// running instance.
+pub fn proxy_router(addr: SocketAddr) -> Result<Router, String> {
+    // No timeout, since event streams stay open; the request timeout of the target applies
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|e| format!("Failed to create proxy client: {}", e))?;
+
+    Ok(Router::new()
+        .fallback(forward)
+        .with_state(Arc::new(ProxyTarget { addr, client })))
+}
+
+async fn forward(
+    State(target): State<Arc<ProxyTarget>>,
+    request: Request,
+) -> Response {
+    let path = request.uri().path_and_query().map(|path| path.as_str()).unwrap_or("/");
+    let url = format!("http://{}{}", target.addr, path);
+    let (parts, body) = request.into_parts();
+
+    let upstream = target.client.request(parts.method, &url)
+        .headers(forwarded_headers(&parts.headers))
+        .body(reqwest::Body::wrap_stream(body.into_data_stream()))
+        .send()
+        .await;
+
+    match upstream {
+        Ok(upstream) => {
+            let status = upstream.status();
+            let headers = forwarded_headers(upstream.headers());
+            let mut response = Response::new(Body::from_stream(upstream.bytes_stream()));
+            *response.status_mut() = status;
+            *response.headers_mut() = headers;
+            response
+        }
+        Err(e) => {
+            error!("[PROXY] Failed to forward request to {}: {}", url, e);
+            ApiError::status(StatusCode::BAD_GATEWAY, format!("Failed to reach the Conduit instance at {}: {}", target.addr, e)).into_response()
+        }
+    }
+}
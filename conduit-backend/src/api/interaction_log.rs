@@ -0,0 +1,266 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use chrono::{DateTime, Duration, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::rag::RetrievedMemory;
+use crate::util::write_atomic;
+use super::openai::{ChatMessage, ContentPart, MessageContent};
+
+/// Replacement for text matched by a redaction rule
+const REDACTED: &str = "[REDACTED]";
+
+/// Redaction rules that are always applied: API keys, bearer tokens and email addresses
+const DEFAULT_REDACTIONS: &[&str] = &[
+    r"sk-[A-Za-z0-9_-]{16,}",
+    r"(?i)bearer\s+[A-Za-z0-9._~+/=-]+",
+    r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}",
+];
+
+fn default_retention_days() -> u32 {
+    30
+}
+
+/// Settings for the opt-in log of chat and embedding requests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InteractionLogConfig {
+    /// JSONL file the entries are appended to
+    pub path: PathBuf,
+    /// Entries older than this many days are removed; `0` keeps them forever
+    #[serde(default = "default_retention_days")]
+    pub retention_days: u32,
+    /// Extra regular expressions whose matches are replaced before writing
+    #[serde(default)]
+    pub redact: Vec<String>,
+}
+
+impl InteractionLogConfig {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            retention_days: default_retention_days(),
+            redact: Vec::new(),
+        }
+    }
+
+    /// Read the log settings from the environment
+    ///
+    /// Returns `None` unless `CONDUIT_INTERACTION_LOG` is set, either to a file path
+    /// or to `true` for `~/.conduit/interactions.jsonl`.
+    /// `CONDUIT_INTERACTION_LOG_RETENTION_DAYS` sets the retention (default 30) and
+    /// `CONDUIT_INTERACTION_LOG_REDACT` adds comma-separated redaction patterns.
+    pub fn from_env() -> Option<Self> {
+        let path = match std::env::var("CONDUIT_INTERACTION_LOG").ok()?.as_str() {
+            "" | "false" | "0" => return None,
+            "true" | "1" => dirs::home_dir()?.join(".conduit").join("interactions.jsonl"),
+            path => PathBuf::from(path),
+        };
+        let mut config = Self::new(path);
+        config.apply_env();
+        Some(config)
+    }
+
+    /// Override the retention and redaction settings with the variables that are set
+    pub fn apply_env(&mut self) {
+        if let Some(days) = std::env::var("CONDUIT_INTERACTION_LOG_RETENTION_DAYS").ok().and_then(|d| d.parse().ok()) {
+            self.retention_days = days;
+        }
+        if let Ok(patterns) = std::env::var("CONDUIT_INTERACTION_LOG_REDACT") {
+            self.redact = patterns.split(',')
+                .map(|pattern| pattern.trim().to_string())
+                .filter(|pattern| !pattern.is_empty())
+                .collect();
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InteractionKind {
+    Chat,
+    Embedding,
+}
+
+/// A memory that was injected into a chat request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Citation {
+    pub id: String,
+    pub title: String,
+    pub score: f32,
+}
+
+impl From<&RetrievedMemory> for Citation {
+    fn from(retrieved: &RetrievedMemory) -> Self {
+        Self {
+            id: retrieved.memory.id.clone(),
+            title: retrieved.memory.title.clone(),
+            score: retrieved.score,
+        }
+    }
+}
+
+/// One line of the interaction log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InteractionEntry {
+    pub timestamp: DateTime<Utc>,
+    pub kind: InteractionKind,
+    pub model: String,
+    /// Which provider handled the request, e.g. `upstream`, `local` or `offline`
+    pub provider: String,
+    pub latency_ms: u64,
+    pub prompt_tokens: i32,
+    pub completion_tokens: i32,
+    pub total_tokens: i32,
+    /// Messages as sent to the model, including injected memory context
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub messages: Vec<ChatMessage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub citations: Vec<Citation>,
+    /// Number of texts in an embedding request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_count: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl InteractionEntry {
+    pub fn new(kind: InteractionKind, model: impl Into<String>, provider: impl Into<String>, latency: std::time::Duration) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            kind,
+            model: model.into(),
+            provider: provider.into(),
+            latency_ms: latency.as_millis() as u64,
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            total_tokens: 0,
+            messages: Vec::new(),
+            response: None,
+            citations: Vec::new(),
+            input_count: None,
+            error: None,
+        }
+    }
+}
+
+// Only the timestamp is needed to decide whether an entry is kept
+#[derive(Deserialize)]
+struct EntryTimestamp {
+    timestamp: DateTime<Utc>,
+}
+
+/// Appends redacted interaction entries to a local JSONL file
+pub struct InteractionLog {
+    config: InteractionLogConfig,
+    redactions: Vec<Regex>,
+    /// Serializes writes and remembers when old entries were last removed
+    last_pruned: Mutex<Option<DateTime<Utc>>>,
+}
+
+impl InteractionLog {
+    pub fn open(config: InteractionLogConfig) -> Result<Self, String> {
+        if let Some(parent) = config.path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create interaction log directory: {}", e))?;
+        }
+
+        let mut redactions = Vec::new();
+        for pattern in DEFAULT_REDACTIONS.iter().copied().chain(config.redact.iter().map(String::as_str)) {
+            match Regex::new(pattern) {
+                Ok(regex) => redactions.push(regex),
+                Err(e) => warn!("[LOG] Ignoring invalid redaction pattern {}: {}", pattern, e),
+            }
+        }
+
+        info!("[LOG] Logging LLM interactions to {:?}", config.path);
+        Ok(Self {
+            config,
+            redactions,
+            last_pruned: Mutex::new(None),
+        })
+    }
+
+    fn redact(&self, text: &str) -> String {
+        self.redactions.iter().fold(text.to_string(), |text, regex| {
+            regex.replace_all(&text, REDACTED).into_owned()
+        })
+    }
+
+    /// Redact and append an entry, removing expired entries about once an hour
+    ///
+    /// Failures are logged rather than returned so logging never fails a request.
+    pub fn record(&self, mut entry: InteractionEntry) {
+        for message in &mut entry.messages {
+            match &mut message.content {
+                MessageContent::Text(text) => *text = self.redact(text),
+                MessageContent::Parts(parts) => {
+                    for part in parts {
+                        match part {
+                            ContentPart::Text { text } => *text = self.redact(text),
+                            // Inlined images would bloat the log, so only their type and size are kept
+                            ContentPart::ImageUrl { image_url } => {
+                                let elided = image_url.data()
+                                    .map(|(media_type, data)| format!("data:{};base64,[{} bytes omitted]", media_type, data.len() / 4 * 3));
+                                if let Some(elided) = elided {
+                                    image_url.url = elided;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        entry.response = entry.response.map(|response| self.redact(&response));
+        entry.error = entry.error.map(|error| self.redact(&error));
+
+        let mut last_pruned = self.last_pruned.lock().unwrap_or_else(|e| e.into_inner());
+        if last_pruned.is_none_or(|at| Utc::now() - at > Duration::hours(1)) {
+            if let Err(e) = self.prune() {
+                error!("[LOG] Failed to prune interaction log: {}", e);
+            }
+            *last_pruned = Some(Utc::now());
+        }
+
+        if let Err(e) = self.append(&entry) {
+            error!("[LOG] Failed to write interaction log entry: {}", e);
+        }
+    }
+
+    fn append(&self, entry: &InteractionEntry) -> std::io::Result<()> {
+        let line = serde_json::to_string(entry)?;
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.config.path)?;
+        writeln!(file, "{}", line)
+    }
+
+    // Drop entries past the retention limit, rewriting the file through a staging copy
+    fn prune(&self) -> std::io::Result<()> {
+        if self.config.retention_days == 0 || !self.config.path.exists() {
+            return Ok(());
+        }
+
+        let cutoff = Utc::now() - Duration::days(self.config.retention_days as i64);
+        let contents = fs::read_to_string(&self.config.path)?;
+        let kept: Vec<&str> = contents.lines()
+            .filter(|line| {
+                serde_json::from_str::<EntryTimestamp>(line)
+                    .map(|entry| entry.timestamp >= cutoff)
+                    .unwrap_or(false)
+            })
+            .collect();
+
+        if kept.len() == contents.lines().count() {
+            return Ok(());
+        }
+
+        let mut output = kept.join("\n");
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        write_atomic(&self.config.path, output)
+    }
+}
@@ -0,0 +1,45 @@
+use std::sync::Arc;
+use std::time::Duration;
+use axum::http::{header, HeaderValue};
+use axum::response::{IntoResponse, Response};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use super::error::ApiError;
+
+/// Caps the number of chat and embedding requests handled at once
+///
+/// Requests over the cap wait up to the queue timeout for a slot and are then
+/// rejected with `429 Too Many Requests`.
+#[derive(Debug, Clone, Default)]
+pub struct LlmLimiter {
+    semaphore: Option<Arc<Semaphore>>,
+    queue_timeout: Duration,
+}
+
+impl LlmLimiter {
+    /// Create a limiter; `None` allows unlimited concurrent requests
+    pub fn new(max_concurrency: Option<usize>, queue_timeout: Duration) -> Self {
+        Self {
+            semaphore: max_concurrency.map(|max| Arc::new(Semaphore::new(max))),
+            queue_timeout,
+        }
+    }
+
+    /// Wait for a free slot, which is released when the permit is dropped
+    pub async fn acquire(&self) -> Result<Option<OwnedSemaphorePermit>, Response> {
+        let Some(semaphore) = &self.semaphore else {
+            return Ok(None);
+        };
+
+        match tokio::time::timeout(self.queue_timeout, semaphore.clone().acquire_owned()).await {
+            Ok(Ok(permit)) => Ok(Some(permit)),
+            _ => {
+                let mut response = ApiError::rate_limit(
+                    "Too many concurrent model requests, please retry shortly",
+                ).into_response();
+                response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from_static("1"));
+                Err(response)
+            }
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::sync::Arc;
+use std::time::Duration;
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use tokio::sync::watch;
+use tracing::{error, info};
+
+use crate::memory::{LockStatus, MemoryError, MemoryStore};
+use super::error::{ApiError, OpenAiErrorResponse};
+use super::state::ServerState;
+
+/// Path of the lock routes, which answer while the workspace is locked
+const LOCK_PATH: &str = "/api/lock";
+
+/// How often work held back by the lock checks whether it was lifted
+const UNLOCK_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Routes for the workspace passphrase lock
+///
+/// Unlocking is left to the desktop app and the library, so the passphrase
+/// never has to be sent to the server.
+pub fn router() -> Router<Arc<ServerState>> {
+    Router::new()
+        .route(LOCK_PATH, get(lock_status_handler).post(lock_handler))
+}
+
+/// Answer `423 Locked` while the workspace is locked, and otherwise count the
+/// request as activity that keeps it from locking when idle
+pub async fn require_unlocked(
+    State(state): State<Arc<ServerState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.uri().path() == LOCK_PATH {
+        return next.run(request).await;
+    }
+    match state.memory_store.workspace_lock().check() {
+        Ok(()) => next.run(request).await,
+        Err(err) if request.uri().path().starts_with("/v1/") => ApiError::memory("Cannot access memories", &err).into_response(),
+        Err(err) => ApiError::from(err).into_response(),
+    }
+}
+
+/// Wait until the workspace is unlocked, returning `false` if the server
+/// stops first
+pub(crate) async fn wait_until_unlocked(store: &MemoryStore, shutdown: &mut watch::Receiver<bool>) -> bool {
+    while store.workspace_lock().is_locked() {
+        tokio::select! {
+            _ = tokio::time::sleep(UNLOCK_POLL_INTERVAL) => {}
+            _ = shutdown.wait_for(|stopped| *stopped) => return false,
+        }
+    }
+    true
+}
+
+async fn lock_status(State(state): State<Arc<ServerState>>) -> impl IntoResponse {
+    info!("[SERVER] Handling lock_status request");
+    (StatusCode::OK, Json(state.memory_store.workspace_lock().status()))
+}
+
+async fn lock(State(state): State<Arc<ServerState>>) -> impl IntoResponse {
+    info!("[SERVER] Handling lock request");
+    match state.memory_store.workspace_lock().lock() {
+        Ok(status) => (StatusCode::OK, Json(status)).into_response(),
+        Err(err @ MemoryError::InvalidPassphrase(_)) => ApiError::conflict(err.to_string()).into_response(),
+        Err(err) => {
+            error!("Error locking the workspace: {:?}", err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    get, path = "/api/lock", tag = "maintenance", operation_id = "lock_status",
+    responses(
+        (status = 200, description = "Whether a passphrase is set and the workspace is locked", body = LockStatus),
+    )
+)]
+#[axum::debug_handler]
+async fn lock_status_handler(state: State<Arc<ServerState>>) -> impl IntoResponse {
+    lock_status(state).await
+}
+
+#[utoipa::path(
+    post, path = "/api/lock", tag = "maintenance", operation_id = "lock",
+    responses(
+        (status = 200, description = "The workspace is locked until it is unlocked from the desktop app", body = LockStatus),
+        (status = 409, description = "No passphrase is set", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn lock_handler(state: State<Arc<ServerState>>) -> impl IntoResponse {
+    lock(state).await
+}
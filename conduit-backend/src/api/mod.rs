@@ -1,40 +1,50 @@
+pub mod accounts;
+pub mod activity;
+pub mod analytics;
+pub mod attachment_text;
+pub mod auth;
+pub mod auto_tag;
+pub mod batch;
+pub mod cache;
+pub mod cancel;
+pub mod capture;
+pub mod chat_tools;
+pub mod cleanup;
+pub mod connectors;
+pub mod conversations;
+pub mod cors;
+pub mod embedding_sync;
+pub mod error;
+pub mod export;
+pub mod fields;
+pub mod import;
+pub mod indexes;
+pub mod inbox;
+pub mod instance;
+pub mod interaction_log;
+pub mod limit;
+pub mod lock;
 pub mod openai;
+pub mod openapi;
+pub mod retention;
+pub mod scheduler;
 pub mod server;
+pub mod sessions;
+pub mod setup;
 pub mod state;
+pub mod structured;
+pub mod summarize;
+pub mod sync;
+pub mod tags;
+pub mod templates;
+pub mod titles;
+pub mod tokens;
+pub mod usage;
+pub mod validation;
+pub mod webhooks;
+pub mod workspaces;
 
-use serde::{Deserialize, Serialize};
-use thiserror::Error;
-
-#[derive(Debug, Error)]
-pub enum ApiError {
-    #[error("Memory error: {0}")]
-    Memory(#[from] crate::memory::MemoryError),
-    
-    #[error("Invalid request: {0}")]
-    InvalidRequest(String),
-    
-    #[error("Server error: {0}")]
-    Server(String),
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ApiResponse<T> {
-    pub data: T,
-    pub error: Option<String>,
-}
-
-impl<T> ApiResponse<T> {
-    pub fn success(data: T) -> Self {
-        Self {
-            data,
-            error: None,
-        }
-    }
-    
-    pub fn error(data: T, error: impl Into<String>) -> Self {
-        Self {
-            data,
-            error: Some(error.into()),
-        }
-    }
-}
+pub use error::ApiError;
+pub use server::router;
+pub use state::ServerState;
+pub use conduit_types::ApiResponse;
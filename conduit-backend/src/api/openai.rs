@@ -1,29 +1,65 @@
  use std::sync::Arc;
+use std::time::Instant;
 use axum::{
-    extract::State,
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{
+        rejection::{JsonRejection, PathRejection, QueryRejection},
+        OriginalUri, Query, State,
+    },
+    http::{header, HeaderMap, StatusCode},
+    response::{sse::{Event, Sse}, IntoResponse, Response},
     routing::{get, post},
     Json, Router,
 };
-use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc};
+use futures::stream::StreamExt;
+use chrono::Utc;
 use uuid::Uuid;
 use tracing::{info, error};
 
-use crate::memory::Memory;
+pub use conduit_types::openai::{
+    ChatCompletionChoice, ChatCompletionChunk, ChatCompletionChunkChoice, ChatCompletionRequest,
+    ChatCompletionResponse, ChatCompletionUsage, ChatDelta, ChatMessage, ContentPart, EmbeddingData,
+    EmbeddingRequest, EmbeddingResponse, EmbeddingUsage, FunctionCall, FunctionDefinition, ImageUrl, JsonSchemaFormat,
+    MemoryRequest, MemoryResponse, MessageContent, Model, ModelCapabilities, ModelList, ResponseFormat, Tool, ToolCall,
+};
+
+use crate::memory::{ListMemoriesQuery, ListOptions, Permission};
+use crate::provider::{tokenizer, ChatCompletionStream, ProviderError, UpstreamProvider};
+use crate::rag::{self, RetrievedMemory};
+use super::auth::Caller;
+use super::cache;
+use super::cancel::run_cancellable;
+use super::chat_tools;
+use super::conversations;
+use super::interaction_log::{Citation, InteractionEntry, InteractionKind};
+use super::error::{ApiError, OpenAiErrorResponse};
 use super::state::ServerState;
+use super::structured;
+use super::auto_tag::tag_new_memory;
+use super::titles::resolve_title;
+use super::usage::UsageKey;
+use super::validation::ValidJson;
 
 pub fn router() -> Router<Arc<ServerState>> {
     Router::new()
         .route("/models", get(list_models_handler))
+        .route("/models/*id", get(get_model_handler))
         .route("/chat/completions", post(chat_completions_handler))
         .route("/embeddings", post(create_embeddings_handler))
         .route("/memories", get(list_memories_handler).post(create_memory_handler))
         .route("/memories/:id", get(get_memory_handler).delete(delete_memory_handler))
+        .fallback(not_found_handler)
+}
+
+// Unknown routes under /v1 answer with an OpenAI-style error instead of an empty 404
+async fn not_found_handler(OriginalUri(uri): OriginalUri) -> impl IntoResponse {
+    ApiError::not_found(format!("Unknown request URL: {}", uri.path())).code("unknown_url")
 }
 
 // Wrapper functions to ensure correct type signatures for the router
+#[utoipa::path(
+    get, path = "/v1/models", tag = "openai", operation_id = "list_models",
+    responses((status = 200, body = ModelList))
+)]
 #[axum::debug_handler]
 async fn list_models_handler(
     state: State<Arc<ServerState>>,
@@ -31,321 +67,712 @@ async fn list_models_handler(
     list_models(state).await
 }
 
+#[utoipa::path(
+    get, path = "/v1/models/{id}", tag = "openai", operation_id = "get_model",
+    params(("id" = String, Path, description = "Model ID")),
+    responses(
+        (status = 200, body = Model),
+        (status = 404, description = "Model not found", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn get_model_handler(
+    state: State<Arc<ServerState>>,
+    path: Result<axum::extract::Path<String>, PathRejection>,
+) -> Response {
+    match path {
+        Ok(path) => get_model(state, path).await.into_response(),
+        Err(rejection) => ApiError::from(rejection).into_response(),
+    }
+}
+
+#[utoipa::path(
+    post, path = "/v1/chat/completions", tag = "openai", operation_id = "create_chat_completion",
+    request_body = ChatCompletionRequest,
+    responses(
+        (status = 200, description = "The completion, or server-sent `ChatCompletionChunk` events ending with `[DONE]` when streaming", body = ChatCompletionResponse),
+        (status = 400, description = "Invalid request", body = OpenAiErrorResponse),
+        (status = 404, description = "Unknown model", body = OpenAiErrorResponse),
+        (status = 429, description = "Too many concurrent model requests", body = OpenAiErrorResponse),
+        (status = 502, description = "The upstream provider failed, or its reply does not match `response_format`", body = OpenAiErrorResponse),
+        (status = 503, description = "The upstream provider is cooling down after repeated failures", body = OpenAiErrorResponse),
+    )
+)]
 #[axum::debug_handler]
 async fn chat_completions_handler(
     state: State<Arc<ServerState>>,
-    json: Json<ChatCompletionRequest>,
-) -> impl IntoResponse {
-    chat_completions(state, json).await
+    caller: Caller,
+    key: UsageKey,
+    json: Result<Json<ChatCompletionRequest>, JsonRejection>,
+) -> Response {
+    // Hold a slot for the whole request so bursts can't overwhelm the provider
+    let _permit = match state.llm_limiter.acquire().await {
+        Ok(permit) => permit,
+        Err(response) => return response,
+    };
+    
+    match json {
+        Ok(json) => chat_completions(state, caller, key, json).await.into_response(),
+        Err(rejection) => ApiError::from(rejection).into_response(),
+    }
 }
 
+#[utoipa::path(
+    post, path = "/v1/embeddings", tag = "openai", operation_id = "create_embedding",
+    request_body = EmbeddingRequest,
+    responses(
+        (status = 200, body = EmbeddingResponse),
+        (status = 400, description = "Invalid request", body = OpenAiErrorResponse),
+        (status = 404, description = "Unknown model", body = OpenAiErrorResponse),
+        (status = 429, description = "Too many concurrent model requests", body = OpenAiErrorResponse),
+        (status = 502, description = "The upstream provider failed", body = OpenAiErrorResponse),
+    )
+)]
 #[axum::debug_handler]
 async fn create_embeddings_handler(
     state: State<Arc<ServerState>>,
-    json: Json<EmbeddingRequest>,
-) -> impl IntoResponse {
-    create_embeddings(state, json).await
+    key: UsageKey,
+    json: Result<Json<EmbeddingRequest>, JsonRejection>,
+) -> Response {
+    // Hold a slot for the whole request so bursts can't overwhelm the provider
+    let _permit = match state.llm_limiter.acquire().await {
+        Ok(permit) => permit,
+        Err(response) => return response,
+    };
+    
+    match json {
+        Ok(json) => create_embeddings(state, key, json).await.into_response(),
+        Err(rejection) => ApiError::from(rejection).into_response(),
+    }
 }
 
+#[utoipa::path(
+    get, path = "/v1/memories", tag = "openai", operation_id = "openai_list_memories",
+    params(ListMemoriesQuery),
+    responses(
+        (status = 200, description = "A page of memories; the total count is in `X-Total-Count`", body = [MemoryResponse]),
+        (status = 304, description = "Not modified since `If-Modified-Since`"),
+        (status = 400, description = "Invalid request", body = OpenAiErrorResponse),
+    )
+)]
 #[axum::debug_handler]
 async fn list_memories_handler(
     state: State<Arc<ServerState>>,
-) -> impl IntoResponse {
-    list_memories(state).await
+    caller: Caller,
+    query: Result<Query<ListMemoriesQuery>, QueryRejection>,
+    headers: HeaderMap,
+) -> Response {
+    match query {
+        Ok(query) => list_memories(state, caller, query, headers).await.into_response(),
+        Err(rejection) => ApiError::from(rejection).into_response(),
+    }
 }
 
+#[utoipa::path(
+    post, path = "/v1/memories", tag = "openai", operation_id = "openai_create_memory",
+    request_body = MemoryRequest,
+    responses(
+        (status = 201, body = MemoryResponse),
+        (status = 400, description = "Malformed JSON", body = OpenAiErrorResponse),
+        (status = 422, description = "Missing field, blank title and content, or too long a title, content or tag list", body = OpenAiErrorResponse),
+    )
+)]
 #[axum::debug_handler]
 async fn create_memory_handler(
     state: State<Arc<ServerState>>,
-    json: Json<MemoryRequest>,
-) -> impl IntoResponse {
-    create_memory(state, json).await
+    caller: Caller,
+    json: Result<ValidJson<MemoryRequest>, ApiError>,
+) -> Response {
+    match json {
+        Ok(json) => create_memory(state, caller, json).await.into_response(),
+        Err(err) => err.into_response(),
+    }
 }
 
+#[utoipa::path(
+    get, path = "/v1/memories/{id}", tag = "openai", operation_id = "openai_get_memory",
+    params(("id" = String, Path, description = "Memory ID")),
+    responses(
+        (status = 200, body = MemoryResponse),
+        (status = 404, description = "Memory not found", body = OpenAiErrorResponse),
+    )
+)]
 #[axum::debug_handler]
 async fn get_memory_handler(
     state: State<Arc<ServerState>>,
-    path: axum::extract::Path<String>,
-) -> impl IntoResponse {
-    get_memory(state, path).await
+    caller: Caller,
+    path: Result<axum::extract::Path<String>, PathRejection>,
+) -> Response {
+    match path {
+        Ok(path) => get_memory(state, caller, path).await.into_response(),
+        Err(rejection) => ApiError::from(rejection).into_response(),
+    }
 }
 
+#[utoipa::path(
+    delete, path = "/v1/memories/{id}", tag = "openai", operation_id = "openai_delete_memory",
+    params(("id" = String, Path, description = "Memory ID")),
+    responses(
+        (status = 204, description = "Moved to the trash"),
+        (status = 404, description = "Memory not found", body = OpenAiErrorResponse),
+    )
+)]
 #[axum::debug_handler]
 async fn delete_memory_handler(
     state: State<Arc<ServerState>>,
-    path: axum::extract::Path<String>,
-) -> impl IntoResponse {
-    delete_memory(state, path).await
-}
-
-// OpenAI API compatible types
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Model {
-    pub id: String,
-    pub object: String,
-    pub created: i64,
-    pub owned_by: String,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ModelList {
-    pub object: String,
-    pub data: Vec<Model>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ChatMessage {
-    pub role: String,
-    pub content: String,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ChatCompletionRequest {
-    pub model: String,
-    pub messages: Vec<ChatMessage>,
-    pub temperature: Option<f32>,
-    pub max_tokens: Option<i32>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ChatCompletionChoice {
-    pub index: usize,
-    pub message: ChatMessage,
-    pub finish_reason: String,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ChatCompletionUsage {
-    pub prompt_tokens: i32,
-    pub completion_tokens: i32,
-    pub total_tokens: i32,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ChatCompletionResponse {
-    pub id: String,
-    pub object: String,
-    pub created: i64,
-    pub model: String,
-    pub choices: Vec<ChatCompletionChoice>,
-    pub usage: ChatCompletionUsage,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct EmbeddingRequest {
-    pub model: String,
-    pub input: Vec<String>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct EmbeddingData {
-    pub index: usize,
-    pub object: String,
-    pub embedding: Vec<f32>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct EmbeddingResponse {
-    pub object: String,
-    pub data: Vec<EmbeddingData>,
-    pub model: String,
-    pub usage: EmbeddingUsage,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct EmbeddingUsage {
-    pub prompt_tokens: i32,
-    pub total_tokens: i32,
-}
-
-// Memory types for OpenAI API
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct MemoryRequest {
-    pub title: String,
-    pub content: String,
-    pub tags: Vec<String>,
+    caller: Caller,
+    path: Result<axum::extract::Path<String>, PathRejection>,
+) -> Response {
+    match path {
+        Ok(path) => delete_memory(state, caller, path).await.into_response(),
+        Err(rejection) => ApiError::from(rejection).into_response(),
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct MemoryResponse {
-    pub id: String,
-    pub title: String,
-    pub content: String,
-    pub tags: Vec<String>,
-    pub created_at: DateTime<Utc>,
-    pub updated_at: DateTime<Utc>,
+// API handlers
+fn model_not_found(id: &str) -> ApiError {
+    ApiError::not_found(format!("The model '{}' does not exist", id))
+        .param("model")
+        .code("model_not_found")
 }
 
-// API handlers
 async fn list_models(
-    State(_state): State<Arc<ServerState>>,
+    State(state): State<Arc<ServerState>>,
 ) -> impl IntoResponse {
     let models = ModelList {
         object: "list".to_string(),
-        data: vec![
-            Model {
-                id: "gpt-3.5-turbo".to_string(),
-                object: "model".to_string(),
-                created: Utc::now().timestamp(),
-                owned_by: "conduit".to_string(),
-            },
-            Model {
-                id: "text-embedding-ada-002".to_string(),
-                object: "model".to_string(),
-                created: Utc::now().timestamp(),
-                owned_by: "conduit".to_string(),
-            },
-        ],
+        data: state.models.list().await,
     };
     
     (StatusCode::OK, Json(models)).into_response()
 }
 
+async fn get_model(
+    State(state): State<Arc<ServerState>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Response {
+    // Aliases return the model they stand for
+    let id = state.models.lookup(&id).map_or(id, |route| route.id);
+    match state.models.list().await.into_iter().find(|model| model.id == id) {
+        Some(model) => (StatusCode::OK, Json(model)).into_response(),
+        None => model_not_found(&id).into_response(),
+    }
+}
+
 async fn chat_completions(
     State(state): State<Arc<ServerState>>,
-    Json(req): Json<ChatCompletionRequest>,
+    caller: Caller,
+    key: UsageKey,
+    Json(mut req): Json<ChatCompletionRequest>,
 ) -> impl IntoResponse {
     info!("Chat completion request for model: {}", req.model);
+    let Some(route) = state.models.resolve(&req.model).await else {
+        return model_not_found(&req.model).into_response();
+    };
+    if !route.supports_chat() {
+        return ApiError::invalid_request(format!("The model '{}' does not serve chat completions", route.id))
+            .param("model")
+            .code("model_not_supported")
+            .into_response();
+    }
+    let audit = ChatAudit { key, model: route.id.clone(), started: Instant::now() };
+    
+    let format = structured::requested(req.response_format.as_ref()).cloned();
+    if let Some(format) = &format {
+        if let Err(err) = structured::check(format) {
+            return ApiError::invalid_request(err).param("response_format").into_response();
+        }
+    }
     
-    // Process the chat request
-    // In a real implementation, this would call an actual LLM
-    // For now, we'll just echo back the last message with some context
+    // Images are only sent to models that can read them
+    if route.provider.is_some() {
+        let has_images = req.messages.iter().any(|message| message.content.images().next().is_some());
+        if has_images && !route.supports_images() {
+            return ApiError::invalid_request(format!("The model '{}' does not accept images", req.model))
+                .param("messages")
+                .code("images_not_supported")
+                .into_response();
+        }
+    }
+    
+    route.apply(&mut req);
     
-    let memories = match state.memory_store.list() {
-        Ok(mems) => mems,
+    // A continued conversation's history goes before the new messages
+    let exchange = match conversations::continue_conversation(&state, &caller, &mut req) {
+        Ok(exchange) => exchange,
+        Err(err) => {
+            error!("Error loading conversation: {:?}", err);
+            return ApiError::memory("Failed to load the conversation", &err).param("conversation_id").into_response();
+        }
+    };
+    
+    // Get the text of the last user message
+    let last_message = req.messages.iter()
+        .rev()
+        .find(|m| m.role == "user")
+        .map(|m| m.content.text())
+        .unwrap_or_else(|| "Hello".to_string());
+    
+    // Retrieve the memories relevant to the last user message
+    let rag_config = match &req.conduit {
+        Some(options) => state.rag.with_overrides(options),
+        None => state.rag.clone(),
+    };
+    
+    // Retrieval runs off the async runtime and stops if the client goes away
+    let store = state.memory_store.clone();
+    let query = last_message.clone();
+    let retrieval_config = rag_config.clone();
+    let importance = state.importance.clone();
+    let retrieval_caller = caller.clone();
+    let retrieved = match run_cancellable(move |cancel| rag::retrieve(&store, &query, &retrieval_config, &importance, retrieval_caller.principal(), cancel)).await {
+        Ok(retrieved) => retrieved,
         Err(err) => {
             error!("Error retrieving memories: {:?}", err);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "error": {
-                        "message": format!("Failed to retrieve memories: {}", err),
-                        "type": "internal_error"
-                    }
-                }))
-            ).into_response();
+            return ApiError::memory("Failed to retrieve memories", &err).into_response();
         }
     };
+    info!("Retrieved {} relevant memories for chat context", retrieved.len());
     
-    // Get the last user message
-    let last_message = req.messages.last().cloned().unwrap_or(ChatMessage {
-        role: "user".to_string(),
-        content: "Hello".to_string(),
-    });
+    // Forward to the model's provider, if it has one
+    if let Some(provider) = &route.provider {
+        if let Some(context) = rag::context_message(&retrieved, rag_config.max_context_tokens) {
+            rag::inject_context(&mut req.messages, context);
+        }
+        
+        // Models that ignore `response_format` are asked for JSON in a system message
+        if let Some(format) = &format {
+            if !route.supports_response_format() {
+                req.response_format = None;
+                rag::inject_context(&mut req.messages, structured::instruction(format));
+            }
+        }
+        
+        // Calls of the memory tools are run here, so those requests are not streamed from upstream
+        let memory_tools = chat_tools::offer(&state, &mut req);
+        if req.stream == Some(true) && memory_tools.is_empty() {
+            return match provider.chat_completion_stream(&req).await {
+                Ok(chunks) => {
+                    let chunks = match exchange {
+                        Some(exchange) => exchange.record_stream(state.clone(), req.model.clone(), chunks),
+                        None => chunks,
+                    };
+                    stream_response(state.clone(), req, format, retrieved, audit, "upstream", chunks)
+                }
+                Err(err) => {
+                    error!("Upstream chat completion failed: {:?}", err);
+                    log_chat(&state, &req, &retrieved, &audit, "upstream", Err(err.to_string()));
+                    provider_error_response(err)
+                }
+            };
+        }
+        
+        let mut completion = match chat_tools::complete(&state, provider, &mut req, &memory_tools, &caller).await {
+            Ok(completion) => completion,
+            Err(err) => {
+                error!("Upstream chat completion failed: {:?}", err);
+                log_chat(&state, &req, &retrieved, &audit, "upstream", Err(err.to_string()));
+                return provider_error_response(err);
+            }
+        };
+        // Providers that report no usage have it counted here
+        if completion.usage.total_tokens == 0 {
+            let reply = completion.choices.first().map(|choice| choice.message.content.text()).unwrap_or_default();
+            completion.usage = tokenizer::count_usage(&req.model, &req.messages, &reply);
+        }
+        
+        // The tool calls are done, so the reply is streamed as a whole
+        if req.stream == Some(true) {
+            if let Some(exchange) = exchange {
+                exchange.record_completion(&state, &completion);
+            }
+            let chunks = futures::stream::iter(completion_chunks(completion).into_iter().map(Ok)).boxed();
+            return stream_response(state.clone(), req, format, retrieved, audit, "upstream", chunks);
+        }
+        
+        let completion = match &format {
+            Some(format) => match structured_completion(provider, &req, format, completion).await {
+                Ok(completion) => completion,
+                Err(problem) => {
+                    error!("Upstream reply does not match the response format: {}", problem);
+                    log_chat(&state, &req, &retrieved, &audit, "upstream", Err(problem.clone()));
+                    return invalid_format_error(&problem).into_response();
+                }
+            },
+            None => completion,
+        };
+        log_chat(&state, &req, &retrieved, &audit, "upstream", Ok(&completion));
+        if let Some(exchange) = exchange {
+            exchange.record_completion(&state, &completion);
+        }
+        return (StatusCode::OK, Json(completion)).into_response();
+    }
     
-    // Create a simple response that mentions the available memories
-    let memory_titles: Vec<String> = memories.iter()
-        .map(|m| format!("- {}", m.title))
+    // Offline mode: no provider serves the model, so echo back the last
+    // message along with the memories that would have been injected
+    let memory_titles: Vec<String> = retrieved.iter()
+        .map(|r| format!("- {}", r.memory.title))
         .collect();
     
-    let memory_count = memories.len();
-    let response_content = format!(
-        "I received your message: '{}'\n\nI have access to {} memories:\n{}\n\nHow can I help you with these memories?",
-        last_message.content,
-        memory_count,
-        memory_titles.join("\n")
-    );
+    let response_content = if let Some(format) = &format {
+        let titles: Vec<String> = retrieved.iter().map(|r| r.memory.title.clone()).collect();
+        structured::offline_reply(format, &last_message, &titles)
+    } else if memory_titles.is_empty() {
+        format!(
+            "I received your message: '{}'\n\nI couldn't find any memories related to it.",
+            last_message
+        )
+    } else {
+        format!(
+            "I received your message: '{}'\n\nThese {} memories look relevant:\n{}\n\nHow can I help you with these memories?",
+            last_message,
+            memory_titles.len(),
+            memory_titles.join("\n")
+        )
+    };
     
     // Create the completion response
+    let usage = tokenizer::count_usage(&req.model, &req.messages, &response_content);
     let completion = ChatCompletionResponse {
         id: format!("chatcmpl-{}", Uuid::new_v4()),
         object: "chat.completion".to_string(),
         created: Utc::now().timestamp(),
-        model: req.model,
+        model: req.model.clone(),
         choices: vec![
             ChatCompletionChoice {
                 index: 0,
                 message: ChatMessage {
                     role: "assistant".to_string(),
-                    content: response_content,
+                    content: response_content.into(),
+                    ..Default::default()
                 },
                 finish_reason: "stop".to_string(),
             }
         ],
-        usage: ChatCompletionUsage {
-            prompt_tokens: 100, // Placeholder values
-            completion_tokens: 100,
-            total_tokens: 200,
-        },
+        usage,
     };
     
+    if let Some(exchange) = exchange {
+        exchange.record_completion(&state, &completion);
+    }
+    
+    if req.stream == Some(true) {
+        let chunks = futures::stream::iter(completion_chunks(completion).into_iter().map(Ok)).boxed();
+        return stream_response(state.clone(), req, format, retrieved, audit, "offline", chunks);
+    }
+    
+    log_chat(&state, &req, &retrieved, &audit, "offline", Ok(&completion));
     (StatusCode::OK, Json(completion)).into_response()
 }
 
+// Check that every choice of a completion has the requested format, replacing
+// its content with the bare JSON
+fn conform(format: &ResponseFormat, completion: &mut ChatCompletionResponse) -> Result<(), String> {
+    for choice in &mut completion.choices {
+        choice.message.content = structured::validate(format, &choice.message.content.text())?.into();
+    }
+    Ok(())
+}
+
+// Check a completion against the requested format, asking the model once more
+// with the problem when it does not match
+async fn structured_completion(
+    provider: &UpstreamProvider,
+    req: &ChatCompletionRequest,
+    format: &ResponseFormat,
+    mut completion: ChatCompletionResponse,
+) -> Result<ChatCompletionResponse, String> {
+    let Err(problem) = conform(format, &mut completion) else {
+        return Ok(completion);
+    };
+    info!("Asking the model again for a reply matching the response format: {}", problem);
+    
+    let mut retry = req.clone();
+    let reply = completion.choices.first().map(|choice| choice.message.content.clone()).unwrap_or_default();
+    retry.messages.push(ChatMessage { role: "assistant".to_string(), content: reply, ..Default::default() });
+    retry.messages.push(ChatMessage {
+        role: "user".to_string(),
+        content: format!("{}. Reply again with only the corrected JSON.", problem).into(),
+        ..Default::default()
+    });
+    
+    let mut corrected = provider.chat_completion(&retry).await.map_err(|err| err.to_string())?;
+    conform(format, &mut corrected)?;
+    // Both attempts count towards the tokens used
+    corrected.usage.prompt_tokens += completion.usage.prompt_tokens;
+    corrected.usage.completion_tokens += completion.usage.completion_tokens;
+    corrected.usage.total_tokens += completion.usage.total_tokens;
+    Ok(corrected)
+}
+
+fn invalid_format_error(problem: &str) -> ApiError {
+    ApiError::upstream(format!("The model's reply does not match response_format: {}", problem))
+        .param("response_format")
+        .code("invalid_response_format")
+}
+
+// Split a complete response into the chunks a streaming client expects
+fn completion_chunks(completion: ChatCompletionResponse) -> Vec<ChatCompletionChunk> {
+    let chunk = |delta: ChatDelta, finish_reason: Option<String>| ChatCompletionChunk {
+        id: completion.id.clone(),
+        object: "chat.completion.chunk".to_string(),
+        created: completion.created,
+        model: completion.model.clone(),
+        choices: vec![ChatCompletionChunkChoice { index: 0, delta, finish_reason }],
+    };
+    
+    let mut chunks = Vec::new();
+    for choice in &completion.choices {
+        // A streamed tool call is given whole, with its index among the message's calls
+        let tool_calls = (!choice.message.tool_calls.is_empty()).then(|| {
+            choice.message.tool_calls.iter().enumerate()
+                .map(|(index, call)| serde_json::json!({ "index": index, "id": call.id, "type": call.kind, "function": call.function }))
+                .collect()
+        });
+        chunks.push(chunk(ChatDelta {
+            role: Some(choice.message.role.clone()),
+            content: Some(choice.message.content.text()),
+            tool_calls,
+        }, None));
+        chunks.push(chunk(ChatDelta::default(), Some(choice.finish_reason.clone())));
+    }
+    chunks
+}
+
+// Send chunks to the client as server-sent events, ending with `data: [DONE]`
+fn stream_response(
+    state: Arc<ServerState>,
+    req: ChatCompletionRequest,
+    format: Option<ResponseFormat>,
+    retrieved: Vec<RetrievedMemory>,
+    audit: ChatAudit,
+    provider: &'static str,
+    chunks: ChatCompletionStream,
+) -> Response {
+    // The streamed text is collected for the interaction log
+    let streamed = Arc::new(std::sync::Mutex::new((String::new(), None::<String>)));
+    
+    let collected = streamed.clone();
+    let events = chunks.map(move |chunk| {
+        let mut collected = collected.lock().unwrap_or_else(|e| e.into_inner());
+        let event = match chunk {
+            Ok(chunk) => {
+                for choice in &chunk.choices {
+                    collected.0.push_str(choice.delta.content.as_deref().unwrap_or_default());
+                }
+                Event::default().json_data(&chunk).unwrap_or_default()
+            }
+            Err(err) => {
+                error!("Upstream stream failed: {:?}", err);
+                collected.1 = Some(err.to_string());
+                let error = ApiError::upstream(format!("Upstream provider error: {}", err));
+                Event::default().data(serde_json::json!({ "error": error.body }).to_string())
+            }
+        };
+        Ok::<_, std::convert::Infallible>(event)
+    });
+    
+    let done = futures::stream::once(async move {
+        let (content, failure) = std::mem::take(&mut *streamed.lock().unwrap_or_else(|e| e.into_inner()));
+        let mut events = Vec::new();
+        
+        // Streamed text can't be corrected, so a reply without the requested format ends with an error
+        let mismatch = match (&failure, &format) {
+            (None, Some(format)) => structured::validate(format, &content).err(),
+            _ => None,
+        };
+        if let Some(problem) = &mismatch {
+            error!("Streamed reply does not match the response format: {}", problem);
+            let error = invalid_format_error(problem);
+            events.push(Event::default().data(serde_json::json!({ "error": error.body }).to_string()));
+        }
+        
+        let result = match failure.or(mismatch) {
+            Some(err) => Err(err),
+            None => Ok(ChatCompletionResponse {
+                usage: tokenizer::count_usage(&req.model, &req.messages, &content),
+                id: String::new(),
+                object: "chat.completion".to_string(),
+                created: Utc::now().timestamp(),
+                model: req.model.clone(),
+                choices: vec![ChatCompletionChoice {
+                    index: 0,
+                    message: ChatMessage { role: "assistant".to_string(), content: content.into(), ..Default::default() },
+                    finish_reason: "stop".to_string(),
+                }],
+            }),
+        };
+        log_chat(&state, &req, &retrieved, &audit, provider, result.as_ref().map_err(|e| e.clone()));
+        events.push(Event::default().data("[DONE]"));
+        futures::stream::iter(events.into_iter().map(Ok::<_, std::convert::Infallible>))
+    }).flatten();
+    
+    Sse::new(events.chain(done)).into_response()
+}
+
+/// Who made a chat request and when, for the usage report and the interaction log
+struct ChatAudit {
+    key: UsageKey,
+    /// Model the client asked for, with aliases resolved
+    model: String,
+    started: Instant,
+}
+
+// Count the tokens of a chat request against its key, and record it in the
+// interaction log if it is enabled
+fn log_chat(
+    state: &ServerState,
+    req: &ChatCompletionRequest,
+    retrieved: &[RetrievedMemory],
+    audit: &ChatAudit,
+    provider: &str,
+    result: Result<&ChatCompletionResponse, String>,
+) {
+    if let Ok(completion) = &result {
+        state.usage.record(&audit.key, &audit.model, completion.usage.prompt_tokens, completion.usage.completion_tokens);
+    }
+    let Some(log) = &state.interaction_log else {
+        return;
+    };
+    
+    let mut entry = InteractionEntry::new(InteractionKind::Chat, &audit.model, provider, audit.started.elapsed());
+    entry.messages = req.messages.clone();
+    entry.citations = retrieved.iter().map(Citation::from).collect();
+    match result {
+        Ok(completion) => {
+            entry.prompt_tokens = completion.usage.prompt_tokens;
+            entry.completion_tokens = completion.usage.completion_tokens;
+            entry.total_tokens = completion.usage.total_tokens;
+            entry.response = completion.choices.first().map(|choice| choice.message.content.text());
+        }
+        Err(err) => entry.error = Some(err),
+    }
+    log.record(entry);
+}
+
 async fn create_embeddings(
-    State(_state): State<Arc<ServerState>>,
+    State(state): State<Arc<ServerState>>,
+    key: UsageKey,
     Json(req): Json<EmbeddingRequest>,
 ) -> impl IntoResponse {
     info!("Embedding request for model: {}", req.model);
     
-    // In a real implementation, this would call an actual embedding model
-    // For now, we'll just return random embeddings
+    if req.input.is_empty() {
+        return ApiError::invalid_request("Input must not be empty").param("input").into_response();
+    }
+    
+    let Some(route) = state.models.resolve(&req.model).await else {
+        return model_not_found(&req.model).into_response();
+    };
+    if !route.supports_embeddings() {
+        return ApiError::invalid_request(format!("The model '{}' does not serve embeddings", route.id))
+            .param("model")
+            .code("model_not_supported")
+            .into_response();
+    }
     
-    let mut embeddings = Vec::new();
+    let started = Instant::now();
+    let (provider, target) = state.embeddings.select(&route.id);
+    info!("Using {} embedding provider for {} inputs", provider.name(), req.input.len());
     
-    for (i, text) in req.input.iter().enumerate() {
-        // Create a deterministic but simple embedding based on the text length
-        // This is just a placeholder - real embeddings would come from a model
-        let mut embedding = Vec::new();
-        let seed = text.len() as f32;
-        
-        for j in 0..10 {
-            // Generate a simple deterministic value based on text and position
-            let val = ((j as f32 * 0.1) + seed * 0.01).sin();
-            embedding.push(val);
+    let result = provider.embed(&target, &req.input).await;
+    if let Ok(embeddings) = &result {
+        state.usage.record(&key, &embeddings.model, embeddings.prompt_tokens as i32, 0);
+    }
+    if let Some(log) = &state.interaction_log {
+        let mut entry = InteractionEntry::new(InteractionKind::Embedding, &req.model, provider.name(), started.elapsed());
+        entry.input_count = Some(req.input.len());
+        match &result {
+            Ok(embeddings) => {
+                entry.model = embeddings.model.clone();
+                entry.prompt_tokens = embeddings.prompt_tokens as i32;
+                entry.total_tokens = embeddings.prompt_tokens as i32;
+            }
+            Err(err) => entry.error = Some(err.to_string()),
         }
-        
-        embeddings.push(EmbeddingData {
-            index: i,
-            object: "embedding".to_string(),
-            embedding,
-        });
+        log.record(entry);
     }
     
+    let embeddings = match result {
+        Ok(embeddings) => embeddings,
+        Err(err) => {
+            error!("Embedding request failed: {:?}", err);
+            return provider_error_response(err);
+        }
+    };
+    
     let response = EmbeddingResponse {
         object: "list".to_string(),
-        data: embeddings,
-        model: req.model,
+        data: embeddings.vectors.into_iter()
+            .enumerate()
+            .map(|(index, embedding)| EmbeddingData {
+                index,
+                object: "embedding".to_string(),
+                embedding,
+            })
+            .collect(),
+        model: embeddings.model,
         usage: EmbeddingUsage {
-            prompt_tokens: req.input.iter().map(|s| s.len() as i32 / 4).sum(),
-            total_tokens: req.input.iter().map(|s| s.len() as i32 / 4).sum(),
+            prompt_tokens: embeddings.prompt_tokens as i32,
+            total_tokens: embeddings.prompt_tokens as i32,
         },
     };
     
     (StatusCode::OK, Json(response)).into_response()
 }
 
+// Map a failed upstream call to the error clients expect
+fn provider_error_response(err: ProviderError) -> Response {
+    match err {
+        ProviderError::CircuitOpen { retry_at } => {
+            // Fail fast while the upstream is down instead of waiting for another timeout
+            let retry_after = ((retry_at - Utc::now()).num_milliseconds() + 999).div_euclid(1000).max(1);
+            let mut response = ApiError::unavailable(format!(
+                "Upstream provider is unavailable after repeated failures, retry in {} seconds",
+                retry_after
+            )).into_response();
+            if let Ok(value) = header::HeaderValue::from_str(&retry_after.to_string()) {
+                response.headers_mut().insert(header::RETRY_AFTER, value);
+            }
+            response
+        }
+        err => ApiError::upstream(format!("Upstream provider error: {}", err)).into_response(),
+    }
+}
+
 // Memory API handlers for OpenAI API path
 async fn list_memories(
     State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Query(query): Query<ListMemoriesQuery>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
     info!("[API] Handling list_memories request");
     
-    // Ensure the memory directory exists
-    let base_path = state.memory_store.base_path.clone();
-    info!("[API] Memory base path: {:?}", base_path);
-    
-    if !base_path.exists() {
-        info!("[API] Memory directory does not exist, creating it");
-        if let Err(e) = std::fs::create_dir_all(&base_path) {
-            error!("[API] Failed to create memory directory: {:?}", e);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "error": {
-                        "message": format!("Failed to create memory directory: {}", e),
-                        "type": "internal_error"
-                    }
-                }))
-            ).into_response();
+    // Report a missing store instead of recreating it empty
+    if let Err(err) = state.memory_store.check_available() {
+        error!("[API] {}", err);
+        return ApiError::memory("Cannot access memories", &err).into_response();
+    }
+    
+    // Read the mutation timestamp before listing so a concurrent write is never hidden
+    let last_modified = state.memory_store.last_modified().ok();
+    if let Some(last_modified) = last_modified {
+        if cache::is_not_modified(&headers, last_modified) {
+            return (StatusCode::NOT_MODIFIED, cache::cache_headers(last_modified)).into_response();
         }
-        info!("[API] Created memory directory");
     }
     
-    info!("[API] Calling memory_store.list()");
-    match state.memory_store.list() {
-        Ok(memories) => {
-            let memory_responses: Vec<MemoryResponse> = memories.into_iter()
+    info!("[API] Calling memory_store.list_paged()");
+    let summaries = query.summaries;
+    let options = ListOptions { visible_to: caller.0, ..ListOptions::from(query) };
+    match state.memory_store.list_paged(&options) {
+        Ok(mut page) => {
+            if summaries {
+                page.items.iter_mut().for_each(|memory| state.summarize.abridge(memory));
+            }
+            let total = page.total;
+            let memory_responses: Vec<MemoryResponse> = page.items.into_iter()
                 .map(|m| MemoryResponse {
                     id: m.id,
                     title: m.title,
@@ -353,55 +780,41 @@ async fn list_memories(
                     tags: m.tags,
                     created_at: m.created_at,
                     updated_at: m.updated_at,
+                    last_viewed_at: m.last_viewed_at,
+                    collection: m.collection,
+                    read_only: m.read_only,
+                    attachments: m.attachments,
+                    suggested_tags: Vec::new(),
                 })
                 .collect();
                 
-            (StatusCode::OK, Json(memory_responses)).into_response()
+            let mut headers = last_modified.map(cache::cache_headers).unwrap_or_default();
+            headers.insert(cache::TOTAL_COUNT, total.into());
+            
+            (StatusCode::OK, headers, Json(memory_responses)).into_response()
         },
         Err(err) => {
             error!("Error listing memories: {:?}", err);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "error": {
-                        "message": format!("Failed to list memories: {}", err),
-                        "type": "internal_error"
-                    }
-                }))
-            ).into_response()
+            ApiError::memory("Failed to list memories", &err).into_response()
         }
     }
 }
 
 async fn get_memory(
     State(state): State<Arc<ServerState>>,
+    caller: Caller,
     axum::extract::Path(id): axum::extract::Path<String>,
 ) -> impl IntoResponse {
     info!("[API] Handling get_memory request for id: {}", id);
     
-    // Ensure the memory directory exists
-    let base_path = state.memory_store.base_path.clone();
-    info!("[API] Memory base path: {:?}", base_path);
-    
-    if !base_path.exists() {
-        info!("[API] Memory directory does not exist, creating it");
-        if let Err(e) = std::fs::create_dir_all(&base_path) {
-            error!("[API] Failed to create memory directory: {:?}", e);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "error": {
-                        "message": format!("Failed to create memory directory: {}", e),
-                        "type": "internal_error"
-                    }
-                }))
-            ).into_response();
-        }
-        info!("[API] Created memory directory");
+    // Report a missing store instead of recreating it empty
+    if let Err(err) = state.memory_store.check_available() {
+        error!("[API] {}", err);
+        return ApiError::memory("Cannot access memories", &err).into_response();
     }
     
     info!("[API] Calling memory_store.get() for id: {}", id);
-    match state.memory_store.get(&id) {
+    match state.memory_store.authorize(&id, caller.principal(), Permission::Read) {
         Ok(memory) => {
             let memory_response = MemoryResponse {
                 id: memory.id,
@@ -410,60 +823,51 @@ async fn get_memory(
                 tags: memory.tags,
                 created_at: memory.created_at,
                 updated_at: memory.updated_at,
+                last_viewed_at: memory.last_viewed_at,
+                collection: memory.collection,
+                read_only: memory.read_only,
+                attachments: memory.attachments,
+                suggested_tags: Vec::new(),
             };
             
             (StatusCode::OK, Json(memory_response)).into_response()
         },
         Err(err) => {
             error!("Error getting memory {}: {:?}", id, err);
-            let status = match err {
-                crate::memory::MemoryError::NotFound(_) => StatusCode::NOT_FOUND,
-                _ => StatusCode::INTERNAL_SERVER_ERROR,
-            };
-            
-            (
-                status,
-                Json(serde_json::json!({
-                    "error": {
-                        "message": format!("Failed to get memory: {}", err),
-                        "type": "not_found"
-                    }
-                }))
-            ).into_response()
+            ApiError::memory("Failed to get memory", &err).into_response()
         }
     }
 }
 
 async fn create_memory(
     State(state): State<Arc<ServerState>>,
-    Json(req): Json<MemoryRequest>,
+    caller: Caller,
+    ValidJson(req): ValidJson<MemoryRequest>,
 ) -> impl IntoResponse {
     info!("[API] Handling create_memory request with title: {}", req.title);
     
-    // Ensure the memory directory exists
-    let base_path = state.memory_store.base_path.clone();
-    info!("[API] Memory base path: {:?}", base_path);
-    
-    if !base_path.exists() {
-        info!("[API] Memory directory does not exist, creating it");
-        if let Err(e) = std::fs::create_dir_all(&base_path) {
-            error!("[API] Failed to create memory directory: {:?}", e);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "error": {
-                        "message": format!("Failed to create memory directory: {}", e),
-                        "type": "internal_error"
-                    }
-                }))
-            ).into_response();
-        }
-        info!("[API] Created memory directory");
+    // Report a missing store instead of recreating it empty
+    if let Err(err) = state.memory_store.check_available() {
+        error!("[API] {}", err);
+        return ApiError::memory("Cannot access memories", &err).into_response();
     }
     
-    info!("[API] Creating new memory with title: {}", req.title);
-    let memory = Memory::new(req.title, req.content, req.tags);
+    let title = match resolve_title(&state, req.title, &req.content).await {
+        Ok(title) => title,
+        Err(err) => return ApiError::invalid_request(err).param("title").into_response(),
+    };
+    
+    info!("[API] Creating new memory with title: {}", title);
+    let mut memory = match state.memory_store.new_memory(title, req.content, req.tags) {
+        Ok(memory) => memory,
+        Err(err) => {
+            error!("Error generating memory ID: {:?}", err);
+            return ApiError::memory("Failed to create memory", &err).into_response();
+        }
+    };
     info!("[API] Generated memory ID: {}", memory.id);
+    memory.owner = caller.user().map(str::to_string);
+    let suggested_tags = tag_new_memory(&state, &mut memory, caller.principal()).await;
     
     match state.memory_store.save(&memory) {
         Ok(_) => {
@@ -474,50 +878,37 @@ async fn create_memory(
                 tags: memory.tags,
                 created_at: memory.created_at,
                 updated_at: memory.updated_at,
+                last_viewed_at: memory.last_viewed_at,
+                collection: memory.collection,
+                read_only: memory.read_only,
+                attachments: memory.attachments,
+                suggested_tags,
             };
             
             (StatusCode::CREATED, Json(memory_response)).into_response()
         },
         Err(err) => {
             error!("Error creating memory: {:?}", err);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "error": {
-                        "message": format!("Failed to create memory: {}", err),
-                        "type": "internal_error"
-                    }
-                }))
-            ).into_response()
+            ApiError::memory("Failed to create memory", &err).into_response()
         }
     }
 }
 
 async fn delete_memory(
     State(state): State<Arc<ServerState>>,
+    caller: Caller,
     axum::extract::Path(id): axum::extract::Path<String>,
 ) -> impl IntoResponse {
     info!("[API] Handling delete_memory request for id: {}", id);
     
-    // Ensure the memory directory exists
-    let base_path = state.memory_store.base_path.clone();
-    info!("[API] Memory base path: {:?}", base_path);
-    
-    if !base_path.exists() {
-        info!("[API] Memory directory does not exist, creating it");
-        if let Err(e) = std::fs::create_dir_all(&base_path) {
-            error!("[API] Failed to create memory directory: {:?}", e);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "error": {
-                        "message": format!("Failed to create memory directory: {}", e),
-                        "type": "internal_error"
-                    }
-                }))
-            ).into_response();
-        }
-        info!("[API] Created memory directory");
+    // Report a missing store instead of recreating it empty
+    if let Err(err) = state.memory_store.check_available() {
+        error!("[API] {}", err);
+        return ApiError::memory("Cannot access memories", &err).into_response();
+    }
+    
+    if let Err(err) = state.memory_store.authorize(&id, caller.principal(), Permission::Write) {
+        return ApiError::memory("Failed to delete memory", &err).into_response();
     }
     
     info!("[API] Calling memory_store.delete() for id: {}", id);
@@ -525,23 +916,7 @@ async fn delete_memory(
         Ok(_) => StatusCode::NO_CONTENT.into_response(),
         Err(err) => {
             error!("Error deleting memory {}: {:?}", id, err);
-            let status = match err {
-                crate::memory::MemoryError::NotFound(_) => StatusCode::NOT_FOUND,
-                _ => StatusCode::INTERNAL_SERVER_ERROR,
-            };
-            
-            (
-                status,
-                Json(serde_json::json!({
-                    "error": {
-                        "message": format!("Failed to delete memory: {}", err),
-                        "type": match err {
-                            crate::memory::MemoryError::NotFound(_) => "not_found",
-                            _ => "internal_error",
-                        }
-                    }
-                }))
-            ).into_response()
+            ApiError::memory("Failed to delete memory", &err).into_response()
         }
     }
 }
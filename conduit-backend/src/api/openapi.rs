@@ -0,0 +1,270 @@
+use std::sync::Arc;
+use axum::{
+    response::{Html, IntoResponse},
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use utoipa::openapi::schema::{ObjectBuilder, OneOfBuilder, Ref, Schema, SchemaType, Type};
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityRequirement, SecurityScheme};
+use utoipa::openapi::RefOr;
+use utoipa::{Modify, OpenApi};
+
+use crate::rag::RagOptions;
+use super::state::ServerState;
+use super::{accounts, activity, analytics, auto_tag, batch, cleanup, connectors, conversations, export, import, inbox, indexes, lock, openai, scheduler, server, sessions, setup, summarize, sync, tags, tokens, usage, webhooks, workspaces};
+
+/// Swagger UI release loaded by the docs page
+const SWAGGER_UI_VERSION: &str = "5.17.14";
+
+/// Settings for the API description routes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DocsConfig {
+    /// Serve an interactive Swagger UI at `/api/docs`; the page loads its
+    /// scripts from a CDN, so it is off by default
+    pub swagger_ui: bool,
+}
+
+impl DocsConfig {
+    /// Override settings with `CONDUIT_SWAGGER_UI`, if set
+    pub fn apply_env(&mut self) {
+        if let Ok(enabled) = std::env::var("CONDUIT_SWAGGER_UI") {
+            self.swagger_ui = matches!(enabled.to_lowercase().as_str(), "1" | "true" | "yes");
+        }
+    }
+}
+
+/// OpenAPI description of the memory API and the OpenAI-compatible routes
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Conduit API",
+        description = "Memory management and OpenAI-compatible endpoints. \
+            `/api` routes report errors as plain text; `/v1` routes use the OpenAI error shape.",
+    ),
+    paths(
+        server::list_memories_handler,
+        server::create_memory_handler,
+        server::get_memory_handler,
+        server::delete_memory_handler,
+        server::search_memories_handler,
+        server::list_unread_memories_handler,
+        server::mark_memory_viewed_handler,
+        server::rename_memory_handler,
+        server::memory_git_log_handler,
+        server::revert_memory_handler,
+        server::get_memory_acl_handler,
+        server::share_memory_handler,
+        server::set_importance_handler,
+        server::memory_links_handler,
+        server::memory_backlinks_handler,
+        server::related_memories_handler,
+        server::list_attachments_handler,
+        server::upload_attachments_handler,
+        server::download_attachment_handler,
+        server::remove_attachment_handler,
+        server::list_references_handler,
+        server::set_references_handler,
+        server::add_reference_handler,
+        server::remove_reference_handler,
+        server::memory_events_handler,
+        server::graph_handler,
+        server::list_collections_handler,
+        server::create_collection_handler,
+        server::get_collection_handler,
+        server::update_collection_handler,
+        server::delete_collection_handler,
+        server::add_to_collection_handler,
+        server::remove_from_collection_handler,
+        server::list_trash_handler,
+        server::restore_trash_handler,
+        server::purge_trash_handler,
+        server::diagnostics_handler,
+        server::info_handler,
+        server::collect_garbage_handler,
+        cleanup::plan_cleanup_handler,
+        batch::apply_batch_handler,
+        export::export_zip_handler,
+        export::export_json_handler,
+        export::list_profiles_handler,
+        export::run_profile_handler,
+        import::import_handler,
+        import::import_json_handler,
+        tags::list_tag_aliases_handler,
+        tags::set_tag_alias_handler,
+        tags::remove_tag_alias_handler,
+        tags::apply_tag_aliases_handler,
+        auto_tag::auto_tag_memory_handler,
+        auto_tag::auto_tag_store_handler,
+        summarize::summarize_memory_handler,
+        summarize::summarize_store_handler,
+        webhooks::list_webhooks_handler,
+        webhooks::create_webhook_handler,
+        webhooks::delete_webhook_handler,
+        webhooks::list_deliveries_handler,
+        connectors::list_connectors_handler,
+        connectors::list_connector_deliveries_handler,
+        inbox::receive_hook_handler,
+        scheduler::list_tasks_handler,
+        scheduler::list_runs_handler,
+        scheduler::run_task_handler,
+        activity::activity_handler,
+        sync::sync_handler,
+        sync::sync_status_handler,
+        sync::list_conflicts_handler,
+        sync::get_conflict_handler,
+        sync::resolve_conflict_handler,
+        lock::lock_status_handler,
+        lock::lock_handler,
+        tokens::list_tokens_handler,
+        tokens::create_token_handler,
+        tokens::renew_token_handler,
+        tokens::revoke_token_handler,
+        accounts::login_handler,
+        accounts::current_account_handler,
+        accounts::logout_handler,
+        accounts::change_password_handler,
+        accounts::list_accounts_handler,
+        accounts::create_account_handler,
+        accounts::get_account_handler,
+        accounts::update_account_handler,
+        accounts::delete_account_handler,
+        sessions::list_sessions_handler,
+        sessions::get_session_handler,
+        sessions::end_session_handler,
+        sessions::list_session_memories_handler,
+        sessions::create_session_memory_handler,
+        sessions::delete_session_memory_handler,
+        conversations::list_conversations_handler,
+        conversations::create_conversation_handler,
+        conversations::get_conversation_handler,
+        conversations::conversation_to_memory_handler,
+        usage::usage_report_handler,
+        analytics::trends_handler,
+        setup::setup_status_handler,
+        setup::setup_handler,
+        workspaces::list_workspaces_handler,
+        indexes::index_status_handler,
+        openai::list_models_handler,
+        openai::get_model_handler,
+        openai::chat_completions_handler,
+        openai::create_embeddings_handler,
+        openai::list_memories_handler,
+        openai::create_memory_handler,
+        openai::get_memory_handler,
+        openai::delete_memory_handler,
+    ),
+    tags(
+        (name = "memories", description = "Create, read, search and link memories"),
+        (name = "references", description = "Links from memories to tickets, pull requests and documents"),
+        (name = "attachments", description = "Files attached to memories"),
+        (name = "collections", description = "Named groups of memories"),
+        (name = "tags", description = "Tag aliases applied when memories are saved and searched"),
+        (name = "graph", description = "Memories and the links between them"),
+        (name = "trash", description = "Deleted memories"),
+        (name = "maintenance", description = "Store diagnostics, cleanup, backups and imports"),
+        (name = "webhooks", description = "Signed notifications of memory changes"),
+        (name = "connectors", description = "Forwarding new memories to external services"),
+        (name = "inbox", description = "Inbound webhooks that create memories from external events"),
+        (name = "schedule", description = "Tasks run on a cron schedule and their history"),
+        (name = "sync", description = "Syncing a git-mode store with a remote repository, and reviewing conflicts it could not reconcile"),
+        (name = "tokens", description = "Short-lived service tokens for automations"),
+        (name = "accounts", description = "Password logins, sessions and account management of a multi-user server"),
+        (name = "sessions", description = "Scratch memories of agent sessions, dropped when the session ends"),
+        (name = "conversations", description = "Chat sessions kept in the store, which can be saved as memories"),
+        (name = "usage", description = "Tokens used by chat and embedding requests per key and day"),
+        (name = "analytics", description = "Memory count, size and tags of the store per day"),
+        (name = "setup", description = "First-run setup of an empty store"),
+        (name = "workspaces", description = "Named stores hosted by the server, picked per request with the `X-Conduit-Workspace` header or `workspace` query parameter"),
+        (name = "openai", description = "OpenAI-compatible chat, embedding and memory routes"),
+    ),
+    components(schemas(RagOptions)),
+    modifiers(&ApiKeyAuth, &ConduitExtension),
+)]
+pub struct ApiDoc;
+
+// Describe the optional bearer API key; requests without one are accepted
+// unless the server is configured with keys
+struct ApiKeyAuth;
+
+impl Modify for ApiKeyAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme("api_key", SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)));
+        }
+        openapi.security = Some(vec![
+            SecurityRequirement::new("api_key", Vec::<String>::new()),
+            SecurityRequirement::default(),
+        ]);
+    }
+}
+
+// Describe the `conduit` and `conversation_id` extensions of chat requests, which
+// the schema derive leaves out because they are never serialized when forwarding upstream
+struct ConduitExtension;
+
+impl Modify for ConduitExtension {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let Some(components) = openapi.components.as_mut() else {
+            return;
+        };
+        if let Some(RefOr::T(Schema::Object(request))) = components.schemas.remove("ChatCompletionRequest") {
+            let conduit = OneOfBuilder::new()
+                .item(Ref::from_schema_name("RagOptions"))
+                .item(ObjectBuilder::new().schema_type(Type::Null))
+                .description(Some("Conduit-specific retrieval options; never forwarded upstream"))
+                .build();
+            let conversation_id = ObjectBuilder::new()
+                .schema_type(SchemaType::from_iter([Type::String, Type::Null]))
+                .description(Some("Conversation to continue: its history is sent before `messages`, and the messages and the reply are added to it"))
+                .build();
+            let request = ObjectBuilder::from(request)
+                .property("conduit", RefOr::T(Schema::OneOf(conduit)))
+                .property("conversation_id", conversation_id)
+                .build();
+            components.schemas.insert("ChatCompletionRequest".to_string(), request.into());
+        }
+    }
+}
+
+/// Routes serving the API description
+///
+/// `GET /api/openapi.json` is always available; `GET /api/docs` serves
+/// Swagger UI when enabled.
+pub fn router(config: &DocsConfig) -> Router<Arc<ServerState>> {
+    let router = Router::new().route("/api/openapi.json", get(openapi_json_handler));
+    if config.swagger_ui {
+        info!("Swagger UI enabled at /api/docs");
+        router.route("/api/docs", get(swagger_ui_handler))
+    } else {
+        router
+    }
+}
+
+async fn openapi_json_handler() -> impl IntoResponse {
+    Json(ApiDoc::openapi())
+}
+
+async fn swagger_ui_handler() -> impl IntoResponse {
+    Html(format!(
+        r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <title>Conduit API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@{version}/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@{version}/swagger-ui-bundle.js"></script>
+  <script>
+    window.ui = SwaggerUIBundle({{ url: "/api/openapi.json", dom_id: "#swagger-ui" }});
+  </script>
+</body>
+</html>
+"##,
+        version = SWAGGER_UI_VERSION,
+    ))
+}
@@ -0,0 +1,37 @@
+use std::sync::Weak;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use super::state::ServerState;
+
+/// How often collection members are checked against their collection's retention period
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Move members of collections with a retention period to the trash once they expire
+///
+/// Runs until the server state is dropped. The store already checks when it
+/// is opened, so the first check is an interval after the server starts.
+/// Nothing is moved while the store is read-only or the workspace is locked.
+pub fn spawn(state: Weak<ServerState>) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(CHECK_INTERVAL).await;
+            let Some(state) = state.upgrade() else {
+                break;
+            };
+            if state.memory_store.is_read_only() || state.memory_store.workspace_lock().is_locked() {
+                continue;
+            }
+
+            let store = state.memory_store.clone();
+            match tokio::task::spawn_blocking(move || store.expire_collection_members()).await {
+                Ok(Ok(expired)) if !expired.is_empty() => info!("[RETENTION] Moved {} expired collection members to the trash", expired.len()),
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => warn!("[RETENTION] Failed to expire collection members: {}", e),
+                Err(e) => warn!("[RETENTION] Expiry check panicked: {}", e),
+            }
+        }
+
+        info!("[RETENTION] Server stopped, ending collection retention checks");
+    });
+}
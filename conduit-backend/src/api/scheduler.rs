@@ -0,0 +1,561 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, Weak};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+use super::error::{ApiError, OpenAiErrorResponse};
+use super::export::{self, ExportProfiles};
+use super::state::ServerState;
+use super::templates::{self, render, TemplateConfig};
+use super::titles::resolve_title;
+
+/// Number of runs kept in the history when none is configured
+const DEFAULT_HISTORY_SIZE: usize = 200;
+
+/// How far ahead the next run of a schedule is looked for
+const MAX_LOOKAHEAD_DAYS: i64 = 366 * 4;
+
+/// What a scheduled task does
+///
+/// Text settings are templates where `{{date}}`, `{{time}}`, `{{weekday}}`
+/// and `{{week}}` are replaced with the local date, time, day and ISO week of
+/// the run, and `{{date:%Y-%m}}` with the run time in any `strftime` format.
+/// The content of a new memory can also list the results of a saved search
+/// with `{{search:<name>}}`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum TaskAction {
+    /// Create a memory, e.g. a weekly review from a template
+    CreateMemory {
+        title: String,
+        #[serde(default)]
+        content: String,
+        #[serde(default)]
+        tags: Vec<String>,
+        /// User who owns the memory, on servers with users
+        #[serde(default)]
+        owner: Option<String>,
+    },
+    /// Write a markdown copy of every memory into a directory outside the store
+    Export {
+        path: String,
+    },
+    /// Run a named export profile from the `[export]` section
+    ExportProfile {
+        profile: String,
+    },
+}
+
+/// A task run on a cron schedule
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ScheduledTask {
+    pub name: String,
+    /// Five cron fields in local time, `minute hour day-of-month month day-of-week`,
+    /// or `@hourly`, `@daily`, `@weekly`, `@monthly` or `@yearly`
+    pub schedule: String,
+    #[serde(flatten)]
+    pub action: TaskAction,
+}
+
+/// Tasks the backend runs on a schedule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SchedulerConfig {
+    pub tasks: Vec<ScheduledTask>,
+    /// Number of recent runs listed by `/api/schedule/runs`
+    pub history_size: usize,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            tasks: Vec::new(),
+            history_size: DEFAULT_HISTORY_SIZE,
+        }
+    }
+}
+
+// A set of allowed values for one cron field, as a bit mask
+#[derive(Debug, Clone, Copy)]
+struct Field {
+    mask: u64,
+    /// Whether the field starts with `*`, which matters for the day fields
+    any: bool,
+}
+
+impl Field {
+    fn parse(field: &str, min: u32, max: u32) -> Result<Self, String> {
+        let mut mask = 0u64;
+        for part in field.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => (range, step.parse::<u32>().map_err(|_| format!("invalid step in {:?}", part))?),
+                None => (part, 1),
+            };
+            if step == 0 {
+                return Err(format!("invalid step in {:?}", part));
+            }
+            let (start, end) = match range {
+                "*" => (min, max),
+                range => match range.split_once('-') {
+                    Some((start, end)) => (parse_value(start, part)?, parse_value(end, part)?),
+                    None => {
+                        let value = parse_value(range, part)?;
+                        // `5/15` means from 5 to the end in steps of 15
+                        (value, if part.contains('/') { max } else { value })
+                    }
+                },
+            };
+            if start < min || end > max || start > end {
+                return Err(format!("{:?} is outside {}-{}", part, min, max));
+            }
+            for value in (start..=end).step_by(step as usize) {
+                mask |= 1 << value;
+            }
+        }
+        Ok(Self { mask, any: field.starts_with('*') })
+    }
+
+    fn contains(&self, value: u32) -> bool {
+        self.mask & (1 << value) != 0
+    }
+}
+
+fn parse_value(value: &str, part: &str) -> Result<u32, String> {
+    value.parse().map_err(|_| format!("invalid value in {:?}", part))
+}
+
+/// A parsed cron schedule
+#[derive(Debug, Clone, Copy)]
+pub struct CronSchedule {
+    minutes: Field,
+    hours: Field,
+    days: Field,
+    months: Field,
+    weekdays: Field,
+}
+
+impl FromStr for CronSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expression = match s.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            expression => expression,
+        };
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields.as_slice() else {
+            return Err(format!("expected 5 fields in schedule {:?}", s));
+        };
+
+        let mut weekdays = Field::parse(weekdays, 0, 7)?;
+        // Both 0 and 7 are Sunday
+        if weekdays.contains(7) {
+            weekdays.mask |= 1;
+        }
+        Ok(Self {
+            minutes: Field::parse(minutes, 0, 59)?,
+            hours: Field::parse(hours, 0, 23)?,
+            days: Field::parse(days, 1, 31)?,
+            months: Field::parse(months, 1, 12)?,
+            weekdays,
+        })
+    }
+}
+
+impl CronSchedule {
+    // As in cron, a restricted day of month and day of week match either one
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day = self.days.contains(date.day());
+        let weekday = self.weekdays.contains(date.weekday().num_days_from_sunday());
+        match (self.days.any, self.weekdays.any) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+
+    /// The first time after `after` that matches, in local time
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        let start = after.naive_local();
+        let limit = start + Duration::days(MAX_LOOKAHEAD_DAYS);
+        let mut time = start.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+
+        while time < limit {
+            if !self.months.contains(time.month()) {
+                let (year, month) = if time.month() == 12 { (time.year() + 1, 1) } else { (time.year(), time.month() + 1) };
+                time = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !self.matches_day(time.date()) {
+                time = (time.date() + Duration::days(1)).and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !self.hours.contains(time.hour()) {
+                time = time.with_minute(0)? + Duration::hours(1);
+                continue;
+            }
+            if !self.minutes.contains(time.minute()) {
+                time += Duration::minutes(1);
+                continue;
+            }
+
+            // Times skipped by a daylight saving change never happen
+            match Local.from_local_datetime(&time).earliest() {
+                Some(local) => return Some(local),
+                None => time += Duration::minutes(1),
+            }
+        }
+        None
+    }
+}
+
+impl TaskAction {
+    fn templates(&self) -> Vec<&str> {
+        match self {
+            Self::CreateMemory { title, content, tags, .. } => {
+                let mut templates = vec![title.as_str(), content.as_str()];
+                templates.extend(tags.iter().map(String::as_str));
+                templates
+            }
+            Self::Export { path } => vec![path.as_str()],
+            Self::ExportProfile { .. } => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// One run of a scheduled task
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TaskRun {
+    pub id: String,
+    pub task: String,
+    /// Whether the run was started through the API instead of the schedule
+    pub manual: bool,
+    pub status: RunStatus,
+    /// The created memory's ID or where the export was written
+    pub output: Option<String>,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// A scheduled task with when it runs next and how it last went
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TaskStatus {
+    #[serde(flatten)]
+    pub task: ScheduledTask,
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub last_run: Option<TaskRun>,
+}
+
+/// Filters for the run history
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RunQuery {
+    /// Only runs of this task
+    pub task: Option<String>,
+    /// Only runs with this status
+    pub status: Option<RunStatus>,
+}
+
+/// The scheduled tasks and the history of their runs
+pub struct Scheduler {
+    config: SchedulerConfig,
+    schedules: Vec<CronSchedule>,
+    runs: Mutex<VecDeque<TaskRun>>,
+}
+
+impl Scheduler {
+    /// Check the schedules and templates of the configured tasks
+    pub fn new(config: SchedulerConfig, templates: &TemplateConfig) -> Result<Self, String> {
+        let mut schedules = Vec::new();
+        for (index, task) in config.tasks.iter().enumerate() {
+            if task.name.is_empty() || config.tasks[..index].iter().any(|other| other.name == task.name) {
+                return Err(format!("Scheduled task names must be unique and not empty: {:?}", task.name));
+            }
+            schedules.push(task.schedule.parse::<CronSchedule>().map_err(|e| format!("Scheduled task {}: {}", task.name, e))?);
+            for template in task.action.templates() {
+                templates::check(template, templates).map_err(|e| format!("Scheduled task {}: {}", task.name, e))?;
+            }
+        }
+
+        Ok(Self {
+            config,
+            schedules,
+            runs: Mutex::new(VecDeque::new()),
+        })
+    }
+
+    /// Check that every task running an export profile names one that exists
+    pub fn check_export_profiles(&self, profiles: &ExportProfiles) -> Result<(), String> {
+        for task in &self.config.tasks {
+            if let TaskAction::ExportProfile { profile } = &task.action {
+                if profiles.get(profile).is_none() {
+                    return Err(format!("Scheduled task {}: no export profile named {:?}", task.name, profile));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Every task with its next and last run
+    pub fn list(&self) -> Vec<TaskStatus> {
+        let now = Local::now();
+        let runs = self.runs.lock().unwrap_or_else(|e| e.into_inner());
+        self.config.tasks.iter()
+            .zip(&self.schedules)
+            .map(|(task, schedule)| TaskStatus {
+                task: task.clone(),
+                next_run_at: schedule.next_after(now).map(|next| next.with_timezone(&Utc)),
+                last_run: runs.iter().rev().find(|run| run.task == task.name).cloned(),
+            })
+            .collect()
+    }
+
+    /// Recent runs matching `query`, newest first
+    pub fn runs(&self, query: &RunQuery) -> Vec<TaskRun> {
+        let runs = self.runs.lock().unwrap_or_else(|e| e.into_inner());
+        runs.iter()
+            .rev()
+            .filter(|run| query.task.as_ref().is_none_or(|task| &run.task == task))
+            .filter(|run| query.status.is_none_or(|status| run.status == status))
+            .cloned()
+            .collect()
+    }
+
+    fn task(&self, name: &str) -> Option<&ScheduledTask> {
+        self.config.tasks.iter().find(|task| task.name == name)
+    }
+
+    // Add or replace a run in the history, dropping the oldest once it is full
+    fn record(&self, run: &TaskRun) {
+        let mut runs = self.runs.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(logged) = runs.iter_mut().rev().find(|logged| logged.id == run.id) {
+            *logged = run.clone();
+            return;
+        }
+        while runs.len() >= self.config.history_size.max(1) {
+            runs.pop_front();
+        }
+        runs.push_back(run.clone());
+    }
+}
+
+// Run a task once and record the outcome
+async fn run_task(state: &ServerState, task: &ScheduledTask, manual: bool) -> TaskRun {
+    let mut run = TaskRun {
+        id: ulid::Ulid::new().to_string(),
+        task: task.name.clone(),
+        manual,
+        status: RunStatus::Running,
+        output: None,
+        error: None,
+        started_at: Utc::now(),
+        finished_at: None,
+    };
+    state.scheduler.record(&run);
+
+    let now = Local::now();
+    let result = match &task.action {
+        TaskAction::CreateMemory { title, content, tags, owner } => {
+            // Saved searches list what the owner can see
+            let principal = owner.as_deref().filter(|_| state.auth.is_multi_user()).map(|owner| state.auth.user_principal(owner));
+            match templates::instantiate(state, content, None, principal.as_ref(), now).await {
+                Ok(content) => match resolve_title(state, render(title, now), &content).await {
+                    Ok(title) => {
+                        let tags = tags.iter().map(|tag| render(tag, now)).filter(|tag| !tag.trim().is_empty()).collect();
+                        state.memory_store.new_memory(title, content, tags)
+                            .and_then(|mut memory| {
+                                memory.owner = owner.clone();
+                                state.memory_store.save(&memory).map(|_| memory.id)
+                            })
+                            .map_err(|e| e.to_string())
+                    }
+                    Err(e) => Err(e),
+                },
+                Err(e) => Err(e.to_string()),
+            }
+        }
+        TaskAction::Export { path } => {
+            let store = state.memory_store.clone();
+            let dir = PathBuf::from(render(path, now));
+            tokio::task::spawn_blocking(move || store.export_markdown(&dir))
+                .await
+                .map_err(|e| format!("Export panicked: {}", e))
+                .and_then(|result| result.map_err(|e| e.to_string()))
+                .map(|report| format!("{} ({} memories)", report.path.display(), report.memories))
+        }
+        TaskAction::ExportProfile { profile } => match state.export_profiles.get(profile) {
+            Some((profile, options)) => {
+                let store = state.memory_store.clone();
+                tokio::task::spawn_blocking(move || export::run_profile(&store, &profile, &options))
+                    .await
+                    .map_err(|e| format!("Export panicked: {}", e))
+                    .and_then(|result| result.map_err(|e| e.to_string()))
+                    .map(|report| format!("{} ({} memories)", report.path.display(), report.memories))
+            }
+            None => Err(format!("Export profile not found: {}", profile)),
+        },
+    };
+
+    match result {
+        Ok(output) => {
+            info!("[SCHEDULER] Task {} succeeded: {}", task.name, output);
+            run.status = RunStatus::Succeeded;
+            run.output = Some(output);
+        }
+        Err(e) => {
+            error!("[SCHEDULER] Task {} failed: {}", task.name, e);
+            run.status = RunStatus::Failed;
+            run.error = Some(e);
+        }
+    }
+    run.finished_at = Some(Utc::now());
+    state.scheduler.record(&run);
+    state.activity.record_task(&run);
+    run
+}
+
+/// Run every task on its schedule until the server state is dropped or shuts down
+pub fn spawn(state: &Arc<ServerState>) {
+    for (task, schedule) in state.scheduler.config.tasks.iter().zip(&state.scheduler.schedules) {
+        let weak: Weak<ServerState> = Arc::downgrade(state);
+        let mut shutdown = state.shutdown.clone();
+        let task = task.clone();
+        let schedule = *schedule;
+
+        tokio::spawn(async move {
+            loop {
+                let Some(next) = schedule.next_after(Local::now()) else {
+                    warn!("[SCHEDULER] Task {} has no upcoming run, stopping it", task.name);
+                    break;
+                };
+                let wait = (next - Local::now()).to_std().unwrap_or_default();
+                tokio::select! {
+                    _ = tokio::time::sleep(wait) => {}
+                    _ = shutdown.wait_for(|stopped| *stopped) => break,
+                }
+
+                let Some(state) = weak.upgrade() else {
+                    break;
+                };
+                if state.memory_store.is_read_only() {
+                    warn!("[SCHEDULER] Store is read-only, skipping task {}", task.name);
+                    continue;
+                }
+                if state.memory_store.workspace_lock().is_locked() {
+                    info!("[SCHEDULER] Workspace is locked, skipping task {}", task.name);
+                    continue;
+                }
+                run_task(&state, &task, false).await;
+            }
+            info!("[SCHEDULER] Server stopped, ending task {}", task.name);
+        });
+    }
+}
+
+pub fn router() -> Router<Arc<ServerState>> {
+    Router::new()
+        .route("/api/schedule", get(list_tasks_handler))
+        .route("/api/schedule/runs", get(list_runs_handler))
+        .route("/api/schedule/:name/run", post(run_task_handler))
+}
+
+async fn list_tasks(
+    State(state): State<Arc<ServerState>>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling list_scheduled_tasks request");
+    Json(state.scheduler.list())
+}
+
+async fn list_runs(
+    State(state): State<Arc<ServerState>>,
+    Query(query): Query<RunQuery>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling list_task_runs request");
+    Json(state.scheduler.runs(&query))
+}
+
+async fn run_now(
+    State(state): State<Arc<ServerState>>,
+    Path(name): Path<String>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling run_task request for task: {}", name);
+
+    let Some(task) = state.scheduler.task(&name).cloned() else {
+        return ApiError::not_found(format!("Scheduled task not found: {}", name)).into_response();
+    };
+    if let Err(err) = state.memory_store.check_writable() {
+        return ApiError::permission(err.to_string()).into_response();
+    }
+
+    let run = run_task(&state, &task, true).await;
+    let status = match run.status {
+        RunStatus::Failed => StatusCode::INTERNAL_SERVER_ERROR,
+        _ => StatusCode::OK,
+    };
+    (status, Json(run)).into_response()
+}
+
+#[utoipa::path(
+    get, path = "/api/schedule", tag = "schedule", operation_id = "list_scheduled_tasks",
+    responses((status = 200, description = "Scheduled tasks with their next and last run", body = [TaskStatus]))
+)]
+#[axum::debug_handler]
+async fn list_tasks_handler(
+    state: State<Arc<ServerState>>,
+) -> impl IntoResponse {
+    list_tasks(state).await
+}
+
+#[utoipa::path(
+    get, path = "/api/schedule/runs", tag = "schedule", operation_id = "list_task_runs",
+    params(RunQuery),
+    responses((status = 200, description = "Recent runs, newest first", body = [TaskRun]))
+)]
+#[axum::debug_handler]
+async fn list_runs_handler(
+    state: State<Arc<ServerState>>,
+    query: Query<RunQuery>,
+) -> impl IntoResponse {
+    list_runs(state, query).await
+}
+
+#[utoipa::path(
+    post, path = "/api/schedule/{name}/run", tag = "schedule", operation_id = "run_task",
+    params(("name" = String, Path, description = "Task name from the config file")),
+    responses(
+        (status = 200, description = "The task ran successfully", body = TaskRun),
+        (status = 403, description = "The store is read-only", body = OpenAiErrorResponse),
+        (status = 404, description = "No task with this name", body = OpenAiErrorResponse),
+        (status = 500, description = "The task failed", body = TaskRun),
+    )
+)]
+#[axum::debug_handler]
+async fn run_task_handler(
+    state: State<Arc<ServerState>>,
+    path: Path<String>,
+) -> impl IntoResponse {
+    run_now(state, path).await
+}
@@ -1,99 +1,530 @@
+use std::collections::BTreeMap;
 use std::net::SocketAddr;
-use std::sync::{Arc, Mutex};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{DefaultBodyLimit, Multipart, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware,
+    response::{sse::{Event, KeepAlive, Sse}, IntoResponse},
     routing::{get, post},
     Json, Router,
 };
-use tower_http::cors::{Any, CorsLayer};
-use tokio::sync::oneshot;
-use tracing::{info, error};
+use futures::stream::{self, StreamExt};
+use tower_http::timeout::TimeoutLayer;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{oneshot, watch};
+use tokio::task::JoinHandle;
+use conduit_types::requests::{
+    CollectionMembersRequest, CreateCollectionRequest, CreateMemoryRequest, RenameMemoryRequest,
+    RevertMemoryRequest, SearchMemoriesRequest, SetImportanceRequest, ShareMemoryRequest,
+};
+use tracing::{info, error, warn};
+use utoipa::{IntoParams, ToSchema};
 
-use crate::memory::{Memory, MemoryStore};
+use crate::config::ConduitConfig;
+use crate::memory::{
+    filter_visible, Attachment, Backlinks, Collection, CollectionUpdate, Election, EmbeddingStatus, GcReport, GitCommit, Graph, GraphOptions, ImportanceConfig, ListMemoriesQuery, ListOptions,
+    Memory, MemoryAccess, MemoryAcl, MemoryError, MemoryEvent, MemoryStore, OutgoingLinks, Permission, PreprocessConfig, QueryMatcher, Reference, RelatedConfig, RelatedMemory,
+    RelatedQuery, RenameResult, SearchQuery, reachable_addr,
+    SearchResult, StorageBackend, StoreDiagnostics, TextField, TrashedMemory,
+    validate_importance, validate_reference,
+};
+use crate::provider::{EmbeddingConfig, ProviderConfig, ProviderHealth, RegisteredModel};
+use crate::rag::RagConfig;
+use super::activity::{self, ActivityConfig};
+use super::analytics::{self, AnalyticsConfig};
+use super::attachment_text::{self, AttachmentTextConfig};
+use super::auto_tag::{self, tag_new_memory, AutoTagConfig};
+use super::summarize::{self, SummarizeConfig};
+use super::chat_tools::ChatToolsConfig;
+use super::capture::CaptureConfig;
+use super::batch;
+use super::setup;
+use super::cleanup::{self, CleanupConfig};
+use super::accounts::{self, AccountsConfig};
+use super::auth::{require_api_key, AuthConfig, Caller};
+use super::cache;
+use super::cancel::run_cancellable;
+use super::connectors::{self, ConnectorsConfig};
+use super::conversations;
+use super::cors::CorsConfig;
+use super::error::{self, ApiError, OpenAiErrorResponse};
+use super::export::{self, ExportConfig};
+use super::fields::{self, FieldsQuery};
+use super::import;
+use super::indexes;
+use super::inbox::{self, InboxConfig};
+use super::instance::{self, ConflictPolicy, InstanceConfig};
+use super::interaction_log::InteractionLogConfig;
+use super::lock::{self, require_unlocked};
 use super::openai;
+use super::openapi::{self, DocsConfig};
+use super::scheduler::{self, SchedulerConfig};
+use super::sessions::{self, SessionConfig};
 use super::state::ServerState;
+use super::titles::{resolve_title, TitleConfig};
+use super::tokens;
+use super::usage;
+use super::sync::{self, SyncConfig};
+use super::tags;
+use super::templates::{self, TemplateConfig};
+use super::validation::{ValidJson, ValidationConfig};
+use super::webhooks::{self, WebhookConfig};
+use super::workspaces::{self, WorkspaceRegistry};
+
+/// Largest request body accepted by the attachment upload route
+const ATTACHMENT_BODY_LIMIT: usize = 25 * 1024 * 1024;
+
+/// Settings for the API server beyond the memory store and bind address
+#[derive(Debug, Clone, Default)]
+pub struct ServerOptions {
+    /// Upstream LLM provider; `None` runs chat completions in offline mode
+    pub provider: Option<ProviderConfig>,
+    /// Further providers, by the name models refer to them with
+    pub providers: BTreeMap<String, ProviderConfig>,
+    /// Models clients can ask for, and the provider serving each
+    pub models: Vec<RegisteredModel>,
+    /// Retrieval settings for injecting memories into chat context
+    pub rag: RagConfig,
+    /// Which provider serves embedding requests
+    pub embeddings: EmbeddingConfig,
+    /// Requests running longer than this many seconds are aborted with `408 Request Timeout`;
+    /// `None` disables the limit
+    pub request_timeout_secs: Option<u64>,
+    /// Maximum number of chat and embedding requests handled at once; `None` for no limit
+    pub llm_max_concurrency: Option<usize>,
+    /// How long a chat or embedding request waits for a free slot before getting `429`
+    pub llm_queue_timeout_ms: u64,
+    /// Where to log chat and embedding requests; `None` disables the log
+    pub interaction_log: Option<InteractionLogConfig>,
+    /// How memories created without a title get one
+    pub titles: TitleConfig,
+    /// How new memories are filled in from their text
+    pub capture: CaptureConfig,
+    /// API keys required by every route; none leaves the API open
+    pub auth: AuthConfig,
+    /// Accounts logging in with a password, each with a store of their own
+    pub accounts: AccountsConfig,
+    /// Origins allowed to call the API from a browser
+    pub cors: CorsConfig,
+    /// Whether to serve Swagger UI next to the OpenAPI description
+    pub docs: DocsConfig,
+    /// Webhooks notified when memories change
+    pub webhooks: WebhookConfig,
+    /// What to do when another Conduit process already serves the store
+    pub instance: InstanceConfig,
+    /// Remote repository a git-mode store syncs with
+    pub sync: SyncConfig,
+    /// Inbound webhooks that create memories from external events
+    pub inbox: InboxConfig,
+    /// External services new memories are forwarded to
+    pub connectors: ConnectorsConfig,
+    /// Tasks run on a cron schedule
+    pub scheduler: SchedulerConfig,
+    /// Named export profiles
+    pub export: ExportConfig,
+    /// How attachment text is extracted for search
+    pub attachment_text: AttachmentTextConfig,
+    /// Lifetimes and limits of session-scoped scratch memories
+    pub sessions: SessionConfig,
+    /// Model and similarity threshold used when planning a cleanup
+    pub cleanup: CleanupConfig,
+    /// How memories lose importance when left untouched
+    pub importance: ImportanceConfig,
+    /// How memories related to another are scored
+    pub related: RelatedConfig,
+    /// How much recorded activity is kept
+    pub activity: ActivityConfig,
+    /// Whether new memories are tagged by the upstream model
+    pub auto_tag: AutoTagConfig,
+    /// Model for summaries, and when listings return them in place of content
+    pub summarize: SummarizeConfig,
+    /// Whether chat models can create, search and update memories with tools
+    pub chat_tools: ChatToolsConfig,
+    /// Saved searches memory templates can list
+    pub templates: TemplateConfig,
+    /// Limits on titles, content and tags in request bodies
+    pub validation: ValidationConfig,
+    /// How often the store is measured for the trends report
+    pub analytics: AnalyticsConfig,
+    /// Name of the workspace served; `None` for the `[storage]` store
+    pub workspace: Option<String>,
+    /// Store folder of every workspace, by name, which requests pick with `?workspace=`
+    pub workspaces: BTreeMap<String, PathBuf>,
+    /// Content preprocessing of every workspace, by name
+    pub workspace_preprocess: BTreeMap<String, PreprocessConfig>,
+}
+
+impl ServerOptions {
+    /// Read server settings from `CONDUIT_*` environment variables
+    ///
+    /// `CONDUIT_REQUEST_TIMEOUT` sets the request timeout in seconds; `0` disables it.
+    /// `CONDUIT_LLM_MAX_CONCURRENCY` caps concurrent chat and embedding requests, which
+    /// queue for up to `CONDUIT_LLM_QUEUE_TIMEOUT_MS` before being rejected.
+    /// `CONDUIT_INTERACTION_LOG` enables the interaction log, see [`InteractionLogConfig::from_env`].
+    /// See [`ConduitConfig::apply_env`] for the rest.
+    pub fn from_env() -> Self {
+        ConduitConfig::from_env().server_options()
+    }
+}
+
+/// How far a started server has come
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "state", content = "error", rename_all = "snake_case")]
+pub enum Readiness {
+    /// Bound to its address and loading the store
+    Starting,
+    /// Serving requests
+    Ready,
+    /// Stopped before it could serve, for this reason
+    Failed(String),
+}
+
+/// Handle to a running API server
+///
+/// Dropping the handle detaches the server, which keeps running in the background.
+/// Call [`ServerHandle::shutdown`] to stop it.
+pub struct ServerHandle {
+    shutdown_tx: Option<oneshot::Sender<()>>,
+    task: JoinHandle<()>,
+    readiness: watch::Receiver<Readiness>,
+    local_addr: SocketAddr,
+}
+
+impl ServerHandle {
+    /// Signal the server to shut down gracefully
+    ///
+    /// In-flight requests are allowed to complete. Use [`ServerHandle::wait`] to wait
+    /// for the shutdown to finish.
+    pub fn shutdown(&mut self) {
+        if let Some(shutdown_tx) = self.shutdown_tx.take() {
+            info!("[SERVER] Sending shutdown signal");
+            let _ = shutdown_tx.send(());
+        }
+    }
+    
+    /// Address the server is bound to, with the port the system picked when started on port 0
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+    
+    /// What the server tells clients about itself at `GET /api/info`
+    pub fn info(&self) -> ServerInfo {
+        ServerInfo::new(Some(self.local_addr))
+    }
+    
+    /// How far the server has come in starting
+    pub fn readiness(&self) -> Readiness {
+        self.readiness.borrow().clone()
+    }
+    
+    /// Wait until the server has loaded the store and serves requests
+    ///
+    /// The address is bound once [`start_server`] returns, but the store is
+    /// read in the background. Fails if the server stopped before it was ready.
+    pub async fn ready(&mut self) -> Result<(), String> {
+        let readiness = self.readiness
+            .wait_for(|readiness| *readiness != Readiness::Starting)
+            .await
+            .map(|readiness| readiness.clone())
+            .map_err(|_| "API server stopped before it was ready".to_string())?;
+        match readiness {
+            Readiness::Failed(e) => Err(e),
+            _ => Ok(()),
+        }
+    }
+    
+    /// Whether the server task has finished
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+    
+    /// Wait for the server task to finish
+    pub async fn wait(self) -> Result<(), String> {
+        self.task.await.map_err(|e| format!("Server task failed: {}", e))
+    }
+}
+
+/// The memory and OpenAI-compatible routes of a Conduit server, for mounting in
+/// another axum application
+///
+/// Requests are checked against the configured API keys, and inbound webhooks
+/// are served, as by [`start_server`]. The request timeout, CORS and the API
+/// description are left to the application's own middleware. Routes keep their
+/// `/api` and `/v1` prefixes, so `Router::new().nest("/conduit", router(state))`
+/// serves `/conduit/api/memories`.
+///
+/// Unlike [`start_server`], this does not take the store's writer lock, so the
+/// store should not be served by another Conduit process at the same time.
+pub fn router(state: Arc<ServerState>) -> Router {
+    let mut app = Router::new()
+        // Memory API routes
+        .route(
+            "/api/memories",
+            get(list_memories_handler).layer(middleware::from_fn(fields::shape_fields)).post(create_memory_handler),
+        )
+        .route(
+            "/api/memories/:id",
+            get(get_memory_handler).layer(middleware::from_fn(fields::shape_fields)).delete(delete_memory_handler),
+        )
+        .route("/api/memories/search", post(search_memories_handler).layer(middleware::from_fn(fields::shape_fields)))
+        .route("/api/memories/unread", get(list_unread_memories_handler))
+        .route("/api/memories/:id/viewed", post(mark_memory_viewed_handler))
+        .route("/api/memories/:id/rename", post(rename_memory_handler))
+        .route("/api/memories/:id/git-log", get(memory_git_log_handler))
+        .route("/api/memories/:id/revert", post(revert_memory_handler))
+        .route("/api/memories/:id/acl", get(get_memory_acl_handler).put(share_memory_handler))
+        .route("/api/memories/:id/importance", axum::routing::put(set_importance_handler))
+        .route("/api/memories/:id/links", get(memory_links_handler))
+        .route("/api/memories/:id/backlinks", get(memory_backlinks_handler))
+        .route("/api/memories/:id/related", get(related_memories_handler))
+        .route(
+            "/api/memories/:id/attachments",
+            get(list_attachments_handler)
+                .post(upload_attachments_handler)
+                .layer(DefaultBodyLimit::max(ATTACHMENT_BODY_LIMIT)),
+        )
+        .route(
+            "/api/memories/:id/attachments/:name",
+            get(download_attachment_handler).delete(remove_attachment_handler),
+        )
+        .route(
+            "/api/memories/:id/references",
+            get(list_references_handler)
+                .put(set_references_handler)
+                .post(add_reference_handler),
+        )
+        .route(
+            "/api/memories/:id/references/:index",
+            axum::routing::delete(remove_reference_handler),
+        )
+        .route("/api/events", get(memory_events_handler))
+        .route("/api/graph", get(graph_handler))
+        .route("/api/collections", get(list_collections_handler).post(create_collection_handler))
+        .route(
+            "/api/collections/:id",
+            get(get_collection_handler)
+                .put(update_collection_handler)
+                .delete(delete_collection_handler),
+        )
+        .route("/api/collections/:id/memories", post(add_to_collection_handler))
+        .route(
+            "/api/collections/:id/memories/:memory_id",
+            axum::routing::delete(remove_from_collection_handler),
+        )
+        .route("/api/trash", get(list_trash_handler))
+        .route("/api/trash/:id", axum::routing::delete(purge_trash_handler))
+        .route("/api/trash/:id/restore", post(restore_trash_handler))
+        .route("/api/diagnostics", get(diagnostics_handler))
+        .route("/api/info", get(info_handler))
+        .route("/api/maintenance/gc", post(collect_garbage_handler))
+        .merge(batch::router())
+        .merge(cleanup::router())
+        .merge(export::router())
+        .merge(import::router())
+        .merge(tags::router())
+        .merge(auto_tag::router())
+        .merge(summarize::router())
+        .merge(webhooks::router())
+        .merge(connectors::router())
+        .merge(sync::router())
+        .merge(scheduler::router())
+        .merge(activity::router())
+        .merge(lock::router())
+        .merge(tokens::router())
+        .merge(sessions::router())
+        .merge(conversations::router())
+        .merge(usage::router())
+        .merge(analytics::router())
+        .merge(setup::router())
+        .merge(workspaces::router())
+        .merge(indexes::router())
+        .merge(accounts::router())
+        
+        // OpenAI-compatible API routes
+        .nest("/v1", openai::router());
+    
+    // Require an API key when any are configured, or a session with accounts
+    if state.auth.is_enabled() || state.accounts.is_some() {
+        info!("API key authentication enabled with {} key(s)", state.auth.api_keys.len());
+        app = app.layer(middleware::from_fn_with_state(state.clone(), require_api_key));
+    }
+    
+    // Logging in is how accounts get the session they need for everything else
+    if state.accounts.is_some() {
+        app = app.merge(accounts::login_router());
+    }
+    
+    // Inbound webhooks check their own secrets, since senders like Zapier only know those
+    if !state.inbox.is_empty() {
+        app = app.merge(inbox::router());
+    }
+    
+    // A locked workspace answers nothing but the lock routes
+    // Every error leaves in the same JSON shape, with the request's ID
+    app.layer(middleware::from_fn_with_state(state.clone(), require_unlocked))
+        .layer(middleware::from_fn(error::error_envelope))
+        .with_state(state)
+}
 
 pub async fn start_server(
     memory_store: Arc<MemoryStore>,
     addr: SocketAddr,
-) -> Result<oneshot::Sender<()>, String> {
+    options: ServerOptions,
+) -> Result<ServerHandle, String> {
     info!("Starting API server on {}", addr);
     
     // Create a channel for shutdown signal
     let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
     info!("Created shutdown channel");
     
-    // Ensure the memory directory exists
-    let base_path = memory_store.base_path.clone();
-    info!("Memory base path: {:?}", base_path);
+    // Serve even without the store; requests get 503 until it is back
+    info!("Memory base path: {:?}", memory_store.base_path);
+    if !memory_store.is_available() {
+        warn!("Memory store is unavailable: {:?}", memory_store.base_path);
+    }
     
-    if !base_path.exists() {
-        info!("Memory directory does not exist, creating it");
-        match std::fs::create_dir_all(&base_path) {
-            Ok(_) => info!("Successfully created memory directory"),
-            Err(e) => {
-                error!("Failed to create memory directory: {:?}", e);
-                return Err(format!("Failed to create memory directory: {}", e));
+    // Only one process may write to a store, so two app launches never index it at once
+    let mut writer = None;
+    if memory_store.is_available() && !memory_store.is_read_only() {
+        let store = memory_store.clone();
+        let election = tokio::task::spawn_blocking(move || store.elect_writer())
+            .await
+            .map_err(|e| format!("Writer election failed: {}", e))?;
+        match election {
+            Ok(Election::Won(guard)) => writer = Some(guard),
+            Ok(Election::Lost(owner)) => {
+                let description = match &owner {
+                    Some(owner) => match owner.addr {
+                        Some(owner_addr) => format!("Conduit process {} at http://{}", owner.pid, owner_addr),
+                        None => format!("Conduit process {}", owner.pid),
+                    },
+                    None => "another Conduit process".to_string(),
+                };
+                match options.instance.on_conflict {
+                    ConflictPolicy::Refuse => {
+                        return Err(format!(
+                            "Memory store {} is already served by {}; stop it, or set `on_conflict` to `proxy` or `read_only`",
+                            memory_store.base_path.display(), description,
+                        ));
+                    }
+                    ConflictPolicy::ReadOnly => {
+                        warn!("Memory store is already served by {}, serving it read-only", description);
+                        memory_store.set_read_only(true);
+                    }
+                    ConflictPolicy::Proxy => {
+                        let target = owner.as_ref()
+                            .and_then(|owner| owner.connect_addr())
+                            .ok_or_else(|| format!("Memory store is already served by {}, which has not recorded its address to proxy to", description))?;
+                        return start_proxy(addr, target).await;
+                    }
+                }
             }
+            Err(e) => warn!("Could not elect a writer for the memory store, continuing without the lock: {}", e),
         }
-    } else {
-        info!("Memory directory already exists");
+    }
+    if memory_store.is_read_only() {
+        info!("Memory store is read-only, changes are rejected with 403");
     }
     
-    // Create shared state
-    info!("Creating shared server state");
-    let state = Arc::new(ServerState {
-        memory_store,
-        shutdown_tx: Mutex::new(Some(shutdown_tx)),
-    });
-    info!("Server state created successfully");
+    // Other workspaces get their own store, indexes and background tasks
+    let workspaces = Arc::new(WorkspaceRegistry::open(&memory_store, &options, router).await?);
+    let state = ServerState::start(memory_store, &options)?;
     
-    // Set up CORS
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
+    // The API description holds no memories, so it stays reachable without a key
+    let mut app = router(state.clone())
+        .merge(openapi::router(&options.docs).with_state(state.clone()))
+        .layer(middleware::from_fn_with_state(workspaces.clone(), workspaces::select_workspace))
+        .layer(middleware::from_fn(error::error_envelope));
     
-    // Create router
-    let app = Router::new()
-        // Memory API routes
-        .route("/api/memories", get(list_memories_handler).post(create_memory_handler))
-        .route("/api/memories/:id", get(get_memory_handler).delete(delete_memory_handler))
-        .route("/api/memories/search", post(search_memories_handler))
-        
-        // OpenAI-compatible API routes
-        .nest("/v1", openai::router())
-        
-        // Add CORS and state
-        .layer(cors)
-        .with_state(state.clone());
+    // Abort slow requests; dropping the handler also cancels its store scans and upstream calls
+    if let Some(secs) = options.request_timeout_secs {
+        info!("Request timeout set to {} seconds", secs);
+        app = app.layer(TimeoutLayer::new(Duration::from_secs(secs)));
+    }
     
-    // Start the server
-    info!("Starting API server on {}", addr);
+    // Add CORS, unless disabled for embedded use
+    match options.cors.layer() {
+        Some(cors) => {
+            info!("CORS allowed origins: {}", options.cors.origins.join(", "));
+            app = app.layer(cors);
+        }
+        None => info!("CORS disabled"),
+    }
+    
+    // Bind before spawning so that errors like a port already in use reach the caller
+    info!("[SERVER] Binding TCP listener to {}", addr);
+    let listener = match tokio::net::TcpListener::bind(addr).await {
+        Ok(l) => {
+            info!("[SERVER] TCP listener bound successfully");
+            l
+        },
+        Err(e) => {
+            error!("[SERVER] Failed to bind TCP listener: {:?}", e);
+            return Err(format!("Failed to bind to {}: {}", addr, e));
+        }
+    };
+    
+    // With port 0 the system picks the port, so report the one actually bound
+    let local_addr = listener.local_addr().unwrap_or(addr);
+    if local_addr != addr {
+        info!("[SERVER] Bound to {}", local_addr);
+    }
+    let _ = state.listen_addr.set(local_addr);
+    for workspace in workspaces.states() {
+        let _ = workspace.listen_addr.set(local_addr);
+    }
+    
+    // Let other instances find this one
+    if let Some(writer) = &mut writer {
+        if let Err(e) = writer.record_addr(local_addr) {
+            warn!("[SERVER] Failed to record the server address in the store: {}", e);
+        }
+    }
     
     // Spawn the server task
-    tokio::spawn(async move {
-        info!("[SERVER] Binding TCP listener to {}", addr);
-        let listener = match tokio::net::TcpListener::bind(addr).await {
-            Ok(l) => {
-                info!("[SERVER] TCP listener bound successfully");
-                l
-            },
+    let (ready_tx, readiness) = watch::channel(Readiness::Starting);
+    let task = tokio::spawn(async move {
+        // Read the store and build its link index before reporting ready, so the first requests are not slow
+        let store = state.memory_store.clone();
+        match tokio::task::spawn_blocking(move || store.warm_up()).await {
+            Ok(Ok(count)) => info!("[SERVER] Loaded {} memories", count),
+            Ok(Err(e)) => warn!("[SERVER] Could not load the memory store, serving it anyway: {}", e),
             Err(e) => {
-                error!("[SERVER] Failed to bind TCP listener: {:?}", e);
+                error!("[SERVER] Loading the memory store panicked: {}", e);
+                let _ = ready_tx.send(Readiness::Failed(format!("Loading the memory store failed: {}", e)));
+                state.stop();
+                workspaces.stop();
                 return;
             }
-        };
+        }
+        for workspace in workspaces.states() {
+            let store = workspace.memory_store.clone();
+            match tokio::task::spawn_blocking(move || store.warm_up()).await {
+                Ok(Ok(count)) => info!("[SERVER] Loaded {} memories from {:?}", count, workspace.memory_store.base_path),
+                Ok(Err(e)) => warn!("[SERVER] Could not load the store at {:?}, serving it anyway: {}", workspace.memory_store.base_path, e),
+                Err(e) => warn!("[SERVER] Loading the store at {:?} panicked: {}", workspace.memory_store.base_path, e),
+            }
+        }
+        let _ = ready_tx.send(Readiness::Ready);
         
         info!("[SERVER] Starting axum server");
         match axum::serve(listener, app)
-            .with_graceful_shutdown(async {
+            .with_graceful_shutdown(async move {
                 info!("[SERVER] Waiting for shutdown signal");
                 match shutdown_rx.await {
-                    Ok(_) => info!("[SERVER] Shutdown signal received"),
-                    Err(e) => info!("[SERVER] Shutdown channel error: {:?}", e)
+                    Ok(_) => {
+                        info!("[SERVER] Shutdown signal received");
+                        state.stop();
+                        workspaces.stop();
+                    }
+                    Err(_) => {
+                        // The handle was dropped without calling shutdown, keep serving
+                        info!("[SERVER] Server handle dropped, server detached");
+                        std::future::pending::<()>().await;
+                    }
                 }
                 info!("[SERVER] API server shutting down");
             })
@@ -102,140 +533,251 @@ pub async fn start_server(
             Ok(_) => info!("[SERVER] Server shut down gracefully"),
             Err(e) => error!("[SERVER] Server error: {:?}", e)
         }
+        
+        // Hand the store over to the next instance
+        drop(writer);
         info!("[SERVER] Server task completed");
     });
     
-    // Create a new shutdown sender that won't be dropped immediately
-    let (new_shutdown_tx, _) = oneshot::channel::<()>();
-    Ok(new_shutdown_tx)
+    Ok(ServerHandle {
+        shutdown_tx: Some(shutdown_tx),
+        task,
+        readiness,
+        local_addr,
+    })
+}
+
+// Serve `addr` by forwarding every request to the instance that owns the store
+async fn start_proxy(addr: SocketAddr, target: SocketAddr) -> Result<ServerHandle, String> {
+    let same_port = target.port() == addr.port() && (addr.ip().is_unspecified() || addr.ip().is_loopback());
+    if same_port {
+        return Err(format!("Memory store is already served at http://{}; use that instance", target));
+    }
+    
+    info!("[SERVER] Memory store is served by http://{}, proxying requests from {}", target, addr);
+    let app = instance::proxy_router(target)?;
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .map_err(|e| format!("Failed to bind to {}: {}", addr, e))?;
+    let local_addr = listener.local_addr().unwrap_or(addr);
+    
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let task = tokio::spawn(async move {
+        let result = axum::serve(listener, app)
+            .with_graceful_shutdown(async move {
+                // Keep serving when the handle is dropped without calling shutdown
+                if shutdown_rx.await.is_err() {
+                    std::future::pending::<()>().await;
+                }
+            })
+            .await;
+        match result {
+            Ok(_) => info!("[SERVER] Proxy shut down gracefully"),
+            Err(e) => error!("[SERVER] Proxy error: {:?}", e),
+        }
+    });
+    
+    // Nothing to load; the instance behind the proxy has its own readiness
+    let (_, readiness) = watch::channel(Readiness::Ready);
+    Ok(ServerHandle {
+        shutdown_tx: Some(shutdown_tx),
+        task,
+        readiness,
+        local_addr,
+    })
+}
+
+// Check the caller's access to a memory before handling a request for it
+fn authorize(state: &ServerState, caller: &Caller, id: &str, permission: Permission) -> Result<(), MemoryError> {
+    if caller.principal().is_none() {
+        return Ok(());
+    }
+    state.memory_store.authorize(id, caller.principal(), permission).map(|_| ())
+}
+
+// Whether the caller can read a memory, for lists that name other memories
+fn is_visible(state: &ServerState, caller: &Caller, id: &str) -> bool {
+    caller.principal().is_none()
+        || state.memory_store.get(id).is_ok_and(|memory| memory.is_visible_to(caller.principal()))
 }
 
 async fn list_memories(
     State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Query(query): Query<ListMemoriesQuery>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
     info!("[SERVER] Handling list_memories request");
     
-    // Ensure the memory directory exists
-    let base_path = state.memory_store.base_path.clone();
-    info!("[SERVER] Memory base path: {:?}", base_path);
+    // Report a missing store instead of recreating it empty
+    if let Err(err) = state.memory_store.check_available() {
+        error!("[SERVER] {}", err);
+        return ApiError::from(err).into_response();
+    }
     
-    if !base_path.exists() {
-        info!("[SERVER] Memory directory does not exist, creating it");
-        match std::fs::create_dir_all(&base_path) {
-            Ok(_) => info!("[SERVER] Successfully created memory directory"),
-            Err(e) => {
-                error!("[SERVER] Failed to create memory directory: {:?}", e);
-                return (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create memory directory: {}", e)).into_response();
-            }
+    // Read the mutation timestamp before listing so a concurrent write is never hidden
+    let last_modified = state.memory_store.last_modified().ok();
+    if let Some(last_modified) = last_modified {
+        if cache::is_not_modified(&headers, last_modified) {
+            return (StatusCode::NOT_MODIFIED, cache::cache_headers(last_modified)).into_response();
         }
     }
     
-    info!("[SERVER] Calling memory_store.list()");
-    match state.memory_store.list() {
-        Ok(memories) => (StatusCode::OK, Json(memories)).into_response(),
+    info!("[SERVER] Calling memory_store.list_paged()");
+    let summaries = query.summaries;
+    let options = ListOptions { visible_to: caller.0, ..ListOptions::from(query) };
+    match state.memory_store.list_paged(&options) {
+        Ok(mut page) => {
+            if summaries {
+                page.items.iter_mut().for_each(|memory| state.summarize.abridge(memory));
+            }
+            let mut headers = last_modified.map(cache::cache_headers).unwrap_or_default();
+            headers.insert(cache::TOTAL_COUNT, page.total.into());
+            (StatusCode::OK, headers, Json(page.items)).into_response()
+        },
         Err(err) => {
             error!("Error listing memories: {:?}", err);
-            (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+            ApiError::from(err).into_response()
         }
     }
 }
 
 async fn get_memory(
     State(state): State<Arc<ServerState>>,
+    caller: Caller,
     Path(id): Path<String>,
 ) -> impl IntoResponse {
     info!("[SERVER] Handling get_memory request for id: {}", id);
     
-    // Ensure the memory directory exists
-    let base_path = state.memory_store.base_path.clone();
-    info!("[SERVER] Memory base path: {:?}", base_path);
-    
-    if !base_path.exists() {
-        info!("[SERVER] Memory directory does not exist, creating it");
-        match std::fs::create_dir_all(&base_path) {
-            Ok(_) => info!("[SERVER] Successfully created memory directory"),
-            Err(e) => {
-                error!("[SERVER] Failed to create memory directory: {:?}", e);
-                return (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create memory directory: {}", e)).into_response();
-            }
-        }
+    // Report a missing store instead of recreating it empty
+    if let Err(err) = state.memory_store.check_available() {
+        error!("[SERVER] {}", err);
+        return ApiError::from(err).into_response();
     }
     
     info!("[SERVER] Calling memory_store.get() for id: {}", id);
-    match state.memory_store.get(&id) {
+    match state.memory_store.authorize(&id, caller.principal(), Permission::Read) {
         Ok(memory) => (StatusCode::OK, Json(memory)).into_response(),
         Err(err) => {
             error!("Error getting memory {}: {:?}", id, err);
-            let status = match err {
-                crate::memory::MemoryError::NotFound(_) => StatusCode::NOT_FOUND,
-                _ => StatusCode::INTERNAL_SERVER_ERROR,
-            };
-            (status, err.to_string()).into_response()
+            ApiError::from(err).into_response()
         }
     }
 }
 
-#[derive(serde::Deserialize)]
-struct CreateMemoryRequest {
-    title: String,
-    content: String,
-    tags: Vec<String>,
+/// A new memory, with the existing tags the model suggests for it
+#[derive(Debug, serde::Serialize, ToSchema)]
+pub struct CreatedMemory {
+    #[serde(flatten)]
+    pub memory: Memory,
+    /// Tags to confirm, when auto-tagging is in `suggest` mode
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub suggested_tags: Vec<String>,
+    /// Why the memory was saved but not added to the requested collection
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
 }
 
 async fn create_memory(
     State(state): State<Arc<ServerState>>,
-    Json(req): Json<CreateMemoryRequest>,
+    caller: Caller,
+    ValidJson(req): ValidJson<CreateMemoryRequest>,
 ) -> impl IntoResponse {
     info!("[SERVER] Handling create_memory request with title: {}", req.title);
     
-    // Ensure the memory directory exists
-    let base_path = state.memory_store.base_path.clone();
-    info!("[SERVER] Memory base path: {:?}", base_path);
+    // Report a missing store instead of recreating it empty
+    if let Err(err) = state.memory_store.check_available() {
+        error!("[SERVER] {}", err);
+        return ApiError::from(err).into_response();
+    }
+    
+    if let Err(err) = req.references.iter().try_for_each(validate_reference) {
+        return ApiError::invalid_request(err.to_string()).into_response();
+    }
+    if let Err(err) = validate_importance(req.importance) {
+        return ApiError::invalid_request(err.to_string()).into_response();
+    }
     
-    if !base_path.exists() {
-        info!("[SERVER] Memory directory does not exist, creating it");
-        match std::fs::create_dir_all(&base_path) {
-            Ok(_) => info!("[SERVER] Successfully created memory directory"),
-            Err(e) => {
-                error!("[SERVER] Failed to create memory directory: {:?}", e);
-                return (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create memory directory: {}", e)).into_response();
+    // A memory created in a collection starts from its template and tags
+    let (mut content, mut tags) = (req.content, req.tags);
+    let collection = match req.collection.as_deref().map(|id| state.memory_store.get_collection(id)).transpose() {
+        Ok(collection) => collection,
+        Err(err) => return ApiError::from(err).into_response(),
+    };
+    if let Some(defaults) = collection.as_ref().map(|collection| &collection.defaults) {
+        match &defaults.template {
+            Some(template) if content.trim().is_empty() && templates::check_template(template).is_ok() => {
+                match templates::instantiate(&state, template, req.template_context.as_ref(), caller.principal(), chrono::Local::now()).await {
+                    Ok(rendered) => content = rendered,
+                    Err(err) => {
+                        error!("Error filling in the template: {:?}", err);
+                        return ApiError::from(err).into_response();
+                    }
+                }
+            }
+            _ => {}
+        }
+        for tag in &defaults.tags {
+            if !tags.iter().any(|existing| existing.eq_ignore_ascii_case(tag)) {
+                tags.push(tag.clone());
             }
         }
     }
     
-    info!("[SERVER] Creating new memory with title: {}", req.title);
-    let memory = Memory::new(req.title, req.content, req.tags);
-    info!("[SERVER] Generated memory ID: {}", memory.id);
+    let title = match resolve_title(&state, req.title, &content).await {
+        Ok(title) => title,
+        Err(err) => return ApiError::invalid_request(err).into_response(),
+    };
     
-    info!("[SERVER] Calling memory_store.save()");
-    match state.memory_store.save(&memory) {
-        Ok(_) => (StatusCode::CREATED, Json(memory)).into_response(),
+    let remind_at = state.capture.remind_at(req.remind_at, &title, &content);
+    info!("[SERVER] Creating new memory with title: {}", title);
+    let mut memory = match state.memory_store.new_memory(title, content, tags) {
+        Ok(memory) => memory,
         Err(err) => {
-            error!("Error creating memory: {:?}", err);
-            (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+            error!("Error generating memory ID: {:?}", err);
+            return ApiError::from(err).into_response();
         }
+    };
+    info!("[SERVER] Generated memory ID: {}", memory.id);
+    memory.references = req.references;
+    memory.remind_at = remind_at;
+    memory.importance = req.importance;
+    memory.owner = caller.user().map(str::to_string);
+    let suggested_tags = tag_new_memory(&state, &mut memory, caller.principal()).await;
+    
+    info!("[SERVER] Calling memory_store.save()");
+    if let Err(err) = state.memory_store.save(&memory) {
+        error!("Error creating memory: {:?}", err);
+        return ApiError::from(err).into_response();
     }
+
+    // The memory is saved by now, so failing here would make callers retry
+    // and create it twice
+    let warning = collection.and_then(|collection| {
+        let added = state.memory_store.add_to_collection(&collection.id, std::slice::from_ref(&memory.id));
+        added.err().map(|err| {
+            warn!("Memory {} was not added to collection {}: {}", memory.id, collection.id, err);
+            format!("Not added to collection {}: {}", collection.id, err)
+        })
+    });
+    (StatusCode::CREATED, Json(CreatedMemory { memory, suggested_tags, warning })).into_response()
 }
 
 async fn delete_memory(
     State(state): State<Arc<ServerState>>,
+    caller: Caller,
     Path(id): Path<String>,
 ) -> impl IntoResponse {
     info!("[SERVER] Handling delete_memory request for id: {}", id);
     
-    // Ensure the memory directory exists
-    let base_path = state.memory_store.base_path.clone();
-    info!("[SERVER] Memory base path: {:?}", base_path);
+    // Report a missing store instead of recreating it empty
+    if let Err(err) = state.memory_store.check_available() {
+        error!("[SERVER] {}", err);
+        return ApiError::from(err).into_response();
+    }
     
-    if !base_path.exists() {
-        info!("[SERVER] Memory directory does not exist, creating it");
-        match std::fs::create_dir_all(&base_path) {
-            Ok(_) => info!("[SERVER] Successfully created memory directory"),
-            Err(e) => {
-                error!("[SERVER] Failed to create memory directory: {:?}", e);
-                return (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create memory directory: {}", e)).into_response();
-            }
-        }
+    if let Err(err) = authorize(&state, &caller, &id, Permission::Write) {
+        return ApiError::from(err).into_response();
     }
     
     info!("[SERVER] Calling memory_store.delete() for id: {}", id);
@@ -243,76 +785,1512 @@ async fn delete_memory(
         Ok(_) => StatusCode::NO_CONTENT.into_response(),
         Err(err) => {
             error!("Error deleting memory {}: {:?}", id, err);
-            let status = match err {
-                crate::memory::MemoryError::NotFound(_) => StatusCode::NOT_FOUND,
-                _ => StatusCode::INTERNAL_SERVER_ERROR,
-            };
-            (status, err.to_string()).into_response()
+            ApiError::from(err).into_response()
         }
     }
 }
 
-#[derive(serde::Deserialize)]
-struct SearchMemoriesRequest {
-    query: String,
-    tag: Option<String>,
+#[derive(Debug, serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct SearchOptions {
+    /// Return the content of each memory; `false` keeps responses small when
+    /// only snippets are shown
+    #[serde(default = "default_include_content")]
+    include_content: bool,
+}
+
+fn default_include_content() -> bool {
+    true
 }
 
 async fn search_memories(
     State(state): State<Arc<ServerState>>,
-    Json(req): Json<SearchMemoriesRequest>,
+    caller: Caller,
+    Query(options): Query<SearchOptions>,
+    ValidJson(req): ValidJson<SearchMemoriesRequest>,
 ) -> impl IntoResponse {
-    let result = if let Some(tag) = req.tag {
-        state.memory_store.search_by_tag(&tag)
-    } else {
-        state.memory_store.search(&req.query)
+    // A tag replaces the query, and a structured filter narrows either
+    let query = match &req.tag {
+        Some(tag) => SearchQuery::Text { field: TextField::Tag, value: tag.clone() },
+        None => match SearchQuery::parse(&req.query) {
+            Ok(query) => query,
+            Err(err) => return ApiError::invalid_request(MemoryError::from(err).to_string()).into_response(),
+        },
     };
+    let query = match req.filter {
+        Some(filter) => SearchQuery::And { queries: vec![query, filter] },
+        None => query,
+    };
+    
+    // Session memories change without touching the store, so results including them are not cached
+    let session_results = req.session_id.as_deref()
+        .filter(|_| req.collection.is_none())
+        .map(|session| QueryMatcher::plain().results(state.sessions.search(session, caller.user(), &query), &query));
+    let last_modified = state.memory_store.last_modified().ok().filter(|_| req.session_id.is_none());
+    
+    // Scans run off the async runtime and stop if the client goes away
+    let store = state.memory_store.clone();
+    let importance = state.importance.clone();
+    let result = run_cancellable(move |cancel| {
+        let memories = store.search_query_cancellable(&query, cancel)?;
+        let memories = filter_visible(memories, caller.principal());
+        let mut memories = match req.collection {
+            Some(collection) => store.filter_by_collection(memories, &collection)?,
+            None => memories,
+        };
+        importance.rank(&mut memories);
+        Ok(store.query_matcher(&query)?.results(memories, &query))
+    }).await;
     
     match result {
-        Ok(memories) => (StatusCode::OK, Json(memories)).into_response(),
+        Ok(results) => {
+            let results = match session_results {
+                Some(mut session_results) => {
+                    session_results.extend(results);
+                    session_results
+                }
+                None => results,
+            };
+            let mut body = serde_json::to_value(results).unwrap_or_default();
+            if !options.include_content {
+                for result in body.as_array_mut().into_iter().flatten() {
+                    result.as_object_mut().map(|result| result.remove("content"));
+                }
+            }
+            (StatusCode::OK, last_modified.map(cache::cache_headers).unwrap_or_default(), Json(body)).into_response()
+        }
         Err(err) => {
             error!("Error searching memories: {:?}", err);
-            (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+            ApiError::from(err).into_response()
         }
     }
 }
 
-// Wrapper functions to ensure correct type signatures for the router
-#[axum::debug_handler]
-async fn list_memories_handler(
-    state: State<Arc<ServerState>>,
+async fn mark_memory_viewed(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(id): Path<String>,
 ) -> impl IntoResponse {
-    list_memories(state).await
+    info!("[SERVER] Handling mark_memory_viewed request for id: {}", id);
+    
+    if let Err(err) = authorize(&state, &caller, &id, Permission::Read) {
+        return ApiError::from(err).into_response();
+    }
+    
+    match state.memory_store.mark_viewed(&id) {
+        Ok(memory) => (StatusCode::OK, Json(memory)).into_response(),
+        Err(err) => {
+            error!("Error marking memory {} as viewed: {:?}", id, err);
+            ApiError::from(err).into_response()
+        }
+    }
 }
 
-#[axum::debug_handler]
-async fn get_memory_handler(
-    state: State<Arc<ServerState>>,
-    path: Path<String>,
+async fn list_unread_memories(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
 ) -> impl IntoResponse {
-    get_memory(state, path).await
+    info!("[SERVER] Handling list_unread_memories request");
+    
+    match state.memory_store.list_unread() {
+        Ok(memories) => (StatusCode::OK, Json(filter_visible(memories, caller.principal()))).into_response(),
+        Err(err) => {
+            error!("Error listing unread memories: {:?}", err);
+            ApiError::from(err).into_response()
+        }
+    }
 }
 
-#[axum::debug_handler]
-async fn create_memory_handler(
-    state: State<Arc<ServerState>>,
-    json: Json<CreateMemoryRequest>,
+async fn rename_memory(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+    ValidJson(req): ValidJson<RenameMemoryRequest>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling rename_memory request for id: {} -> {}", id, req.new_id);
+    
+    if let Err(err) = authorize(&state, &caller, &id, Permission::Write) {
+        return ApiError::from(err).into_response();
+    }
+    
+    match state.memory_store.rename(&id, &req.new_id) {
+        Ok(result) => {
+            info!("[SERVER] Renamed memory {}, rewrote links in {} memories", id, result.updated_references.len());
+            (StatusCode::OK, Json(result)).into_response()
+        },
+        Err(err) => {
+            error!("Error renaming memory {}: {:?}", id, err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn memory_git_log(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(id): Path<String>,
 ) -> impl IntoResponse {
-    create_memory(state, json).await
+    info!("[SERVER] Handling memory_git_log request for id: {}", id);
+    
+    if let Err(err) = authorize(&state, &caller, &id, Permission::Read) {
+        return ApiError::from(err).into_response();
+    }
+    
+    match state.memory_store.memory_history(&id) {
+        Ok(commits) => (StatusCode::OK, Json(commits)).into_response(),
+        Err(err) => {
+            error!("Error reading history of memory {}: {:?}", id, err);
+            ApiError::from(err).into_response()
+        }
+    }
 }
 
-#[axum::debug_handler]
-async fn delete_memory_handler(
-    state: State<Arc<ServerState>>,
-    path: Path<String>,
+async fn revert_memory(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+    ValidJson(req): ValidJson<RevertMemoryRequest>,
 ) -> impl IntoResponse {
-    delete_memory(state, path).await
+    info!("[SERVER] Handling revert_memory request for id: {} to {}", id, req.commit);
+    
+    if let Err(err) = authorize(&state, &caller, &id, Permission::Write) {
+        return ApiError::from(err).into_response();
+    }
+    
+    match state.memory_store.revert_memory(&id, &req.commit) {
+        Ok(memory) => (StatusCode::OK, Json(memory)).into_response(),
+        Err(err) => {
+            error!("Error reverting memory {}: {:?}", id, err);
+            ApiError::from(err).into_response()
+        }
+    }
 }
 
-#[axum::debug_handler]
-async fn search_memories_handler(
+async fn get_memory_acl(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling get_memory_acl request for id: {}", id);
+    
+    match state.memory_store.authorize(&id, caller.principal(), Permission::Read) {
+        Ok(memory) => (StatusCode::OK, Json(memory.acl())).into_response(),
+        Err(err) => {
+            error!("Error reading sharing of memory {}: {:?}", id, err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn share_memory(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+    ValidJson(req): ValidJson<ShareMemoryRequest>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling share_memory request for id: {} with {} grants", id, req.shared.len());
+    
+    match state.memory_store.share(&id, caller.principal(), req.shared) {
+        Ok(acl) => (StatusCode::OK, Json(acl)).into_response(),
+        Err(err) => {
+            error!("Error sharing memory {}: {:?}", id, err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn set_importance(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+    ValidJson(req): ValidJson<SetImportanceRequest>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling set_importance request for id: {}", id);
+    
+    if let Err(err) = authorize(&state, &caller, &id, Permission::Write) {
+        return ApiError::from(err).into_response();
+    }
+    
+    match state.memory_store.set_importance(&id, req.importance) {
+        Ok(memory) => (StatusCode::OK, Json(memory)).into_response(),
+        Err(err) => {
+            error!("Error setting importance of memory {}: {:?}", id, err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn memory_links(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling memory_links request for id: {}", id);
+    
+    if let Err(err) = authorize(&state, &caller, &id, Permission::Read) {
+        return ApiError::from(err).into_response();
+    }
+    
+    match state.memory_store.links(&id) {
+        Ok(mut links) => {
+            // Leave out memories the caller cannot read
+            links.links.retain(|link| is_visible(&state, &caller, &link.id));
+            (StatusCode::OK, Json(links)).into_response()
+        },
+        Err(err) => {
+            error!("Error reading links of memory {}: {:?}", id, err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn memory_backlinks(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling memory_backlinks request for id: {}", id);
+    
+    if let Err(err) = authorize(&state, &caller, &id, Permission::Read) {
+        return ApiError::from(err).into_response();
+    }
+    
+    match state.memory_store.backlinks(&id) {
+        Ok(mut backlinks) => {
+            // Leave out memories the caller cannot read
+            backlinks.backlinks.retain(|backlink| is_visible(&state, &caller, &backlink.id));
+            (StatusCode::OK, Json(backlinks)).into_response()
+        },
+        Err(err) => {
+            error!("Error reading backlinks of memory {}: {:?}", id, err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn related_memories(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+    Query(query): Query<RelatedQuery>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling related_memories request for id: {}", id);
+    
+    if let Err(err) = authorize(&state, &caller, &id, Permission::Read) {
+        return ApiError::from(err).into_response();
+    }
+    
+    let config = state.related.with_overrides(&query);
+    match state.memory_store.related(&id, &config, caller.principal()) {
+        Ok(related) => (StatusCode::OK, Json(related)).into_response(),
+        Err(err) => {
+            error!("Error finding memories related to {}: {:?}", id, err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn graph(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Query(mut options): Query<GraphOptions>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling graph request");
+    options.visible_to = caller.0;
+    
+    match state.memory_store.graph(&options) {
+        Ok(graph) => (StatusCode::OK, Json(graph)).into_response(),
+        Err(err) => {
+            error!("Error building memory graph: {:?}", err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn list_collections(
+    State(state): State<Arc<ServerState>>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling list_collections request");
+    
+    match state.memory_store.list_collections() {
+        Ok(collections) => (StatusCode::OK, Json(collections)).into_response(),
+        Err(err) => {
+            error!("Error listing collections: {:?}", err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn create_collection(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    ValidJson(req): ValidJson<CreateCollectionRequest>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling create_collection request with name: {}", req.name);
+    
+    for memory_id in &req.memory_ids {
+        if let Err(err) = authorize(&state, &caller, memory_id, Permission::Read) {
+            return ApiError::from(err).into_response();
+        }
+    }
+    
+    if let Some(Err(err)) = req.defaults.template.as_deref().map(|template| templates::check(template, &state.templates)) {
+        return ApiError::invalid_request(format!("Invalid template: {}", err)).into_response();
+    }
+    
+    match state.memory_store.create_collection(req.name, req.description, req.memory_ids, req.defaults) {
+        Ok(collection) => (StatusCode::CREATED, Json(collection)).into_response(),
+        Err(err) => {
+            error!("Error creating collection: {:?}", err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn get_collection(
+    State(state): State<Arc<ServerState>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling get_collection request for id: {}", id);
+    
+    match state.memory_store.get_collection(&id) {
+        Ok(collection) => (StatusCode::OK, Json(collection)).into_response(),
+        Err(err) => {
+            error!("Error getting collection {}: {:?}", id, err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn update_collection(
+    State(state): State<Arc<ServerState>>,
+    Path(id): Path<String>,
+    ValidJson(update): ValidJson<CollectionUpdate>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling update_collection request for id: {}", id);
+    
+    let template = update.defaults.as_ref().and_then(|defaults| defaults.template.as_deref());
+    if let Some(Err(err)) = template.map(|template| templates::check(template, &state.templates)) {
+        return ApiError::invalid_request(format!("Invalid template: {}", err)).into_response();
+    }
+    
+    match state.memory_store.update_collection(&id, update) {
+        Ok(collection) => (StatusCode::OK, Json(collection)).into_response(),
+        Err(err) => {
+            error!("Error updating collection {}: {:?}", id, err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn delete_collection(
+    State(state): State<Arc<ServerState>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling delete_collection request for id: {}", id);
+    
+    match state.memory_store.delete_collection(&id) {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(err) => {
+            error!("Error deleting collection {}: {:?}", id, err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn add_to_collection(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+    ValidJson(req): ValidJson<CollectionMembersRequest>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling add_to_collection request for id: {}", id);
+    
+    for memory_id in &req.memory_ids {
+        if let Err(err) = authorize(&state, &caller, memory_id, Permission::Read) {
+            return ApiError::from(err).into_response();
+        }
+    }
+    
+    match state.memory_store.add_to_collection(&id, &req.memory_ids) {
+        Ok(collection) => (StatusCode::OK, Json(collection)).into_response(),
+        Err(err) => {
+            error!("Error adding memories to collection {}: {:?}", id, err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn remove_from_collection(
+    State(state): State<Arc<ServerState>>,
+    Path((id, memory_id)): Path<(String, String)>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling remove_from_collection request for {}/{}", id, memory_id);
+    
+    match state.memory_store.remove_from_collection(&id, &memory_id) {
+        Ok(collection) => (StatusCode::OK, Json(collection)).into_response(),
+        Err(err) => {
+            error!("Error removing memory {} from collection {}: {:?}", memory_id, id, err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn upload_attachments(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+    mut multipart: Multipart,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling upload_attachments request for id: {}", id);
+    
+    if let Err(err) = authorize(&state, &caller, &id, Permission::Write) {
+        return ApiError::from(err).into_response();
+    }
+    
+    let mut uploaded = Vec::new();
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(err) => return ApiError::invalid_request(err.to_string()).into_response(),
+        };
+        
+        // Prefer the uploaded file's name, falling back to the form field name
+        let Some(name) = field.file_name().or(field.name()).map(str::to_string) else {
+            return ApiError::invalid_request("Attachment is missing a file name".to_string()).into_response();
+        };
+        let data = match field.bytes().await {
+            Ok(data) => data,
+            Err(err) => return ApiError::invalid_request(err.to_string()).into_response(),
+        };
+        
+        let store = state.memory_store.clone();
+        let config = state.attachment_text.clone();
+        let (memory_id, file_name) = (id.clone(), name.clone());
+        let stored = tokio::task::spawn_blocking(move || {
+            let attachment = store.add_attachment(&memory_id, &file_name, &data)?;
+            attachment_text::index_stored(&store, &config, &memory_id, &file_name);
+            Ok::<_, MemoryError>(attachment)
+        }).await.unwrap_or_else(|e| Err(MemoryError::Io(std::io::Error::other(format!("Upload panicked: {}", e)))));
+        match stored {
+            Ok(attachment) => uploaded.push(attachment),
+            Err(err) => {
+                error!("Error storing attachment {} for memory {}: {:?}", name, id, err);
+                return ApiError::from(err).into_response();
+            }
+        }
+    }
+    
+    if uploaded.is_empty() {
+        return ApiError::invalid_request("No files in upload".to_string()).into_response();
+    }
+    
+    (StatusCode::CREATED, Json(uploaded)).into_response()
+}
+
+async fn list_attachments(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling list_attachments request for id: {}", id);
+    
+    if let Err(err) = authorize(&state, &caller, &id, Permission::Read) {
+        return ApiError::from(err).into_response();
+    }
+    
+    match state.memory_store.list_attachments(&id) {
+        Ok(attachments) => (StatusCode::OK, Json(attachments)).into_response(),
+        Err(err) => {
+            error!("Error listing attachments for memory {}: {:?}", id, err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn download_attachment(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path((id, name)): Path<(String, String)>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling download_attachment request for {}/{}", id, name);
+    
+    if let Err(err) = authorize(&state, &caller, &id, Permission::Read) {
+        return ApiError::from(err).into_response();
+    }
+    
+    match state.memory_store.read_attachment(&id, &name) {
+        Ok((attachment, data)) => (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, attachment.content_type),
+                (header::CONTENT_DISPOSITION, format!("inline; filename=\"{}\"", attachment.name)),
+            ],
+            data,
+        ).into_response(),
+        Err(err) => {
+            error!("Error reading attachment {}/{}: {:?}", id, name, err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn remove_attachment(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path((id, name)): Path<(String, String)>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling remove_attachment request for {}/{}", id, name);
+    
+    if let Err(err) = authorize(&state, &caller, &id, Permission::Write) {
+        return ApiError::from(err).into_response();
+    }
+    
+    match state.memory_store.remove_attachment(&id, &name) {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(err) => {
+            error!("Error removing attachment {}/{}: {:?}", id, name, err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn list_references(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling list_references request for id: {}", id);
+    
+    match state.memory_store.authorize(&id, caller.principal(), Permission::Read) {
+        Ok(memory) => (StatusCode::OK, Json(memory.references)).into_response(),
+        Err(err) => {
+            error!("Error listing references for memory {}: {:?}", id, err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn set_references(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+    ValidJson(references): ValidJson<Vec<Reference>>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling set_references request for id: {}", id);
+    
+    if let Err(err) = authorize(&state, &caller, &id, Permission::Write) {
+        return ApiError::from(err).into_response();
+    }
+    
+    match state.memory_store.set_references(&id, references) {
+        Ok(memory) => (StatusCode::OK, Json(memory)).into_response(),
+        Err(err) => {
+            error!("Error setting references for memory {}: {:?}", id, err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn add_reference(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+    ValidJson(reference): ValidJson<Reference>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling add_reference request for id: {}", id);
+    
+    if let Err(err) = authorize(&state, &caller, &id, Permission::Write) {
+        return ApiError::from(err).into_response();
+    }
+    
+    match state.memory_store.add_reference(&id, reference) {
+        Ok(memory) => (StatusCode::CREATED, Json(memory)).into_response(),
+        Err(err) => {
+            error!("Error adding reference to memory {}: {:?}", id, err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn remove_reference(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path((id, index)): Path<(String, usize)>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling remove_reference request for {}/{}", id, index);
+    
+    if let Err(err) = authorize(&state, &caller, &id, Permission::Write) {
+        return ApiError::from(err).into_response();
+    }
+    
+    match state.memory_store.remove_reference(&id, index) {
+        Ok(memory) => (StatusCode::OK, Json(memory)).into_response(),
+        Err(err) => {
+            error!("Error removing reference {} from memory {}: {:?}", index, id, err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn list_trash(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling list_trash request");
+    
+    match state.memory_store.list_trash() {
+        Ok(mut trashed) => {
+            trashed.retain(|trashed| trashed.memory.is_visible_to(caller.principal()));
+            (StatusCode::OK, Json(trashed)).into_response()
+        },
+        Err(err) => {
+            error!("Error listing trash: {:?}", err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn restore_trash(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling restore_trash request for id: {}", id);
+    
+    if let Err(err) = state.memory_store.authorize_trashed(&id, caller.principal(), Permission::Write) {
+        return ApiError::from(err).into_response();
+    }
+    
+    match state.memory_store.restore(&id) {
+        Ok(memory) => (StatusCode::OK, Json(memory)).into_response(),
+        Err(err) => {
+            error!("Error restoring memory {}: {:?}", id, err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn purge_trash(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling purge_trash request for id: {}", id);
+    
+    if let Err(err) = state.memory_store.authorize_trashed(&id, caller.principal(), Permission::Write) {
+        return ApiError::from(err).into_response();
+    }
+    
+    match state.memory_store.purge(&id) {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(err) => {
+            error!("Error purging memory {}: {:?}", id, err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn memory_events(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling memory_events request");
+    
+    let receiver = state.events.subscribe();
+    let events = stream::unfold((receiver, caller), |(mut receiver, caller)| async move {
+        let event = loop {
+            match receiver.recv().await {
+                // Changes to memories the caller cannot read are not sent
+                Ok(event) if event.memory.as_ref().is_some_and(|memory| !memory.is_visible_to(caller.principal())) => continue,
+                Ok(event) => break Event::default().event(event.kind.as_str()).json_data(&event),
+                // Tell clients that fell behind to reload instead of silently dropping changes
+                Err(RecvError::Lagged(missed)) => {
+                    warn!("[SERVER] Event stream lagged, {} events missed", missed);
+                    break Ok(Event::default().event("resync").data(missed.to_string()));
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        };
+        Some((event, (receiver, caller)))
+    });
+    
+    let mut shutdown = state.shutdown.clone();
+    let events = events.take_until(async move {
+        let _ = shutdown.wait_for(|stopped| *stopped).await;
+    });
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+/// What a running server tells clients about itself
+#[derive(Debug, Clone, serde::Serialize, ToSchema)]
+pub struct ServerInfo {
+    /// Version of the Conduit backend
+    pub version: String,
+    /// Address the server is bound to, with the port the system picked for port 0;
+    /// absent when the routes are mounted in another application
+    pub addr: Option<String>,
+    /// URL reaching the server from this machine, through the loopback
+    /// interface when it is bound to all interfaces
+    pub url: Option<String>,
+    /// ID of the server's process
+    pub pid: u32,
+}
+
+impl ServerInfo {
+    pub fn new(addr: Option<SocketAddr>) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            addr: addr.map(|addr| addr.to_string()),
+            url: addr.map(|addr| format!("http://{}", reachable_addr(addr))),
+            pid: std::process::id(),
+        }
+    }
+}
+
+#[derive(serde::Serialize, ToSchema)]
+struct DiagnosticsResponse {
+    store: StoreDiagnostics,
+    /// Upstream health, absent in offline mode
+    provider: Option<ProviderHealth>,
+    /// Progress of embedding stored memories
+    embeddings: EmbeddingStatus,
+}
+
+async fn diagnostics(
+    State(state): State<Arc<ServerState>>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling diagnostics request");
+    
+    let diagnostics = state.memory_store.diagnostics().and_then(|store| {
+        let embeddings = state.memory_store.embedding_status(state.embeddings.memory_model())?;
+        Ok((store, embeddings))
+    });
+    
+    match diagnostics {
+        Ok((store, embeddings)) => {
+            let provider = state.provider.as_ref().map(|provider| provider.health());
+            (StatusCode::OK, Json(DiagnosticsResponse { store, provider, embeddings })).into_response()
+        }
+        Err(err) => {
+            error!("Error scanning memory store: {:?}", err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn info(
+    State(state): State<Arc<ServerState>>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling info request");
+    Json(ServerInfo::new(state.listen_addr.get().copied()))
+}
+
+#[derive(serde::Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct GcQuery {
+    /// Report orphaned artifacts without deleting them (the default)
+    #[serde(default = "default_dry_run")]
+    dry_run: bool,
+}
+
+fn default_dry_run() -> bool {
+    true
+}
+
+async fn collect_garbage(
+    State(state): State<Arc<ServerState>>,
+    Query(query): Query<GcQuery>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling collect_garbage request (dry_run: {})", query.dry_run);
+    
+    match state.memory_store.collect_garbage(query.dry_run) {
+        Ok(report) => (StatusCode::OK, Json(report)).into_response(),
+        Err(err) => {
+            error!("Error collecting garbage: {:?}", err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+// Wrapper functions to ensure correct type signatures for the router
+#[utoipa::path(
+    get, path = "/api/memories", tag = "memories", operation_id = "list_memories",
+    params(ListMemoriesQuery, FieldsQuery),
+    responses(
+        (status = 200, description = "A page of memories; the total count is in `X-Total-Count`", body = [Memory]),
+        (status = 400, description = "Unknown field in `fields`", body = OpenAiErrorResponse),
+        (status = 304, description = "Not modified since `If-Modified-Since`"),
+    )
+)]
+#[axum::debug_handler]
+async fn list_memories_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    query: Query<ListMemoriesQuery>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    list_memories(state, caller, query, headers).await
+}
+
+#[utoipa::path(
+    get, path = "/api/memories/{id}", tag = "memories", operation_id = "get_memory",
+    params(("id" = String, Path, description = "Memory ID"), FieldsQuery),
+    responses(
+        (status = 200, body = Memory),
+        (status = 400, description = "Unknown field in `fields`", body = OpenAiErrorResponse),
+        (status = 404, description = "Memory not found", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn get_memory_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+) -> impl IntoResponse {
+    get_memory(state, caller, path).await
+}
+
+#[utoipa::path(
+    post, path = "/api/memories", tag = "memories", operation_id = "create_memory",
+    request_body = CreateMemoryRequest,
+    responses(
+        (status = 201, body = CreatedMemory),
+        (status = 400, description = "Malformed JSON, invalid reference, or no title could be generated", body = OpenAiErrorResponse),
+        (status = 422, description = "Missing field, blank title and content, too long a title, content or tag list, or invalid collection ID", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn create_memory_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    json: ValidJson<CreateMemoryRequest>,
+) -> impl IntoResponse {
+    create_memory(state, caller, json).await
+}
+
+#[utoipa::path(
+    delete, path = "/api/memories/{id}", tag = "memories", operation_id = "delete_memory",
+    params(("id" = String, Path, description = "Memory ID")),
+    responses(
+        (status = 204, description = "Moved to the trash"),
+        (status = 404, description = "Memory not found", body = OpenAiErrorResponse),
+        (status = 409, description = "A memory with the same ID is already in the trash", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn delete_memory_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+) -> impl IntoResponse {
+    delete_memory(state, caller, path).await
+}
+
+#[utoipa::path(
+    post, path = "/api/memories/search", tag = "memories", operation_id = "search_memories",
+    params(FieldsQuery, SearchOptions),
+    request_body = SearchMemoriesRequest,
+    responses(
+        (status = 200, description = "Matching memories, each with where the query matched it", body = [SearchResult]),
+        (status = 400, description = "Invalid query, or unknown field in `fields`", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn search_memories_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    options: Query<SearchOptions>,
+    json: ValidJson<SearchMemoriesRequest>,
+) -> impl IntoResponse {
+    search_memories(state, caller, options, json).await
+}
+
+#[utoipa::path(
+    post, path = "/api/memories/{id}/viewed", tag = "memories", operation_id = "mark_memory_viewed",
+    params(("id" = String, Path, description = "Memory ID")),
+    responses(
+        (status = 200, body = Memory),
+        (status = 403, description = "Memory or store is read-only", body = OpenAiErrorResponse),
+        (status = 404, description = "Memory not found", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn mark_memory_viewed_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+) -> impl IntoResponse {
+    mark_memory_viewed(state, caller, path).await
+}
+
+#[utoipa::path(
+    get, path = "/api/memories/unread", tag = "memories", operation_id = "list_unread_memories",
+    responses((status = 200, description = "Unread memories, oldest first", body = [Memory]))
+)]
+#[axum::debug_handler]
+async fn list_unread_memories_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+) -> impl IntoResponse {
+    list_unread_memories(state, caller).await
+}
+
+#[utoipa::path(
+    get, path = "/api/diagnostics", tag = "maintenance", operation_id = "diagnostics",
+    responses((status = 200, body = DiagnosticsResponse))
+)]
+#[axum::debug_handler]
+async fn diagnostics_handler(
+    state: State<Arc<ServerState>>,
+) -> impl IntoResponse {
+    diagnostics(state).await
+}
+
+#[utoipa::path(
+    get, path = "/api/info", tag = "maintenance", operation_id = "server_info",
+    responses((status = 200, description = "Version and address of the server", body = ServerInfo))
+)]
+#[axum::debug_handler]
+async fn info_handler(
+    state: State<Arc<ServerState>>,
+) -> impl IntoResponse {
+    info(state).await
+}
+
+#[utoipa::path(
+    post, path = "/api/memories/{id}/rename", tag = "memories", operation_id = "rename_memory",
+    params(("id" = String, Path, description = "Memory ID")),
+    request_body = RenameMemoryRequest,
+    responses(
+        (status = 200, body = RenameResult),
+        (status = 400, description = "Invalid new ID", body = OpenAiErrorResponse),
+        (status = 404, description = "Memory not found", body = OpenAiErrorResponse),
+        (status = 409, description = "New ID already in use", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn rename_memory_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+    json: ValidJson<RenameMemoryRequest>,
+) -> impl IntoResponse {
+    rename_memory(state, caller, path, json).await
+}
+
+#[utoipa::path(
+    get, path = "/api/memories/{id}/git-log", tag = "memories", operation_id = "memory_git_log",
+    params(("id" = String, Path, description = "Memory ID")),
+    responses(
+        (status = 200, body = Vec<GitCommit>),
+        (status = 400, description = "Git mode is disabled", body = OpenAiErrorResponse),
+        (status = 404, description = "Memory has no history", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn memory_git_log_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+) -> impl IntoResponse {
+    memory_git_log(state, caller, path).await
+}
+
+#[utoipa::path(
+    post, path = "/api/memories/{id}/revert", tag = "memories", operation_id = "revert_memory",
+    params(("id" = String, Path, description = "Memory ID")),
+    request_body = RevertMemoryRequest,
+    responses(
+        (status = 200, body = Memory),
+        (status = 400, description = "Git mode is disabled", body = OpenAiErrorResponse),
+        (status = 403, description = "Store is read-only", body = OpenAiErrorResponse),
+        (status = 404, description = "Memory not found at that commit", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn revert_memory_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+    json: ValidJson<RevertMemoryRequest>,
+) -> impl IntoResponse {
+    revert_memory(state, caller, path, json).await
+}
+
+#[utoipa::path(
+    get, path = "/api/memories/{id}/acl", tag = "memories", operation_id = "get_memory_acl",
+    params(("id" = String, Path, description = "Memory ID")),
+    responses(
+        (status = 200, body = MemoryAcl),
+        (status = 404, description = "Memory not found", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn get_memory_acl_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+) -> impl IntoResponse {
+    get_memory_acl(state, caller, path).await
+}
+
+#[utoipa::path(
+    put, path = "/api/memories/{id}/acl", tag = "memories", operation_id = "share_memory",
+    params(("id" = String, Path, description = "Memory ID")),
+    request_body = ShareMemoryRequest,
+    responses(
+        (status = 200, body = MemoryAcl),
+        (status = 400, description = "Invalid user or group name", body = OpenAiErrorResponse),
+        (status = 403, description = "Only the owner can share the memory", body = OpenAiErrorResponse),
+        (status = 404, description = "Memory not found", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn share_memory_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+    json: ValidJson<ShareMemoryRequest>,
+) -> impl IntoResponse {
+    share_memory(state, caller, path, json).await
+}
+
+#[utoipa::path(
+    put, path = "/api/memories/{id}/importance", tag = "memories", operation_id = "set_importance",
+    params(("id" = String, Path, description = "Memory ID")),
+    request_body = SetImportanceRequest,
+    responses(
+        (status = 200, description = "The memory with its new importance", body = Memory),
+        (status = 400, description = "Importance outside 0 to 1", body = OpenAiErrorResponse),
+        (status = 403, description = "The memory is read-only", body = OpenAiErrorResponse),
+        (status = 404, description = "Memory not found", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn set_importance_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+    json: ValidJson<SetImportanceRequest>,
+) -> impl IntoResponse {
+    set_importance(state, caller, path, json).await
+}
+
+#[utoipa::path(
+    get, path = "/api/trash", tag = "trash", operation_id = "list_trash",
+    responses((status = 200, body = [TrashedMemory]))
+)]
+#[axum::debug_handler]
+async fn list_trash_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+) -> impl IntoResponse {
+    list_trash(state, caller).await
+}
+
+#[utoipa::path(
+    post, path = "/api/trash/{id}/restore", tag = "trash", operation_id = "restore_trash",
+    params(("id" = String, Path, description = "Memory ID")),
+    responses(
+        (status = 200, body = Memory),
+        (status = 404, description = "Memory not in the trash", body = OpenAiErrorResponse),
+        (status = 409, description = "A memory with this ID exists again", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn restore_trash_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+) -> impl IntoResponse {
+    restore_trash(state, caller, path).await
+}
+
+#[utoipa::path(
+    delete, path = "/api/trash/{id}", tag = "trash", operation_id = "purge_trash",
+    params(("id" = String, Path, description = "Memory ID")),
+    responses(
+        (status = 204, description = "Deleted permanently"),
+        (status = 404, description = "Memory not in the trash", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn purge_trash_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+) -> impl IntoResponse {
+    purge_trash(state, caller, path).await
+}
+
+#[utoipa::path(
+    post, path = "/api/maintenance/gc", tag = "maintenance", operation_id = "collect_garbage",
+    params(GcQuery),
+    responses((status = 200, body = GcReport))
+)]
+#[axum::debug_handler]
+async fn collect_garbage_handler(
+    state: State<Arc<ServerState>>,
+    query: Query<GcQuery>,
+) -> impl IntoResponse {
+    collect_garbage(state, query).await
+}
+
+#[utoipa::path(
+    post, path = "/api/memories/{id}/attachments", tag = "attachments", operation_id = "upload_attachments",
+    params(("id" = String, Path, description = "Memory ID")),
+    request_body(content_type = "multipart/form-data", description = "One or more files, up to 25 MB in total"),
+    responses(
+        (status = 201, body = [Attachment]),
+        (status = 400, description = "No files or an invalid file name", body = OpenAiErrorResponse),
+        (status = 404, description = "Memory not found", body = OpenAiErrorResponse),
+        (status = 413, description = "A file is larger than the attachment policy allows", body = OpenAiErrorResponse),
+        (status = 415, description = "A file's type is not allowed or the scanner rejected it", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn upload_attachments_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+    multipart: Multipart,
+) -> impl IntoResponse {
+    upload_attachments(state, caller, path, multipart).await
+}
+
+#[utoipa::path(
+    get, path = "/api/memories/{id}/attachments", tag = "attachments", operation_id = "list_attachments",
+    params(("id" = String, Path, description = "Memory ID")),
+    responses(
+        (status = 200, body = [Attachment]),
+        (status = 404, description = "Memory not found", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn list_attachments_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+) -> impl IntoResponse {
+    list_attachments(state, caller, path).await
+}
+
+#[utoipa::path(
+    get, path = "/api/memories/{id}/attachments/{name}", tag = "attachments", operation_id = "download_attachment",
+    params(
+        ("id" = String, Path, description = "Memory ID"),
+        ("name" = String, Path, description = "Attachment file name"),
+    ),
+    responses(
+        (status = 200, description = "The file, with its detected content type"),
+        (status = 404, description = "Memory or attachment not found", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn download_attachment_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<(String, String)>,
+) -> impl IntoResponse {
+    download_attachment(state, caller, path).await
+}
+
+#[utoipa::path(
+    delete, path = "/api/memories/{id}/attachments/{name}", tag = "attachments", operation_id = "remove_attachment",
+    params(
+        ("id" = String, Path, description = "Memory ID"),
+        ("name" = String, Path, description = "Attachment file name"),
+    ),
+    responses(
+        (status = 204, description = "Attachment removed"),
+        (status = 404, description = "Memory or attachment not found", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn remove_attachment_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<(String, String)>,
+) -> impl IntoResponse {
+    remove_attachment(state, caller, path).await
+}
+
+#[utoipa::path(
+    get, path = "/api/memories/{id}/links", tag = "memories", operation_id = "memory_links",
+    params(("id" = String, Path, description = "Memory ID")),
+    responses(
+        (status = 200, body = OutgoingLinks),
+        (status = 404, description = "Memory not found", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn memory_links_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+) -> impl IntoResponse {
+    memory_links(state, caller, path).await
+}
+
+#[utoipa::path(
+    get, path = "/api/memories/{id}/backlinks", tag = "memories", operation_id = "memory_backlinks",
+    params(("id" = String, Path, description = "Memory ID")),
+    responses(
+        (status = 200, body = Backlinks),
+        (status = 404, description = "Memory not found", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn memory_backlinks_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+) -> impl IntoResponse {
+    memory_backlinks(state, caller, path).await
+}
+
+#[utoipa::path(
+    get, path = "/api/memories/{id}/related", tag = "memories", operation_id = "related_memories",
+    params(("id" = String, Path, description = "Memory ID"), RelatedQuery),
+    responses(
+        (status = 200, description = "The most similar memories by shared tags, links and embeddings, best first", body = [RelatedMemory]),
+        (status = 404, description = "Memory not found", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn related_memories_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+    query: Query<RelatedQuery>,
+) -> impl IntoResponse {
+    related_memories(state, caller, path, query).await
+}
+
+#[utoipa::path(
+    get, path = "/api/events", tag = "memories", operation_id = "memory_events",
+    responses(
+        (status = 200, description = "Server-sent events named after their `type`, one per memory change. \
+            A `resync` event means changes were missed and memories should be reloaded.",
+            content_type = "text/event-stream", body = MemoryEvent),
+    )
+)]
+#[axum::debug_handler]
+async fn memory_events_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+) -> impl IntoResponse {
+    memory_events(state, caller).await
+}
+
+#[utoipa::path(
+    get, path = "/api/graph", tag = "graph", operation_id = "graph",
+    params(GraphOptions),
+    responses(
+        (status = 200, body = Graph),
+        (status = 404, description = "`center` memory not found", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn graph_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    query: Query<GraphOptions>,
+) -> impl IntoResponse {
+    graph(state, caller, query).await
+}
+
+#[utoipa::path(
+    get, path = "/api/collections", tag = "collections", operation_id = "list_collections",
+    responses((status = 200, body = [Collection]))
+)]
+#[axum::debug_handler]
+async fn list_collections_handler(
+    state: State<Arc<ServerState>>,
+) -> impl IntoResponse {
+    list_collections(state).await
+}
+
+#[utoipa::path(
+    post, path = "/api/collections", tag = "collections", operation_id = "create_collection",
+    request_body = CreateCollectionRequest,
+    responses(
+        (status = 201, body = Collection),
+        (status = 400, description = "Invalid name", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn create_collection_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    json: ValidJson<CreateCollectionRequest>,
+) -> impl IntoResponse {
+    create_collection(state, caller, json).await
+}
+
+#[utoipa::path(
+    get, path = "/api/collections/{id}", tag = "collections", operation_id = "get_collection",
+    params(("id" = String, Path, description = "Collection ID")),
+    responses(
+        (status = 200, body = Collection),
+        (status = 404, description = "Collection not found", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn get_collection_handler(
+    state: State<Arc<ServerState>>,
+    path: Path<String>,
+) -> impl IntoResponse {
+    get_collection(state, path).await
+}
+
+#[utoipa::path(
+    put, path = "/api/collections/{id}", tag = "collections", operation_id = "update_collection",
+    params(("id" = String, Path, description = "Collection ID")),
+    request_body = CollectionUpdate,
+    responses(
+        (status = 200, body = Collection),
+        (status = 404, description = "Collection not found", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn update_collection_handler(
+    state: State<Arc<ServerState>>,
+    path: Path<String>,
+    json: ValidJson<CollectionUpdate>,
+) -> impl IntoResponse {
+    update_collection(state, path, json).await
+}
+
+#[utoipa::path(
+    delete, path = "/api/collections/{id}", tag = "collections", operation_id = "delete_collection",
+    params(("id" = String, Path, description = "Collection ID")),
+    responses(
+        (status = 204, description = "Collection deleted; its memories are kept"),
+        (status = 404, description = "Collection not found", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn delete_collection_handler(
+    state: State<Arc<ServerState>>,
+    path: Path<String>,
+) -> impl IntoResponse {
+    delete_collection(state, path).await
+}
+
+#[utoipa::path(
+    post, path = "/api/collections/{id}/memories", tag = "collections", operation_id = "add_to_collection",
+    params(("id" = String, Path, description = "Collection ID")),
+    request_body = CollectionMembersRequest,
+    responses(
+        (status = 200, body = Collection),
+        (status = 404, description = "Collection or memory not found", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn add_to_collection_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+    json: ValidJson<CollectionMembersRequest>,
+) -> impl IntoResponse {
+    add_to_collection(state, caller, path, json).await
+}
+
+#[utoipa::path(
+    delete, path = "/api/collections/{id}/memories/{memory_id}", tag = "collections", operation_id = "remove_from_collection",
+    params(
+        ("id" = String, Path, description = "Collection ID"),
+        ("memory_id" = String, Path, description = "Memory ID"),
+    ),
+    responses(
+        (status = 200, body = Collection),
+        (status = 404, description = "Collection not found", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn remove_from_collection_handler(
+    state: State<Arc<ServerState>>,
+    path: Path<(String, String)>,
+) -> impl IntoResponse {
+    remove_from_collection(state, path).await
+}
+
+#[utoipa::path(
+    get, path = "/api/memories/{id}/references", tag = "references", operation_id = "list_references",
+    params(("id" = String, Path, description = "Memory ID")),
+    responses(
+        (status = 200, body = [Reference]),
+        (status = 404, description = "Memory not found", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn list_references_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+) -> impl IntoResponse {
+    list_references(state, caller, path).await
+}
+
+#[utoipa::path(
+    put, path = "/api/memories/{id}/references", tag = "references", operation_id = "set_references",
+    params(("id" = String, Path, description = "Memory ID")),
+    request_body = [Reference],
+    responses(
+        (status = 200, body = Memory),
+        (status = 400, description = "Invalid reference", body = OpenAiErrorResponse),
+        (status = 404, description = "Memory not found", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn set_references_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+    json: ValidJson<Vec<Reference>>,
+) -> impl IntoResponse {
+    set_references(state, caller, path, json).await
+}
+
+#[utoipa::path(
+    post, path = "/api/memories/{id}/references", tag = "references", operation_id = "add_reference",
+    params(("id" = String, Path, description = "Memory ID")),
+    request_body = Reference,
+    responses(
+        (status = 201, body = Memory),
+        (status = 400, description = "Invalid reference", body = OpenAiErrorResponse),
+        (status = 404, description = "Memory not found", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn add_reference_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+    json: ValidJson<Reference>,
+) -> impl IntoResponse {
+    add_reference(state, caller, path, json).await
+}
+
+#[utoipa::path(
+    delete, path = "/api/memories/{id}/references/{index}", tag = "references", operation_id = "remove_reference",
+    params(
+        ("id" = String, Path, description = "Memory ID"),
+        ("index" = usize, Path, description = "Position of the reference, starting at 0"),
+    ),
+    responses(
+        (status = 200, body = Memory),
+        (status = 404, description = "Memory or reference not found", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn remove_reference_handler(
     state: State<Arc<ServerState>>,
-    json: Json<SearchMemoriesRequest>,
+    caller: Caller,
+    path: Path<(String, usize)>,
 ) -> impl IntoResponse {
-    search_memories(state, json).await
+    remove_reference(state, caller, path).await
 }
@@ -0,0 +1,447 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{delete, get},
+    Json, Router,
+};
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+pub use conduit_types::sessions::{CreateSessionMemoryRequest, SessionInfo};
+
+use crate::memory::{Memory, QueryMatcher, SearchQuery};
+use super::error::{ApiError, OpenAiErrorResponse};
+use super::auth::Caller;
+use super::state::ServerState;
+use super::validation::ValidJson;
+use super::titles::resolve_title;
+
+/// Longest accepted session ID
+const MAX_SESSION_ID_LEN: usize = 128;
+
+/// Limits on the scratch memories of agent sessions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionConfig {
+    /// Seconds a session is kept after its last write when the write gives no TTL
+    pub default_ttl_secs: u64,
+    /// Longest TTL a write may ask for
+    pub max_ttl_secs: u64,
+    /// Most memories a single session may hold
+    pub max_memories: usize,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            default_ttl_secs: 3600,
+            max_ttl_secs: 86400,
+            max_memories: 1000,
+        }
+    }
+}
+
+impl SessionConfig {
+    /// Override settings with `CONDUIT_SESSION_TTL_SECS`, `CONDUIT_SESSION_MAX_TTL_SECS`
+    /// and `CONDUIT_SESSION_MAX_MEMORIES`, if set
+    pub fn apply_env(&mut self) {
+        if let Some(secs) = std::env::var("CONDUIT_SESSION_TTL_SECS").ok().and_then(|secs| secs.parse().ok()) {
+            self.default_ttl_secs = secs;
+        }
+        if let Some(secs) = std::env::var("CONDUIT_SESSION_MAX_TTL_SECS").ok().and_then(|secs| secs.parse().ok()) {
+            self.max_ttl_secs = secs;
+        }
+        if let Some(max) = std::env::var("CONDUIT_SESSION_MAX_MEMORIES").ok().and_then(|max| max.parse().ok()) {
+            self.max_memories = max;
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SessionError {
+    #[error("Invalid session ID: {0}")]
+    InvalidId(String),
+
+    #[error("Session not found: {0}")]
+    NotFound(String),
+
+    #[error("Memory {0} not found in the session")]
+    MemoryNotFound(String),
+
+    #[error("Session {0} already holds the maximum number of memories")]
+    Full(String),
+}
+
+impl SessionError {
+    fn status(&self) -> StatusCode {
+        match self {
+            Self::InvalidId(_) => StatusCode::BAD_REQUEST,
+            Self::NotFound(_) | Self::MemoryNotFound(_) => StatusCode::NOT_FOUND,
+            Self::Full(_) => StatusCode::CONFLICT,
+        }
+    }
+}
+
+impl From<SessionError> for ApiError {
+    fn from(err: SessionError) -> Self {
+        ApiError::status(err.status(), err.to_string())
+    }
+}
+
+struct Session {
+    info: SessionInfo,
+    memories: Vec<Memory>,
+}
+
+impl Session {
+    // Whether the caller may use the session: its owner, or a full-access key
+    fn is_visible_to(&self, owner: Option<&str>) -> bool {
+        owner.is_none() || self.info.owner.as_deref() == owner
+    }
+}
+
+fn validate_id(id: &str) -> Result<(), SessionError> {
+    let valid = !id.is_empty()
+        && id.len() <= MAX_SESSION_ID_LEN
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'));
+    if valid {
+        Ok(())
+    } else {
+        Err(SessionError::InvalidId(id.to_string()))
+    }
+}
+
+// Lifetimes are capped at a century so expiry times never overflow
+fn lifetime(secs: u64) -> Duration {
+    Duration::seconds(secs.min(100 * 365 * 86400) as i64)
+}
+
+/// Scratch memories of agent sessions, held in memory only
+///
+/// Session memories are never written to the store, so they do not show up in
+/// listings, search, events or backups unless a search names the session. A
+/// session is dropped with its memories when it is ended, or once no write has
+/// extended it for its TTL. Restarting the server ends every session.
+pub struct Sessions {
+    config: SessionConfig,
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+impl Sessions {
+    pub fn new(config: SessionConfig) -> Self {
+        Self {
+            config,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Lock the sessions, dropping the ones that have expired
+    fn live(&self) -> std::sync::MutexGuard<'_, HashMap<String, Session>> {
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Utc::now();
+        sessions.retain(|id, session| {
+            let live = session.info.expires_at > now;
+            if !live {
+                debug!("[SESSIONS] Session {} expired with {} memories", id, session.memories.len());
+            }
+            live
+        });
+        sessions
+    }
+
+    // The caller's session with this ID; sessions of other users are reported as missing
+    fn visible<'a>(sessions: &'a mut HashMap<String, Session>, id: &str, owner: Option<&str>) -> Result<&'a mut Session, SessionError> {
+        sessions.get_mut(id)
+            .filter(|session| session.is_visible_to(owner))
+            .ok_or_else(|| SessionError::NotFound(id.to_string()))
+    }
+
+    /// Add a memory to a session, starting the session if needed
+    ///
+    /// The session is kept for `ttl_secs` (or the default) from now, unless it
+    /// was already set to last longer.
+    pub fn add(&self, id: &str, owner: Option<&str>, memory: Memory, ttl_secs: Option<u64>) -> Result<SessionInfo, SessionError> {
+        validate_id(id)?;
+        let ttl = lifetime(ttl_secs.unwrap_or(self.config.default_ttl_secs).min(self.config.max_ttl_secs));
+        let now = Utc::now();
+
+        let mut sessions = self.live();
+        let session = sessions.entry(id.to_string()).or_insert_with(|| Session {
+            info: SessionInfo {
+                id: id.to_string(),
+                owner: owner.map(str::to_string),
+                created_at: now,
+                expires_at: now,
+                memory_count: 0,
+            },
+            memories: Vec::new(),
+        });
+        if !session.is_visible_to(owner) {
+            return Err(SessionError::NotFound(id.to_string()));
+        }
+        if session.memories.len() >= self.config.max_memories {
+            return Err(SessionError::Full(id.to_string()));
+        }
+
+        session.memories.push(memory);
+        session.info.memory_count = session.memories.len();
+        session.info.expires_at = session.info.expires_at.max(now + ttl);
+        Ok(session.info.clone())
+    }
+
+    /// The caller's active sessions, most recently started first
+    pub fn list(&self, owner: Option<&str>) -> Vec<SessionInfo> {
+        let sessions = self.live();
+        let mut listed: Vec<SessionInfo> = sessions.values()
+            .filter(|session| session.is_visible_to(owner))
+            .map(|session| session.info.clone())
+            .collect();
+        listed.sort_by_key(|session| std::cmp::Reverse(session.created_at));
+        listed
+    }
+
+    /// Memories of a session, oldest first
+    pub fn memories(&self, id: &str, owner: Option<&str>) -> Result<Vec<Memory>, SessionError> {
+        let mut sessions = self.live();
+        Ok(Self::visible(&mut sessions, id, owner)?.memories.clone())
+    }
+
+    /// Memories of a session matching a search query, or having a tag
+    ///
+    /// A missing or expired session has no matches.
+    pub fn search(&self, id: &str, owner: Option<&str>, query: &SearchQuery) -> Vec<Memory> {
+        let matcher = QueryMatcher::plain();
+        let mut sessions = self.live();
+        match Self::visible(&mut sessions, id, owner) {
+            Ok(session) => session.memories.iter()
+                .filter(|memory| matcher.matches(memory, query))
+                .cloned()
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Remove one memory from a session
+    pub fn remove(&self, id: &str, owner: Option<&str>, memory_id: &str) -> Result<(), SessionError> {
+        let mut sessions = self.live();
+        let session = Self::visible(&mut sessions, id, owner)?;
+        let before = session.memories.len();
+        session.memories.retain(|memory| memory.id != memory_id);
+        if session.memories.len() == before {
+            return Err(SessionError::MemoryNotFound(memory_id.to_string()));
+        }
+        session.info.memory_count = session.memories.len();
+        Ok(())
+    }
+
+    /// End a session, dropping its memories
+    pub fn end(&self, id: &str, owner: Option<&str>) -> Result<SessionInfo, SessionError> {
+        let mut sessions = self.live();
+        Self::visible(&mut sessions, id, owner)?;
+        let session = sessions.remove(id).ok_or_else(|| SessionError::NotFound(id.to_string()))?;
+        info!("[SESSIONS] Ended session {} with {} memories", id, session.memories.len());
+        Ok(session.info)
+    }
+
+    /// The session's current state, without its memories
+    pub fn get(&self, id: &str, owner: Option<&str>) -> Result<SessionInfo, SessionError> {
+        let mut sessions = self.live();
+        Ok(Self::visible(&mut sessions, id, owner)?.info.clone())
+    }
+}
+
+pub fn router() -> Router<Arc<ServerState>> {
+    Router::new()
+        .route("/api/sessions", get(list_sessions_handler))
+        .route("/api/sessions/:id", get(get_session_handler).delete(end_session_handler))
+        .route("/api/sessions/:id/memories", get(list_session_memories_handler).post(create_session_memory_handler))
+        .route("/api/sessions/:id/memories/:memory_id", delete(delete_session_memory_handler))
+}
+
+async fn list_sessions(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling list_sessions request");
+    Json(state.sessions.list(caller.user()))
+}
+
+async fn get_session(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling get_session request for ID: {}", id);
+    match state.sessions.get(&id, caller.user()) {
+        Ok(session) => (StatusCode::OK, Json(session)).into_response(),
+        Err(err) => ApiError::from(err).into_response(),
+    }
+}
+
+async fn end_session(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling end_session request for ID: {}", id);
+    match state.sessions.end(&id, caller.user()) {
+        Ok(session) => (StatusCode::OK, Json(session)).into_response(),
+        Err(err) => ApiError::from(err).into_response(),
+    }
+}
+
+async fn list_session_memories(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling list_session_memories request for ID: {}", id);
+    match state.sessions.memories(&id, caller.user()) {
+        Ok(memories) => (StatusCode::OK, Json(memories)).into_response(),
+        Err(err) => ApiError::from(err).into_response(),
+    }
+}
+
+async fn create_session_memory(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+    ValidJson(req): ValidJson<CreateSessionMemoryRequest>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling create_session_memory request for session: {}", id);
+
+    if let Err(err) = validate_id(&id) {
+        return ApiError::from(err).into_response();
+    }
+    let title = match resolve_title(&state, req.title, &req.content).await {
+        Ok(title) => title,
+        Err(err) => return ApiError::invalid_request(err).into_response(),
+    };
+
+    let mut memory = Memory::new(title, req.content, req.tags);
+    memory.owner = caller.user().map(str::to_string);
+    match state.sessions.add(&id, caller.user(), memory.clone(), req.ttl_secs) {
+        Ok(_) => (StatusCode::CREATED, Json(memory)).into_response(),
+        Err(err) => ApiError::from(err).into_response(),
+    }
+}
+
+async fn delete_session_memory(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path((id, memory_id)): Path<(String, String)>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling delete_session_memory request for {} in session {}", memory_id, id);
+    match state.sessions.remove(&id, caller.user(), &memory_id) {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(err) => ApiError::from(err).into_response(),
+    }
+}
+
+#[utoipa::path(
+    get, path = "/api/sessions", tag = "sessions", operation_id = "list_sessions",
+    responses((status = 200, description = "Active sessions of the calling user, or every session for API keys", body = [SessionInfo]))
+)]
+#[axum::debug_handler]
+async fn list_sessions_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+) -> impl IntoResponse {
+    list_sessions(state, caller).await
+}
+
+#[utoipa::path(
+    get, path = "/api/sessions/{id}", tag = "sessions", operation_id = "get_session",
+    params(("id" = String, Path, description = "Session ID")),
+    responses(
+        (status = 200, body = SessionInfo),
+        (status = 404, description = "Session not found or expired", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn get_session_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+) -> impl IntoResponse {
+    get_session(state, caller, path).await
+}
+
+#[utoipa::path(
+    delete, path = "/api/sessions/{id}", tag = "sessions", operation_id = "end_session",
+    params(("id" = String, Path, description = "Session ID")),
+    responses(
+        (status = 200, description = "The ended session; its memories are gone", body = SessionInfo),
+        (status = 404, description = "Session not found or expired", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn end_session_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+) -> impl IntoResponse {
+    end_session(state, caller, path).await
+}
+
+#[utoipa::path(
+    get, path = "/api/sessions/{id}/memories", tag = "sessions", operation_id = "list_session_memories",
+    params(("id" = String, Path, description = "Session ID")),
+    responses(
+        (status = 200, description = "Scratch memories of the session, oldest first", body = [Memory]),
+        (status = 404, description = "Session not found or expired", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn list_session_memories_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+) -> impl IntoResponse {
+    list_session_memories(state, caller, path).await
+}
+
+#[utoipa::path(
+    post, path = "/api/sessions/{id}/memories", tag = "sessions", operation_id = "create_session_memory",
+    params(("id" = String, Path, description = "Session ID; the session starts with its first memory")),
+    request_body = CreateSessionMemoryRequest,
+    responses(
+        (status = 201, description = "The scratch memory, which is not saved to the store", body = Memory),
+        (status = 400, description = "Invalid session ID", body = OpenAiErrorResponse),
+        (status = 404, description = "The session belongs to another user", body = OpenAiErrorResponse),
+        (status = 409, description = "The session holds the maximum number of memories", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn create_session_memory_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+    json: ValidJson<CreateSessionMemoryRequest>,
+) -> impl IntoResponse {
+    create_session_memory(state, caller, path, json).await
+}
+
+#[utoipa::path(
+    delete, path = "/api/sessions/{id}/memories/{memory_id}", tag = "sessions", operation_id = "delete_session_memory",
+    params(
+        ("id" = String, Path, description = "Session ID"),
+        ("memory_id" = String, Path, description = "Memory ID"),
+    ),
+    responses(
+        (status = 204, description = "Removed from the session"),
+        (status = 404, description = "Session or memory not found", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn delete_session_memory_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<(String, String)>,
+) -> impl IntoResponse {
+    delete_session_memory(state, caller, path).await
+}
@@ -0,0 +1,251 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::IntoResponse,
+    routing::get,
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+use utoipa::ToSchema;
+
+use crate::config::ConduitConfig;
+use crate::import::{self as importers, ImportFormat};
+use crate::memory::{
+    init_store_dir, starter_templates, Collection, ImportJob, ImportReport, ImportStrategy, MemoryError, MemoryStore,
+    StarterTemplate,
+};
+use super::auth::Caller;
+use super::error::{ApiError, OpenAiErrorResponse};
+use super::state::ServerState;
+use super::validation::ValidJson;
+
+/// Whether the store still needs first-run setup, and what setup offers
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SetupStatus {
+    /// The store was never set up and holds no memories or collections
+    pub needs_setup: bool,
+    /// When setup finished, if it has
+    pub completed_at: Option<DateTime<Utc>>,
+    #[schema(value_type = String)]
+    pub store_path: PathBuf,
+    pub memories: usize,
+    pub collections: usize,
+    /// Collections with templates setup can create
+    pub starter_templates: Vec<StarterTemplate>,
+}
+
+/// Notes to bring into a new store, from a folder or export file on the server
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SetupImport {
+    #[schema(value_type = String)]
+    pub path: PathBuf,
+    /// Kind of export: `markdown`, `notion` or `enex`
+    #[serde(default)]
+    pub format: ImportFormat,
+}
+
+/// Choices made during first-run setup
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct SetupRequest {
+    /// Folder for the store, saved to the config file; the server keeps
+    /// serving its current store until it is restarted
+    #[schema(value_type = Option<String>)]
+    pub store_path: Option<PathBuf>,
+    /// Existing notes to import
+    pub import: Option<SetupImport>,
+    /// Names of the starter templates to create; all of them when left out
+    pub starter_templates: Option<Vec<String>>,
+}
+
+/// What first-run setup did
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SetupReport {
+    #[schema(value_type = String)]
+    pub store_path: PathBuf,
+    /// The store differs from the one being served, which takes a restart to switch to
+    pub restart_required: bool,
+    /// Outcome of the import, if notes were imported
+    pub import: Option<ImportReport>,
+    /// Collections created from starter templates
+    pub collections: Vec<Collection>,
+    pub completed_at: DateTime<Utc>,
+}
+
+/// Set up a store: import existing notes, create starter collections and
+/// record that setup finished
+///
+/// The request's `store_path` is left to the caller, which opens `store`
+/// there. Fails with [`MemoryError::AlreadyExists`] when the store was set
+/// up before.
+pub fn run_setup(store: &MemoryStore, request: &SetupRequest) -> Result<SetupReport, MemoryError> {
+    store.check_writable()?;
+    if let Some(completed_at) = store.setup_completed_at() {
+        return Err(MemoryError::AlreadyExists(format!("store at {} was set up on {}", store.base_path.display(), completed_at)));
+    }
+
+    // Check the template names first, so a typo fails before notes are imported
+    if let Some(names) = &request.starter_templates {
+        let starters = starter_templates();
+        if let Some(unknown) = names.iter().find(|name| !starters.iter().any(|starter| starter.name.eq_ignore_ascii_case(name.trim()))) {
+            return Err(MemoryError::InvalidCollection(format!("No starter template named {}", unknown)));
+        }
+    }
+
+    let import = match &request.import {
+        Some(import) => {
+            // Nothing is in a new store to match, so notes are only ever created
+            let job = ImportJob { strategy: ImportStrategy::Skip, dry_run: false };
+            Some(importers::import_path(store, import.format, &import.path, &job)?)
+        }
+        None => None,
+    };
+    let collections = store.seed_starter_templates(request.starter_templates.as_deref())?;
+    let completed_at = store.complete_setup()?;
+
+    Ok(SetupReport {
+        store_path: store.base_path.clone(),
+        restart_required: false,
+        import,
+        collections,
+        completed_at,
+    })
+}
+
+/// Routes for first-run setup of the store
+pub fn router() -> Router<Arc<ServerState>> {
+    Router::new().route("/api/setup", get(setup_status_handler).post(setup_handler))
+}
+
+async fn setup_status(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling setup_status request");
+    // Counts cover every memory, including ones the caller cannot see
+    if caller.principal().is_some() {
+        return ApiError::permission("Setup requires an API key with access to every memory".to_string()).into_response();
+    }
+
+    let store = &state.memory_store;
+    let status = store.needs_setup().and_then(|needs_setup| Ok(SetupStatus {
+        needs_setup,
+        completed_at: store.setup_completed_at(),
+        store_path: store.base_path.clone(),
+        memories: store.list()?.len(),
+        collections: store.list_collections()?.len(),
+        starter_templates: starter_templates(),
+    }));
+    match status {
+        Ok(status) => (StatusCode::OK, Json(status)).into_response(),
+        Err(err) => ApiError::from(err).into_response(),
+    }
+}
+
+// Whether a path names the same folder as the store, once both are resolved
+fn is_same_store(store: &MemoryStore, path: &Path) -> bool {
+    match (store.base_path.canonicalize(), path.canonicalize()) {
+        (Ok(store), Ok(path)) => store == path,
+        _ => store.base_path == path,
+    }
+}
+
+async fn setup(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    ValidJson(request): ValidJson<SetupRequest>,
+) -> impl IntoResponse {
+    info!(
+        "[SERVER] Handling setup request (store_path: {:?}, import: {}, starter_templates: {:?})",
+        request.store_path, request.import.is_some(), request.starter_templates,
+    );
+    // Setup reads server folders, and may move the store of every user
+    if caller.principal().is_some() {
+        return ApiError::permission("Setup requires an API key with access to every memory".to_string()).into_response();
+    }
+
+    let current = state.memory_store.clone();
+    let setup = tokio::task::spawn_blocking(move || {
+        let Some(path) = request.store_path.clone().filter(|path| !is_same_store(&current, path)) else {
+            return run_setup(&current, &request);
+        };
+
+        // Only first-run onboarding may choose the store; afterwards it is moved in the settings
+        if !current.needs_setup()? {
+            return Err(MemoryError::AlreadyExists(format!(
+                "store at {} is in use, so setup can no longer choose another store", current.base_path.display(),
+            )));
+        }
+
+        // Set up the other store before saving it, so a failed setup leaves the config untouched
+        init_store_dir(&path)?;
+        let store = MemoryStore::with_options(&path, current.options.clone());
+        let mut report = run_setup(&store, &request)?;
+        ConduitConfig::set_store_path(&path).map_err(|e| MemoryError::Io(std::io::Error::other(e)))?;
+        report.restart_required = true;
+        Ok(report)
+    }).await;
+
+    match setup {
+        Ok(Ok(report)) => {
+            // Imports into another store are not activity of the store being served
+            if let (Some(import), false) = (&report.import, report.restart_required) {
+                state.activity.record_import("setup", &Ok(import.clone()));
+            }
+            info!("[SERVER] Set up store at {}", report.store_path.display());
+            (StatusCode::OK, Json(report)).into_response()
+        }
+        Ok(Err(MemoryError::AlreadyExists(store))) => {
+            ApiError::conflict(format!("Setup already finished: the {}", store)).code("already_set_up").into_response()
+        }
+        // Notes that are not a valid export are the client's mistake, not a broken store
+        Ok(Err(err @ MemoryError::InvalidFormat(_))) => ApiError::invalid_request(err.to_string()).into_response(),
+        Ok(Err(err)) => {
+            error!("Error setting up the store: {:?}", err);
+            ApiError::from(err).into_response()
+        }
+        Err(err) => {
+            error!("Setup task failed: {:?}", err);
+            ApiError::server(err.to_string()).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    get, path = "/api/setup", tag = "setup", operation_id = "setup_status",
+    responses(
+        (status = 200, description = "Whether the store needs first-run setup", body = SetupStatus),
+        (status = 403, description = "Caller acts as a user", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn setup_status_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+) -> impl IntoResponse {
+    setup_status(state, caller).await
+}
+
+#[utoipa::path(
+    post, path = "/api/setup", tag = "setup", operation_id = "setup",
+    request_body = SetupRequest,
+    responses(
+        (status = 200, description = "The store was set up", body = SetupReport),
+        (status = 400, description = "Unknown starter template, or notes that cannot be imported", body = OpenAiErrorResponse),
+        (status = 403, description = "Caller acts as a user, or the store is read-only", body = OpenAiErrorResponse),
+        (status = 409, description = "The store was already set up, or `store_path` was given after first run", body = OpenAiErrorResponse),
+        (status = 422, description = "Empty store or import path", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn setup_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    json: ValidJson<SetupRequest>,
+) -> impl IntoResponse {
+    setup(state, caller, json).await
+}
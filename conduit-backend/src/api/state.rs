@@ -1,9 +1,277 @@
-use std::sync::{Arc, Mutex};
-use tokio::sync::oneshot;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use tokio::sync::watch;
+use tracing::info;
 
-use crate::memory::MemoryStore;
+use crate::memory::{EventBus, ImportanceConfig, MemoryStore, RelatedConfig};
+use crate::provider::{EmbeddingRouter, ModelRegistry, UpstreamProvider};
+use crate::rag::RagConfig;
+use super::accounts::Accounts;
+use super::activity::{self, ActivityLog};
+use super::attachment_text::{self, AttachmentTextConfig};
+use super::indexes::IndexJobs;
+use super::auth::AuthConfig;
+use super::auto_tag::AutoTagConfig;
+use super::summarize::SummarizeConfig;
+use super::chat_tools::ChatToolsConfig;
+use super::capture::CaptureConfig;
+use super::cleanup::CleanupConfig;
+use super::connectors::{self, Connectors};
+use super::embedding_sync;
+use super::export::ExportProfiles;
+use super::inbox::Inbox;
+use super::interaction_log::InteractionLog;
+use super::limit::LlmLimiter;
+use super::retention;
+use super::scheduler::{self, Scheduler};
+use super::server::ServerOptions;
+use super::sessions::Sessions;
+use super::sync::{self, Syncer};
+use super::templates::TemplateConfig;
+use super::validation::ValidationConfig;
+use super::titles::TitleConfig;
+use super::tokens::ServiceTokens;
+use super::usage::UsageLedger;
+use super::analytics::{self, StatsHistory};
+use super::webhooks::{self, Webhooks};
 
 pub struct ServerState {
     pub memory_store: Arc<MemoryStore>,
-    pub shutdown_tx: Mutex<Option<oneshot::Sender<()>>>,
+    /// Upstream LLM provider; `None` runs chat completions in offline mode
+    pub provider: Option<Arc<UpstreamProvider>>,
+    /// Models clients can ask for, and the provider serving each
+    pub models: Arc<ModelRegistry>,
+    /// Selects the embedding provider for each request
+    pub embeddings: EmbeddingRouter,
+    /// Retrieval settings for injecting memories into chat context
+    pub rag: RagConfig,
+    /// Concurrency cap for chat and embedding requests
+    pub llm_limiter: LlmLimiter,
+    /// Opt-in log of chat and embedding requests
+    pub interaction_log: Option<InteractionLog>,
+    /// How memories created without a title get one
+    pub titles: TitleConfig,
+    /// How new memories are filled in from their text
+    pub capture: CaptureConfig,
+    /// Whether attachment text is extracted for search, and up to which size
+    pub attachment_text: AttachmentTextConfig,
+    /// Memory changes published by the store, streamed to `/api/events` clients
+    pub events: EventBus,
+    /// Webhooks receiving memory changes, and their delivery log
+    pub webhooks: Arc<Webhooks>,
+    /// External services new memories are forwarded to, and their delivery log
+    pub connectors: Arc<Connectors>,
+    /// Inbound webhooks turning external events into memories
+    pub inbox: Inbox,
+    /// Syncs a git-mode store with its remote, and the state of the last sync
+    pub sync: Syncer,
+    /// Tasks run on a schedule, and the history of their runs
+    pub scheduler: Scheduler,
+    /// Named exports that can be run by name
+    pub export_profiles: ExportProfiles,
+    /// API keys and users allowed to call the server
+    pub auth: AuthConfig,
+    /// Short-lived tokens minted by authenticated callers, and their revocations
+    pub tokens: ServiceTokens,
+    /// Tokens used by model requests per key and day
+    pub usage: UsageLedger,
+    /// Daily snapshots of the store for the trends report
+    pub stats: StatsHistory,
+    /// Scratch memories of agent sessions, kept out of the store
+    pub sessions: Sessions,
+    /// Model and similarity threshold used when planning a cleanup
+    pub cleanup: CleanupConfig,
+    /// How memories lose importance when left untouched, for ranking search and chat context
+    pub importance: ImportanceConfig,
+    /// Default limit and signal weights for related memories
+    pub related: RelatedConfig,
+    /// Whether new memories are tagged by the upstream model, and with which model
+    pub auto_tag: AutoTagConfig,
+    /// Model for summaries, and when listings return them in place of content
+    pub summarize: SummarizeConfig,
+    /// Whether chat models can create, search and update memories with tools
+    pub chat_tools: ChatToolsConfig,
+    /// Saved searches memory templates can list
+    pub templates: TemplateConfig,
+    /// Limits on titles, content and tags in request bodies
+    pub validation: ValidationConfig,
+    /// Memory changes, imports, syncs and task runs for the activity feed
+    pub activity: Arc<ActivityLog>,
+    /// Background jobs updating the indexes, for the index status
+    pub indexing: IndexJobs,
+    /// Name of the workspace served; `None` for the `[storage]` store
+    pub workspace: Option<String>,
+    /// Store folder of every workspace the server hosts, by name
+    pub workspaces: BTreeMap<String, PathBuf>,
+    /// Accounts logging in with a password, shared by the stores of every
+    /// account; `None` unless `[accounts]` is enabled
+    pub accounts: Option<Arc<Accounts>>,
+    /// Account whose own store is served; `None` for the server's stores
+    pub account: Option<String>,
+    /// Address the server is bound to, once it is; unset when the routes are mounted in another application
+    pub listen_addr: OnceLock<SocketAddr>,
+    pub started_at: DateTime<Utc>,
+    /// Turns true when the server shuts down, ending long-lived streams
+    pub shutdown: watch::Receiver<bool>,
+    stop: watch::Sender<bool>,
+}
+
+impl ServerState {
+    /// Set up everything the API routes need and start the background tasks
+    ///
+    /// Opens the webhook, token and interaction logs, connects the upstream
+    /// provider, and starts embedding sync, attachment text extraction, webhook
+    /// and connector delivery, scheduled tasks and git sync as configured. Must
+    /// be called from within a tokio runtime. The tasks run until [`ServerState::stop`].
+    pub fn start(memory_store: Arc<MemoryStore>, options: &ServerOptions) -> Result<Arc<Self>, String> {
+        let accounts = match options.accounts.enabled {
+            true => Some(Arc::new(Accounts::open(&options.accounts, &memory_store, options)?)),
+            false => None,
+        };
+        Self::start_with(memory_store, options, accounts, None)
+    }
+
+    /// Set up the state of an account's own store, sharing the server's accounts
+    pub fn start_for_account(
+        memory_store: Arc<MemoryStore>,
+        options: &ServerOptions,
+        accounts: Arc<Accounts>,
+        account: &str,
+    ) -> Result<Arc<Self>, String> {
+        Self::start_with(memory_store, options, Some(accounts), Some(account.to_string()))
+    }
+
+    fn start_with(
+        memory_store: Arc<MemoryStore>,
+        options: &ServerOptions,
+        accounts: Option<Arc<Accounts>>,
+        account: Option<String>,
+    ) -> Result<Arc<Self>, String> {
+        // Set up the upstream LLM provider, if configured
+        let provider = match options.provider.clone() {
+            Some(config) => {
+                info!("Using upstream LLM provider at {}", config.base_url);
+                Some(Arc::new(UpstreamProvider::new(config).map_err(|e| format!("Failed to create upstream provider: {}", e))?))
+            }
+            None => {
+                info!("No upstream LLM provider configured, chat completions run in offline mode");
+                None
+            }
+        };
+        
+        let models = Arc::new(ModelRegistry::new(
+            &options.models,
+            &options.providers,
+            provider.clone(),
+            options.embeddings.local_dimensions,
+        )?);
+        
+        let interaction_log = match options.interaction_log.clone() {
+            Some(config) => Some(InteractionLog::open(config)?),
+            None => None,
+        };
+        
+        let export_profiles = ExportProfiles::new(options.export.clone())?;
+        options.templates.check()?;
+        let scheduler = Scheduler::new(options.scheduler.clone(), &options.templates)?;
+        scheduler.check_export_profiles(&export_profiles)?;
+        
+        // Event streams and background tasks never finish on their own, so they are told to end
+        let (stop, shutdown) = watch::channel(false);
+        
+        info!("Creating shared server state");
+        let state = Arc::new(Self {
+            events: memory_store.events().clone(),
+            webhooks: Arc::new(Webhooks::open(options.webhooks.clone(), &memory_store)?),
+            tokens: ServiceTokens::open(&options.auth, &memory_store)?,
+            usage: UsageLedger::open(&memory_store)?,
+            stats: StatsHistory::open(&memory_store, &options.analytics)?,
+            memory_store,
+            embeddings: EmbeddingRouter::new(&options.embeddings, provider.clone(), models.clone()),
+            models,
+            provider,
+            rag: options.rag.clone(),
+            llm_limiter: LlmLimiter::new(options.llm_max_concurrency, Duration::from_millis(options.llm_queue_timeout_ms)),
+            interaction_log,
+            titles: options.titles.clone(),
+            capture: options.capture.clone(),
+            attachment_text: options.attachment_text.clone(),
+            connectors: Arc::new(Connectors::new(options.connectors.clone())?),
+            inbox: Inbox::new(options.inbox.clone(), options.auth.is_enabled())?,
+            sync: Syncer::new(options.sync.clone()),
+            scheduler,
+            export_profiles,
+            auth: options.auth.clone(),
+            sessions: Sessions::new(options.sessions.clone()),
+            cleanup: options.cleanup.clone(),
+            importance: options.importance.clone(),
+            related: options.related.clone(),
+            auto_tag: options.auto_tag.clone(),
+            summarize: options.summarize.clone(),
+            chat_tools: options.chat_tools.clone(),
+            templates: options.templates.clone(),
+            validation: options.validation.clone(),
+            activity: Arc::new(ActivityLog::new(options.activity.clone())),
+            indexing: IndexJobs::default(),
+            workspace: options.workspace.clone(),
+            workspaces: options.workspaces.clone(),
+            accounts,
+            account,
+            listen_addr: OnceLock::new(),
+            started_at: Utc::now(),
+            shutdown,
+            stop,
+        });
+        info!("Server state created successfully");
+        
+        // Embed new and changed memories in the background
+        if options.embeddings.sync_interval_secs > 0 {
+            embedding_sync::spawn(Arc::downgrade(&state), Duration::from_secs(options.embeddings.sync_interval_secs));
+        }
+        
+        // Extract the text of new and replaced attachments for search
+        if options.attachment_text.interval_secs > 0 {
+            attachment_text::spawn(Arc::downgrade(&state), options.attachment_text.clone());
+        }
+        
+        // Record memory changes for the activity feed
+        activity::spawn(state.activity.clone(), &state);
+        
+        // Send memory changes to the webhooks
+        webhooks::spawn(state.webhooks.clone(), &state);
+        
+        // Forward new memories matching the rules to external services
+        connectors::spawn(state.connectors.clone(), &state);
+        
+        // Run scheduled tasks, such as weekly review memories and nightly exports
+        scheduler::spawn(&state);
+        
+        // Move members of collections with a retention period to the trash once they expire
+        retention::spawn(Arc::downgrade(&state));
+        
+        // Measure the store once a day or more for the trends report
+        if options.analytics.snapshot_interval_secs > 0 {
+            analytics::spawn(Arc::downgrade(&state), Duration::from_secs(options.analytics.snapshot_interval_secs));
+        }
+        
+        // Pull and push remote changes in the background
+        if options.sync.interval_secs > 0 {
+            sync::spawn(Arc::downgrade(&state), Duration::from_secs(options.sync.interval_secs));
+        }
+        
+        Ok(state)
+    }
+    
+    /// End the event streams and background tasks, as when the server shuts down
+    pub fn stop(&self) {
+        self.stop.send_replace(true);
+        // The server's store also stops the stores of the accounts
+        if let (Some(accounts), None) = (&self.accounts, &self.account) {
+            accounts.stop();
+        }
+    }
 }
@@ -0,0 +1,257 @@
+use serde_json::{Map, Value};
+
+use super::openai::{ChatMessage, JsonSchemaFormat, ResponseFormat};
+
+/// The format a request asks for, unless it is plain text
+pub fn requested(format: Option<&ResponseFormat>) -> Option<&ResponseFormat> {
+    format.filter(|format| !matches!(format, ResponseFormat::Text))
+}
+
+/// Check that a response format can be enforced
+pub fn check(format: &ResponseFormat) -> Result<(), String> {
+    let ResponseFormat::JsonSchema { json_schema } = format else {
+        return Ok(());
+    };
+    if json_schema.name.trim().is_empty() {
+        return Err("response_format.json_schema.name must not be empty".to_string());
+    }
+    match &json_schema.schema {
+        Some(schema) if !schema.is_object() => Err("response_format.json_schema.schema must be an object".to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// System message asking for JSON, for models that do not honour `response_format`
+pub fn instruction(format: &ResponseFormat) -> ChatMessage {
+    let content = match format {
+        ResponseFormat::JsonSchema { json_schema } => match &json_schema.schema {
+            Some(schema) => format!(
+                "Reply with a single JSON value matching this JSON Schema, and nothing else:\n{}",
+                schema
+            ),
+            None => "Reply with a single JSON object, and nothing else.".to_string(),
+        },
+        _ => "Reply with a single JSON object, and nothing else.".to_string(),
+    };
+    ChatMessage { role: "system".to_string(), content: content.into(), ..Default::default() }
+}
+
+/// The JSON of a reply, if it has the requested format
+///
+/// Markdown code fences and surrounding whitespace are removed. The error
+/// says where the reply differs from the schema.
+pub fn validate(format: &ResponseFormat, content: &str) -> Result<String, String> {
+    let json = strip_fences(content);
+    let value: Value = serde_json::from_str(json).map_err(|err| format!("The reply is not valid JSON: {}", err))?;
+    match format {
+        ResponseFormat::Text => {}
+        ResponseFormat::JsonObject => {
+            if !value.is_object() {
+                return Err("The reply is not a JSON object".to_string());
+            }
+        }
+        ResponseFormat::JsonSchema { json_schema } => match &json_schema.schema {
+            Some(schema) => matches_schema(schema, &value, "$")?,
+            None if !value.is_object() => return Err("The reply is not a JSON object".to_string()),
+            None => {}
+        },
+    }
+    Ok(json.to_string())
+}
+
+// Models that are only asked for JSON often wrap it in ```json fences
+fn strip_fences(content: &str) -> &str {
+    let trimmed = content.trim();
+    let Some(inner) = trimmed.strip_prefix("```").and_then(|rest| rest.strip_suffix("```")) else {
+        return trimmed;
+    };
+    // Drop the language after the opening fence
+    inner.split_once('\n').map_or(inner, |(_, body)| body).trim()
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(number) if number.is_i64() || number.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn has_type(value: &Value, expected: &str) -> bool {
+    let actual = type_name(value);
+    actual == expected || (expected == "number" && actual == "integer")
+}
+
+// Check a value against the common keywords of JSON Schema; others, such as
+// `$ref` and `pattern`, are not enforced
+fn matches_schema(schema: &Value, value: &Value, path: &str) -> Result<(), String> {
+    let Some(schema) = schema.as_object() else {
+        // `true` accepts everything and `false` nothing
+        return match schema {
+            Value::Bool(false) => Err(format!("{}: no value is allowed here", path)),
+            _ => Ok(()),
+        };
+    };
+
+    match schema.get("type") {
+        Some(Value::String(expected)) if !has_type(value, expected) => {
+            return Err(format!("{}: expected {}, found {}", path, expected, type_name(value)));
+        }
+        Some(Value::Array(expected)) if !expected.iter().filter_map(Value::as_str).any(|expected| has_type(value, expected)) => {
+            let expected: Vec<&str> = expected.iter().filter_map(Value::as_str).collect();
+            return Err(format!("{}: expected {}, found {}", path, expected.join(" or "), type_name(value)));
+        }
+        _ => {}
+    }
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            return Err(format!("{}: {} is not one of the allowed values", path, value));
+        }
+    }
+    if let Some(constant) = schema.get("const") {
+        if constant != value {
+            return Err(format!("{}: must be {}", path, constant));
+        }
+    }
+
+    if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+        for option in all {
+            matches_schema(option, value, path)?;
+        }
+    }
+    let matching = |options: &Vec<Value>| options.iter().filter(|option| matches_schema(option, value, path).is_ok()).count();
+    if schema.get("anyOf").and_then(Value::as_array).is_some_and(|options| matching(options) == 0) {
+        return Err(format!("{}: matches none of the anyOf schemas", path));
+    }
+    if schema.get("oneOf").and_then(Value::as_array).is_some_and(|options| matching(options) != 1) {
+        return Err(format!("{}: must match exactly one of the oneOf schemas", path));
+    }
+
+    match value {
+        Value::Object(object) => matches_object(schema, object, path),
+        Value::Array(items) => {
+            let bound = |keyword: &str| schema.get(keyword).and_then(Value::as_u64);
+            let count = items.len() as u64;
+            if let Some(min) = bound("minItems").filter(|min| count < *min) {
+                return Err(format!("{}: needs at least {} items", path, min));
+            }
+            if let Some(max) = bound("maxItems").filter(|max| count > *max) {
+                return Err(format!("{}: allows at most {} items", path, max));
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    matches_schema(item_schema, item, &format!("{}[{}]", path, index))?;
+                }
+            }
+            Ok(())
+        }
+        Value::String(text) => {
+            let length = text.chars().count() as u64;
+            let bound = |keyword: &str| schema.get(keyword).and_then(Value::as_u64);
+            if let Some(min) = bound("minLength").filter(|min| length < *min) {
+                return Err(format!("{}: needs at least {} characters", path, min));
+            }
+            if let Some(max) = bound("maxLength").filter(|max| length > *max) {
+                return Err(format!("{}: allows at most {} characters", path, max));
+            }
+            Ok(())
+        }
+        Value::Number(number) => {
+            let number = number.as_f64().unwrap_or_default();
+            let bound = |keyword: &str| schema.get(keyword).and_then(Value::as_f64);
+            if let Some(minimum) = bound("minimum").filter(|minimum| number < *minimum) {
+                return Err(format!("{}: must be at least {}", path, minimum));
+            }
+            if let Some(maximum) = bound("maximum").filter(|maximum| number > *maximum) {
+                return Err(format!("{}: must be at most {}", path, maximum));
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn matches_object(schema: &Map<String, Value>, object: &Map<String, Value>, path: &str) -> Result<(), String> {
+    let empty = Map::new();
+    let properties = schema.get("properties").and_then(Value::as_object).unwrap_or(&empty);
+
+    for required in schema.get("required").and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str) {
+        if !object.contains_key(required) {
+            return Err(format!("{}: missing required property {}", path, required));
+        }
+    }
+    for (key, value) in object {
+        let key_path = format!("{}.{}", path, key);
+        match (properties.get(key), schema.get("additionalProperties")) {
+            (Some(property), _) => matches_schema(property, value, &key_path)?,
+            (None, Some(Value::Bool(false))) => return Err(format!("{}: unexpected property", key_path)),
+            (None, Some(additional)) => matches_schema(additional, value, &key_path)?,
+            (None, None) => {}
+        }
+    }
+    Ok(())
+}
+
+/// The reply of offline mode in the requested format
+///
+/// Without a schema the message and memory titles are returned as an object;
+/// with one, the smallest value the schema accepts is built.
+pub fn offline_reply(format: &ResponseFormat, message: &str, titles: &[String]) -> String {
+    let value = match format {
+        ResponseFormat::JsonSchema { json_schema: JsonSchemaFormat { schema: Some(schema), .. } } => skeleton(schema),
+        _ => serde_json::json!({ "message": message, "memories": titles }),
+    };
+    value.to_string()
+}
+
+// A value with only what the schema requires
+fn skeleton(schema: &Value) -> Value {
+    let Some(schema) = schema.as_object() else {
+        return Value::Null;
+    };
+    if let Some(constant) = schema.get("const") {
+        return constant.clone();
+    }
+    if let Some(first) = schema.get("enum").and_then(Value::as_array).and_then(|allowed| allowed.first()) {
+        return first.clone();
+    }
+    for keyword in ["allOf", "anyOf", "oneOf"] {
+        if let Some(first) = schema.get(keyword).and_then(Value::as_array).and_then(|options| options.first()) {
+            return skeleton(first);
+        }
+    }
+
+    let kind = match schema.get("type") {
+        Some(Value::String(kind)) => kind.as_str(),
+        Some(Value::Array(kinds)) => kinds.iter().filter_map(Value::as_str).next().unwrap_or("null"),
+        None if schema.contains_key("properties") => "object",
+        None => "null",
+        Some(_) => "null",
+    };
+    match kind {
+        "object" => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            let required = schema.get("required").and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str);
+            let object = required
+                .map(|key| (key.to_string(), properties.and_then(|properties| properties.get(key)).map_or(Value::Null, skeleton)))
+                .collect();
+            Value::Object(object)
+        }
+        "array" => {
+            let count = schema.get("minItems").and_then(Value::as_u64).unwrap_or(0) as usize;
+            let item = schema.get("items").map_or(Value::Null, skeleton);
+            Value::Array(vec![item; count])
+        }
+        "string" => {
+            let length = schema.get("minLength").and_then(Value::as_u64).unwrap_or(0) as usize;
+            Value::String(" ".repeat(length))
+        }
+        "integer" | "number" => schema.get("minimum").cloned().unwrap_or_else(|| Value::from(0)),
+        "boolean" => Value::Bool(false),
+        _ => Value::Null,
+    }
+}
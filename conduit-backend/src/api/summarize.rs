@@ -0,0 +1,306 @@
+use std::sync::Arc;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::memory::{summary_from_content, Memory, MemoryError, Permission};
+use super::error::{ApiError, OpenAiErrorResponse};
+use super::auth::Caller;
+use super::openai::{ChatCompletionRequest, ChatMessage};
+use super::state::ServerState;
+
+/// Longest summary asked of the model when none is configured, in words
+const DEFAULT_MAX_WORDS: usize = 50;
+
+/// Memories longer than this, in characters, are listed with their summary when asked
+const DEFAULT_LARGE_NOTE_CHARS: usize = 2000;
+
+/// Most content sent to the model when summarizing, in characters
+const MAX_PROMPT_CHARS: usize = 12000;
+
+/// Settings for summarizing memories with the upstream model, set under `[summarize]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SummarizeConfig {
+    /// Model asked for summaries; without one memories cannot be summarized
+    pub model: Option<String>,
+    /// Longest summary asked for, in words
+    pub max_words: usize,
+    /// Memories longer than this, in characters, are listed with their summary
+    /// in place of their content when the list asks for `summaries`
+    pub large_note_chars: usize,
+}
+
+impl Default for SummarizeConfig {
+    fn default() -> Self {
+        Self {
+            model: None,
+            max_words: DEFAULT_MAX_WORDS,
+            large_note_chars: DEFAULT_LARGE_NOTE_CHARS,
+        }
+    }
+}
+
+impl SummarizeConfig {
+    /// Override settings with `CONDUIT_SUMMARIZE_MODEL`, `CONDUIT_SUMMARIZE_MAX_WORDS`
+    /// and `CONDUIT_SUMMARIZE_LARGE_NOTE_CHARS`, if set
+    pub fn apply_env(&mut self) {
+        if let Some(model) = std::env::var("CONDUIT_SUMMARIZE_MODEL").ok().filter(|model| !model.is_empty()) {
+            self.model = Some(model);
+        }
+        if let Some(max_words) = std::env::var("CONDUIT_SUMMARIZE_MAX_WORDS").ok().and_then(|v| v.parse().ok()) {
+            self.max_words = max_words;
+        }
+        if let Some(chars) = std::env::var("CONDUIT_SUMMARIZE_LARGE_NOTE_CHARS").ok().and_then(|v| v.parse().ok()) {
+            self.large_note_chars = chars;
+        }
+    }
+
+    /// Replace the content of a large memory with its summary, for listings
+    ///
+    /// Memories without a stored summary get the start of their content as
+    /// plain text instead.
+    pub fn abridge(&self, memory: &mut Memory) {
+        if memory.content.chars().count() <= self.large_note_chars {
+            return;
+        }
+        memory.content = match &memory.summary {
+            Some(summary) => summary.clone(),
+            None => summary_from_content(&memory.content),
+        };
+    }
+}
+
+/// Routes for summarizing memories with the upstream model
+pub fn router() -> Router<Arc<ServerState>> {
+    Router::new()
+        .route("/api/memories/summarize", post(summarize_store_handler))
+        .route("/api/memories/:id/summarize", post(summarize_memory_handler))
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(default)]
+pub struct SummarizeStoreQuery {
+    /// Also summarize memories that already have a summary
+    pub force: bool,
+    /// Only summarize memories with at least this many characters of content
+    pub min_chars: Option<usize>,
+    /// Most memories to ask the model about
+    pub limit: Option<usize>,
+}
+
+/// Summary stored for a memory
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SummaryResult {
+    pub id: String,
+    pub title: String,
+    pub summary: String,
+}
+
+/// Outcome of summarizing the existing store
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SummarizeReport {
+    /// Memories the model was asked about
+    pub checked: usize,
+    /// Memories that were given a summary
+    pub items: Vec<SummaryResult>,
+    /// Memories that could not be summarized, by ID, with why
+    pub failed: Vec<SummarizeFailure>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SummarizeFailure {
+    pub id: String,
+    pub error: String,
+}
+
+// Ask the upstream model for a short summary of a memory
+async fn summarize(state: &ServerState, model: &str, memory: &Memory) -> Result<String, String> {
+    let provider = state.provider.as_ref().ok_or("No upstream provider is configured")?;
+    let content: String = memory.content.chars().take(MAX_PROMPT_CHARS).collect();
+    let max_words = state.summarize.max_words.max(1);
+
+    let req = ChatCompletionRequest {
+        model: model.to_string(),
+        messages: vec![
+            ChatMessage {
+                role: "system".to_string(),
+                content: format!(
+                    "You summarize the notes of a note-taking app. Summarize the user's note in at most {} words, \
+                    as plain text on one line, in the language of the note. Reply with the summary only.",
+                    max_words,
+                ).into(),
+                ..Default::default()
+            },
+            ChatMessage {
+                role: "user".to_string(),
+                content: format!("{}\n\n{}", memory.title, content).into(),
+                ..Default::default()
+            },
+        ],
+        temperature: Some(0.2),
+        max_tokens: Some((max_words * 3).max(64) as i32),
+        stream: None,
+        response_format: None,
+        tools: Vec::new(),
+        tool_choice: None,
+        conversation_id: None,
+        conduit: None,
+    };
+
+    let completion = provider.chat_completion(&req).await.map_err(|err| err.to_string())?;
+    let reply = completion.choices.into_iter().next().map(|choice| choice.message.content.text()).unwrap_or_default();
+    let summary = reply.split_whitespace().collect::<Vec<_>>().join(" ");
+    if summary.is_empty() {
+        return Err(format!("{} replied with an empty summary", model));
+    }
+    Ok(summary)
+}
+
+// The configured model, or why memories cannot be summarized
+fn configured_model(state: &ServerState) -> Result<String, &'static str> {
+    match (&state.summarize.model, &state.provider) {
+        (Some(model), Some(_)) => Ok(model.clone()),
+        (None, _) => Err("No summarize model is configured"),
+        (_, None) => Err("No upstream provider is configured"),
+    }
+}
+
+fn error_response(context: &str, err: MemoryError) -> Response {
+    error!("Error {}: {:?}", context, err);
+    ApiError::from(err).into_response()
+}
+
+async fn summarize_memory(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling summarize_memory request for id: {}", id);
+    let model = match configured_model(&state) {
+        Ok(model) => model,
+        Err(err) => return ApiError::status(StatusCode::SERVICE_UNAVAILABLE, err.to_string()).into_response(),
+    };
+    let memory = match state.memory_store.authorize(&id, caller.principal(), Permission::Write) {
+        Ok(memory) => memory,
+        Err(err) => return error_response("authorizing summary", err),
+    };
+    if memory.read_only {
+        return error_response("summarizing memory", MemoryError::ReadOnly(id));
+    }
+
+    let summary = match summarize(&state, &model, &memory).await {
+        Ok(summary) => summary,
+        Err(err) => {
+            error!("[SUMMARIZE] Summarizing {} failed: {}", id, err);
+            return ApiError::status(StatusCode::BAD_GATEWAY, err).into_response();
+        }
+    };
+    match state.memory_store.set_summary(&id, Some(&summary)) {
+        Ok(memory) => {
+            let summary = memory.summary.unwrap_or_default();
+            (StatusCode::OK, Json(SummaryResult { id, title: memory.title, summary })).into_response()
+        }
+        Err(err) => error_response("storing summary", err),
+    }
+}
+
+async fn summarize_store(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Query(query): Query<SummarizeStoreQuery>,
+) -> impl IntoResponse {
+    info!(
+        "[SERVER] Handling summarize_store request (force: {}, min_chars: {:?}, limit: {:?})",
+        query.force, query.min_chars, query.limit,
+    );
+    // Summarizing the store changes every user's memories
+    if caller.principal().is_some() {
+        return ApiError::permission("Summarizing the store requires an API key with access to every memory".to_string()).into_response();
+    }
+    let model = match configured_model(&state) {
+        Ok(model) => model,
+        Err(err) => return ApiError::status(StatusCode::SERVICE_UNAVAILABLE, err.to_string()).into_response(),
+    };
+    let memories = match state.memory_store.list() {
+        Ok(memories) => memories,
+        Err(err) => return error_response("listing memories", err),
+    };
+
+    let mut report = SummarizeReport { checked: 0, items: Vec::new(), failed: Vec::new() };
+    let min_chars = query.min_chars.unwrap_or(0);
+    let candidates = memories.iter()
+        .filter(|memory| !memory.read_only && (query.force || memory.summary.is_none()))
+        .filter(|memory| memory.content.chars().count() >= min_chars)
+        .take(query.limit.unwrap_or(usize::MAX));
+    for memory in candidates {
+        report.checked += 1;
+        let stored = match summarize(&state, &model, memory).await {
+            Ok(summary) => state.memory_store.set_summary(&memory.id, Some(&summary)).map_err(|err| err.to_string()),
+            Err(err) => Err(err),
+        };
+        match stored {
+            Ok(stored) => report.items.push(SummaryResult {
+                id: stored.id,
+                title: stored.title,
+                summary: stored.summary.unwrap_or_default(),
+            }),
+            Err(err) => {
+                warn!("[SUMMARIZE] Summarizing {} failed: {}", memory.id, err);
+                report.failed.push(SummarizeFailure { id: memory.id.clone(), error: err });
+            }
+        }
+    }
+
+    info!(
+        "[SUMMARIZE] Checked {} memories, {} summarized, {} failed",
+        report.checked, report.items.len(), report.failed.len(),
+    );
+    (StatusCode::OK, Json(report)).into_response()
+}
+
+#[utoipa::path(
+    post, path = "/api/memories/{id}/summarize", tag = "memories", operation_id = "summarize_memory",
+    params(("id" = String, Path, description = "Memory ID")),
+    responses(
+        (status = 200, description = "Summary written by the model and stored in the memory's frontmatter", body = SummaryResult),
+        (status = 403, description = "The memory is read-only", body = OpenAiErrorResponse),
+        (status = 404, description = "Memory not found", body = OpenAiErrorResponse),
+        (status = 502, description = "The model failed or replied with an empty summary", body = OpenAiErrorResponse),
+        (status = 503, description = "No summarize model or upstream provider is configured", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn summarize_memory_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+) -> impl IntoResponse {
+    summarize_memory(state, caller, path).await
+}
+
+#[utoipa::path(
+    post, path = "/api/memories/summarize", tag = "maintenance", operation_id = "summarize_store",
+    params(SummarizeStoreQuery),
+    responses(
+        (status = 200, description = "Summaries stored for the existing memories", body = SummarizeReport),
+        (status = 403, description = "The API key cannot access every memory", body = OpenAiErrorResponse),
+        (status = 503, description = "No summarize model or upstream provider is configured", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn summarize_store_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    query: Query<SummarizeStoreQuery>,
+) -> impl IntoResponse {
+    summarize_store(state, caller, query).await
+}
@@ -0,0 +1,368 @@
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+
+use crate::memory::{
+    ConflictResolution, ConflictStrategy, Memory, MemoryAccess, MemoryError, MemoryStore, MergedVersion,
+    Permission, QuarantinedConflict, SyncReport, SyncScope,
+};
+use super::auth::Caller;
+use super::error::{ApiError, OpenAiErrorResponse};
+use super::state::ServerState;
+use super::validation::ValidJson;
+
+/// Where and how often a git-mode store syncs with a remote repository
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncConfig {
+    /// URL of the remote; without one, the `origin` remote of the store repository is used
+    pub remote: Option<String>,
+    /// Remote branch to sync with, defaults to the current local branch
+    pub branch: Option<String>,
+    /// How memories changed on both sides are resolved
+    pub strategy: ConflictStrategy,
+    /// Sync every this many seconds; `0` only syncs on request
+    pub interval_secs: u64,
+}
+
+impl SyncConfig {
+    /// Override settings with `CONDUIT_SYNC_REMOTE`, `CONDUIT_SYNC_BRANCH`,
+    /// `CONDUIT_SYNC_STRATEGY` (`ours`, `theirs`, `duplicate` or `quarantine`) and
+    /// `CONDUIT_SYNC_INTERVAL_SECS`, if set
+    pub fn apply_env(&mut self) {
+        if let Ok(remote) = std::env::var("CONDUIT_SYNC_REMOTE") {
+            self.remote = Some(remote).filter(|remote| !remote.is_empty());
+        }
+        if let Ok(branch) = std::env::var("CONDUIT_SYNC_BRANCH") {
+            self.branch = Some(branch).filter(|branch| !branch.is_empty());
+        }
+        if let Some(strategy) = std::env::var("CONDUIT_SYNC_STRATEGY").ok().and_then(|strategy| strategy.parse().ok()) {
+            self.strategy = strategy;
+        }
+        if let Some(secs) = std::env::var("CONDUIT_SYNC_INTERVAL_SECS").ok().and_then(|secs| secs.parse().ok()) {
+            self.interval_secs = secs;
+        }
+    }
+}
+
+/// State of syncing with the remote
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct SyncStatus {
+    /// Whether the store runs in git mode, which syncing requires
+    pub git_enabled: bool,
+    pub remote: Option<String>,
+    pub branch: Option<String>,
+    pub strategy: ConflictStrategy,
+    /// Seconds between background syncs; `0` when only syncing on request
+    pub interval_secs: u64,
+    pub running: bool,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    pub last_success_at: Option<DateTime<Utc>>,
+    /// Why the last sync failed; cleared by the next successful one
+    pub last_error: Option<String>,
+    /// Outcome of the last successful sync
+    pub last_report: Option<SyncReport>,
+    /// Which memories are committed and synced
+    pub scope: SyncScope,
+    /// Memories kept out of the repository, by the scope or their collection
+    pub local_only: usize,
+}
+
+/// Syncs the store with its remote, one sync at a time
+pub struct Syncer {
+    config: SyncConfig,
+    status: Mutex<SyncStatus>,
+    running: tokio::sync::Mutex<()>,
+}
+
+impl Syncer {
+    pub fn new(config: SyncConfig) -> Self {
+        Self {
+            config,
+            status: Mutex::new(SyncStatus::default()),
+            running: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    pub fn status(&self, store: &MemoryStore) -> SyncStatus {
+        let mut status = self.status.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        status.git_enabled = store.is_git_enabled();
+        status.remote = self.config.remote.clone();
+        status.branch = self.config.branch.clone();
+        status.strategy = self.config.strategy;
+        status.interval_secs = self.config.interval_secs;
+        status.scope = store.options.sync_scope.clone();
+        status.running = self.running.try_lock().is_err();
+        status
+    }
+
+    /// Sync now, or return `None` if a sync is already running
+    pub async fn run(&self, store: Arc<MemoryStore>, strategy: Option<ConflictStrategy>) -> Option<Result<SyncReport, MemoryError>> {
+        let _running = self.running.try_lock().ok()?;
+        self.status.lock().unwrap_or_else(|e| e.into_inner()).last_attempt_at = Some(Utc::now());
+
+        let remote = self.config.remote.clone();
+        let branch = self.config.branch.clone();
+        let strategy = strategy.unwrap_or(self.config.strategy);
+        let result = tokio::task::spawn_blocking(move || store.sync_remote(remote.as_deref(), branch.as_deref(), strategy))
+            .await
+            .unwrap_or_else(|e| Err(MemoryError::Git(format!("Sync panicked: {}", e))));
+
+        let mut status = self.status.lock().unwrap_or_else(|e| e.into_inner());
+        match &result {
+            Ok(report) => {
+                status.last_success_at = Some(report.completed_at);
+                status.last_error = None;
+                status.last_report = Some(report.clone());
+            }
+            Err(err) => status.last_error = Some(err.to_string()),
+        }
+        Some(result)
+    }
+}
+
+/// Sync the store every `interval` until the server state is dropped
+///
+/// Nothing is synced while the store is read-only, not in git mode or locked.
+pub fn spawn(state: Weak<ServerState>, interval: Duration) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+            let Some(state) = state.upgrade() else {
+                break;
+            };
+
+            let store = &state.memory_store;
+            if store.is_git_enabled() && !store.is_read_only() && !store.workspace_lock().is_locked() {
+                let result = state.sync.run(state.memory_store.clone(), None).await;
+                if let Some(result) = &result {
+                    state.activity.record_sync(result);
+                }
+                match result {
+                    Some(Ok(report)) => info!("[SYNC] Synced, {} memories pulled, {} conflicts", report.pulled.len(), report.conflicts.len()),
+                    Some(Err(e)) => warn!("[SYNC] Sync failed, retrying later: {}", e),
+                    None => {}
+                }
+            }
+        }
+
+        info!("[SYNC] Server stopped, ending background sync");
+    });
+}
+
+pub fn router() -> Router<Arc<ServerState>> {
+    Router::new()
+        .route("/api/sync", post(sync_handler))
+        .route("/api/sync/status", get(sync_status_handler))
+        .route("/api/conflicts", get(list_conflicts_handler))
+        .route("/api/conflicts/:id", get(get_conflict_handler))
+        .route("/api/conflicts/:id/resolve", post(resolve_conflict_handler))
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct SyncRequest {
+    /// Overrides the configured conflict strategy for this sync
+    #[serde(default)]
+    pub strategy: Option<ConflictStrategy>,
+}
+
+/// Which version of a quarantined memory to keep
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ResolveConflictRequest {
+    pub resolution: ConflictResolution,
+    /// Title, content or tags of the merged memory, replacing the automatic merge
+    #[serde(flatten)]
+    pub merged: MergedVersion,
+}
+
+async fn sync(
+    State(state): State<Arc<ServerState>>,
+    request: Option<Json<SyncRequest>>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling sync request");
+
+    let strategy = request.and_then(|Json(request)| request.strategy);
+    let result = state.sync.run(state.memory_store.clone(), strategy).await;
+    if let Some(result) = &result {
+        state.activity.record_sync(result);
+    }
+    match result {
+        Some(Ok(report)) => (StatusCode::OK, Json(report)).into_response(),
+        Some(Err(err)) => {
+            error!("[SERVER] Sync failed: {}", err);
+            match err {
+                MemoryError::Git(_) => ApiError::upstream(err.to_string()).code("sync_failed").into_response(),
+                _ => ApiError::from(err).into_response(),
+            }
+        }
+        None => ApiError::conflict("A sync is already running".to_string()).into_response(),
+    }
+}
+
+async fn sync_status(
+    State(state): State<Arc<ServerState>>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling sync_status request");
+    let mut status = state.sync.status(&state.memory_store);
+    if status.git_enabled {
+        let store = state.memory_store.clone();
+        match tokio::task::spawn_blocking(move || store.local_only_memories()).await {
+            Ok(Ok(local)) => status.local_only = local.len(),
+            Ok(Err(e)) => warn!("[SYNC] Failed to count local-only memories: {}", e),
+            Err(e) => warn!("[SYNC] Counting local-only memories panicked: {}", e),
+        }
+    }
+    Json(status)
+}
+
+async fn list_conflicts(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling list_conflicts request");
+
+    match state.memory_store.list_conflicts() {
+        Ok(mut conflicts) => {
+            conflicts.retain(|conflict| {
+                conflict.mine.iter().chain(&conflict.theirs).any(|version| version.is_visible_to(caller.principal()))
+            });
+            (StatusCode::OK, Json(conflicts)).into_response()
+        }
+        Err(err) => {
+            error!("Error listing sync conflicts: {:?}", err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn get_conflict(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling get_conflict request for id: {}", id);
+
+    let conflict = match state.memory_store.get_conflict(&id) {
+        Ok(conflict) => conflict,
+        Err(err) => return ApiError::from(err).into_response(),
+    };
+    if let Err(err) = state.memory_store.authorize_conflict(&conflict, caller.principal(), Permission::Read) {
+        return ApiError::from(err).into_response();
+    }
+    (StatusCode::OK, Json(conflict)).into_response()
+}
+
+async fn resolve_conflict(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(id): Path<String>,
+    ValidJson(request): ValidJson<ResolveConflictRequest>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling resolve_conflict request for id: {} ({:?})", id, request.resolution);
+
+    let authorized = state.memory_store.get_conflict(&id)
+        .and_then(|conflict| state.memory_store.authorize_conflict(&conflict, caller.principal(), Permission::Write));
+    if let Err(err) = authorized {
+        return ApiError::from(err).into_response();
+    }
+
+    match state.memory_store.resolve_quarantined(&id, request.resolution, request.merged) {
+        Ok(Some(memory)) => (StatusCode::OK, Json(memory)).into_response(),
+        Ok(None) => StatusCode::NO_CONTENT.into_response(),
+        Err(err) => {
+            error!("Error resolving sync conflict {}: {:?}", id, err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    post, path = "/api/sync", tag = "sync", operation_id = "sync",
+    request_body(content = Option<SyncRequest>, description = "Optional settings for this sync"),
+    responses(
+        (status = 200, description = "Remote changes were merged and local commits pushed", body = SyncReport),
+        (status = 400, description = "Git mode is disabled", body = OpenAiErrorResponse),
+        (status = 403, description = "The store is read-only", body = OpenAiErrorResponse),
+        (status = 409, description = "A sync is already running", body = OpenAiErrorResponse),
+        (status = 502, description = "The remote could not be reached or git failed", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn sync_handler(
+    state: State<Arc<ServerState>>,
+    json: Option<Json<SyncRequest>>,
+) -> impl IntoResponse {
+    sync(state, json).await
+}
+
+#[utoipa::path(
+    get, path = "/api/sync/status", tag = "sync", operation_id = "sync_status",
+    responses((status = 200, body = SyncStatus))
+)]
+#[axum::debug_handler]
+async fn sync_status_handler(
+    state: State<Arc<ServerState>>,
+) -> impl IntoResponse {
+    sync_status(state).await
+}
+
+#[utoipa::path(
+    get, path = "/api/conflicts", tag = "sync", operation_id = "list_conflicts",
+    responses((status = 200, description = "Memories changed on both sides of a sync, with both versions, oldest first", body = Vec<QuarantinedConflict>))
+)]
+#[axum::debug_handler]
+async fn list_conflicts_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+) -> impl IntoResponse {
+    list_conflicts(state, caller).await
+}
+
+#[utoipa::path(
+    get, path = "/api/conflicts/{id}", tag = "sync", operation_id = "get_conflict",
+    params(("id" = String, Path, description = "Memory ID")),
+    responses(
+        (status = 200, description = "Both versions of the memory", body = QuarantinedConflict),
+        (status = 404, description = "No conflict is quarantined for the memory", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn get_conflict_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+) -> impl IntoResponse {
+    get_conflict(state, caller, path).await
+}
+
+#[utoipa::path(
+    post, path = "/api/conflicts/{id}/resolve", tag = "sync", operation_id = "resolve_conflict",
+    params(("id" = String, Path, description = "Memory ID")),
+    request_body = ResolveConflictRequest,
+    responses(
+        (status = 200, description = "The memory as kept", body = Memory),
+        (status = 204, description = "The memory was deleted, as the remote did"),
+        (status = 403, description = "The store is read-only or the caller cannot change the memory", body = OpenAiErrorResponse),
+        (status = 404, description = "No conflict is quarantined for the memory", body = OpenAiErrorResponse),
+        (status = 422, description = "Invalid resolution, or too long a title, content or tag list", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn resolve_conflict_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    path: Path<String>,
+    json: ValidJson<ResolveConflictRequest>,
+) -> impl IntoResponse {
+    resolve_conflict(state, caller, path, json).await
+}
@@ -0,0 +1,177 @@
+use std::sync::Arc;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
+    Json, Router,
+};
+use serde::Deserialize;
+use tracing::{error, info};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::memory::{MemoryError, TagAlias, TagMigration};
+use super::error::{ApiError, OpenAiErrorResponse};
+use super::auth::Caller;
+use super::state::ServerState;
+use super::validation::ValidJson;
+
+/// Routes for managing tag aliases
+pub fn router() -> Router<Arc<ServerState>> {
+    Router::new()
+        .route("/api/tags/aliases", get(list_tag_aliases_handler))
+        .route("/api/tags/aliases/apply", post(apply_tag_aliases_handler))
+        .route("/api/tags/aliases/:alias", put(set_tag_alias_handler).delete(remove_tag_alias_handler))
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetTagAliasRequest {
+    /// The tag the alias stands for
+    pub tag: String,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(default)]
+pub struct ApplyTagAliasesQuery {
+    /// Only report which memories would change
+    pub dry_run: bool,
+}
+
+fn error_response(context: &str, err: MemoryError) -> Response {
+    error!("Error {}: {:?}", context, err);
+    ApiError::from(err).into_response()
+}
+
+// Aliases change the tags of every user's memories
+fn check_caller(caller: &Caller) -> Option<Response> {
+    caller.principal().is_some().then(|| {
+        ApiError::permission("Changing tag aliases requires an API key with access to every memory".to_string()).into_response()
+    })
+}
+
+async fn list_tag_aliases(State(state): State<Arc<ServerState>>) -> impl IntoResponse {
+    info!("[SERVER] Handling list_tag_aliases request");
+    match state.memory_store.tag_aliases() {
+        Ok(aliases) => (StatusCode::OK, Json(aliases)).into_response(),
+        Err(err) => error_response("listing tag aliases", err),
+    }
+}
+
+async fn set_tag_alias(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(alias): Path<String>,
+    ValidJson(req): ValidJson<SetTagAliasRequest>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling set_tag_alias request: {} -> {}", alias, req.tag);
+    if let Some(response) = check_caller(&caller) {
+        return response;
+    }
+    match state.memory_store.set_tag_alias(&alias, &req.tag) {
+        Ok(alias) => (StatusCode::OK, Json(alias)).into_response(),
+        Err(err) => error_response("setting tag alias", err),
+    }
+}
+
+async fn remove_tag_alias(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Path(alias): Path<String>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling remove_tag_alias request: {}", alias);
+    if let Some(response) = check_caller(&caller) {
+        return response;
+    }
+    match state.memory_store.remove_tag_alias(&alias) {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(err) => error_response("removing tag alias", err),
+    }
+}
+
+async fn apply_tag_aliases(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Query(query): Query<ApplyTagAliasesQuery>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling apply_tag_aliases request (dry_run: {})", query.dry_run);
+    if let Some(response) = check_caller(&caller) {
+        return response;
+    }
+    let store = state.memory_store.clone();
+    match tokio::task::spawn_blocking(move || store.apply_tag_aliases(query.dry_run)).await {
+        Ok(Ok(migration)) => {
+            info!("[SERVER] Retagged {} memories", migration.memories.len());
+            (StatusCode::OK, Json(migration)).into_response()
+        }
+        Ok(Err(err)) => error_response("applying tag aliases", err),
+        Err(err) => {
+            error!("Tag alias task failed: {:?}", err);
+            ApiError::server(err.to_string()).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    get, path = "/api/tags/aliases", tag = "tags", operation_id = "list_tag_aliases",
+    responses((status = 200, description = "Every tag alias, ordered by alias", body = [TagAlias]))
+)]
+#[axum::debug_handler]
+async fn list_tag_aliases_handler(state: State<Arc<ServerState>>) -> impl IntoResponse {
+    list_tag_aliases(state).await
+}
+
+#[utoipa::path(
+    put, path = "/api/tags/aliases/{alias}", tag = "tags", operation_id = "set_tag_alias",
+    params(("alias" = String, Path, description = "Tag to replace, such as `js`")),
+    request_body = SetTagAliasRequest,
+    responses(
+        (status = 200, description = "The alias; memories saved from now on get its tag", body = TagAlias),
+        (status = 400, description = "Empty, or an alias of itself", body = OpenAiErrorResponse),
+        (status = 403, description = "The caller cannot access every memory", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn set_tag_alias_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    alias: Path<String>,
+    req: ValidJson<SetTagAliasRequest>,
+) -> impl IntoResponse {
+    set_tag_alias(state, caller, alias, req).await
+}
+
+#[utoipa::path(
+    delete, path = "/api/tags/aliases/{alias}", tag = "tags", operation_id = "remove_tag_alias",
+    params(("alias" = String, Path, description = "Alias to remove")),
+    responses(
+        (status = 204, description = "Alias removed; memories keep the tags they have"),
+        (status = 403, description = "The caller cannot access every memory", body = OpenAiErrorResponse),
+        (status = 404, description = "No such alias", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn remove_tag_alias_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    alias: Path<String>,
+) -> impl IntoResponse {
+    remove_tag_alias(state, caller, alias).await
+}
+
+#[utoipa::path(
+    post, path = "/api/tags/aliases/apply", tag = "tags", operation_id = "apply_tag_aliases",
+    params(ApplyTagAliasesQuery),
+    responses(
+        (status = 200, description = "Memories whose tags were replaced by the tags their aliases stand for", body = TagMigration),
+        (status = 403, description = "The caller cannot access every memory, or the store is read-only", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn apply_tag_aliases_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    query: Query<ApplyTagAliasesQuery>,
+) -> impl IntoResponse {
+    apply_tag_aliases(state, caller, query).await
+}
@@ -0,0 +1,227 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::memory::{filter_visible, MemoryError, Principal, SearchQuery};
+use super::state::ServerState;
+
+/// Most memories a search placeholder lists when no limit is configured
+const DEFAULT_SEARCH_LIMIT: usize = 20;
+
+/// Saved searches whose results memory templates can list
+///
+/// ```toml
+/// [templates]
+/// search_limit = 10
+///
+/// [templates.searches]
+/// open-tasks = "tag:todo -tag:done"
+/// blockers = "tag:blocker -tag:resolved"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TemplateConfig {
+    /// Queries in the search language, by the name `{{search:<name>}}` uses
+    pub searches: BTreeMap<String, String>,
+    /// Most memories a search placeholder lists, most recently updated first
+    pub search_limit: usize,
+}
+
+impl Default for TemplateConfig {
+    fn default() -> Self {
+        Self {
+            searches: BTreeMap::new(),
+            search_limit: DEFAULT_SEARCH_LIMIT,
+        }
+    }
+}
+
+impl TemplateConfig {
+    /// Override the search limit with `CONDUIT_TEMPLATE_SEARCH_LIMIT`, if set
+    pub fn apply_env(&mut self) {
+        if let Some(limit) = std::env::var("CONDUIT_TEMPLATE_SEARCH_LIMIT").ok().and_then(|v| v.parse().ok()) {
+            self.search_limit = limit;
+        }
+    }
+
+    /// Check that every saved search parses
+    pub fn check(&self) -> Result<(), String> {
+        for (name, query) in &self.searches {
+            SearchQuery::parse(query).map_err(|e| format!("Saved search {}: {}", name, e.0))?;
+        }
+        Ok(())
+    }
+}
+
+// A `{{...}}` placeholder of a template
+enum Placeholder<'a> {
+    /// The time of instantiation in a `strftime` format
+    Date(&'a str),
+    /// A value of the context object, by its dotted path
+    Context { path: &'a str, fallback: Option<&'a str> },
+    /// The results of a saved search, by its name
+    Search(&'a str),
+    /// Anything else, which is left as it is
+    Unknown,
+}
+
+impl<'a> Placeholder<'a> {
+    fn parse(placeholder: &'a str) -> Self {
+        match placeholder.trim() {
+            "date" => Self::Date("%Y-%m-%d"),
+            "time" => Self::Date("%H:%M"),
+            "weekday" => Self::Date("%A"),
+            "week" => Self::Date("%G-W%V"),
+            placeholder => {
+                if let Some(format) = placeholder.strip_prefix("date:") {
+                    Self::Date(format)
+                } else if let Some(name) = placeholder.strip_prefix("search:") {
+                    Self::Search(name.trim())
+                } else if let Some(path) = placeholder.strip_prefix("context.") {
+                    match path.split_once('|') {
+                        Some((path, fallback)) => Self::Context { path: path.trim(), fallback: Some(fallback.trim()) },
+                        None => Self::Context { path: path.trim(), fallback: None },
+                    }
+                } else {
+                    Self::Unknown
+                }
+            }
+        }
+    }
+}
+
+// The placeholders of a template, in order; fails on an unclosed `{{`
+fn placeholders(template: &str) -> Result<Vec<Placeholder<'_>>, String> {
+    let mut placeholders = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let end = rest[start..].find("}}").ok_or_else(|| format!("unclosed `{{{{` in {:?}", template))?;
+        placeholders.push(Placeholder::parse(&rest[start + 2..start + end]));
+        rest = &rest[start + end + 2..];
+    }
+    Ok(placeholders)
+}
+
+// Replace each placeholder with what `value` returns for it, keeping those it has none for
+fn replace<'a>(template: &'a str, mut value: impl FnMut(Placeholder<'a>) -> Option<String>) -> String {
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        rendered.push_str(&rest[..start]);
+        match value(Placeholder::parse(&rest[start + 2..start + end])) {
+            Some(text) => rendered.push_str(&text),
+            None => rendered.push_str(&rest[start..start + end + 2]),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+/// Replace the date placeholders of a template: `{{date}}`, `{{time}}`,
+/// `{{weekday}}`, `{{week}}` and `{{date:<strftime>}}`
+pub(super) fn render(template: &str, at: DateTime<Local>) -> String {
+    replace(template, |placeholder| match placeholder {
+        Placeholder::Date(format) => Some(at.format(format).to_string()),
+        _ => None,
+    })
+}
+
+/// Check the `strftime` formats of a template, which would panic when rendered
+pub(super) fn check_template(template: &str) -> Result<(), String> {
+    for placeholder in placeholders(template)? {
+        if let Placeholder::Date(format) = placeholder {
+            if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+                return Err(format!("invalid date format {:?}", format));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check a memory template, including that the saved searches it lists exist
+pub(super) fn check(template: &str, config: &TemplateConfig) -> Result<(), String> {
+    check_template(template)?;
+    for placeholder in placeholders(template)? {
+        if let Placeholder::Search(name) = placeholder {
+            if !config.searches.contains_key(name) {
+                return Err(format!("no saved search named {:?}", name));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Fill in a memory template as it is instantiated
+///
+/// Besides the dates, `{{context.<path>}}` is replaced with the value at that
+/// dotted path of `context`, or the text after `|` when it is missing, and
+/// `{{search:<name>}}` with a list of links to the memories the saved search
+/// finds that `principal` can see. Unknown searches are left as they are.
+pub(super) async fn instantiate(
+    state: &ServerState,
+    template: &str,
+    context: Option<&Value>,
+    principal: Option<&Principal>,
+    at: DateTime<Local>,
+) -> Result<String, MemoryError> {
+    let mut results = HashMap::new();
+    for placeholder in placeholders(template).unwrap_or_default() {
+        let Placeholder::Search(name) = placeholder else {
+            continue;
+        };
+        let Some(query) = state.templates.searches.get(name).filter(|_| !results.contains_key(name)) else {
+            continue;
+        };
+        let store = state.memory_store.clone();
+        let query = query.clone();
+        let principal = principal.cloned();
+        let limit = state.templates.search_limit;
+        let listed = tokio::task::spawn_blocking(move || {
+            let query = SearchQuery::parse(&query)?;
+            let mut memories = filter_visible(store.search_query(&query)?, principal.as_ref());
+            memories.sort_by_key(|memory| Reverse(memory.updated_at));
+            memories.truncate(limit);
+            // Brackets in a title would end the link early
+            let links: Vec<String> = memories.iter()
+                .map(|memory| format!("- [[{}|{}]]", memory.id, memory.title.replace(['[', ']', '|'], "")))
+                .collect();
+            Ok::<_, MemoryError>(links.join("\n"))
+        })
+        .await
+        .unwrap_or_else(|e| Err(MemoryError::Io(std::io::Error::other(format!("Template search panicked: {}", e)))))?;
+        results.insert(name, listed);
+    }
+
+    Ok(replace(template, |placeholder| match placeholder {
+        Placeholder::Date(format) => Some(at.format(format).to_string()),
+        Placeholder::Context { path, fallback } => Some(
+            context.and_then(|context| lookup(context, path))
+                .map(value_text)
+                .unwrap_or_else(|| fallback.unwrap_or_default().to_string()),
+        ),
+        Placeholder::Search(name) => results.get(name).cloned(),
+        Placeholder::Unknown => None,
+    }))
+}
+
+fn lookup<'a>(context: &'a Value, path: &str) -> Option<&'a Value> {
+    let value = path.split('.').try_fold(context, |value, key| match value {
+        Value::Array(items) => items.get(key.trim().parse::<usize>().ok()?),
+        value => value.get(key.trim()),
+    })?;
+    (!value.is_null()).then_some(value)
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
@@ -0,0 +1,139 @@
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::memory::title_from_content;
+use super::openai::{ChatCompletionRequest, ChatMessage};
+use super::state::ServerState;
+
+/// Title used when neither the client nor generation produced one
+const UNTITLED: &str = "Untitled";
+
+/// Most content sent to the model when generating a title, in characters
+const MAX_PROMPT_CHARS: usize = 2000;
+
+/// How memories created without a title get one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TitleGeneration {
+    /// Use the first heading or sentence of the content
+    Heuristic,
+    /// Ask the upstream model, falling back to the heuristic
+    Llm,
+    /// Reject memories without a title
+    Off,
+}
+
+impl std::str::FromStr for TitleGeneration {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "heuristic" => Ok(Self::Heuristic),
+            "llm" => Ok(Self::Llm),
+            "off" | "none" => Ok(Self::Off),
+            other => Err(format!("Unknown title generation mode: {}", other)),
+        }
+    }
+}
+
+/// Settings for generating titles of untitled memories
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TitleConfig {
+    pub mode: TitleGeneration,
+    /// Model asked for titles in `llm` mode
+    pub model: Option<String>,
+}
+
+impl Default for TitleConfig {
+    fn default() -> Self {
+        Self {
+            mode: TitleGeneration::Heuristic,
+            model: None,
+        }
+    }
+}
+
+impl TitleConfig {
+    /// Read title settings from `CONDUIT_TITLE_GENERATION` and `CONDUIT_TITLE_MODEL`,
+    /// falling back to the defaults
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        config.apply_env();
+        config
+    }
+
+    /// Override settings with the `CONDUIT_TITLE_*` variables that are set
+    pub fn apply_env(&mut self) {
+        if let Some(mode) = std::env::var("CONDUIT_TITLE_GENERATION").ok().and_then(|v| v.parse().ok()) {
+            self.mode = mode;
+        }
+        if let Some(model) = std::env::var("CONDUIT_TITLE_MODEL").ok().filter(|model| !model.is_empty()) {
+            self.model = Some(model);
+        }
+    }
+}
+
+// Ask the upstream model for a title
+async fn llm_title(state: &ServerState, model: &str, content: &str) -> Option<String> {
+    let provider = state.provider.as_ref()?;
+    let content: String = content.chars().take(MAX_PROMPT_CHARS).collect();
+
+    let req = ChatCompletionRequest {
+        model: model.to_string(),
+        messages: vec![
+            ChatMessage {
+                role: "system".to_string(),
+                content: "Write a short title of at most eight words for the user's note. Reply with the title only.".into(),
+                ..Default::default()
+            },
+            ChatMessage {
+                role: "user".to_string(),
+                content: content.into(),
+                ..Default::default()
+            },
+        ],
+        temperature: Some(0.2),
+        max_tokens: Some(24),
+        stream: None,
+        response_format: None,
+        tools: Vec::new(),
+        tool_choice: None,
+        conversation_id: None,
+        conduit: None,
+    };
+
+    match provider.chat_completion(&req).await {
+        Ok(completion) => completion.choices.into_iter().next()
+            .and_then(|choice| choice.message.content.text().lines().map(str::trim).find(|line| !line.is_empty()).map(str::to_string))
+            .map(|title| title.trim_matches(['"', '\'', '*', '#', ' ']).trim_end_matches('.').to_string())
+            .filter(|title| !title.is_empty()),
+        Err(err) => {
+            warn!("[TITLES] Title generation failed, using the content instead: {}", err);
+            None
+        }
+    }
+}
+
+/// The title for a new memory
+///
+/// Returns the given title when it is not blank, and otherwise generates one
+/// according to the server's title settings. Fails when generation is off.
+pub async fn resolve_title(state: &ServerState, title: String, content: &str) -> Result<String, String> {
+    if !title.trim().is_empty() {
+        return Ok(title);
+    }
+
+    let generated = match (state.titles.mode, &state.titles.model) {
+        (TitleGeneration::Off, _) => return Err("A title is required".to_string()),
+        (TitleGeneration::Llm, Some(model)) => match llm_title(state, model, content).await {
+            Some(title) => Some(title),
+            None => title_from_content(content),
+        },
+        _ => title_from_content(content),
+    };
+
+    let title = generated.unwrap_or_else(|| UNTITLED.to_string());
+    info!("[TITLES] Generated title: {}", title);
+    Ok(title)
+}
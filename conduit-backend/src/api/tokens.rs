@@ -0,0 +1,464 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use chrono::{DateTime, Duration, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{error, info};
+use utoipa::ToSchema;
+
+use crate::memory::MemoryStore;
+use crate::util::write_atomic;
+use super::error::{ApiError, OpenAiErrorResponse};
+use super::auth::{AuthConfig, Caller};
+use super::state::ServerState;
+use super::validation::ValidJson;
+
+/// File in the store directory holding issued tokens, including revoked ones
+const TOKENS_FILE: &str = ".service-tokens.json";
+
+/// Prefix that tells service tokens apart from API keys
+pub const TOKEN_PREFIX: &str = "cst_";
+
+/// How long expired tokens stay listed before they are dropped from the file
+const EXPIRED_RETENTION_DAYS: i64 = 7;
+
+/// A short-lived credential for automations, acting as the key that minted it
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ServiceToken {
+    pub id: String,
+    pub name: String,
+    /// User whose access the token has; `None` for tokens minted with an API key
+    pub user: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub renewed_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl ServiceToken {
+    /// Whether the token can still authenticate requests
+    pub fn is_active(&self) -> bool {
+        self.revoked_at.is_none() && self.expires_at > Utc::now()
+    }
+}
+
+/// A newly minted or renewed token, with the secret that is never shown again
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct IssuedToken {
+    /// Send as `Authorization: Bearer <token>`
+    pub token: String,
+    #[serde(flatten)]
+    pub info: ServiceToken,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredToken {
+    #[serde(flatten)]
+    info: ServiceToken,
+    /// SHA-256 of the secret; the secret itself is not kept
+    hash: String,
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct CreateTokenRequest {
+    /// What the token is for, e.g. the automation using it
+    pub name: String,
+    /// Lifetime in seconds, defaults to `token_ttl_secs` and is capped at `token_max_ttl_secs`
+    #[serde(default)]
+    pub ttl_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct RenewTokenRequest {
+    /// New lifetime in seconds from now, with the same default and cap as minting
+    #[serde(default)]
+    pub ttl_secs: Option<u64>,
+}
+
+/// Set on requests authenticated with a service token, holding its ID
+#[derive(Debug, Clone)]
+pub struct TokenAuth(pub String);
+
+#[derive(Debug, thiserror::Error)]
+pub enum TokenError {
+    #[error("Token name is empty")]
+    InvalidName,
+
+    #[error("Token not found: {0}")]
+    NotFound(String),
+
+    #[error("Token {0} has expired or was revoked")]
+    Inactive(String),
+
+    #[error("Service tokens cannot mint other tokens")]
+    MintedByToken,
+
+    #[error("Service tokens need API keys or users to be configured")]
+    AuthDisabled,
+
+    #[error("Failed to save tokens: {0}")]
+    Storage(String),
+}
+
+impl TokenError {
+    fn status(&self) -> StatusCode {
+        match self {
+            Self::InvalidName | Self::AuthDisabled => StatusCode::BAD_REQUEST,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::Inactive(_) => StatusCode::CONFLICT,
+            Self::MintedByToken => StatusCode::FORBIDDEN,
+            Self::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<TokenError> for ApiError {
+    fn from(err: TokenError) -> Self {
+        ApiError::status(err.status(), err.to_string())
+    }
+}
+
+fn generate_token() -> String {
+    let bytes: [u8; 32] = rand::thread_rng().gen();
+    format!("{}{}", TOKEN_PREFIX, hex::encode(bytes))
+}
+
+// Lifetimes are capped at a century so expiry times never overflow
+fn lifetime(secs: u64) -> Duration {
+    Duration::seconds(secs.min(100 * 365 * 86400) as i64)
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Issued service tokens, saved in the store directory
+///
+/// Only hashes of the secrets are saved. Revoked tokens stay in the file until
+/// a week after they would have expired, so a restart never brings them back.
+pub struct ServiceTokens {
+    enabled: bool,
+    default_ttl: Duration,
+    max_ttl: Duration,
+    tokens: Mutex<Vec<StoredToken>>,
+    path: PathBuf,
+}
+
+impl ServiceTokens {
+    pub fn open(config: &AuthConfig, store: &MemoryStore) -> Result<Self, String> {
+        let path = store.base_path.join(TOKENS_FILE);
+        let tokens = if path.is_file() {
+            let json = fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read tokens file {}: {}", path.display(), e))?;
+            serde_json::from_str(&json)
+                .map_err(|e| format!("Invalid tokens file {}: {}", path.display(), e))?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            enabled: config.is_enabled(),
+            default_ttl: lifetime(config.token_ttl_secs),
+            max_ttl: lifetime(config.token_max_ttl_secs),
+            tokens: Mutex::new(tokens),
+            path,
+        })
+    }
+
+    fn ttl(&self, ttl_secs: Option<u64>) -> Duration {
+        ttl_secs
+            .map(lifetime)
+            .unwrap_or(self.default_ttl)
+            .min(self.max_ttl)
+    }
+
+    /// The active token with this secret, if any
+    pub fn verify(&self, token: &str) -> Option<ServiceToken> {
+        let hash = hash_token(token);
+        let tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner());
+        tokens.iter()
+            .find(|stored| stored.hash == hash)
+            .map(|stored| stored.info.clone())
+            .filter(ServiceToken::is_active)
+    }
+
+    /// Tokens of a user, or every token for `None`, newest first
+    pub fn list(&self, user: Option<&str>) -> Vec<ServiceToken> {
+        let tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner());
+        let mut listed: Vec<ServiceToken> = tokens.iter()
+            .map(|stored| stored.info.clone())
+            .filter(|token| user.is_none() || token.user.as_deref() == user)
+            .collect();
+        listed.sort_by_key(|token| std::cmp::Reverse(token.created_at));
+        listed
+    }
+
+    /// Mint a token acting as `user`, or with full access for `None`
+    pub fn mint(&self, request: CreateTokenRequest, user: Option<&str>) -> Result<IssuedToken, TokenError> {
+        if !self.enabled {
+            return Err(TokenError::AuthDisabled);
+        }
+        let name = request.name.trim();
+        if name.is_empty() {
+            return Err(TokenError::InvalidName);
+        }
+
+        let token = generate_token();
+        let now = Utc::now();
+        let info = ServiceToken {
+            id: ulid::Ulid::new().to_string(),
+            name: name.to_string(),
+            user: user.map(str::to_string),
+            created_at: now,
+            expires_at: now + self.ttl(request.ttl_secs),
+            renewed_at: None,
+            revoked_at: None,
+        };
+
+        self.update(|tokens| {
+            tokens.push(StoredToken { info: info.clone(), hash: hash_token(&token) });
+            Ok(())
+        })?;
+        Ok(IssuedToken { token, info })
+    }
+
+    /// Replace the secret of an active token and extend its lifetime
+    ///
+    /// The previous secret stops working immediately.
+    pub fn renew(&self, id: &str, ttl_secs: Option<u64>) -> Result<IssuedToken, TokenError> {
+        let token = generate_token();
+        let ttl = self.ttl(ttl_secs);
+        let info = self.update(|tokens| {
+            let stored = tokens.iter_mut()
+                .find(|stored| stored.info.id == id)
+                .ok_or_else(|| TokenError::NotFound(id.to_string()))?;
+            if !stored.info.is_active() {
+                return Err(TokenError::Inactive(id.to_string()));
+            }
+
+            let now = Utc::now();
+            stored.hash = hash_token(&token);
+            stored.info.expires_at = now + ttl;
+            stored.info.renewed_at = Some(now);
+            Ok(stored.info.clone())
+        })?;
+        Ok(IssuedToken { token, info })
+    }
+
+    /// Revoke a token; it stays listed as revoked
+    pub fn revoke(&self, id: &str) -> Result<ServiceToken, TokenError> {
+        self.update(|tokens| {
+            let stored = tokens.iter_mut()
+                .find(|stored| stored.info.id == id)
+                .ok_or_else(|| TokenError::NotFound(id.to_string()))?;
+            stored.info.revoked_at.get_or_insert_with(Utc::now);
+            Ok(stored.info.clone())
+        })
+    }
+
+    /// The token with this ID, if it exists
+    pub fn get(&self, id: &str) -> Option<ServiceToken> {
+        let tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner());
+        tokens.iter().find(|stored| stored.info.id == id).map(|stored| stored.info.clone())
+    }
+
+    // Change the tokens and save them, leaving them unchanged if saving fails
+    fn update<T>(&self, change: impl FnOnce(&mut Vec<StoredToken>) -> Result<T, TokenError>) -> Result<T, TokenError> {
+        let mut tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner());
+        let mut changed = tokens.clone();
+        let result = change(&mut changed)?;
+
+        let cutoff = Utc::now() - Duration::days(EXPIRED_RETENTION_DAYS);
+        changed.retain(|stored| stored.info.expires_at > cutoff);
+
+        let json = serde_json::to_string_pretty(&changed).map_err(|e| TokenError::Storage(e.to_string()))?;
+        write_atomic(&self.path, json).map_err(|e| TokenError::Storage(e.to_string()))?;
+
+        *tokens = changed;
+        Ok(result)
+    }
+}
+
+pub fn router() -> Router<Arc<ServerState>> {
+    Router::new()
+        .route("/api/tokens", get(list_tokens_handler).post(create_token_handler))
+        .route("/api/tokens/:id", axum::routing::delete(revoke_token_handler))
+        .route("/api/tokens/:id/renew", post(renew_token_handler))
+}
+
+// Whether the caller may manage a token: its own user, a full-access key, or the token itself
+fn can_manage(token: &ServiceToken, caller: &Caller, auth: Option<&TokenAuth>) -> bool {
+    match auth {
+        Some(TokenAuth(id)) if *id == token.id => true,
+        Some(_) => false,
+        None => caller.user().is_none() || caller.user() == token.user.as_deref(),
+    }
+}
+
+async fn list_tokens(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling list_tokens request");
+    Json(state.tokens.list(caller.user()))
+}
+
+async fn create_token(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    auth: Option<Extension<TokenAuth>>,
+    ValidJson(request): ValidJson<CreateTokenRequest>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling create_token request for {}", request.name);
+
+    // Tokens are saved in the store
+    if let Err(err) = state.memory_store.check_writable() {
+        return ApiError::permission(err.to_string()).into_response();
+    }
+    if auth.is_some() {
+        return ApiError::from(TokenError::MintedByToken).into_response();
+    }
+
+    match state.tokens.mint(request, caller.user()) {
+        Ok(token) => (StatusCode::CREATED, Json(token)).into_response(),
+        Err(err) => {
+            error!("[SERVER] Failed to mint token: {}", err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn renew_token(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    auth: Option<Extension<TokenAuth>>,
+    Path(id): Path<String>,
+    request: Option<Json<RenewTokenRequest>>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling renew_token request for ID: {}", id);
+
+    if let Err(err) = state.memory_store.check_writable() {
+        return ApiError::permission(err.to_string()).into_response();
+    }
+    let auth = auth.map(|Extension(auth)| auth);
+    if !state.tokens.get(&id).is_some_and(|token| can_manage(&token, &caller, auth.as_ref())) {
+        let err = TokenError::NotFound(id);
+        return ApiError::from(err).into_response();
+    }
+
+    let ttl_secs = request.and_then(|Json(request)| request.ttl_secs);
+    match state.tokens.renew(&id, ttl_secs) {
+        Ok(token) => (StatusCode::OK, Json(token)).into_response(),
+        Err(err) => {
+            error!("[SERVER] Failed to renew token {}: {}", id, err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn revoke_token(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    auth: Option<Extension<TokenAuth>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling revoke_token request for ID: {}", id);
+
+    if let Err(err) = state.memory_store.check_writable() {
+        return ApiError::permission(err.to_string()).into_response();
+    }
+    let auth = auth.map(|Extension(auth)| auth);
+    if !state.tokens.get(&id).is_some_and(|token| can_manage(&token, &caller, auth.as_ref())) {
+        let err = TokenError::NotFound(id);
+        return ApiError::from(err).into_response();
+    }
+
+    match state.tokens.revoke(&id) {
+        Ok(token) => (StatusCode::OK, Json(token)).into_response(),
+        Err(err) => {
+            error!("[SERVER] Failed to revoke token {}: {}", id, err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+#[utoipa::path(
+    get, path = "/api/tokens", tag = "tokens", operation_id = "list_tokens",
+    responses((status = 200, description = "Tokens of the calling user, or every token for API keys, without secrets", body = [ServiceToken]))
+)]
+#[axum::debug_handler]
+async fn list_tokens_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+) -> impl IntoResponse {
+    list_tokens(state, caller).await
+}
+
+#[utoipa::path(
+    post, path = "/api/tokens", tag = "tokens", operation_id = "create_token",
+    request_body = CreateTokenRequest,
+    responses(
+        (status = 201, description = "The token, including its secret", body = IssuedToken),
+        (status = 400, description = "Empty name, or authentication is not enabled", body = OpenAiErrorResponse),
+        (status = 403, description = "Called with a service token, or the store is read-only", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn create_token_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    auth: Option<Extension<TokenAuth>>,
+    json: ValidJson<CreateTokenRequest>,
+) -> impl IntoResponse {
+    create_token(state, caller, auth, json).await
+}
+
+#[utoipa::path(
+    post, path = "/api/tokens/{id}/renew", tag = "tokens", operation_id = "renew_token",
+    params(("id" = String, Path, description = "Token ID")),
+    request_body(content = Option<RenewTokenRequest>, description = "Optional new lifetime"),
+    responses(
+        (status = 200, description = "The token with a new secret; the previous one stops working", body = IssuedToken),
+        (status = 403, description = "The store is read-only", body = OpenAiErrorResponse),
+        (status = 404, description = "Token not found", body = OpenAiErrorResponse),
+        (status = 409, description = "Token has expired or was revoked", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn renew_token_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    auth: Option<Extension<TokenAuth>>,
+    path: Path<String>,
+    json: Option<Json<RenewTokenRequest>>,
+) -> impl IntoResponse {
+    renew_token(state, caller, auth, path, json).await
+}
+
+#[utoipa::path(
+    delete, path = "/api/tokens/{id}", tag = "tokens", operation_id = "revoke_token",
+    params(("id" = String, Path, description = "Token ID")),
+    responses(
+        (status = 200, description = "The revoked token", body = ServiceToken),
+        (status = 403, description = "The store is read-only", body = OpenAiErrorResponse),
+        (status = 404, description = "Token not found", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn revoke_token_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    auth: Option<Extension<TokenAuth>>,
+    path: Path<String>,
+) -> impl IntoResponse {
+    revoke_token(state, caller, auth, path).await
+}
@@ -0,0 +1,219 @@
+use std::convert::Infallible;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Query, State},
+    http::{request::Parts, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Json, Router,
+};
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::memory::MemoryStore;
+use crate::util::write_atomic;
+use super::auth::{Caller, KeyAuth};
+use super::state::ServerState;
+use super::tokens::TokenAuth;
+
+/// File in the store directory holding token usage per key and day
+const USAGE_FILE: &str = ".usage.json";
+
+/// Who model requests are counted against in the usage report
+///
+/// Service tokens are counted as `token:<id>`, users as `user:<name>`, API
+/// keys as `key:<fingerprint>`, and requests to a server without keys as
+/// `anonymous`.
+#[derive(Debug, Clone)]
+pub struct UsageKey {
+    pub key: String,
+    /// User the request acts as, who can see its usage
+    pub user: Option<String>,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for UsageKey {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let user = parts.extensions.get::<Caller>().and_then(Caller::user).map(str::to_string);
+        let key = if let Some(TokenAuth(id)) = parts.extensions.get::<TokenAuth>() {
+            format!("token:{}", id)
+        } else if let Some(user) = &user {
+            format!("user:{}", user)
+        } else if let Some(KeyAuth(fingerprint)) = parts.extensions.get::<KeyAuth>() {
+            format!("key:{}", fingerprint)
+        } else {
+            "anonymous".to_string()
+        };
+        Ok(Self { key, user })
+    }
+}
+
+/// Tokens used by one key with one model on one day
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UsageRow {
+    /// Day the requests were made, in UTC
+    pub date: NaiveDate,
+    pub key: String,
+    #[serde(default)]
+    pub user: Option<String>,
+    pub model: String,
+    #[serde(flatten)]
+    pub usage: UsageTotals,
+}
+
+/// Requests and the tokens they used
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct UsageTotals {
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, other: &UsageTotals) {
+        self.requests += other.requests;
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.total_tokens += other.total_tokens;
+    }
+}
+
+/// Usage in a range of days, with the sum of its rows
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UsageReport {
+    /// One row per day, key and model, oldest first
+    pub rows: Vec<UsageRow>,
+    pub total: UsageTotals,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(default)]
+pub struct UsageQuery {
+    /// First day to report, as `YYYY-MM-DD`
+    pub from: Option<NaiveDate>,
+    /// Last day to report, as `YYYY-MM-DD`
+    pub to: Option<NaiveDate>,
+    /// Only report this key, e.g. `user:alice` or `token:<id>`
+    pub key: Option<String>,
+    /// Only report this model
+    pub model: Option<String>,
+}
+
+/// Tokens used by chat and embedding requests, saved in the store directory
+///
+/// Usage is only kept in memory while the store is read-only.
+pub struct UsageLedger {
+    rows: Mutex<Vec<UsageRow>>,
+    path: PathBuf,
+    store: Arc<MemoryStore>,
+}
+
+impl UsageLedger {
+    pub fn open(store: &Arc<MemoryStore>) -> Result<Self, String> {
+        let path = store.base_path.join(USAGE_FILE);
+        let rows = if path.is_file() {
+            let json = fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read usage file {}: {}", path.display(), e))?;
+            serde_json::from_str(&json)
+                .map_err(|e| format!("Invalid usage file {}: {}", path.display(), e))?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self { rows: Mutex::new(rows), path, store: store.clone() })
+    }
+
+    /// Count a request against `key`
+    ///
+    /// The request has already been answered, so failing to save is only logged.
+    pub fn record(&self, key: &UsageKey, model: &str, prompt_tokens: i32, completion_tokens: i32) {
+        let prompt_tokens = prompt_tokens.max(0) as u64;
+        let completion_tokens = completion_tokens.max(0) as u64;
+        let usage = UsageTotals {
+            requests: 1,
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+        };
+        let date = Utc::now().date_naive();
+
+        let mut rows = self.rows.lock().unwrap_or_else(|e| e.into_inner());
+        match rows.iter_mut().find(|row| row.date == date && row.key == key.key && row.model == model) {
+            Some(row) => row.usage.add(&usage),
+            None => rows.push(UsageRow {
+                date,
+                key: key.key.clone(),
+                user: key.user.clone(),
+                model: model.to_string(),
+                usage,
+            }),
+        }
+
+        if self.store.check_writable().is_err() {
+            return;
+        }
+        let saved = serde_json::to_string_pretty(&*rows)
+            .map_err(|e| e.to_string())
+            .and_then(|json| write_atomic(&self.path, json).map_err(|e| e.to_string()));
+        if let Err(err) = saved {
+            error!("[USAGE] Failed to save usage: {}", err);
+        }
+    }
+
+    /// Usage matching the query, limited to `user`'s requests when given
+    pub fn report(&self, query: &UsageQuery, user: Option<&str>) -> UsageReport {
+        let rows = self.rows.lock().unwrap_or_else(|e| e.into_inner());
+        let mut rows: Vec<UsageRow> = rows.iter()
+            .filter(|row| user.is_none() || row.user.as_deref() == user)
+            .filter(|row| query.from.is_none_or(|from| row.date >= from))
+            .filter(|row| query.to.is_none_or(|to| row.date <= to))
+            .filter(|row| query.key.as_ref().is_none_or(|key| row.key == *key))
+            .filter(|row| query.model.as_ref().is_none_or(|model| row.model == *model))
+            .cloned()
+            .collect();
+        rows.sort_by(|a, b| (a.date, &a.key, &a.model).cmp(&(b.date, &b.key, &b.model)));
+
+        let mut total = UsageTotals::default();
+        for row in &rows {
+            total.add(&row.usage);
+        }
+        UsageReport { rows, total }
+    }
+}
+
+/// Routes for the token usage report
+pub fn router() -> Router<Arc<ServerState>> {
+    Router::new().route("/api/usage", get(usage_report_handler))
+}
+
+async fn usage_report(
+    State(state): State<Arc<ServerState>>,
+    caller: Caller,
+    Query(query): Query<UsageQuery>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling usage_report request (from: {:?}, to: {:?}, key: {:?})", query.from, query.to, query.key);
+    (StatusCode::OK, Json(state.usage.report(&query, caller.user())))
+}
+
+#[utoipa::path(
+    get, path = "/api/usage", tag = "usage", operation_id = "usage_report",
+    params(UsageQuery),
+    responses((status = 200, description = "Tokens used per day, key and model; users only see their own requests", body = UsageReport))
+)]
+#[axum::debug_handler]
+async fn usage_report_handler(
+    state: State<Arc<ServerState>>,
+    caller: Caller,
+    query: Query<UsageQuery>,
+) -> impl IntoResponse {
+    usage_report(state, caller, query).await
+}
@@ -0,0 +1,327 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use axum::{
+    extract::{FromRequest, Request},
+    http::StatusCode,
+    Json,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use conduit_types::openai::MemoryRequest;
+use conduit_types::requests::{
+    CollectionMembersRequest, CreateCollectionRequest, CreateMemoryRequest, RenameMemoryRequest,
+    RevertMemoryRequest, SearchMemoriesRequest, SetImportanceRequest, ShareMemoryRequest,
+};
+use conduit_types::sessions::CreateSessionMemoryRequest;
+use conduit_types::memory::{CollectionUpdate, Reference};
+use conduit_types::conversations::CreateConversationRequest;
+use crate::memory::is_valid_id;
+use super::accounts::{ChangePasswordRequest, CreateAccountRequest, LoginRequest, UpdateAccountRequest};
+use super::batch::BatchRequest;
+use super::setup::SetupRequest;
+use super::sync::ResolveConflictRequest;
+use super::error::{ApiError, OpenAiErrorType};
+use super::state::ServerState;
+use super::tags::SetTagAliasRequest;
+use super::tokens::CreateTokenRequest;
+use super::webhooks::CreateWebhookRequest;
+
+/// Longest title accepted when none is configured, in characters
+const DEFAULT_MAX_TITLE_CHARS: usize = 500;
+
+/// Largest memory content accepted when none is configured, in bytes
+const DEFAULT_MAX_CONTENT_BYTES: usize = 1024 * 1024;
+
+/// Most tags one memory can be given when none is configured
+const DEFAULT_MAX_TAGS: usize = 64;
+
+/// Longest tag accepted when none is configured, in characters
+const DEFAULT_MAX_TAG_CHARS: usize = 100;
+
+/// Limits on what request bodies may contain
+///
+/// Bodies breaking them are refused with `422` before anything is written.
+/// Bodies larger than 2 MiB are refused with `413` before they are read.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ValidationConfig {
+    pub max_title_chars: usize,
+    pub max_content_bytes: usize,
+    pub max_tags: usize,
+    pub max_tag_chars: usize,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        Self {
+            max_title_chars: DEFAULT_MAX_TITLE_CHARS,
+            max_content_bytes: DEFAULT_MAX_CONTENT_BYTES,
+            max_tags: DEFAULT_MAX_TAGS,
+            max_tag_chars: DEFAULT_MAX_TAG_CHARS,
+        }
+    }
+}
+
+impl ValidationConfig {
+    /// Override the content limit with `CONDUIT_MAX_CONTENT_BYTES`, if set
+    pub fn apply_env(&mut self) {
+        if let Some(limit) = std::env::var("CONDUIT_MAX_CONTENT_BYTES").ok().and_then(|v| v.parse().ok()) {
+            self.max_content_bytes = limit;
+        }
+    }
+
+    pub fn check_title(&self, param: &str, title: &str) -> Result<(), ApiError> {
+        let chars = title.chars().count();
+        if chars > self.max_title_chars {
+            return Err(invalid(param, "title_too_long", format!(
+                "The title is {} characters long, at most {} are accepted", chars, self.max_title_chars,
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn check_content(&self, param: &str, content: &str) -> Result<(), ApiError> {
+        if content.len() > self.max_content_bytes {
+            return Err(invalid(param, "content_too_large", format!(
+                "The content is {} bytes long, at most {} are accepted", content.len(), self.max_content_bytes,
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn check_tags(&self, param: &str, tags: &[String]) -> Result<(), ApiError> {
+        if tags.len() > self.max_tags {
+            return Err(invalid(param, "too_many_tags", format!(
+                "{} tags were given, at most {} are accepted", tags.len(), self.max_tags,
+            )));
+        }
+        for tag in tags {
+            if tag.trim().is_empty() {
+                return Err(invalid(param, "empty_tag", "Tags cannot be blank".to_string()));
+            }
+            if tag.chars().count() > self.max_tag_chars {
+                return Err(invalid(param, "tag_too_long", format!(
+                    "The tag {:?} is longer than {} characters", tag, self.max_tag_chars,
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check that `id` can name a memory or collection
+pub fn check_id(param: &str, id: &str) -> Result<(), ApiError> {
+    if !is_valid_id(id) {
+        return Err(invalid(param, "invalid_id", format!("{:?} is not a valid ID", id)));
+    }
+    Ok(())
+}
+
+fn check_ids(param: &str, ids: &[String]) -> Result<(), ApiError> {
+    ids.iter().try_for_each(|id| check_id(param, id))
+}
+
+fn check_not_blank(param: &str, value: &str) -> Result<(), ApiError> {
+    if value.trim().is_empty() {
+        return Err(invalid(param, "empty_field", format!("`{}` cannot be blank", param)));
+    }
+    Ok(())
+}
+
+// 422 for a body that parsed but breaks a rule
+fn invalid(param: &str, code: &str, message: String) -> ApiError {
+    ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, OpenAiErrorType::InvalidRequestError, message)
+        .param(param)
+        .code(code)
+}
+
+/// Rules a request body must follow beyond parsing
+pub trait Validate {
+    fn validate(&self, _limits: &ValidationConfig) -> Result<(), ApiError> {
+        Ok(())
+    }
+}
+
+/// A JSON body that parsed and passed [`Validate`]
+///
+/// Rejections use the OpenAI error shape on every route: `400` for malformed
+/// JSON, `422` for missing or mistyped fields and broken rules, and `415`
+/// without a JSON content type.
+pub struct ValidJson<T>(pub T);
+
+#[async_trait]
+impl<T> FromRequest<Arc<ServerState>> for ValidJson<T>
+where
+    T: DeserializeOwned + Validate + Send,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &Arc<ServerState>) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state).await?;
+        value.validate(&state.validation)?;
+        Ok(Self(value))
+    }
+}
+
+impl Validate for CreateMemoryRequest {
+    fn validate(&self, limits: &ValidationConfig) -> Result<(), ApiError> {
+        // A collection's template can fill in the content, and a title is generated from it
+        if self.title.trim().is_empty() && self.content.trim().is_empty() && self.collection.is_none() {
+            return Err(invalid("title", "empty_title", "A title or content is required".to_string()));
+        }
+        limits.check_title("title", &self.title)?;
+        limits.check_content("content", &self.content)?;
+        limits.check_tags("tags", &self.tags)?;
+        if let Some(collection) = &self.collection {
+            check_id("collection", collection)?;
+        }
+        Ok(())
+    }
+}
+
+impl Validate for MemoryRequest {
+    fn validate(&self, limits: &ValidationConfig) -> Result<(), ApiError> {
+        if self.title.trim().is_empty() && self.content.trim().is_empty() {
+            return Err(invalid("title", "empty_title", "A title or content is required".to_string()));
+        }
+        limits.check_title("title", &self.title)?;
+        limits.check_content("content", &self.content)?;
+        limits.check_tags("tags", &self.tags)
+    }
+}
+
+impl Validate for CreateSessionMemoryRequest {
+    fn validate(&self, limits: &ValidationConfig) -> Result<(), ApiError> {
+        if self.title.trim().is_empty() && self.content.trim().is_empty() {
+            return Err(invalid("title", "empty_title", "A title or content is required".to_string()));
+        }
+        limits.check_title("title", &self.title)?;
+        limits.check_content("content", &self.content)?;
+        limits.check_tags("tags", &self.tags)
+    }
+}
+
+impl Validate for RenameMemoryRequest {
+    fn validate(&self, _limits: &ValidationConfig) -> Result<(), ApiError> {
+        check_id("new_id", &self.new_id)
+    }
+}
+
+impl Validate for RevertMemoryRequest {
+    fn validate(&self, _limits: &ValidationConfig) -> Result<(), ApiError> {
+        check_not_blank("commit", &self.commit)
+    }
+}
+
+impl Validate for CreateCollectionRequest {
+    fn validate(&self, limits: &ValidationConfig) -> Result<(), ApiError> {
+        check_not_blank("name", &self.name)?;
+        limits.check_title("name", &self.name)?;
+        check_ids("memory_ids", &self.memory_ids)?;
+        limits.check_tags("defaults.tags", &self.defaults.tags)
+    }
+}
+
+impl Validate for CollectionUpdate {
+    fn validate(&self, limits: &ValidationConfig) -> Result<(), ApiError> {
+        if let Some(name) = &self.name {
+            check_not_blank("name", name)?;
+            limits.check_title("name", name)?;
+        }
+        match &self.defaults {
+            Some(defaults) => limits.check_tags("defaults.tags", &defaults.tags),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Validate for CollectionMembersRequest {
+    fn validate(&self, _limits: &ValidationConfig) -> Result<(), ApiError> {
+        check_ids("memory_ids", &self.memory_ids)
+    }
+}
+
+impl Validate for CreateConversationRequest {
+    fn validate(&self, limits: &ValidationConfig) -> Result<(), ApiError> {
+        match &self.title {
+            Some(title) => limits.check_title("title", title),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Validate for ResolveConflictRequest {
+    fn validate(&self, limits: &ValidationConfig) -> Result<(), ApiError> {
+        if let Some(title) = &self.merged.title {
+            limits.check_title("title", title)?;
+        }
+        if let Some(content) = &self.merged.content {
+            limits.check_content("content", content)?;
+        }
+        match &self.merged.tags {
+            Some(tags) => limits.check_tags("tags", tags),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Validate for SetupRequest {
+    fn validate(&self, _limits: &ValidationConfig) -> Result<(), ApiError> {
+        if let Some(path) = &self.store_path {
+            check_not_blank("store_path", &path.to_string_lossy())?;
+        }
+        if let Some(import) = &self.import {
+            check_not_blank("import.path", &import.path.to_string_lossy())?;
+        }
+        match &self.starter_templates {
+            Some(names) => names.iter().try_for_each(|name| check_not_blank("starter_templates", name)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Validate for SetTagAliasRequest {
+    fn validate(&self, limits: &ValidationConfig) -> Result<(), ApiError> {
+        limits.check_tags("tag", std::slice::from_ref(&self.tag))
+    }
+}
+
+impl Validate for CreateTokenRequest {
+    fn validate(&self, _limits: &ValidationConfig) -> Result<(), ApiError> {
+        check_not_blank("name", &self.name)
+    }
+}
+
+impl Validate for CreateAccountRequest {
+    fn validate(&self, _limits: &ValidationConfig) -> Result<(), ApiError> {
+        check_not_blank("name", &self.name)?;
+        check_not_blank("password", &self.password)
+    }
+}
+
+impl Validate for LoginRequest {
+    fn validate(&self, _limits: &ValidationConfig) -> Result<(), ApiError> {
+        check_not_blank("name", &self.name)?;
+        check_not_blank("password", &self.password)
+    }
+}
+
+impl Validate for CreateWebhookRequest {
+    fn validate(&self, _limits: &ValidationConfig) -> Result<(), ApiError> {
+        check_not_blank("url", &self.url)
+    }
+}
+
+// Checked by the routes themselves, which report each problem on its own
+impl Validate for SearchMemoriesRequest {}
+impl Validate for ShareMemoryRequest {}
+impl Validate for SetImportanceRequest {}
+impl Validate for BatchRequest {}
+impl Validate for UpdateAccountRequest {}
+impl Validate for ChangePasswordRequest {}
+impl Validate for Reference {}
+impl<T: Validate> Validate for Vec<T> {
+    fn validate(&self, limits: &ValidationConfig) -> Result<(), ApiError> {
+        self.iter().try_for_each(|item| item.validate(limits))
+    }
+}
@@ -0,0 +1,704 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::get,
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::watch;
+use tracing::{error, info, warn};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::memory::{MemoryEvent, MemoryEventKind, MemoryStore};
+use crate::util::write_atomic;
+use super::error::{ApiError, OpenAiErrorResponse};
+use super::lock::wait_until_unlocked;
+use super::state::ServerState;
+use super::validation::ValidJson;
+
+/// File in the store directory holding webhooks registered through the API
+const WEBHOOKS_FILE: &str = ".webhooks.json";
+
+/// Delivery attempts made when none is configured
+const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+
+/// Upstream timeout for a single delivery attempt when none is configured
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+
+/// Number of deliveries kept in the log when none is configured
+const DEFAULT_LOG_SIZE: usize = 200;
+
+/// Wait before the first retry; doubled after every failed attempt
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+
+/// Longest wait between two attempts
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(300);
+
+/// Body sent to a webhook
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookFormat {
+    /// The [`MemoryEvent`] as JSON
+    #[default]
+    Json,
+    /// A `{"text": ...}` message for Slack incoming webhooks
+    Slack,
+}
+
+/// A webhook set up in the config file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookEndpoint {
+    pub url: String,
+    /// Key used to sign deliveries; unsigned when absent
+    #[serde(default)]
+    pub secret: Option<String>,
+    /// Events sent to the webhook; empty for all
+    #[serde(default)]
+    pub events: Vec<MemoryEventKind>,
+    #[serde(default)]
+    pub format: WebhookFormat,
+}
+
+/// Settings for outgoing webhooks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    /// Webhooks that always receive events, in addition to those registered through the API
+    pub endpoints: Vec<WebhookEndpoint>,
+    /// Attempts made per delivery before giving up
+    pub max_attempts: u32,
+    /// Seconds to wait for a webhook to answer
+    pub timeout_secs: u64,
+    /// Number of recent deliveries listed by `/api/webhooks/deliveries`
+    pub log_size: usize,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            endpoints: Vec::new(),
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            timeout_secs: DEFAULT_TIMEOUT_SECS,
+            log_size: DEFAULT_LOG_SIZE,
+        }
+    }
+}
+
+impl WebhookConfig {
+    /// Override settings with the `CONDUIT_WEBHOOK_*` variables that are set
+    ///
+    /// `CONDUIT_WEBHOOK_URLS` replaces the configured endpoints with a
+    /// comma-separated list of URLs, signed with `CONDUIT_WEBHOOK_SECRET` if set.
+    /// `CONDUIT_WEBHOOK_MAX_ATTEMPTS` sets the attempts per delivery.
+    pub fn apply_env(&mut self) {
+        if let Ok(urls) = std::env::var("CONDUIT_WEBHOOK_URLS") {
+            let secret = std::env::var("CONDUIT_WEBHOOK_SECRET").ok().filter(|secret| !secret.is_empty());
+            self.endpoints = urls.split(',')
+                .map(str::trim)
+                .filter(|url| !url.is_empty())
+                .map(|url| WebhookEndpoint {
+                    url: url.to_string(),
+                    secret: secret.clone(),
+                    events: Vec::new(),
+                    format: WebhookFormat::default(),
+                })
+                .collect();
+        }
+        if let Some(attempts) = std::env::var("CONDUIT_WEBHOOK_MAX_ATTEMPTS").ok().and_then(|a| a.parse().ok()) {
+            self.max_attempts = attempts;
+        }
+    }
+}
+
+/// Where a webhook was set up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookSource {
+    Config,
+    Api,
+}
+
+/// A webhook receiving memory events
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    /// Events sent to the webhook; empty for all
+    pub events: Vec<MemoryEventKind>,
+    pub format: WebhookFormat,
+    pub source: WebhookSource,
+    /// Whether deliveries carry an `X-Conduit-Signature` header
+    pub signed: bool,
+    /// The signing key; only returned when the webhook is registered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Request body for registering a webhook
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    /// Events sent to the webhook; omit for all
+    #[serde(default)]
+    pub events: Vec<MemoryEventKind>,
+    #[serde(default)]
+    pub format: WebhookFormat,
+    /// Signing key; one is generated when omitted
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+// A webhook as stored, including its secret
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Registration {
+    id: String,
+    url: String,
+    #[serde(default)]
+    events: Vec<MemoryEventKind>,
+    #[serde(default)]
+    format: WebhookFormat,
+    #[serde(default)]
+    secret: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+impl Registration {
+    fn wants(&self, kind: MemoryEventKind) -> bool {
+        self.events.is_empty() || self.events.contains(&kind)
+    }
+
+    fn to_webhook(&self, source: WebhookSource) -> Webhook {
+        Webhook {
+            id: self.id.clone(),
+            url: self.url.clone(),
+            events: self.events.clone(),
+            format: self.format,
+            source,
+            signed: self.secret.is_some(),
+            secret: None,
+            created_at: self.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    /// Waiting for its next attempt
+    Pending,
+    Succeeded,
+    /// Every attempt failed, or the webhook rejected the payload
+    Failed,
+}
+
+/// An event sent, or being sent, to a webhook
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WebhookDelivery {
+    pub id: String,
+    pub webhook_id: String,
+    pub url: String,
+    pub event: MemoryEventKind,
+    pub memory_id: String,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    /// HTTP status of the last answer, if any
+    pub response_status: Option<u16>,
+    /// Why the last attempt failed
+    pub error: Option<String>,
+    /// When the next attempt is made, while pending
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Filters for the delivery log
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeliveryQuery {
+    /// Only deliveries to this webhook
+    pub webhook_id: Option<String>,
+    /// Only deliveries with this status
+    pub status: Option<DeliveryStatus>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WebhookError {
+    #[error("Invalid webhook URL: {0}")]
+    InvalidUrl(String),
+
+    #[error("Webhook not found: {0}")]
+    NotFound(String),
+
+    #[error("Webhook {0} is set up in the config file and cannot be removed through the API")]
+    Configured(String),
+
+    #[error("Failed to save webhooks: {0}")]
+    Storage(String),
+}
+
+impl WebhookError {
+    fn status(&self) -> StatusCode {
+        match self {
+            Self::InvalidUrl(_) => StatusCode::BAD_REQUEST,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::Configured(_) => StatusCode::CONFLICT,
+            Self::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<WebhookError> for ApiError {
+    fn from(err: WebhookError) -> Self {
+        ApiError::status(err.status(), err.to_string())
+    }
+}
+
+/// Registered webhooks and the log of their deliveries
+pub struct Webhooks {
+    config: WebhookConfig,
+    /// Webhooks from the config file, which cannot be changed at runtime
+    configured: Vec<Registration>,
+    /// Webhooks registered through the API, saved in the store directory
+    registered: Mutex<Vec<Registration>>,
+    path: PathBuf,
+    deliveries: Mutex<VecDeque<WebhookDelivery>>,
+    client: reqwest::Client,
+}
+
+impl Webhooks {
+    /// Load the webhooks registered with the store and add those from `config`
+    pub fn open(config: WebhookConfig, store: &MemoryStore) -> Result<Self, String> {
+        let path = store.base_path.join(WEBHOOKS_FILE);
+        let registered = if path.is_file() {
+            let json = fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read webhooks file {}: {}", path.display(), e))?;
+            serde_json::from_str(&json)
+                .map_err(|e| format!("Invalid webhooks file {}: {}", path.display(), e))?
+        } else {
+            Vec::new()
+        };
+
+        let configured = config.endpoints.iter()
+            .enumerate()
+            .map(|(index, endpoint)| Registration {
+                id: format!("config-{}", index + 1),
+                url: endpoint.url.clone(),
+                events: endpoint.events.clone(),
+                format: endpoint.format,
+                secret: endpoint.secret.clone(),
+                created_at: Utc::now(),
+            })
+            .collect();
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .user_agent(concat!("conduit-webhooks/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(|e| format!("Failed to create webhook client: {}", e))?;
+
+        Ok(Self {
+            config,
+            configured,
+            registered: Mutex::new(registered),
+            path,
+            deliveries: Mutex::new(VecDeque::new()),
+            client,
+        })
+    }
+
+    /// Every webhook, configured ones first; secrets are left out
+    pub fn list(&self) -> Vec<Webhook> {
+        let registered = self.registered.lock().unwrap_or_else(|e| e.into_inner());
+        self.configured.iter()
+            .map(|registration| registration.to_webhook(WebhookSource::Config))
+            .chain(registered.iter().map(|registration| registration.to_webhook(WebhookSource::Api)))
+            .collect()
+    }
+
+    /// Register a webhook and save it with the store
+    ///
+    /// The returned webhook includes its secret, which is generated when the
+    /// request has none.
+    pub fn register(&self, request: CreateWebhookRequest) -> Result<Webhook, WebhookError> {
+        let url = reqwest::Url::parse(&request.url).map_err(|e| WebhookError::InvalidUrl(format!("{}: {}", request.url, e)))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(WebhookError::InvalidUrl(format!("{}: only http and https are supported", request.url)));
+        }
+
+        let secret = request.secret.filter(|secret| !secret.is_empty()).unwrap_or_else(generate_secret);
+        let registration = Registration {
+            id: ulid::Ulid::new().to_string(),
+            url: url.to_string(),
+            events: request.events,
+            format: request.format,
+            secret: Some(secret.clone()),
+            created_at: Utc::now(),
+        };
+
+        let mut registered = self.registered.lock().unwrap_or_else(|e| e.into_inner());
+        registered.push(registration.clone());
+        if let Err(e) = self.save(&registered) {
+            registered.pop();
+            return Err(e);
+        }
+
+        let mut webhook = registration.to_webhook(WebhookSource::Api);
+        webhook.secret = Some(secret);
+        Ok(webhook)
+    }
+
+    /// Remove a webhook registered through the API
+    pub fn remove(&self, id: &str) -> Result<(), WebhookError> {
+        if self.configured.iter().any(|registration| registration.id == id) {
+            return Err(WebhookError::Configured(id.to_string()));
+        }
+
+        let mut registered = self.registered.lock().unwrap_or_else(|e| e.into_inner());
+        let index = registered.iter()
+            .position(|registration| registration.id == id)
+            .ok_or_else(|| WebhookError::NotFound(id.to_string()))?;
+        let removed = registered.remove(index);
+        if let Err(e) = self.save(&registered) {
+            registered.insert(index, removed);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Recent deliveries matching `query`, newest first
+    pub fn deliveries(&self, query: &DeliveryQuery) -> Vec<WebhookDelivery> {
+        let deliveries = self.deliveries.lock().unwrap_or_else(|e| e.into_inner());
+        deliveries.iter()
+            .rev()
+            .filter(|delivery| query.webhook_id.as_ref().is_none_or(|id| &delivery.webhook_id == id))
+            .filter(|delivery| query.status.is_none_or(|status| delivery.status == status))
+            .cloned()
+            .collect()
+    }
+
+    fn save(&self, registered: &[Registration]) -> Result<(), WebhookError> {
+        let json = serde_json::to_string_pretty(registered).map_err(|e| WebhookError::Storage(e.to_string()))?;
+        write_atomic(&self.path, json).map_err(|e| WebhookError::Storage(e.to_string()))
+    }
+
+    // Webhooks that want an event of this kind
+    fn targets(&self, kind: MemoryEventKind) -> Vec<Registration> {
+        let registered = self.registered.lock().unwrap_or_else(|e| e.into_inner());
+        self.configured.iter()
+            .chain(registered.iter())
+            .filter(|registration| registration.wants(kind))
+            .cloned()
+            .collect()
+    }
+
+    // Add a delivery to the log, dropping the oldest once it is full
+    fn record(&self, delivery: WebhookDelivery) {
+        let mut deliveries = self.deliveries.lock().unwrap_or_else(|e| e.into_inner());
+        while deliveries.len() >= self.config.log_size.max(1) {
+            deliveries.pop_front();
+        }
+        deliveries.push_back(delivery);
+    }
+
+    // Replace the logged state of a delivery, unless it was already dropped from the log
+    fn update(&self, delivery: &WebhookDelivery) {
+        let mut deliveries = self.deliveries.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(logged) = deliveries.iter_mut().rev().find(|logged| logged.id == delivery.id) {
+            *logged = delivery.clone();
+        }
+    }
+}
+
+// Random signing key for webhooks registered without one
+fn generate_secret() -> String {
+    let bytes: [u8; 24] = rand::thread_rng().gen();
+    format!("whsec_{}", hex::encode(bytes))
+}
+
+/// Signature sent in `X-Conduit-Signature`
+///
+/// The HMAC-SHA256 of `<timestamp>.<body>` keyed with the webhook secret, as
+/// `t=<timestamp>,v1=<hex digest>`. Receivers recompute it to check that the
+/// payload came from this server and reject old timestamps to stop replays.
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("t={},v1={}", timestamp, hex::encode(mac.finalize().into_bytes()))
+}
+
+// The body sent to a webhook for an event
+fn payload(event: &MemoryEvent, format: WebhookFormat) -> Vec<u8> {
+    let body = match format {
+        WebhookFormat::Json => serde_json::to_value(event).unwrap_or_default(),
+        WebhookFormat::Slack => {
+            let title = event.memory.as_ref().map(|memory| memory.title.as_str()).unwrap_or(&event.id);
+            let text = match event.kind {
+                MemoryEventKind::Created => format!("New memory: *{}*", title),
+                MemoryEventKind::Updated => format!("Updated memory: *{}*", title),
+                MemoryEventKind::Deleted => format!("Deleted memory `{}`", event.id),
+            };
+            serde_json::json!({ "text": text })
+        }
+    };
+    serde_json::to_vec(&body).unwrap_or_default()
+}
+
+/// Send memory events to the webhooks until the server shuts down
+///
+/// While the workspace is locked, events wait to be sent until it is unlocked.
+pub fn spawn(webhooks: Arc<Webhooks>, state: &ServerState) {
+    let mut receiver = state.events.subscribe();
+    let mut shutdown = state.shutdown.clone();
+    let store = state.memory_store.clone();
+
+    tokio::spawn(async move {
+        loop {
+            let event = tokio::select! {
+                event = receiver.recv() => event,
+                _ = shutdown.wait_for(|stopped| *stopped) => break,
+            };
+            match event {
+                Ok(event) => {
+                    if !wait_until_unlocked(&store, &mut shutdown).await {
+                        break;
+                    }
+                    for target in webhooks.targets(event.kind) {
+                        tokio::spawn(deliver(webhooks.clone(), target, event.clone(), shutdown.clone()));
+                    }
+                }
+                Err(RecvError::Lagged(missed)) => warn!("[WEBHOOKS] Fell behind, {} events not delivered", missed),
+                Err(RecvError::Closed) => break,
+            }
+        }
+        info!("[WEBHOOKS] Server stopped, ending webhook deliveries");
+    });
+}
+
+// Send one event to one webhook, retrying with exponential backoff
+async fn deliver(webhooks: Arc<Webhooks>, target: Registration, event: MemoryEvent, mut shutdown: watch::Receiver<bool>) {
+    let body = payload(&event, target.format);
+    let mut delivery = WebhookDelivery {
+        id: ulid::Ulid::new().to_string(),
+        webhook_id: target.id.clone(),
+        url: target.url.clone(),
+        event: event.kind,
+        memory_id: event.id.clone(),
+        status: DeliveryStatus::Pending,
+        attempts: 0,
+        response_status: None,
+        error: None,
+        next_attempt_at: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    };
+    webhooks.record(delivery.clone());
+
+    let max_attempts = webhooks.config.max_attempts.max(1);
+    let mut delay = RETRY_BASE_DELAY;
+    loop {
+        delivery.attempts += 1;
+        let mut request = webhooks.client.post(&target.url)
+            .header("Content-Type", "application/json")
+            .header("X-Conduit-Event", event.kind.as_str())
+            .header("X-Conduit-Delivery", &delivery.id);
+        if let Some(secret) = &target.secret {
+            request = request.header("X-Conduit-Signature", sign(secret, Utc::now().timestamp(), &body));
+        }
+
+        // Retry network errors, server errors and rate limits; other answers are final
+        let retry = match request.body(body.clone()).send().await {
+            Ok(response) if response.status().is_success() => {
+                delivery.status = DeliveryStatus::Succeeded;
+                delivery.response_status = Some(response.status().as_u16());
+                delivery.error = None;
+                false
+            }
+            Ok(response) => {
+                let status = response.status();
+                delivery.response_status = Some(status.as_u16());
+                delivery.error = Some(format!("Webhook answered {}", status));
+                status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            }
+            Err(e) => {
+                delivery.response_status = None;
+                delivery.error = Some(e.to_string());
+                true
+            }
+        };
+
+        if delivery.status != DeliveryStatus::Succeeded && (!retry || delivery.attempts >= max_attempts) {
+            delivery.status = DeliveryStatus::Failed;
+        }
+        delivery.next_attempt_at = (delivery.status == DeliveryStatus::Pending)
+            .then(|| Utc::now() + chrono::Duration::from_std(delay).unwrap_or_default());
+        delivery.updated_at = Utc::now();
+        webhooks.update(&delivery);
+
+        match delivery.status {
+            DeliveryStatus::Succeeded => {
+                info!("[WEBHOOKS] Delivered {} for {} to {}", event.kind.as_str(), event.id, target.url);
+                return;
+            }
+            DeliveryStatus::Failed => {
+                error!(
+                    "[WEBHOOKS] Giving up on {} for {} to {} after {} attempt(s): {}",
+                    event.kind.as_str(), event.id, target.url, delivery.attempts, delivery.error.as_deref().unwrap_or_default(),
+                );
+                return;
+            }
+            DeliveryStatus::Pending => {
+                warn!(
+                    "[WEBHOOKS] Attempt {} of {} for {} failed, retrying in {:?}",
+                    delivery.attempts, max_attempts, target.url, delay,
+                );
+            }
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = shutdown.wait_for(|stopped| *stopped) => {
+                delivery.status = DeliveryStatus::Failed;
+                delivery.error = Some("Server stopped before the delivery succeeded".to_string());
+                delivery.next_attempt_at = None;
+                delivery.updated_at = Utc::now();
+                webhooks.update(&delivery);
+                return;
+            }
+        }
+        delay = (delay * 2).min(RETRY_MAX_DELAY);
+    }
+}
+
+pub fn router() -> Router<Arc<ServerState>> {
+    Router::new()
+        .route("/api/webhooks", get(list_webhooks_handler).post(create_webhook_handler))
+        .route("/api/webhooks/deliveries", get(list_deliveries_handler))
+        .route("/api/webhooks/:id", axum::routing::delete(delete_webhook_handler))
+}
+
+async fn list_webhooks(
+    State(state): State<Arc<ServerState>>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling list_webhooks request");
+    Json(state.webhooks.list())
+}
+
+async fn create_webhook(
+    State(state): State<Arc<ServerState>>,
+    ValidJson(request): ValidJson<CreateWebhookRequest>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling create_webhook request for {}", request.url);
+
+    // Registrations are saved in the store
+    if let Err(err) = state.memory_store.check_writable() {
+        return ApiError::permission(err.to_string()).into_response();
+    }
+
+    match state.webhooks.register(request) {
+        Ok(webhook) => (StatusCode::CREATED, Json(webhook)).into_response(),
+        Err(err) => {
+            error!("[SERVER] Failed to register webhook: {}", err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn delete_webhook(
+    State(state): State<Arc<ServerState>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling delete_webhook request for ID: {}", id);
+
+    if let Err(err) = state.memory_store.check_writable() {
+        return ApiError::permission(err.to_string()).into_response();
+    }
+
+    match state.webhooks.remove(&id) {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(err) => {
+            error!("[SERVER] Failed to remove webhook {}: {}", id, err);
+            ApiError::from(err).into_response()
+        }
+    }
+}
+
+async fn list_deliveries(
+    State(state): State<Arc<ServerState>>,
+    Query(query): Query<DeliveryQuery>,
+) -> impl IntoResponse {
+    info!("[SERVER] Handling list_webhook_deliveries request");
+    Json(state.webhooks.deliveries(&query))
+}
+
+#[utoipa::path(
+    get, path = "/api/webhooks", tag = "webhooks", operation_id = "list_webhooks",
+    responses((status = 200, description = "Configured and registered webhooks, without secrets", body = [Webhook]))
+)]
+#[axum::debug_handler]
+async fn list_webhooks_handler(
+    state: State<Arc<ServerState>>,
+) -> impl IntoResponse {
+    list_webhooks(state).await
+}
+
+#[utoipa::path(
+    post, path = "/api/webhooks", tag = "webhooks", operation_id = "create_webhook",
+    request_body = CreateWebhookRequest,
+    responses(
+        (status = 201, description = "The webhook, including its signing secret", body = Webhook),
+        (status = 400, description = "Invalid URL", body = OpenAiErrorResponse),
+        (status = 403, description = "The store is read-only", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn create_webhook_handler(
+    state: State<Arc<ServerState>>,
+    json: ValidJson<CreateWebhookRequest>,
+) -> impl IntoResponse {
+    create_webhook(state, json).await
+}
+
+#[utoipa::path(
+    delete, path = "/api/webhooks/{id}", tag = "webhooks", operation_id = "delete_webhook",
+    params(("id" = String, Path, description = "Webhook ID")),
+    responses(
+        (status = 204, description = "Webhook removed"),
+        (status = 403, description = "The store is read-only", body = OpenAiErrorResponse),
+        (status = 404, description = "Webhook not found", body = OpenAiErrorResponse),
+        (status = 409, description = "Webhook is set up in the config file", body = OpenAiErrorResponse),
+    )
+)]
+#[axum::debug_handler]
+async fn delete_webhook_handler(
+    state: State<Arc<ServerState>>,
+    path: Path<String>,
+) -> impl IntoResponse {
+    delete_webhook(state, path).await
+}
+
+#[utoipa::path(
+    get, path = "/api/webhooks/deliveries", tag = "webhooks", operation_id = "list_webhook_deliveries",
+    params(DeliveryQuery),
+    responses((status = 200, description = "Recent deliveries, newest first", body = [WebhookDelivery]))
+)]
+#[axum::debug_handler]
+async fn list_deliveries_handler(
+    state: State<Arc<ServerState>>,
+    query: Query<DeliveryQuery>,
+) -> impl IntoResponse {
+    list_deliveries(state, query).await
+}
@@ -0,0 +1,294 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Serialize};
+use tower::ServiceExt;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+use crate::memory::{Election, MemoryStore, PreprocessConfig, StoreOptions, WriterGuard};
+use super::accounts::{is_session_token, AccountsConfig};
+use super::error::ApiError;
+use super::scheduler::SchedulerConfig;
+use super::server::ServerOptions;
+use super::state::ServerState;
+use super::sync::SyncConfig;
+
+/// Name of the workspace holding the `[storage]` store
+pub const DEFAULT_WORKSPACE: &str = "default";
+
+/// Header naming the workspace a request is for
+pub const WORKSPACE_HEADER: &str = "x-conduit-workspace";
+
+/// Query parameter naming the workspace a request is for, used when the header is absent
+const WORKSPACE_PARAM: &str = "workspace";
+
+/// Named memory stores served next to the `[storage]` store, set under `[workspaces]`
+///
+/// ```toml
+/// [workspaces]
+/// active = "work"
+///
+/// [workspaces.stores]
+/// work = "/home/me/work-notes"
+/// research = "/home/me/research"
+///
+/// [workspaces.preprocess.research]
+/// steps = ["strip_tracking_params", "straighten_quotes"]
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorkspaceConfig {
+    /// Workspace the backend opens and requests go to when they name none;
+    /// the `[storage]` store, named `default`, when unset
+    pub active: Option<String>,
+    /// Store folder of each further workspace, by name
+    pub stores: BTreeMap<String, PathBuf>,
+    /// Content preprocessing of a workspace, by name, in place of `[storage.preprocess]`
+    pub preprocess: BTreeMap<String, PreprocessConfig>,
+}
+
+impl WorkspaceConfig {
+    /// Override the active workspace with `CONDUIT_WORKSPACE`, if set
+    pub fn apply_env(&mut self) {
+        if let Ok(name) = std::env::var("CONDUIT_WORKSPACE") {
+            self.active = Some(name.trim().to_string()).filter(|name| !name.is_empty());
+        }
+    }
+
+    /// Name of the active workspace
+    pub fn active_name(&self) -> &str {
+        self.active.as_deref().unwrap_or(DEFAULT_WORKSPACE)
+    }
+
+    /// Check that every workspace has a valid name and the active one exists
+    pub fn check(&self) -> Result<(), String> {
+        for name in self.stores.keys() {
+            if name == DEFAULT_WORKSPACE {
+                return Err(format!("Workspace name `{}` is taken by the [storage] store", DEFAULT_WORKSPACE));
+            }
+            if !is_valid_workspace_name(name) {
+                return Err(format!("Invalid workspace name {:?}: use letters, digits, `-` and `_`", name));
+            }
+        }
+        let active = self.active_name();
+        if active != DEFAULT_WORKSPACE && !self.stores.contains_key(active) {
+            return Err(format!("Unknown workspace {}; add it under [workspaces.stores]", active));
+        }
+        for (name, preprocess) in &self.preprocess {
+            if name != DEFAULT_WORKSPACE && !self.stores.contains_key(name) {
+                return Err(format!("Preprocessing is set for unknown workspace {}", name));
+            }
+            preprocess.check()?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether a workspace name is usable in configs, headers and query strings
+pub fn is_valid_workspace_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// A workspace and whether it answered the request
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WorkspaceInfo {
+    pub name: String,
+    #[schema(value_type = String)]
+    pub path: PathBuf,
+    /// The request was answered by this workspace
+    pub current: bool,
+}
+
+/// Routes describing the workspaces the server hosts
+pub fn router() -> Router<Arc<ServerState>> {
+    Router::new().route("/api/workspaces", get(list_workspaces_handler))
+}
+
+// A served workspace other than the one requests go to by default
+struct Workspace {
+    state: Arc<ServerState>,
+    router: Router,
+}
+
+/// The workspaces served by one server, which requests pick with the
+/// `X-Conduit-Workspace` header or the `workspace` query parameter
+///
+/// Each workspace has its own store, indexes, background tasks and writer
+/// lock. Requests naming no workspace, or the active one, go to the
+/// server's own routes.
+pub struct WorkspaceRegistry {
+    active: String,
+    others: BTreeMap<String, Workspace>,
+    // Released when the server stops, handing the stores to the next instance
+    writers: std::sync::Mutex<Vec<WriterGuard>>,
+}
+
+impl WorkspaceRegistry {
+    /// Open the stores of the workspaces other than the active one and start
+    /// their background tasks
+    ///
+    /// A store already written to by another process is served read-only.
+    /// Scheduled tasks, git sync and accounts only belong to the active
+    /// workspace, since their settings name one store. `build` makes each workspace's routes.
+    pub async fn open(
+        active: &MemoryStore,
+        options: &ServerOptions,
+        build: impl Fn(Arc<ServerState>) -> Router,
+    ) -> Result<Self, String> {
+        let active_name = options.workspace.clone().unwrap_or_else(|| DEFAULT_WORKSPACE.to_string());
+        let active_path = active.base_path.canonicalize().unwrap_or_else(|_| active.base_path.clone());
+        let mut others = BTreeMap::new();
+        let mut writers = Vec::new();
+
+        for (name, path) in &options.workspaces {
+            if *name == active_name {
+                continue;
+            }
+            if path.canonicalize().is_ok_and(|path| path == active_path) {
+                warn!("[SERVER] Workspace {} is the active store {}, skipping it", name, path.display());
+                continue;
+            }
+
+            let store_options = StoreOptions {
+                preprocess: options.workspace_preprocess.get(name).cloned().unwrap_or_else(|| active.options.preprocess.clone()),
+                ..active.options.clone()
+            };
+            let store = Arc::new(MemoryStore::with_options(path, store_options));
+            if !store.is_available() {
+                warn!("[SERVER] Store of workspace {} is unavailable: {:?}", name, path);
+            } else if !store.is_read_only() {
+                let election_store = store.clone();
+                let election = tokio::task::spawn_blocking(move || election_store.elect_writer())
+                    .await
+                    .map_err(|e| format!("Writer election for workspace {} failed: {}", name, e))?;
+                match election {
+                    Ok(Election::Won(guard)) => writers.push(guard),
+                    Ok(Election::Lost(_)) => {
+                        warn!("[SERVER] Workspace {} is already served by another Conduit process, serving it read-only", name);
+                        store.set_read_only(true);
+                    }
+                    Err(e) => warn!("[SERVER] Could not elect a writer for workspace {}, continuing without the lock: {}", name, e),
+                }
+            }
+
+            let workspace_options = ServerOptions {
+                workspace: Some(name.clone()),
+                scheduler: SchedulerConfig::default(),
+                sync: SyncConfig::default(),
+                accounts: AccountsConfig::default(),
+                ..options.clone()
+            };
+            let state = ServerState::start(store, &workspace_options)
+                .map_err(|e| format!("Failed to open workspace {}: {}", name, e))?;
+            info!("[SERVER] Serving workspace {} from {}", name, path.display());
+            others.insert(name.clone(), Workspace { router: build(state.clone()), state });
+        }
+
+        Ok(Self {
+            active: active_name,
+            others,
+            writers: std::sync::Mutex::new(writers),
+        })
+    }
+
+    /// State of each workspace besides the active one
+    pub fn states(&self) -> impl Iterator<Item = &Arc<ServerState>> {
+        self.others.values().map(|workspace| &workspace.state)
+    }
+
+    /// Stop the background tasks of the workspaces and release their stores
+    pub fn stop(&self) {
+        for state in self.states() {
+            state.stop();
+        }
+        self.writers.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+}
+
+// Workspace named by the request's header or query string, if any
+fn requested_workspace(request: &Request) -> Option<String> {
+    if let Some(name) = request.headers().get(WORKSPACE_HEADER).and_then(|value| value.to_str().ok()) {
+        return Some(name.trim().to_string()).filter(|name| !name.is_empty());
+    }
+    request.uri().query()?
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == WORKSPACE_PARAM)
+        .map(|(_, value)| percent_decode_str(&value.replace('+', " ")).decode_utf8_lossy().trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+/// Answer a request with the routes of another store
+///
+/// The request was already matched against a route here, so the path
+/// parameters that left behind are dropped before it is matched again.
+pub async fn dispatch(router: Router, mut request: Request) -> Response {
+    request.extensions_mut().clear();
+    match router.oneshot(request).await {
+        Ok(response) => response,
+        Err(never) => match never {},
+    }
+}
+
+/// Send each request to the routes of the workspace it names, answering
+/// `404` for workspaces the server does not host
+///
+/// Requests with an account's session token always go to the active
+/// workspace, which sends them on to the account's own store.
+pub async fn select_workspace(
+    State(workspaces): State<Arc<WorkspaceRegistry>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let has_session = request.headers()
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|key| is_session_token(key.trim()));
+    let Some(name) = requested_workspace(&request).filter(|_| !has_session) else {
+        return next.run(request).await;
+    };
+    if name == workspaces.active {
+        return next.run(request).await;
+    }
+    match workspaces.others.get(&name) {
+        Some(workspace) => dispatch(workspace.router.clone(), request).await,
+        None => ApiError::not_found(format!("No workspace named {}", name)).code("unknown_workspace").into_response(),
+    }
+}
+
+async fn list_workspaces(State(state): State<Arc<ServerState>>) -> impl IntoResponse {
+    info!("[SERVER] Handling list_workspaces request");
+    let current = state.workspace.as_deref().unwrap_or(DEFAULT_WORKSPACE);
+    let mut workspaces: Vec<WorkspaceInfo> = state.workspaces.iter()
+        .map(|(name, path)| WorkspaceInfo { name: name.clone(), path: path.clone(), current: name == current })
+        .collect();
+    // Embedded routers know no workspace registry, only the store they serve
+    if !workspaces.iter().any(|workspace| workspace.current) {
+        workspaces.push(WorkspaceInfo { name: current.to_string(), path: state.memory_store.base_path.clone(), current: true });
+    }
+    (StatusCode::OK, Json(workspaces))
+}
+
+#[utoipa::path(
+    get, path = "/api/workspaces", tag = "workspaces", operation_id = "list_workspaces",
+    responses(
+        (status = 200, description = "Workspaces the server hosts, which requests pick with the `X-Conduit-Workspace` header or `workspace` query parameter", body = [WorkspaceInfo]),
+    )
+)]
+#[axum::debug_handler]
+async fn list_workspaces_handler(state: State<Arc<ServerState>>) -> impl IntoResponse {
+    list_workspaces(state).await
+}
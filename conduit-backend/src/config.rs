@@ -0,0 +1,486 @@
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+
+use crate::api::accounts::AccountsConfig;
+use crate::api::attachment_text::AttachmentTextConfig;
+use crate::api::capture::CaptureConfig;
+use crate::api::cleanup::CleanupConfig;
+use crate::api::auth::AuthConfig;
+use crate::api::auto_tag::AutoTagConfig;
+use crate::api::summarize::SummarizeConfig;
+use crate::api::chat_tools::ChatToolsConfig;
+use crate::api::templates::TemplateConfig;
+use crate::api::validation::ValidationConfig;
+use crate::api::connectors::ConnectorsConfig;
+use crate::api::cors::CorsConfig;
+use crate::api::inbox::InboxConfig;
+use crate::api::instance::InstanceConfig;
+use crate::api::interaction_log::InteractionLogConfig;
+use crate::api::openapi::DocsConfig;
+use crate::api::activity::ActivityConfig;
+use crate::api::analytics::AnalyticsConfig;
+use crate::api::export::ExportConfig;
+use crate::api::scheduler::SchedulerConfig;
+use crate::api::server::ServerOptions;
+use crate::api::sessions::SessionConfig;
+use crate::api::sync::SyncConfig;
+use crate::api::titles::TitleConfig;
+use crate::api::webhooks::WebhookConfig;
+use crate::api::workspaces::{is_valid_workspace_name, WorkspaceConfig, DEFAULT_WORKSPACE};
+use crate::memory::{init_store_dir, ImportanceConfig, PreprocessConfig, RelatedConfig, StoreOptions, DEFAULT_DEMO_SEED};
+use crate::provider::{EmbeddingConfig, ProviderConfig, RegisteredModel};
+use crate::rag::RagConfig;
+use crate::util::write_atomic;
+
+/// Address the API server listens on when none is configured
+pub const DEFAULT_LISTEN_ADDR: &str = "0.0.0.0:3000";
+
+/// Request timeout used when none is configured, longer than the default
+/// upstream timeout so provider errors are reported before the request is cut off
+const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 300;
+
+/// Queue time for model requests used when none is configured
+const DEFAULT_LLM_QUEUE_TIMEOUT_MS: u64 = 2000;
+
+/// Where memories are stored and how the store is scanned
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    /// Memory directory; defaults to `~/.conduit/memories`
+    pub path: Option<PathBuf>,
+    #[serde(flatten)]
+    pub options: StoreOptions,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ListenConfig {
+    /// Socket address the API server binds to
+    pub addr: SocketAddr,
+}
+
+impl Default for ListenConfig {
+    fn default() -> Self {
+        Self {
+            addr: DEFAULT_LISTEN_ADDR.parse().expect("valid default listen address"),
+        }
+    }
+}
+
+/// Sandbox mode that serves generated sample memories instead of the configured store
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DemoConfig {
+    /// Create a fresh store in a temporary directory, filled with sample memories
+    pub enabled: bool,
+    /// Seed for the sample memories; the same seed produces the same sample store
+    pub seed: u64,
+}
+
+impl Default for DemoConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            seed: DEFAULT_DEMO_SEED,
+        }
+    }
+}
+
+/// Request limits of the API server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LimitsConfig {
+    /// Requests running longer than this many seconds are aborted with `408`; `0` disables the limit
+    pub request_timeout_secs: u64,
+    /// Maximum number of chat and embedding requests handled at once; `0` for no limit
+    pub llm_max_concurrency: usize,
+    /// How long a chat or embedding request waits for a free slot before getting `429`
+    pub llm_queue_timeout_ms: u64,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            request_timeout_secs: DEFAULT_REQUEST_TIMEOUT_SECS,
+            llm_max_concurrency: 0,
+            llm_queue_timeout_ms: DEFAULT_LLM_QUEUE_TIMEOUT_MS,
+        }
+    }
+}
+
+/// All backend settings, as read from `config.toml`
+///
+/// Every section is optional. Settings are layered: built-in defaults, then the
+/// config file, then any `CONDUIT_*` environment variables that are set.
+///
+/// ```toml
+/// [storage]
+/// path = "/home/me/notes"
+/// recursive = true
+///
+/// [listen]
+/// addr = "127.0.0.1:3000"
+///
+/// [auth]
+/// api_keys = ["change-me"]
+///
+/// [cors]
+/// origins = ["localhost", "https://notes.example.com"]
+///
+/// [provider]
+/// base_url = "http://localhost:11434"
+/// kind = "ollama"
+///
+/// [[models]]
+/// id = "llama3"
+/// upstream_id = "llama3.1:8b"
+///
+/// [limits]
+/// request_timeout_secs = 120
+/// llm_max_concurrency = 4
+///
+/// [[webhooks.endpoints]]
+/// url = "https://hooks.slack.com/services/..."
+/// format = "slack"
+/// events = ["memory.created"]
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConduitConfig {
+    pub storage: StorageConfig,
+    pub listen: ListenConfig,
+    pub auth: AuthConfig,
+    pub accounts: AccountsConfig,
+    pub cors: CorsConfig,
+    /// Upstream LLM provider; `None` runs chat completions in offline mode
+    pub provider: Option<ProviderConfig>,
+    /// Further providers, by the name `[[models]]` refer to them with
+    pub providers: BTreeMap<String, ProviderConfig>,
+    /// Models clients can ask for, and the provider serving each
+    pub models: Vec<RegisteredModel>,
+    pub embeddings: EmbeddingConfig,
+    pub rag: RagConfig,
+    pub titles: TitleConfig,
+    pub capture: CaptureConfig,
+    /// Where to log chat and embedding requests; `None` disables the log
+    pub interaction_log: Option<InteractionLogConfig>,
+    pub limits: LimitsConfig,
+    pub docs: DocsConfig,
+    pub webhooks: WebhookConfig,
+    pub instance: InstanceConfig,
+    pub sync: SyncConfig,
+    pub inbox: InboxConfig,
+    pub connectors: ConnectorsConfig,
+    pub scheduler: SchedulerConfig,
+    pub export: ExportConfig,
+    pub attachment_text: AttachmentTextConfig,
+    pub sessions: SessionConfig,
+    pub cleanup: CleanupConfig,
+    pub importance: ImportanceConfig,
+    pub related: RelatedConfig,
+    pub activity: ActivityConfig,
+    pub auto_tag: AutoTagConfig,
+    pub summarize: SummarizeConfig,
+    pub chat_tools: ChatToolsConfig,
+    pub templates: TemplateConfig,
+    pub validation: ValidationConfig,
+    pub analytics: AnalyticsConfig,
+    pub workspaces: WorkspaceConfig,
+    pub demo: DemoConfig,
+}
+
+impl ConduitConfig {
+    /// The config file location: `CONDUIT_CONFIG` if set, otherwise `~/.conduit/config.toml`
+    pub fn default_path() -> Option<PathBuf> {
+        match std::env::var_os("CONDUIT_CONFIG") {
+            Some(path) => Some(PathBuf::from(path)),
+            None => dirs::home_dir().map(|home| home.join(".conduit").join("config.toml")),
+        }
+    }
+
+    /// Read settings from a TOML file, without applying the environment
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config file {}: {}", path.display(), e))?;
+        toml::from_str(&contents)
+            .map_err(|e| format!("Invalid config file {}: {}", path.display(), e))
+    }
+
+    /// Read the defaults overridden by the `CONDUIT_*` environment variables
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        config.apply_env();
+        config
+    }
+
+    /// Read the config file, if there is one, and apply the environment on top
+    ///
+    /// A missing `~/.conduit/config.toml` is not an error, but a missing file
+    /// named by `CONDUIT_CONFIG` is.
+    pub fn load() -> Result<Self, String> {
+        let mut config = match Self::default_path() {
+            Some(path) if path.exists() || std::env::var_os("CONDUIT_CONFIG").is_some() => {
+                tracing::info!("Loading config file: {}", path.display());
+                Self::from_file(&path)?
+            }
+            _ => Self::default(),
+        };
+        config.apply_env();
+        Ok(config)
+    }
+
+    /// The default store directory, `~/.conduit/memories`
+    pub fn default_store_path() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".conduit").join("memories"))
+    }
+
+    /// Store directory of every workspace, by name, with the `[storage]` store as `default`
+    pub fn workspace_paths(&self) -> BTreeMap<String, PathBuf> {
+        let mut paths = self.workspaces.stores.clone();
+        if let Some(path) = self.storage.path.clone().or_else(Self::default_store_path) {
+            paths.insert(DEFAULT_WORKSPACE.to_string(), path);
+        }
+        paths
+    }
+
+    /// Content preprocessing of a workspace: its own under `[workspaces.preprocess]`,
+    /// or else `[storage.preprocess]`
+    pub fn preprocess_for(&self, workspace: &str) -> &PreprocessConfig {
+        self.workspaces.preprocess.get(workspace).unwrap_or(&self.storage.options.preprocess)
+    }
+
+    /// Save `path` as the store directory in the config file
+    ///
+    /// Only `path` in the `[storage]` section is changed; the rest of the file
+    /// is kept as it is, and the file is created if there is none. The
+    /// directory is created if needed. Fails if `CONDUIT_MEMORY_PATH` is set,
+    /// since it would override the saved path. Returns the path of the config file.
+    pub fn set_store_path(path: &Path) -> Result<PathBuf, String> {
+        if std::env::var_os("CONDUIT_MEMORY_PATH").is_some_and(|path| !path.is_empty()) {
+            return Err("CONDUIT_MEMORY_PATH is set and overrides the store path in the config file".to_string());
+        }
+        let path = create_store_dir(path)?;
+        let file = Self::edit_file(|document, file| {
+            let storage = document.entry("storage")
+                .or_insert_with(toml_edit::table)
+                .as_table_like_mut()
+                .ok_or_else(|| format!("Invalid config file {}: `storage` is not a section", file.display()))?;
+            storage.insert("path", toml_edit::value(path.to_string_lossy().as_ref()));
+            Ok(())
+        })?;
+        tracing::info!("Saved store path {} to {}", path.display(), file.display());
+        Ok(file)
+    }
+
+    /// Add a workspace with its store at `path` to the config file
+    ///
+    /// The directory is created if needed. Fails if the name is invalid or
+    /// already taken. Returns the path of the config file.
+    pub fn add_workspace(name: &str, path: &Path) -> Result<PathBuf, String> {
+        if !is_valid_workspace_name(name) {
+            return Err(format!("Invalid workspace name {:?}: use letters, digits, `-` and `_`", name));
+        }
+        if name == DEFAULT_WORKSPACE || Self::load()?.workspaces.stores.contains_key(name) {
+            return Err(format!("A workspace named {} already exists", name));
+        }
+        let path = create_store_dir(path)?;
+        let file = Self::edit_file(|document, file| {
+            let stores = workspaces_section(document, file)?
+                .entry("stores")
+                .or_insert_with(toml_edit::table)
+                .as_table_like_mut()
+                .ok_or_else(|| format!("Invalid config file {}: `workspaces.stores` is not a section", file.display()))?;
+            stores.insert(name, toml_edit::value(path.to_string_lossy().as_ref()));
+            Ok(())
+        })?;
+        tracing::info!("Saved workspace {} at {} to {}", name, path.display(), file.display());
+        Ok(file)
+    }
+
+    /// Save `name` as the active workspace in the config file
+    ///
+    /// Fails if no such workspace is configured, or if `CONDUIT_WORKSPACE`
+    /// is set, since it would override the saved choice. Returns the path of
+    /// the config file.
+    pub fn set_active_workspace(name: &str) -> Result<PathBuf, String> {
+        if std::env::var_os("CONDUIT_WORKSPACE").is_some_and(|name| !name.is_empty()) {
+            return Err("CONDUIT_WORKSPACE is set and overrides the active workspace in the config file".to_string());
+        }
+        if name != DEFAULT_WORKSPACE && !Self::load()?.workspaces.stores.contains_key(name) {
+            return Err(format!("Unknown workspace {}", name));
+        }
+        let file = Self::edit_file(|document, file| {
+            let workspaces = workspaces_section(document, file)?;
+            if name == DEFAULT_WORKSPACE {
+                workspaces.remove("active");
+            } else {
+                workspaces.insert("active", toml_edit::value(name));
+            }
+            Ok(())
+        })?;
+        tracing::info!("Saved active workspace {} to {}", name, file.display());
+        Ok(file)
+    }
+
+    // Change the config file in place, keeping comments and layout, and
+    // return its path; the file is created if there is none
+    fn edit_file(
+        change: impl FnOnce(&mut toml_edit::DocumentMut, &Path) -> Result<(), String>,
+    ) -> Result<PathBuf, String> {
+        let file = Self::default_path().ok_or_else(|| "Could not find home directory".to_string())?;
+        let mut document: toml_edit::DocumentMut = match std::fs::read_to_string(&file) {
+            Ok(contents) => contents.parse().map_err(|e| format!("Invalid config file {}: {}", file.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => toml_edit::DocumentMut::new(),
+            Err(e) => return Err(format!("Failed to read config file {}: {}", file.display(), e)),
+        };
+        change(&mut document, &file)?;
+
+        if let Some(folder) = file.parent() {
+            std::fs::create_dir_all(folder).map_err(|e| format!("Failed to create {}: {}", folder.display(), e))?;
+        }
+        write_atomic(&file, document.to_string())
+            .map_err(|e| format!("Failed to write config file {}: {}", file.display(), e))?;
+        Ok(file)
+    }
+
+    /// Override settings with the `CONDUIT_*` environment variables that are set
+    ///
+    /// `CONDUIT_MEMORY_PATH` sets the store directory, `CONDUIT_LISTEN` the
+    /// server address and `CONDUIT_DEMO`/`CONDUIT_DEMO_SEED` the demo mode; the
+    /// remaining variables are described on each section.
+    pub fn apply_env(&mut self) {
+        if let Some(path) = std::env::var_os("CONDUIT_MEMORY_PATH").filter(|path| !path.is_empty()) {
+            self.storage.path = Some(PathBuf::from(path));
+        }
+        self.storage.options.apply_env();
+
+        if let Some(addr) = std::env::var("CONDUIT_LISTEN").ok().and_then(|addr| addr.parse().ok()) {
+            self.listen.addr = addr;
+        }
+
+        self.auth.apply_env();
+        self.accounts.apply_env();
+        self.cors.apply_env();
+        self.docs.apply_env();
+        self.webhooks.apply_env();
+        self.instance.apply_env();
+        self.sync.apply_env();
+        self.attachment_text.apply_env();
+        self.capture.apply_env();
+        self.sessions.apply_env();
+        self.cleanup.apply_env();
+        self.importance.apply_env();
+        self.related.apply_env();
+        self.activity.apply_env();
+        self.auto_tag.apply_env();
+        self.summarize.apply_env();
+        self.chat_tools.apply_env();
+        self.templates.apply_env();
+        self.validation.apply_env();
+        self.analytics.apply_env();
+        self.workspaces.apply_env();
+
+        // An upstream set in the environment replaces the one from the file
+        match ProviderConfig::from_env() {
+            Some(provider) => self.provider = Some(provider),
+            None => {
+                if let Some(provider) = &mut self.provider {
+                    provider.apply_env();
+                }
+            }
+        }
+
+        self.embeddings.apply_env();
+        self.rag.apply_env();
+        self.titles.apply_env();
+
+        if std::env::var_os("CONDUIT_INTERACTION_LOG").is_some() {
+            self.interaction_log = InteractionLogConfig::from_env();
+        } else if let Some(log) = &mut self.interaction_log {
+            log.apply_env();
+        }
+
+        if let Some(secs) = std::env::var("CONDUIT_REQUEST_TIMEOUT").ok().and_then(|t| t.parse().ok()) {
+            self.limits.request_timeout_secs = secs;
+        }
+        if let Some(max) = std::env::var("CONDUIT_LLM_MAX_CONCURRENCY").ok().and_then(|max| max.parse().ok()) {
+            self.limits.llm_max_concurrency = max;
+        }
+        if let Some(ms) = std::env::var("CONDUIT_LLM_QUEUE_TIMEOUT_MS").ok().and_then(|ms| ms.parse().ok()) {
+            self.limits.llm_queue_timeout_ms = ms;
+        }
+
+        if let Ok(demo) = std::env::var("CONDUIT_DEMO") {
+            self.demo.enabled = matches!(demo.to_lowercase().as_str(), "1" | "true" | "yes");
+        }
+        if let Some(seed) = std::env::var("CONDUIT_DEMO_SEED").ok().and_then(|seed| seed.parse().ok()) {
+            self.demo.seed = seed;
+        }
+    }
+
+    /// The API server settings from this configuration
+    pub fn server_options(&self) -> ServerOptions {
+        ServerOptions {
+            provider: self.provider.clone(),
+            providers: self.providers.clone(),
+            models: self.models.clone(),
+            rag: self.rag.clone(),
+            embeddings: self.embeddings.clone(),
+            request_timeout_secs: Some(self.limits.request_timeout_secs).filter(|secs| *secs > 0),
+            llm_max_concurrency: Some(self.limits.llm_max_concurrency).filter(|max| *max > 0),
+            llm_queue_timeout_ms: self.limits.llm_queue_timeout_ms,
+            interaction_log: self.interaction_log.clone(),
+            titles: self.titles.clone(),
+            capture: self.capture.clone(),
+            auth: self.auth.clone(),
+            accounts: self.accounts.clone(),
+            cors: self.cors.clone(),
+            docs: self.docs.clone(),
+            webhooks: self.webhooks.clone(),
+            instance: self.instance.clone(),
+            sync: self.sync.clone(),
+            inbox: self.inbox.clone(),
+            connectors: self.connectors.clone(),
+            scheduler: self.scheduler.clone(),
+            export: self.export.clone(),
+            attachment_text: self.attachment_text.clone(),
+            sessions: self.sessions.clone(),
+            cleanup: self.cleanup.clone(),
+            importance: self.importance.clone(),
+            related: self.related.clone(),
+            activity: self.activity.clone(),
+            auto_tag: self.auto_tag.clone(),
+            summarize: self.summarize.clone(),
+            chat_tools: self.chat_tools.clone(),
+            templates: self.templates.clone(),
+            validation: self.validation.clone(),
+            analytics: self.analytics.clone(),
+            workspace: self.workspaces.active.clone(),
+            workspaces: self.workspace_paths(),
+            workspace_preprocess: self.workspace_paths().into_keys()
+                .map(|name| {
+                    let preprocess = self.preprocess_for(&name).clone();
+                    (name, preprocess)
+                })
+                .collect(),
+        }
+    }
+}
+
+// Create a store directory and resolve it to an absolute path for the config file
+fn create_store_dir(path: &Path) -> Result<PathBuf, String> {
+    init_store_dir(path)
+        .map_err(|e| format!("Failed to create memory directory {}: {}", path.display(), e))?;
+    path.canonicalize()
+        .map_err(|e| format!("Invalid memory directory {}: {}", path.display(), e))
+}
+
+// The `[workspaces]` section of a config file, added if missing
+fn workspaces_section<'a>(document: &'a mut toml_edit::DocumentMut, file: &Path) -> Result<&'a mut dyn toml_edit::TableLike, String> {
+    document.entry("workspaces")
+        .or_insert_with(toml_edit::table)
+        .as_table_like_mut()
+        .ok_or_else(|| format!("Invalid config file {}: `workspaces` is not a section", file.display()))
+}
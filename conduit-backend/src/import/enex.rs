@@ -0,0 +1,322 @@
+//! Evernote `.enex` exports
+//!
+//! An export is an XML file of notes, usually one per notebook. Each note
+//! holds its body as ENML, a subset of XHTML, and its images and files as
+//! base64 resources that the body refers to by MD5 hash.
+
+use std::collections::HashMap;
+use base64::Engine;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use md5::{Digest, Md5};
+use roxmltree::{Document, Node, ParsingOptions};
+
+use crate::memory::{Memory, MemoryError};
+use super::{attachment_name, link_target, ExportFiles, Note, NOTE_ID};
+
+fn parse_xml(text: &str) -> Result<Document<'_>, roxmltree::Error> {
+    Document::parse_with_options(text, ParsingOptions { allow_dtd: true, ..ParsingOptions::default() })
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+fn child_text(node: Node, name: &str) -> Option<String> {
+    child(node, name).map(|child| child.text().unwrap_or_default().trim().to_string())
+}
+
+// Evernote times look like `20240105T153000Z`
+fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ").ok().map(|time| time.and_utc())
+}
+
+// A resource stored as an attachment, found by the hash the note body uses for it
+struct Resource {
+    name: String,
+    is_image: bool,
+}
+
+// Converts ENML to markdown
+struct Converter<'a> {
+    resources: &'a HashMap<String, Resource>,
+    out: String,
+    lists: Vec<Option<usize>>,
+}
+
+impl Converter<'_> {
+    // Start a new block, separated from the previous one by a blank line
+    fn block(&mut self) {
+        let trimmed = self.out.trim_end_matches(' ').len();
+        self.out.truncate(trimmed);
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push_str(if self.out.ends_with('\n') { "\n" } else { "\n\n" });
+        }
+    }
+
+    fn line(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+    }
+
+    fn inline(&mut self, node: Node, marker: &str) {
+        self.out.push_str(marker);
+        self.children(node);
+        self.out.push_str(marker);
+    }
+
+    fn children(&mut self, node: Node) {
+        for child in node.children() {
+            self.node(child);
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        let mut collapsed = String::new();
+        for (index, word) in text.split_whitespace().enumerate() {
+            if index > 0 {
+                collapsed.push(' ');
+            }
+            collapsed.push_str(word);
+        }
+        if text.starts_with(char::is_whitespace) && !self.out.is_empty() && !self.out.ends_with([' ', '\n']) {
+            self.out.push(' ');
+        }
+        self.out.push_str(&collapsed);
+        if text.ends_with(char::is_whitespace) && !collapsed.is_empty() {
+            self.out.push(' ');
+        }
+    }
+
+    fn node(&mut self, node: Node) {
+        if node.is_text() {
+            self.text(node.text().unwrap_or_default());
+            return;
+        }
+        if !node.is_element() {
+            return;
+        }
+
+        match node.tag_name().name() {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = node.tag_name().name()[1..].parse().unwrap_or(1);
+                self.block();
+                self.out.push_str(&"#".repeat(level));
+                self.out.push(' ');
+                self.children(node);
+                self.block();
+            }
+            "p" | "div" | "en-note" => {
+                if self.lists.is_empty() {
+                    self.block();
+                } else {
+                    self.line();
+                }
+                self.children(node);
+                if self.lists.is_empty() {
+                    self.block();
+                }
+            }
+            "br" => self.out.push('\n'),
+            "hr" => {
+                self.block();
+                self.out.push_str("---");
+                self.block();
+            }
+            "b" | "strong" => self.inline(node, "**"),
+            "i" | "em" => self.inline(node, "*"),
+            "s" | "strike" | "del" => self.inline(node, "~~"),
+            "code" => self.inline(node, "`"),
+            "pre" => {
+                self.block();
+                self.out.push_str("```\n");
+                self.out.push_str(node.descendants().filter(|n| n.is_text()).filter_map(|n| n.text()).collect::<String>().trim_end());
+                self.out.push_str("\n```");
+                self.block();
+            }
+            "blockquote" => {
+                self.block();
+                let start = self.out.len();
+                self.children(node);
+                let quoted = self.out[start..].trim().lines().map(|line| format!("> {}", line)).collect::<Vec<_>>().join("\n");
+                self.out.truncate(start);
+                self.out.push_str(&quoted);
+                self.block();
+            }
+            "a" => {
+                let start = self.out.len();
+                self.children(node);
+                let text = self.out[start..].trim().to_string();
+                self.out.truncate(start);
+                match node.attribute("href") {
+                    Some(href) => self.out.push_str(&format!("[{}]({})", text, href)),
+                    None => self.out.push_str(&text),
+                }
+            }
+            "ul" | "ol" => {
+                if self.lists.is_empty() {
+                    self.block();
+                }
+                self.lists.push((node.tag_name().name() == "ol").then_some(1));
+                self.children(node);
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.block();
+                }
+            }
+            "li" => {
+                self.line();
+                let depth = self.lists.len().saturating_sub(1);
+                self.out.push_str(&"  ".repeat(depth));
+                match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        self.out.push_str(&format!("{}. ", number));
+                        *number += 1;
+                    }
+                    _ => self.out.push_str("- "),
+                }
+                self.children(node);
+            }
+            "en-todo" => self.out.push_str(if node.attribute("checked") == Some("true") { "[x] " } else { "[ ] " }),
+            "en-media" => {
+                let resource = node.attribute("hash").and_then(|hash| self.resources.get(&hash.to_lowercase()));
+                if let Some(resource) = resource {
+                    let target = format!("attachments/{}/{}", NOTE_ID, link_target(&resource.name));
+                    let embed = if resource.is_image { "!" } else { "" };
+                    self.out.push_str(&format!("{}[{}]({})", embed, resource.name, target));
+                }
+            }
+            "table" => {
+                self.block();
+                for (index, row) in node.descendants().filter(|n| n.has_tag_name("tr")).enumerate() {
+                    let cells: Vec<String> = row.children()
+                        .filter(|cell| cell.has_tag_name("td") || cell.has_tag_name("th"))
+                        .map(|cell| {
+                            let mut converter = Converter { resources: self.resources, out: String::new(), lists: Vec::new() };
+                            converter.children(cell);
+                            converter.out.split_whitespace().collect::<Vec<_>>().join(" ").replace('|', "\\|")
+                        })
+                        .collect();
+                    self.out.push_str(&format!("| {} |\n", cells.join(" | ")));
+                    if index == 0 {
+                        self.out.push_str(&format!("|{}\n", " --- |".repeat(cells.len())));
+                    }
+                }
+                self.block();
+            }
+            _ => self.children(node),
+        }
+    }
+}
+
+// Markdown for a note body, with media pointing at the note's attachments
+fn to_markdown(enml: &str, resources: &HashMap<String, Resource>) -> Result<String, String> {
+    let document = parse_xml(enml).map_err(|e| format!("Invalid note content: {}", e))?;
+    let mut converter = Converter { resources, out: String::new(), lists: Vec::new() };
+    converter.node(document.root_element());
+
+    let mut markdown = String::new();
+    let mut blank = 0;
+    for line in converter.out.lines() {
+        let line = line.trim_end();
+        blank = if line.is_empty() { blank + 1 } else { 0 };
+        if blank < 2 {
+            markdown.push_str(line);
+            markdown.push('\n');
+        }
+    }
+    Ok(markdown.trim().to_string())
+}
+
+fn read_note(note: Node, source: &str, notebook: Option<&str>) -> Result<Note, String> {
+    let title = child_text(note, "title").filter(|title| !title.is_empty()).unwrap_or_else(|| "Untitled".to_string());
+
+    let mut attachments: Vec<(String, Vec<u8>)> = Vec::new();
+    let mut resources = HashMap::new();
+    for (index, resource) in note.children().filter(|child| child.has_tag_name("resource")).enumerate() {
+        let encoded: String = child_text(resource, "data").unwrap_or_default().split_whitespace().collect();
+        let data = base64::engine::general_purpose::STANDARD.decode(encoded)
+            .map_err(|e| format!("Invalid attachment data: {}", e))?;
+        let mime = child_text(resource, "mime").unwrap_or_default();
+        let file_name = child(resource, "resource-attributes")
+            .and_then(|attributes| child_text(attributes, "file-name"))
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| {
+                let extension = mime_guess::get_mime_extensions_str(&mime).and_then(|extensions| extensions.first()).unwrap_or(&"bin");
+                format!("attachment-{}.{}", index + 1, extension)
+            });
+
+        let name = attachment_name(&attachments, &file_name);
+        let hash = hex::encode(Md5::digest(&data));
+        resources.insert(hash, Resource { name: name.clone(), is_image: mime.starts_with("image/") });
+        attachments.push((name, data));
+    }
+
+    let content = match child_text(note, "content") {
+        Some(enml) if !enml.is_empty() => to_markdown(&enml, &resources)?,
+        _ => String::new(),
+    };
+    let tags = note.children()
+        .filter(|child| child.has_tag_name("tag"))
+        .filter_map(|tag| tag.text().map(|text| text.trim().to_string()))
+        .filter(|tag| !tag.is_empty())
+        .collect();
+
+    let mut memory = Memory::new(title, content, tags);
+    memory.id = String::new();
+    if let Some(created) = child_text(note, "created").and_then(|time| parse_time(&time)) {
+        memory.created_at = created;
+        memory.updated_at = created;
+    }
+    if let Some(updated) = child_text(note, "updated").and_then(|time| parse_time(&time)) {
+        memory.updated_at = updated;
+    }
+    if let Some(url) = child(note, "note-attributes").and_then(|attributes| child_text(attributes, "source-url")).filter(|url| !url.is_empty()) {
+        memory.content = format!("{}\n\nSource: {}", memory.content, url).trim().to_string();
+    }
+
+    Ok(Note {
+        source: source.to_string(),
+        memory,
+        notebook: notebook.map(str::to_string),
+        attachments,
+    })
+}
+
+/// Read the notes of an `.enex` file, putting them in `notebook` if set
+///
+/// Note bodies are converted to markdown, tags and times are kept, and
+/// images and files become attachments. Returns `MemoryError::InvalidFormat`
+/// if the file is not an Evernote export. A note that cannot be read fails
+/// the whole file, so that a broken export is not half imported.
+pub fn parse(data: &[u8], notebook: Option<&str>) -> Result<Vec<Note>, MemoryError> {
+    let text = std::str::from_utf8(data).map_err(|_| MemoryError::InvalidFormat("Export is not valid UTF-8".to_string()))?;
+    let document = parse_xml(text).map_err(|e| MemoryError::InvalidFormat(format!("Invalid Evernote export: {}", e)))?;
+    let root = document.root_element();
+    if !root.has_tag_name("en-export") {
+        return Err(MemoryError::InvalidFormat("Not an Evernote export".to_string()));
+    }
+
+    let name = notebook.unwrap_or("export");
+    root.children()
+        .filter(|child| child.has_tag_name("note"))
+        .enumerate()
+        .map(|(index, note)| {
+            let source = format!("{} (note {})", name, index + 1);
+            read_note(note, &source, notebook)
+                .map_err(|e| MemoryError::InvalidFormat(format!("{}: {}", source, e)))
+        })
+        .collect()
+}
+
+/// Read every `.enex` file of a folder or zip, each into a notebook named after the file
+pub(crate) fn read_files(files: &ExportFiles) -> Result<Vec<Note>, MemoryError> {
+    let mut notes = Vec::new();
+    for (path, file) in files {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        if let Some(notebook) = name.strip_suffix(".enex") {
+            notes.extend(parse(&file.data, Some(notebook))?);
+        }
+    }
+    Ok(notes)
+}
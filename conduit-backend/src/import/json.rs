@@ -0,0 +1,70 @@
+//! Memories as JSON objects
+//!
+//! The format is an array of memory objects, as returned by
+//! `GET /api/memories/export.json`. Only the title or content is required, so
+//! that scripts can build memories without knowing every field.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::memory::{Grant, ImportJob, ImportReport, Memory, MemoryError, MemoryStore, Reference};
+
+/// A memory to import; missing fields get the values a new memory would have
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct JsonMemory {
+    /// Kept if it is valid and no other memory has it
+    pub id: Option<String>,
+    pub title: String,
+    pub content: String,
+    pub tags: Vec<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub remind_at: Option<DateTime<Utc>>,
+    pub references: Vec<Reference>,
+    pub owner: Option<String>,
+    pub shared: Vec<Grant>,
+}
+
+impl JsonMemory {
+    fn into_memory(self) -> Result<Memory, String> {
+        if self.title.trim().is_empty() && self.content.trim().is_empty() {
+            return Err("A memory needs a title or content".to_string());
+        }
+
+        let mut memory = Memory::new(self.title, self.content, self.tags);
+        memory.id = self.id.unwrap_or_default();
+        if let Some(created) = self.created_at {
+            memory.created_at = created;
+            memory.updated_at = created;
+        }
+        if let Some(updated) = self.updated_at {
+            memory.updated_at = updated;
+        }
+        memory.remind_at = self.remind_at;
+        memory.references = self.references;
+        memory.owner = self.owner;
+        memory.shared = self.shared;
+        Ok(memory)
+    }
+}
+
+/// Read a JSON array of memory objects
+///
+/// Returns `MemoryError::InvalidFormat` if the data is not an array of objects.
+pub fn parse(data: &[u8]) -> Result<Vec<JsonMemory>, MemoryError> {
+    serde_json::from_slice(data).map_err(|e| MemoryError::InvalidFormat(format!("Invalid JSON import: {}", e)))
+}
+
+/// Import memory objects with [`MemoryStore::import_memories`]
+///
+/// Each memory is reported by its position, as `memories[0]` and so on. A
+/// memory with neither a title nor content is reported as failed.
+pub fn import_json(store: &MemoryStore, memories: Vec<JsonMemory>, job: &ImportJob) -> Result<ImportReport, MemoryError> {
+    let documents = memories.into_iter()
+        .enumerate()
+        .map(|(index, memory)| (format!("memories[{}]", index), memory.into_memory()))
+        .collect();
+    store.import_documents(documents, job)
+}
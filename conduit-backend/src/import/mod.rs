@@ -0,0 +1,306 @@
+//! Importers for notes exported from other apps
+//!
+//! Each importer reads an export into [`Note`]s, which [`import_notes`] saves
+//! as memories with the usual [`ImportJob`] strategies. Notebooks become
+//! collections, and embedded images and files become attachments.
+
+pub mod enex;
+pub mod json;
+pub mod notion;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{Cursor, Read, Seek};
+use std::path::Path;
+use std::str::FromStr;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use zip::ZipArchive;
+
+use crate::memory::{ImportJob, ImportReport, Memory, MemoryError, MemoryStore, ZipBudget, MAX_ZIP_DEPTH};
+
+/// Placeholder for the memory ID in links to a note's attachments, as in
+/// `attachments/{id}/photo.png`; it is replaced once the note has been saved
+pub const NOTE_ID: &str = "{id}";
+
+/// Kind of export being imported
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    /// Markdown files, such as a Conduit export or an Obsidian vault
+    #[default]
+    Markdown,
+    /// A Notion "Markdown & CSV" export
+    Notion,
+    /// Evernote `.enex` files
+    Enex,
+}
+
+impl FromStr for ImportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "markdown" | "md" => Ok(Self::Markdown),
+            "notion" => Ok(Self::Notion),
+            "enex" | "evernote" => Ok(Self::Enex),
+            other => Err(format!("Unknown import format: {}", other)),
+        }
+    }
+}
+
+/// A note read from an export, ready to be imported
+#[derive(Debug, Clone)]
+pub struct Note {
+    /// Where the note came from, such as a file name
+    pub source: String,
+    /// The memory to create; an empty ID lets the store pick one
+    pub memory: Memory,
+    /// Notebook, database or parent page the note belongs to, imported as a collection
+    pub notebook: Option<String>,
+    /// Files to attach, by name; the content links to them through [`NOTE_ID`]
+    pub attachments: Vec<(String, Vec<u8>)>,
+}
+
+/// A file of an export, by its `/`-separated path
+pub(crate) struct ExportFile {
+    pub data: Vec<u8>,
+    pub modified: DateTime<Utc>,
+}
+
+pub(crate) type ExportFiles = BTreeMap<String, ExportFile>;
+
+// Folders such as `__MACOSX` and `.git` hold metadata, not notes
+fn is_hidden(path: &str) -> bool {
+    path.split('/').any(|part| part.starts_with('.') || part == "__MACOSX")
+}
+
+/// Read every file of a zip, including those of zips inside it, as Notion
+/// splits large exports into several parts
+///
+/// Zips that unpack to too much data, or nest too deeply, are refused with
+/// [`MemoryError::InvalidFormat`].
+pub(crate) fn read_zip<R: Read + Seek>(reader: R) -> Result<ExportFiles, MemoryError> {
+    read_nested_zip(reader, 0, &mut ZipBudget::new())
+}
+
+fn read_nested_zip<R: Read + Seek>(reader: R, depth: usize, budget: &mut ZipBudget) -> Result<ExportFiles, MemoryError> {
+    let mut archive = ZipArchive::new(reader)
+        .map_err(|e| MemoryError::InvalidFormat(format!("Not a zip archive: {}", e)))?;
+
+    let mut files = ExportFiles::new();
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)
+            .map_err(|e| MemoryError::InvalidFormat(format!("Unreadable zip entry: {}", e)))?;
+        let Some(path) = entry.enclosed_name().map(|path| path.to_string_lossy().replace('\\', "/")) else {
+            continue;
+        };
+        if entry.is_dir() || is_hidden(&path) {
+            continue;
+        }
+        let data = budget.read(&mut entry)?;
+        if path.to_lowercase().ends_with(".zip") {
+            if depth == MAX_ZIP_DEPTH {
+                return Err(MemoryError::InvalidFormat(format!("Zip archives nested more than {} deep", MAX_ZIP_DEPTH)));
+            }
+            files.extend(read_nested_zip(Cursor::new(data), depth + 1, budget)?);
+            continue;
+        }
+        let modified = crate::memory::zip_time(entry.last_modified());
+        files.insert(path, ExportFile { data, modified });
+    }
+    Ok(files)
+}
+
+/// Read every file of a folder and its subfolders
+pub(crate) fn read_dir(root: &Path) -> Result<ExportFiles, MemoryError> {
+    let mut files = ExportFiles::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir)?.flatten() {
+            let path = entry.path();
+            let Ok(relative) = path.strip_prefix(root) else {
+                continue;
+            };
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            if is_hidden(&relative) {
+                continue;
+            }
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                let modified = fs::metadata(&path)
+                    .and_then(|metadata| metadata.modified())
+                    .map(DateTime::<Utc>::from)
+                    .unwrap_or_else(|_| Utc::now());
+                files.insert(relative, ExportFile { data: fs::read(&path)?, modified });
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Read an export in the given format from a folder, or from a single file
+/// such as a Notion zip or an `.enex` file
+pub fn read_path(format: ImportFormat, path: &Path) -> Result<Vec<Note>, MemoryError> {
+    if path.is_file() {
+        let name = path.file_name().map(|name| name.to_string_lossy().to_string());
+        return read_bytes(format, &fs::read(path)?, name.as_deref());
+    }
+    if !path.is_dir() {
+        return Err(MemoryError::InvalidImportPath(format!("{} does not exist", path.display())));
+    }
+    let files = read_dir(path)?;
+    match format {
+        ImportFormat::Notion => Ok(notion::read_files(&files)),
+        ImportFormat::Enex => enex::read_files(&files),
+        ImportFormat::Markdown => Err(MemoryError::InvalidFormat("Markdown is imported by the store".to_string())),
+    }
+}
+
+/// Read an export in the given format from an upload
+///
+/// Notion exports are zips. Evernote notes may be a single `.enex` file, whose
+/// notes go into the `notebook` collection if one is named, or a zip of them.
+pub fn read_bytes(format: ImportFormat, data: &[u8], notebook: Option<&str>) -> Result<Vec<Note>, MemoryError> {
+    let is_zip = data.starts_with(b"PK\x03\x04");
+    match format {
+        ImportFormat::Notion => Ok(notion::read_files(&read_zip(Cursor::new(data))?)),
+        ImportFormat::Enex if is_zip => enex::read_files(&read_zip(Cursor::new(data))?),
+        ImportFormat::Enex => {
+            let notebook = notebook.map(|name| name.strip_suffix(".enex").unwrap_or(name));
+            enex::parse(data, notebook.filter(|name| !name.is_empty()))
+        }
+        ImportFormat::Markdown => Err(MemoryError::InvalidFormat("Markdown is imported by the store".to_string())),
+    }
+}
+
+/// Import an export from a folder or file on the server
+///
+/// Markdown folders are imported with [`MemoryStore::import_dir`]. Folders may
+/// not overlap the store.
+pub fn import_path(store: &MemoryStore, format: ImportFormat, path: &Path, job: &ImportJob) -> Result<ImportReport, MemoryError> {
+    if format == ImportFormat::Markdown {
+        return store.import_dir(path, job);
+    }
+    let path = if path.is_dir() { store.check_import_dir(path)? } else { path.to_path_buf() };
+    import_notes(store, read_path(format, &path)?, job)
+}
+
+/// Import an uploaded export
+///
+/// Markdown zips are imported with [`MemoryStore::import_zip`].
+pub fn import_bytes(store: &MemoryStore, format: ImportFormat, data: &[u8], notebook: Option<&str>, job: &ImportJob) -> Result<ImportReport, MemoryError> {
+    if format == ImportFormat::Markdown {
+        return store.import_zip(Cursor::new(data), job);
+    }
+    import_notes(store, read_bytes(format, data, notebook)?, job)
+}
+
+/// Import notes read from an export
+///
+/// Notes are matched and saved like [`MemoryStore::import_memories`] does.
+/// Their attachments are then added, links to them are pointed at the saved
+/// memory, and each memory is added to the collection named after its
+/// notebook, which is created if no collection has that name.
+pub fn import_notes(store: &MemoryStore, notes: Vec<Note>, job: &ImportJob) -> Result<ImportReport, MemoryError> {
+    let mut extras = Vec::new();
+    let mut memories = Vec::new();
+    for note in notes {
+        memories.push((note.source, note.memory));
+        extras.push((note.notebook, note.attachments));
+    }
+    let mut report = store.import_memories(memories, job)?;
+
+    let mut notebooks: HashMap<String, Vec<String>> = HashMap::new();
+    for (item, (notebook, attachments)) in report.items.iter_mut().zip(extras) {
+        let Some(id) = item.id.clone() else {
+            continue;
+        };
+        let mut errors = Vec::new();
+        if job.dry_run {
+            item.attachments.extend(attachments.into_iter().map(|(name, _)| name));
+        } else if !attachments.is_empty() {
+            match attach(store, &id, attachments) {
+                Ok((added, failed)) => {
+                    item.attachments = added;
+                    errors.extend(failed);
+                }
+                Err(err) => errors.push(format!("attachments: {}", err)),
+            }
+        }
+        if !errors.is_empty() {
+            item.error = Some(errors.join("; "));
+        }
+        if let Some(notebook) = notebook {
+            notebooks.entry(notebook.clone()).or_default().push(id);
+            item.collection = Some(notebook);
+        }
+    }
+
+    if !job.dry_run {
+        let mut collections = store.list_collections()?;
+        for (name, ids) in notebooks {
+            match collections.iter().find(|collection| collection.name.eq_ignore_ascii_case(&name)) {
+                Some(collection) => {
+                    store.add_to_collection(&collection.id, &ids)?;
+                }
+                None => collections.push(store.create_collection(name, None, ids, Default::default())?),
+            }
+        }
+    }
+    Ok(report)
+}
+
+// Add a note's attachments to its memory and point the links to them at its ID
+//
+// The memory keeps the update time it was imported with, rather than the
+// time its attachments were added.
+fn attach(store: &MemoryStore, id: &str, attachments: Vec<(String, Vec<u8>)>) -> Result<(Vec<String>, Vec<String>), MemoryError> {
+    let updated_at = store.get(id)?.updated_at;
+    let mut added = Vec::new();
+    let mut failed = Vec::new();
+    for (name, data) in attachments {
+        match store.add_attachment(id, &name, &data) {
+            Ok(_) => added.push(name),
+            Err(err) => failed.push(format!("attachment {}: {}", name, err)),
+        }
+    }
+
+    let mut memory = store.get(id)?;
+    memory.content = memory.content.replace(&format!("attachments/{}/", NOTE_ID), &format!("attachments/{}/", id));
+    memory.updated_at = updated_at;
+    store.save(&memory)?;
+    Ok((added, failed))
+}
+
+// A file name that is unique among a note's attachments and safe to store
+pub(crate) fn attachment_name(taken: &[(String, Vec<u8>)], name: &str) -> String {
+    let name: String = name.chars()
+        .map(|c| if c.is_control() || matches!(c, '/' | '\\' | '<' | '>' | ':' | '"' | '|' | '?' | '*') { '_' } else { c })
+        .collect();
+    let name = name.trim_start_matches('.').trim();
+    let name = if name.is_empty() { "attachment" } else { name };
+
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{}", extension)),
+        _ => (name, String::new()),
+    };
+    let mut candidate = name.to_string();
+    let mut counter = 2;
+    while taken.iter().any(|(existing, _)| existing.eq_ignore_ascii_case(&candidate)) {
+        candidate = format!("{}-{}{}", stem, counter, extension);
+        counter += 1;
+    }
+    candidate
+}
+
+/// Percent-encode an attachment name for a markdown link
+pub(crate) fn link_target(name: &str) -> String {
+    percent_encoding::utf8_percent_encode(name, percent_encoding::NON_ALPHANUMERIC)
+        .to_string()
+        .replace("%2E", ".")
+        .replace("%2D", "-")
+        .replace("%5F", "_")
+}
@@ -0,0 +1,259 @@
+//! Notion "Markdown & CSV" exports
+//!
+//! Pages are markdown files named after their title and a 32-character ID,
+//! with their subpages and embedded files in a folder of the same name.
+//! Databases are CSV files whose rows also have a page each.
+
+use std::collections::HashSet;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use regex::{Captures, Regex};
+
+use crate::memory::Memory;
+use super::{attachment_name, link_target, ExportFile, ExportFiles, Note, NOTE_ID};
+
+/// Page properties holding tags
+const TAG_PROPERTIES: &[&str] = &["tags", "tag", "labels", "label", "keywords", "categories"];
+/// Page properties holding the creation time
+const CREATED_PROPERTIES: &[&str] = &["created", "created time", "date created"];
+/// Page properties holding the time of the last edit
+const UPDATED_PROPERTIES: &[&str] = &["last edited time", "last edited", "updated"];
+
+fn id_suffix() -> Regex {
+    Regex::new(r"\s+[0-9a-f]{32}$").unwrap()
+}
+
+// A file or folder name without Notion's ID
+fn clean_name(name: &str) -> String {
+    id_suffix().replace(name, "").trim().to_string()
+}
+
+fn stem(path: &str) -> &str {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.rsplit_once('.').map_or(name, |(stem, _)| stem)
+}
+
+fn parent(path: &str) -> &str {
+    path.rsplit_once('/').map_or("", |(parent, _)| parent)
+}
+
+// Resolve `..` and `.` in a path relative to the export root
+fn normalize(path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+// The top-level page or database a page is nested under, which becomes its collection
+//
+// Folders without a Notion ID, such as the workspace folder some exports
+// start with, are skipped. A top-level page with subpages is in its own collection.
+fn notebook(path: &str, files: &ExportFiles) -> Option<String> {
+    let suffix = id_suffix();
+    let parts: Vec<&str> = path.split('/').collect();
+    let start = parts.iter().position(|part| suffix.is_match(stem(part)))?;
+    let top = parts[start];
+    if parts.len() - start > 1 {
+        return Some(clean_name(top));
+    }
+    let prefix = format!("{}/", parts[..start].iter().chain([&stem(top)]).copied().collect::<Vec<_>>().join("/"));
+    files.range(prefix.clone()..)
+        .next()
+        .filter(|(other, _)| other.starts_with(&prefix))
+        .map(|_| clean_name(stem(top)))
+}
+
+fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(value, "%B %d, %Y %I:%M %p")
+        .or_else(|_| NaiveDate::parse_from_str(value, "%B %d, %Y").map(|date| date.and_hms_opt(0, 0, 0).unwrap_or_default()))
+        .or_else(|_| NaiveDate::parse_from_str(value, "%Y-%m-%d").map(|date| date.and_hms_opt(0, 0, 0).unwrap_or_default()))
+        .ok()
+        .map(|time| time.and_utc())
+}
+
+fn split_tags(value: &str) -> Vec<String> {
+    value.split(',')
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+// Tags and times from a page's properties
+fn apply_property(memory: &mut Memory, key: &str, value: &str) {
+    let key = key.trim().to_lowercase();
+    if TAG_PROPERTIES.contains(&key.as_str()) {
+        for tag in split_tags(value) {
+            if !memory.tags.contains(&tag) {
+                memory.tags.push(tag);
+            }
+        }
+    } else if CREATED_PROPERTIES.contains(&key.as_str()) {
+        if let Some(time) = parse_time(value) {
+            memory.created_at = time;
+        }
+    } else if UPDATED_PROPERTIES.contains(&key.as_str()) {
+        if let Some(time) = parse_time(value) {
+            memory.updated_at = time;
+        }
+    }
+}
+
+// A page, with links to other pages turned into wiki-links and embedded files into attachments
+fn read_page(path: &str, file: &ExportFile, files: &ExportFiles) -> Note {
+    let text = String::from_utf8_lossy(&file.data).replace("\r\n", "\n");
+    let mut lines: Vec<&str> = text.lines().collect();
+
+    let mut title = clean_name(stem(path));
+    if let Some(heading) = lines.first().and_then(|line| line.strip_prefix("# ")) {
+        title = heading.trim().to_string();
+        lines.remove(0);
+        if lines.first().is_some_and(|line| line.trim().is_empty()) {
+            lines.remove(0);
+        }
+    }
+
+    let mut memory = Memory::new(title, String::new(), Vec::new());
+    memory.id = String::new();
+    memory.created_at = file.modified;
+    memory.updated_at = file.modified;
+
+    // Database rows list their properties as `Key: Value` lines under the title
+    let property = Regex::new(r"^([^:]{1,40}): (.+)$").unwrap();
+    for line in lines.iter().take_while(|line| !line.trim().is_empty()) {
+        if let Some(captures) = property.captures(line) {
+            apply_property(&mut memory, &captures[1], &captures[2]);
+        }
+    }
+    if memory.updated_at < memory.created_at {
+        memory.updated_at = memory.created_at;
+    }
+
+    let mut attachments: Vec<(String, Vec<u8>)> = Vec::new();
+    let link = Regex::new(r"(!?)\[([^\]]*)\]\(([^)\s]+)\)").unwrap();
+    let dir = parent(path);
+    let body = lines.join("\n");
+    let content = link.replace_all(&body, |captures: &Captures| {
+        let (embed, text, target) = (&captures[1], &captures[2], &captures[3]);
+        if target.contains("://") || target.starts_with('#') || target.starts_with("mailto:") {
+            return captures[0].to_string();
+        }
+        let decoded = percent_encoding::percent_decode_str(target).decode_utf8_lossy();
+        let linked = normalize(&format!("{}/{}", dir, decoded));
+        let Some(linked_file) = files.get(&linked) else {
+            return captures[0].to_string();
+        };
+
+        let lower = linked.to_lowercase();
+        if lower.ends_with(".md") {
+            let page = clean_name(stem(&linked));
+            return if text.is_empty() || text == page { format!("[[{}]]", page) } else { format!("[[{}|{}]]", page, text) };
+        }
+        if lower.ends_with(".csv") {
+            return text.to_string();
+        }
+        let file_name = decoded.rsplit('/').next().unwrap_or(&decoded);
+        let name = match attachments.iter().find(|(_, data)| *data == linked_file.data) {
+            Some((name, _)) => name.clone(),
+            None => {
+                let name = attachment_name(&attachments, file_name);
+                attachments.push((name.clone(), linked_file.data.clone()));
+                name
+            }
+        };
+        format!("{}[{}](attachments/{}/{})", embed, text, NOTE_ID, link_target(&name))
+    });
+    memory.content = content.trim().to_string();
+
+    Note {
+        source: path.to_string(),
+        memory,
+        notebook: notebook(path, files),
+        attachments,
+    }
+}
+
+// Rows of a database that have no page of their own
+fn read_database(path: &str, file: &ExportFile, files: &ExportFiles, pages: &HashSet<String>) -> Vec<Note> {
+    let name = stem(path);
+    let name = name.strip_suffix("_all").unwrap_or(name);
+    let rows_dir = [parent(path), name].iter().filter(|part| !part.is_empty()).copied().collect::<Vec<_>>().join("/");
+
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(file.data.as_slice());
+    let headers: Vec<String> = match reader.headers() {
+        Ok(headers) => headers.iter().map(|header| header.trim_start_matches('\u{feff}').to_string()).collect(),
+        Err(e) => {
+            tracing::debug!("Failed to read Notion database {}: {:?}", path, e);
+            return Vec::new();
+        }
+    };
+
+    let mut notes = Vec::new();
+    for (index, record) in reader.records().flatten().enumerate() {
+        let title = record.get(0).unwrap_or_default().trim().to_string();
+        if title.is_empty() || pages.contains(&format!("{}/{}", rows_dir, title)) {
+            continue;
+        }
+
+        let mut memory = Memory::new(title.clone(), String::new(), Vec::new());
+        memory.id = String::new();
+        memory.created_at = file.modified;
+        memory.updated_at = file.modified;
+        let mut lines = Vec::new();
+        for (key, value) in headers.iter().zip(record.iter()).skip(1) {
+            if value.trim().is_empty() {
+                continue;
+            }
+            apply_property(&mut memory, key, value);
+            lines.push(format!("{}: {}", key, value.trim()));
+        }
+        memory.content = lines.join("\n");
+
+        notes.push(Note {
+            source: format!("{} (row {})", path, index + 1),
+            memory,
+            notebook: notebook(&format!("{}/{}.md", rows_dir, title), files),
+            attachments: Vec::new(),
+        });
+    }
+    notes
+}
+
+/// Read the pages and database rows of an export
+///
+/// Each page becomes a note titled after its heading, with the tags and
+/// times of its properties. Links to other pages become wiki-links, and
+/// images and files it links to become attachments. Pages nested under a
+/// top-level page or database go into a collection named after it. Database
+/// rows without a page of their own are imported from the CSV file.
+pub(crate) fn read_files(files: &ExportFiles) -> Vec<Note> {
+    let mut notes = Vec::new();
+    let mut pages = HashSet::new();
+    for (path, file) in files {
+        if path.to_lowercase().ends_with(".md") {
+            let note = read_page(path, file, files);
+            pages.insert(format!("{}/{}", parent(path), note.memory.title));
+            pages.insert(format!("{}/{}", parent(path), clean_name(stem(path))));
+            notes.push(note);
+        }
+    }
+
+    for (path, file) in files {
+        if !path.to_lowercase().ends_with(".csv") {
+            continue;
+        }
+        // Notion writes both the current view and `_all` with every row; only read the latter
+        let all = format!("{}_all.csv", &path[..path.len() - 4]);
+        if files.contains_key(&all) {
+            continue;
+        }
+        notes.extend(read_database(path, file, files, &pages));
+    }
+    notes
+}
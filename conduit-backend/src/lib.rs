@@ -1,16 +1,72 @@
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
-use std::path::Path;
 
 pub mod api;
+pub mod config;
+pub mod import;
 pub mod memory;
+pub mod provider;
+pub mod rag;
+mod util;
 
 /// The main entry point for the Conduit backend.
 /// 
 /// This struct provides a clean API for interacting with the memory store
 /// and starting the API server.
 pub struct ConduitBackend {
-    memory_store: Arc<memory::MemoryStore>,
+    memory_store: Option<Arc<memory::MemoryStore>>,
+    storage: Arc<dyn memory::StorageBackend>,
+    server_options: api::server::ServerOptions,
+    listen_addr: SocketAddr,
+}
+
+/// Where [`ConduitBackend::new`] keeps memories
+///
+/// Converts from a store path, as `Option<String>`, `String`, `&str` or
+/// `PathBuf`, and from any [`memory::StorageBackend`] in an `Arc`.
+#[derive(Clone)]
+pub enum Storage {
+    /// Markdown files in this directory, or in `~/.conduit/memories` when `None`
+    Files(Option<PathBuf>),
+    /// Another storage backend, such as [`memory::InMemoryBackend`]
+    Backend(Arc<dyn memory::StorageBackend>),
+}
+
+impl From<Option<String>> for Storage {
+    fn from(path: Option<String>) -> Self {
+        Self::Files(path.map(PathBuf::from))
+    }
+}
+
+impl From<String> for Storage {
+    fn from(path: String) -> Self {
+        Self::Files(Some(path.into()))
+    }
+}
+
+impl From<&str> for Storage {
+    fn from(path: &str) -> Self {
+        Self::Files(Some(path.into()))
+    }
+}
+
+impl From<PathBuf> for Storage {
+    fn from(path: PathBuf) -> Self {
+        Self::Files(Some(path))
+    }
+}
+
+impl From<Arc<dyn memory::StorageBackend>> for Storage {
+    fn from(backend: Arc<dyn memory::StorageBackend>) -> Self {
+        Self::Backend(backend)
+    }
+}
+
+impl<T: memory::StorageBackend + 'static> From<Arc<T>> for Storage {
+    fn from(backend: Arc<T>) -> Self {
+        Self::Backend(backend)
+    }
 }
 
 impl ConduitBackend {
@@ -18,8 +74,16 @@ impl ConduitBackend {
     ///
     /// # Arguments
     ///
-    /// * `memory_path` - Optional path to the memory storage directory. If not provided,
-    ///   the default location (~/.conduit/memories) will be used.
+    /// * `storage` - Where memories are kept: a path to the memory storage directory,
+    ///   `None` for the default location (~/.conduit/memories), or a
+    ///   [`memory::StorageBackend`] instance, as with [`ConduitBackend::from_storage`].
+    ///
+    /// The upstream LLM provider is read from the `CONDUIT_UPSTREAM_URL` and
+    /// `CONDUIT_UPSTREAM_API_KEY` environment variables. Without them, chat
+    /// completions run in offline mode. Retrieval settings are read from the
+    /// `CONDUIT_RAG_*` variables, and setting `CONDUIT_RECURSIVE_SCAN=true`
+    /// includes memories stored in subdirectories. The config file is not read;
+    /// use [`ConduitBackend::from_config`] for that.
     ///
     /// # Returns
     ///
@@ -35,40 +99,356 @@ impl ConduitBackend {
     ///
     /// // Or specify a custom path
     /// let backend = ConduitBackend::new(Some("/path/to/memories".to_string())).unwrap();
+    ///
+    /// // Or keep memories in another storage backend
+    /// use std::sync::Arc;
+    /// use conduit_backend::memory::InMemoryBackend;
+    /// let backend = ConduitBackend::new(Arc::new(InMemoryBackend::new())).unwrap();
     /// ```
-    pub fn new(memory_path: Option<String>) -> Result<Self, String> {
-        // Set up the memory directory in the user's home directory if not provided
-        let memory_path = if let Some(path) = memory_path {
-            tracing::info!("Using provided memory_path: {}", path);
-            path
-        } else {
-            let home_dir = dirs::home_dir().ok_or_else(|| "Could not find home directory".to_string())?;
-            let memory_dir = home_dir.join(".conduit").join("memories");
-            let path = memory_dir.to_string_lossy().to_string();
-            tracing::info!("Using default memory path: {}", path);
-            path
+    pub fn new(storage: impl Into<Storage>) -> Result<Self, String> {
+        let path = match storage.into() {
+            Storage::Files(path) => path,
+            Storage::Backend(storage) => return Ok(Self::from_storage(storage)),
         };
-        
-        // Ensure the memory directory exists
-        let path = Path::new(&memory_path);
-        if !path.exists() {
-            tracing::info!("Memory directory does not exist, creating it: {}", memory_path);
-            std::fs::create_dir_all(path).map_err(|e| format!("Failed to create memory directory: {}", e))?;
+        let mut config = config::ConduitConfig::from_env();
+        if let Some(path) = path {
+            tracing::info!("Using provided memory_path: {}", path.display());
+            config.storage.path = Some(path);
+        }
+        Self::from_settings(config)
+    }
+    
+    /// Create a new ConduitBackend instance from the config file and environment
+    ///
+    /// Settings are read from `~/.conduit/config.toml`, or the file named by
+    /// `CONDUIT_CONFIG`, and then overridden by any `CONDUIT_*` environment
+    /// variables. Without a config file this is equivalent to `new(None)`.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the ConduitBackend instance or an error message if
+    /// the config file cannot be read.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use conduit_backend::ConduitBackend;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), String> {
+    ///     let backend = ConduitBackend::from_config()?;
+    ///     let server = backend.start_server(backend.listen_addr()).await?;
+    ///     server.wait().await
+    /// }
+    /// ```
+    pub fn from_config() -> Result<Self, String> {
+        Self::from_settings(config::ConduitConfig::load()?)
+    }
+    
+    /// Create a new ConduitBackend instance from the given settings
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The backend settings
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the ConduitBackend instance or an error message.
+    pub fn from_settings(config: config::ConduitConfig) -> Result<Self, String> {
+        // Demo mode never touches the configured store
+        if config.demo.enabled {
+            return Self::demo_with(config);
         }
         
-        let memory_store = Arc::new(memory::MemoryStore::new(memory_path));
-        Ok(Self { memory_store })
+        // Open the active workspace, the `[storage]` store unless another is chosen
+        config.workspaces.check()?;
+        let workspace = config.workspaces.active_name();
+        let memory_path = match (config.workspaces.stores.get(workspace), &config.storage.path) {
+            (Some(path), _) => {
+                tracing::info!("Using workspace {}: {}", workspace, path.display());
+                path.to_string_lossy().to_string()
+            }
+            (None, Some(path)) => path.to_string_lossy().to_string(),
+            // Set up the memory directory in the user's home directory if not provided
+            (None, None) => {
+                let memory_dir = config::ConduitConfig::default_store_path().ok_or_else(|| "Could not find home directory".to_string())?;
+                let path = memory_dir.to_string_lossy().to_string();
+                tracing::info!("Using default memory path: {}", path);
+                
+                // Ensure the default memory directory exists, unless nothing may be written
+                if !memory_dir.exists() && !config.storage.options.read_only {
+                    tracing::info!("Memory directory does not exist, creating it: {}", path);
+                    memory::init_store_dir(&memory_dir).map_err(|e| format!("Failed to create memory directory: {}", e))?;
+                }
+                path
+            }
+        };
+        
+        // Each workspace may clean up memories differently
+        config.storage.options.preprocess.check()?;
+        let options = memory::StoreOptions {
+            preprocess: config.preprocess_for(workspace).clone(),
+            ..config.storage.options.clone()
+        };
+        let memory_store = Arc::new(memory::MemoryStore::with_options(memory_path, options));
+        Ok(Self {
+            storage: memory_store.clone(),
+            memory_store: Some(memory_store),
+            server_options: config.server_options(),
+            listen_addr: config.listen.addr,
+        })
+    }
+    
+    /// Create a new ConduitBackend instance with a sample store for demos and bug reports
+    ///
+    /// A fresh store is created in the system's temporary directory and filled
+    /// with generated memories that have varied tags, dates, wiki-links and
+    /// references. Personal notes are never read. Other settings come from the
+    /// environment, as with [`ConduitBackend::new`].
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the ConduitBackend instance or an error message.
+    pub fn demo() -> Result<Self, String> {
+        Self::demo_with(config::ConduitConfig::from_env())
+    }
+    
+    // Point the configuration at a new, seeded temporary store
+    fn demo_with(mut config: config::ConduitConfig) -> Result<Self, String> {
+        let path = std::env::temp_dir().join(format!("conduit-demo-{}", ulid::Ulid::new()));
+        tracing::info!("Demo mode: creating sample store in {}", path.display());
+        
+        config.demo.enabled = false;
+        config.storage.path = Some(path);
+        config.storage.options = memory::StoreOptions::default();
+        let backend = Self::from_settings(config.clone())?;
+        
+        let count = memory::seed_demo_memories(backend.storage.as_ref(), config.demo.seed)
+            .map_err(|e| format!("Failed to create demo memories: {}", e))?;
+        tracing::info!("Demo mode: created {} sample memories", count);
+        Ok(backend)
+    }
+    
+    /// Create a new ConduitBackend instance that keeps memories in the given storage backend
+    ///
+    /// Creating, reading, listing, searching and deleting memories go through the
+    /// backend. Features that depend on the markdown file store, such as the API
+    /// server, attachments, collections and the trash, return an error.
+    ///
+    /// # Arguments
+    ///
+    /// * `storage` - The storage backend
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use conduit_backend::ConduitBackend;
+    /// use conduit_backend::memory::InMemoryBackend;
+    ///
+    /// let backend = ConduitBackend::from_storage(Arc::new(InMemoryBackend::new()));
+    /// let id = backend.create_memory("Title".to_string(), "Content".to_string(), vec![]).unwrap();
+    /// assert_eq!(backend.get_memory(&id).unwrap().title, "Title");
+    /// ```
+    pub fn from_storage(storage: Arc<dyn memory::StorageBackend>) -> Self {
+        let config = config::ConduitConfig::from_env();
+        Self {
+            memory_store: None,
+            storage,
+            server_options: config.server_options(),
+            listen_addr: config.listen.addr,
+        }
+    }
+    
+    /// Create a new ConduitBackend instance that keeps memories in memory only
+    ///
+    /// Nothing is written to disk, which suits unit tests and demos. See
+    /// [`ConduitBackend::from_storage`] for the features that are not available.
+    pub fn in_memory() -> Self {
+        Self::from_storage(Arc::new(memory::InMemoryBackend::new()))
+    }
+    
+    /// The address the API server should listen on, from the configuration
+    pub fn listen_addr(&self) -> SocketAddr {
+        self.listen_addr
+    }
+    
+    /// Use the given upstream LLM provider for chat completions
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The provider configuration, or `None` to run in offline mode
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use conduit_backend::ConduitBackend;
+    /// use conduit_backend::provider::ProviderConfig;
+    ///
+    /// let backend = ConduitBackend::new(None).unwrap()
+    ///     .with_provider(Some(ProviderConfig::new("http://localhost:11434/v1", None)));
+    /// ```
+    pub fn with_provider(mut self, config: Option<provider::ProviderConfig>) -> Self {
+        self.server_options.provider = config;
+        self
+    }
+    
+    /// Use the given retrieval settings for injecting memories into chat context
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The retrieval settings
+    pub fn with_rag_config(mut self, config: rag::RagConfig) -> Self {
+        self.server_options.rag = config;
+        self
+    }
+    
+    /// Choose which provider serves embedding requests
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The embedding settings
+    pub fn with_embedding_config(mut self, config: provider::EmbeddingConfig) -> Self {
+        self.server_options.embeddings = config;
+        self
+    }
+    
+    /// Choose how memories created through the API without a title get one
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The title generation settings
+    pub fn with_title_config(mut self, config: api::titles::TitleConfig) -> Self {
+        self.server_options.titles = config;
+        self
+    }
+    
+    /// Choose how memories without a set importance lose it with age
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The importance settings, such as the half-life of untouched memories
+    pub fn with_importance_config(mut self, config: memory::ImportanceConfig) -> Self {
+        self.server_options.importance = config;
+        self
+    }
+    
+    /// Choose how new memories are filled in from their text
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The capture settings, such as whether to set reminders from dates in the text
+    pub fn with_capture_config(mut self, config: api::capture::CaptureConfig) -> Self {
+        self.server_options.capture = config;
+        self
+    }
+    
+    /// Require one of the given API keys on every request
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The accepted API keys; an empty list leaves the API open
+    pub fn with_auth_config(mut self, config: api::auth::AuthConfig) -> Self {
+        self.server_options.auth = config;
+        self
+    }
+    
+    /// Let accounts log in with a password, each keeping its memories in a store of its own
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Where the accounts and their stores are kept, and how long sessions last
+    pub fn with_accounts_config(mut self, config: api::accounts::AccountsConfig) -> Self {
+        self.server_options.accounts = config;
+        self
+    }
+    
+    /// Choose which browser origins may call the API
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The CORS settings
+    pub fn with_cors_config(mut self, config: api::cors::CorsConfig) -> Self {
+        self.server_options.cors = config;
+        self
+    }
+    
+    /// Log chat and embedding requests to a local JSONL file for debugging
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The log settings, or `None` to disable the log
+    pub fn with_interaction_log(mut self, config: Option<api::interaction_log::InteractionLogConfig>) -> Self {
+        self.server_options.interaction_log = config;
+        self
+    }
+    
+    /// Limit how many chat and embedding requests are handled at once
+    ///
+    /// # Arguments
+    ///
+    /// * `max` - The maximum number of concurrent requests, or `None` for no limit
+    /// * `queue_timeout_ms` - How long a request waits for a free slot before being rejected
+    pub fn with_llm_concurrency(mut self, max: Option<usize>, queue_timeout_ms: u64) -> Self {
+        self.server_options.llm_max_concurrency = max;
+        self.server_options.llm_queue_timeout_ms = queue_timeout_ms;
+        self
+    }
+    
+    /// Abort API requests that run longer than the given number of seconds
+    ///
+    /// # Arguments
+    ///
+    /// * `secs` - The timeout in seconds, or `None` to disable it
+    pub fn with_request_timeout(mut self, secs: Option<u64>) -> Self {
+        self.server_options.request_timeout_secs = secs;
+        self
+    }
+    
+    /// Reject every change to the memories, through the API and this backend alike
+    ///
+    /// Reading, listing and searching keep working; changes fail with
+    /// `MemoryError::StoreReadOnly`, reported by the API as `403 Forbidden`.
+    /// The store was already opened when this is called, so to leave it
+    /// completely untouched, including the store marker and trash cleanup done
+    /// on opening, set `read_only` in the `[storage]` settings or
+    /// `CONDUIT_READ_ONLY=true` instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `read_only` - Whether changes are rejected
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use conduit_backend::ConduitBackend;
+    ///
+    /// let backend = ConduitBackend::in_memory().with_read_only(true);
+    /// assert!(backend.create_memory("Title".to_string(), "Content".to_string(), vec![]).is_err());
+    /// ```
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        match &self.memory_store {
+            Some(store) => store.set_read_only(read_only),
+            None if read_only => self.storage = Arc::new(memory::ReadOnlyBackend::new(self.storage)),
+            None => {}
+        }
+        self
     }
     
     /// Start the API server
     ///
+    /// The server runs in the background. The returned handle can be used to shut
+    /// it down; dropping the handle leaves the server running. The address is
+    /// bound when this returns, and `ServerHandle::ready` waits until the store
+    /// is loaded as well.
+    ///
     /// # Arguments
     ///
     /// * `addr` - The socket address to bind the server to
     ///
     /// # Returns
     ///
-    /// A Result indicating success or an error message.
+    /// A Result containing the handle of the running server or an error message.
     ///
     /// # Example
     ///
@@ -80,33 +460,62 @@ impl ConduitBackend {
     /// async fn main() -> Result<(), String> {
     ///     let backend = ConduitBackend::new(None)?;
     ///     let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
-    ///     backend.start_server(addr).await?;
+    ///     let mut server = backend.start_server(addr).await?;
+    ///
+    ///     // The port is bound; wait until the store is loaded too
+    ///     server.ready().await?;
+    ///
+    ///     // ... later, stop the server and wait for it to finish
+    ///     server.shutdown();
+    ///     server.wait().await?;
     ///     Ok(())
     /// }
     /// ```
-    pub async fn start_server(&self, addr: SocketAddr) -> Result<(), String> {
-        // The start_server function returns a shutdown sender, but we don't need to expose that
-        // in our public API. We'll just return success if the server started successfully.
-        match api::server::start_server(self.memory_store.clone(), addr).await {
-            Ok(_) => Ok(()),
-            Err(e) => Err(e),
-        }
+    pub async fn start_server(&self, addr: SocketAddr) -> Result<api::server::ServerHandle, String> {
+        api::server::start_server(self.store()?.clone(), addr, self.server_options.clone()).await
+    }
+
+    /// Build the API routes without binding a listener
+    ///
+    /// Use this to mount Conduit inside an existing axum application. Background
+    /// work such as embedding sync and webhooks starts immediately and stops when
+    /// the router is dropped. Unlike `start_server`, no writer lock is taken and
+    /// no timeout, CORS or docs routes are added.
+    ///
+    /// Must be called from within a tokio runtime.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the router or an error message.
+    pub fn router(&self) -> Result<axum::Router, String> {
+        api::ServerState::start(self.store()?.clone(), &self.server_options).map(api::router)
     }
     
     /// Create a new memory
     ///
     /// # Arguments
     ///
-    /// * `title` - The title of the memory
+    /// * `title` - The title of the memory; when blank, one is derived from the
+    ///   first heading or sentence of the content
     /// * `content` - The content of the memory
     /// * `tags` - A vector of tags for the memory
     ///
+    /// When date parsing is enabled with [`ConduitBackend::with_capture_config`],
+    /// a date mentioned in the title or content becomes the memory's reminder.
+    ///
     /// # Returns
     ///
     /// A Result containing the ID of the created memory or an error message.
     pub fn create_memory(&self, title: String, content: String, tags: Vec<String>) -> Result<String, String> {
-        let memory = memory::Memory::new(title, content, tags);
-        self.memory_store.save(&memory).map_err(|e| e.to_string())?;
+        let title = if title.trim().is_empty() {
+            memory::title_from_content(&content).unwrap_or_else(|| "Untitled".to_string())
+        } else {
+            title
+        };
+        let remind_at = self.server_options.capture.remind_at(None, &title, &content);
+        let mut memory = self.storage.new_memory(title, content, tags).map_err(|e| e.to_string())?;
+        memory.remind_at = remind_at;
+        self.storage.save(&memory).map_err(|e| e.to_string())?;
         Ok(memory.id)
     }
     
@@ -120,7 +529,7 @@ impl ConduitBackend {
     ///
     /// A Result containing the Memory or an error message.
     pub fn get_memory(&self, id: &str) -> Result<memory::Memory, String> {
-        self.memory_store.get(id).map_err(|e| e.to_string())
+        self.storage.get(id).map_err(|e| e.to_string())
     }
     
     /// List all memories
@@ -129,24 +538,91 @@ impl ConduitBackend {
     ///
     /// A Result containing a vector of all memories or an error message.
     pub fn list_memories(&self) -> Result<Vec<memory::Memory>, String> {
-        self.memory_store.list().map_err(|e| e.to_string())
+        self.storage.list().map_err(|e| e.to_string())
+    }
+    
+    /// List memories with paging, sorting and filtering
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Limit, offset, sort order and filters to apply
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the requested page and the total number of matches, or an error message.
+    pub fn list_memories_paged(&self, options: &memory::ListOptions) -> Result<memory::Page<memory::Memory>, String> {
+        match &self.memory_store {
+            Some(store) => store.list_paged(options).map_err(|e| e.to_string()),
+            None => {
+                let memories = self.storage.list().map_err(|e| e.to_string())?
+                    .into_iter()
+                    .filter(|memory| options.collection.as_ref().is_none_or(|collection| memory.collection.as_ref() == Some(collection)))
+                    .collect();
+                Ok(options.apply(memories))
+            }
+        }
     }
     
     /// Search memories
     ///
+    /// The query is written in the search language: words must all match,
+    /// and `title:`, `tag:`, `-tag:`, `created:>2024-01-01`, quoted phrases,
+    /// `OR` and parentheses narrow or widen it. See [`memory::SearchQuery`].
+    ///
     /// # Arguments
     ///
     /// * `query` - The search query
     ///
     /// # Returns
     ///
-    /// A Result containing a vector of matching memories or an error message.
+    /// A Result containing a vector of matching memories, most important first, or an error message.
     pub fn search_memories(&self, query: &str) -> Result<Vec<memory::Memory>, String> {
-        self.memory_store.search(query).map_err(|e| e.to_string())
+        let mut memories = self.storage.search(query).map_err(|e| e.to_string())?;
+        self.server_options.importance.rank(&mut memories);
+        Ok(memories)
+    }
+    
+    /// Search memories and report where the query matched each one
+    ///
+    /// Each result carries snippets around the matches in its title, content
+    /// and tags, for showing context without rendering whole memories.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The search query
+    ///
+    /// # Returns
+    ///
+    /// A Result containing a vector of matching memories with their matches, most important first, or an error message.
+    pub fn search_memories_with_matches(&self, query: &str) -> Result<Vec<memory::SearchResult>, String> {
+        let query = memory::SearchQuery::parse(query).map_err(|e| memory::MemoryError::from(e).to_string())?;
+        let memories = self.search_memories_query(&query)?;
+        match &self.memory_store {
+            Some(store) => Ok(store.query_matcher(&query).map_err(|e| e.to_string())?.results(memories, &query)),
+            None => Ok(memory::QueryMatcher::plain().results(memories, &query)),
+        }
+    }
+    
+    /// Search memories with an already parsed query
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The query, such as one from [`memory::SearchQuery::parse`]
+    ///
+    /// # Returns
+    ///
+    /// A Result containing a vector of matching memories, most important first, or an error message.
+    pub fn search_memories_query(&self, query: &memory::SearchQuery) -> Result<Vec<memory::Memory>, String> {
+        let mut memories = self.storage.search_query(query).map_err(|e| e.to_string())?;
+        self.server_options.importance.rank(&mut memories);
+        Ok(memories)
     }
     
     /// Delete a memory by ID
     ///
+    /// With the markdown file store, the memory is moved to the trash and can be
+    /// restored with [`ConduitBackend::restore_memory`] until it is purged.
+    ///
     /// # Arguments
     ///
     /// * `id` - The ID of the memory to delete
@@ -155,17 +631,805 @@ impl ConduitBackend {
     ///
     /// A Result indicating success or an error message.
     pub fn delete_memory(&self, id: &str) -> Result<(), String> {
-        self.memory_store.delete(id).map_err(|e| e.to_string())
+        self.storage.delete(id).map_err(|e| e.to_string())
     }
     
-    /// Get the memory store
+    /// List memories in the trash
     ///
-    /// This method provides direct access to the memory store for advanced usage.
+    /// # Returns
+    ///
+    /// A Result containing the trashed memories, most recently deleted first, or an error message.
+    pub fn list_trash(&self) -> Result<Vec<memory::TrashedMemory>, String> {
+        self.store()?.list_trash().map_err(|e| e.to_string())
+    }
+    
+    /// Restore a memory from the trash
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the trashed memory
     ///
     /// # Returns
     ///
-    /// A clone of the Arc-wrapped MemoryStore.
-    pub fn memory_store(&self) -> Arc<memory::MemoryStore> {
-        self.memory_store.clone()
+    /// A Result containing the restored Memory or an error message.
+    pub fn restore_memory(&self, id: &str) -> Result<memory::Memory, String> {
+        self.store()?.restore(id).map_err(|e| e.to_string())
+    }
+    
+    /// Permanently delete a memory from the trash
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the trashed memory
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or an error message.
+    pub fn purge_memory(&self, id: &str) -> Result<(), String> {
+        self.store()?.purge(id).map_err(|e| e.to_string())
+    }
+    
+    /// Get the wiki-links from a memory to other memories
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the memory
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the resolved and broken links or an error message.
+    pub fn memory_links(&self, id: &str) -> Result<memory::OutgoingLinks, String> {
+        self.store()?.links(id).map_err(|e| e.to_string())
+    }
+    
+    /// Get the memories that link to a memory
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the memory
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the backlinks or an error message.
+    pub fn memory_backlinks(&self, id: &str) -> Result<memory::Backlinks, String> {
+        self.store()?.backlinks(id).map_err(|e| e.to_string())
+    }
+    
+    /// Get the memories most like a memory, for a "see also" list
+    ///
+    /// Memories are scored by shared tags, wiki-links and stored embeddings,
+    /// weighted as configured under `[related]`.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the memory
+    /// * `limit` - Most memories to return; the configured limit when `None`
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the related memories, best first, or an error message.
+    pub fn related_memories(&self, id: &str, limit: Option<usize>) -> Result<Vec<memory::RelatedMemory>, String> {
+        let config = self.server_options.related.with_overrides(&memory::RelatedQuery { limit, ..Default::default() });
+        self.store()?.related(id, &config, None).map_err(|e| e.to_string())
+    }
+    
+    /// Build the graph of memories connected by wiki-links and shared tags
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Tag and date filters, and an optional center memory and depth
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the graph or an error message.
+    pub fn memory_graph(&self, options: &memory::GraphOptions) -> Result<memory::Graph, String> {
+        self.store()?.graph(options).map_err(|e| e.to_string())
+    }
+    
+    /// List tag aliases, such as `js` standing for `javascript`
+    ///
+    /// # Returns
+    ///
+    /// A Result containing every alias, ordered by alias, or an error message.
+    pub fn tag_aliases(&self) -> Result<Vec<memory::TagAlias>, String> {
+        self.store()?.tag_aliases().map_err(|e| e.to_string())
+    }
+    
+    /// Make a tag an alias of another, so memories saved with it get the other tag instead
+    ///
+    /// # Arguments
+    ///
+    /// * `alias` - The tag to replace
+    /// * `tag` - The tag it stands for
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the alias as stored or an error message.
+    pub fn set_tag_alias(&self, alias: &str, tag: &str) -> Result<memory::TagAlias, String> {
+        self.store()?.set_tag_alias(alias, tag).map_err(|e| e.to_string())
+    }
+    
+    /// Stop replacing a tag alias
+    ///
+    /// # Arguments
+    ///
+    /// * `alias` - The alias to remove
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or an error message.
+    pub fn remove_tag_alias(&self, alias: &str) -> Result<(), String> {
+        self.store()?.remove_tag_alias(alias).map_err(|e| e.to_string())
+    }
+    
+    /// Replace aliased tags on memories saved before the aliases were added
+    ///
+    /// # Arguments
+    ///
+    /// * `dry_run` - If true, only report which memories would change
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the changed memories with their old and new tags, or an error message.
+    pub fn apply_tag_aliases(&self, dry_run: bool) -> Result<memory::TagMigration, String> {
+        self.store()?.apply_tag_aliases(dry_run).map_err(|e| e.to_string())
+    }
+    
+    /// List all collections
+    ///
+    /// # Returns
+    ///
+    /// A Result containing a vector of Collections or an error message.
+    pub fn list_collections(&self) -> Result<Vec<memory::Collection>, String> {
+        self.store()?.list_collections().map_err(|e| e.to_string())
+    }
+    
+    /// Get a collection by ID
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the collection
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the Collection or an error message.
+    pub fn get_collection(&self, id: &str) -> Result<memory::Collection, String> {
+        self.store()?.get_collection(id).map_err(|e| e.to_string())
+    }
+    
+    /// Create a new collection
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the collection, unique ignoring case
+    /// * `description` - An optional description
+    /// * `memory_ids` - IDs of the memories to add to it
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the new Collection or an error message.
+    pub fn create_collection(&self, name: &str, description: Option<&str>, memory_ids: Vec<String>) -> Result<memory::Collection, String> {
+        self.store()?.create_collection(name.to_string(), description.map(|d| d.to_string()), memory_ids, Default::default())
+            .map_err(|e| e.to_string())
+    }
+    
+    /// Rename a collection or change its description
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the collection
+    /// * `update` - The fields to change
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the updated Collection or an error message.
+    pub fn update_collection(&self, id: &str, update: memory::CollectionUpdate) -> Result<memory::Collection, String> {
+        self.store()?.update_collection(id, update).map_err(|e| e.to_string())
+    }
+    
+    /// Delete a collection without touching its memories
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the collection
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or an error message.
+    pub fn delete_collection(&self, id: &str) -> Result<(), String> {
+        self.store()?.delete_collection(id).map_err(|e| e.to_string())
+    }
+    
+    /// Add memories to a collection
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the collection
+    /// * `memory_ids` - IDs of the memories to add
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the updated Collection or an error message.
+    pub fn add_to_collection(&self, id: &str, memory_ids: &[String]) -> Result<memory::Collection, String> {
+        self.store()?.add_to_collection(id, memory_ids).map_err(|e| e.to_string())
+    }
+    
+    /// Remove a memory from a collection
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the collection
+    /// * `memory_id` - The ID of the memory to remove
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the updated Collection or an error message.
+    pub fn remove_from_collection(&self, id: &str, memory_id: &str) -> Result<memory::Collection, String> {
+        self.store()?.remove_from_collection(id, memory_id).map_err(|e| e.to_string())
+    }
+    
+    /// Attach a file to a memory
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the memory
+    /// * `name` - The file name of the attachment
+    /// * `data` - The file contents
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the stored Attachment or an error message.
+    pub fn add_attachment(&self, id: &str, name: &str, data: &[u8]) -> Result<memory::Attachment, String> {
+        let store = self.store()?;
+        let attachment = store.add_attachment(id, name, data).map_err(|e| e.to_string())?;
+        api::attachment_text::index_stored(store, &self.server_options.attachment_text, id, name);
+        Ok(attachment)
+    }
+    
+    /// List the attachments of a memory
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the memory
+    ///
+    /// # Returns
+    ///
+    /// A Result containing a vector of Attachments or an error message.
+    pub fn list_attachments(&self, id: &str) -> Result<Vec<memory::Attachment>, String> {
+        self.store()?.list_attachments(id).map_err(|e| e.to_string())
+    }
+    
+    /// Read the contents of an attachment
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the memory
+    /// * `name` - The file name of the attachment
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the file contents or an error message.
+    pub fn read_attachment(&self, id: &str, name: &str) -> Result<Vec<u8>, String> {
+        self.store()?.read_attachment(id, name)
+            .map(|(_, data)| data)
+            .map_err(|e| e.to_string())
+    }
+    
+    /// Remove an attachment from a memory
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the memory
+    /// * `name` - The file name of the attachment
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or an error message.
+    pub fn remove_attachment(&self, id: &str, name: &str) -> Result<(), String> {
+        self.store()?.remove_attachment(id, name).map_err(|e| e.to_string())
+    }
+    
+    /// List the external references of a memory
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the memory
+    ///
+    /// # Returns
+    ///
+    /// A Result containing a vector of References or an error message.
+    pub fn memory_references(&self, id: &str) -> Result<Vec<memory::Reference>, String> {
+        self.storage.get(id)
+            .map(|memory| memory.references)
+            .map_err(|e| e.to_string())
+    }
+    
+    /// Replace the external references of a memory
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the memory
+    /// * `references` - The new references
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the updated Memory or an error message.
+    pub fn set_memory_references(&self, id: &str, references: Vec<memory::Reference>) -> Result<memory::Memory, String> {
+        self.store()?.set_references(id, references).map_err(|e| e.to_string())
+    }
+    
+    /// Add an external reference to a memory
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the memory
+    /// * `reference` - The reference to add
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the updated Memory or an error message.
+    pub fn add_memory_reference(&self, id: &str, reference: memory::Reference) -> Result<memory::Memory, String> {
+        self.store()?.add_reference(id, reference).map_err(|e| e.to_string())
+    }
+    
+    /// Remove an external reference from a memory
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the memory
+    /// * `index` - The position of the reference in the memory's references
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the updated Memory or an error message.
+    pub fn remove_memory_reference(&self, id: &str, index: usize) -> Result<memory::Memory, String> {
+        self.store()?.remove_reference(id, index).map_err(|e| e.to_string())
+    }
+    
+    /// Set or clear how much a memory matters in search and chat context
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the memory
+    /// * `importance` - From 0 to 1, or `None` to let it decay with age
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the updated Memory or an error message.
+    pub fn set_memory_importance(&self, id: &str, importance: Option<f32>) -> Result<memory::Memory, String> {
+        self.store()?.set_importance(id, importance).map_err(|e| e.to_string())
+    }
+    
+    /// Find and remove orphaned artifacts left behind by interrupted writes or manual edits
+    ///
+    /// # Arguments
+    ///
+    /// * `dry_run` - If true, only report what would be removed
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the GC report or an error message.
+    pub fn collect_garbage(&self, dry_run: bool) -> Result<memory::GcReport, String> {
+        self.store()?.collect_garbage(dry_run).map_err(|e| e.to_string())
+    }
+
+    /// Import the markdown files of a folder, such as another notes app's vault or an unpacked backup
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The folder to import, outside the memory store
+    /// * `job` - How memories matching an existing one are handled, and whether to only report
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the import report, with the outcome for each file, or an error message.
+    pub fn import_dir(&self, path: impl AsRef<std::path::Path>, job: &memory::ImportJob) -> Result<memory::ImportReport, String> {
+        self.store()?.import_dir(path.as_ref(), job).map_err(|e| e.to_string())
+    }
+
+    /// Import a Notion or Evernote export, or a folder of markdown files
+    ///
+    /// Notebooks, and Notion pages with subpages, become collections, and
+    /// embedded images and files become attachments.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A Notion export zip or unpacked folder, an `.enex` file or a folder of them
+    /// * `format` - The kind of export
+    /// * `job` - How memories matching an existing one are handled, and whether to only report
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the import report, with the outcome for each note, or an error message.
+    pub fn import_from(&self, path: impl AsRef<std::path::Path>, format: import::ImportFormat, job: &memory::ImportJob) -> Result<memory::ImportReport, String> {
+        import::import_path(self.store()?, format, path.as_ref(), job).map_err(|e| e.to_string())
+    }
+
+    /// Write a zip backup of every memory to a file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The zip file to write, outside the memory store; an existing file is replaced
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the export report or an error message.
+    pub fn export_to(&self, path: impl AsRef<std::path::Path>) -> Result<memory::ExportReport, String> {
+        self.store()?.export_zip(path.as_ref(), None).map_err(|e| e.to_string())
+    }
+
+    /// Write a zip backup of every memory from a fresh read-only view of the store
+    ///
+    /// Unlike [`ConduitBackend::export_to`], this does not use the store shared
+    /// with the API server: the store folder is opened again read-only, so the
+    /// export works whatever state the server is in, takes no writer lock and
+    /// changes no file of the store.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The zip file to write, outside the memory store; an existing file is replaced
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the export report or an error message.
+    pub fn emergency_export(&self, path: impl AsRef<std::path::Path>) -> Result<memory::ExportReport, String> {
+        let store = self.store()?;
+        store.workspace_lock().check().map_err(|e| e.to_string())?;
+        let options = memory::StoreOptions { read_only: true, ..store.options.clone() };
+        memory::MemoryStore::with_options(&store.base_path, options)
+            .export_zip(path.as_ref(), None)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Run a named export profile from the `[export]` section of the config
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The profile's name
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the export report or an error message.
+    pub fn run_export_profile(&self, name: &str) -> Result<memory::ExportReport, String> {
+        let profiles = api::export::ExportProfiles::new(self.server_options.export.clone())?;
+        let (profile, options) = profiles.get(name).ok_or_else(|| format!("Export profile not found: {}", name))?;
+        api::export::run_profile(self.store()?, &profile, &options).map_err(|e| e.to_string())
+    }
+
+    /// Every memory, oldest first, as JSON for other tools
+    ///
+    /// # Returns
+    ///
+    /// A Result containing a JSON array of memory objects or an error message.
+    pub fn export_json(&self) -> Result<String, String> {
+        let memories = self.store()?.export_memories(None).map_err(|e| e.to_string())?;
+        serde_json::to_string_pretty(&memories).map_err(|e| e.to_string())
+    }
+
+    /// Import memories from a JSON array of memory objects
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - Memory objects, as written by `export_json`; only a title or content is required
+    /// * `job` - How memories matching an existing one are handled, and whether to only report
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the import report, with the action taken for each memory, or an error message.
+    pub fn import_json(&self, json: &str, job: &memory::ImportJob) -> Result<memory::ImportReport, String> {
+        let memories = import::json::parse(json.as_bytes()).map_err(|e| e.to_string())?;
+        import::json::import_json(self.store()?, memories, job).map_err(|e| e.to_string())
+    }
+
+    /// Import memories from another source
+    ///
+    /// # Arguments
+    ///
+    /// * `memories` - Each memory with a description of where it came from, such as a file name
+    /// * `job` - How memories matching an existing one are handled, and whether to only report
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the import report, with the action taken for each memory, or an error message.
+    pub fn import_memories(&self, memories: Vec<(String, memory::Memory)>, job: &memory::ImportJob) -> Result<memory::ImportReport, String> {
+        self.store()?.import_memories(memories, job).map_err(|e| e.to_string())
+    }
+
+    /// Mark a memory as viewed
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the memory that was viewed
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the updated Memory or an error message.
+    pub fn mark_memory_viewed(&self, id: &str) -> Result<memory::Memory, String> {
+        self.storage.mark_viewed(id).map_err(|e| e.to_string())
+    }
+    
+    /// List unread memories
+    ///
+    /// A memory is unread if it has never been viewed or has been updated since it
+    /// was last viewed. The queue is ordered oldest first.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing a vector of unread memories or an error message.
+    pub fn list_unread_memories(&self) -> Result<Vec<memory::Memory>, String> {
+        self.storage.list_unread().map_err(|e| e.to_string())
+    }
+    
+    /// Change the ID of a memory
+    ///
+    /// Wiki-links (`[[old-id]]`) in other memories are rewritten to the new ID.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The current ID of the memory
+    /// * `new_id` - The new ID, which must not be in use
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the renamed memory and the IDs of updated memories, or an error message.
+    pub fn rename_memory(&self, id: &str, new_id: &str) -> Result<memory::RenameResult, String> {
+        self.store()?.rename(id, new_id).map_err(|e| e.to_string())
+    }
+    
+    /// List the commits that changed a memory, newest first
+    ///
+    /// Only available when the store runs in git mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the memory, which may since have been deleted
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the commits or an error message.
+    pub fn memory_history(&self, id: &str) -> Result<Vec<memory::GitCommit>, String> {
+        self.store()?.memory_history(id).map_err(|e| e.to_string())
+    }
+    
+    /// Bring a memory back to how it was at an earlier commit
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the memory
+    /// * `commit` - A commit hash from the memory's history
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the reverted Memory or an error message.
+    pub fn revert_memory(&self, id: &str, commit: &str) -> Result<memory::Memory, String> {
+        self.store()?.revert_memory(id, commit).map_err(|e| e.to_string())
+    }
+    
+    /// Scan the memory store for files that are not memories
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the store diagnostics or an error message.
+    pub fn diagnostics(&self) -> Result<memory::StoreDiagnostics, String> {
+        self.store()?.diagnostics().map_err(|e| e.to_string())
+    }
+    
+    /// Check whether the memory store directory can currently be reached
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the store path and availability, or an error message.
+    pub fn store_status(&self) -> Result<memory::StoreStatus, String> {
+        Ok(self.store()?.status())
+    }
+
+    /// Check whether the store still needs first-run setup
+    ///
+    /// # Returns
+    ///
+    /// A Result that is `true` when the store was never set up and holds no
+    /// memories or collections, or an error message.
+    pub fn needs_setup(&self) -> Result<bool, String> {
+        self.store()?.needs_setup().map_err(|e| e.to_string())
+    }
+
+    /// Run first-run setup on the store: import existing notes, create starter
+    /// collections and record that setup finished
+    ///
+    /// The request's `store_path` is ignored; open a backend on that store to set it up.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The notes to import and the starter templates to create
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the setup report, or an error message if the store was already set up.
+    pub fn setup(&self, request: &api::setup::SetupRequest) -> Result<api::setup::SetupReport, String> {
+        api::setup::run_setup(self.store()?, request).map_err(|e| e.to_string())
+    }
+
+    /// Subscribe to memories being created, updated and deleted
+    ///
+    /// Changes made through the API server are included, as are changes made
+    /// through any other backend opened on the same store in this process.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing a receiver of every change made from now on, or an error message.
+    pub fn memory_events(&self) -> Result<tokio::sync::broadcast::Receiver<memory::MemoryEvent>, String> {
+        Ok(self.store()?.events().subscribe())
+    }
+    
+    /// Watch the memory store for becoming unavailable, e.g. when its drive is
+    /// unmounted, and available again
+    ///
+    /// The store is checked on a background thread, which stops once every
+    /// clone of the returned receiver has been dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - How often to check the store
+    ///
+    /// # Returns
+    ///
+    /// A Result containing a receiver updated whenever the availability changes, or an error message.
+    pub fn watch_store_status(&self, interval: std::time::Duration) -> Result<tokio::sync::watch::Receiver<memory::StoreStatus>, String> {
+        let store = self.store()?.clone();
+        let (tx, rx) = tokio::sync::watch::channel(store.status());
+        
+        std::thread::Builder::new()
+            .name("conduit-store-watch".to_string())
+            .spawn(move || {
+                while !tx.is_closed() {
+                    std::thread::sleep(interval);
+                    let status = store.status();
+                    tx.send_if_modified(|current| {
+                        if *current == status {
+                            return false;
+                        }
+                        if status.available {
+                            tracing::info!("Memory store is available again: {}", status.path.display());
+                        } else {
+                            tracing::warn!("Memory store is unavailable: {}", status.path.display());
+                        }
+                        *current = status;
+                        true
+                    });
+                }
+            })
+            .map_err(|e| format!("Failed to start store watcher: {}", e))?;
+        
+        Ok(rx)
+    }
+    
+    /// Whether a workspace passphrase is set and the workspace is locked
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the lock status or an error message.
+    pub fn lock_status(&self) -> Result<memory::LockStatus, String> {
+        Ok(self.store()?.workspace_lock().status())
+    }
+    
+    /// Fail while the workspace is locked, and otherwise count the call as
+    /// activity that keeps the workspace from locking when idle
+    ///
+    /// Other storage backends have no lock and always pass.
+    ///
+    /// # Returns
+    ///
+    /// A Result that is an error message while the workspace is locked.
+    pub fn check_unlocked(&self) -> Result<(), String> {
+        match &self.memory_store {
+            Some(store) => store.workspace_lock().check().map_err(|e| e.to_string()),
+            None => Ok(()),
+        }
+    }
+    
+    /// Lock the workspace until its passphrase is given
+    ///
+    /// While locked, the API server answers `423 Locked`.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the lock status, or an error message when no passphrase is set.
+    pub fn lock_workspace(&self) -> Result<memory::LockStatus, String> {
+        self.store()?.workspace_lock().lock().map_err(|e| e.to_string())
+    }
+    
+    /// Unlock the workspace
+    ///
+    /// # Arguments
+    ///
+    /// * `passphrase` - The workspace passphrase
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the lock status, or an error message when the passphrase is wrong.
+    pub fn unlock_workspace(&self, passphrase: &str) -> Result<memory::LockStatus, String> {
+        self.store()?.workspace_lock().unlock(passphrase).map_err(|e| e.to_string())
+    }
+    
+    /// Set, change or remove the workspace passphrase
+    ///
+    /// The passphrase is kept as a salted hash in `.workspace-lock.json` inside
+    /// the store. Once one is set, the workspace locks after the idle timeout of
+    /// `[storage.lock]` and whenever the backend is opened.
+    ///
+    /// # Arguments
+    ///
+    /// * `current` - The passphrase already set, if any
+    /// * `new` - The new passphrase of at least 8 characters, or `None` to remove the lock
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the lock status or an error message.
+    pub fn set_lock_passphrase(&self, current: Option<&str>, new: Option<&str>) -> Result<memory::LockStatus, String> {
+        let store = self.store()?;
+        if store.is_read_only() {
+            return Err(memory::MemoryError::StoreReadOnly(store.base_path.display().to_string()).to_string());
+        }
+        store.workspace_lock().set_passphrase(current, new).map_err(|e| e.to_string())
+    }
+    
+    /// Watch the workspace lock, for showing a lock screen when it engages
+    /// after the idle timeout
+    ///
+    /// The lock is checked on a background thread, which stops once every
+    /// clone of the returned receiver has been dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - How often to check the lock
+    ///
+    /// # Returns
+    ///
+    /// A Result containing a receiver updated whenever the lock status changes, or an error message.
+    pub fn watch_lock_status(&self, interval: std::time::Duration) -> Result<tokio::sync::watch::Receiver<memory::LockStatus>, String> {
+        let store = self.store()?.clone();
+        let (tx, rx) = tokio::sync::watch::channel(store.workspace_lock().status());
+        
+        std::thread::Builder::new()
+            .name("conduit-lock-watch".to_string())
+            .spawn(move || {
+                while !tx.is_closed() {
+                    std::thread::sleep(interval);
+                    let status = store.workspace_lock().status();
+                    tx.send_if_modified(|current| {
+                        if *current == status {
+                            return false;
+                        }
+                        *current = status;
+                        true
+                    });
+                }
+            })
+            .map_err(|e| format!("Failed to start lock watcher: {}", e))?;
+        
+        Ok(rx)
+    }
+    
+    /// Get the memory store
+    ///
+    /// This method provides direct access to the memory store for advanced usage.
+    ///
+    /// # Returns
+    ///
+    /// A clone of the Arc-wrapped MemoryStore, or `None` when the backend was
+    /// created with another storage backend.
+    pub fn memory_store(&self) -> Option<Arc<memory::MemoryStore>> {
+        self.memory_store.clone()
+    }
+    
+    /// Get the storage backend that holds the memories
+    pub fn storage(&self) -> Arc<dyn memory::StorageBackend> {
+        self.storage.clone()
+    }
+    
+    // The markdown file store, required by features beyond basic storage
+    fn store(&self) -> Result<&Arc<memory::MemoryStore>, String> {
+        self.memory_store.as_ref()
+            .ok_or_else(|| "Not supported by this storage backend; it requires the markdown file store".to_string())
     }
 }
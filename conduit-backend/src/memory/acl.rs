@@ -0,0 +1,215 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{frontmatter_field, Memory, MemoryError, MemoryStore, QuarantinedConflict};
+
+pub use conduit_types::{Grant, GranteeKind, Permission};
+
+// `user:bob=read`, as stored in the frontmatter
+fn grant_frontmatter(grant: &Grant) -> String {
+    format!("{}:{}={}", grant.kind.as_str(), grant.name, grant.permission.as_str())
+}
+
+fn parse_grant(entry: &str) -> Option<Grant> {
+    let (grantee, permission) = entry.trim().rsplit_once('=')?;
+    let (kind, name) = grantee.split_once(':')?;
+    Some(Grant {
+        kind: match kind {
+            "user" => GranteeKind::User,
+            "group" => GranteeKind::Group,
+            _ => return None,
+        },
+        name: name.to_string(),
+        permission: match permission {
+            "read" => Permission::Read,
+            "write" => Permission::Write,
+            _ => return None,
+        },
+    })
+}
+
+fn validate_grant(grant: &Grant) -> Result<(), MemoryError> {
+    if !is_valid_name(&grant.name) {
+        return Err(MemoryError::InvalidGrant(format!("invalid {} name: {:?}", grant.kind.as_str(), grant.name)));
+    }
+    Ok(())
+}
+
+fn grant_applies_to(grant: &Grant, principal: &Principal) -> bool {
+    match grant.kind {
+        GranteeKind::User => grant.name == principal.user,
+        GranteeKind::Group => principal.groups.contains(&grant.name),
+    }
+}
+
+// Names end up in the frontmatter list, so they cannot contain its separators
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | '@'))
+}
+
+/// Who owns a memory and who else it is shared with
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct MemoryAcl {
+    /// User who created the memory; memories without an owner are shared with everyone
+    pub owner: Option<String>,
+    #[serde(default)]
+    pub shared: Vec<Grant>,
+}
+
+/// A user of a multi-user server, as authenticated for a request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Principal {
+    pub user: String,
+    #[serde(default)]
+    pub groups: Vec<String>,
+}
+
+pub(super) fn acl_frontmatter(owner: Option<&str>, shared: &[Grant]) -> String {
+    let mut md = String::new();
+    if let Some(owner) = owner {
+        md.push_str(&format!("owner: {}\n", owner));
+    }
+    if !shared.is_empty() {
+        let grants: Vec<String> = shared.iter().map(grant_frontmatter).collect();
+        md.push_str(&format!("shared: [{}]\n", grants.join(", ")));
+    }
+    md
+}
+
+pub(super) fn parse_acl(frontmatter: &str) -> MemoryAcl {
+    MemoryAcl {
+        owner: frontmatter_field(frontmatter, "owner").map(str::to_string),
+        shared: frontmatter_field(frontmatter, "shared")
+            .and_then(|s| s.strip_prefix('[')?.strip_suffix(']'))
+            .map(|s| s.split(',').filter_map(parse_grant).collect())
+            .unwrap_or_default(),
+    }
+}
+
+/// Access checks on memories shared between the users of a multi-user server
+pub trait MemoryAccess {
+    /// Access a user has to this memory, if any
+    ///
+    /// The owner can read and change the memory, as can everyone when it has no
+    /// owner. Other users get the highest permission granted to them or to one
+    /// of their groups.
+    fn permission_for(&self, principal: &Principal) -> Option<Permission>;
+
+    /// Whether a memory can be read; without a principal, every memory can
+    fn is_visible_to(&self, principal: Option<&Principal>) -> bool;
+
+    /// The owner and grants of the memory
+    fn acl(&self) -> MemoryAcl;
+}
+
+impl MemoryAccess for Memory {
+    fn permission_for(&self, principal: &Principal) -> Option<Permission> {
+        match &self.owner {
+            None => Some(Permission::Write),
+            Some(owner) if *owner == principal.user => Some(Permission::Write),
+            Some(_) => self.shared.iter()
+                .filter(|grant| grant_applies_to(grant, principal))
+                .map(|grant| grant.permission)
+                .max(),
+        }
+    }
+
+    fn is_visible_to(&self, principal: Option<&Principal>) -> bool {
+        principal.is_none_or(|principal| self.permission_for(principal).is_some())
+    }
+
+    fn acl(&self) -> MemoryAcl {
+        MemoryAcl {
+            owner: self.owner.clone(),
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+/// Keep the memories a user can read; without a principal, all of them are kept
+pub fn filter_visible(memories: Vec<Memory>, principal: Option<&Principal>) -> Vec<Memory> {
+    memories.into_iter()
+        .filter(|memory| memory.is_visible_to(principal))
+        .collect()
+}
+
+// Check the access a principal has to a memory, which is hidden if it cannot be read
+fn authorize_memory(memory: &Memory, principal: Option<&Principal>, permission: Permission) -> Result<(), MemoryError> {
+    let Some(principal) = principal else {
+        return Ok(());
+    };
+    match memory.permission_for(principal) {
+        None => Err(MemoryError::NotFound(memory.id.clone())),
+        Some(granted) if granted < permission => Err(MemoryError::AccessDenied(memory.id.clone())),
+        Some(_) => Ok(()),
+    }
+}
+
+impl MemoryStore {
+    /// Load a memory after checking that a user has `permission` on it
+    ///
+    /// Memories the user cannot read are reported as not found, so their
+    /// existence is not revealed. Without a principal every memory is allowed.
+    pub fn authorize(&self, id: &str, principal: Option<&Principal>, permission: Permission) -> Result<Memory, MemoryError> {
+        let memory = self.get(id)?;
+        authorize_memory(&memory, principal, permission)?;
+        Ok(memory)
+    }
+
+    /// Check that a user has `permission` on a memory in the trash
+    pub fn authorize_trashed(&self, id: &str, principal: Option<&Principal>, permission: Permission) -> Result<(), MemoryError> {
+        if principal.is_none() {
+            return Ok(());
+        }
+        let trashed = self.list_trash()?
+            .into_iter()
+            .find(|trashed| trashed.memory.id == id)
+            .ok_or_else(|| MemoryError::NotFound(id.to_string()))?;
+        authorize_memory(&trashed.memory, principal, permission)
+    }
+
+    /// Check that a user has `permission` on a memory with a quarantined conflict
+    ///
+    /// Either version of the memory granting it is enough.
+    pub fn authorize_conflict(&self, conflict: &QuarantinedConflict, principal: Option<&Principal>, permission: Permission) -> Result<(), MemoryError> {
+        let mut result = Err(MemoryError::NotFound(conflict.memory_id.clone()));
+        for version in conflict.mine.iter().chain(&conflict.theirs) {
+            match authorize_memory(version, principal, permission) {
+                Ok(()) => return Ok(()),
+                Err(MemoryError::AccessDenied(id)) => result = Err(MemoryError::AccessDenied(id)),
+                Err(_) => {}
+            }
+        }
+        result
+    }
+
+    /// Replace who a memory is shared with
+    ///
+    /// Only the owner, or a caller without a principal, can change the grants.
+    /// A user sharing a memory without an owner becomes its owner, since
+    /// grants have no effect on memories everyone can already change.
+    pub fn share(&self, id: &str, principal: Option<&Principal>, shared: Vec<Grant>) -> Result<MemoryAcl, MemoryError> {
+        shared.iter().try_for_each(validate_grant)?;
+        let mut memory = self.authorize(id, principal, Permission::Write)?;
+
+        if let Some(principal) = principal {
+            match &memory.owner {
+                Some(owner) if *owner != principal.user => return Err(MemoryError::AccessDenied(id.to_string())),
+                Some(_) => {}
+                None => memory.owner = Some(principal.user.clone()),
+            }
+        }
+
+        let mut grants: Vec<Grant> = Vec::new();
+        for grant in shared {
+            match grants.iter_mut().find(|g| g.kind == grant.kind && g.name == grant.name) {
+                Some(existing) => existing.permission = existing.permission.max(grant.permission),
+                None => grants.push(grant),
+            }
+        }
+        memory.shared = grants;
+
+        self.save_as(&memory, Some(&format!("Share memory: {} ({})", memory.title, memory.id)))?;
+        Ok(memory.acl())
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::PathBuf;
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use zip::ZipArchive;
+
+use crate::util::write_atomic;
+use super::{is_valid_id, IndexState, Memory, MemoryError, MemoryStore};
+
+/// Hidden folder inside the store holding the text of attachments as `<memory_id>/<name>.txt`
+const TEXT_DIR: &str = ".attachment-text";
+
+/// Text of each memory's attachments, folded with the store's collation for search
+pub(super) type TextIndex = HashMap<String, Vec<(String, String)>>;
+
+// Parts of office documents that hold their text, by file extension
+fn office_parts(extension: &str) -> Option<fn(&str) -> bool> {
+    match extension {
+        "docx" => Some(|part| part == "word/document.xml" || part.starts_with("word/footnotes") || part.starts_with("word/header") || part.starts_with("word/footer")),
+        "pptx" => Some(|part| part.starts_with("ppt/slides/slide") && part.ends_with(".xml")),
+        "xlsx" => Some(|part| part == "xl/sharedStrings.xml"),
+        "odt" | "ods" | "odp" => Some(|part| part == "content.xml"),
+        _ => None,
+    }
+}
+
+// Text of an XML document, with a line break after each paragraph, cell or row
+fn xml_text(xml: &str) -> String {
+    let breaks = Regex::new(r"</(w:p|a:p|si|text:p|text:h|table:table-cell)>|<(w:br|w:tab|text:line-break|text:tab)\s*/>").unwrap();
+    let tags = Regex::new(r"<[^>]*>").unwrap();
+    let text = breaks.replace_all(xml, "\n");
+    let text = tags.replace_all(&text, "");
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn office_text(data: &[u8], is_part: fn(&str) -> bool) -> Option<String> {
+    let mut archive = ZipArchive::new(Cursor::new(data)).ok()?;
+    let mut parts: Vec<String> = archive.file_names().filter(|name| is_part(name)).map(str::to_string).collect();
+    // Slides are numbered without padding, so `slide10` sorts after `slide9`
+    parts.sort_by_key(|name| (name.len(), name.clone()));
+
+    let mut text = String::new();
+    for part in parts {
+        let mut xml = String::new();
+        archive.by_name(&part).ok()?.read_to_string(&mut xml).ok()?;
+        text.push_str(&xml_text(&xml));
+        text.push('\n');
+    }
+    Some(text)
+}
+
+/// Extract the searchable text of an attachment
+///
+/// PDFs, Word, PowerPoint and Excel files, OpenDocument files and plain text
+/// are supported. Returns `None` for other types and for files that cannot
+/// be read.
+pub(super) fn extract_text(name: &str, data: &[u8]) -> Option<String> {
+    let extension = name.rsplit_once('.').map(|(_, ext)| ext.to_lowercase()).unwrap_or_default();
+    if extension == "pdf" {
+        // The PDF parser panics on some malformed files
+        return std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem(data).ok())
+            .ok()
+            .flatten();
+    }
+    if let Some(is_part) = office_parts(&extension) {
+        return office_text(data, is_part);
+    }
+    let content_type = mime_guess::from_path(name).first_or_octet_stream();
+    if content_type.type_() == mime_guess::mime::TEXT {
+        return String::from_utf8(data.to_vec()).ok();
+    }
+    None
+}
+
+impl MemoryStore {
+    fn text_dir(&self, id: &str) -> PathBuf {
+        self.base_path.join(TEXT_DIR).join(id)
+    }
+
+    fn text_path(&self, id: &str, name: &str) -> PathBuf {
+        self.text_dir(id).join(format!("{}.txt", name))
+    }
+
+    // Whether the stored text of an attachment is missing or older than the file
+    fn is_text_stale(&self, id: &str, name: &str) -> bool {
+        let modified = |path: PathBuf| fs::metadata(path).and_then(|m| m.modified()).ok();
+        match (modified(self.attachments_dir(id).join(name)), modified(self.text_path(id, name))) {
+            (Some(attachment), Some(text)) => text < attachment,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+
+    /// Attachments, as memory ID and file name, whose text has not been extracted since they last changed
+    pub fn stale_attachment_text(&self) -> Result<Vec<(String, String)>, MemoryError> {
+        Ok(self.list()?
+            .into_iter()
+            .filter(|memory| !memory.read_only)
+            .flat_map(|memory| {
+                let id = memory.id;
+                memory.attachments.into_iter().map(move |name| (id.clone(), name))
+            })
+            .filter(|(id, name)| self.is_text_stale(id, name))
+            .collect())
+    }
+
+    // How far extracted attachment text is behind `memories`, whose own text is searched as saved
+    pub(super) fn text_index_state(&self, memories: &[Memory]) -> Result<IndexState, MemoryError> {
+        let mut state = IndexState {
+            documents: memories.len(),
+            updated_at: memories.iter().map(|memory| memory.updated_at).max(),
+            ..IndexState::default()
+        };
+        for memory in memories.iter().filter(|memory| !memory.read_only) {
+            for name in &memory.attachments {
+                if self.is_text_stale(&memory.id, name) {
+                    state.pending += 1;
+                    continue;
+                }
+                if let Ok(modified) = fs::metadata(self.text_path(&memory.id, name)).and_then(|m| m.modified()) {
+                    state.documents += 1;
+                    state.updated_at = state.updated_at.max(Some(DateTime::<Utc>::from(modified)));
+                }
+            }
+        }
+        Ok(state)
+    }
+
+    /// Extract and store the text of an attachment for search
+    ///
+    /// Files larger than `max_size` bytes, of unsupported types or that cannot
+    /// be read are stored with no text, so they are not tried again until they
+    /// change. Returns whether any text was found.
+    pub fn index_attachment_text(&self, id: &str, name: &str, max_size: u64) -> Result<bool, MemoryError> {
+        if !is_valid_id(id) || !is_valid_id(name) {
+            return Err(MemoryError::InvalidAttachmentName(format!("{}/{}", id, name)));
+        }
+        self.check_available()?;
+        self.check_writable()?;
+
+        let path = self.attachments_dir(id).join(name);
+        let text = match fs::metadata(&path)?.len() {
+            size if size > max_size => None,
+            _ => extract_text(name, &fs::read(&path)?),
+        };
+        let text = text.map(|text| text.split_whitespace().collect::<Vec<_>>().join(" ")).unwrap_or_default();
+
+        // Searches never see partial text
+        fs::create_dir_all(self.text_dir(id))?;
+        write_atomic(&self.text_path(id, name), &text)?;
+        self.invalidate_attachment_text();
+
+        Ok(!text.is_empty())
+    }
+
+    pub(super) fn invalidate_attachment_text(&self) {
+        *self.attachment_text.write().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    // Stored attachment text of every memory, loaded once and kept until text changes
+    pub(super) fn attachment_text_index(&self) -> Arc<TextIndex> {
+        if let Some(index) = self.attachment_text.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+            return index.clone();
+        }
+
+        let mut index = TextIndex::new();
+        for dir in fs::read_dir(self.base_path.join(TEXT_DIR)).into_iter().flatten().flatten() {
+            let id = dir.file_name().to_string_lossy().to_string();
+            for file in fs::read_dir(dir.path()).into_iter().flatten().flatten() {
+                let file_name = file.file_name().to_string_lossy().to_string();
+                let Some(name) = file_name.strip_suffix(".txt") else {
+                    continue;
+                };
+                match fs::read_to_string(file.path()) {
+                    Ok(text) if !text.is_empty() => index.entry(id.clone()).or_default().push((name.to_string(), self.options.collation.fold(&text))),
+                    Ok(_) => {}
+                    Err(e) => tracing::debug!("Error reading attachment text {:?}: {:?}", file.path(), e),
+                }
+            }
+        }
+
+        let index = Arc::new(index);
+        *self.attachment_text.write().unwrap_or_else(|e| e.into_inner()) = Some(index.clone());
+        index
+    }
+
+    /// Name of the first of a memory's attachments whose text contains a query already folded with the store's collation
+    pub fn attachment_matching(&self, memory: &Memory, query: &str) -> Option<String> {
+        self.attachment_text_index()
+            .get(&memory.id)?
+            .iter()
+            .find(|(name, text)| memory.attachments.contains(name) && text.contains(query))
+            .map(|(name, _)| name.clone())
+    }
+
+    // Extracted text, as written, of each of a memory's attachments whose text
+    // contains a query already folded with the store's collation
+    pub(super) fn attachment_text_containing(&self, memory: &Memory, query: &str) -> Vec<(String, String)> {
+        let index = self.attachment_text_index();
+        let Some(texts) = index.get(&memory.id) else {
+            return Vec::new();
+        };
+        texts.iter()
+            .filter(|(name, text)| memory.attachments.contains(name) && text.contains(query))
+            .filter_map(|(name, _)| Some((name.clone(), fs::read_to_string(self.text_path(&memory.id, name)).ok()?)))
+            .collect()
+    }
+
+    // Text follows a memory's attachments to its new ID
+    pub(super) fn rename_attachment_text(&self, old_id: &str, new_id: &str) -> Result<(), MemoryError> {
+        let old_dir = self.text_dir(old_id);
+        if old_dir.exists() {
+            fs::rename(old_dir, self.text_dir(new_id))?;
+            self.invalidate_attachment_text();
+        }
+        Ok(())
+    }
+
+    // Remove the stored text of attachments that are gone
+    pub(super) fn remove_attachment_text(&self, id: &str, name: Option<&str>) -> Result<(), MemoryError> {
+        if !is_valid_id(id) {
+            return Ok(());
+        }
+        let path = match name {
+            Some(name) => self.text_path(id, name),
+            None => self.text_dir(id),
+        };
+        if path.is_dir() {
+            fs::remove_dir_all(path)?;
+        } else if path.exists() {
+            fs::remove_file(path)?;
+        }
+        self.invalidate_attachment_text();
+        Ok(())
+    }
+
+    // Stored text of attachments no memory, live or trashed, refers to any more
+    pub(super) fn orphaned_attachment_text(&self, references: &HashMap<String, Vec<String>>) -> Vec<PathBuf> {
+        let mut orphans = Vec::new();
+        for dir in fs::read_dir(self.base_path.join(TEXT_DIR)).into_iter().flatten().flatten() {
+            let id = dir.file_name().to_string_lossy().to_string();
+            let Some(names) = references.get(&id) else {
+                orphans.push(dir.path());
+                continue;
+            };
+            for file in fs::read_dir(dir.path()).into_iter().flatten().flatten() {
+                let file_name = file.file_name().to_string_lossy().to_string();
+                let referenced = file_name.strip_suffix(".txt")
+                    .is_some_and(|name| names.iter().any(|existing| existing == name));
+                if !referenced {
+                    orphans.push(file.path());
+                }
+            }
+        }
+        orphans
+    }
+}
@@ -0,0 +1,301 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use ulid::Ulid;
+use utoipa::ToSchema;
+
+use conduit_types::openai::ChatMessage;
+use super::{is_valid_id, MemoryError, MemoryStore};
+
+/// Folder inside the store that holds `<memory_id>/<filename>` attachments
+pub(super) const ATTACHMENTS_DIR: &str = "attachments";
+
+/// A file attached to a memory
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Attachment {
+    pub name: String,
+    pub size: u64,
+    pub content_type: String,
+    pub modified_at: DateTime<Utc>,
+}
+
+/// Rules uploads must meet before they are stored
+///
+/// With no rules, any file is accepted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AttachmentPolicy {
+    /// Content types accepted, such as `application/pdf`, or `image/*` for a
+    /// whole family; empty accepts every type. The type is guessed from the
+    /// file name, except that executables are recognized by their contents.
+    pub allowed_types: Vec<String>,
+    /// Largest file accepted, in bytes
+    pub max_size: Option<u64>,
+    /// Command run on each upload with the file's path appended, such as
+    /// `["clamdscan", "--no-summary"]`. The file is accepted if it exits with
+    /// status 0 and rejected otherwise, including when it cannot be run.
+    pub scanner: Vec<String>,
+    /// Seconds the scanner may take before the upload is rejected
+    pub scanner_timeout_secs: u64,
+}
+
+impl Default for AttachmentPolicy {
+    fn default() -> Self {
+        Self {
+            allowed_types: Vec::new(),
+            max_size: None,
+            scanner: Vec::new(),
+            scanner_timeout_secs: 60,
+        }
+    }
+}
+
+impl AttachmentPolicy {
+    /// Override rules with `CONDUIT_ATTACHMENT_TYPES` (comma-separated),
+    /// `CONDUIT_ATTACHMENT_MAX_SIZE` and `CONDUIT_ATTACHMENT_SCANNER` (split on
+    /// whitespace), if set
+    pub fn apply_env(&mut self) {
+        if let Ok(types) = std::env::var("CONDUIT_ATTACHMENT_TYPES") {
+            self.allowed_types = types.split(',').map(|t| t.trim().to_lowercase()).filter(|t| !t.is_empty()).collect();
+        }
+        if let Some(size) = std::env::var("CONDUIT_ATTACHMENT_MAX_SIZE").ok().and_then(|size| size.parse().ok()) {
+            self.max_size = Some(size);
+        }
+        if let Ok(scanner) = std::env::var("CONDUIT_ATTACHMENT_SCANNER") {
+            self.scanner = scanner.split_whitespace().map(str::to_string).collect();
+        }
+    }
+
+    fn allows_type(&self, content_type: &str) -> bool {
+        self.allowed_types.is_empty() || self.allowed_types.iter().any(|allowed| {
+            match allowed.strip_suffix("/*") {
+                Some(family) => content_type.split('/').next() == Some(family),
+                None => allowed.eq_ignore_ascii_case(content_type),
+            }
+        })
+    }
+
+    // Check the name and contents of an upload before it is written
+    fn check(&self, name: &str, data: &[u8]) -> Result<(), MemoryError> {
+        if let Some(max_size) = self.max_size.filter(|max| data.len() as u64 > *max) {
+            return Err(MemoryError::AttachmentTooLarge(format!("{} is {} bytes, more than {}", name, data.len(), max_size)));
+        }
+        let content_type = content_type(name, data);
+        if !self.allows_type(&content_type) {
+            return Err(MemoryError::AttachmentRejected(format!("{} has type {}, which is not allowed", name, content_type)));
+        }
+        Ok(())
+    }
+
+    // Run the scanner on a written upload
+    fn scan(&self, name: &str, path: &Path) -> Result<(), MemoryError> {
+        let Some((program, args)) = self.scanner.split_first() else {
+            return Ok(());
+        };
+        let rejected = |reason: String| MemoryError::AttachmentRejected(format!("{} {}", name, reason));
+
+        let mut child = Command::new(program)
+            .args(args)
+            .arg(path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| rejected(format!("could not be scanned: {}", e)))?;
+        let deadline = Instant::now() + Duration::from_secs(self.scanner_timeout_secs);
+        let status = loop {
+            match child.try_wait()? {
+                Some(status) => break status,
+                None if Instant::now() >= deadline => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(rejected("could not be scanned in time".to_string()));
+                }
+                None => std::thread::sleep(Duration::from_millis(50)),
+            }
+        };
+
+        if status.success() {
+            Ok(())
+        } else {
+            tracing::debug!("Scanner rejected attachment {}: {}", name, status);
+            Err(rejected(format!("was rejected by the scanner ({})", status)))
+        }
+    }
+}
+
+// Content type of a file from its name, unless its contents show it is a program
+fn content_type(name: &str, data: &[u8]) -> String {
+    let executable = [
+        (&b"MZ"[..], "application/x-msdownload"),
+        (&b"\x7fELF"[..], "application/x-executable"),
+        (&b"\xcf\xfa\xed\xfe"[..], "application/x-mach-binary"),
+        (&b"\xce\xfa\xed\xfe"[..], "application/x-mach-binary"),
+        (&b"\xca\xfe\xba\xbe"[..], "application/x-mach-binary"),
+        (&b"#!"[..], "text/x-shellscript"),
+    ];
+    match executable.iter().find(|(magic, _)| data.starts_with(magic)) {
+        Some((_, content_type)) => content_type.to_string(),
+        None => mime_guess::from_path(name).first_or_octet_stream().to_string(),
+    }
+}
+
+// Attachment names are plain file names, with the same rules as memory IDs. They are
+// also listed as `attachments: [a, b]` in the frontmatter, so they can't hold the list's
+// brackets or commas, or have surrounding spaces that reading the list trims away
+fn check_name(name: &str) -> Result<(), MemoryError> {
+    if is_valid_id(name) && name.trim() == name && !name.contains([',', '[', ']']) {
+        Ok(())
+    } else {
+        Err(MemoryError::InvalidAttachmentName(name.to_string()))
+    }
+}
+
+impl MemoryStore {
+    pub(super) fn attachments_root(&self) -> PathBuf {
+        self.base_path.join(ATTACHMENTS_DIR)
+    }
+
+    pub(super) fn attachments_dir(&self, id: &str) -> PathBuf {
+        self.attachments_root().join(id)
+    }
+
+    fn attachment_info(&self, id: &str, name: &str) -> Result<Attachment, MemoryError> {
+        let metadata = fs::metadata(self.attachments_dir(id).join(name))?;
+
+        Ok(Attachment {
+            name: name.to_string(),
+            size: metadata.len(),
+            content_type: mime_guess::from_path(name).first_or_octet_stream().to_string(),
+            modified_at: metadata.modified().map(DateTime::<Utc>::from)?,
+        })
+    }
+
+    /// Store a file alongside a memory and reference it from the memory's frontmatter
+    ///
+    /// An existing attachment with the same name is replaced. Files that do
+    /// not meet the store's [`AttachmentPolicy`] are refused.
+    pub fn add_attachment(&self, id: &str, name: &str, data: &[u8]) -> Result<Attachment, MemoryError> {
+        check_name(name)?;
+        self.options.attachments.check(name, data)?;
+        let _change = self.begin_change()?;
+        let mut memory = self.get(id)?;
+        if memory.read_only {
+            return Err(MemoryError::ReadOnly(id.to_string()));
+        }
+
+        let dir = self.attachments_dir(id);
+        fs::create_dir_all(&dir)?;
+
+        // Replace the file instead of rewriting it, so snapshots linking it keep their contents
+        let staging = dir.join(format!(".{}.rename-tmp", name));
+        fs::write(&staging, data)?;
+        if let Err(err) = self.options.attachments.scan(name, &staging) {
+            let _ = fs::remove_file(&staging);
+            return Err(err);
+        }
+        fs::rename(&staging, dir.join(name))?;
+
+        if !memory.attachments.iter().any(|existing| existing == name) {
+            memory.attachments.push(name.to_string());
+        }
+        memory.updated_at = Utc::now();
+        self.save_as(&memory, Some(&format!("Attach {} to memory: {} ({})", name, memory.title, id)))?;
+
+        self.attachment_info(id, name)
+    }
+
+    /// List the attachments of a memory, in frontmatter order
+    pub fn list_attachments(&self, id: &str) -> Result<Vec<Attachment>, MemoryError> {
+        let memory = self.get(id)?;
+
+        Ok(memory.attachments.iter()
+            .filter_map(|name| self.attachment_info(id, name).ok())
+            .collect())
+    }
+
+    /// Read an attachment's metadata and contents
+    pub fn read_attachment(&self, id: &str, name: &str) -> Result<(Attachment, Vec<u8>), MemoryError> {
+        check_name(name)?;
+        let memory = self.get(id)?;
+        let path = self.attachments_dir(id).join(name);
+        if !memory.attachments.iter().any(|existing| existing == name) || !path.is_file() {
+            return Err(MemoryError::NotFound(format!("{}/{}", id, name)));
+        }
+
+        Ok((self.attachment_info(id, name)?, fs::read(path)?))
+    }
+
+    /// Delete an attachment and drop its frontmatter reference
+    pub fn remove_attachment(&self, id: &str, name: &str) -> Result<(), MemoryError> {
+        check_name(name)?;
+        let _change = self.begin_change()?;
+        let mut memory = self.get(id)?;
+        if memory.read_only {
+            return Err(MemoryError::ReadOnly(id.to_string()));
+        }
+        if !memory.attachments.iter().any(|existing| existing == name) {
+            return Err(MemoryError::NotFound(format!("{}/{}", id, name)));
+        }
+
+        let path = self.attachments_dir(id).join(name);
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        self.remove_attachment_text(id, Some(name))?;
+
+        memory.attachments.retain(|existing| existing != name);
+        memory.updated_at = Utc::now();
+        self.save_as(&memory, Some(&format!("Remove attachment {} from memory: {} ({})", name, memory.title, id)))
+    }
+
+    /// Store the inlined images of chat messages as attachments of a memory
+    ///
+    /// Each `data:` URL is replaced by the attachment's URL under
+    /// `/api/memories`, so saved messages stay small. Linked images are kept
+    /// as links. Returns how many images were stored.
+    pub fn attach_message_images(&self, id: &str, messages: &mut [ChatMessage]) -> Result<usize, MemoryError> {
+        let mut stored = 0;
+        for image in messages.iter_mut().flat_map(|message| message.content.images_mut()) {
+            let Some((media_type, data)) = image.data() else {
+                continue;
+            };
+            let bytes = base64::engine::general_purpose::STANDARD.decode(data.trim())
+                .map_err(|err| MemoryError::AttachmentRejected(format!("image is not valid base64: {}", err)))?;
+            let name = format!("image-{}.{}", Ulid::new().to_string().to_lowercase(), image_extension(media_type));
+
+            self.add_attachment(id, &name, &bytes)?;
+            image.url = format!("/api/memories/{}/attachments/{}", id, name);
+            stored += 1;
+        }
+        Ok(stored)
+    }
+
+    // Remove every attachment of a memory that is gone for good
+    pub(super) fn remove_attachments(&self, id: &str) -> Result<(), MemoryError> {
+        let dir = self.attachments_dir(id);
+        if is_valid_id(id) && dir.exists() {
+            fs::remove_dir_all(dir)?;
+        }
+        Ok(())
+    }
+}
+
+// File extension for an image's media type, so its content type can be guessed again
+fn image_extension(media_type: &str) -> &'static str {
+    match media_type {
+        "image/jpeg" => "jpg",
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        _ => mime_guess::get_mime_extensions_str(media_type)
+            .and_then(|extensions| extensions.first())
+            .copied()
+            .unwrap_or("bin"),
+    }
+}
@@ -0,0 +1,112 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use serde::{Deserialize, Serialize};
+
+use super::{MemoryError, MemoryStore};
+
+/// Marker file written to the store root, so an unmounted or removed store
+/// directory is told apart from an empty one
+const STORE_MARKER: &str = ".conduit-store";
+
+/// Contents of the marker file
+const MARKER_TEXT: &str = "This directory is a Conduit memory store.\n";
+
+/// Set up `path` as a store directory, creating it if needed
+///
+/// For folders picked to hold a store. Opening a store only marks a directory
+/// it creates itself, or one that already holds memories, so an existing empty
+/// folder, like the mount point of an unmounted drive, stays unavailable until
+/// it is set up this way.
+pub fn init_store_dir(path: &Path) -> Result<(), MemoryError> {
+    fs::create_dir_all(path)?;
+    let marker = path.join(STORE_MARKER);
+    if !marker.exists() {
+        fs::write(marker, MARKER_TEXT)?;
+    }
+    Ok(())
+}
+
+/// Whether the store directory can currently be reached
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoreStatus {
+    pub path: PathBuf,
+    pub available: bool,
+    /// Whether changes are rejected
+    pub read_only: bool,
+}
+
+impl MemoryStore {
+    fn marker_path(&self) -> PathBuf {
+        self.base_path.join(STORE_MARKER)
+    }
+
+    // Mark a directory the store was just created in, or one holding memories
+    // from before stores had a marker
+    pub(super) fn write_marker(&self, created: bool) -> Result<(), MemoryError> {
+        let marker = self.marker_path();
+        if marker.exists() {
+            return Ok(());
+        }
+        let has_memories = self.base_path.join(".git").is_dir()
+            || self.memory_files().is_ok_and(|files| !files.is_empty());
+        if created || has_memories {
+            fs::write(marker, MARKER_TEXT)?;
+        }
+        Ok(())
+    }
+
+    /// Whether the store directory and its marker are present
+    ///
+    /// Turns false when the directory is on a drive that was unmounted or was
+    /// removed, and true again once it is back. A read-only store cannot write
+    /// its marker, so there the directory itself is enough.
+    pub fn is_available(&self) -> bool {
+        self.marker_path().is_file() || (self.is_read_only() && self.base_path.is_dir())
+    }
+
+    /// Fail with [`MemoryError::StoreUnavailable`] unless the store can be reached
+    ///
+    /// Called before reading or writing so that a missing store is reported
+    /// instead of being recreated as an empty directory.
+    pub fn check_available(&self) -> Result<(), MemoryError> {
+        if self.is_available() {
+            Ok(())
+        } else {
+            Err(MemoryError::StoreUnavailable(self.base_path.display().to_string()))
+        }
+    }
+
+    /// Whether the store rejects changes
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.load(Ordering::Relaxed)
+    }
+
+    /// Start or stop rejecting changes
+    ///
+    /// Unlike [`StoreOptions::read_only`](super::StoreOptions::read_only), which
+    /// also keeps the store from being set up when it is opened, this only
+    /// affects changes made from now on.
+    pub fn set_read_only(&self, read_only: bool) {
+        self.read_only.store(read_only, Ordering::Relaxed);
+    }
+
+    /// Fail with [`MemoryError::StoreReadOnly`] if the store rejects changes
+    ///
+    /// Called by every operation that writes to the store directory.
+    pub fn check_writable(&self) -> Result<(), MemoryError> {
+        if self.is_read_only() {
+            Err(MemoryError::StoreReadOnly(self.base_path.display().to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn status(&self) -> StoreStatus {
+        StoreStatus {
+            path: self.base_path.clone(),
+            available: self.is_available(),
+            read_only: self.is_read_only(),
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use chrono::Utc;
+
+use super::query::QueryMatcher;
+use super::{is_valid_id, Memory, MemoryError, MemoryStore, SearchQuery};
+
+/// Persistence for memories
+///
+/// [`MemoryStore`], which keeps memories as markdown files, is the default
+/// implementation. [`InMemoryBackend`] keeps them in memory for tests and demos.
+pub trait StorageBackend: Send + Sync {
+    /// Create a new, unsaved memory with an ID not used by the backend
+    fn new_memory(&self, title: String, content: String, tags: Vec<String>) -> Result<Memory, MemoryError>;
+
+    /// Create or overwrite a memory
+    fn save(&self, memory: &Memory) -> Result<(), MemoryError>;
+
+    fn get(&self, id: &str) -> Result<Memory, MemoryError>;
+
+    fn list(&self) -> Result<Vec<Memory>, MemoryError>;
+
+    fn delete(&self, id: &str) -> Result<(), MemoryError>;
+
+    /// Find memories matching a query in the search language, see [`SearchQuery`]
+    fn search(&self, query: &str) -> Result<Vec<Memory>, MemoryError> {
+        self.search_query(&SearchQuery::parse(query)?)
+    }
+
+    /// Find memories matching a parsed query
+    fn search_query(&self, query: &SearchQuery) -> Result<Vec<Memory>, MemoryError> {
+        let matcher = QueryMatcher::plain();
+        Ok(self.list()?
+            .into_iter()
+            .filter(|memory| matcher.matches(memory, query))
+            .collect())
+    }
+
+    /// Record that a memory has been viewed, without touching its `updated_at`
+    fn mark_viewed(&self, id: &str) -> Result<Memory, MemoryError> {
+        let mut memory = self.get(id)?;
+        if memory.read_only {
+            return Err(MemoryError::ReadOnly(id.to_string()));
+        }
+        memory.last_viewed_at = Some(Utc::now());
+        self.save(&memory)?;
+
+        Ok(memory)
+    }
+
+    /// List memories that were never viewed or changed since they were last viewed,
+    /// oldest first
+    fn list_unread(&self) -> Result<Vec<Memory>, MemoryError> {
+        let mut unread: Vec<Memory> = self.list()?
+            .into_iter()
+            .filter(|memory| memory.is_unread())
+            .collect();
+        unread.sort_by_key(|memory| memory.created_at);
+
+        Ok(unread)
+    }
+}
+
+impl StorageBackend for MemoryStore {
+    fn new_memory(&self, title: String, content: String, tags: Vec<String>) -> Result<Memory, MemoryError> {
+        MemoryStore::new_memory(self, title, content, tags)
+    }
+
+    fn save(&self, memory: &Memory) -> Result<(), MemoryError> {
+        MemoryStore::save(self, memory)
+    }
+
+    fn get(&self, id: &str) -> Result<Memory, MemoryError> {
+        MemoryStore::get(self, id)
+    }
+
+    fn list(&self) -> Result<Vec<Memory>, MemoryError> {
+        MemoryStore::list(self)
+    }
+
+    /// Moves the memory to the trash
+    fn delete(&self, id: &str) -> Result<(), MemoryError> {
+        MemoryStore::delete(self, id)
+    }
+
+    fn search(&self, query: &str) -> Result<Vec<Memory>, MemoryError> {
+        MemoryStore::search(self, query)
+    }
+
+    fn search_query(&self, query: &SearchQuery) -> Result<Vec<Memory>, MemoryError> {
+        MemoryStore::search_query(self, query)
+    }
+}
+
+/// Keeps memories in memory, for unit tests and demos that should not touch the filesystem
+///
+/// Deleted memories are dropped rather than moved to a trash.
+#[derive(Debug, Default)]
+pub struct InMemoryBackend {
+    memories: RwLock<BTreeMap<String, Memory>>,
+}
+
+impl InMemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start with the given memories
+    pub fn with_memories(memories: impl IntoIterator<Item = Memory>) -> Self {
+        Self {
+            memories: RwLock::new(memories.into_iter().map(|memory| (memory.id.clone(), memory)).collect()),
+        }
+    }
+}
+
+impl StorageBackend for InMemoryBackend {
+    fn new_memory(&self, title: String, content: String, tags: Vec<String>) -> Result<Memory, MemoryError> {
+        Ok(Memory::new(title, content, tags))
+    }
+
+    fn save(&self, memory: &Memory) -> Result<(), MemoryError> {
+        if !is_valid_id(&memory.id) {
+            return Err(MemoryError::InvalidId(memory.id.clone()));
+        }
+        if memory.read_only {
+            return Err(MemoryError::ReadOnly(memory.id.clone()));
+        }
+
+        self.memories.write().unwrap().insert(memory.id.clone(), memory.clone());
+        Ok(())
+    }
+
+    fn get(&self, id: &str) -> Result<Memory, MemoryError> {
+        self.memories.read().unwrap()
+            .get(id)
+            .cloned()
+            .ok_or_else(|| MemoryError::NotFound(id.to_string()))
+    }
+
+    fn list(&self) -> Result<Vec<Memory>, MemoryError> {
+        Ok(self.memories.read().unwrap().values().cloned().collect())
+    }
+
+    fn delete(&self, id: &str) -> Result<(), MemoryError> {
+        self.memories.write().unwrap()
+            .remove(id)
+            .map(|_| ())
+            .ok_or_else(|| MemoryError::NotFound(id.to_string()))
+    }
+}
+
+/// Wraps another storage backend and rejects every change with [`MemoryError::StoreReadOnly`]
+///
+/// Used for read-only mode with backends other than [`MemoryStore`], which
+/// has its own switch.
+pub struct ReadOnlyBackend {
+    inner: Arc<dyn StorageBackend>,
+}
+
+impl ReadOnlyBackend {
+    pub fn new(inner: Arc<dyn StorageBackend>) -> Self {
+        Self { inner }
+    }
+
+    fn rejected(&self) -> MemoryError {
+        MemoryError::StoreReadOnly("storage backend".to_string())
+    }
+}
+
+impl StorageBackend for ReadOnlyBackend {
+    fn new_memory(&self, title: String, content: String, tags: Vec<String>) -> Result<Memory, MemoryError> {
+        self.inner.new_memory(title, content, tags)
+    }
+
+    fn save(&self, _memory: &Memory) -> Result<(), MemoryError> {
+        Err(self.rejected())
+    }
+
+    fn get(&self, id: &str) -> Result<Memory, MemoryError> {
+        self.inner.get(id)
+    }
+
+    fn list(&self) -> Result<Vec<Memory>, MemoryError> {
+        self.inner.list()
+    }
+
+    fn delete(&self, _id: &str) -> Result<(), MemoryError> {
+        Err(self.rejected())
+    }
+
+    fn search(&self, query: &str) -> Result<Vec<Memory>, MemoryError> {
+        self.inner.search(query)
+    }
+
+    fn search_query(&self, query: &SearchQuery) -> Result<Vec<Memory>, MemoryError> {
+        self.inner.search_query(query)
+    }
+}
@@ -0,0 +1,162 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::import::merge;
+use super::{Memory, MemoryError, MemoryStore};
+
+/// A change applied to several memories at once
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BatchOperation {
+    /// Replace any of the `from` tags with `to`, on the listed memories or on
+    /// every memory when none are listed
+    Retag {
+        from: Vec<String>,
+        to: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        memory_ids: Vec<String>,
+    },
+    /// Fold the tags, references and content of `memory_ids` into `into`, then
+    /// move them to the trash
+    Merge {
+        into: String,
+        memory_ids: Vec<String>,
+    },
+    /// Move memories to the trash
+    Delete {
+        memory_ids: Vec<String>,
+    },
+}
+
+impl BatchOperation {
+    /// Memories the operation names; empty for a retag of every memory
+    pub fn memory_ids(&self) -> Vec<&str> {
+        match self {
+            Self::Retag { memory_ids, .. } | Self::Delete { memory_ids } => memory_ids.iter().map(String::as_str).collect(),
+            Self::Merge { into, memory_ids } => std::iter::once(into.as_str()).chain(memory_ids.iter().map(String::as_str)).collect(),
+        }
+    }
+}
+
+/// What one operation of a batch did, or would do
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BatchResult {
+    /// Position of the operation in the request
+    pub index: usize,
+    /// Memories that were changed
+    pub updated: Vec<String>,
+    /// Memories moved to the trash
+    pub trashed: Vec<String>,
+    /// Why the operation failed; failed operations change nothing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Outcome of a batch of operations
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BatchReport {
+    /// When set, nothing was changed and the results show what would be
+    pub dry_run: bool,
+    pub results: Vec<BatchResult>,
+}
+
+// Replace the `from` tags with `to`, keeping the order and dropping duplicates
+fn retag(tags: &[String], from: &[String], to: &str) -> Vec<String> {
+    let mut retagged: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = if from.iter().any(|from| from.eq_ignore_ascii_case(tag)) { to } else { tag.as_str() };
+        if !retagged.iter().any(|existing| existing.eq_ignore_ascii_case(tag)) {
+            retagged.push(tag.to_string());
+        }
+    }
+    retagged
+}
+
+impl MemoryStore {
+    /// Apply operations in order, each on the memories as left by the previous ones
+    ///
+    /// Every operation is checked before it changes anything, so one naming a
+    /// missing or read-only memory fails on its own without stopping the rest.
+    pub fn apply_batch(&self, operations: &[BatchOperation], dry_run: bool) -> Result<BatchReport, MemoryError> {
+        let _change = if dry_run { None } else { Some(self.begin_change()?) };
+
+        let results = operations.iter().enumerate()
+            .map(|(index, operation)| {
+                let mut result = BatchResult { index, updated: Vec::new(), trashed: Vec::new(), error: None };
+                if let Err(err) = self.apply_operation(operation, dry_run, &mut result) {
+                    tracing::debug!("Batch operation {} failed: {}", index, err);
+                    result.error = Some(err.to_string());
+                }
+                result
+            })
+            .collect();
+
+        Ok(BatchReport { dry_run, results })
+    }
+
+    fn apply_operation(&self, operation: &BatchOperation, dry_run: bool, result: &mut BatchResult) -> Result<(), MemoryError> {
+        // Load everything the operation names first, so a bad ID changes nothing
+        let mut named: Vec<Memory> = Vec::new();
+        for id in operation.memory_ids() {
+            let memory = self.get(id)?;
+            if memory.read_only {
+                return Err(MemoryError::ReadOnly(id.to_string()));
+            }
+            named.push(memory);
+        }
+
+        match operation {
+            BatchOperation::Retag { from, to, memory_ids } => {
+                let to = to.trim();
+                if to.is_empty() {
+                    return Err(MemoryError::InvalidFormat("Retag needs a tag to change to".to_string()));
+                }
+                let memories = if memory_ids.is_empty() {
+                    self.list()?.into_iter().filter(|memory| !memory.read_only).collect()
+                } else {
+                    named
+                };
+                for memory in memories {
+                    let tags = retag(&memory.tags, from, to);
+                    if tags == memory.tags {
+                        continue;
+                    }
+                    if !dry_run {
+                        let mut retagged = memory.clone();
+                        retagged.tags = tags;
+                        self.save_as(&retagged, Some(&format!("Retag memory: {} ({})", memory.title, memory.id)))?;
+                    }
+                    result.updated.push(memory.id);
+                }
+            }
+            BatchOperation::Merge { into, memory_ids } => {
+                if memory_ids.iter().any(|id| id == into) {
+                    return Err(MemoryError::InvalidFormat(format!("Cannot merge memory {} into itself", into)));
+                }
+                let mut merged = named.remove(0);
+                for memory in &named {
+                    merged = merge(&merged, memory);
+                }
+                merged.updated_at = Utc::now();
+                if !dry_run {
+                    self.save_as(&merged, Some(&format!("Merge {} memories into: {} ({})", named.len(), merged.title, merged.id)))?;
+                    for memory in &named {
+                        self.delete(&memory.id)?;
+                    }
+                }
+                result.updated.push(merged.id);
+                result.trashed.extend(named.into_iter().map(|memory| memory.id));
+            }
+            BatchOperation::Delete { .. } => {
+                for memory in named {
+                    if !dry_run {
+                        self.delete(&memory.id)?;
+                    }
+                    result.trashed.push(memory.id);
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+use std::cmp::Ordering;
+use icu_collator::{Collator, CollatorOptions};
+use icu_locid::Locale;
+use serde::{Deserialize, Serialize};
+use unicode_normalization::char::{decompose_canonical, is_combining_mark};
+
+/// How titles are sorted and search text is matched, set under `[storage.collation]`
+///
+/// ```toml
+/// [storage.collation]
+/// locale = "sv"
+/// ignore_accents = false
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Collation {
+    /// Locale whose alphabetical order titles follow, such as `de`, `sv` or
+    /// `zh-u-co-pinyin`; the Unicode default order when unset
+    pub locale: Option<String>,
+    /// Search matches letters whatever their accents, so `cafe` finds `Café`
+    pub ignore_accents: bool,
+}
+
+impl Default for Collation {
+    fn default() -> Self {
+        Self {
+            locale: None,
+            ignore_accents: true,
+        }
+    }
+}
+
+impl Collation {
+    /// Override the settings with `CONDUIT_LOCALE` and `CONDUIT_SEARCH_IGNORE_ACCENTS`, if set
+    pub fn apply_env(&mut self) {
+        if let Some(locale) = std::env::var("CONDUIT_LOCALE").ok().filter(|locale| !locale.trim().is_empty()) {
+            self.locale = Some(locale);
+        }
+        if let Some(ignore_accents) = super::env_flag("CONDUIT_SEARCH_IGNORE_ACCENTS") {
+            self.ignore_accents = ignore_accents;
+        }
+    }
+
+    /// A collator for the locale, falling back to the Unicode default order
+    /// when the locale is not valid
+    ///
+    /// Letters differing only in case or accents sort next to each other, with
+    /// lowercase and unaccented ones first.
+    pub fn collator(&self) -> Collator {
+        let locale = match self.locale.as_deref().map(str::parse::<Locale>) {
+            Some(Ok(locale)) => locale,
+            Some(Err(e)) => {
+                tracing::debug!("Invalid collation locale {:?}, using the default order: {}", self.locale, e);
+                Locale::UND
+            }
+            None => Locale::UND,
+        };
+        Collator::try_new(&locale.into(), CollatorOptions::new())
+            .or_else(|_| Collator::try_new(&Locale::UND.into(), CollatorOptions::new()))
+            .expect("compiled collation data covers the default order")
+    }
+
+    /// Compare two strings in the locale's order
+    ///
+    /// Builds a collator for each call; use [`Collation::collator`] to sort.
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        self.collator().compare(a, b)
+    }
+
+    /// Text as search compares it: lowercased and, when accents are ignored, without them
+    pub fn fold(&self, text: &str) -> String {
+        text.chars().filter_map(|c| self.fold_char(c)).collect()
+    }
+
+    /// One character as search compares it, `None` for an accent that is ignored
+    pub(crate) fn fold_char(&self, c: char) -> Option<char> {
+        let mut base = c;
+        if self.ignore_accents {
+            if is_combining_mark(c) {
+                return None;
+            }
+            let mut first = None;
+            decompose_canonical(c, |part| {
+                first.get_or_insert(part);
+            });
+            base = match first.unwrap_or(c) {
+                // Letters with a stroke have no decomposition but read as their base letter
+                'ø' => 'o',
+                'Ø' => 'O',
+                'ł' => 'l',
+                'Ł' => 'L',
+                'đ' => 'd',
+                'Đ' => 'D',
+                'ħ' => 'h',
+                'Ħ' => 'H',
+                other => other,
+            };
+        }
+        Some(base.to_lowercase().next().unwrap_or(base))
+    }
+}
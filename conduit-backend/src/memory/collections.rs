@@ -0,0 +1,319 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use chrono::{Duration, Utc};
+
+use crate::util::write_atomic;
+use super::{Memory, MemoryError, MemoryStore};
+
+pub use conduit_types::{Collection, CollectionDefaults, CollectionUpdate};
+
+/// File inside the store holding every collection
+const COLLECTIONS_FILE: &str = ".collections.json";
+
+/// Longest retention a collection can have, about a hundred years
+pub const MAX_RETENTION_DAYS: u32 = 36500;
+
+impl MemoryStore {
+    fn collections_path(&self) -> PathBuf {
+        self.base_path.join(COLLECTIONS_FILE)
+    }
+
+    pub(super) fn read_collections(&self) -> Result<Vec<Collection>, MemoryError> {
+        let path = self.collections_path();
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        serde_json::from_str(&fs::read_to_string(path)?)
+            .map_err(|e| MemoryError::InvalidFormat(format!("Invalid collections file: {}", e)))
+    }
+
+    fn write_collections(&self, collections: &[Collection]) -> Result<(), MemoryError> {
+        self.check_available()?;
+        let _change = self.begin_change()?;
+        let json = serde_json::to_string_pretty(collections)
+            .map_err(|e| MemoryError::InvalidFormat(e.to_string()))?;
+        write_atomic(&self.collections_path(), json)?;
+        Ok(())
+    }
+
+    // Read, change and write back the collections while holding the lock
+    fn update_collections<T>(
+        &self,
+        update: impl FnOnce(&mut Vec<Collection>) -> Result<T, MemoryError>,
+    ) -> Result<T, MemoryError> {
+        let result = self.update_collections_uncommitted(update)?;
+        self.git_commit("Update collections");
+        Ok(result)
+    }
+
+    // Same as `update_collections`, for changes that are part of a larger commit
+    fn update_collections_uncommitted<T>(
+        &self,
+        update: impl FnOnce(&mut Vec<Collection>) -> Result<T, MemoryError>,
+    ) -> Result<T, MemoryError> {
+        let _guard = self.collections_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut collections = self.read_collections()?;
+        let result = update(&mut collections)?;
+        self.write_collections(&collections)?;
+        Ok(result)
+    }
+
+    fn find_collection<'a>(collections: &'a mut [Collection], id: &str) -> Result<&'a mut Collection, MemoryError> {
+        collections.iter_mut()
+            .find(|collection| collection.id == id)
+            .ok_or_else(|| MemoryError::NotFound(format!("collection {}", id)))
+    }
+
+    fn check_collection_name(collections: &[Collection], name: &str, except: Option<&str>) -> Result<(), MemoryError> {
+        if name.trim().is_empty() {
+            return Err(MemoryError::InvalidCollection("name is empty".to_string()));
+        }
+        if collections.iter().any(|c| Some(c.id.as_str()) != except && c.name.eq_ignore_ascii_case(name)) {
+            return Err(MemoryError::AlreadyExists(format!("collection {}", name)));
+        }
+        Ok(())
+    }
+
+    fn check_collection_defaults(defaults: &CollectionDefaults) -> Result<(), MemoryError> {
+        if defaults.retention_days == Some(0) {
+            return Err(MemoryError::InvalidCollection("retention_days must be at least 1".to_string()));
+        }
+        if defaults.retention_days.is_some_and(|days| days > MAX_RETENTION_DAYS) {
+            return Err(MemoryError::InvalidCollection(format!("retention_days must be at most {}", MAX_RETENTION_DAYS)));
+        }
+        if defaults.tags.iter().any(|tag| tag.trim().is_empty()) {
+            return Err(MemoryError::InvalidCollection("default tags cannot be empty".to_string()));
+        }
+        Ok(())
+    }
+
+    /// List all collections, ordered by name
+    pub fn list_collections(&self) -> Result<Vec<Collection>, MemoryError> {
+        let mut collections = self.read_collections()?;
+        let collator = self.options.collation.collator();
+        collections.sort_by(|a, b| collator.compare(&a.name, &b.name));
+        Ok(collections)
+    }
+
+    pub fn get_collection(&self, id: &str) -> Result<Collection, MemoryError> {
+        self.read_collections()?
+            .into_iter()
+            .find(|collection| collection.id == id)
+            .ok_or_else(|| MemoryError::NotFound(format!("collection {}", id)))
+    }
+
+    /// Create a collection, optionally with initial members
+    pub fn create_collection(
+        &self,
+        name: String,
+        description: Option<String>,
+        memory_ids: Vec<String>,
+        defaults: CollectionDefaults,
+    ) -> Result<Collection, MemoryError> {
+        Self::check_collection_defaults(&defaults)?;
+        for id in &memory_ids {
+            self.get(id)?;
+        }
+        // Always a ULID: the store's ID scheme only avoids memory IDs, so timestamp IDs
+        // would repeat for collections created within the same minute
+        let id = ulid::Ulid::new().to_string();
+
+        self.update_collections(|collections| {
+            Self::check_collection_name(collections, &name, None)?;
+
+            let now = Utc::now();
+            let mut members: Vec<String> = Vec::new();
+            for memory_id in memory_ids {
+                if !members.contains(&memory_id) {
+                    members.push(memory_id);
+                }
+            }
+
+            let collection = Collection {
+                id,
+                name,
+                description,
+                memory_ids: members,
+                defaults,
+                created_at: now,
+                updated_at: now,
+            };
+            collections.push(collection.clone());
+            Ok(collection)
+        })
+    }
+
+    /// Rename a collection or change its description or defaults
+    pub fn update_collection(&self, id: &str, update: CollectionUpdate) -> Result<Collection, MemoryError> {
+        if let Some(defaults) = &update.defaults {
+            Self::check_collection_defaults(defaults)?;
+        }
+        self.update_collections(|collections| {
+            if let Some(name) = &update.name {
+                Self::check_collection_name(collections, name, Some(id))?;
+            }
+
+            let collection = Self::find_collection(collections, id)?;
+            if let Some(name) = update.name {
+                collection.name = name;
+            }
+            if let Some(description) = update.description {
+                collection.description = Some(description).filter(|d| !d.is_empty());
+            }
+            if let Some(defaults) = update.defaults {
+                collection.defaults = defaults;
+            }
+            collection.updated_at = Utc::now();
+            Ok(collection.clone())
+        })
+    }
+
+    /// Delete a collection; its memories are not affected
+    pub fn delete_collection(&self, id: &str) -> Result<(), MemoryError> {
+        self.update_collections(|collections| {
+            let before = collections.len();
+            collections.retain(|collection| collection.id != id);
+            if collections.len() == before {
+                return Err(MemoryError::NotFound(format!("collection {}", id)));
+            }
+            Ok(())
+        })
+    }
+
+    /// Add memories to a collection, ignoring ones that are already members
+    pub fn add_to_collection(&self, id: &str, memory_ids: &[String]) -> Result<Collection, MemoryError> {
+        for memory_id in memory_ids {
+            self.get(memory_id)?;
+        }
+
+        self.update_collections(|collections| {
+            let collection = Self::find_collection(collections, id)?;
+            for memory_id in memory_ids {
+                if !collection.contains(memory_id) {
+                    collection.memory_ids.push(memory_id.clone());
+                }
+            }
+            collection.updated_at = Utc::now();
+            Ok(collection.clone())
+        })
+    }
+
+    /// Remove a memory from a collection
+    pub fn remove_from_collection(&self, id: &str, memory_id: &str) -> Result<Collection, MemoryError> {
+        self.update_collections(|collections| {
+            let collection = Self::find_collection(collections, id)?;
+            if !collection.contains(memory_id) {
+                return Err(MemoryError::NotFound(memory_id.to_string()));
+            }
+            collection.memory_ids.retain(|member| member != memory_id);
+            collection.updated_at = Utc::now();
+            Ok(collection.clone())
+        })
+    }
+
+    /// Move members of collections with a retention period to the trash once
+    /// they have not changed for that long
+    ///
+    /// Members of a pinned collection and read-only memories are kept.
+    /// Returns the IDs of the memories moved to the trash.
+    pub fn expire_collection_members(&self) -> Result<Vec<String>, MemoryError> {
+        let collections = self.read_collections()?;
+        if collections.iter().all(|collection| collection.defaults.retention_days.is_none()) {
+            return Ok(Vec::new());
+        }
+
+        let pinned: HashSet<&str> = collections.iter()
+            .filter(|collection| collection.defaults.pinned)
+            .flat_map(|collection| collection.memory_ids.iter().map(String::as_str))
+            .collect();
+        let now = Utc::now();
+        let mut expired: Vec<String> = Vec::new();
+
+        for collection in &collections {
+            let Some(days) = collection.defaults.retention_days else {
+                continue;
+            };
+            // Collections saved before retention was bounded may hold any value
+            let Some(cutoff) = now.checked_sub_signed(Duration::days(days as i64)) else {
+                continue;
+            };
+            for id in &collection.memory_ids {
+                if pinned.contains(id.as_str()) || expired.contains(id) {
+                    continue;
+                }
+                // Members may have been deleted since they were added
+                let Ok(memory) = self.get(id) else {
+                    continue;
+                };
+                if memory.read_only || memory.updated_at > cutoff {
+                    continue;
+                }
+                match self.delete(id) {
+                    Ok(()) => expired.push(id.clone()),
+                    // Expired again before the earlier copy left the trash; try next time
+                    Err(MemoryError::AlreadyExists(_)) => {
+                        tracing::warn!("Not expiring memory {}: a memory with its ID is still in the trash", id);
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+
+        if !expired.is_empty() {
+            tracing::debug!("Moved {} expired collection members to the trash", expired.len());
+        }
+        Ok(expired)
+    }
+
+    // IDs of the members of collections that are kept on this device
+    pub(super) fn local_only_members(&self) -> Result<Vec<String>, MemoryError> {
+        let mut members: Vec<String> = Vec::new();
+        for collection in self.read_collections()?.into_iter().filter(|collection| !collection.defaults.sync) {
+            for id in collection.memory_ids {
+                if !members.contains(&id) {
+                    members.push(id);
+                }
+            }
+        }
+        Ok(members)
+    }
+
+    /// Keep the memories that are members of the collection with this ID, or
+    /// that are stored in the folder with this name
+    pub fn filter_by_collection(&self, memories: Vec<Memory>, collection: &str) -> Result<Vec<Memory>, MemoryError> {
+        let members = self.read_collections()?
+            .into_iter()
+            .find(|c| c.id == collection)
+            .map(|c| c.memory_ids)
+            .unwrap_or_default();
+
+        Ok(memories.into_iter()
+            .filter(|memory| members.contains(&memory.id) || memory.collection.as_deref() == Some(collection))
+            .collect())
+    }
+
+    // Keep memberships pointing at the right memory after a rename or purge
+    pub(super) fn replace_in_collections(&self, old_id: &str, new_id: Option<&str>) -> Result<(), MemoryError> {
+        if !self.collections_path().exists() {
+            return Ok(());
+        }
+
+        self.update_collections_uncommitted(|collections| {
+            for collection in collections.iter_mut().filter(|c| c.contains(old_id)) {
+                match new_id {
+                    Some(new_id) => {
+                        let mut seen = HashSet::new();
+                        for member in collection.memory_ids.iter_mut().filter(|member| *member == old_id) {
+                            *member = new_id.to_string();
+                        }
+                        collection.memory_ids.retain(|member| seen.insert(member.clone()));
+                    }
+                    None => collection.memory_ids.retain(|member| member != old_id),
+                }
+            }
+            Ok(())
+        })
+    }
+}
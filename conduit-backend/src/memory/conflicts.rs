@@ -0,0 +1,207 @@
+use std::fs;
+use std::path::PathBuf;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::import::merge;
+use super::{is_valid_id, Memory, MemoryError, MemoryMarkdown, MemoryStore};
+
+/// Directory of the store holding both versions of each quarantined conflict
+pub(super) const CONFLICTS_DIR: &str = ".conflicts";
+
+/// Sidecar describing a quarantined conflict
+const CONFLICT_FILE: &str = "conflict.json";
+const MINE_FILE: &str = "mine.md";
+const THEIRS_FILE: &str = "theirs.md";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ConflictInfo {
+    path: String,
+    source: String,
+    detected_at: DateTime<Utc>,
+}
+
+/// A memory changed on both sides of a sync, held until someone picks a version
+///
+/// The store keeps the local version meanwhile.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct QuarantinedConflict {
+    pub memory_id: String,
+    /// Path of the memory file relative to the store
+    pub path: String,
+    /// What found the conflict, e.g. `git`
+    pub source: String,
+    pub detected_at: DateTime<Utc>,
+    /// The local version, `None` when it was deleted locally
+    pub mine: Option<Memory>,
+    /// The remote version, `None` when it was deleted on the remote
+    pub theirs: Option<Memory>,
+}
+
+/// Which version of a quarantined memory to keep
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictResolution {
+    /// Keep the memory as it is in the store
+    Mine,
+    /// Replace the memory with the remote version, or delete it if the remote did
+    Theirs,
+    /// Combine both versions, or save an edited version given by the caller
+    Merge,
+}
+
+/// The parts of a merged memory chosen by the caller; the rest is merged automatically
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct MergedVersion {
+    pub title: Option<String>,
+    pub content: Option<String>,
+    pub tags: Option<Vec<String>>,
+}
+
+impl MemoryStore {
+    fn conflict_dir(&self, id: &str) -> PathBuf {
+        self.base_path.join(CONFLICTS_DIR).join(id)
+    }
+
+    /// Keep both versions of a memory a sync could not reconcile
+    ///
+    /// A newer conflict for the same memory replaces the older one, whose
+    /// versions remain in the history of the sync.
+    pub(super) fn quarantine_conflict(
+        &self,
+        path: &str,
+        source: &str,
+        mine: Option<&str>,
+        theirs: Option<&str>,
+    ) -> Result<String, MemoryError> {
+        let markdown = mine.or(theirs).ok_or_else(|| MemoryError::InvalidFormat(format!("Conflict in {} has no versions", path)))?;
+        let id = Memory::from_markdown(markdown)?.id;
+        if !is_valid_id(&id) {
+            return Err(MemoryError::InvalidId(id));
+        }
+
+        let dir = self.conflict_dir(&id);
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(&dir)?;
+        for (file, version) in [(MINE_FILE, mine), (THEIRS_FILE, theirs)] {
+            if let Some(version) = version {
+                fs::write(dir.join(file), version)?;
+            }
+        }
+        let info = ConflictInfo { path: path.to_string(), source: source.to_string(), detected_at: Utc::now() };
+        let info = serde_json::to_string_pretty(&info).map_err(|e| MemoryError::InvalidFormat(e.to_string()))?;
+        fs::write(dir.join(CONFLICT_FILE), info)?;
+
+        tracing::debug!("Quarantined {} conflict in {}", source, path);
+        Ok(id)
+    }
+
+    /// A quarantined conflict with both of its versions
+    pub fn get_conflict(&self, id: &str) -> Result<QuarantinedConflict, MemoryError> {
+        let dir = self.conflict_dir(id);
+        let info_path = dir.join(CONFLICT_FILE);
+        if !is_valid_id(id) || !info_path.is_file() {
+            return Err(MemoryError::NotFound(id.to_string()));
+        }
+
+        let info: ConflictInfo = serde_json::from_str(&fs::read_to_string(info_path)?)
+            .map_err(|e| MemoryError::InvalidFormat(e.to_string()))?;
+        let version = |file: &str| -> Result<Option<Memory>, MemoryError> {
+            let path = dir.join(file);
+            if !path.is_file() {
+                return Ok(None);
+            }
+            let mut memory = Memory::from_markdown(&fs::read_to_string(&path)?)?;
+            memory.collection = self.collection_for(&self.base_path.join(&info.path));
+            Ok(Some(memory))
+        };
+
+        Ok(QuarantinedConflict {
+            memory_id: id.to_string(),
+            mine: version(MINE_FILE)?,
+            theirs: version(THEIRS_FILE)?,
+            path: info.path,
+            source: info.source,
+            detected_at: info.detected_at,
+        })
+    }
+
+    /// Quarantined conflicts waiting for a resolution, oldest first
+    pub fn list_conflicts(&self) -> Result<Vec<QuarantinedConflict>, MemoryError> {
+        let dir = self.base_path.join(CONFLICTS_DIR);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut conflicts = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let id = entry?.file_name().to_string_lossy().to_string();
+            match self.get_conflict(&id) {
+                Ok(conflict) => conflicts.push(conflict),
+                Err(e) => tracing::debug!("Error reading quarantined conflict {}: {:?}", id, e),
+            }
+        }
+
+        conflicts.sort_by_key(|conflict| conflict.detected_at);
+        Ok(conflicts)
+    }
+
+    /// Settle a quarantined conflict and drop it from the quarantine
+    ///
+    /// Returns the memory as it is afterwards, or `None` when it was deleted.
+    /// Fields of `merged` only apply to [`ConflictResolution::Merge`].
+    pub fn resolve_quarantined(
+        &self,
+        id: &str,
+        resolution: ConflictResolution,
+        merged: MergedVersion,
+    ) -> Result<Option<Memory>, MemoryError> {
+        let _change = self.begin_change()?;
+        let conflict = self.get_conflict(id)?;
+        let current = self.find_memory_path(id)?.map(|path| self.read_memory(&path)).transpose()?;
+
+        let kept = match resolution {
+            ConflictResolution::Mine => current,
+            ConflictResolution::Theirs => match conflict.theirs {
+                Some(theirs) => {
+                    self.save_as(&theirs, Some(&format!("Resolve sync conflict with the remote version: {} ({})", theirs.title, id)))?;
+                    Some(theirs)
+                }
+                None => {
+                    if current.is_some() {
+                        self.delete(id)?;
+                    }
+                    None
+                }
+            },
+            ConflictResolution::Merge => {
+                let mine = current.or(conflict.mine);
+                let mut memory = match (mine, conflict.theirs) {
+                    (Some(mine), Some(theirs)) => merge(&mine, &theirs),
+                    (Some(memory), None) | (None, Some(memory)) => memory,
+                    (None, None) => return Err(MemoryError::NotFound(id.to_string())),
+                };
+                if let Some(title) = merged.title {
+                    memory.title = title;
+                }
+                if let Some(content) = merged.content {
+                    memory.content = content;
+                }
+                if let Some(tags) = merged.tags {
+                    memory.tags = tags;
+                }
+                memory.updated_at = Utc::now();
+                self.save_as(&memory, Some(&format!("Resolve sync conflict by merging: {} ({})", memory.title, id)))?;
+                Some(memory)
+            }
+        };
+
+        fs::remove_dir_all(self.conflict_dir(id))?;
+        tracing::debug!("Resolved quarantined conflict {} as {:?}", id, resolution);
+        Ok(kept)
+    }
+}
@@ -0,0 +1,207 @@
+use std::fs;
+use std::path::PathBuf;
+use chrono::Utc;
+
+use super::titles::title_from_content;
+use crate::util::write_atomic;
+use super::{is_valid_id, Memory, MemoryError, MemoryStore};
+use conduit_types::openai::ChatMessage;
+
+pub use conduit_types::conversations::{
+    Conversation, ConversationInfo, ConversationToMemoryRequest, CreateConversationRequest,
+};
+
+/// Folder inside the store holding one JSON file per conversation
+pub(super) const CONVERSATIONS_DIR: &str = "conversations";
+
+/// Title of conversations that have no text to take one from
+const UNTITLED: &str = "New conversation";
+
+// Title for a conversation, from its first user message
+fn conversation_title(messages: &[ChatMessage]) -> String {
+    messages.iter()
+        .filter(|message| message.role == "user")
+        .find_map(|message| title_from_content(&message.content.text()))
+        .unwrap_or_else(|| UNTITLED.to_string())
+}
+
+// Markdown transcript of the user's and the assistant's messages
+fn transcript(messages: &[ChatMessage]) -> String {
+    let mut md = Vec::new();
+    for message in messages {
+        let speaker = match message.role.as_str() {
+            "user" => "User",
+            "assistant" => "Assistant",
+            _ => continue,
+        };
+        let text = message.content.text();
+        let images: Vec<String> = message.content.images().map(|image| format!("![image]({})", image.url)).collect();
+        if text.trim().is_empty() && images.is_empty() {
+            continue;
+        }
+
+        let mut turn = format!("**{}:** {}", speaker, text.trim());
+        for image in images {
+            turn.push_str("\n\n");
+            turn.push_str(&image);
+        }
+        md.push(turn);
+    }
+    md.join("\n\n")
+}
+
+impl MemoryStore {
+    pub(super) fn conversations_dir(&self) -> PathBuf {
+        self.base_path.join(CONVERSATIONS_DIR)
+    }
+
+    fn conversation_path(&self, id: &str) -> PathBuf {
+        self.conversations_dir().join(format!("{}.json", id))
+    }
+
+    fn read_conversation(&self, id: &str) -> Result<Conversation, MemoryError> {
+        let path = self.conversation_path(id);
+        if !is_valid_id(id) || !path.exists() {
+            return Err(MemoryError::NotFound(format!("conversation {}", id)));
+        }
+
+        serde_json::from_str(&fs::read_to_string(path)?)
+            .map_err(|e| MemoryError::InvalidFormat(format!("Invalid conversation {}: {}", id, e)))
+    }
+
+    fn write_conversation(&self, conversation: &Conversation) -> Result<(), MemoryError> {
+        self.check_available()?;
+        let _change = self.begin_change()?;
+        let json = serde_json::to_string_pretty(conversation)
+            .map_err(|e| MemoryError::InvalidFormat(e.to_string()))?;
+        fs::create_dir_all(self.conversations_dir())?;
+        write_atomic(&self.conversation_path(&conversation.id), json)?;
+        Ok(())
+    }
+
+    // Read, change and write back a conversation the caller can see while holding the lock
+    fn update_conversation(
+        &self,
+        id: &str,
+        owner: Option<&str>,
+        update: impl FnOnce(&mut Conversation),
+    ) -> Result<Conversation, MemoryError> {
+        let _guard = self.conversations_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut conversation = self.get_conversation(id, owner)?;
+        update(&mut conversation);
+        self.write_conversation(&conversation)?;
+        self.git_commit(&format!("Update conversation: {} ({})", conversation.title, id));
+        Ok(conversation)
+    }
+
+    /// List the conversations `owner` can see, most recently updated first
+    ///
+    /// Without an owner every conversation is listed; users only see their own.
+    pub fn list_conversations(&self, owner: Option<&str>) -> Result<Vec<ConversationInfo>, MemoryError> {
+        self.check_available()?;
+        let mut conversations = Vec::new();
+        for entry in fs::read_dir(self.conversations_dir()).into_iter().flatten().flatten() {
+            let path = entry.path();
+            let Some(id) = path.file_name().and_then(|name| name.to_str()).and_then(|name| name.strip_suffix(".json")) else {
+                continue;
+            };
+            match self.read_conversation(id) {
+                Ok(conversation) if owner.is_none() || conversation.owner.as_deref() == owner => {
+                    conversations.push(ConversationInfo::from(&conversation));
+                }
+                Ok(_) => {}
+                Err(e) => tracing::debug!("Skipping conversation {:?}: {}", path, e),
+            }
+        }
+        conversations.sort_by_key(|conversation| std::cmp::Reverse(conversation.updated_at));
+        Ok(conversations)
+    }
+
+    /// Get a conversation with its messages, if `owner` can see it
+    pub fn get_conversation(&self, id: &str, owner: Option<&str>) -> Result<Conversation, MemoryError> {
+        let conversation = self.read_conversation(id)?;
+        if owner.is_some() && conversation.owner.as_deref() != owner {
+            return Err(MemoryError::NotFound(format!("conversation {}", id)));
+        }
+        Ok(conversation)
+    }
+
+    /// Start a conversation belonging to `owner`, optionally with some history
+    pub fn create_conversation(&self, req: CreateConversationRequest, owner: Option<&str>) -> Result<Conversation, MemoryError> {
+        let title = match req.title.map(|title| title.trim().to_string()).filter(|title| !title.is_empty()) {
+            Some(title) => title,
+            None => conversation_title(&req.messages),
+        };
+        let now = Utc::now();
+        let conversation = Conversation {
+            id: ulid::Ulid::new().to_string(),
+            title,
+            model: req.model,
+            owner: owner.map(str::to_string),
+            created_at: now,
+            updated_at: now,
+            memory_id: None,
+            messages: req.messages,
+        };
+
+        self.write_conversation(&conversation)?;
+        self.git_commit(&format!("Start conversation: {} ({})", conversation.title, conversation.id));
+        Ok(conversation)
+    }
+
+    /// Add messages to the end of a conversation
+    ///
+    /// A conversation still without a title takes it from the new messages.
+    pub fn append_to_conversation(
+        &self,
+        id: &str,
+        owner: Option<&str>,
+        model: Option<&str>,
+        messages: Vec<ChatMessage>,
+    ) -> Result<Conversation, MemoryError> {
+        self.update_conversation(id, owner, |conversation| {
+            conversation.messages.extend(messages);
+            if conversation.title == UNTITLED {
+                conversation.title = conversation_title(&conversation.messages);
+            }
+            if let Some(model) = model {
+                conversation.model = Some(model.to_string());
+            }
+            conversation.updated_at = Utc::now();
+        })
+    }
+
+    /// Save a conversation as a memory holding its transcript
+    ///
+    /// Images sent inline in the conversation become attachments of the
+    /// memory. The memory belongs to the conversation's owner.
+    pub fn conversation_to_memory(
+        &self,
+        id: &str,
+        owner: Option<&str>,
+        req: ConversationToMemoryRequest,
+    ) -> Result<Memory, MemoryError> {
+        let conversation = self.get_conversation(id, owner)?;
+        let title = req.title
+            .map(|title| title.trim().to_string())
+            .filter(|title| !title.is_empty())
+            .unwrap_or_else(|| conversation.title.clone());
+
+        let mut messages = conversation.messages;
+        let inline_images = messages.iter().any(|message| message.content.images().any(|image| image.data().is_some()));
+        let mut memory = self.new_memory(title, String::new(), req.tags)?;
+        memory.owner = conversation.owner;
+        if inline_images {
+            // Attachments need the memory to exist, so the transcript is added once the images are stored
+            self.save(&memory)?;
+            self.attach_message_images(&memory.id, &mut messages)?;
+            memory = self.get(&memory.id)?;
+        }
+        memory.content = transcript(&messages);
+        self.save(&memory)?;
+
+        let memory_id = memory.id.clone();
+        self.update_conversation(id, owner, |conversation| conversation.memory_id = Some(memory_id))?;
+        Ok(memory)
+    }
+}
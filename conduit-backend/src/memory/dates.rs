@@ -0,0 +1,99 @@
+use std::sync::OnceLock;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use regex::{Captures, Regex};
+
+/// Time of day given to dates mentioned without one
+const DEFAULT_HOUR: u32 = 9;
+
+// Dates such as `2024-07-01`, `tomorrow`, `next Tuesday` or `in 3 days`,
+// optionally followed by a time such as `at 3pm` or `15:30`
+fn date_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(
+            r"(?ix)
+            \b(?:
+                (?P<iso>\d{4}-\d{2}-\d{2})(?:T(?P<iso_hour>\d{2}):(?P<iso_minute>\d{2}))?
+                | (?P<relative>today|tonight|tomorrow)
+                | (?:(?P<prefix>next|this|on)\s+)(?P<weekday>monday|tuesday|wednesday|thursday|friday|saturday|sunday)
+                | in\s+(?P<count>\d+|an?)\s+(?P<unit>days?|weeks?)
+            )\b
+            (?:(?P<at>\s+at)?\s+(?P<hour>\d{1,2})(?::(?P<minute>\d{2}))?\s*(?P<meridiem>am|pm)?\b)?",
+        ).unwrap()
+    })
+}
+
+fn weekday(name: &str) -> Option<Weekday> {
+    name.parse().ok()
+}
+
+// The day a match refers to, counted from `today`
+fn matched_day(captures: &Captures, today: NaiveDate) -> Option<NaiveDate> {
+    if let Some(iso) = captures.name("iso") {
+        return NaiveDate::parse_from_str(iso.as_str(), "%Y-%m-%d").ok();
+    }
+    if let Some(relative) = captures.name("relative") {
+        return match relative.as_str().to_lowercase().as_str() {
+            "tomorrow" => today.succ_opt(),
+            _ => Some(today),
+        };
+    }
+    if let Some(name) = captures.name("weekday") {
+        let target = weekday(name.as_str())?;
+        let mut days = (7 + target.num_days_from_monday() as i64 - today.weekday().num_days_from_monday() as i64) % 7;
+        // `next Tuesday` said on a Tuesday means a week from now; `this Tuesday` means today
+        if days == 0 && !captures.name("prefix").is_some_and(|prefix| prefix.as_str().eq_ignore_ascii_case("this")) {
+            days = 7;
+        }
+        return today.checked_add_signed(Duration::days(days));
+    }
+    let count = captures.name("count")?.as_str();
+    let count: i64 = count.parse().unwrap_or(1);
+    let days = if captures.name("unit")?.as_str().to_lowercase().starts_with("week") { count * 7 } else { count };
+    today.checked_add_signed(Duration::days(days))
+}
+
+// The time a match gives, if it has a valid one
+fn matched_time(captures: &Captures) -> Option<NaiveTime> {
+    if let (Some(hour), Some(minute)) = (captures.name("iso_hour"), captures.name("iso_minute")) {
+        return NaiveTime::from_hms_opt(hour.as_str().parse().ok()?, minute.as_str().parse().ok()?, 0);
+    }
+    let mut hour: u32 = captures.name("hour")?.as_str().parse().ok()?;
+    let minute: u32 = captures.name("minute").map_or(Some(0), |minute| minute.as_str().parse().ok())?;
+    let meridiem = captures.name("meridiem").map(|meridiem| meridiem.as_str().to_lowercase());
+    match meridiem.as_deref() {
+        Some(_) if hour == 0 || hour > 12 => return None,
+        Some("pm") if hour < 12 => hour += 12,
+        Some("am") if hour == 12 => hour = 0,
+        // A bare number after a date is only a time when written as `at 9`
+        None if captures.name("minute").is_none() && captures.name("at").is_none() => return None,
+        _ => {}
+    }
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+/// The first date mentioned in `text`, such as "remind me next Tuesday" or
+/// "meeting 2024-07-01 at 3pm"
+///
+/// Understands ISO dates, `today`, `tonight`, `tomorrow`, weekdays after
+/// `next`, `this` or `on`, and `in 3 days` or `in a week`, each optionally
+/// followed by a time. Relative dates are counted from `now`, and dates
+/// without a time are taken to be at 9:00 (18:00 for `tonight`) in the
+/// time zone of `now`.
+pub fn find_date<Tz: TimeZone>(text: &str, now: &DateTime<Tz>) -> Option<DateTime<Utc>> {
+    let timezone = now.timezone();
+    date_pattern().captures_iter(text).find_map(|captures| {
+        let day = matched_day(&captures, now.date_naive())?;
+        let is_tonight = captures.name("relative").is_some_and(|relative| relative.as_str().eq_ignore_ascii_case("tonight"));
+        let default = NaiveTime::from_hms_opt(if is_tonight { 18 } else { DEFAULT_HOUR }, 0, 0)?;
+        let time = matched_time(&captures).unwrap_or(default);
+        timezone.from_local_datetime(&day.and_time(time))
+            .earliest()
+            .map(|date| date.with_timezone(&Utc))
+    })
+}
+
+/// [`find_date`] counted from the current time in the local time zone
+pub fn find_local_date(text: &str) -> Option<DateTime<Utc>> {
+    find_date(text, &Local::now())
+}
@@ -0,0 +1,112 @@
+use chrono::{Duration, Utc};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use super::{MemoryError, Reference, StorageBackend};
+
+/// Seed used when none is given, so demo stores look the same on every run
+pub const DEFAULT_DEMO_SEED: u64 = 42;
+
+/// How far back the oldest demo memory may have been created, in days
+const DEMO_HISTORY_DAYS: i64 = 180;
+
+const DEMO_TAGS: &[&str] = &[
+    "project", "meeting", "idea", "research", "todo", "reading", "design", "rust", "personal", "travel",
+];
+
+// Title and body of each demo memory; links to other memories are added when seeding
+const DEMO_NOTES: &[(&str, &str)] = &[
+    ("Project kickoff", "# Project kickoff\n\nGoals for the first quarter:\n\n- Ship the desktop app beta\n- Keep notes as plain markdown\n- Make search fast enough to feel instant"),
+    ("Weekly sync notes", "Discussed the release checklist and who owns the installer. Follow up on the signing certificate before Friday."),
+    ("Reading list", "## Books\n\n- *A Philosophy of Software Design*\n- *Designing Data-Intensive Applications*\n- *How to Take Smart Notes*"),
+    ("Idea: offline-first sync", "What if every device kept a full copy and we only exchanged diffs? Conflicts would be rare for personal notes."),
+    ("Interview with a power user", "They keep about 3,000 notes and mostly navigate through links rather than search. Tags are used sparingly."),
+    ("Rust error handling patterns", "Prefer `thiserror` for library errors and map them to HTTP statuses at the edge. Avoid `unwrap` outside tests."),
+    ("Trip to Lisbon", "Flights booked for May. Visit the tile museum, take tram 28 early in the morning and try the custard tarts in Belém."),
+    ("Design review: graph view", "Nodes are sized by degree. Tag edges get noisy above 200 memories, so they should be optional."),
+    ("Retrospective", "### Went well\n\n- Fast iteration on the editor\n\n### To improve\n\n- Too many late changes before the release"),
+    ("Research: embedding models", "Compared small local models against hosted ones. Local models are good enough for keyword-heavy notes."),
+    ("Grocery list", "- [ ] Coffee beans\n- [ ] Oat milk\n- [x] Bread\n- [ ] Tomatoes"),
+    ("Meeting with design team", "Agreed on a neutral colour palette and a single accent colour. Icons come from one set only."),
+    ("Idea: daily review prompt", "Show three random older notes every morning to resurface forgotten ideas."),
+    ("Release checklist", "1. Bump the version\n2. Update the changelog\n3. Build and sign installers\n4. Publish release notes"),
+    ("Notes on Zettelkasten", "One idea per note, link generously and write in your own words. The value comes from the connections."),
+    ("Bug: search misses accented words", "Searching for \"cafe\" does not find \"café\". Normalise both the query and the content before comparing."),
+    ("Podcast recommendations", "A few episodes on local-first software and one long interview about building developer tools."),
+    ("Quarterly planning", "Focus areas: reliability, import from other tools, and a better onboarding flow."),
+    ("Idea: templates for meeting notes", "A template with attendees, decisions and action items would make meeting notes easier to scan later."),
+    ("Running log", "5 km in 27 minutes. Legs felt heavy after the long week; plan an easy run on Sunday."),
+    ("API design principles", "Consistent error shapes, predictable pagination and no breaking changes without a version bump."),
+    ("Research: note-taking habits", "Most people capture far more than they ever revisit. Review features matter more than capture speed."),
+    ("Book notes: Deep Work", "Schedule focus blocks, batch shallow tasks and keep a shutdown ritual at the end of the day."),
+    ("Todo: clean up tags", "Merge duplicate tags like `todo` and `to-do`, and retire tags used only once."),
+];
+
+// Sample external references, attached to a few memories
+const DEMO_REFERENCES: &[(&str, &str, &str)] = &[
+    ("ticket", "https://tracker.example.com/issues/128", "Issue 128"),
+    ("pr", "https://git.example.com/conduit/pull/42", "Pull request 42"),
+    ("doc", "https://docs.example.com/design/graph-view", "Graph view design doc"),
+];
+
+/// Fill a store with generated sample memories for demos, screenshots and bug reports
+///
+/// Memories get varied tags, creation dates spread over the last six months,
+/// wiki-links to each other and a few external references. The same `seed`
+/// always produces the same tags, dates and link structure; only the IDs differ.
+///
+/// Returns the number of memories created.
+pub fn seed_demo_memories(storage: &dyn StorageBackend, seed: u64) -> Result<usize, MemoryError> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let now = Utc::now();
+
+    let mut memories = Vec::with_capacity(DEMO_NOTES.len());
+    for (title, content) in DEMO_NOTES {
+        let tag_count = rng.gen_range(1..=3);
+        let tags = DEMO_TAGS
+            .choose_multiple(&mut rng, tag_count)
+            .map(|tag| tag.to_string())
+            .collect();
+        let mut memory = storage.new_memory(title.to_string(), content.to_string(), tags)?;
+
+        memory.created_at = now - Duration::minutes(rng.gen_range(0..DEMO_HISTORY_DAYS * 24 * 60));
+        let age = (now - memory.created_at).num_minutes().max(1);
+        memory.updated_at = memory.created_at + Duration::minutes(rng.gen_range(0..age));
+        if rng.gen_bool(0.5) {
+            memory.last_viewed_at = Some(memory.updated_at + Duration::minutes(rng.gen_range(0..=(now - memory.updated_at).num_minutes())));
+        }
+        memories.push(memory);
+    }
+
+    // Link each memory to a few others, now that every ID is known
+    let ids: Vec<String> = memories.iter().map(|memory| memory.id.clone()).collect();
+    for memory in memories.iter_mut() {
+        let link_count = rng.gen_range(0..=3);
+        let links: Vec<&String> = ids.iter()
+            .filter(|id| **id != memory.id)
+            .collect::<Vec<_>>()
+            .choose_multiple(&mut rng, link_count)
+            .copied()
+            .collect();
+        if !links.is_empty() {
+            let related = links.iter().map(|id| format!("[[{}]]", id)).collect::<Vec<_>>().join(", ");
+            memory.content.push_str(&format!("\n\nRelated: {}", related));
+        }
+    }
+
+    for (memory, (kind, url, label)) in memories.iter_mut().step_by(7).zip(DEMO_REFERENCES) {
+        memory.references.push(Reference {
+            kind: kind.to_string(),
+            url: Some(url.to_string()),
+            path: None,
+            label: Some(label.to_string()),
+        });
+    }
+
+    for memory in &memories {
+        storage.save(memory)?;
+    }
+    Ok(memories.len())
+}
+
@@ -0,0 +1,187 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::util::{fnv1a, write_atomic};
+use super::{is_valid_id, IndexState, Memory, MemoryError, MemoryStore};
+
+/// Hidden folder inside the store holding one `<memory_id>.json` embedding per memory
+const EMBEDDINGS_DIR: &str = ".embeddings";
+
+/// The embedding of a memory, along with what it was computed from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredEmbedding {
+    pub model: String,
+    /// [`Memory::content_hash`] of the memory when it was embedded
+    pub content_hash: String,
+    pub vector: Vec<f32>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// How many memories have an up-to-date embedding for a model
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct EmbeddingStatus {
+    pub model: String,
+    pub total: usize,
+    pub embedded: usize,
+    /// Memories without an embedding, or whose content changed since it was computed
+    pub stale: usize,
+}
+
+/// What is embedded for a memory, and how changes to it are detected
+pub trait MemoryEmbedding {
+    /// Text that is embedded for this memory
+    fn embedding_text(&self) -> String;
+
+    /// Stable hash of the embedded text, used to detect content changes
+    fn content_hash(&self) -> String;
+}
+
+impl MemoryEmbedding for Memory {
+    fn embedding_text(&self) -> String {
+        format!("{}\n\n{}", self.title, self.content)
+    }
+
+    fn content_hash(&self) -> String {
+        format!("{:016x}", fnv1a(self.embedding_text().as_bytes()))
+    }
+}
+
+/// Cosine similarity of two vectors; 0 when their lengths differ or either is zero
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+impl MemoryStore {
+    pub(super) fn embeddings_dir(&self) -> PathBuf {
+        self.base_path.join(EMBEDDINGS_DIR)
+    }
+
+    fn embedding_path(&self, id: &str) -> PathBuf {
+        self.embeddings_dir().join(format!("{}.json", id))
+    }
+
+    /// The stored embedding of a memory, if it has one
+    pub fn get_embedding(&self, id: &str) -> Result<Option<StoredEmbedding>, MemoryError> {
+        if !is_valid_id(id) {
+            return Err(MemoryError::InvalidId(id.to_string()));
+        }
+
+        let path = self.embedding_path(id);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        serde_json::from_str(&fs::read_to_string(path)?)
+            .map(Some)
+            .map_err(|e| MemoryError::InvalidFormat(format!("Invalid embedding for {}: {}", id, e)))
+    }
+
+    /// Store the embedding of a memory, replacing any previous one
+    pub fn save_embedding(&self, id: &str, embedding: &StoredEmbedding) -> Result<(), MemoryError> {
+        if !is_valid_id(id) {
+            return Err(MemoryError::InvalidId(id.to_string()));
+        }
+        self.check_available()?;
+        self.check_writable()?;
+
+        let json = serde_json::to_string(embedding).map_err(|e| MemoryError::InvalidFormat(e.to_string()))?;
+        fs::create_dir_all(self.embeddings_dir())?;
+        write_atomic(&self.embedding_path(id), json)?;
+        Ok(())
+    }
+
+    // Whether a memory needs to be (re-)embedded with `model`
+    fn is_stale(&self, memory: &Memory, model: &str) -> bool {
+        match self.get_embedding(&memory.id) {
+            Ok(Some(embedding)) => embedding.model != model || embedding.content_hash != memory.content_hash(),
+            _ => true,
+        }
+    }
+
+    /// Memories that have no embedding for `model`, or whose content changed since it was computed
+    pub fn stale_embeddings(&self, model: &str) -> Result<Vec<Memory>, MemoryError> {
+        Ok(self.list()?
+            .into_iter()
+            .filter(|memory| self.is_stale(memory, model))
+            .collect())
+    }
+
+    /// Count the memories with an up-to-date embedding for `model`
+    pub fn embedding_status(&self, model: &str) -> Result<EmbeddingStatus, MemoryError> {
+        let memories = self.list()?;
+        let stale = memories.iter().filter(|memory| self.is_stale(memory, model)).count();
+
+        Ok(EmbeddingStatus {
+            model: model.to_string(),
+            total: memories.len(),
+            embedded: memories.len() - stale,
+            stale,
+        })
+    }
+
+    // How many of `memories` have an up-to-date embedding for `model`, and when one was last stored
+    pub(super) fn embedding_index_state(&self, memories: &[Memory], model: &str) -> IndexState {
+        let pending = memories.iter().filter(|memory| self.is_stale(memory, model)).count();
+        // File times stand in for `updated_at`, so the vectors are not read
+        let updated_at = fs::read_dir(self.embeddings_dir()).into_iter().flatten().flatten()
+            .filter_map(|entry| entry.metadata().and_then(|m| m.modified()).ok())
+            .max()
+            .map(DateTime::<Utc>::from);
+        IndexState {
+            documents: memories.len() - pending,
+            pending,
+            updated_at,
+            rebuilding: false,
+        }
+    }
+
+    // Embeddings follow a memory to its new ID
+    pub(super) fn rename_embedding(&self, old_id: &str, new_id: &str) -> Result<(), MemoryError> {
+        let old_path = self.embedding_path(old_id);
+        if old_path.exists() {
+            fs::rename(old_path, self.embedding_path(new_id))?;
+        }
+        Ok(())
+    }
+
+    // Remove the embedding of a memory that is gone for good
+    pub(super) fn remove_embedding(&self, id: &str) -> Result<(), MemoryError> {
+        let path = self.embedding_path(id);
+        if is_valid_id(id) && path.exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    // Embedding files whose memory is neither live nor trashed
+    pub(super) fn orphaned_embeddings(&self, known_ids: &HashSet<String>) -> Vec<PathBuf> {
+        fs::read_dir(self.embeddings_dir())
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                let file_name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+                match file_name.strip_suffix(".json") {
+                    Some(id) => !known_ids.contains(id),
+                    // Staging files left behind by an interrupted write
+                    None => file_name.ends_with(".json.tmp"),
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+
+use super::Memory;
+
+/// How far a slow subscriber may fall behind before it misses events
+const EVENT_CAPACITY: usize = 256;
+
+/// Buses of the stores opened in this process, by directory
+static STORE_BUSES: OnceLock<Mutex<HashMap<PathBuf, EventBus>>> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum MemoryEventKind {
+    #[serde(rename = "memory.created")]
+    Created,
+    #[serde(rename = "memory.updated")]
+    Updated,
+    #[serde(rename = "memory.deleted")]
+    Deleted,
+}
+
+impl MemoryEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Created => "memory.created",
+            Self::Updated => "memory.updated",
+            Self::Deleted => "memory.deleted",
+        }
+    }
+}
+
+/// A change made to a memory through the store
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MemoryEvent {
+    #[serde(rename = "type")]
+    pub kind: MemoryEventKind,
+    pub id: String,
+    /// The memory after the change; absent for deletions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<Memory>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl MemoryEvent {
+    pub fn created(memory: &Memory) -> Self {
+        Self::with_memory(MemoryEventKind::Created, memory)
+    }
+
+    pub fn updated(memory: &Memory) -> Self {
+        Self::with_memory(MemoryEventKind::Updated, memory)
+    }
+
+    pub fn deleted(id: &str) -> Self {
+        Self {
+            kind: MemoryEventKind::Deleted,
+            id: id.to_string(),
+            memory: None,
+            timestamp: Utc::now(),
+        }
+    }
+
+    fn with_memory(kind: MemoryEventKind, memory: &Memory) -> Self {
+        Self {
+            kind,
+            id: memory.id.clone(),
+            memory: Some(memory.clone()),
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+/// Broadcasts memory changes to any number of subscribers
+///
+/// Cloning the bus shares it. Publishing without subscribers is not an error;
+/// the event is simply dropped.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<MemoryEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_CAPACITY);
+        Self { sender }
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The bus shared by every store opened on `path` in this process
+    ///
+    /// Apps may open the same directory several times, like the desktop app
+    /// does for each command, and subscribers see the changes made through
+    /// any of them.
+    pub fn for_path(path: &Path) -> Self {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let mut buses = STORE_BUSES.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+        buses.entry(path).or_default().clone()
+    }
+
+    pub fn publish(&self, event: MemoryEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    /// Receive every event published from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<MemoryEvent> {
+        self.sender.subscribe()
+    }
+}
@@ -0,0 +1,396 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use super::attachments::ATTACHMENTS_DIR;
+use super::snapshot::StoreSnapshot;
+use super::{Collection, Memory, MemoryAccess, MemoryError, MemoryMarkdown, MemoryStore, Principal};
+
+/// Name of the manifest at the root of a zip export
+pub const MANIFEST_NAME: &str = "index.json";
+
+pub use conduit_types::export::{ExportFormat, ExportReport};
+
+/// Replacement for text matched by a redaction rule
+const REDACTED: &str = "[REDACTED]";
+
+/// Which memories an export includes and how they are changed on the way out
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    /// Only memories with at least one of these tags; every memory when empty
+    pub tags: Vec<String>,
+    /// Leave out memories with any of these tags
+    pub exclude_tags: Vec<String>,
+    /// Text of titles and content matching any of these is replaced with `[REDACTED]`
+    pub redactions: Vec<Regex>,
+    /// Whether attachments are copied; they are never redacted
+    pub attachments: bool,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            tags: Vec::new(),
+            exclude_tags: Vec::new(),
+            redactions: Vec::new(),
+            attachments: true,
+        }
+    }
+}
+
+impl ExportOptions {
+    fn has_tag_filter(&self) -> bool {
+        !self.tags.is_empty() || !self.exclude_tags.is_empty()
+    }
+
+    fn includes(&self, memory: &Memory) -> bool {
+        let has = |tags: &[String]| memory.tags.iter().any(|own| tags.iter().any(|tag| own.eq_ignore_ascii_case(tag)));
+        (self.tags.is_empty() || has(&self.tags)) && !has(&self.exclude_tags)
+    }
+
+    fn redact(&self, text: &str) -> String {
+        self.redactions.iter().fold(text.to_string(), |text, regex| {
+            regex.replace_all(&text, REDACTED).into_owned()
+        })
+    }
+
+    // The copy of a memory that is written out
+    fn prepare(&self, memory: &Memory) -> Memory {
+        let mut memory = memory.clone();
+        if !self.redactions.is_empty() {
+            memory.title = self.redact(&memory.title);
+            memory.content = self.redact(&memory.content);
+        }
+        if !self.attachments {
+            memory.attachments.clear();
+        }
+        memory
+    }
+}
+
+/// A memory listed in the manifest of a zip export
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ManifestEntry {
+    pub id: String,
+    pub title: String,
+    pub tags: Vec<String>,
+    pub collection: Option<String>,
+    /// Path of the markdown file in the zip
+    pub path: String,
+    /// Paths of the attachments in the zip
+    pub attachments: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Contents of `index.json` in a zip export
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ExportManifest {
+    pub snapshot_at: DateTime<Utc>,
+    pub memories: Vec<ManifestEntry>,
+    pub collections: Vec<Collection>,
+}
+
+// Path of a memory's markdown file in an export, inside its collection folder
+fn memory_file(memory: &Memory) -> String {
+    match &memory.collection {
+        Some(collection) => format!("{}/{}.md", collection.replace('\\', "/"), memory.id),
+        None => format!("{}.md", memory.id),
+    }
+}
+
+fn attachment_file(id: &str, name: &str) -> String {
+    format!("{}/{}/{}", ATTACHMENTS_DIR, id, name)
+}
+
+// Absolute form of a path that may not exist yet, with symlinks in its existing part resolved
+fn resolve(path: &Path) -> Result<PathBuf, MemoryError> {
+    let path = std::path::absolute(path)?;
+    let mut existing = path.as_path();
+    let mut missing = Vec::new();
+    while !existing.exists() {
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name);
+                existing = parent;
+            }
+            _ => break,
+        }
+    }
+    let mut resolved = existing.canonicalize()?;
+    resolved.extend(missing.iter().rev());
+    Ok(resolved)
+}
+
+fn zip_error(err: ZipError) -> MemoryError {
+    match err {
+        ZipError::Io(err) => MemoryError::Io(err),
+        err => MemoryError::Io(io::Error::other(err)),
+    }
+}
+
+// Options for a zip entry last changed at `time`; dates a zip cannot hold keep the default
+fn entry_options(time: DateTime<Utc>) -> SimpleFileOptions {
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let Ok(year) = u16::try_from(time.year()) else {
+        return options;
+    };
+    match zip::DateTime::from_date_and_time(
+        year,
+        time.month() as u8,
+        time.day() as u8,
+        time.hour() as u8,
+        time.minute() as u8,
+        time.second() as u8,
+    ) {
+        Ok(modified) => options.last_modified_time(modified),
+        Err(_) => options,
+    }
+}
+
+// Write the memories of a snapshot and their attachments into a zip, followed by the manifest
+fn write_zip(file: File, snapshot: &StoreSnapshot, memories: &[Memory], collections: Vec<Collection>) -> Result<(), MemoryError> {
+    let mut zip = ZipWriter::new(io::BufWriter::new(file));
+    let mut manifest = ExportManifest {
+        snapshot_at: snapshot.taken_at,
+        memories: Vec::new(),
+        collections,
+    };
+
+    for memory in memories {
+        let path = memory_file(memory);
+        let options = entry_options(memory.updated_at);
+        zip.start_file(path.as_str(), options).map_err(zip_error)?;
+        zip.write_all(memory.to_markdown().as_bytes())?;
+
+        let mut attachments = Vec::new();
+        for name in &memory.attachments {
+            let source = snapshot.attachment_path(&memory.id, name);
+            if !source.is_file() {
+                continue;
+            }
+            let large = fs::metadata(&source)?.len() >= u32::MAX as u64;
+            let entry = attachment_file(&memory.id, name);
+            zip.start_file(entry.as_str(), options.large_file(large)).map_err(zip_error)?;
+            io::copy(&mut File::open(&source)?, &mut zip)?;
+            attachments.push(entry);
+        }
+
+        manifest.memories.push(ManifestEntry {
+            id: memory.id.clone(),
+            title: memory.title.clone(),
+            tags: memory.tags.clone(),
+            collection: memory.collection.clone(),
+            path,
+            attachments,
+            created_at: memory.created_at,
+            updated_at: memory.updated_at,
+        });
+    }
+
+    zip.start_file(MANIFEST_NAME, entry_options(snapshot.taken_at)).map_err(zip_error)?;
+    serde_json::to_writer_pretty(&mut zip, &manifest).map_err(|e| MemoryError::Io(e.into()))?;
+    zip.finish().map_err(zip_error)?.flush()?;
+    Ok(())
+}
+
+impl MemoryStore {
+    // Refuse export targets inside the store, where the copies would be picked up as memories
+    fn check_export_path(&self, path: &Path) -> Result<(), MemoryError> {
+        let store = self.base_path.canonicalize()?;
+        if resolve(path)?.starts_with(&store) {
+            return Err(MemoryError::InvalidExportPath(format!("{} is inside the store", path.display())));
+        }
+        Ok(())
+    }
+
+    // Check a file target and create its folder; returns the staging file written before it is moved into place
+    fn prepare_export_file(&self, path: &Path) -> Result<PathBuf, MemoryError> {
+        self.check_export_path(path)?;
+        if path.is_dir() {
+            return Err(MemoryError::InvalidExportPath(format!("{} is a directory", path.display())));
+        }
+        let name = path.file_name()
+            .ok_or_else(|| MemoryError::InvalidExportPath(format!("{} is not a file name", path.display())))?;
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        Ok(path.with_file_name(format!(".{}.partial", name.to_string_lossy())))
+    }
+
+    /// Write a copy of every memory as markdown into `dir`
+    ///
+    /// The copy is taken from a snapshot, so changes made while it is written
+    /// are left out. Memories in a collection folder keep their folder and
+    /// attachments go into `attachments/<id>/`. The directory is
+    /// created if needed and may not be inside the store, where the copies
+    /// would be picked up as memories.
+    pub fn export_markdown(&self, dir: &Path) -> Result<ExportReport, MemoryError> {
+        self.export_markdown_with(dir, &ExportOptions::default())
+    }
+
+    /// Write the memories `options` selects as markdown into `dir`, like [`MemoryStore::export_markdown`]
+    pub fn export_markdown_with(&self, dir: &Path, options: &ExportOptions) -> Result<ExportReport, MemoryError> {
+        self.check_available()?;
+        self.check_export_path(dir)?;
+        fs::create_dir_all(dir)?;
+        let target = dir.canonicalize()?;
+
+        let snapshot = self.snapshot()?;
+        let memories: Vec<Memory> = snapshot.memories.iter()
+            .filter(|memory| options.includes(memory))
+            .map(|memory| options.prepare(memory))
+            .collect();
+        for memory in &memories {
+            let file = target.join(memory_file(memory));
+            if let Some(folder) = file.parent() {
+                fs::create_dir_all(folder)?;
+            }
+            fs::write(file, memory.to_markdown())?;
+
+            for name in &memory.attachments {
+                let source = snapshot.attachment_path(&memory.id, name);
+                if source.is_file() {
+                    let copy = target.join(attachment_file(&memory.id, name));
+                    if let Some(folder) = copy.parent() {
+                        fs::create_dir_all(folder)?;
+                    }
+                    fs::copy(source, copy)?;
+                }
+            }
+        }
+
+        tracing::debug!("Exported {} memories to {:?}", memories.len(), target);
+        Ok(ExportReport {
+            path: target,
+            memories: memories.len(),
+            snapshot_at: snapshot.taken_at,
+            completed_at: Utc::now(),
+        })
+    }
+
+    /// Every memory a `principal` can read, or every memory without one
+    ///
+    /// The memories are ordered by creation time, so the list can be imported
+    /// elsewhere in the order it was written.
+    pub fn export_memories(&self, principal: Option<&Principal>) -> Result<Vec<Memory>, MemoryError> {
+        self.check_available()?;
+        let mut memories: Vec<Memory> = self.list()?.into_iter()
+            .filter(|memory| memory.is_visible_to(principal))
+            .collect();
+        memories.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+        tracing::debug!("Exported {} memories as JSON", memories.len());
+        Ok(memories)
+    }
+
+    /// Write a zip backup of the store to the file at `path`
+    ///
+    /// The zip holds the memories laid out like [`MemoryStore::export_markdown`],
+    /// their attachments, and an `index.json` manifest listing every memory and
+    /// collection. It is taken from a snapshot. With a `principal`, only the
+    /// memories it can read are included. The zip is written next to `path`
+    /// and moved into place when complete, so an existing backup is only
+    /// replaced by a finished one.
+    pub fn export_zip(&self, path: &Path, principal: Option<&Principal>) -> Result<ExportReport, MemoryError> {
+        self.export_zip_with(path, principal, &ExportOptions::default())
+    }
+
+    /// Write a zip of the memories `options` selects to `path`, like [`MemoryStore::export_zip`]
+    ///
+    /// Collections are listed with only their exported memories, and left out
+    /// when none of them are.
+    pub fn export_zip_with(&self, path: &Path, principal: Option<&Principal>, options: &ExportOptions) -> Result<ExportReport, MemoryError> {
+        self.check_available()?;
+        let partial = self.prepare_export_file(path)?;
+
+        let snapshot = self.snapshot()?;
+        let memories: Vec<Memory> = snapshot.memories.iter()
+            .filter(|memory| memory.is_visible_to(principal) && options.includes(memory))
+            .map(|memory| options.prepare(memory))
+            .collect();
+        let collections = snapshot.collections.iter()
+            .cloned()
+            .map(|mut collection| {
+                collection.memory_ids.retain(|id| memories.iter().any(|memory| &memory.id == id));
+                collection
+            })
+            .filter(|collection| (principal.is_none() && !options.has_tag_filter()) || !collection.memory_ids.is_empty())
+            .collect();
+
+        let written = File::create(&partial)
+            .map_err(MemoryError::from)
+            .and_then(|file| write_zip(file, &snapshot, &memories, collections))
+            .and_then(|_| fs::rename(&partial, path).map_err(MemoryError::from));
+        if let Err(err) = written {
+            let _ = fs::remove_file(&partial);
+            return Err(err);
+        }
+
+        let target = path.canonicalize()?;
+        tracing::debug!("Exported {} memories to zip {:?}", memories.len(), target);
+        Ok(ExportReport {
+            path: target,
+            memories: memories.len(),
+            snapshot_at: snapshot.taken_at,
+            completed_at: Utc::now(),
+        })
+    }
+
+    /// Write the memories `options` selects to a JSON file at `path`
+    ///
+    /// The file holds memory objects ordered by creation time, as
+    /// [`MemoryStore::export_memories`] returns them, taken from a snapshot. It
+    /// is written next to `path` and moved into place when complete.
+    pub fn export_json_with(&self, path: &Path, options: &ExportOptions) -> Result<ExportReport, MemoryError> {
+        self.check_available()?;
+        let partial = self.prepare_export_file(path)?;
+
+        let snapshot = self.snapshot()?;
+        let mut memories: Vec<Memory> = snapshot.memories.iter()
+            .filter(|memory| options.includes(memory))
+            .map(|memory| options.prepare(memory))
+            .collect();
+        memories.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+
+        let written = File::create(&partial)
+            .map_err(MemoryError::from)
+            .and_then(|file| {
+                let mut writer = io::BufWriter::new(file);
+                serde_json::to_writer_pretty(&mut writer, &memories).map_err(|e| MemoryError::Io(e.into()))?;
+                writer.flush().map_err(MemoryError::from)
+            })
+            .and_then(|_| fs::rename(&partial, path).map_err(MemoryError::from));
+        if let Err(err) = written {
+            let _ = fs::remove_file(&partial);
+            return Err(err);
+        }
+
+        let target = path.canonicalize()?;
+        tracing::debug!("Exported {} memories as JSON to {:?}", memories.len(), target);
+        Ok(ExportReport {
+            path: target,
+            memories: memories.len(),
+            snapshot_at: snapshot.taken_at,
+            completed_at: Utc::now(),
+        })
+    }
+
+    /// Write the memories `options` selects to `path` in `format`
+    ///
+    /// Every memory is considered, whoever owns it. `path` is a directory for
+    /// markdown and a file for the other formats.
+    pub fn export_as(&self, format: ExportFormat, path: &Path, options: &ExportOptions) -> Result<ExportReport, MemoryError> {
+        match format {
+            ExportFormat::Markdown => self.export_markdown_with(path, options),
+            ExportFormat::Zip => self.export_zip_with(path, None, options),
+            ExportFormat::Json => self.export_json_with(path, options),
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+
+use crate::util::fnv1a;
+use super::{is_hidden, Memory, MemoryMarkdown};
+
+/// A read-only markdown source: a configured external folder or a symlinked
+/// file/folder inside the store
+#[derive(Debug, Clone)]
+pub(crate) struct ExternalSource {
+    /// The path as it appears in configuration or in the store
+    pub root: PathBuf,
+    /// Collection name reported for memories from this source
+    pub name: String,
+}
+
+/// ID of a document that has no memory frontmatter, derived from its path so
+/// it survives restarts
+pub(crate) fn external_id(path: &Path) -> String {
+    format!("ext-{:016x}", fnv1a(path.to_string_lossy().as_bytes()))
+}
+
+pub(crate) fn is_markdown(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "md" || ext == "markdown")
+}
+
+/// Collect the markdown files of a source, following nested folders and
+/// guarding against symlink loops
+pub(crate) fn markdown_files(root: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut visited = HashSet::new();
+    let mut pending = vec![root.to_path_buf()];
+
+    if root.is_file() {
+        if is_markdown(root) {
+            files.push(root.to_path_buf());
+        }
+        return files;
+    }
+
+    while let Some(dir) = pending.pop() {
+        let canonical = fs::canonicalize(&dir).unwrap_or_else(|_| dir.clone());
+        if !visited.insert(canonical) {
+            continue;
+        }
+
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                tracing::debug!("Error reading external directory {:?}: {:?}", dir, e);
+                continue;
+            }
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if is_hidden(&path) {
+                continue;
+            }
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.is_file() && is_markdown(&path) {
+                files.push(path);
+            }
+        }
+    }
+
+    files
+}
+
+pub(crate) fn file_times(path: &Path) -> (DateTime<Utc>, DateTime<Utc>) {
+    let metadata = fs::metadata(path).ok();
+    let modified = metadata.as_ref()
+        .and_then(|m| m.modified().ok())
+        .map(DateTime::<Utc>::from)
+        .unwrap_or_else(Utc::now);
+    let created = metadata.as_ref()
+        .and_then(|m| m.created().ok())
+        .map(DateTime::<Utc>::from)
+        .unwrap_or(modified);
+    (created, modified)
+}
+
+// Title from the first markdown heading, falling back to the file name
+pub(crate) fn title_for(content: &str, path: &Path) -> String {
+    content.lines()
+        .find_map(|line| line.trim_start().strip_prefix('#'))
+        .map(|heading| heading.trim_start_matches('#').trim().to_string())
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default()
+        })
+}
+
+/// Read a file from an external source as a read-only memory
+///
+/// Files with memory frontmatter keep their ID and metadata; plain markdown
+/// gets a path-derived ID, a title from its first heading and file timestamps.
+pub(crate) fn read_document(source: &ExternalSource, path: &Path) -> Option<Memory> {
+    let content = fs::read_to_string(path).ok()?;
+
+    let mut memory = match Memory::from_markdown(&content) {
+        Ok(memory) => memory,
+        Err(_) => {
+            let (created_at, updated_at) = file_times(path);
+            let mut memory = Memory::new(title_for(&content, path), content, Vec::new());
+            memory.id = external_id(path);
+            memory.created_at = created_at;
+            memory.updated_at = updated_at;
+            memory
+        }
+    };
+
+    let relative = path.parent()
+        .and_then(|parent| parent.strip_prefix(&source.root).ok())
+        .map(|rel| rel.to_string_lossy().replace('\\', "/"))
+        .filter(|rel| !rel.is_empty());
+    memory.collection = Some(match relative {
+        Some(rel) => format!("{}/{}", source.name, rel),
+        None => source.name.clone(),
+    });
+    memory.read_only = true;
+
+    Some(memory)
+}
@@ -0,0 +1,160 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{MemoryError, MemoryStore};
+
+/// Kind of artifact left behind without a memory to belong to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtifactKind {
+    /// Staging file from an interrupted multi-file write
+    StagingFile,
+    /// Trash metadata whose trashed memory no longer exists
+    TrashSidecar,
+    /// Attachment not referenced by any memory, live or trashed
+    Attachment,
+    /// Stored embedding of a memory that no longer exists
+    Embedding,
+    /// Extracted text of an attachment that no longer exists
+    AttachmentText,
+}
+
+/// An artifact that garbage collection found (and possibly removed)
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct OrphanedArtifact {
+    #[schema(value_type = String)]
+pub path: PathBuf,
+    pub kind: ArtifactKind,
+    pub bytes: u64,
+}
+
+/// Result of a garbage collection run
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct GcReport {
+    /// When set, nothing was deleted and the report lists what would be removed
+    pub dry_run: bool,
+    pub artifacts: Vec<OrphanedArtifact>,
+    /// Total size of the artifacts, freed unless this was a dry run
+    pub reclaimed_bytes: u64,
+}
+
+// Size of a file, or of everything below a directory
+fn disk_usage(path: &Path) -> u64 {
+    if path.is_dir() {
+        fs::read_dir(path)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| disk_usage(&entry.path()))
+            .sum()
+    } else {
+        fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+    }
+}
+
+fn artifact(path: PathBuf, kind: ArtifactKind) -> OrphanedArtifact {
+    let bytes = disk_usage(&path);
+    OrphanedArtifact { path, kind, bytes }
+}
+
+fn hidden_files_with_suffix(dir: &Path, suffix: &str) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with('.') && name.ends_with(suffix))
+        })
+        .collect()
+}
+
+impl MemoryStore {
+    // Collect orphaned artifacts without touching them
+    fn find_orphans(&self) -> Result<Vec<OrphanedArtifact>, MemoryError> {
+        let mut orphans = Vec::new();
+
+        // Leftovers from a rename that failed half-way
+        for dir in self.store_dirs()? {
+            for path in hidden_files_with_suffix(&dir, ".rename-tmp") {
+                orphans.push(artifact(path, ArtifactKind::StagingFile));
+            }
+        }
+
+        // Trash sidecars whose memory was removed by hand
+        for entry in fs::read_dir(self.trash_dir()).into_iter().flatten().flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "json") && !path.with_extension("md").exists() {
+                orphans.push(artifact(path, ArtifactKind::TrashSidecar));
+            }
+        }
+
+        // Attachments of memories that no longer exist, or that were dropped from frontmatter
+        let mut references: HashMap<String, Vec<String>> = self.list()?
+            .into_iter()
+            .map(|memory| (memory.id, memory.attachments))
+            .collect();
+        for trashed in self.read_trash()? {
+            references.insert(trashed.memory.id, trashed.memory.attachments);
+        }
+        for entry in fs::read_dir(self.attachments_root()).into_iter().flatten().flatten() {
+            let dir = entry.path();
+            let id = entry.file_name().to_string_lossy().to_string();
+            match references.get(&id) {
+                None => orphans.push(artifact(dir, ArtifactKind::Attachment)),
+                Some(names) => {
+                    for file in fs::read_dir(&dir).into_iter().flatten().flatten() {
+                        let name = file.file_name().to_string_lossy().to_string();
+                        if !names.contains(&name) {
+                            orphans.push(artifact(file.path(), ArtifactKind::Attachment));
+                        }
+                    }
+                }
+            }
+        }
+
+        for path in self.orphaned_attachment_text(&references) {
+            orphans.push(artifact(path, ArtifactKind::AttachmentText));
+        }
+
+        // Embeddings are kept for trashed memories so a restore does not need to re-embed them
+        let known_ids: HashSet<String> = references.into_keys().collect();
+        for path in self.orphaned_embeddings(&known_ids) {
+            orphans.push(artifact(path, ArtifactKind::Embedding));
+        }
+
+        orphans.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(orphans)
+    }
+
+    /// Find and remove orphaned artifacts
+    ///
+    /// With `dry_run` set, nothing is deleted and the report lists what would be removed.
+    pub fn collect_garbage(&self, dry_run: bool) -> Result<GcReport, MemoryError> {
+        let _change = if dry_run { None } else { Some(self.begin_change()?) };
+        let artifacts = self.find_orphans()?;
+        let reclaimed_bytes = artifacts.iter().map(|a| a.bytes).sum();
+
+        if !dry_run {
+            for artifact in &artifacts {
+                if artifact.path.is_dir() {
+                    fs::remove_dir_all(&artifact.path)?;
+                } else {
+                    fs::remove_file(&artifact.path)?;
+                }
+            }
+            self.invalidate_attachment_text();
+        }
+
+        Ok(GcReport {
+            dry_run,
+            artifacts,
+            reclaimed_bytes,
+        })
+    }
+}
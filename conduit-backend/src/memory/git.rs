@@ -0,0 +1,283 @@
+use std::fs;
+use std::process::Command;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::attachments::ATTACHMENTS_DIR;
+use super::{is_valid_id, Memory, MemoryError, MemoryMarkdown, MemoryStore};
+
+/// Files of the store that are rebuilt, local to the machine or secret, and never committed
+const GIT_IGNORED: &[&str] = &[
+    ".trash/",
+    ".conflicts/",
+    ".embeddings/",
+    ".attachment-text/",
+    ".conduit-store",
+    ".conduit-lock",
+    ".conduit-instance.json",
+    ".conduit-instance.json.tmp",
+    ".webhooks.json",
+    ".webhooks.json.tmp",
+    ".snapshots/",
+    ".service-tokens.json",
+    ".service-tokens.json.tmp",
+    ".usage.json",
+    ".stats.json",
+    ".workspace-lock.json",
+    ".accounts/",
+    "*.tmp",
+    ".*.rename-tmp",
+];
+
+/// Marks the lines of `.git/info/exclude` listing memories of collections that are not synced
+const LOCAL_ONLY_START: &str = "# Conduit: members of local-only collections";
+const LOCAL_ONLY_END: &str = "# Conduit: end of local-only collections";
+
+/// Identity used for commits when git has none configured
+const GIT_USER_NAME: &str = "Conduit";
+const GIT_USER_EMAIL: &str = "conduit@localhost";
+
+/// Separates the fields of a `git log` line
+const FIELD_SEPARATOR: char = '\u{1f}';
+
+/// A commit that changed a memory
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct GitCommit {
+    pub hash: String,
+    pub author: String,
+    pub date: DateTime<Utc>,
+    pub message: String,
+}
+
+// An ignore pattern matching exactly one path, relative to the store
+fn literal_pattern(path: &str) -> String {
+    let mut pattern = String::from("/");
+    for c in path.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern
+}
+
+// Whether a string can only be a commit hash, so it is never read as an option
+fn is_commit_hash(commit: &str) -> bool {
+    (4..=64).contains(&commit.len()) && commit.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+impl MemoryStore {
+    /// Whether changes are committed to a git repository in the store directory
+    pub fn is_git_enabled(&self) -> bool {
+        self.options.git
+    }
+
+    pub(super) fn check_git(&self) -> Result<(), MemoryError> {
+        if self.is_git_enabled() {
+            Ok(())
+        } else {
+            Err(MemoryError::GitDisabled)
+        }
+    }
+
+    // Run git in the store directory and return its output
+    pub(super) fn git(&self, args: &[&str]) -> Result<String, MemoryError> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(&self.base_path)
+            .args(args)
+            // Fail instead of waiting for credentials nobody can type
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .output()
+            .map_err(|e| MemoryError::Git(format!("Failed to run git: {}", e)))?;
+
+        if !output.status.success() {
+            return Err(MemoryError::Git(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Turn the store directory into a git repository, if it is not one yet
+    ///
+    /// Files that are rebuilt, machine-specific or secret are ignored, and
+    /// memories already in the store are committed.
+    pub(super) fn init_git(&self) -> Result<(), MemoryError> {
+        let _guard = self.git_lock.lock().unwrap_or_else(|e| e.into_inner());
+
+        if !self.base_path.join(".git").exists() {
+            tracing::debug!("Initializing git repository in {:?}", self.base_path);
+            self.git(&["init", "--quiet"])?;
+        }
+
+        let gitignore = self.base_path.join(".gitignore");
+        let existing = fs::read_to_string(&gitignore).unwrap_or_default();
+        let missing: Vec<&str> = GIT_IGNORED.iter()
+            .copied()
+            .filter(|pattern| !existing.lines().any(|line| line.trim() == *pattern))
+            .collect();
+        if !missing.is_empty() {
+            let separator = if existing.is_empty() || existing.ends_with('\n') { "" } else { "\n" };
+            fs::write(&gitignore, format!("{}{}{}\n", existing, separator, missing.join("\n")))?;
+        }
+
+        // Commits need an identity; fall back to one for the store only
+        if self.git(&["config", "user.email"]).is_err() {
+            self.git(&["config", "user.name", GIT_USER_NAME])?;
+            self.git(&["config", "user.email", GIT_USER_EMAIL])?;
+        }
+
+        self.commit_all("Initialize Conduit memory store")
+    }
+
+    // Keep the members of collections that are not synced, and memories
+    // outside the sync scope, out of the repository
+    //
+    // They are listed in the repository's own exclude file, which is never
+    // pushed, and removed from the index if an earlier commit included them.
+    fn exclude_local_only(&self) -> Result<(), MemoryError> {
+        let mut paths = Vec::new();
+        for id in self.local_only_memories()? {
+            if let Some(path) = self.find_memory_path(&id)? {
+                if let Ok(relative) = path.strip_prefix(&self.base_path) {
+                    paths.push(relative.to_string_lossy().replace('\\', "/"));
+                }
+            }
+            paths.push(format!("{}/{}", ATTACHMENTS_DIR, id));
+        }
+
+        let exclude = self.base_path.join(".git").join("info").join("exclude");
+        let existing = fs::read_to_string(&exclude).unwrap_or_default();
+        let mut updated = String::new();
+        let mut in_block = false;
+        for line in existing.lines() {
+            match line {
+                LOCAL_ONLY_START => in_block = true,
+                LOCAL_ONLY_END => in_block = false,
+                line if !in_block => {
+                    updated.push_str(line);
+                    updated.push('\n');
+                }
+                _ => {}
+            }
+        }
+        if !paths.is_empty() {
+            updated.push_str(LOCAL_ONLY_START);
+            updated.push('\n');
+            for path in &paths {
+                updated.push_str(&literal_pattern(path));
+                updated.push('\n');
+            }
+            updated.push_str(LOCAL_ONLY_END);
+            updated.push('\n');
+        }
+        if updated != existing {
+            if let Some(dir) = exclude.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(&exclude, updated)?;
+        }
+
+        if !paths.is_empty() {
+            let pathspecs: Vec<String> = paths.iter().map(|path| format!(":(literal){}", path)).collect();
+            let mut args = vec!["rm", "-r", "--cached", "--quiet", "--ignore-unmatch", "--"];
+            args.extend(pathspecs.iter().map(String::as_str));
+            self.git(&args)?;
+        }
+        Ok(())
+    }
+
+    // Stage every change in the store and commit it, if there is any
+    pub(super) fn commit_all(&self, message: &str) -> Result<(), MemoryError> {
+        self.exclude_local_only()?;
+        self.git(&["add", "--all"])?;
+        if self.git(&["diff", "--cached", "--quiet"]).is_ok() {
+            return Ok(());
+        }
+        self.git(&["commit", "--quiet", "--message", message])?;
+        Ok(())
+    }
+
+    /// Commit the store after a change, when git mode is enabled
+    ///
+    /// A failed commit never fails the change itself, which is already on
+    /// disk; it is logged and the change is included in the next commit.
+    pub(super) fn git_commit(&self, message: &str) {
+        if !self.is_git_enabled() {
+            return;
+        }
+        let _guard = self.git_lock.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = self.commit_all(message) {
+            tracing::debug!("Failed to commit \"{}\": {}", message, e);
+        }
+    }
+
+    /// Commits that changed a memory, newest first
+    ///
+    /// Memories that were deleted or moved to another collection are included.
+    pub fn memory_history(&self, id: &str) -> Result<Vec<GitCommit>, MemoryError> {
+        self.check_git()?;
+        if !is_valid_id(id) {
+            return Err(MemoryError::InvalidId(id.to_string()));
+        }
+
+        let format = format!("--format=%H{0}%an{0}%aI{0}%s", FIELD_SEPARATOR);
+        let pathspec = format!(":(glob)**/{}.md", id);
+        let log = self.git(&["log", &format, "--", &pathspec])?;
+
+        let commits: Vec<GitCommit> = log.lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(4, FIELD_SEPARATOR);
+                Some(GitCommit {
+                    hash: fields.next()?.to_string(),
+                    author: fields.next()?.to_string(),
+                    date: DateTime::parse_from_rfc3339(fields.next()?).ok()?.with_timezone(&Utc),
+                    message: fields.next()?.to_string(),
+                })
+            })
+            .collect();
+
+        if commits.is_empty() {
+            return Err(MemoryError::NotFound(id.to_string()));
+        }
+        Ok(commits)
+    }
+
+    /// Bring a memory back to how it was at `commit`, as a new commit
+    ///
+    /// A deleted memory is recreated in the collection it had at that commit.
+    pub fn revert_memory(&self, id: &str, commit: &str) -> Result<Memory, MemoryError> {
+        self.check_git()?;
+        let _change = self.begin_change()?;
+        if !is_valid_id(id) {
+            return Err(MemoryError::InvalidId(id.to_string()));
+        }
+        if !is_commit_hash(commit) {
+            return Err(MemoryError::NotFound(format!("{}@{}", id, commit)));
+        }
+
+        let not_found = || MemoryError::NotFound(format!("{}@{}", id, commit));
+        // `ls-tree` takes literal paths only, so the memory is looked up in the full listing
+        let file_name = format!("{}.md", id);
+        let path = self.git(&["ls-tree", "-r", "--name-only", commit])
+            .map_err(|_| not_found())?
+            .lines()
+            .find(|path| path.rsplit('/').next() == Some(file_name.as_str()))
+            .map(str::to_string)
+            .ok_or_else(not_found)?;
+        let markdown = self.git(&["show", &format!("{}:{}", commit, path)]).map_err(|_| not_found())?;
+
+        let mut memory = Memory::from_markdown(&markdown)?;
+        memory.id = id.to_string();
+        memory.collection = match self.find_memory_path(id)? {
+            Some(current) => self.collection_for(&current),
+            None => self.collection_for(&self.base_path.join(&path)),
+        };
+        memory.read_only = false;
+        memory.updated_at = Utc::now();
+
+        let short = &commit[..commit.len().min(7)];
+        self.save_as(&memory, Some(&format!("Revert memory: {} ({}) to {}", memory.title, id, short)))?;
+        Ok(memory)
+    }
+}
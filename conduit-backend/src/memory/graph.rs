@@ -0,0 +1,207 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use super::{has_any_tag, MemoryAccess, MemoryError, MemoryStore, Permission, Principal};
+
+/// Filters for building the memory graph
+#[derive(Debug, Clone, Default, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GraphOptions {
+    /// Only include memories with this tag (case-insensitive)
+    pub tag: Option<String>,
+    /// Only include memories updated at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only include memories updated at or before this time
+    pub until: Option<DateTime<Utc>>,
+    /// Limit the graph to the neighborhood of this memory
+    pub center: Option<String>,
+    /// Number of hops from `center` to include, defaults to 1
+    pub depth: Option<usize>,
+    /// Connect memories that share tags, defaults to true
+    pub tag_edges: Option<bool>,
+    /// Include external references as nodes, defaults to true
+    pub references: Option<bool>,
+    /// Only include memories this user can read; set by the server, never from the query
+    #[serde(skip)]
+    pub visible_to: Option<Principal>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphNodeKind {
+    #[default]
+    Memory,
+    /// A ticket, pull request or document referenced by a memory
+    Reference,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct GraphNode {
+    /// Memory ID, or `ref:<url or path>` for reference nodes
+    pub id: String,
+    #[serde(default)]
+    pub kind: GraphNodeKind,
+    pub title: String,
+    pub tags: Vec<String>,
+    pub collection: Option<String>,
+    /// Number of edges touching this node
+    pub degree: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphEdgeKind {
+    /// A wiki-link from `source` to `target`
+    Link,
+    /// `source` and `target` share one or more tags
+    Tag,
+    /// A reference from the `source` memory to an external `target`
+    Reference,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    pub kind: GraphEdgeKind,
+    /// Shared tags, for tag edges
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+/// Memories as nodes, connected by wiki-links, shared tags and external references
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Graph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+impl MemoryStore {
+    /// Build the graph of memories and the links and tags between them
+    pub fn graph(&self, options: &GraphOptions) -> Result<Graph, MemoryError> {
+        if let Some(center) = &options.center {
+            // Fail early on an unknown center rather than returning an empty graph
+            self.authorize(center, options.visible_to.as_ref(), Permission::Read)?;
+        }
+
+        let synonyms = options.tag.as_ref().map(|tag| self.tag_synonyms(tag)).transpose()?;
+        let memories: Vec<_> = self.list()?
+            .into_iter()
+            .filter(|memory| {
+                memory.is_visible_to(options.visible_to.as_ref())
+                    && synonyms.as_ref().is_none_or(|synonyms| has_any_tag(memory, synonyms))
+                    && options.since.is_none_or(|since| memory.updated_at >= since)
+                    && options.until.is_none_or(|until| memory.updated_at <= until)
+            })
+            .collect();
+        let ids: HashSet<&str> = memories.iter().map(|memory| memory.id.as_str()).collect();
+
+        let mut edges: Vec<GraphEdge> = self.link_index()?
+            .edges()
+            .into_iter()
+            .filter(|(source, target)| ids.contains(source.as_str()) && ids.contains(target.as_str()))
+            .map(|(source, target)| GraphEdge { source, target, kind: GraphEdgeKind::Link, tags: Vec::new() })
+            .collect();
+
+        if options.tag_edges.unwrap_or(true) {
+            // Group memories by tag, then connect every pair within a group
+            let mut by_tag: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+            for memory in &memories {
+                for tag in memory.tags.iter().filter(|tag| !tag.is_empty()) {
+                    by_tag.entry(tag.to_lowercase()).or_default().push(&memory.id);
+                }
+            }
+
+            let mut shared: BTreeMap<(&str, &str), Vec<String>> = BTreeMap::new();
+            for (tag, members) in &by_tag {
+                for (i, a) in members.iter().enumerate() {
+                    for b in &members[i + 1..] {
+                        let pair = if a < b { (*a, *b) } else { (*b, *a) };
+                        shared.entry(pair).or_default().push(tag.clone());
+                    }
+                }
+            }
+
+            edges.extend(shared.into_iter().map(|((source, target), tags)| GraphEdge {
+                source: source.to_string(),
+                target: target.to_string(),
+                kind: GraphEdgeKind::Tag,
+                tags,
+            }));
+        }
+
+        // External references become nodes shared by every memory pointing at them
+        let mut references: BTreeMap<String, (String, String)> = BTreeMap::new();
+        if options.references.unwrap_or(true) {
+            for memory in &memories {
+                for reference in &memory.references {
+                    let id = format!("ref:{}", reference.target());
+                    let title = reference.label.clone().unwrap_or_else(|| reference.target().to_string());
+                    references.entry(id.clone()).or_insert((title, reference.kind.clone()));
+                    edges.push(GraphEdge { source: memory.id.clone(), target: id, kind: GraphEdgeKind::Reference, tags: Vec::new() });
+                }
+            }
+        }
+
+        // Keep only nodes within `depth` hops of the center, treating edges as undirected
+        let included: Option<HashSet<String>> = options.center.as_ref().map(|center| {
+            let mut neighbors: HashMap<&str, Vec<&str>> = HashMap::new();
+            for edge in &edges {
+                neighbors.entry(&edge.source).or_default().push(&edge.target);
+                neighbors.entry(&edge.target).or_default().push(&edge.source);
+            }
+
+            let depth = options.depth.unwrap_or(1);
+            let mut seen = HashSet::from([center.clone()]);
+            let mut queue = VecDeque::from([(center.as_str(), 0)]);
+            while let Some((id, distance)) = queue.pop_front() {
+                if distance == depth {
+                    continue;
+                }
+                for next in neighbors.get(id).into_iter().flatten() {
+                    if seen.insert(next.to_string()) {
+                        queue.push_back((next, distance + 1));
+                    }
+                }
+            }
+            seen
+        });
+
+        if let Some(included) = &included {
+            edges.retain(|edge| included.contains(&edge.source) && included.contains(&edge.target));
+        }
+
+        let mut degrees: HashMap<&str, usize> = HashMap::new();
+        for edge in &edges {
+            *degrees.entry(&edge.source).or_default() += 1;
+            *degrees.entry(&edge.target).or_default() += 1;
+        }
+
+        let mut nodes: Vec<GraphNode> = memories.iter()
+            .filter(|memory| included.as_ref().is_none_or(|included| included.contains(&memory.id)))
+            .map(|memory| GraphNode {
+                id: memory.id.clone(),
+                kind: GraphNodeKind::Memory,
+                title: memory.title.clone(),
+                tags: memory.tags.clone(),
+                collection: memory.collection.clone(),
+                degree: degrees.get(memory.id.as_str()).copied().unwrap_or(0),
+            })
+            .collect();
+        nodes.extend(references.into_iter()
+            .filter(|(id, _)| included.as_ref().is_none_or(|included| included.contains(id)))
+            .map(|(id, (title, kind))| GraphNode {
+                degree: degrees.get(id.as_str()).copied().unwrap_or(0),
+                id,
+                kind: GraphNodeKind::Reference,
+                title,
+                tags: vec![kind],
+                collection: None,
+            }));
+        nodes.sort_by(|a, b| a.id.cmp(&b.id));
+
+        Ok(Graph { nodes, edges })
+    }
+}
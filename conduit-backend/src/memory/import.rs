@@ -0,0 +1,546 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Seek};
+use std::path::{Component, Path, PathBuf};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+use zip::ZipArchive;
+
+use super::attachments::ATTACHMENTS_DIR;
+use super::external::{file_times, is_markdown, markdown_files, title_for};
+use super::{is_valid_id, Memory, MemoryError, MemoryMarkdown, MemoryStore};
+
+/// How an imported memory that matches an existing one is handled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStrategy {
+    /// Leave the existing memory alone and drop the imported one
+    #[default]
+    Skip,
+    /// Replace the title, content, tags and references of the existing memory
+    Overwrite,
+    /// Keep the existing memory and add the imported one under a new ID
+    Duplicate,
+    /// Add the imported tags and references to the existing memory, and its
+    /// content unless the existing memory already contains it
+    Merge,
+}
+
+impl std::str::FromStr for ImportStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "skip" => Ok(Self::Skip),
+            "overwrite" => Ok(Self::Overwrite),
+            "duplicate" => Ok(Self::Duplicate),
+            "merge" => Ok(Self::Merge),
+            other => Err(format!("Unknown import strategy: {}", other)),
+        }
+    }
+}
+
+/// Settings for one import
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct ImportJob {
+    /// Applied to every imported memory that matches an existing one
+    pub strategy: ImportStrategy,
+    /// When set, nothing is written and the report lists what would be done
+    pub dry_run: bool,
+}
+
+/// Why an imported memory was taken for an existing one, checked in this order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMatch {
+    /// Same ID
+    Id,
+    /// Same title, ignoring case and surrounding whitespace
+    Title,
+    /// Same content, ignoring surrounding whitespace
+    Content,
+}
+
+/// What was done with an imported memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportAction {
+    Created,
+    Skipped,
+    Overwritten,
+    Duplicated,
+    Merged,
+    Failed,
+}
+
+/// An entry of the import report
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ImportedItem {
+    /// Where the memory came from, such as a file name
+    pub source: String,
+    pub title: String,
+    pub action: ImportAction,
+    /// ID of the memory holding the import; unset when it was skipped or failed
+    pub id: Option<String>,
+    /// How the imported memory matched an existing one, if it did
+    pub matched: Option<ImportMatch>,
+    /// ID of the existing memory it matched
+    pub existing_id: Option<String>,
+    /// Attachments added to the memory, or that would be in a dry run
+    pub attachments: Vec<String>,
+    /// Name of the collection the memory was added to, such as the notebook it came from
+    pub collection: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Outcome of an import
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ImportReport {
+    pub strategy: ImportStrategy,
+    /// When set, nothing was written and the report lists what would be done
+    pub dry_run: bool,
+    pub items: Vec<ImportedItem>,
+    pub completed_at: DateTime<Utc>,
+}
+
+impl ImportReport {
+    /// Number of imported memories that ended with `action`
+    pub fn count(&self, action: ImportAction) -> usize {
+        self.items.iter().filter(|item| item.action == action).count()
+    }
+}
+
+fn title_key(title: &str) -> String {
+    title.trim().to_lowercase()
+}
+
+fn content_hash(content: &str) -> String {
+    hex::encode(Sha256::digest(content.trim().as_bytes()))
+}
+
+// The store's memories by ID, title and content, kept up to date as the import adds to it
+#[derive(Default)]
+struct MatchIndex {
+    ids: HashMap<String, Memory>,
+    titles: HashMap<String, String>,
+    hashes: HashMap<String, String>,
+}
+
+impl MatchIndex {
+    fn insert(&mut self, memory: Memory) {
+        self.titles.entry(title_key(&memory.title)).or_insert_with(|| memory.id.clone());
+        self.hashes.entry(content_hash(&memory.content)).or_insert_with(|| memory.id.clone());
+        self.ids.insert(memory.id.clone(), memory);
+    }
+
+    fn find(&self, memory: &Memory) -> Option<(ImportMatch, &Memory)> {
+        let by_key = |key: Option<&String>, kind| Some((kind, self.ids.get(key?)?));
+        self.ids.get(&memory.id).map(|existing| (ImportMatch::Id, existing))
+            .or_else(|| by_key(self.titles.get(&title_key(&memory.title)), ImportMatch::Title))
+            .or_else(|| by_key(self.hashes.get(&content_hash(&memory.content)), ImportMatch::Content))
+    }
+}
+
+// The existing memory with the imported tags, references and content added
+pub(super) fn merge(existing: &Memory, imported: &Memory) -> Memory {
+    let mut merged = existing.clone();
+    for tag in &imported.tags {
+        if !merged.tags.contains(tag) {
+            merged.tags.push(tag.clone());
+        }
+    }
+    for reference in &imported.references {
+        if !merged.references.contains(reference) {
+            merged.references.push(reference.clone());
+        }
+    }
+    let content = imported.content.trim();
+    if !content.is_empty() && !merged.content.contains(content) {
+        merged.content = format!("{}\n\n{}", merged.content.trim_end(), content);
+    }
+    merged
+}
+
+impl MemoryStore {
+    /// Add memories from another source to the store
+    ///
+    /// Each memory is given as its source, such as a file name, and the
+    /// memory read from it. A memory matching an existing one by ID, title or
+    /// content is handled with the job's strategy; the others are created,
+    /// keeping their ID when it is valid and free. Memories earlier in the list
+    /// count as existing for later ones. Attachments are not copied; importers
+    /// add them to the reported IDs afterwards. A memory that cannot be saved
+    /// is reported as failed without stopping the import.
+    pub fn import_memories(&self, memories: Vec<(String, Memory)>, job: &ImportJob) -> Result<ImportReport, MemoryError> {
+        let documents = memories.into_iter().map(|(source, memory)| (source, Ok(memory))).collect();
+        self.import_documents(documents, job)
+    }
+
+    // Import memories read from a source, reporting those that could not be read as failed
+    pub(crate) fn import_documents(&self, documents: Vec<(String, Result<Memory, String>)>, job: &ImportJob) -> Result<ImportReport, MemoryError> {
+        let _change = if job.dry_run { None } else { Some(self.begin_change()?) };
+        self.check_available()?;
+
+        let mut index = MatchIndex::default();
+        for memory in self.list()? {
+            index.insert(memory);
+        }
+
+        let mut items = Vec::new();
+        for (source, memory) in documents {
+            let mut memory = match memory {
+                Ok(memory) => memory,
+                Err(error) => {
+                    tracing::debug!("Failed to read {} for import: {}", source, error);
+                    items.push(ImportedItem {
+                        source,
+                        title: String::new(),
+                        action: ImportAction::Failed,
+                        id: None,
+                        matched: None,
+                        existing_id: None,
+                        attachments: Vec::new(),
+                        collection: None,
+                        error: Some(error),
+                    });
+                    continue;
+                }
+            };
+            memory.read_only = false;
+            memory.attachments.clear();
+            let title = memory.title.clone();
+            let matched = index.find(&memory).map(|(kind, existing)| (kind, existing.clone()));
+
+            let planned = match (&matched, job.strategy) {
+                (None, _) => {
+                    if !is_valid_id(&memory.id) || self.get(&memory.id).is_ok() {
+                        memory.id = self.generate_id()?;
+                    }
+                    Some((ImportAction::Created, memory))
+                }
+                (Some(_), ImportStrategy::Skip) => None,
+                (Some((_, existing)), ImportStrategy::Overwrite) => {
+                    let mut replaced = existing.clone();
+                    replaced.title = memory.title;
+                    replaced.content = memory.content;
+                    replaced.tags = memory.tags;
+                    replaced.references = memory.references;
+                    replaced.updated_at = Utc::now();
+                    Some((ImportAction::Overwritten, replaced))
+                }
+                (Some(_), ImportStrategy::Duplicate) => {
+                    memory.id = self.generate_id()?;
+                    Some((ImportAction::Duplicated, memory))
+                }
+                (Some((_, existing)), ImportStrategy::Merge) => {
+                    let mut merged = merge(existing, &memory);
+                    if merged.to_markdown() != existing.to_markdown() {
+                        merged.updated_at = Utc::now();
+                    }
+                    Some((ImportAction::Merged, merged))
+                }
+            };
+
+            let (kind, existing_id) = match matched {
+                Some((kind, existing)) => (Some(kind), Some(existing.id)),
+                None => (None, None),
+            };
+            let mut item = ImportedItem {
+                source,
+                title,
+                action: ImportAction::Skipped,
+                id: None,
+                matched: kind,
+                existing_id,
+                attachments: Vec::new(),
+                collection: None,
+                error: None,
+            };
+            if let Some((action, memory)) = planned {
+                let saved = if job.dry_run { Ok(()) } else { self.save(&memory) };
+                match saved {
+                    Ok(()) => {
+                        item.action = action;
+                        item.id = Some(memory.id.clone());
+                        index.insert(memory);
+                    }
+                    Err(err) => {
+                        tracing::debug!("Failed to import {}: {:?}", item.source, err);
+                        item.action = ImportAction::Failed;
+                        item.error = Some(err.to_string());
+                    }
+                }
+            }
+            items.push(item);
+        }
+
+        let report = ImportReport {
+            strategy: job.strategy,
+            dry_run: job.dry_run,
+            items,
+            completed_at: Utc::now(),
+        };
+        tracing::debug!(
+            "Imported {} memories: {} created, {} skipped, {} failed",
+            report.items.len(),
+            report.count(ImportAction::Created),
+            report.count(ImportAction::Skipped),
+            report.count(ImportAction::Failed),
+        );
+        Ok(report)
+    }
+}
+
+// Where an attachment being imported is read from
+enum AttachmentData {
+    File(PathBuf),
+    Bytes(Vec<u8>),
+}
+
+impl AttachmentData {
+    fn read(&self) -> std::io::Result<Vec<u8>> {
+        match self {
+            Self::File(path) => fs::read(path),
+            Self::Bytes(data) => Ok(data.clone()),
+        }
+    }
+}
+
+// The markdown files and attachments of a folder or zip being imported
+#[derive(Default)]
+struct ImportSource {
+    documents: Vec<(String, Result<Memory, String>)>,
+    /// Attachments by the ID of the memory they belong to in the source
+    attachments: HashMap<String, Vec<(String, AttachmentData)>>,
+}
+
+// Memory ID and file name of a path of the form `attachments/<id>/<name>`
+fn attachment_owner(relative: &Path) -> Option<(String, String)> {
+    let mut parts = relative.components().map(|component| match component {
+        Component::Normal(part) => part.to_str(),
+        _ => None,
+    });
+    match (parts.next()??, parts.next()??, parts.next()??, parts.next()) {
+        (ATTACHMENTS_DIR, id, name, None) => Some((id.to_string(), name.to_string())),
+        _ => None,
+    }
+}
+
+fn source_name(relative: &Path) -> String {
+    relative.to_string_lossy().replace('\\', "/")
+}
+
+// A memory from a markdown file being imported
+//
+// Files with memory frontmatter keep their ID and metadata. Plain markdown
+// gets a title from its first heading or file name, and an ID from the store.
+// In a recursive store, the file's folder becomes its collection.
+fn read_document(relative: &Path, data: Vec<u8>, times: (DateTime<Utc>, DateTime<Utc>), recursive: bool) -> Result<Memory, String> {
+    let content = String::from_utf8(data).map_err(|_| "File is not valid UTF-8".to_string())?;
+    let mut memory = match Memory::from_markdown(&content) {
+        Ok(memory) => memory,
+        Err(_) => {
+            let mut memory = Memory::new(title_for(&content, relative), content, Vec::new());
+            memory.id = String::new();
+            (memory.created_at, memory.updated_at) = times;
+            memory
+        }
+    };
+    memory.tags.retain(|tag| !tag.is_empty());
+    if recursive {
+        memory.collection = relative.parent()
+            .map(source_name)
+            .filter(|folder| !folder.is_empty());
+    }
+    Ok(memory)
+}
+
+/// Most bytes an imported zip may unpack to, counting the zips inside it
+pub(crate) const MAX_ZIP_BYTES: u64 = 1 << 30;
+
+/// How many levels deep zips inside an imported zip are read
+pub(crate) const MAX_ZIP_DEPTH: usize = 2;
+
+/// Bytes still allowed out of an imported zip, so a small upload that unpacks
+/// to gigabytes is refused instead of filling memory
+pub(crate) struct ZipBudget {
+    remaining: u64,
+}
+
+impl ZipBudget {
+    pub(crate) fn new() -> Self {
+        Self { remaining: MAX_ZIP_BYTES }
+    }
+
+    /// Read a zip entry, failing with [`MemoryError::InvalidFormat`] once the
+    /// archive has unpacked to more than [`MAX_ZIP_BYTES`]
+    pub(crate) fn read(&mut self, entry: impl Read) -> Result<Vec<u8>, MemoryError> {
+        let mut data = Vec::new();
+        entry.take(self.remaining + 1).read_to_end(&mut data)?;
+        if data.len() as u64 > self.remaining {
+            return Err(MemoryError::InvalidFormat(format!("Zip archive unpacks to more than {} MiB", MAX_ZIP_BYTES >> 20)));
+        }
+        self.remaining -= data.len() as u64;
+        Ok(data)
+    }
+}
+
+// Time a zip entry was last changed; zips do not record a time zone, so it is taken as UTC
+pub(crate) fn zip_time(time: Option<zip::DateTime>) -> DateTime<Utc> {
+    time.and_then(|time| {
+        NaiveDate::from_ymd_opt(time.year().into(), time.month().into(), time.day().into())?
+            .and_hms_opt(time.hour().into(), time.minute().into(), time.second().into())
+    })
+    .map(|time| time.and_utc())
+    .unwrap_or_else(Utc::now)
+}
+
+impl MemoryStore {
+    /// The full path of a folder to import from, which may not overlap the store
+    pub(crate) fn check_import_dir(&self, dir: &Path) -> Result<PathBuf, MemoryError> {
+        if !dir.is_dir() {
+            return Err(MemoryError::InvalidImportPath(format!("{} is not a directory", dir.display())));
+        }
+        let root = dir.canonicalize()?;
+        let store = self.base_path.canonicalize()?;
+        if root.starts_with(&store) || store.starts_with(&root) {
+            return Err(MemoryError::InvalidImportPath(format!("{} overlaps the store", dir.display())));
+        }
+        Ok(root)
+    }
+
+    /// Import the markdown files of a folder and its subfolders
+    ///
+    /// Files with memory frontmatter keep their ID and metadata, and files
+    /// without it get a title from their first heading or file name and a new
+    /// ID. Files under `attachments/<id>/` are attached to the memory that had
+    /// that ID, as laid out by the zip export. Files that cannot be read are
+    /// reported as failed. The folder may not overlap the store.
+    pub fn import_dir(&self, dir: &Path, job: &ImportJob) -> Result<ImportReport, MemoryError> {
+        self.check_available()?;
+        let root = self.check_import_dir(dir)?;
+
+        let mut source = ImportSource::default();
+        let mut files = markdown_files(&root);
+        files.sort();
+        for path in files {
+            let Ok(relative) = path.strip_prefix(&root) else {
+                continue;
+            };
+            if relative.starts_with(ATTACHMENTS_DIR) {
+                continue;
+            }
+            let document = fs::read(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|data| read_document(relative, data, file_times(&path), self.options.recursive));
+            source.documents.push((source_name(relative), document));
+        }
+
+        let attachments = root.join(ATTACHMENTS_DIR);
+        for folder in fs::read_dir(&attachments).into_iter().flatten().flatten() {
+            for file in fs::read_dir(folder.path()).into_iter().flatten().flatten() {
+                let path = file.path();
+                let relative = path.strip_prefix(&root).unwrap_or(&path);
+                if let Some((id, name)) = attachment_owner(relative).filter(|_| path.is_file()) {
+                    source.attachments.entry(id).or_default().push((name, AttachmentData::File(path)));
+                }
+            }
+        }
+
+        self.import_source(source, job)
+    }
+
+    /// Import the markdown files and attachments of a zip, such as one written by
+    /// [`MemoryStore::export_zip`]
+    ///
+    /// Entries are read like the files of [`MemoryStore::import_dir`]. Other
+    /// files, including the `index.json` manifest, are ignored.
+    pub fn import_zip<R: Read + Seek>(&self, reader: R, job: &ImportJob) -> Result<ImportReport, MemoryError> {
+        self.check_available()?;
+        let mut archive = ZipArchive::new(reader)
+            .map_err(|e| MemoryError::InvalidFormat(format!("Not a zip archive: {}", e)))?;
+
+        let mut source = ImportSource::default();
+        let mut budget = ZipBudget::new();
+        for index in 0..archive.len() {
+            let mut entry = match archive.by_index(index) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    source.documents.push((format!("entry {}", index), Err(e.to_string())));
+                    continue;
+                }
+            };
+            if entry.is_dir() {
+                continue;
+            }
+            let Some(relative) = entry.enclosed_name() else {
+                source.documents.push((entry.name().to_string(), Err("Path leaves the archive".to_string())));
+                continue;
+            };
+            // Folders such as `__MACOSX` and `.git` hold metadata, not notes
+            let hidden = relative.components().any(|component| {
+                component.as_os_str().to_str().is_some_and(|part| part.starts_with('.') || part == "__MACOSX")
+            });
+            if hidden {
+                continue;
+            }
+
+            let attachment = attachment_owner(&relative);
+            if attachment.is_none() && !is_markdown(&relative) {
+                continue;
+            }
+            // An unreadable entry fails on its own, but a zip bomb fails the whole import
+            let read = match budget.read(&mut entry) {
+                Err(err @ MemoryError::InvalidFormat(_)) => return Err(err),
+                read => read.map_err(|e| e.to_string()),
+            };
+            match attachment {
+                Some((id, name)) => match read {
+                    Ok(data) => source.attachments.entry(id).or_default().push((name, AttachmentData::Bytes(data))),
+                    Err(error) => source.documents.push((source_name(&relative), Err(error))),
+                },
+                None => {
+                    let modified = zip_time(entry.last_modified());
+                    let document = read.and_then(|data| read_document(&relative, data, (modified, modified), self.options.recursive));
+                    source.documents.push((source_name(&relative), document));
+                }
+            }
+        }
+
+        self.import_source(source, job)
+    }
+
+    // Import the documents of a source, then add their attachments to the resulting memories
+    fn import_source(&self, mut source: ImportSource, job: &ImportJob) -> Result<ImportReport, MemoryError> {
+        let original_ids: Vec<Option<String>> = source.documents.iter()
+            .map(|(_, document)| document.as_ref().ok().map(|memory| memory.id.clone()))
+            .collect();
+        let mut report = self.import_documents(source.documents, job)?;
+
+        for (item, original) in report.items.iter_mut().zip(original_ids) {
+            let (Some(id), Some(files)) = (item.id.clone(), original.and_then(|id| source.attachments.remove(&id))) else {
+                continue;
+            };
+            for (name, data) in files {
+                if job.dry_run {
+                    item.attachments.push(name);
+                    continue;
+                }
+                match data.read().map_err(MemoryError::from).and_then(|data| self.add_attachment(&id, &name, &data)) {
+                    Ok(_) => item.attachments.push(name),
+                    Err(err) => {
+                        let message = format!("attachment {}: {}", name, err);
+                        item.error = Some(match item.error.take() {
+                            Some(error) => format!("{}; {}", error, message),
+                            None => message,
+                        });
+                    }
+                }
+            }
+        }
+        Ok(report)
+    }
+}
@@ -0,0 +1,87 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{Memory, MemoryError, MemoryStore};
+
+/// Importance of memories that were not given one, before any decay
+const DEFAULT_IMPORTANCE: f32 = 0.5;
+
+/// How memories without a set importance lose it as they go untouched
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImportanceConfig {
+    /// Days after which the importance of a memory that was neither changed
+    /// nor viewed is halved; `0` keeps it at 0.5
+    pub half_life_days: f64,
+}
+
+impl Default for ImportanceConfig {
+    fn default() -> Self {
+        Self { half_life_days: 0.0 }
+    }
+}
+
+impl ImportanceConfig {
+    /// Override settings with `CONDUIT_IMPORTANCE_HALF_LIFE_DAYS`, if set
+    pub fn apply_env(&mut self) {
+        if let Some(days) = std::env::var("CONDUIT_IMPORTANCE_HALF_LIFE_DAYS").ok().and_then(|v| v.parse().ok()) {
+            self.half_life_days = days;
+        }
+    }
+
+    /// The importance of a memory at `now`, from 0 to 1
+    ///
+    /// A set importance is used as is. Otherwise it starts at 0.5 and, with a
+    /// half-life, decays from the last time the memory was changed or viewed.
+    pub fn importance(&self, memory: &Memory, now: DateTime<Utc>) -> f32 {
+        if let Some(importance) = memory.importance {
+            return importance;
+        }
+        if self.half_life_days <= 0.0 {
+            return DEFAULT_IMPORTANCE;
+        }
+        let touched = memory.last_viewed_at.map_or(memory.updated_at, |viewed| viewed.max(memory.updated_at));
+        let age_days = (now - touched).num_seconds().max(0) as f64 / 86_400.0;
+        DEFAULT_IMPORTANCE * 0.5f64.powf(age_days / self.half_life_days) as f32
+    }
+
+    /// Factor a relevance score is multiplied by, from 0.5 for the least
+    /// important memories to 1.5 for the most, and 1 for unset ones
+    pub fn weight(&self, memory: &Memory, now: DateTime<Utc>) -> f32 {
+        0.5 + self.importance(memory, now)
+    }
+
+    /// Order memories by weight, keeping the order of equally weighted ones
+    pub fn rank(&self, memories: &mut [Memory]) {
+        let now = Utc::now();
+        memories.sort_by_cached_key(|memory| std::cmp::Reverse(ordered(self.weight(memory, now))));
+    }
+}
+
+// Weights are finite and not negative, so their bits sort like the numbers
+fn ordered(weight: f32) -> u32 {
+    weight.max(0.0).to_bits()
+}
+
+/// Check that an importance is between 0 and 1
+pub fn validate_importance(importance: Option<f32>) -> Result<(), MemoryError> {
+    match importance {
+        Some(importance) if !(0.0..=1.0).contains(&importance) => Err(MemoryError::InvalidImportance(importance)),
+        _ => Ok(()),
+    }
+}
+
+impl MemoryStore {
+    /// Set or clear the importance of a memory, without touching its `updated_at`
+    pub fn set_importance(&self, id: &str, importance: Option<f32>) -> Result<Memory, MemoryError> {
+        validate_importance(importance)?;
+        let mut memory = self.get(id)?;
+        if memory.read_only {
+            return Err(MemoryError::ReadOnly(id.to_string()));
+        }
+
+        memory.importance = importance;
+        self.save(&memory)?;
+        Ok(memory)
+    }
+}
@@ -0,0 +1,46 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{MemoryError, MemoryStore};
+
+/// How far one of the store's indexes has caught up with the memories
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct IndexState {
+    /// Documents the index holds
+    pub documents: usize,
+    /// Documents added, changed or removed since the index was last updated
+    pub pending: usize,
+    /// When the index was last updated, if it ever was
+    pub updated_at: Option<DateTime<Utc>>,
+    /// A background job is updating the index right now
+    pub rebuilding: bool,
+}
+
+/// State of each index searches and links are answered from
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct IndexStatus {
+    /// Titles and wiki-links of every memory, rebuilt on the first lookup after a change
+    pub metadata: IndexState,
+    /// Memory text, which search reads as saved, and the text extracted from attachments
+    pub full_text: IndexState,
+    /// Embeddings used for semantic search and chat context
+    pub vector: IndexState,
+}
+
+impl MemoryStore {
+    /// How far each index has caught up with the store, with embeddings for `model`
+    ///
+    /// Nothing is rebuilt; an index that is out of date reports what it is
+    /// missing as pending. Background jobs are not known to the store, so
+    /// `rebuilding` is left for the caller to fill in.
+    pub fn index_status(&self, model: &str) -> Result<IndexStatus, MemoryError> {
+        self.check_available()?;
+        let memories = self.list()?;
+        Ok(IndexStatus {
+            metadata: self.link_index_state(&memories)?,
+            full_text: self.text_index_state(&memories)?,
+            vector: self.embedding_index_state(&memories, model),
+        })
+    }
+}
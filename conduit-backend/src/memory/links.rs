@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use chrono::{DateTime, Utc};
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{IndexState, Memory, MemoryError, MemoryStore};
+
+// Matches `[[target]]`, `[[target|alias]]` and `[[target#heading|alias]]`
+fn wiki_link_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\[\[([^\[\]|#]+)(#[^\[\]|]*)?(\|[^\[\]]*)?\]\]").unwrap())
+}
+
+/// Rewrite wiki-links pointing at `old_target` to point at `new_target`,
+/// keeping any heading anchor and alias
+///
+/// Returns `None` if the content has no matching links.
+pub(crate) fn rewrite_links(content: &str, old_target: &str, new_target: &str) -> Option<String> {
+    let mut changed = false;
+    let rewritten = wiki_link_regex().replace_all(content, |caps: &Captures| {
+        if caps[1].trim() == old_target {
+            changed = true;
+            format!(
+                "[[{}{}{}]]",
+                new_target,
+                caps.get(2).map_or("", |m| m.as_str()),
+                caps.get(3).map_or("", |m| m.as_str())
+            )
+        } else {
+            caps[0].to_string()
+        }
+    });
+
+    if changed {
+        Some(rewritten.into_owned())
+    } else {
+        None
+    }
+}
+
+/// A `[[target#heading|alias]]` link found in memory content
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct WikiLink {
+    /// The linked ID or title, as written
+    pub target: String,
+    pub heading: Option<String>,
+    pub alias: Option<String>,
+}
+
+/// Extract the wiki-links of a memory's content, in order of appearance
+pub fn parse_links(content: &str) -> Vec<WikiLink> {
+    wiki_link_regex()
+        .captures_iter(content)
+        .map(|caps| WikiLink {
+            target: caps[1].trim().to_string(),
+            heading: caps.get(2).map(|m| m.as_str()[1..].trim().to_string()).filter(|h| !h.is_empty()),
+            alias: caps.get(3).map(|m| m.as_str()[1..].trim().to_string()).filter(|a| !a.is_empty()),
+        })
+        .filter(|link| !link.target.is_empty())
+        .collect()
+}
+
+/// A wiki-link along with the memory it points to
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ResolvedLink {
+    #[serde(flatten)]
+    pub link: WikiLink,
+    pub id: String,
+    pub title: String,
+}
+
+/// Links from a memory to other memories
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct OutgoingLinks {
+    pub id: String,
+    pub links: Vec<ResolvedLink>,
+    /// Links whose target matches no memory ID or title
+    pub broken: Vec<WikiLink>,
+}
+
+/// A memory that links to another one
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Backlink {
+    pub id: String,
+    pub title: String,
+    /// The links in the source memory that point at the target
+    pub links: Vec<WikiLink>,
+}
+
+/// Memories linking to a memory
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Backlinks {
+    pub id: String,
+    pub backlinks: Vec<Backlink>,
+}
+
+/// Parsed links of every memory, rebuilt whenever the store changes
+#[derive(Debug, Default)]
+pub(crate) struct LinkIndex {
+    /// `last_modified` of the store when the index was built
+    stamp: Option<DateTime<Utc>>,
+    built_at: Option<DateTime<Utc>>,
+    /// Set by a write, so the next lookup rebuilds the index
+    stale: AtomicBool,
+    titles: HashMap<String, String>,
+    ids_by_title: HashMap<String, String>,
+    outgoing: Vec<(String, Vec<WikiLink>)>,
+}
+
+impl LinkIndex {
+    fn build(memories: &[Memory], stamp: DateTime<Utc>) -> Self {
+        let mut index = LinkIndex {
+            stamp: Some(stamp),
+            built_at: Some(Utc::now()),
+            ..Default::default()
+        };
+
+        for memory in memories {
+            index.titles.insert(memory.id.clone(), memory.title.clone());
+            // The first memory with a title wins when titles are duplicated
+            index.ids_by_title.entry(memory.title.to_lowercase()).or_insert_with(|| memory.id.clone());
+            index.outgoing.push((memory.id.clone(), parse_links(&memory.content)));
+        }
+        index.outgoing.sort_by(|a, b| a.0.cmp(&b.0));
+
+        index
+    }
+
+    /// Distinct `(source, target)` pairs of links that resolve to a memory
+    pub(super) fn edges(&self) -> Vec<(String, String)> {
+        let mut edges: Vec<(String, String)> = self.outgoing.iter()
+            .flat_map(|(source, links)| {
+                links.iter()
+                    .filter_map(|link| self.resolve(&link.target))
+                    .filter(move |target| *target != source)
+                    .map(move |target| (source.clone(), target.to_string()))
+            })
+            .collect();
+        edges.sort();
+        edges.dedup();
+        edges
+    }
+
+    // IDs take precedence over titles, which match case-insensitively
+    fn resolve(&self, target: &str) -> Option<&str> {
+        if let Some((id, _)) = self.titles.get_key_value(target) {
+            return Some(id);
+        }
+        self.ids_by_title.get(&target.to_lowercase()).map(String::as_str)
+    }
+}
+
+impl MemoryStore {
+    // The link index, rebuilt if the store changed since it was last built
+    pub(super) fn link_index(&self) -> Result<Arc<LinkIndex>, MemoryError> {
+        let stamp = self.last_modified()?;
+        if let Some(index) = self.link_index.read().ok().and_then(|cached| cached.clone()) {
+            if index.stamp == Some(stamp) && !index.stale.load(Ordering::Relaxed) {
+                return Ok(index);
+            }
+        }
+
+        let index = Arc::new(LinkIndex::build(&self.list()?, stamp));
+        if let Ok(mut cached) = self.link_index.write() {
+            *cached = Some(index.clone());
+        }
+        Ok(index)
+    }
+
+    /// Read every memory and build the link index, returning how many memories there are
+    ///
+    /// Servers call this before reporting ready, so the first requests do not
+    /// wait for the store to be parsed.
+    pub fn warm_up(&self) -> Result<usize, MemoryError> {
+        Ok(self.link_index()?.titles.len())
+    }
+
+    // How far the cached index is behind `memories`, without rebuilding it
+    pub(super) fn link_index_state(&self, memories: &[Memory]) -> Result<IndexState, MemoryError> {
+        let stamp = self.last_modified()?;
+        let Some(index) = self.link_index.read().ok().and_then(|cached| cached.clone()) else {
+            return Ok(IndexState { pending: memories.len(), ..IndexState::default() });
+        };
+        let pending = if index.stamp == Some(stamp) && !index.stale.load(Ordering::Relaxed) {
+            0
+        } else {
+            let changed = memories.iter()
+                .filter(|memory| !index.titles.contains_key(&memory.id) || index.stamp.is_none_or(|stamp| memory.updated_at > stamp))
+                .count();
+            let removed = index.titles.keys().filter(|id| !memories.iter().any(|memory| &memory.id == *id)).count();
+            changed + removed
+        };
+        Ok(IndexState {
+            documents: index.titles.len(),
+            pending,
+            updated_at: index.built_at,
+            rebuilding: false,
+        })
+    }
+
+    // Mark the cached index stale after a write so the next lookup re-parses the store
+    pub(super) fn invalidate_link_index(&self) {
+        if let Some(index) = self.link_index.read().ok().and_then(|cached| cached.clone()) {
+            index.stale.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Links from a memory to other memories, with the ones that resolve to nothing
+    /// reported as broken
+    pub fn links(&self, id: &str) -> Result<OutgoingLinks, MemoryError> {
+        let memory = self.get(id)?;
+        let index = self.link_index()?;
+
+        let mut links = Vec::new();
+        let mut broken = Vec::new();
+        for link in parse_links(&memory.content) {
+            match index.resolve(&link.target) {
+                Some(target_id) => links.push(ResolvedLink {
+                    id: target_id.to_string(),
+                    title: index.titles.get(target_id).cloned().unwrap_or_default(),
+                    link,
+                }),
+                None => broken.push(link),
+            }
+        }
+
+        Ok(OutgoingLinks { id: memory.id, links, broken })
+    }
+
+    /// Memories that link to a memory by ID or title
+    pub fn backlinks(&self, id: &str) -> Result<Backlinks, MemoryError> {
+        let memory = self.get(id)?;
+        let index = self.link_index()?;
+
+        let backlinks = index.outgoing.iter()
+            .filter(|(source, _)| source != &memory.id)
+            .filter_map(|(source, links)| {
+                let links: Vec<WikiLink> = links.iter()
+                    .filter(|link| index.resolve(&link.target) == Some(memory.id.as_str()))
+                    .cloned()
+                    .collect();
+                if links.is_empty() {
+                    return None;
+                }
+                Some(Backlink {
+                    id: source.clone(),
+                    title: index.titles.get(source).cloned().unwrap_or_default(),
+                    links,
+                })
+            })
+            .collect();
+
+        Ok(Backlinks { id: memory.id, backlinks })
+    }
+}
@@ -1,11 +1,96 @@
-use std::fs::{self, File};
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 use thiserror::Error;
 
+mod acl;
+mod attachment_text;
+mod attachments;
+mod availability;
+mod backend;
+mod batch;
+mod collation;
+mod collections;
+mod conflicts;
+mod conversations;
+mod dates;
+mod demo;
+mod embeddings;
+mod events;
+mod export;
+mod external;
+mod gc;
+mod git;
+mod graph;
+mod import;
+mod importance;
+mod index_status;
+mod links;
+mod preprocess;
+mod query;
+mod references;
+mod related;
+mod setup;
+mod snapshot;
+mod snippets;
+mod sync;
+mod tag_aliases;
+mod titles;
+mod trash;
+mod workspace_lock;
+mod writer_lock;
+
+use external::ExternalSource;
+pub use acl::{filter_visible, Grant, GranteeKind, MemoryAccess, MemoryAcl, Permission, Principal};
+pub use attachments::{Attachment, AttachmentPolicy};
+pub use availability::{init_store_dir, StoreStatus};
+pub use backend::{InMemoryBackend, ReadOnlyBackend, StorageBackend};
+pub use batch::{BatchOperation, BatchReport, BatchResult};
+pub use collation::Collation;
+pub use collections::{Collection, CollectionDefaults, CollectionUpdate, MAX_RETENTION_DAYS};
+pub use conflicts::{ConflictResolution, MergedVersion, QuarantinedConflict};
+pub use conversations::{
+    Conversation, ConversationInfo, ConversationToMemoryRequest, CreateConversationRequest,
+};
+pub use dates::{find_date, find_local_date};
+pub use demo::{seed_demo_memories, DEFAULT_DEMO_SEED};
+pub use embeddings::{cosine_similarity, EmbeddingStatus, MemoryEmbedding, StoredEmbedding};
+pub use events::{EventBus, MemoryEvent, MemoryEventKind};
+pub use export::{ExportFormat, ExportManifest, ExportOptions, ExportReport, ManifestEntry, MANIFEST_NAME};
+pub use gc::{ArtifactKind, GcReport, OrphanedArtifact};
+pub use git::GitCommit;
+pub use graph::{Graph, GraphEdge, GraphEdgeKind, GraphNode, GraphNodeKind, GraphOptions};
+pub use import::{ImportAction, ImportJob, ImportMatch, ImportReport, ImportStrategy, ImportedItem};
+pub(crate) use import::{zip_time, ZipBudget, MAX_ZIP_DEPTH};
+pub use importance::{validate_importance, ImportanceConfig};
+pub use index_status::{IndexState, IndexStatus};
+pub use tag_aliases::{RetaggedMemory, TagAlias, TagMigration};
+pub use query::{Comparison, DateField, QueryError, SearchQuery, TextField};
+pub(crate) use query::QueryMatcher;
+pub use preprocess::{PreprocessConfig, PreprocessStep, Replacement};
+pub use links::{parse_links, Backlink, Backlinks, OutgoingLinks, ResolvedLink, WikiLink};
+pub use references::{validate_reference, Reference};
+pub use related::{RelatedConfig, RelatedMemory, RelatedQuery, RelatedSignals};
+pub use setup::{starter_templates, StarterTemplate};
+pub use snapshot::StoreSnapshot;
+pub use snippets::{MatchField, SearchMatch, SearchResult};
+pub use sync::{ConflictStrategy, SyncConflict, SyncReport, SyncScope, CONFLICT_TAG};
+pub use titles::{summary_from_content, title_from_content};
+pub use trash::TrashedMemory;
+pub use workspace_lock::{LockPolicy, LockStatus, WorkspaceLock};
+pub(crate) use workspace_lock::PassphraseHash;
+pub use writer_lock::{Election, InstanceInfo, WriterGuard};
+pub(crate) use writer_lock::reachable_addr;
+pub use conduit_types::Memory;
+pub use conduit_types::requests::{ListMemoriesQuery, SortField, SortOrder};
+
 #[derive(Debug, Error)]
 pub enum MemoryError {
     #[error("IO error: {0}")]
@@ -16,32 +101,136 @@ pub enum MemoryError {
     
     #[error("Invalid memory format: {0}")]
     InvalidFormat(String),
+    
+    #[error("Memory is read-only: {0}")]
+    ReadOnly(String),
+    
+    #[error("Invalid memory ID: {0}")]
+    InvalidId(String),
+    
+    #[error("Memory already exists: {0}")]
+    AlreadyExists(String),
+    
+    #[error("Invalid attachment name: {0}")]
+    InvalidAttachmentName(String),
+    
+    #[error("Attachment too large: {0}")]
+    AttachmentTooLarge(String),
+    
+    #[error("Attachment rejected: {0}")]
+    AttachmentRejected(String),
+    
+    #[error("Invalid collection: {0}")]
+    InvalidCollection(String),
+    
+    #[error("Invalid reference: {0}")]
+    InvalidReference(String),
+    
+    #[error("Invalid sharing grant: {0}")]
+    InvalidGrant(String),
+    
+    #[error("Invalid export path: {0}")]
+    InvalidExportPath(String),
+    
+    #[error("Invalid import path: {0}")]
+    InvalidImportPath(String),
+    
+    #[error("Invalid tag alias: {0}")]
+    InvalidTagAlias(String),
+    
+    #[error("Importance must be between 0 and 1: {0}")]
+    InvalidImportance(f32),
+    
+    #[error("Invalid search query: {0}")]
+    InvalidQuery(String),
+    
+    /// The caller can read the memory but not change it, or cannot change who it is shared with
+    #[error("Access denied to memory: {0}")]
+    AccessDenied(String),
+    
+    /// The store directory or its marker file is missing, e.g. on an unmounted drive
+    #[error("Memory store unavailable: {0}")]
+    StoreUnavailable(String),
+    
+    /// The store was opened in read-only mode
+    #[error("Memory store is read-only: {0}")]
+    StoreReadOnly(String),
+    
+    #[error("Git error: {0}")]
+    Git(String),
+    
+    #[error("Git mode is not enabled for this store")]
+    GitDisabled,
+    
+    #[error("Operation cancelled")]
+    Cancelled,
+    
+    /// The workspace passphrase lock is engaged
+    #[error("The workspace is locked")]
+    Locked,
+    
+    #[error("Wrong passphrase")]
+    WrongPassphrase,
+    
+    #[error("Invalid passphrase: {0}")]
+    InvalidPassphrase(String),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Memory {
-    pub id: String,
-    pub title: String,
-    pub content: String,
-    pub tags: Vec<String>,
-    pub created_at: DateTime<Utc>,
-    pub updated_at: DateTime<Utc>,
-}
+/// Flag checked by long-running store scans so abandoned requests stop early
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
 
-impl Memory {
-    pub fn new(title: String, content: String, tags: Vec<String>) -> Self {
-        let now = Utc::now();
-        Self {
-            id: Uuid::new_v4().to_string(),
-            title,
-            content,
-            tags,
-            created_at: now,
-            updated_at: now,
-        }
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
     }
     
-    pub fn to_markdown(&self) -> String {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+    
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+    
+    fn check(&self) -> Result<(), MemoryError> {
+        if self.is_cancelled() {
+            Err(MemoryError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+// Whether a memory's title, content or tags contain a query already folded with `collation`
+pub(crate) fn matches_query(memory: &Memory, query: &str, collation: &Collation) -> bool {
+    collation.fold(&memory.title).contains(query) ||
+        collation.fold(&memory.content).contains(query) ||
+        memory.tags.iter().any(|tag| collation.fold(tag).contains(query))
+}
+
+// Whether a memory has one of a list of already lowercased tags
+pub(crate) fn has_any_tag(memory: &Memory, tags: &[String]) -> bool {
+    memory.tags.iter().any(|tag| tags.contains(&tag.to_lowercase()))
+}
+
+// Parse an optional `key: value` line from the frontmatter
+fn frontmatter_field<'a>(frontmatter: &'a str, key: &str) -> Option<&'a str> {
+    frontmatter.lines()
+        .find_map(|line| line.strip_prefix(key)?.strip_prefix(": "))
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+}
+
+/// How memories are stored as markdown files with a frontmatter header
+pub trait MemoryMarkdown: Sized {
+    fn to_markdown(&self) -> String;
+
+    fn from_markdown(markdown: &str) -> Result<Self, MemoryError>;
+}
+
+impl MemoryMarkdown for Memory {
+    fn to_markdown(&self) -> String {
         let mut md = String::new();
         
         // Add YAML frontmatter
@@ -51,6 +240,25 @@ impl Memory {
         md.push_str(&format!("tags: [{}]\n", self.tags.join(", ")));
         md.push_str(&format!("created_at: {}\n", self.created_at.to_rfc3339()));
         md.push_str(&format!("updated_at: {}\n", self.updated_at.to_rfc3339()));
+        if let Some(last_viewed_at) = self.last_viewed_at {
+            md.push_str(&format!("last_viewed_at: {}\n", last_viewed_at.to_rfc3339()));
+        }
+        if let Some(remind_at) = self.remind_at {
+            md.push_str(&format!("remind_at: {}\n", remind_at.to_rfc3339()));
+        }
+        if let Some(importance) = self.importance {
+            md.push_str(&format!("importance: {}\n", importance));
+        }
+        if let Some(summary) = &self.summary {
+            md.push_str(&format!("summary: {}\n", summary));
+        }
+        if !self.attachments.is_empty() {
+            md.push_str(&format!("attachments: [{}]\n", self.attachments.join(", ")));
+        }
+        if !self.references.is_empty() {
+            md.push_str(&references::references_frontmatter(&self.references));
+        }
+        md.push_str(&acl::acl_frontmatter(self.owner.as_deref(), &self.shared));
         md.push_str("---\n\n");
         
         // Add content
@@ -59,7 +267,7 @@ impl Memory {
         md
     }
     
-    pub fn from_markdown(markdown: &str) -> Result<Self, MemoryError> {
+    fn from_markdown(markdown: &str) -> Result<Self, MemoryError> {
         let re = regex::Regex::new(r"(?s)---\n(.*?)\n---\n\n(.*)").unwrap();
         
         if let Some(captures) = re.captures(markdown) {
@@ -133,6 +341,25 @@ impl Memory {
                 }
             };
             
+            let last_viewed_at = frontmatter_field(frontmatter, "last_viewed_at")
+                .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+                .map(|dt| dt.with_timezone(&Utc));
+            let remind_at = frontmatter_field(frontmatter, "remind_at")
+                .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+                .map(|dt| dt.with_timezone(&Utc));
+            let importance = frontmatter_field(frontmatter, "importance")
+                .and_then(|s| s.parse::<f32>().ok())
+                .filter(|importance| (0.0..=1.0).contains(importance));
+            let summary = frontmatter_field(frontmatter, "summary").map(str::to_string);
+            
+            let attachments = frontmatter_field(frontmatter, "attachments")
+                .and_then(|s| s.strip_prefix('[')?.strip_suffix(']'))
+                .map(|s| s.split(',').map(|name| name.trim().to_string()).filter(|name| !name.is_empty()).collect())
+                .unwrap_or_default();
+            
+            let references = references::parse_references(frontmatter);
+            let acl = acl::parse_acl(frontmatter);
+            
             Ok(Self {
                 id,
                 title,
@@ -140,6 +367,16 @@ impl Memory {
                 tags,
                 created_at,
                 updated_at,
+                last_viewed_at,
+                remind_at,
+                importance,
+                summary,
+                collection: None,
+                read_only: false,
+                attachments,
+                references,
+                owner: acl.owner,
+                shared: acl.shared,
             })
         } else {
             Err(MemoryError::InvalidFormat("Invalid markdown format".to_string()))
@@ -147,24 +384,345 @@ impl Memory {
     }
 }
 
+/// Why a file in the store directory is not treated as a memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ForeignFileKind {
+    /// Dotfiles such as editor swap files or sync metadata
+    Hidden,
+    /// Subdirectories are not scanned
+    Directory,
+    /// Files that are not markdown
+    NotMarkdown,
+    /// Markdown files without valid memory frontmatter
+    InvalidMemory,
+    /// Symbolic links are not followed unless enabled
+    Symlink,
+}
+
+/// A file in the store directory that is not a memory
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ForeignFile {
+    #[schema(value_type = String)]
+pub path: PathBuf,
+    pub kind: ForeignFileKind,
+    pub reason: String,
+}
+
+/// Result of scanning the store directory
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StoreDiagnostics {
+    #[schema(value_type = String)]
+pub base_path: PathBuf,
+    pub memory_count: usize,
+    /// Read-only documents from external folders and followed symlinks
+    pub external_count: usize,
+    pub foreign_files: Vec<ForeignFile>,
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with('.'))
+}
+
+/// Whether `id` can be used as a memory ID, which doubles as the file name
+pub fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && !id.starts_with('.')
+        && !id.chars().any(|c| c.is_control() || matches!(c, '/' | '\\' | '<' | '>' | ':' | '"' | '|' | '?' | '*'))
+}
+
+/// Outcome of renaming a memory
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RenameResult {
+    pub memory: Memory,
+    /// IDs of memories whose links were rewritten
+    pub updated_references: Vec<String>,
+}
+
+// Only visible `.md` files are candidates for memories
+fn is_memory_file(path: &Path) -> bool {
+    path.is_file() && !is_hidden(path) && path.extension().is_some_and(|ext| ext == "md")
+}
+
+/// Paging, sorting and filtering options for listing memories
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ListOptions {
+    /// Maximum number of memories to return
+    pub limit: Option<usize>,
+    /// Number of memories to skip
+    pub offset: Option<usize>,
+    /// Sort field, defaults to `updated_at`
+    pub sort: Option<SortField>,
+    /// Sort order, defaults to descending
+    pub order: Option<SortOrder>,
+    /// Only include memories with this tag (case-insensitive); [`MemoryStore::list_paged`] also matches its aliases
+    pub tag: Option<String>,
+    /// Only include memories updated at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only include memories updated at or before this time
+    pub until: Option<DateTime<Utc>>,
+    /// Only include members of the collection with this ID, or memories stored in
+    /// the folder with this name; applied by [`MemoryStore::list_paged`]
+    pub collection: Option<String>,
+    /// Only include memories this user can read; set by the server, never from the query
+    #[serde(skip)]
+    pub visible_to: Option<Principal>,
+    /// Order of titles; [`MemoryStore::list_paged`] uses the store's when unset
+    #[serde(skip)]
+    pub collation: Option<Collation>,
+}
+
+/// A page of results along with the total number of matches
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: usize,
+}
+
+impl From<ListMemoriesQuery> for ListOptions {
+    fn from(query: ListMemoriesQuery) -> Self {
+        // Summaries only change how the listed memories are returned
+        let ListMemoriesQuery { limit, offset, sort, order, tag, since, until, collection, summaries: _ } = query;
+        Self { limit, offset, sort, order, tag, since, until, collection, visible_to: None, collation: None }
+    }
+}
+
+impl ListOptions {
+    fn matches(&self, memory: &Memory) -> bool {
+        if !memory.is_visible_to(self.visible_to.as_ref()) {
+            return false;
+        }
+        if let Some(tag) = &self.tag {
+            if !memory.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                return false;
+            }
+        }
+        if self.since.is_some_and(|since| memory.updated_at < since) {
+            return false;
+        }
+        if self.until.is_some_and(|until| memory.updated_at > until) {
+            return false;
+        }
+        true
+    }
+    
+    /// Filter, sort and paginate a set of memories
+    pub fn apply(&self, memories: Vec<Memory>) -> Page<Memory> {
+        let mut memories: Vec<Memory> = memories.into_iter()
+            .filter(|memory| self.matches(memory))
+            .collect();
+        
+        match self.sort.unwrap_or_default() {
+            SortField::UpdatedAt => memories.sort_by_key(|m| m.updated_at),
+            SortField::CreatedAt => memories.sort_by_key(|m| m.created_at),
+            SortField::Title => {
+                let collator = self.collation.clone().unwrap_or_default().collator();
+                memories.sort_by(|a, b| collator.compare(&a.title, &b.title));
+            }
+        }
+        if self.order.unwrap_or_default() == SortOrder::Desc {
+            memories.reverse();
+        }
+        
+        let total = memories.len();
+        let items = memories.into_iter()
+            .skip(self.offset.unwrap_or(0))
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect();
+        
+        Page { items, total }
+    }
+}
+
+/// How IDs are generated for new memories
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IdScheme {
+    /// Random UUID v4
+    #[default]
+    Uuid,
+    /// Lexicographically sortable ULID
+    Ulid,
+    /// Zettelkasten-style timestamp such as `202405141230`
+    Timestamp,
+}
+
+impl std::str::FromStr for IdScheme {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "uuid" => Ok(Self::Uuid),
+            "ulid" => Ok(Self::Ulid),
+            "timestamp" | "zettelkasten" => Ok(Self::Timestamp),
+            other => Err(format!("Unknown ID scheme: {}", other)),
+        }
+    }
+}
+
+/// Options controlling how the store reads and writes memories
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StoreOptions {
+    /// Scan subdirectories, exposing their relative path as the memory's collection
+    pub recursive: bool,
+    /// Additional folders whose markdown files are listed and searched but never modified
+    pub external_dirs: Vec<PathBuf>,
+    /// Include symlinked files and folders inside the store as read-only documents
+    pub follow_symlinks: bool,
+    /// ID scheme for new memories
+    pub id_scheme: IdScheme,
+    /// Days to keep deleted memories in the trash; `None` keeps them until purged
+    pub trash_retention_days: Option<u64>,
+    /// Reject every change, for stores that another process syncs or inspects
+    pub read_only: bool,
+    /// Keep the store in a git repository and commit every change
+    pub git: bool,
+    /// Rules for uploaded attachments
+    pub attachments: AttachmentPolicy,
+    /// When the passphrase lock engages on its own
+    pub lock: LockPolicy,
+    /// How titles are sorted and search text is matched
+    pub collation: Collation,
+    /// Which memories are committed in git mode, and so synced
+    pub sync_scope: SyncScope,
+    /// How memories are cleaned up each time they are saved
+    pub preprocess: PreprocessConfig,
+}
+
+// Parse a boolean variable, `None` when it is not set
+fn env_flag(name: &str) -> Option<bool> {
+    std::env::var(name)
+        .ok()
+        .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"))
+}
+
+impl StoreOptions {
+    /// Read store options from the environment (`CONDUIT_RECURSIVE_SCAN`,
+    /// `CONDUIT_EXTERNAL_DIRS` as a path list, `CONDUIT_FOLLOW_SYMLINKS`,
+    /// `CONDUIT_ID_SCHEME` as `uuid`, `ulid` or `timestamp`,
+    /// `CONDUIT_TRASH_RETENTION_DAYS`, `CONDUIT_READ_ONLY`, `CONDUIT_GIT` and
+    /// the `CONDUIT_ATTACHMENT_*` policy and `CONDUIT_SYNC_*` scope variables)
+    pub fn from_env() -> Self {
+        let mut options = Self::default();
+        options.apply_env();
+        options
+    }
+    
+    /// Override options with the store variables that are set
+    pub fn apply_env(&mut self) {
+        if let Some(recursive) = env_flag("CONDUIT_RECURSIVE_SCAN") {
+            self.recursive = recursive;
+        }
+        if let Some(dirs) = std::env::var_os("CONDUIT_EXTERNAL_DIRS") {
+            self.external_dirs = std::env::split_paths(&dirs).filter(|p| !p.as_os_str().is_empty()).collect();
+        }
+        if let Some(follow_symlinks) = env_flag("CONDUIT_FOLLOW_SYMLINKS") {
+            self.follow_symlinks = follow_symlinks;
+        }
+        if let Some(scheme) = std::env::var("CONDUIT_ID_SCHEME").ok().and_then(|scheme| scheme.parse().ok()) {
+            self.id_scheme = scheme;
+        }
+        if let Some(days) = std::env::var("CONDUIT_TRASH_RETENTION_DAYS").ok().and_then(|days| days.parse().ok()) {
+            self.trash_retention_days = Some(days);
+        }
+        if let Some(read_only) = env_flag("CONDUIT_READ_ONLY") {
+            self.read_only = read_only;
+        }
+        if let Some(git) = env_flag("CONDUIT_GIT") {
+            self.git = git;
+        }
+        self.attachments.apply_env();
+        self.lock.apply_env();
+        self.sync_scope.apply_env();
+        self.collation.apply_env();
+    }
+}
+
 pub struct MemoryStore {
     pub base_path: PathBuf,
+    pub options: StoreOptions,
+    link_index: RwLock<Option<Arc<links::LinkIndex>>>,
+    attachment_text: RwLock<Option<Arc<attachment_text::TextIndex>>>,
+    collections_lock: Mutex<()>,
+    conversations_lock: Mutex<()>,
+    tag_aliases_lock: Mutex<()>,
+    events: EventBus,
+    read_only: AtomicBool,
+    git_lock: Mutex<()>,
+    /// Timestamp IDs handed out by `generate_id` whose memory is not saved yet
+    reserved_ids: Mutex<HashSet<String>>,
+    /// Shared by changes and held exclusively while a snapshot is taken
+    snapshot_lock: RwLock<()>,
+    workspace_lock: WorkspaceLock,
+    preprocessor: preprocess::Preprocessor,
 }
 
 impl MemoryStore {
     pub fn new(base_path: impl AsRef<Path>) -> Self {
+        Self::with_options(base_path, StoreOptions::default())
+    }
+    
+    pub fn with_options(base_path: impl AsRef<Path>, options: StoreOptions) -> Self {
         let path = base_path.as_ref().to_path_buf();
         
-        // Create directory if it doesn't exist
-        if !path.exists() {
-            fs::create_dir_all(&path).expect("Failed to create memory directory");
+        // Create the directory on first use, but not below a parent that is gone,
+        // like the mount point of an unmounted drive
+        let mut created = false;
+        if !path.exists() && !options.read_only {
+            match path.parent() {
+                Some(parent) if parent.as_os_str().is_empty() || parent.exists() => {
+                    fs::create_dir(&path).expect("Failed to create memory directory");
+                    created = true;
+                }
+                _ => tracing::debug!("Parent of memory directory is missing, store unavailable: {:?}", path),
+            }
         }
         
-        let store = Self { base_path: path };
+        let store = Self {
+            events: EventBus::for_path(&path),
+            workspace_lock: WorkspaceLock::open(&path, &options.lock),
+            preprocessor: preprocess::Preprocessor::new(&options.preprocess),
+            base_path: path,
+            read_only: AtomicBool::new(options.read_only),
+            options,
+            link_index: RwLock::new(None),
+            attachment_text: RwLock::new(None),
+            collections_lock: Mutex::new(()),
+            conversations_lock: Mutex::new(()),
+            tag_aliases_lock: Mutex::new(()),
+            git_lock: Mutex::new(()),
+            reserved_ids: Mutex::new(HashSet::new()),
+            snapshot_lock: RwLock::new(()),
+        };
+        
+        // Opening a read-only store leaves every file as it is
+        if store.is_read_only() {
+            tracing::debug!("Opened memory store read-only: {:?}", store.base_path);
+            return store;
+        }
+        
+        if let Err(e) = store.write_marker(created) {
+            tracing::debug!("Failed to write store marker: {:?}", e);
+        }
         
         // Try to fix any existing memory files with invalid date formats
         let _ = store.fix_invalid_memory_files();
         
+        // Drop trashed memories past the retention period
+        let _ = store.purge_expired_trash();
+        
+        if store.is_git_enabled() && store.is_available() {
+            if let Err(e) = store.init_git() {
+                tracing::debug!("Failed to set up git for the memory store: {:?}", e);
+            }
+        }
+        
+        // Move collection members past their collection's retention period to the trash
+        let _ = store.expire_collection_members();
+        
         store
     }
     
@@ -174,30 +732,25 @@ impl MemoryStore {
             return Ok(());
         }
         
-        for entry in fs::read_dir(&self.base_path)? {
-            let entry = entry?;
-            let path = entry.path();
+        for path in self.memory_files()? {
+            let mut file = File::open(&path)?;
+            let mut content = String::new();
+            file.read_to_string(&mut content)?;
             
-            if path.is_file() && path.extension().map_or(false, |ext| ext == "md") {
-                let mut file = File::open(&path)?;
-                let mut content = String::new();
-                file.read_to_string(&mut content)?;
-                
-                // Try to parse the memory file
-                match Memory::from_markdown(&content) {
-                    Ok(_) => {}, // File is valid, no need to fix
-                    Err(e) => {
-                        // Only try to fix invalid date format errors
-                        if let MemoryError::InvalidFormat(msg) = &e {
-                            if msg.contains("Invalid created_at format") || msg.contains("Invalid updated_at format") {
-                                // Try to extract the memory data and rewrite the file with valid date formats
-                                if let Some(fixed_memory) = self.try_fix_memory_file(&content) {
-                                    // Save the fixed memory back to the file
-                                    let markdown = fixed_memory.to_markdown();
-                                    let mut file = File::create(&path)?;
-                                    file.write_all(markdown.as_bytes())?;
-                                    println!("Fixed memory file: {:?}", path);
-                                }
+            // Try to parse the memory file
+            match Memory::from_markdown(&content) {
+                Ok(_) => {}, // File is valid, no need to fix
+                Err(e) => {
+                    // Only try to fix invalid date format errors
+                    if let MemoryError::InvalidFormat(msg) = &e {
+                        if msg.contains("Invalid created_at format") || msg.contains("Invalid updated_at format") {
+                            // Try to extract the memory data and rewrite the file with valid date formats
+                            if let Some(fixed_memory) = self.try_fix_memory_file(&content) {
+                                // Save the fixed memory back to the file
+                                let markdown = fixed_memory.to_markdown();
+                                let mut file = File::create(&path)?;
+                                file.write_all(markdown.as_bytes())?;
+                                tracing::info!("Fixed memory file: {:?}", path);
                             }
                         }
                     }
@@ -227,6 +780,8 @@ impl MemoryStore {
             let tags_str = tags_re.captures(frontmatter)?.get(1)?.as_str();
             let tags: Vec<String> = tags_str.split(',').map(|s| s.trim().to_string()).collect();
             
+            let acl = acl::parse_acl(frontmatter);
+            
             // Create a new memory with current timestamps
             let now = Utc::now();
             let memory = Memory {
@@ -236,6 +791,16 @@ impl MemoryStore {
                 tags,
                 created_at: now,
                 updated_at: now,
+                last_viewed_at: None,
+                remind_at: None,
+                importance: None,
+                summary: None,
+                collection: None,
+                read_only: false,
+                attachments: Vec::new(),
+                references: Vec::new(),
+                owner: acl.owner,
+                shared: acl.shared,
             };
             
             return Some(memory);
@@ -244,131 +809,602 @@ impl MemoryStore {
         None
     }
     
-    fn get_memory_path(&self, id: &str) -> PathBuf {
-        self.base_path.join(format!("{}.md", id))
+    /// Generate an unused ID according to the configured scheme
+    pub fn generate_id(&self) -> Result<String, MemoryError> {
+        match self.options.id_scheme {
+            IdScheme::Uuid => Ok(Uuid::new_v4().to_string()),
+            IdScheme::Ulid => Ok(ulid::Ulid::new().to_string()),
+            IdScheme::Timestamp => {
+                // Minute precision like most Zettelkasten tools, falling back to
+                // seconds and then a counter when several notes share a timestamp
+                let now = Utc::now();
+                let minute = now.format("%Y%m%d%H%M").to_string();
+                let second = now.format("%Y%m%d%H%M%S").to_string();
+                
+                // The ID stays reserved until its memory is saved, so memories created
+                // at the same time never get the same one
+                let mut reserved = self.reserved_ids.lock().unwrap_or_else(|e| e.into_inner());
+                
+                for candidate in [minute, second.clone()] {
+                    if !reserved.contains(&candidate) && self.find_memory_path(&candidate)?.is_none() {
+                        reserved.insert(candidate.clone());
+                        return Ok(candidate);
+                    }
+                }
+                
+                let mut counter = 2;
+                loop {
+                    let candidate = format!("{}-{}", second, counter);
+                    if !reserved.contains(&candidate) && self.find_memory_path(&candidate)?.is_none() {
+                        reserved.insert(candidate.clone());
+                        return Ok(candidate);
+                    }
+                    counter += 1;
+                }
+            }
+        }
     }
     
-    pub fn save(&self, memory: &Memory) -> Result<(), MemoryError> {
-        let path = self.get_memory_path(&memory.id);
-        let markdown = memory.to_markdown();
+    /// Create a new, unsaved memory with an ID from the configured scheme and aliased tags replaced
+    pub fn new_memory(&self, title: String, content: String, tags: Vec<String>) -> Result<Memory, MemoryError> {
+        let tags = self.normalize_tags(&tags)?;
+        let mut memory = Memory::new(title, content, tags);
+        memory.id = self.generate_id()?;
+        Ok(memory)
+    }
+    
+    // Folders the store keeps its own files in, which never hold memories
+    fn is_reserved_dir(&self, path: &Path) -> bool {
+        path == self.attachments_root() || path == self.conversations_dir()
+    }
+    
+    // The directories that hold memories: the root, plus every visible
+    // subdirectory when scanning recursively
+    fn store_dirs(&self) -> Result<Vec<PathBuf>, MemoryError> {
+        let mut dirs = vec![self.base_path.clone()];
+        if !self.options.recursive {
+            return Ok(dirs);
+        }
         
-        let mut file = File::create(path)?;
-        file.write_all(markdown.as_bytes())?;
+        let mut pending = vec![self.base_path.clone()];
+        while let Some(dir) = pending.pop() {
+            let entries = match fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(e) if dir == self.base_path => return Err(MemoryError::Io(e)),
+                Err(e) => {
+                    tracing::debug!("Error reading directory {:?}: {:?}", dir, e);
+                    continue;
+                }
+            };
+            
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.is_dir() && !is_hidden(&path) && !path.is_symlink() && !self.is_reserved_dir(&path) {
+                    dirs.push(path.clone());
+                    pending.push(path);
+                }
+            }
+        }
         
-        Ok(())
+        Ok(dirs)
     }
     
-    pub fn get(&self, id: &str) -> Result<Memory, MemoryError> {
-        let path = self.get_memory_path(id);
+    // All memory files in the store
+    fn memory_files(&self) -> Result<Vec<PathBuf>, MemoryError> {
+        let mut files = Vec::new();
+        
+        for dir in self.store_dirs()? {
+            let entries = match fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(e) if dir == self.base_path => return Err(MemoryError::Io(e)),
+                Err(e) => {
+                    tracing::debug!("Error reading directory {:?}: {:?}", dir, e);
+                    continue;
+                }
+            };
+            
+            for entry_result in entries {
+                match entry_result {
+                    Ok(entry) => {
+                        let path = entry.path();
+                        // Symlinks are handled as external, read-only sources
+                        if is_memory_file(&path) && !path.is_symlink() {
+                            files.push(path);
+                        }
+                    },
+                    Err(e) => tracing::debug!("Error accessing directory entry: {:?}", e)
+                }
+            }
+        }
+        
+        Ok(files)
+    }
+    
+    // The collection of a memory file is its folder relative to the store root
+    fn collection_for(&self, path: &Path) -> Option<String> {
+        let relative = path.parent()?.strip_prefix(&self.base_path).ok()?;
+        let collection = relative.components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+            .join("/");
+        
+        if collection.is_empty() { None } else { Some(collection) }
+    }
+    
+    // Find the file holding the memory with the given ID
+    fn find_memory_path(&self, id: &str) -> Result<Option<PathBuf>, MemoryError> {
+        // IDs are file stems, never paths
+        if !is_valid_id(id) {
+            return Ok(None);
+        }
+        self.check_available()?;
         
-        if !path.exists() {
-            return Err(MemoryError::NotFound(id.to_string()));
+        let path = self.base_path.join(format!("{}.md", id));
+        if path.exists() {
+            return Ok(Some(path));
         }
         
+        if !self.options.recursive {
+            return Ok(None);
+        }
+        
+        let file_name = format!("{}.md", id);
+        Ok(self.memory_files()?
+            .into_iter()
+            .find(|path| path.file_name().is_some_and(|name| name == file_name.as_str())))
+    }
+    
+    // Where a memory should be written, based on its collection
+    fn target_path(&self, memory: &Memory) -> Result<PathBuf, MemoryError> {
+        let mut dir = self.base_path.clone();
+        
+        if let Some(collection) = memory.collection.as_deref().filter(|_| self.options.recursive) {
+            for segment in collection.split('/').filter(|s| !s.is_empty()) {
+                if segment.starts_with('.') || segment.contains('\\') || self.is_reserved_dir(&dir.join(segment)) {
+                    return Err(MemoryError::InvalidFormat(format!("Invalid collection: {}", collection)));
+                }
+                dir.push(segment);
+            }
+        }
+        
+        Ok(dir.join(format!("{}.md", memory.id)))
+    }
+    
+    fn read_memory(&self, path: &Path) -> Result<Memory, MemoryError> {
         let mut file = File::open(path)?;
         let mut content = String::new();
         file.read_to_string(&mut content)?;
         
-        Memory::from_markdown(&content)
+        let mut memory = Memory::from_markdown(&content)?;
+        memory.collection = self.collection_for(path);
+        Ok(memory)
     }
     
-    pub fn delete(&self, id: &str) -> Result<(), MemoryError> {
-        let path = self.get_memory_path(id);
+    // Configured external folders, plus symlinks inside the store when enabled
+    fn external_sources(&self) -> Vec<ExternalSource> {
+        let mut sources: Vec<ExternalSource> = self.options.external_dirs.iter()
+            .map(|dir| ExternalSource {
+                root: dir.clone(),
+                name: dir.file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_else(|| dir.to_string_lossy().to_string()),
+            })
+            .collect();
+        
+        if self.options.follow_symlinks {
+            for dir in self.store_dirs().unwrap_or_default() {
+                for entry in fs::read_dir(&dir).into_iter().flatten().flatten() {
+                    let path = entry.path();
+                    if path.is_symlink() && !is_hidden(&path) {
+                        let name = path.file_stem()
+                            .map(|stem| stem.to_string_lossy().to_string())
+                            .unwrap_or_default();
+                        let name = match self.collection_for(&path) {
+                            Some(collection) => format!("{}/{}", collection, name),
+                            None => name,
+                        };
+                        sources.push(ExternalSource { root: path, name });
+                    }
+                }
+            }
+        }
+        
+        sources
+    }
+    
+    // Memories read from external sources, all marked read-only
+    fn external_memories(&self) -> Vec<Memory> {
+        self.external_sources()
+            .iter()
+            .flat_map(|source| {
+                external::markdown_files(&source.root)
+                    .into_iter()
+                    .filter_map(move |path| external::read_document(source, &path))
+            })
+            .collect()
+    }
+    
+    fn get_external(&self, id: &str) -> Option<Memory> {
+        self.external_memories().into_iter().find(|memory| memory.id == id)
+    }
+    
+    /// Changes made through this store, published as they happen
+    ///
+    /// The bus is shared with every other store opened on the same directory
+    /// in this process. Edits made to the files by other programs are not reported.
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+    
+    pub fn save(&self, memory: &Memory) -> Result<(), MemoryError> {
+        self.save_as(memory, None)
+    }
+    
+    // Save a memory, committing it with `message` in git mode instead of a
+    // message describing the change
+    pub(super) fn save_as(&self, memory: &Memory, message: Option<&str>) -> Result<(), MemoryError> {
+        let _change = self.begin_change()?;
+        // Content is cleaned up, and tags are stored under the name their aliases stand for
+        let preprocessed = self.preprocess(memory);
+        let memory = preprocessed.as_ref().unwrap_or(memory);
+        let normalized = self.with_normalized_tags(memory)?;
+        let memory = normalized.as_ref().unwrap_or(memory);
+        let previous_path = self.find_memory_path(&memory.id)?;
+        let existed = previous_path.is_some();
+        
+        // Views alone are not worth a commit; they are included in the next one
+        let viewed_only = self.is_git_enabled() && previous_path.as_ref()
+            .and_then(|path| self.read_memory(path).ok())
+            .is_some_and(|mut previous| {
+                previous.last_viewed_at = memory.last_viewed_at;
+                previous.to_markdown() == memory.to_markdown() && previous.collection == memory.collection
+            });
+        if memory.read_only || (!existed && self.get_external(&memory.id).is_some()) {
+            return Err(MemoryError::ReadOnly(memory.id.clone()));
+        }
+        
+        let path = self.target_path(memory)?;
+        let markdown = memory.to_markdown();
         
-        if !path.exists() {
-            return Err(MemoryError::NotFound(id.to_string()));
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
         }
         
-        fs::remove_file(path)?;
+        // A new memory never replaces a file that appeared since its ID was picked
+        let mut file = if existed {
+            File::create(&path)?
+        } else {
+            OpenOptions::new().write(true).create_new(true).open(&path).map_err(|e| match e.kind() {
+                std::io::ErrorKind::AlreadyExists => MemoryError::AlreadyExists(memory.id.clone()),
+                _ => e.into(),
+            })?
+        };
+        file.write_all(markdown.as_bytes())?;
+        self.reserved_ids.lock().unwrap_or_else(|e| e.into_inner()).remove(&memory.id);
+        self.invalidate_link_index();
+        
+        // Remove the old file if the memory moved to another collection
+        if let Some(previous) = previous_path.filter(|previous| *previous != path) {
+            fs::remove_file(previous)?;
+        }
+        
+        self.events.publish(if existed { MemoryEvent::updated(memory) } else { MemoryEvent::created(memory) });
+        
+        if !viewed_only {
+            let message = match message {
+                Some(message) => message.to_string(),
+                None if existed => format!("Update memory: {} ({})", memory.title, memory.id),
+                None => format!("Create memory: {} ({})", memory.title, memory.id),
+            };
+            self.git_commit(&message);
+        }
+        Ok(())
+    }
+    
+    pub fn get(&self, id: &str) -> Result<Memory, MemoryError> {
+        match self.find_memory_path(id)? {
+            Some(path) => self.read_memory(&path),
+            None => self.get_external(id).ok_or_else(|| MemoryError::NotFound(id.to_string())),
+        }
+    }
+    
+    /// Move a memory to the trash
+    pub fn delete(&self, id: &str) -> Result<(), MemoryError> {
+        let _change = self.begin_change()?;
+        let path = match self.find_memory_path(id)? {
+            Some(path) => path,
+            None if self.get_external(id).is_some() => return Err(MemoryError::ReadOnly(id.to_string())),
+            None => return Err(MemoryError::NotFound(id.to_string())),
+        };
+        
+        // Deleted memories go to the trash and can be restored until purged
+        let title = self.read_memory(&path).map(|memory| memory.title).unwrap_or_default();
+        self.move_to_trash(id, &path)?;
+        self.invalidate_link_index();
+        self.events.publish(MemoryEvent::deleted(id));
+        self.git_commit(&format!("Delete memory: {} ({})", title, id));
         
         Ok(())
     }
     
     pub fn list(&self) -> Result<Vec<Memory>, MemoryError> {
-        println!("[DEBUG] Listing memories from path: {:?}", self.base_path);
+        self.list_cancellable(&CancelToken::default())
+    }
+    
+    /// List memories, stopping with `MemoryError::Cancelled` once `cancel` is set
+    pub fn list_cancellable(&self, cancel: &CancelToken) -> Result<Vec<Memory>, MemoryError> {
+        tracing::debug!("Listing memories from path: {:?}", self.base_path);
         let mut memories = Vec::new();
         
-        if !self.base_path.exists() {
-            println!("[DEBUG] Memory directory does not exist, creating it");
-            fs::create_dir_all(&self.base_path)?;
-            return Ok(memories);
-        }
-        
-        match fs::read_dir(&self.base_path) {
-            Ok(entries) => {
-                for entry_result in entries {
-                    match entry_result {
-                        Ok(entry) => {
-                            let path = entry.path();
-                            println!("[DEBUG] Processing file: {:?}", path);
-                            
-                            if path.is_file() && path.extension().map_or(false, |ext| ext == "md") {
-                                match File::open(&path) {
-                                    Ok(mut file) => {
-                                        let mut content = String::new();
-                                        match file.read_to_string(&mut content) {
-                                            Ok(_) => {
-                                                match Memory::from_markdown(&content) {
-                                                    Ok(memory) => {
-                                                        println!("[DEBUG] Successfully parsed memory: {}", memory.id);
-                                                        memories.push(memory);
-                                                    },
-                                                    Err(e) => {
-                                                        println!("[DEBUG] Error parsing memory file {:?}: {:?}", path, e);
-                                                        // Try to recover the memory if possible
-                                                        if let Some(fixed_memory) = self.try_fix_memory_file(&content) {
-                                                            println!("[DEBUG] Recovered memory: {}", fixed_memory.id);
-                                                            memories.push(fixed_memory);
-                                                        }
-                                                    }
-                                                }
-                                            },
-                                            Err(e) => println!("[DEBUG] Error reading file {:?}: {:?}", path, e)
-                                        }
-                                    },
-                                    Err(e) => println!("[DEBUG] Error opening file {:?}: {:?}", path, e)
-                                }
-                            }
+        // A missing store is reported rather than recreated empty
+        self.check_available()?;
+        
+        for path in self.memory_files()? {
+            cancel.check()?;
+            tracing::debug!("Processing file: {:?}", path);
+            
+            match fs::read_to_string(&path) {
+                Ok(content) => {
+                    match Memory::from_markdown(&content) {
+                        Ok(mut memory) => {
+                            tracing::debug!("Successfully parsed memory: {}", memory.id);
+                            memory.collection = self.collection_for(&path);
+                            memories.push(memory);
                         },
-                        Err(e) => println!("[DEBUG] Error accessing directory entry: {:?}", e)
+                        Err(e) => {
+                            tracing::debug!("Error parsing memory file {:?}: {:?}", path, e);
+                            // Try to recover the memory if possible
+                            if let Some(mut fixed_memory) = self.try_fix_memory_file(&content) {
+                                tracing::debug!("Recovered memory: {}", fixed_memory.id);
+                                fixed_memory.collection = self.collection_for(&path);
+                                memories.push(fixed_memory);
+                            }
+                        }
                     }
-                }
-            },
-            Err(e) => {
-                println!("[DEBUG] Error reading directory {:?}: {:?}", self.base_path, e);
-                return Err(MemoryError::Io(e));
+                },
+                Err(e) => tracing::debug!("Error reading file {:?}: {:?}", path, e)
             }
         }
         
-        println!("[DEBUG] Found {} memories", memories.len());
+        // Add documents from external sources, never shadowing store memories
+        let external = self.external_memories();
+        if !external.is_empty() {
+            let ids: std::collections::HashSet<String> = memories.iter().map(|m| m.id.clone()).collect();
+            memories.extend(external.into_iter().filter(|m| !ids.contains(&m.id)));
+        }
+        
+        tracing::debug!("Found {} memories", memories.len());
         Ok(memories)
     }
     
+    /// List memories with paging, sorting and filtering
+    pub fn list_paged(&self, options: &ListOptions) -> Result<Page<Memory>, MemoryError> {
+        let mut memories = match &options.collection {
+            Some(collection) => self.filter_by_collection(self.list()?, collection)?,
+            None => self.list()?,
+        };
+        // Tag aliases match like the tag they stand for
+        let mut options = options.clone();
+        options.collation.get_or_insert_with(|| self.options.collation.clone());
+        if let Some(tag) = options.tag.take() {
+            let synonyms = self.tag_synonyms(&tag)?;
+            memories.retain(|memory| has_any_tag(memory, &synonyms));
+        }
+        Ok(options.apply(memories))
+    }
+    
     pub fn search(&self, query: &str) -> Result<Vec<Memory>, MemoryError> {
-        let memories = self.list()?;
+        self.search_cancellable(query, &CancelToken::default())
+    }
+    
+    /// Search memories with a query in the search language, stopping with
+    /// `MemoryError::Cancelled` once `cancel` is set
+    pub fn search_cancellable(&self, query: &str, cancel: &CancelToken) -> Result<Vec<Memory>, MemoryError> {
+        let query = SearchQuery::parse(query)?;
+        self.search_query_cancellable(&query, cancel)
+    }
+    
+    pub fn search_by_tag(&self, tag: &str) -> Result<Vec<Memory>, MemoryError> {
+        self.search_by_tag_cancellable(tag, &CancelToken::default())
+    }
+    
+    /// Find memories with a tag or one of its aliases, stopping with `MemoryError::Cancelled` once `cancel` is set
+    pub fn search_by_tag_cancellable(&self, tag: &str, cancel: &CancelToken) -> Result<Vec<Memory>, MemoryError> {
+        let memories = self.list_cancellable(cancel)?;
         
-        let query = query.to_lowercase();
+        let synonyms = self.tag_synonyms(tag)?;
         let filtered = memories.into_iter()
-            .filter(|memory| {
-                memory.title.to_lowercase().contains(&query) ||
-                memory.content.to_lowercase().contains(&query) ||
-                memory.tags.iter().any(|tag| tag.to_lowercase().contains(&query))
-            })
+            .filter(|memory| has_any_tag(memory, &synonyms))
             .collect();
             
         Ok(filtered)
     }
     
-    pub fn search_by_tag(&self, tag: &str) -> Result<Vec<Memory>, MemoryError> {
-        let memories = self.list()?;
+    /// Timestamp of the most recent mutation in the store
+    ///
+    /// Derived from the modification times of the memory directory (which changes
+    /// when files are added or removed) and of every memory file, so edits made by
+    /// other tools are picked up as well.
+    pub fn last_modified(&self) -> Result<DateTime<Utc>, MemoryError> {
+        self.check_available()?;
+        let mut latest = fs::metadata(&self.base_path)?.modified()?;
         
-        let tag = tag.to_lowercase();
-        let filtered = memories.into_iter()
-            .filter(|memory| {
-                memory.tags.iter().any(|t| t.to_lowercase() == tag)
-            })
-            .collect();
+        // Directory mtimes change when memories are added or removed
+        let dirs = self.store_dirs()?;
+        let mut files = self.memory_files()?;
+        for source in self.external_sources() {
+            files.extend(external::markdown_files(&source.root));
+        }
+        for path in dirs.iter().chain(files.iter()) {
+            if let Ok(modified) = fs::metadata(path).and_then(|m| m.modified()) {
+                if modified > latest {
+                    latest = modified;
+                }
+            }
+        }
+        
+        Ok(DateTime::<Utc>::from(latest))
+    }
+    
+    /// Scan the store directory and report files that are not memories
+    ///
+    /// Foreign files are never modified by the store; this lets callers see why a
+    /// note kept in the same folder does not show up as a memory.
+    pub fn diagnostics(&self) -> Result<StoreDiagnostics, MemoryError> {
+        self.check_available()?;
+        let mut memory_count = 0;
+        let mut foreign_files = Vec::new();
+        
+        let paths = self.store_dirs()?
+            .iter()
+            .filter_map(|dir| fs::read_dir(dir).ok())
+            .flat_map(|entries| entries.flatten().map(|entry| entry.path()))
+            .collect::<Vec<_>>();
+        
+        for path in paths {
+            // Attachments and conversations are managed by the store alongside the memories
+            if self.is_reserved_dir(&path) {
+                continue;
+            }
             
-        Ok(filtered)
+            let (kind, reason) = if is_hidden(&path) {
+                (ForeignFileKind::Hidden, "Hidden files are ignored".to_string())
+            } else if path.is_symlink() {
+                if self.options.follow_symlinks {
+                    continue;
+                }
+                (ForeignFileKind::Symlink, "Symbolic links are only followed when enabled".to_string())
+            } else if path.is_dir() {
+                if self.options.recursive {
+                    continue;
+                }
+                (ForeignFileKind::Directory, "Subdirectories are not scanned unless recursive scanning is enabled".to_string())
+            } else if !is_memory_file(&path) {
+                (ForeignFileKind::NotMarkdown, "Only .md files are read as memories".to_string())
+            } else {
+                match fs::read_to_string(&path) {
+                    Ok(content) => match Memory::from_markdown(&content) {
+                        Ok(_) => {
+                            memory_count += 1;
+                            continue;
+                        }
+                        Err(e) if self.try_fix_memory_file(&content).is_some() => {
+                            // Still listed, but with reset timestamps
+                            memory_count += 1;
+                            (ForeignFileKind::InvalidMemory, format!("Recovered with reset timestamps: {}", e))
+                        }
+                        Err(e) => (ForeignFileKind::InvalidMemory, e.to_string()),
+                    },
+                    Err(e) => (ForeignFileKind::InvalidMemory, format!("Unreadable: {}", e)),
+                }
+            };
+            
+            foreign_files.push(ForeignFile { path, kind, reason });
+        }
+        
+        foreign_files.sort_by(|a, b| a.path.cmp(&b.path));
+        
+        Ok(StoreDiagnostics {
+            base_path: self.base_path.clone(),
+            memory_count,
+            external_count: self.external_memories().len(),
+            foreign_files,
+        })
+    }
+    
+    /// Change the ID of a memory, rewriting `[[old-id]]` wiki-links across the store
+    ///
+    /// Every affected file is staged next to its target first and only swapped in
+    /// once all writes succeeded, so a failure leaves the store untouched.
+    pub fn rename(&self, id: &str, new_id: &str) -> Result<RenameResult, MemoryError> {
+        let _change = self.begin_change()?;
+        let old_path = match self.find_memory_path(id)? {
+            Some(path) => path,
+            None if self.get_external(id).is_some() => return Err(MemoryError::ReadOnly(id.to_string())),
+            None => return Err(MemoryError::NotFound(id.to_string())),
+        };
+        
+        if !is_valid_id(new_id) {
+            return Err(MemoryError::InvalidId(new_id.to_string()));
+        }
+        
+        let mut memory = self.read_memory(&old_path)?;
+        if new_id == id {
+            return Ok(RenameResult { memory, updated_references: Vec::new() });
+        }
+        if self.find_memory_path(new_id)?.is_some() || self.get_external(new_id).is_some() {
+            return Err(MemoryError::AlreadyExists(new_id.to_string()));
+        }
+        
+        let now = Utc::now();
+        let mut updates = Vec::new();
+        let mut updated_references = Vec::new();
+        let mut rewritten_memories = Vec::new();
+        
+        for path in self.memory_files()? {
+            if path == old_path {
+                continue;
+            }
+            
+            let Ok(content) = fs::read_to_string(&path) else { continue };
+            let Ok(mut other) = Memory::from_markdown(&content) else { continue };
+            
+            if let Some(rewritten) = links::rewrite_links(&other.content, id, new_id) {
+                other.content = rewritten;
+                other.updated_at = now;
+                other.collection = self.collection_for(&path);
+                updated_references.push(other.id.clone());
+                updates.push((path, other.to_markdown()));
+                rewritten_memories.push(other);
+            }
+        }
+        
+        memory.id = new_id.to_string();
+        memory.updated_at = now;
+        if let Some(rewritten) = links::rewrite_links(&memory.content, id, new_id) {
+            memory.content = rewritten;
+        }
+        let new_path = old_path.with_file_name(format!("{}.md", new_id));
+        updates.push((new_path, memory.to_markdown()));
+        
+        // Stage every write as a hidden file so a failure can be rolled back
+        let mut staged: Vec<(PathBuf, PathBuf)> = Vec::new();
+        for (target, markdown) in updates {
+            let file_name = target.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            let staging = target.with_file_name(format!(".{}.rename-tmp", file_name));
+            
+            if let Err(e) = fs::write(&staging, markdown.as_bytes()) {
+                let _ = fs::remove_file(&staging);
+                for (staging, _) in &staged {
+                    let _ = fs::remove_file(staging);
+                }
+                return Err(MemoryError::Io(e));
+            }
+            staged.push((staging, target));
+        }
+        
+        for (staging, target) in &staged {
+            fs::rename(staging, target)?;
+        }
+        fs::remove_file(&old_path)?;
+        self.invalidate_link_index();
+        self.replace_in_collections(id, Some(new_id))?;
+        self.rename_embedding(id, new_id)?;
+        
+        // Attachments follow the memory to its new ID
+        let old_attachments = self.attachments_dir(id);
+        if old_attachments.exists() {
+            fs::rename(old_attachments, self.attachments_dir(new_id))?;
+        }
+        self.rename_attachment_text(id, new_id)?;
+        
+        self.events.publish(MemoryEvent::deleted(id));
+        self.events.publish(MemoryEvent::created(&memory));
+        for other in &rewritten_memories {
+            self.events.publish(MemoryEvent::updated(other));
+        }
+        self.git_commit(&format!("Rename memory: {} to {}", id, new_id));
+        
+        Ok(RenameResult { memory, updated_references })
     }
 }
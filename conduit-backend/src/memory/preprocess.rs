@@ -0,0 +1,243 @@
+use std::sync::OnceLock;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::{Memory, MemoryStore};
+
+/// Query parameters added by ad and mail platforms to follow clicks, removed
+/// by [`PreprocessStep::StripTrackingParams`] along with every `utm_*` parameter
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid", "gclid", "gbraid", "wbraid", "dclid", "msclkid", "yclid", "twclid", "igshid",
+    "mc_cid", "mc_eid", "_hsenc", "_hsmi", "mkt_tok",
+];
+
+/// A built-in cleanup step of the preprocessing chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreprocessStep {
+    /// Remove `utm_*`, `fbclid`, `gclid` and similar tracking parameters from links
+    StripTrackingParams,
+    /// Use `\n` line endings, drop trailing spaces and runs of blank lines, and
+    /// collapse spaces in titles
+    NormalizeWhitespace,
+    /// Replace curly quotes and apostrophes with straight ones
+    StraightenQuotes,
+}
+
+/// A regex replacement applied to memory content, as in [`Regex::replace_all`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Replacement {
+    pub pattern: String,
+    /// Text put in place of each match; `$1` or `${name}` insert groups
+    #[serde(default)]
+    pub replacement: String,
+}
+
+/// How memories are cleaned up each time they are saved, set under `[storage.preprocess]`
+///
+/// The steps run in the order listed, then the replacements in theirs. Every
+/// save runs the chain again, so replacements should leave text they already
+/// produced unchanged.
+///
+/// ```toml
+/// [storage.preprocess]
+/// steps = ["strip_tracking_params", "normalize_whitespace", "straighten_quotes"]
+/// tracking_params = ["ref"]
+///
+/// [[storage.preprocess.replacements]]
+/// pattern = "(^|[^\\[])JIRA-(\\d+)\\b"
+/// replacement = "${1}[JIRA-${2}](https://jira.example.com/browse/JIRA-${2})"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PreprocessConfig {
+    pub steps: Vec<PreprocessStep>,
+    /// Query parameters stripped from links besides the known tracking ones
+    pub tracking_params: Vec<String>,
+    pub replacements: Vec<Replacement>,
+}
+
+impl PreprocessConfig {
+    /// Check that every replacement pattern is a valid regex
+    pub fn check(&self) -> Result<(), String> {
+        for replacement in &self.replacements {
+            Regex::new(&replacement.pattern)
+                .map_err(|e| format!("Invalid preprocessing pattern {:?}: {}", replacement.pattern, e))?;
+        }
+        Ok(())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.steps.is_empty() && self.replacements.is_empty()
+    }
+}
+
+/// The preprocessing chain of a store, with its patterns compiled
+#[derive(Debug, Default)]
+pub(super) struct Preprocessor {
+    steps: Vec<PreprocessStep>,
+    tracking_params: Vec<String>,
+    replacements: Vec<(Regex, String)>,
+}
+
+impl Preprocessor {
+    pub(super) fn new(config: &PreprocessConfig) -> Self {
+        let mut replacements = Vec::new();
+        for replacement in &config.replacements {
+            match Regex::new(&replacement.pattern) {
+                Ok(regex) => replacements.push((regex, replacement.replacement.clone())),
+                Err(e) => tracing::warn!("Ignoring invalid preprocessing pattern {}: {}", replacement.pattern, e),
+            }
+        }
+        Self {
+            steps: config.steps.clone(),
+            tracking_params: config.tracking_params.iter().map(|param| param.to_lowercase()).collect(),
+            replacements,
+        }
+    }
+
+    /// Run the chain over a memory's title and content
+    pub(super) fn apply(&self, title: &str, content: &str) -> (String, String) {
+        let (mut title, mut content) = (title.to_string(), content.to_string());
+        for step in &self.steps {
+            match step {
+                PreprocessStep::StripTrackingParams => {
+                    title = self.strip_tracking_params(&title);
+                    content = self.strip_tracking_params(&content);
+                }
+                PreprocessStep::NormalizeWhitespace => {
+                    title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+                    content = normalize_whitespace(&content);
+                }
+                PreprocessStep::StraightenQuotes => {
+                    title = straighten_quotes(&title);
+                    content = outside_code(&content, straighten_quotes);
+                }
+            }
+        }
+        for (regex, replacement) in &self.replacements {
+            content = regex.replace_all(&content, replacement.as_str()).into_owned();
+        }
+        (title, content)
+    }
+
+    fn is_tracking_param(&self, key: &str) -> bool {
+        let key = key.to_lowercase();
+        key.starts_with("utm_") || TRACKING_PARAMS.contains(&key.as_str()) || self.tracking_params.contains(&key)
+    }
+
+    // Every link in the text with its tracking parameters removed
+    fn strip_tracking_params(&self, text: &str) -> String {
+        static URL: OnceLock<Regex> = OnceLock::new();
+        let url = URL.get_or_init(|| Regex::new(r#"https?://[^\s<>()\[\]"'`]+"#).unwrap());
+        url.replace_all(text, |captures: &regex::Captures| {
+            let link = &captures[0];
+            let (rest, fragment) = match link.split_once('#') {
+                Some((rest, fragment)) => (rest, Some(fragment)),
+                None => (link, None),
+            };
+            let Some((base, query)) = rest.split_once('?') else {
+                return link.to_string();
+            };
+            let kept: Vec<&str> = query.split('&')
+                .filter(|pair| !pair.is_empty() && !self.is_tracking_param(pair.split('=').next().unwrap_or(pair)))
+                .collect();
+            let mut cleaned = base.to_string();
+            if !kept.is_empty() {
+                cleaned.push('?');
+                cleaned.push_str(&kept.join("&"));
+            }
+            if let Some(fragment) = fragment {
+                cleaned.push('#');
+                cleaned.push_str(fragment);
+            }
+            cleaned
+        }).into_owned()
+    }
+}
+
+// Whether a line opens or closes a fenced code block
+fn is_fence(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("```") || line.starts_with("~~~")
+}
+
+// Apply `change` to the text outside fenced code blocks, keeping code exact
+fn outside_code(content: &str, change: fn(&str) -> String) -> String {
+    let mut in_code = false;
+    content.split_inclusive('\n')
+        .map(|line| {
+            if is_fence(line) {
+                in_code = !in_code;
+                line.to_string()
+            } else if in_code {
+                line.to_string()
+            } else {
+                change(line)
+            }
+        })
+        .collect()
+}
+
+fn straighten_quotes(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}' => '\'',
+            '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{201F}' => '"',
+            other => other,
+        })
+        .collect()
+}
+
+// Unix line endings, no trailing spaces, at most one blank line in a row
+// outside code blocks, and no blank lines at either end
+fn normalize_whitespace(content: &str) -> String {
+    let content = content.replace("\r\n", "\n").replace('\r', "\n");
+    let mut lines: Vec<String> = Vec::new();
+    let mut in_code = false;
+    for line in content.split('\n') {
+        if is_fence(line) {
+            in_code = !in_code;
+        }
+        if in_code || is_fence(line) {
+            lines.push(line.to_string());
+            continue;
+        }
+        let trimmed = line.trim_end();
+        if trimmed.is_empty() && lines.last().is_none_or(|last| last.is_empty()) {
+            continue;
+        }
+        // Two trailing spaces are a markdown line break, so those are kept
+        if !trimmed.is_empty() && line.ends_with("  ") {
+            lines.push(format!("{}  ", trimmed));
+        } else {
+            lines.push(trimmed.to_string());
+        }
+    }
+    while lines.last().is_some_and(|last| last.trim().is_empty()) {
+        lines.pop();
+    }
+    lines.join("\n")
+}
+
+impl MemoryStore {
+    /// Run the store's preprocessing chain over a memory, as saving does
+    ///
+    /// Returns `None` when the chain leaves the memory as it is.
+    pub fn preprocess(&self, memory: &Memory) -> Option<Memory> {
+        if self.options.preprocess.is_empty() {
+            return None;
+        }
+        let (title, content) = self.preprocessor.apply(&memory.title, &memory.content);
+        if title == memory.title && content == memory.content {
+            return None;
+        }
+        let mut cleaned = memory.clone();
+        // A title made only of quotes or spaces is kept rather than left blank
+        if !title.is_empty() {
+            cleaned.title = title;
+        }
+        cleaned.content = content;
+        Some(cleaned)
+    }
+}
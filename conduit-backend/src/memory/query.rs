@@ -0,0 +1,117 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use super::{has_any_tag, matches_query, CancelToken, Collation, Memory, MemoryError, MemoryStore};
+
+pub use conduit_types::query::{Comparison, DateField, QueryError, SearchQuery, TextField};
+
+impl From<QueryError> for MemoryError {
+    fn from(err: QueryError) -> Self {
+        MemoryError::InvalidQuery(err.0)
+    }
+}
+
+/// Decides which memories a query matches
+///
+/// Tags match their aliases as well, and words also match attachment text
+/// when the matcher belongs to a store. Both are looked up once per query
+/// rather than once per memory. Words match text with the store's
+/// [`Collation`], ignoring case and, unless configured otherwise, accents.
+pub(crate) struct QueryMatcher<'a> {
+    pub(super) store: Option<&'a MemoryStore>,
+    /// Lowercased tags each tag and word of the query stands for
+    synonyms: HashMap<String, Vec<String>>,
+    pub(super) collation: Collation,
+}
+
+impl<'a> QueryMatcher<'a> {
+    /// A matcher that only looks at the memories themselves, with the default collation
+    pub(crate) fn plain() -> Self {
+        Self { store: None, synonyms: HashMap::new(), collation: Collation::default() }
+    }
+
+    pub(crate) fn matches(&self, memory: &Memory, query: &SearchQuery) -> bool {
+        match query {
+            SearchQuery::All => true,
+            SearchQuery::Text { field, value } => {
+                let folded = self.collation.fold(value);
+                match field {
+                    TextField::Any => {
+                        matches_query(memory, &folded, &self.collation)
+                            || self.has_tag(memory, &value.to_lowercase())
+                            || self.store.is_some_and(|store| store.attachment_matching(memory, &folded).is_some())
+                    }
+                    TextField::Title => self.collation.fold(&memory.title).contains(&folded),
+                    TextField::Content => self.collation.fold(&memory.content).contains(&folded),
+                    TextField::Tag => self.has_tag(memory, &value.to_lowercase()),
+                }
+            }
+            SearchQuery::Date { field, comparison, date } => {
+                let memory_date = match field {
+                    DateField::Created => memory.created_at,
+                    DateField::Updated => memory.updated_at,
+                };
+                comparison.holds(memory_date.date_naive(), *date)
+            }
+            SearchQuery::Not { query } => !self.matches(memory, query),
+            SearchQuery::And { queries } => queries.iter().all(|query| self.matches(memory, query)),
+            SearchQuery::Or { queries } => queries.iter().any(|query| self.matches(memory, query)),
+        }
+    }
+
+    // Whether the memory has the tag, one of its aliases, or the tag an alias stands for
+    fn has_tag(&self, memory: &Memory, tag: &str) -> bool {
+        match self.synonyms.get(tag) {
+            Some(synonyms) => has_any_tag(memory, synonyms),
+            None => memory.tags.iter().any(|own| own.eq_ignore_ascii_case(tag)),
+        }
+    }
+
+    // Whether a tag of a memory is the lowercased tag searched for, or an alias of it
+    pub(super) fn is_tag(&self, own: &str, tag: &str) -> bool {
+        match self.synonyms.get(tag) {
+            Some(synonyms) => synonyms.contains(&own.to_lowercase()),
+            None => own.eq_ignore_ascii_case(tag),
+        }
+    }
+}
+
+// Every value of a query that is matched against tags, lowercased
+fn tag_values(query: &SearchQuery, values: &mut Vec<String>) {
+    match query {
+        SearchQuery::Text { field: TextField::Any | TextField::Tag, value } => values.push(value.to_lowercase()),
+        SearchQuery::Not { query } => tag_values(query, values),
+        SearchQuery::And { queries } | SearchQuery::Or { queries } => {
+            queries.iter().for_each(|query| tag_values(query, values));
+        }
+        _ => {}
+    }
+}
+
+impl MemoryStore {
+    /// A matcher for `query` that knows the store's tag aliases and attachment text
+    pub(crate) fn query_matcher(&self, query: &SearchQuery) -> Result<QueryMatcher<'_>, MemoryError> {
+        let mut values = Vec::new();
+        tag_values(query, &mut values);
+        let mut synonyms = HashMap::new();
+        for value in values {
+            if let Entry::Vacant(entry) = synonyms.entry(value) {
+                let tags = self.tag_synonyms(entry.key())?;
+                entry.insert(tags);
+            }
+        }
+        Ok(QueryMatcher { store: Some(self), synonyms, collation: self.options.collation.clone() })
+    }
+
+    /// Find the memories matching a parsed query
+    pub fn search_query(&self, query: &SearchQuery) -> Result<Vec<Memory>, MemoryError> {
+        self.search_query_cancellable(query, &CancelToken::default())
+    }
+
+    /// Find the memories matching a parsed query, stopping with `MemoryError::Cancelled` once `cancel` is set
+    pub fn search_query_cancellable(&self, query: &SearchQuery, cancel: &CancelToken) -> Result<Vec<Memory>, MemoryError> {
+        let memories = self.list_cancellable(cancel)?;
+        let matcher = self.query_matcher(query)?;
+        Ok(memories.into_iter().filter(|memory| matcher.matches(memory, query)).collect())
+    }
+}
@@ -0,0 +1,91 @@
+use chrono::Utc;
+
+use super::{Memory, MemoryError, MemoryStore};
+
+pub use conduit_types::Reference;
+
+/// Check that a reference has a type and a target, and nothing that would break its frontmatter line
+pub fn validate_reference(reference: &Reference) -> Result<(), MemoryError> {
+    if reference.kind.trim().is_empty() {
+        return Err(MemoryError::InvalidReference("type is empty".to_string()));
+    }
+    if reference.target().trim().is_empty() {
+        return Err(MemoryError::InvalidReference("a url or path is required".to_string()));
+    }
+    if reference.target().contains('\n') || reference.label.as_deref().is_some_and(|label| label.contains('\n')) {
+        return Err(MemoryError::InvalidReference("line breaks are not allowed".to_string()));
+    }
+    Ok(())
+}
+
+/// Frontmatter lines for a memory's references, one JSON object per list item
+///
+/// JSON objects are valid YAML flow mappings, so the frontmatter stays readable
+/// by other tools while URLs and labels need no escaping rules of their own.
+pub(super) fn references_frontmatter(references: &[Reference]) -> String {
+    let mut lines = String::from("references:\n");
+    for reference in references {
+        let json = serde_json::to_string(reference).unwrap_or_default();
+        lines.push_str(&format!("  - {}\n", json));
+    }
+    lines
+}
+
+/// Parse the `references:` list from frontmatter, skipping malformed entries
+pub(super) fn parse_references(frontmatter: &str) -> Vec<Reference> {
+    frontmatter.lines()
+        .skip_while(|line| line.trim_end() != "references:")
+        .skip(1)
+        .take_while(|line| line.starts_with(' '))
+        .filter_map(|line| line.trim().strip_prefix("- "))
+        .filter_map(|item| serde_json::from_str(item).ok())
+        .collect()
+}
+
+impl MemoryStore {
+    // Change a memory's references and save it
+    fn update_references(
+        &self,
+        id: &str,
+        update: impl FnOnce(&mut Vec<Reference>) -> Result<(), MemoryError>,
+    ) -> Result<Memory, MemoryError> {
+        let mut memory = self.get(id)?;
+        if memory.read_only {
+            return Err(MemoryError::ReadOnly(id.to_string()));
+        }
+
+        update(&mut memory.references)?;
+        memory.updated_at = Utc::now();
+        self.save(&memory)?;
+        Ok(memory)
+    }
+
+    /// Replace all references of a memory
+    pub fn set_references(&self, id: &str, references: Vec<Reference>) -> Result<Memory, MemoryError> {
+        references.iter().try_for_each(validate_reference)?;
+        self.update_references(id, |existing| {
+            *existing = references;
+            Ok(())
+        })
+    }
+
+    /// Add a reference to a memory
+    pub fn add_reference(&self, id: &str, reference: Reference) -> Result<Memory, MemoryError> {
+        validate_reference(&reference)?;
+        self.update_references(id, |existing| {
+            existing.push(reference);
+            Ok(())
+        })
+    }
+
+    /// Remove the reference at `index` from a memory
+    pub fn remove_reference(&self, id: &str, index: usize) -> Result<Memory, MemoryError> {
+        self.update_references(id, |existing| {
+            if index >= existing.len() {
+                return Err(MemoryError::NotFound(format!("{} reference {}", id, index)));
+            }
+            existing.remove(index);
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use super::embeddings::{cosine_similarity, MemoryEmbedding};
+use super::{Memory, MemoryAccess, MemoryError, MemoryStore, Principal};
+
+/// Number of related memories returned when none is asked for
+const DEFAULT_LIMIT: usize = 10;
+
+/// Most related memories returned for one request
+const MAX_LIMIT: usize = 100;
+
+/// How memories related to another are scored, set under `[related]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RelatedConfig {
+    /// Number of related memories returned when the request does not say
+    pub limit: usize,
+    /// Weight of the tags the memories share
+    pub tag_weight: f32,
+    /// Weight of wiki-links between the memories and of the memories they both link with
+    pub link_weight: f32,
+    /// Weight of the similarity of their stored embeddings, when both have an up-to-date one
+    pub embedding_weight: f32,
+}
+
+impl Default for RelatedConfig {
+    fn default() -> Self {
+        Self {
+            limit: DEFAULT_LIMIT,
+            tag_weight: 1.0,
+            link_weight: 1.0,
+            embedding_weight: 1.0,
+        }
+    }
+}
+
+impl RelatedConfig {
+    /// Override the weights with `CONDUIT_RELATED_TAG_WEIGHT`,
+    /// `CONDUIT_RELATED_LINK_WEIGHT` and `CONDUIT_RELATED_EMBEDDING_WEIGHT`, if set
+    pub fn apply_env(&mut self) {
+        let weight = |name: &str| std::env::var(name).ok().and_then(|v| v.parse::<f32>().ok());
+        if let Some(weight) = weight("CONDUIT_RELATED_TAG_WEIGHT") {
+            self.tag_weight = weight;
+        }
+        if let Some(weight) = weight("CONDUIT_RELATED_LINK_WEIGHT") {
+            self.link_weight = weight;
+        }
+        if let Some(weight) = weight("CONDUIT_RELATED_EMBEDDING_WEIGHT") {
+            self.embedding_weight = weight;
+        }
+    }
+
+    /// These settings with the values a request overrides
+    pub fn with_overrides(&self, query: &RelatedQuery) -> Self {
+        Self {
+            limit: query.limit.unwrap_or(self.limit),
+            tag_weight: query.tag_weight.unwrap_or(self.tag_weight),
+            link_weight: query.link_weight.unwrap_or(self.link_weight),
+            embedding_weight: query.embedding_weight.unwrap_or(self.embedding_weight),
+        }
+    }
+}
+
+/// Overrides of the configured related-memory settings for one request
+#[derive(Debug, Clone, Default, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RelatedQuery {
+    /// Most memories to return, at most 100
+    pub limit: Option<usize>,
+    pub tag_weight: Option<f32>,
+    pub link_weight: Option<f32>,
+    pub embedding_weight: Option<f32>,
+}
+
+/// How alike two memories are by each signal, from 0 to 1
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RelatedSignals {
+    /// Shared tags out of all the tags of the two memories
+    pub tags: f32,
+    /// 1 when one links to the other, otherwise the share of linked memories they have in common
+    pub links: f32,
+    /// Cosine similarity of the embeddings; `None` when either has no up-to-date embedding
+    pub embedding: Option<f32>,
+}
+
+/// A memory related to another, with why
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RelatedMemory {
+    pub id: String,
+    pub title: String,
+    pub tags: Vec<String>,
+    /// Weighted average of the signals that apply, from 0 to 1
+    pub score: f32,
+    pub signals: RelatedSignals,
+    /// Tags both memories have, as written on the other memory
+    pub shared_tags: Vec<String>,
+}
+
+fn jaccard<T: Eq + std::hash::Hash>(a: &HashSet<T>, b: &HashSet<T>) -> f32 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f32 / union as f32
+}
+
+fn lowercase_tags(memory: &Memory) -> HashSet<String> {
+    memory.tags.iter().filter(|tag| !tag.is_empty()).map(|tag| tag.to_lowercase()).collect()
+}
+
+impl MemoryStore {
+    /// The memories most like the one with `id`, best first
+    ///
+    /// Each other memory is scored by the tags they share, the wiki-links
+    /// between them or to the same memories, and the cosine similarity of their
+    /// stored embeddings. The score is the average of these signals weighted
+    /// by `config`, leaving out the embedding signal when either memory has no
+    /// up-to-date embedding of the same model. Memories with no score, and
+    /// with a `principal`, those it cannot read, are left out.
+    pub fn related(&self, id: &str, config: &RelatedConfig, principal: Option<&Principal>) -> Result<Vec<RelatedMemory>, MemoryError> {
+        let memory = self.get(id)?;
+        let tag_weight = config.tag_weight.max(0.0);
+        let link_weight = config.link_weight.max(0.0);
+        let embedding_weight = config.embedding_weight.max(0.0);
+
+        let mut neighbors: HashMap<String, HashSet<String>> = HashMap::new();
+        if link_weight > 0.0 {
+            for (source, target) in self.link_index()?.edges() {
+                neighbors.entry(source.clone()).or_default().insert(target.clone());
+                neighbors.entry(target).or_default().insert(source);
+            }
+        }
+        let no_neighbors = HashSet::new();
+        let own_neighbors = neighbors.get(&memory.id).unwrap_or(&no_neighbors);
+
+        // Only embeddings of the memory's current text are compared
+        let own_embedding = match embedding_weight > 0.0 {
+            true => self.get_embedding(&memory.id)?.filter(|stored| stored.content_hash == memory.content_hash()),
+            false => None,
+        };
+
+        let own_tags = lowercase_tags(&memory);
+        let mut related: Vec<(RelatedMemory, Memory)> = Vec::new();
+        for other in self.list()? {
+            if other.id == memory.id || !other.is_visible_to(principal) {
+                continue;
+            }
+
+            let tags = jaccard(&own_tags, &lowercase_tags(&other));
+            let other_neighbors = neighbors.get(&other.id).unwrap_or(&no_neighbors);
+            let links = if own_neighbors.contains(&other.id) {
+                1.0
+            } else {
+                jaccard(own_neighbors, other_neighbors)
+            };
+            let embedding = own_embedding.as_ref().and_then(|own| {
+                let stored = self.get_embedding(&other.id).ok()??;
+                (stored.model == own.model && stored.content_hash == other.content_hash())
+                    .then(|| cosine_similarity(&own.vector, &stored.vector).max(0.0))
+            });
+
+            let mut total = tag_weight * tags + link_weight * links;
+            let mut weights = tag_weight + link_weight;
+            if let Some(embedding) = embedding {
+                total += embedding_weight * embedding;
+                weights += embedding_weight;
+            }
+            let score = if weights > 0.0 { total / weights } else { 0.0 };
+            if score <= 0.0 {
+                continue;
+            }
+
+            let shared_tags = other.tags.iter()
+                .filter(|tag| own_tags.contains(&tag.to_lowercase()))
+                .cloned()
+                .collect();
+            related.push((RelatedMemory {
+                id: other.id.clone(),
+                title: other.title.clone(),
+                tags: other.tags.clone(),
+                score,
+                signals: RelatedSignals { tags, links, embedding },
+                shared_tags,
+            }, other));
+        }
+
+        related.sort_by(|(a, a_memory), (b, b_memory)| {
+            b.score.total_cmp(&a.score).then_with(|| b_memory.updated_at.cmp(&a_memory.updated_at))
+        });
+        related.truncate(config.limit.min(MAX_LIMIT));
+        Ok(related.into_iter().map(|(related, _)| related).collect())
+    }
+}
@@ -0,0 +1,144 @@
+use std::fs;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{Collection, CollectionDefaults, MemoryError, MemoryStore};
+
+/// Records that first-run setup finished, so an empty store is not set up again
+///
+/// Committed in git mode, so other devices syncing the store skip setup too.
+const SETUP_FILE: &str = ".conduit-setup.json";
+
+/// A collection offered to new stores, whose template fills new members
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StarterTemplate {
+    /// Name of the collection it creates, also used to pick it
+    pub name: String,
+    pub description: String,
+    pub template: String,
+    /// Tags added to memories created in the collection
+    pub tags: Vec<String>,
+}
+
+impl StarterTemplate {
+    fn new(name: &str, description: &str, template: &str, tags: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            template: template.to_string(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        }
+    }
+}
+
+/// Collections with templates a new store can start with
+pub fn starter_templates() -> Vec<StarterTemplate> {
+    vec![
+        StarterTemplate::new(
+            "Daily notes",
+            "One note per day",
+            "# {{weekday}}, {{date}}\n\n## Plan\n\n## Notes\n",
+            &["daily"],
+        ),
+        StarterTemplate::new(
+            "Meetings",
+            "Meeting notes with attendees and action items",
+            "# Meeting on {{date}} at {{time}}\n\n## Attendees\n\n## Notes\n\n## Action items\n",
+            &["meeting"],
+        ),
+        StarterTemplate::new(
+            "Ideas",
+            "Ideas to come back to",
+            "# Idea\n\n## Why\n\n## Next step\n",
+            &["idea"],
+        ),
+        StarterTemplate::new(
+            "Reading",
+            "Notes on articles and books",
+            "# Title\n\nAuthor:\nLink:\n\n## Takeaways\n",
+            &["reading"],
+        ),
+    ]
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SetupRecord {
+    completed_at: DateTime<Utc>,
+}
+
+impl MemoryStore {
+    /// When first-run setup finished for this store, if it has
+    pub fn setup_completed_at(&self) -> Option<DateTime<Utc>> {
+        let json = fs::read_to_string(self.base_path.join(SETUP_FILE)).ok()?;
+        match serde_json::from_str::<SetupRecord>(&json) {
+            Ok(record) => Some(record.completed_at),
+            Err(e) => {
+                tracing::debug!("Invalid setup file in {:?}: {}", self.base_path, e);
+                None
+            }
+        }
+    }
+
+    /// Whether the store still needs first-run setup: it was never set up and
+    /// holds no memories or collections
+    ///
+    /// Stores used before setup existed are taken as set up once they hold anything.
+    pub fn needs_setup(&self) -> Result<bool, MemoryError> {
+        if self.setup_completed_at().is_some() {
+            return Ok(false);
+        }
+        self.check_available()?;
+        Ok(self.list()?.is_empty() && self.read_collections()?.is_empty())
+    }
+
+    /// Create the collections of the named starter templates, or of all of them
+    ///
+    /// Templates whose collection name is taken are skipped. Returns the
+    /// collections created; unknown names fail before anything is created.
+    pub fn seed_starter_templates(&self, names: Option<&[String]>) -> Result<Vec<Collection>, MemoryError> {
+        let starters = starter_templates();
+        let chosen: Vec<StarterTemplate> = match names {
+            None => starters,
+            Some(names) => {
+                let mut chosen = Vec::new();
+                for name in names {
+                    let starter = starters.iter()
+                        .find(|starter| starter.name.eq_ignore_ascii_case(name.trim()))
+                        .ok_or_else(|| MemoryError::InvalidCollection(format!("No starter template named {}", name)))?;
+                    chosen.push(starter.clone());
+                }
+                chosen
+            }
+        };
+
+        let existing = self.read_collections()?;
+        let mut created = Vec::new();
+        for starter in chosen {
+            let taken = existing.iter().chain(&created)
+                .any(|collection: &Collection| collection.name.eq_ignore_ascii_case(&starter.name));
+            if taken {
+                continue;
+            }
+            let defaults = CollectionDefaults {
+                template: Some(starter.template),
+                tags: starter.tags,
+                ..CollectionDefaults::default()
+            };
+            created.push(self.create_collection(starter.name, Some(starter.description), Vec::new(), defaults)?);
+        }
+        Ok(created)
+    }
+
+    /// Record that first-run setup finished, returning when
+    pub fn complete_setup(&self) -> Result<DateTime<Utc>, MemoryError> {
+        self.check_writable()?;
+        let completed_at = Utc::now();
+        let json = serde_json::to_string_pretty(&SetupRecord { completed_at })
+            .map_err(|e| MemoryError::InvalidFormat(e.to_string()))?;
+        fs::write(self.base_path.join(SETUP_FILE), json)?;
+        self.git_commit("Complete setup");
+        tracing::debug!("Completed setup of memory store {:?}", self.base_path);
+        Ok(completed_at)
+    }
+}
@@ -0,0 +1,128 @@
+use std::cell::Cell;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::RwLockReadGuard;
+use chrono::{DateTime, Utc};
+
+use super::{Collection, Memory, MemoryError, MemoryStore};
+
+/// Folder inside the store holding the attachment links of open snapshots
+const SNAPSHOTS_DIR: &str = ".snapshots";
+
+thread_local! {
+    // Changes in progress on this thread, so nested changes do not lock twice
+    static CHANGE_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Held while a change to the store is in progress, keeping snapshots out
+///
+/// Any number of changes can run at once; a snapshot waits for them to finish
+/// and holds new ones back while it is taken.
+pub(super) struct ChangeGuard<'a> {
+    _lock: Option<RwLockReadGuard<'a, ()>>,
+}
+
+impl Drop for ChangeGuard<'_> {
+    fn drop(&mut self) {
+        CHANGE_DEPTH.with(|depth| depth.set(depth.get() - 1));
+    }
+}
+
+/// The store's memories, collections and attachments at a single point in time
+///
+/// Attachments are hard links made while changes were held back, so they keep
+/// their contents when the store replaces or removes them. The links are
+/// removed when the snapshot is dropped.
+pub struct StoreSnapshot {
+    pub taken_at: DateTime<Utc>,
+    pub memories: Vec<Memory>,
+    pub collections: Vec<Collection>,
+    staging: PathBuf,
+}
+
+impl StoreSnapshot {
+    /// Path of an attachment as it was when the snapshot was taken
+    pub fn attachment_path(&self, id: &str, name: &str) -> PathBuf {
+        self.staging.join(id).join(name)
+    }
+
+    pub fn read_attachment(&self, id: &str, name: &str) -> Result<Vec<u8>, MemoryError> {
+        Ok(fs::read(self.attachment_path(id, name))?)
+    }
+}
+
+impl Drop for StoreSnapshot {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(&self.staging) {
+            if e.kind() != io::ErrorKind::NotFound {
+                tracing::debug!("Failed to remove snapshot staging {:?}: {:?}", self.staging, e);
+            }
+        }
+    }
+}
+
+// Link a file into the snapshot, copying it where links are not possible
+fn link_or_copy(from: &Path, to: &Path) -> io::Result<()> {
+    fs::hard_link(from, to).or_else(|_| fs::copy(from, to).map(|_| ()))
+}
+
+impl MemoryStore {
+    /// Check that the store may be changed and hold snapshots back until the change is done
+    pub(super) fn begin_change(&self) -> Result<ChangeGuard<'_>, MemoryError> {
+        self.check_writable()?;
+        let outermost = CHANGE_DEPTH.with(|depth| {
+            depth.set(depth.get() + 1);
+            depth.get() == 1
+        });
+        let lock = outermost.then(|| self.snapshot_lock.read().unwrap_or_else(|e| e.into_inner()));
+        Ok(ChangeGuard { _lock: lock })
+    }
+
+    /// Take a consistent copy of the store for exports and backups
+    ///
+    /// Changes in progress are finished first and new ones wait until the
+    /// memories and collections are read and the attachments linked, which
+    /// only takes a moment. Reading the snapshot does not block changes.
+    pub fn snapshot(&self) -> Result<StoreSnapshot, MemoryError> {
+        self.check_available()?;
+
+        // A read-only store cannot hold the staging folder, and nothing changes it from here
+        let root = if self.is_read_only() {
+            std::env::temp_dir().join("conduit-snapshots")
+        } else {
+            self.base_path.join(SNAPSHOTS_DIR)
+        };
+        let staging = root.join(ulid::Ulid::new().to_string());
+        fs::create_dir_all(&staging)?;
+
+        let _exclusive = self.snapshot_lock.write().unwrap_or_else(|e| e.into_inner());
+        let mut snapshot = StoreSnapshot {
+            taken_at: Utc::now(),
+            memories: Vec::new(),
+            collections: Vec::new(),
+            staging,
+        };
+        snapshot.memories = self.list()?;
+        snapshot.collections = self.list_collections()?;
+
+        for memory in &snapshot.memories {
+            let source = self.attachments_dir(&memory.id);
+            let mut linked = false;
+            for name in &memory.attachments {
+                let file = source.join(name);
+                if !file.is_file() {
+                    continue;
+                }
+                if !linked {
+                    fs::create_dir_all(snapshot.staging.join(&memory.id))?;
+                    linked = true;
+                }
+                link_or_copy(&file, &snapshot.attachment_path(&memory.id, name))?;
+            }
+        }
+
+        tracing::debug!("Took snapshot of {} memories at {}", snapshot.memories.len(), snapshot.taken_at);
+        Ok(snapshot)
+    }
+}
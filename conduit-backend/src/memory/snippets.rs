@@ -0,0 +1,133 @@
+use super::{Collation, Memory, QueryMatcher, SearchQuery, TextField};
+
+pub use conduit_types::query::{MatchField, SearchMatch, SearchResult};
+
+/// Characters kept on each side of a match in its snippet, at most
+const CONTEXT_CHARS: usize = 40;
+
+/// Most matches of one term reported for each field
+const MATCHES_PER_FIELD: usize = 3;
+
+/// Most matches reported for a memory
+const MAX_MATCHES: usize = 10;
+
+// The text terms of a query that are not negated
+fn positive_terms<'q>(query: &'q SearchQuery, terms: &mut Vec<(TextField, &'q str)>) {
+    match query {
+        SearchQuery::Text { field, value } => terms.push((*field, value)),
+        SearchQuery::And { queries } | SearchQuery::Or { queries } => {
+            queries.iter().for_each(|query| positive_terms(query, terms));
+        }
+        SearchQuery::All | SearchQuery::Date { .. } | SearchQuery::Not { .. } => {}
+    }
+}
+
+// Character offsets of the first occurrences of a term already folded with `collation`
+fn find_all(chars: &[char], term: &[char], collation: &Collation) -> Vec<(usize, usize)> {
+    // Each folded character with the offset it came from; ignored accents leave gaps
+    let folded: Vec<(usize, char)> = chars.iter()
+        .enumerate()
+        .filter_map(|(offset, c)| Some((offset, collation.fold_char(*c)?)))
+        .collect();
+    let mut found = Vec::new();
+    let mut start = 0;
+    while !term.is_empty() && start + term.len() <= folded.len() && found.len() < MATCHES_PER_FIELD {
+        if folded[start..start + term.len()].iter().map(|(_, c)| c).eq(term) {
+            // Accents following the last letter belong to the match
+            let end = folded.get(start + term.len()).map_or(chars.len(), |(offset, _)| *offset);
+            found.push((folded[start].0, end));
+            start += term.len();
+        } else {
+            start += 1;
+        }
+    }
+    found
+}
+
+// A match with the words around it, cut at word boundaries and marked with an
+// ellipsis where text was left out
+fn snippet_match(field: MatchField, name: Option<&str>, chars: &[char], start: usize, end: usize) -> SearchMatch {
+    let mut from = start.saturating_sub(CONTEXT_CHARS);
+    let mut to = (end + CONTEXT_CHARS).min(chars.len());
+    if from > 0 {
+        if let Some(space) = chars[from..start].iter().position(|c| c.is_whitespace()) {
+            from += space + 1;
+        }
+    }
+    if to < chars.len() {
+        if let Some(space) = chars[end..to].iter().rposition(|c| c.is_whitespace()) {
+            to = end + space;
+        }
+    }
+
+    let prefix = if from > 0 { "…" } else { "" };
+    let suffix = if to < chars.len() { "…" } else { "" };
+    let text: String = chars[from..to].iter().map(|c| if c.is_whitespace() { ' ' } else { *c }).collect();
+    let highlight_start = start - from + prefix.chars().count();
+    SearchMatch {
+        field,
+        name: name.map(str::to_string),
+        start,
+        end,
+        snippet: format!("{}{}{}", prefix, text, suffix),
+        highlight_start,
+        highlight_end: highlight_start + (end - start),
+    }
+}
+
+fn text_matches(field: MatchField, name: Option<&str>, text: &str, term: &[char], collation: &Collation, matches: &mut Vec<SearchMatch>) {
+    let chars: Vec<char> = text.chars().collect();
+    for (start, end) in find_all(&chars, term, collation) {
+        matches.push(snippet_match(field, name, &chars, start, end));
+    }
+}
+
+impl QueryMatcher<'_> {
+    /// Where the terms of a query appear in a memory, for showing snippets
+    ///
+    /// Negated terms and date terms are not reported. Matches are ordered by
+    /// field and then by position.
+    pub(crate) fn find_matches(&self, memory: &Memory, query: &SearchQuery) -> Vec<SearchMatch> {
+        let mut terms = Vec::new();
+        positive_terms(query, &mut terms);
+
+        let mut matches = Vec::new();
+        for (field, value) in terms {
+            let folded = self.collation.fold(value);
+            let term: Vec<char> = folded.chars().collect();
+            if matches!(field, TextField::Any | TextField::Title) {
+                text_matches(MatchField::Title, None, &memory.title, &term, &self.collation, &mut matches);
+            }
+            if matches!(field, TextField::Any | TextField::Content) {
+                text_matches(MatchField::Content, None, &memory.content, &term, &self.collation, &mut matches);
+            }
+            if matches!(field, TextField::Any | TextField::Tag) {
+                let value = value.to_lowercase();
+                for tag in memory.tags.iter().filter(|tag| self.is_tag(tag, &value)) {
+                    let chars: Vec<char> = tag.chars().collect();
+                    matches.push(snippet_match(MatchField::Tag, Some(tag), &chars, 0, chars.len()));
+                }
+            }
+            if let (TextField::Any, Some(store)) = (field, self.store) {
+                for (name, text) in store.attachment_text_containing(memory, &folded) {
+                    text_matches(MatchField::Attachment, Some(&name), &text, &term, &self.collation, &mut matches);
+                }
+            }
+        }
+
+        matches.sort_by(|a, b| (a.field, &a.name, a.start).cmp(&(b.field, &b.name, b.start)));
+        matches.dedup_by(|a, b| a.field == b.field && a.name == b.name && a.start == b.start);
+        matches.truncate(MAX_MATCHES);
+        matches
+    }
+
+    /// The memories matching a query, each with where it matched
+    pub(crate) fn results(&self, memories: Vec<Memory>, query: &SearchQuery) -> Vec<SearchResult> {
+        memories.into_iter()
+            .map(|memory| {
+                let matches = self.find_matches(&memory, query);
+                SearchResult { memory, matches }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,346 @@
+use std::fs;
+use std::path::Path;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{is_valid_id, Collection, Memory, MemoryError, MemoryEvent, MemoryMarkdown, MemoryStore};
+
+/// Name of the git remote the store syncs with
+const REMOTE: &str = "origin";
+
+/// Tag added to the copy of a memory that was changed on both sides
+pub const CONFLICT_TAG: &str = "conflict";
+
+/// How a memory changed both locally and on the remote is resolved
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictStrategy {
+    /// Keep the local version
+    Ours,
+    /// Keep the remote version
+    Theirs,
+    /// Keep the local version and add the remote one as a new memory tagged `conflict`
+    Duplicate,
+    /// Keep the local version for now and hold both under `.conflicts/` until
+    /// one is picked through `/api/conflicts`
+    #[default]
+    Quarantine,
+}
+
+impl std::str::FromStr for ConflictStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ours" => Ok(Self::Ours),
+            "theirs" => Ok(Self::Theirs),
+            "duplicate" => Ok(Self::Duplicate),
+            "quarantine" => Ok(Self::Quarantine),
+            other => Err(format!("Unknown conflict strategy: {}", other)),
+        }
+    }
+}
+
+/// Which memories a git-mode store commits, and so syncs, set under `[storage.sync_scope]`
+///
+/// Memories outside the scope are kept out of the repository, like members
+/// of collections with `sync` turned off, so they never leave the device.
+/// Collections are named by ID or name, and also match memories in a folder
+/// of that name. Tags match without regard to case.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct SyncScope {
+    /// Only sync members of these collections; with `tags`, memories matching either are synced
+    pub collections: Vec<String>,
+    /// Only sync memories with one of these tags
+    pub tags: Vec<String>,
+    /// Never sync members of these collections
+    pub exclude_collections: Vec<String>,
+    /// Never sync memories with one of these tags
+    pub exclude_tags: Vec<String>,
+}
+
+// Split a comma-separated list, dropping blank entries
+fn env_list(name: &str) -> Option<Vec<String>> {
+    let list = std::env::var(name).ok()?;
+    Some(list.split(',').map(|item| item.trim().to_string()).filter(|item| !item.is_empty()).collect())
+}
+
+impl SyncScope {
+    /// Override the scope with `CONDUIT_SYNC_COLLECTIONS`, `CONDUIT_SYNC_TAGS`,
+    /// `CONDUIT_SYNC_EXCLUDE_COLLECTIONS` and `CONDUIT_SYNC_EXCLUDE_TAGS`
+    /// (comma-separated), if set
+    pub fn apply_env(&mut self) {
+        if let Some(collections) = env_list("CONDUIT_SYNC_COLLECTIONS") {
+            self.collections = collections;
+        }
+        if let Some(tags) = env_list("CONDUIT_SYNC_TAGS") {
+            self.tags = tags;
+        }
+        if let Some(collections) = env_list("CONDUIT_SYNC_EXCLUDE_COLLECTIONS") {
+            self.exclude_collections = collections;
+        }
+        if let Some(tags) = env_list("CONDUIT_SYNC_EXCLUDE_TAGS") {
+            self.exclude_tags = tags;
+        }
+    }
+
+    /// Whether every memory is synced, apart from collections with `sync` turned off
+    pub fn is_everything(&self) -> bool {
+        *self == Self::default()
+    }
+
+    // Whether a memory is synced, given the collections it belongs to
+    fn includes(&self, memory: &Memory, collections: &[&Collection]) -> bool {
+        let in_any = |names: &[String]| names.iter().any(|name| {
+            memory.collection.as_deref() == Some(name.as_str())
+                || collections.iter().any(|collection| collection.id == *name || collection.name.eq_ignore_ascii_case(name))
+        });
+        let tagged_any = |tags: &[String]| tags.iter().any(|tag| memory.tags.iter().any(|own| own.eq_ignore_ascii_case(tag)));
+
+        if in_any(&self.exclude_collections) || tagged_any(&self.exclude_tags) {
+            return false;
+        }
+        (self.collections.is_empty() && self.tags.is_empty()) || in_any(&self.collections) || tagged_any(&self.tags)
+    }
+}
+
+/// A file changed both locally and on the remote since the last sync
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SyncConflict {
+    /// Path of the file relative to the store
+    pub path: String,
+    /// The memory stored in the file, if it is one
+    pub memory_id: Option<String>,
+    /// Which version was kept
+    pub resolution: ConflictStrategy,
+    /// ID of the memory holding the remote version, for duplicated memories
+    pub copy_id: Option<String>,
+    /// Whether both versions wait under `/api/conflicts` for a resolution
+    #[serde(default)]
+    pub quarantined: bool,
+}
+
+/// Outcome of syncing the store with its remote
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SyncReport {
+    pub branch: String,
+    /// Commit the store is at after the sync
+    pub head: String,
+    /// Memories created, changed or deleted by the remote changes
+    pub pulled: Vec<String>,
+    pub conflicts: Vec<SyncConflict>,
+    pub completed_at: DateTime<Utc>,
+}
+
+// Memory ID of a markdown file in the store, if it holds one
+fn memory_id(path: &str) -> Option<String> {
+    let id = Path::new(path).file_name()?.to_str()?.strip_suffix(".md")?;
+    is_valid_id(id).then(|| id.to_string())
+}
+
+impl MemoryStore {
+    /// IDs of the memories kept out of the repository: members of collections
+    /// with `sync` turned off, and memories outside the sync scope
+    pub fn local_only_memories(&self) -> Result<Vec<String>, MemoryError> {
+        let mut local = self.local_only_members()?;
+        let scope = &self.options.sync_scope;
+        if scope.is_everything() {
+            return Ok(local);
+        }
+
+        let collections = self.read_collections()?;
+        for memory in self.list()? {
+            let memberships: Vec<&Collection> = collections.iter()
+                .filter(|collection| collection.memory_ids.contains(&memory.id))
+                .collect();
+            if !scope.includes(&memory, &memberships) && !local.contains(&memory.id) {
+                local.push(memory.id);
+            }
+        }
+        Ok(local)
+    }
+
+    /// Pull changes from the git remote, merge them and push local commits
+    ///
+    /// The remote is `origin`, pointed at `remote` when one is given. The
+    /// remote branch defaults to the current local branch. Conflicting changes
+    /// to the same file are resolved with `strategy`; files that are not
+    /// memories, like the collections file, keep the local version when
+    /// duplicating or quarantining. Credentials come from git's own configuration, such as an
+    /// SSH agent or credential helper.
+    pub fn sync_remote(
+        &self,
+        remote: Option<&str>,
+        branch: Option<&str>,
+        strategy: ConflictStrategy,
+    ) -> Result<SyncReport, MemoryError> {
+        self.check_git()?;
+        let _change = self.begin_change()?;
+        let _guard = self.git_lock.lock().unwrap_or_else(|e| e.into_inner());
+
+        match (remote, self.git(&["remote", "get-url", REMOTE])) {
+            (Some(url), Ok(current)) if current.trim() != url => {
+                self.git(&["remote", "set-url", REMOTE, url])?;
+            }
+            (Some(_), Ok(_)) => {}
+            (Some(url), Err(_)) => {
+                self.git(&["remote", "add", REMOTE, url])?;
+            }
+            (None, Ok(_)) => {}
+            (None, Err(_)) => {
+                return Err(MemoryError::Git("No sync remote configured".to_string()));
+            }
+        }
+
+        // Include changes that were not committed yet, e.g. after a failed commit
+        self.commit_all("Commit local changes before sync")?;
+
+        let branch = match branch {
+            Some(branch) => branch.to_string(),
+            None => self.git(&["rev-parse", "--abbrev-ref", "HEAD"])?.trim().to_string(),
+        };
+        let before = self.git(&["rev-parse", "HEAD"])?.trim().to_string();
+
+        let mut conflicts = Vec::new();
+        let remote_ref = format!("refs/heads/{}", branch);
+        if !self.git(&["ls-remote", "--heads", REMOTE, &remote_ref])?.trim().is_empty() {
+            tracing::debug!("Fetching {} from {}", branch, REMOTE);
+            self.git(&["fetch", "--quiet", REMOTE, &remote_ref])?;
+
+            let message = format!("Merge remote changes from {}/{}", REMOTE, branch);
+            let merge = self.git(&["merge", "--quiet", "--no-edit", "--allow-unrelated-histories", "-m", &message, "FETCH_HEAD"]);
+            if let Err(err) = merge {
+                let unmerged = self.git(&["diff", "--name-only", "--diff-filter=U"])?;
+                if unmerged.trim().is_empty() {
+                    let _ = self.git(&["merge", "--abort"]);
+                    return Err(err);
+                }
+                for path in unmerged.lines().filter(|path| !path.is_empty()) {
+                    match self.resolve_conflict(path, strategy) {
+                        Ok(conflict) => conflicts.push(conflict),
+                        Err(err) => {
+                            let _ = self.git(&["merge", "--abort"]);
+                            return Err(err);
+                        }
+                    }
+                }
+                self.git(&["commit", "--quiet", "--message", &message])?;
+            }
+        }
+
+        let head = self.git(&["rev-parse", "HEAD"])?.trim().to_string();
+        let pulled = if head != before { self.publish_pulled(&before, &head)? } else { Vec::new() };
+
+        self.git(&["push", "--quiet", REMOTE, &format!("HEAD:{}", remote_ref)])?;
+        tracing::debug!("Synced {} with {}: {} memories pulled, {} conflicts", branch, REMOTE, pulled.len(), conflicts.len());
+
+        Ok(SyncReport {
+            branch,
+            head,
+            pulled,
+            conflicts,
+            completed_at: Utc::now(),
+        })
+    }
+
+    // Keep one side of a conflicted file, or both for a memory when duplicating
+    fn resolve_conflict(&self, path: &str, strategy: ConflictStrategy) -> Result<SyncConflict, MemoryError> {
+        let ours = self.git(&["cat-file", "-e", &format!(":2:{}", path)]).is_ok();
+        let theirs = self.git(&["cat-file", "-e", &format!(":3:{}", path)]).is_ok();
+        let memory_id = memory_id(path);
+
+        // Duplicating keeps everything; a side that deleted the file loses to one that changed it.
+        // Quarantining keeps everything too, and files that are not memories keep the local version.
+        let resolution = match strategy {
+            ConflictStrategy::Duplicate if ours && theirs && memory_id.is_some() => ConflictStrategy::Duplicate,
+            ConflictStrategy::Duplicate if ours => ConflictStrategy::Ours,
+            ConflictStrategy::Duplicate => ConflictStrategy::Theirs,
+            ConflictStrategy::Quarantine if memory_id.is_some() => ConflictStrategy::Quarantine,
+            ConflictStrategy::Quarantine if ours => ConflictStrategy::Ours,
+            ConflictStrategy::Quarantine => ConflictStrategy::Theirs,
+            strategy => strategy,
+        };
+
+        // The remote version is only staged until the conflict is resolved
+        let theirs_markdown = match resolution {
+            ConflictStrategy::Duplicate => Some(self.git(&["show", &format!(":3:{}", path)])?),
+            _ => None,
+        };
+        let quarantined = resolution == ConflictStrategy::Quarantine;
+        if quarantined {
+            let version = |stage: u8, exists: bool| match exists {
+                true => self.git(&["show", &format!(":{}:{}", stage, path)]).map(Some),
+                false => Ok(None),
+            };
+            let (mine, theirs) = (version(2, ours)?, version(3, theirs)?);
+            self.quarantine_conflict(path, "git", mine.as_deref(), theirs.as_deref())?;
+        }
+
+        let kept = match resolution {
+            ConflictStrategy::Theirs => theirs,
+            _ => ours,
+        };
+        if kept {
+            let side = if resolution == ConflictStrategy::Theirs { "--theirs" } else { "--ours" };
+            self.git(&["checkout", side, "--", path])?;
+            self.git(&["add", "--", path])?;
+        } else {
+            self.git(&["rm", "--quiet", "--", path])?;
+        }
+
+        let mut copy_id = None;
+        if let Some(markdown) = theirs_markdown {
+            let mut copy = Memory::from_markdown(&markdown)?;
+            copy.id = self.generate_id()?;
+            if !copy.tags.iter().any(|tag| tag.eq_ignore_ascii_case(CONFLICT_TAG)) {
+                copy.tags.push(CONFLICT_TAG.to_string());
+            }
+
+            // Store the copy next to the local version, in the same collection
+            let copy_path = Path::new(path).with_file_name(format!("{}.md", copy.id));
+            fs::write(self.base_path.join(&copy_path), copy.to_markdown())?;
+            self.git(&["add", "--", &copy_path.to_string_lossy()])?;
+            copy_id = Some(copy.id);
+        }
+
+        tracing::debug!("Resolved sync conflict in {} as {:?}", path, resolution);
+        Ok(SyncConflict {
+            path: path.to_string(),
+            memory_id,
+            resolution,
+            copy_id,
+            quarantined,
+        })
+    }
+
+    // Tell subscribers about memories the merge changed, returning their IDs
+    fn publish_pulled(&self, before: &str, after: &str) -> Result<Vec<String>, MemoryError> {
+        self.invalidate_link_index();
+
+        let diff = self.git(&["diff", "--name-status", "--no-renames", before, after])?;
+        let mut pulled = Vec::new();
+        for line in diff.lines() {
+            let Some((status, path)) = line.split_once('\t') else {
+                continue;
+            };
+            let Some(id) = memory_id(path).filter(|id| !pulled.contains(id)) else {
+                continue;
+            };
+
+            // A memory moved to another collection shows up as deleted in its old folder
+            let event = match status {
+                "D" if self.find_memory_path(&id)?.is_some() => self.get(&id).ok().map(|memory| MemoryEvent::updated(&memory)),
+                "D" => Some(MemoryEvent::deleted(&id)),
+                "A" => self.get(&id).ok().map(|memory| MemoryEvent::created(&memory)),
+                _ => self.get(&id).ok().map(|memory| MemoryEvent::updated(&memory)),
+            };
+            if let Some(event) = event {
+                self.events.publish(event);
+            }
+            pulled.push(id);
+        }
+        Ok(pulled)
+    }
+}
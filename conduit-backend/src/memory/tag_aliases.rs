@@ -0,0 +1,183 @@
+use std::fs;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::util::write_atomic;
+use super::{Memory, MemoryError, MemoryStore};
+
+/// File inside the store holding the tag aliases
+const TAG_ALIASES_FILE: &str = ".tag-aliases.json";
+
+/// A tag that is replaced by another, such as `js` by `javascript`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct TagAlias {
+    pub alias: String,
+    /// The tag memories get instead
+    pub tag: String,
+}
+
+/// A memory whose tags were changed by applying aliases
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RetaggedMemory {
+    pub id: String,
+    pub title: String,
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
+/// Outcome of applying tag aliases to existing memories
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TagMigration {
+    pub dry_run: bool,
+    /// Memories whose tags were, or would be, changed
+    pub memories: Vec<RetaggedMemory>,
+}
+
+// The tag an alias list gives for a tag, which is the tag itself if it has no alias
+fn resolve<'a>(aliases: &'a [TagAlias], tag: &'a str) -> &'a str {
+    aliases.iter()
+        .find(|alias| alias.alias.eq_ignore_ascii_case(tag))
+        .map_or(tag, |alias| alias.tag.as_str())
+}
+
+// Replace aliased tags and drop the duplicates that leaves, keeping the order
+fn normalize(aliases: &[TagAlias], tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = resolve(aliases, tag);
+        if !normalized.iter().any(|existing| existing.eq_ignore_ascii_case(tag)) {
+            normalized.push(tag.to_string());
+        }
+    }
+    normalized
+}
+
+impl MemoryStore {
+    fn tag_aliases_path(&self) -> PathBuf {
+        self.base_path.join(TAG_ALIASES_FILE)
+    }
+
+    /// Every tag alias, ordered by alias
+    pub fn tag_aliases(&self) -> Result<Vec<TagAlias>, MemoryError> {
+        let path = self.tag_aliases_path();
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        serde_json::from_str(&fs::read_to_string(path)?)
+            .map_err(|e| MemoryError::InvalidFormat(format!("Invalid tag aliases file: {}", e)))
+    }
+
+    fn write_tag_aliases(&self, mut aliases: Vec<TagAlias>, message: &str) -> Result<(), MemoryError> {
+        self.check_available()?;
+        let _change = self.begin_change()?;
+        aliases.sort_by_key(|alias| alias.alias.to_lowercase());
+        let json = serde_json::to_string_pretty(&aliases)
+            .map_err(|e| MemoryError::InvalidFormat(e.to_string()))?;
+        write_atomic(&self.tag_aliases_path(), json)?;
+        self.git_commit(message);
+        Ok(())
+    }
+
+    /// Make `alias` stand for `tag`
+    ///
+    /// Aliases of `alias` are pointed at `tag` too, and an alias given as the
+    /// tag is followed to its tag, so aliases never chain. Memories are not
+    /// changed until they are next saved or [`MemoryStore::apply_tag_aliases`] runs.
+    pub fn set_tag_alias(&self, alias: &str, tag: &str) -> Result<TagAlias, MemoryError> {
+        let _guard = self.tag_aliases_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let (alias, tag) = (alias.trim(), tag.trim());
+        if alias.is_empty() || tag.is_empty() {
+            return Err(MemoryError::InvalidTagAlias("alias and tag must not be empty".to_string()));
+        }
+
+        let mut aliases = self.tag_aliases()?;
+        let tag = resolve(&aliases, tag).to_string();
+        if tag.eq_ignore_ascii_case(alias) {
+            return Err(MemoryError::InvalidTagAlias(format!("{} cannot be an alias of itself", alias)));
+        }
+
+        aliases.retain(|existing| !existing.alias.eq_ignore_ascii_case(alias));
+        for existing in aliases.iter_mut().filter(|existing| existing.tag.eq_ignore_ascii_case(alias)) {
+            existing.tag = tag.clone();
+        }
+        let added = TagAlias { alias: alias.to_string(), tag };
+        aliases.push(added.clone());
+        self.write_tag_aliases(aliases, &format!("Alias tag {} to {}", added.alias, added.tag))?;
+        Ok(added)
+    }
+
+    /// Stop replacing `alias`
+    pub fn remove_tag_alias(&self, alias: &str) -> Result<(), MemoryError> {
+        let _guard = self.tag_aliases_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut aliases = self.tag_aliases()?;
+        let count = aliases.len();
+        aliases.retain(|existing| !existing.alias.eq_ignore_ascii_case(alias));
+        if aliases.len() == count {
+            return Err(MemoryError::NotFound(format!("tag alias {}", alias)));
+        }
+        self.write_tag_aliases(aliases, &format!("Remove tag alias {}", alias))
+    }
+
+    /// Tags with aliases replaced, without duplicates
+    pub fn normalize_tags(&self, tags: &[String]) -> Result<Vec<String>, MemoryError> {
+        Ok(normalize(&self.tag_aliases()?, tags))
+    }
+
+    /// The lowercased tags a search for `tag` matches: the tag it stands for and every alias of that tag
+    pub fn tag_synonyms(&self, tag: &str) -> Result<Vec<String>, MemoryError> {
+        let aliases = self.tag_aliases()?;
+        let tag = resolve(&aliases, tag).to_lowercase();
+        let mut synonyms: Vec<String> = aliases.iter()
+            .filter(|alias| alias.tag.to_lowercase() == tag)
+            .map(|alias| alias.alias.to_lowercase())
+            .collect();
+        synonyms.push(tag);
+        Ok(synonyms)
+    }
+
+    // The memory with aliased tags replaced, if it has any
+    pub(super) fn with_normalized_tags(&self, memory: &Memory) -> Result<Option<Memory>, MemoryError> {
+        let aliases = self.tag_aliases()?;
+        let tags = normalize(&aliases, &memory.tags);
+        if tags == memory.tags {
+            return Ok(None);
+        }
+        let mut normalized = memory.clone();
+        normalized.tags = tags;
+        Ok(Some(normalized))
+    }
+
+    /// Replace aliased tags on every existing memory
+    ///
+    /// Memories saved after an alias is added get the new tag anyway; this
+    /// updates the ones written before. Read-only memories are left as they
+    /// are. With `dry_run` set, nothing is changed and the report lists what would be.
+    pub fn apply_tag_aliases(&self, dry_run: bool) -> Result<TagMigration, MemoryError> {
+        let _change = if dry_run { None } else { Some(self.begin_change()?) };
+        let aliases = self.tag_aliases()?;
+
+        let mut memories = Vec::new();
+        for memory in self.list()?.into_iter().filter(|memory| !memory.read_only) {
+            let tags = normalize(&aliases, &memory.tags);
+            if tags == memory.tags {
+                continue;
+            }
+            if !dry_run {
+                let mut retagged = memory.clone();
+                retagged.tags = tags.clone();
+                self.save_as(&retagged, Some(&format!("Apply tag aliases to memory: {} ({})", memory.title, memory.id)))?;
+            }
+            memories.push(RetaggedMemory {
+                id: memory.id,
+                title: memory.title,
+                before: memory.tags,
+                after: tags,
+            });
+        }
+
+        tracing::debug!("Applied tag aliases to {} memories", memories.len());
+        Ok(TagMigration { dry_run, memories })
+    }
+}
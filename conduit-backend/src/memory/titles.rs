@@ -0,0 +1,108 @@
+use super::{Memory, MemoryError, MemoryStore};
+
+/// Longest title derived from content, in characters
+const MAX_TITLE_CHARS: usize = 60;
+
+/// Longest summary derived from content, in characters
+const MAX_SUMMARY_CHARS: usize = 200;
+
+// Remove list, quote and emphasis markers around a line of markdown
+fn strip_markdown(line: &str) -> String {
+    let line = line.trim_start_matches(['>', ' ', '\t']);
+    let line = line
+        .strip_prefix("- [ ] ")
+        .or_else(|| line.strip_prefix("- [x] "))
+        .or_else(|| line.strip_prefix("- "))
+        .or_else(|| line.strip_prefix("* "))
+        .or_else(|| line.strip_prefix("+ "))
+        .unwrap_or(line);
+
+    line.chars()
+        .filter(|c| !matches!(c, '*' | '_' | '`'))
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+// The first sentence of a line, including its closing punctuation
+fn first_sentence(line: &str) -> &str {
+    let mut chars = line.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if matches!(c, '.' | '!' | '?') && chars.peek().is_none_or(|(_, next)| next.is_whitespace()) {
+            return &line[..i + c.len_utf8()];
+        }
+    }
+    line
+}
+
+// Shorten to `max` characters at a word boundary
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+
+    let cut: String = text.chars().take(max).collect();
+    let cut = match cut.rfind(char::is_whitespace) {
+        Some(end) if end > max / 2 => &cut[..end],
+        _ => cut.as_str(),
+    };
+    format!("{}…", cut.trim_end_matches([' ', ',', ';', ':', '-']))
+}
+
+/// Derive a title for untitled content
+///
+/// Uses the first markdown heading, or otherwise the first sentence of the
+/// first non-empty line, shortened to 60 characters. Returns `None` when the
+/// content has no text.
+pub fn title_from_content(content: &str) -> Option<String> {
+    let lines = || content.lines().map(str::trim).filter(|line| !line.is_empty());
+
+    let heading = lines()
+        .find(|line| line.starts_with('#'))
+        .map(|line| line.trim_start_matches('#').trim())
+        .filter(|heading| !heading.is_empty());
+
+    let title = match heading {
+        Some(heading) => strip_markdown(heading),
+        None => {
+            let line = lines().map(strip_markdown).find(|line| !line.is_empty())?;
+            first_sentence(&line).trim_end_matches('.').to_string()
+        }
+    };
+
+    Some(truncate(&title, MAX_TITLE_CHARS)).filter(|title| !title.is_empty())
+}
+
+/// A short plain-text summary of content, for listings
+///
+/// Joins the text of the lines that are not headings, without markdown
+/// markers, and shortens it to 200 characters.
+pub fn summary_from_content(content: &str) -> String {
+    let text = content.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(strip_markdown)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    truncate(&text, MAX_SUMMARY_CHARS)
+}
+
+impl MemoryStore {
+    /// Store or clear the summary of a memory, without touching its `updated_at`
+    ///
+    /// The summary is kept on one line of the frontmatter, so line breaks and
+    /// runs of whitespace are collapsed into single spaces.
+    pub fn set_summary(&self, id: &str, summary: Option<&str>) -> Result<Memory, MemoryError> {
+        let mut memory = self.get(id)?;
+        if memory.read_only {
+            return Err(MemoryError::ReadOnly(id.to_string()));
+        }
+
+        memory.summary = summary
+            .map(|summary| summary.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|summary| !summary.is_empty());
+        self.save(&memory)?;
+        Ok(memory)
+    }
+}
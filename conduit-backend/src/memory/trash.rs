@@ -0,0 +1,190 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{is_valid_id, Memory, MemoryError, MemoryEvent, MemoryMarkdown, MemoryStore};
+
+const TRASH_DIR: &str = ".trash";
+
+/// Sidecar stored next to each trashed memory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TrashInfo {
+    deleted_at: DateTime<Utc>,
+    collection: Option<String>,
+}
+
+/// A memory in the trash
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TrashedMemory {
+    pub memory: Memory,
+    pub deleted_at: DateTime<Utc>,
+    /// When the memory will be purged automatically, if a retention period is configured
+    pub purge_at: Option<DateTime<Utc>>,
+}
+
+impl MemoryStore {
+    pub(super) fn trash_dir(&self) -> PathBuf {
+        self.base_path.join(TRASH_DIR)
+    }
+
+    fn trash_paths(&self, id: &str) -> (PathBuf, PathBuf) {
+        let dir = self.trash_dir();
+        (dir.join(format!("{}.md", id)), dir.join(format!("{}.json", id)))
+    }
+
+    fn purge_at(&self, deleted_at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.options.trash_retention_days
+            .map(|days| deleted_at + Duration::days(days as i64))
+    }
+
+    // Move a memory file into the trash, unless an earlier memory with the same ID
+    // is still there, which would be lost
+    pub(super) fn move_to_trash(&self, id: &str, path: &Path) -> Result<(), MemoryError> {
+        fs::create_dir_all(self.trash_dir())?;
+        let (trash_path, info_path) = self.trash_paths(id);
+        if trash_path.exists() {
+            return Err(MemoryError::AlreadyExists(format!("{} in the trash", id)));
+        }
+
+        let info = TrashInfo {
+            deleted_at: Utc::now(),
+            collection: self.collection_for(path),
+        };
+        let info = serde_json::to_string(&info)
+            .map_err(|e| MemoryError::InvalidFormat(e.to_string()))?;
+
+        fs::write(&info_path, info)?;
+        fs::rename(path, &trash_path)?;
+
+        Ok(())
+    }
+
+    fn read_trashed(&self, id: &str) -> Result<TrashedMemory, MemoryError> {
+        let (trash_path, info_path) = self.trash_paths(id);
+        if !is_valid_id(id) || !trash_path.exists() {
+            return Err(MemoryError::NotFound(id.to_string()));
+        }
+
+        let mut memory = Memory::from_markdown(&fs::read_to_string(&trash_path)?)?;
+        let info = fs::read_to_string(&info_path).ok()
+            .and_then(|info| serde_json::from_str::<TrashInfo>(&info).ok());
+
+        // Fall back to the file's modification time if the sidecar is missing
+        let deleted_at = match &info {
+            Some(info) => info.deleted_at,
+            None => fs::metadata(&trash_path)?.modified().map(DateTime::<Utc>::from)?,
+        };
+        memory.collection = info.and_then(|info| info.collection);
+
+        Ok(TrashedMemory {
+            memory,
+            deleted_at,
+            purge_at: self.purge_at(deleted_at),
+        })
+    }
+
+    /// List memories in the trash, most recently deleted first
+    pub fn list_trash(&self) -> Result<Vec<TrashedMemory>, MemoryError> {
+        self.purge_expired_trash()?;
+        self.read_trash()
+    }
+
+    // Read everything in the trash without purging expired entries
+    pub(super) fn read_trash(&self) -> Result<Vec<TrashedMemory>, MemoryError> {
+        let dir = self.trash_dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut trashed = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "md") {
+                let Some(id) = path.file_stem().map(|s| s.to_string_lossy().to_string()) else { continue };
+                match self.read_trashed(&id) {
+                    Ok(item) => trashed.push(item),
+                    Err(e) => tracing::debug!("Error reading trashed memory {:?}: {:?}", path, e),
+                }
+            }
+        }
+
+        trashed.sort_by_key(|item| std::cmp::Reverse(item.deleted_at));
+        Ok(trashed)
+    }
+
+    /// Restore a memory from the trash to its original collection
+    pub fn restore(&self, id: &str) -> Result<Memory, MemoryError> {
+        let _change = self.begin_change()?;
+        let trashed = self.read_trashed(id)?;
+        if self.find_memory_path(id)?.is_some() {
+            return Err(MemoryError::AlreadyExists(id.to_string()));
+        }
+
+        let (trash_path, info_path) = self.trash_paths(id);
+        let target = self.target_path(&trashed.memory)?;
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::rename(&trash_path, &target)?;
+        self.invalidate_link_index();
+        self.events.publish(MemoryEvent::created(&trashed.memory));
+        let _ = fs::remove_file(info_path);
+        self.git_commit(&format!("Restore memory: {} ({})", trashed.memory.title, id));
+
+        Ok(trashed.memory)
+    }
+
+    /// Permanently delete a memory from the trash, along with its attachments,
+    /// embedding and collection memberships
+    pub fn purge(&self, id: &str) -> Result<(), MemoryError> {
+        let _change = self.begin_change()?;
+        let (trash_path, info_path) = self.trash_paths(id);
+        if !is_valid_id(id) || !trash_path.exists() {
+            return Err(MemoryError::NotFound(id.to_string()));
+        }
+
+        fs::remove_file(trash_path)?;
+        let _ = fs::remove_file(info_path);
+        self.remove_attachments(id)?;
+        self.remove_attachment_text(id, None)?;
+        self.remove_embedding(id)?;
+        self.replace_in_collections(id, None)?;
+        self.git_commit(&format!("Purge memory: {}", id));
+
+        Ok(())
+    }
+
+    /// Purge trashed memories older than the configured retention period
+    ///
+    /// Returns the IDs of purged memories.
+    pub fn purge_expired_trash(&self) -> Result<Vec<String>, MemoryError> {
+        let _change = self.begin_change()?;
+        let dir = self.trash_dir();
+        if self.options.trash_retention_days.is_none() || !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let now = Utc::now();
+        let mut purged = Vec::new();
+
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "md") {
+                continue;
+            }
+            let Some(id) = path.file_stem().map(|s| s.to_string_lossy().to_string()) else { continue };
+
+            if let Ok(trashed) = self.read_trashed(&id) {
+                if trashed.purge_at.is_some_and(|purge_at| purge_at <= now) {
+                    self.purge(&id)?;
+                    purged.push(id);
+                }
+            }
+        }
+
+        Ok(purged)
+    }
+}
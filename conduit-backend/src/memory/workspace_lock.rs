@@ -0,0 +1,254 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
+use hmac::Hmac;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use utoipa::ToSchema;
+
+use crate::util::write_atomic;
+use super::{MemoryError, MemoryStore};
+
+/// File inside the store holding the hash of the workspace passphrase
+const LOCK_FILE: &str = ".workspace-lock.json";
+
+/// PBKDF2 rounds used to hash new passphrases
+const PBKDF2_ROUNDS: u32 = 100_000;
+
+/// Rounds a stored hash must use to be checked; weaker or absurdly slow
+/// hashes in an edited file never verify
+const MIN_PBKDF2_ROUNDS: u32 = 100_000;
+const MAX_PBKDF2_ROUNDS: u32 = 10_000_000;
+
+/// Shortest passphrase accepted, in characters
+const MIN_PASSPHRASE_CHARS: usize = 8;
+
+/// When the workspace locks itself, set under `[storage.lock]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LockPolicy {
+    /// Seconds without API requests or app commands after which the workspace
+    /// locks; `0` only locks when asked to
+    pub idle_timeout_secs: u64,
+}
+
+impl Default for LockPolicy {
+    fn default() -> Self {
+        Self { idle_timeout_secs: 900 }
+    }
+}
+
+impl LockPolicy {
+    /// Override the idle timeout with `CONDUIT_LOCK_IDLE_TIMEOUT_SECS`, if set
+    pub fn apply_env(&mut self) {
+        if let Some(secs) = std::env::var("CONDUIT_LOCK_IDLE_TIMEOUT_SECS").ok().and_then(|v| v.parse().ok()) {
+            self.idle_timeout_secs = secs;
+        }
+    }
+}
+
+fn pbkdf2(passphrase: &str, salt: &[u8], rounds: u32) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    pbkdf2::pbkdf2::<Hmac<Sha256>>(passphrase.as_bytes(), salt, rounds, &mut hash)
+        .expect("HMAC accepts keys of any length");
+    hash
+}
+
+/// Salted hash of a passphrase, as stored in the lock file and with accounts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct PassphraseHash {
+    rounds: u32,
+    salt: String,
+    hash: String,
+}
+
+impl PassphraseHash {
+    pub(crate) fn new(passphrase: &str) -> Self {
+        let mut salt = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut salt);
+        Self {
+            rounds: PBKDF2_ROUNDS,
+            salt: hex::encode(salt),
+            hash: hex::encode(pbkdf2(passphrase, &salt, PBKDF2_ROUNDS)),
+        }
+    }
+
+    pub(crate) fn verify(&self, passphrase: &str) -> bool {
+        if !(MIN_PBKDF2_ROUNDS..=MAX_PBKDF2_ROUNDS).contains(&self.rounds) {
+            tracing::warn!("Refusing a passphrase hash with {} PBKDF2 rounds", self.rounds);
+            return false;
+        }
+        let (Ok(salt), Ok(expected)) = (hex::decode(&self.salt), hex::decode(&self.hash)) else {
+            return false;
+        };
+        let hash = pbkdf2(passphrase, &salt, self.rounds);
+        // Compare every byte so the time taken does not reveal how much matched
+        expected.len() == hash.len() && expected.iter().zip(hash.iter()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+    }
+}
+
+/// Whether the workspace is locked, for showing a lock screen
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct LockStatus {
+    /// Whether a passphrase is set; without one the workspace never locks
+    pub enabled: bool,
+    pub locked: bool,
+    pub locked_at: Option<DateTime<Utc>>,
+    /// Seconds of inactivity after which the workspace locks; `0` never locks on its own
+    pub idle_timeout_secs: u64,
+}
+
+struct LockState {
+    passphrase: Option<PassphraseHash>,
+    locked_at: Option<DateTime<Utc>>,
+    last_activity: Instant,
+}
+
+/// Passphrase lock of a workspace, shared by the API server and the desktop app
+///
+/// While locked, the API answers `423 Locked`, the desktop app refuses its
+/// commands and the server's background work (sync, scheduled tasks,
+/// retention, embedding, connectors and webhooks) holds off until the
+/// passphrase is given again. The lock only guards access through Conduit:
+/// the store is not encrypted, so the memories stay readable on disk.
+pub struct WorkspaceLock {
+    path: PathBuf,
+    idle_timeout: Option<Duration>,
+    state: Mutex<LockState>,
+}
+
+impl WorkspaceLock {
+    // A store with a passphrase opens locked, so restarting the app does not unlock it
+    pub(super) fn open(base_path: &Path, policy: &LockPolicy) -> Self {
+        let path = base_path.join(LOCK_FILE);
+        let passphrase = match fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str::<PassphraseHash>(&json)
+                .map_err(|e| tracing::warn!("Ignoring unreadable workspace lock file {:?}: {}", path, e))
+                .ok(),
+            Err(_) => None,
+        };
+        Self {
+            path,
+            idle_timeout: (policy.idle_timeout_secs > 0).then(|| Duration::from_secs(policy.idle_timeout_secs)),
+            state: Mutex::new(LockState {
+                locked_at: passphrase.as_ref().map(|_| Utc::now()),
+                passphrase,
+                last_activity: Instant::now(),
+            }),
+        }
+    }
+
+    // The lock state, locked first if the workspace has been idle for too long
+    fn state(&self) -> MutexGuard<'_, LockState> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let idle = self.idle_timeout.is_some_and(|timeout| state.last_activity.elapsed() >= timeout);
+        if state.passphrase.is_some() && state.locked_at.is_none() && idle {
+            tracing::info!("Workspace idle, locking it");
+            state.locked_at = Some(Utc::now());
+        }
+        state
+    }
+
+    fn status_of(&self, state: &LockState) -> LockStatus {
+        LockStatus {
+            enabled: state.passphrase.is_some(),
+            locked: state.locked_at.is_some(),
+            locked_at: state.locked_at,
+            idle_timeout_secs: self.idle_timeout.map_or(0, |timeout| timeout.as_secs()),
+        }
+    }
+
+    pub fn status(&self) -> LockStatus {
+        self.status_of(&self.state())
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.state().locked_at.is_some()
+    }
+
+    /// Check that the workspace is unlocked, counting the check as activity
+    pub fn check(&self) -> Result<(), MemoryError> {
+        let mut state = self.state();
+        if state.locked_at.is_some() {
+            return Err(MemoryError::Locked);
+        }
+        state.last_activity = Instant::now();
+        Ok(())
+    }
+
+    /// Lock the workspace until the passphrase is given
+    pub fn lock(&self) -> Result<LockStatus, MemoryError> {
+        let mut state = self.state();
+        if state.passphrase.is_none() {
+            return Err(MemoryError::InvalidPassphrase("Set a passphrase before locking the workspace".to_string()));
+        }
+        if state.locked_at.is_none() {
+            tracing::info!("Locking the workspace");
+            state.locked_at = Some(Utc::now());
+        }
+        Ok(self.status_of(&state))
+    }
+
+    /// Unlock the workspace with its passphrase
+    pub fn unlock(&self, passphrase: &str) -> Result<LockStatus, MemoryError> {
+        let mut state = self.state();
+        let Some(stored) = &state.passphrase else {
+            return Ok(self.status_of(&state));
+        };
+        if !stored.verify(passphrase) {
+            tracing::warn!("Wrong passphrase given to unlock the workspace");
+            return Err(MemoryError::WrongPassphrase);
+        }
+        tracing::info!("Unlocking the workspace");
+        state.locked_at = None;
+        state.last_activity = Instant::now();
+        Ok(self.status_of(&state))
+    }
+
+    /// Set, change or, with no `new` passphrase, remove the passphrase
+    ///
+    /// The workspace must be unlocked, and `current` must match the passphrase
+    /// already set, if any.
+    pub fn set_passphrase(&self, current: Option<&str>, new: Option<&str>) -> Result<LockStatus, MemoryError> {
+        let mut state = self.state();
+        if state.locked_at.is_some() {
+            return Err(MemoryError::Locked);
+        }
+        if let Some(stored) = &state.passphrase {
+            if !current.is_some_and(|current| stored.verify(current)) {
+                return Err(MemoryError::WrongPassphrase);
+            }
+        }
+
+        match new {
+            Some(new) => {
+                if new.chars().count() < MIN_PASSPHRASE_CHARS {
+                    return Err(MemoryError::InvalidPassphrase(format!(
+                        "The passphrase must have at least {} characters", MIN_PASSPHRASE_CHARS,
+                    )));
+                }
+                let hash = PassphraseHash::new(new);
+                write_atomic(&self.path, serde_json::to_string_pretty(&hash).map_err(|e| MemoryError::InvalidFormat(e.to_string()))?)?;
+                state.passphrase = Some(hash);
+            }
+            None => {
+                if self.path.exists() {
+                    fs::remove_file(&self.path)?;
+                }
+                state.passphrase = None;
+            }
+        }
+        state.last_activity = Instant::now();
+        Ok(self.status_of(&state))
+    }
+}
+
+impl MemoryStore {
+    /// The passphrase lock of this store
+    pub fn workspace_lock(&self) -> &WorkspaceLock {
+        &self.workspace_lock
+    }
+}
@@ -0,0 +1,151 @@
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::net::{SocketAddr, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::util::write_atomic;
+use super::{MemoryError, MemoryStore};
+
+/// File locked by the process writing to the store
+const LOCK_FILE: &str = ".conduit-lock";
+
+/// File in which the writer records where it serves the store
+const INSTANCE_FILE: &str = ".conduit-instance.json";
+
+/// How long to wait for a recorded writer to accept a connection
+const PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// The Conduit process serving a store
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstanceInfo {
+    pub pid: u32,
+    /// Where the API server listens, once it is bound
+    pub addr: Option<SocketAddr>,
+    pub version: String,
+    pub started_at: DateTime<Utc>,
+}
+
+impl InstanceInfo {
+    /// Address to reach the instance at from this machine
+    ///
+    /// A server bound to all interfaces is reached over the loopback interface.
+    pub fn connect_addr(&self) -> Option<SocketAddr> {
+        self.addr.map(reachable_addr)
+    }
+
+    // Whether the recorded server still accepts connections
+    fn is_reachable(&self) -> bool {
+        self.connect_addr()
+            .is_some_and(|addr| TcpStream::connect_timeout(&addr, PROBE_TIMEOUT).is_ok())
+    }
+}
+
+/// Address to connect to a server bound to `addr` from the same machine,
+/// through the loopback interface when it is bound to all interfaces
+pub(crate) fn reachable_addr(mut addr: SocketAddr) -> SocketAddr {
+    if addr.ip().is_unspecified() {
+        addr.set_ip(if addr.is_ipv4() { [127, 0, 0, 1].into() } else { std::net::Ipv6Addr::LOCALHOST.into() });
+    }
+    addr
+}
+
+/// Outcome of trying to become the single writer of a store
+#[derive(Debug)]
+pub enum Election {
+    /// This process writes to the store until the guard is dropped
+    Won(WriterGuard),
+    /// Another process writes to the store; its details, if it recorded them
+    Lost(Option<InstanceInfo>),
+}
+
+/// Proof that this process is the writer of a store
+///
+/// The lock is held by the operating system, so it is released even when the
+/// process is killed. Dropping the guard releases it and removes the
+/// recorded instance details.
+#[derive(Debug)]
+pub struct WriterGuard {
+    // Held only to keep the lock
+    _lock: Option<File>,
+    instance_path: PathBuf,
+    info: InstanceInfo,
+}
+
+impl WriterGuard {
+    /// Record the address the API server listens on, for other instances to find
+    pub fn record_addr(&mut self, addr: SocketAddr) -> Result<(), MemoryError> {
+        self.info.addr = Some(addr);
+        write_info(&self.instance_path, &self.info)
+    }
+
+    pub fn info(&self) -> &InstanceInfo {
+        &self.info
+    }
+}
+
+impl Drop for WriterGuard {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.instance_path);
+    }
+}
+
+fn write_info(path: &Path, info: &InstanceInfo) -> Result<(), MemoryError> {
+    let json = serde_json::to_string_pretty(info).map_err(|e| MemoryError::InvalidFormat(e.to_string()))?;
+    write_atomic(path, json)?;
+    Ok(())
+}
+
+impl MemoryStore {
+    /// Details of the process currently recorded as the writer, if any
+    pub fn instance_info(&self) -> Option<InstanceInfo> {
+        let json = fs::read_to_string(self.base_path.join(INSTANCE_FILE)).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    /// Try to become the only process writing to the store
+    ///
+    /// An exclusive lock on a file in the store decides the election, so two
+    /// app launches cannot both index and write the same store. Where the
+    /// filesystem does not support locks, such as some network shares, the
+    /// recorded writer is probed instead and loses its claim once its server
+    /// stops accepting connections.
+    pub fn elect_writer(&self) -> Result<Election, MemoryError> {
+        self.check_available()?;
+        self.check_writable()?;
+
+        let lock = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.base_path.join(LOCK_FILE))?;
+
+        let lock = match lock.try_lock() {
+            Ok(()) => Some(lock),
+            Err(TryLockError::WouldBlock) => return Ok(Election::Lost(self.instance_info())),
+            Err(TryLockError::Error(e)) => {
+                tracing::debug!("Store lock unsupported, probing the recorded writer instead: {}", e);
+                if let Some(owner) = self.instance_info() {
+                    if owner.pid != std::process::id() && owner.is_reachable() {
+                        return Ok(Election::Lost(Some(owner)));
+                    }
+                }
+                None
+            }
+        };
+
+        let guard = WriterGuard {
+            _lock: lock,
+            instance_path: self.base_path.join(INSTANCE_FILE),
+            info: InstanceInfo {
+                pid: std::process::id(),
+                addr: None,
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                started_at: Utc::now(),
+            },
+        };
+        write_info(&guard.instance_path, &guard.info)?;
+        Ok(Election::Won(guard))
+    }
+}
@@ -0,0 +1,297 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::api::openai::EmbeddingResponse;
+use crate::util::fnv1a;
+use super::{ollama, ModelRegistry, ProviderError, ProviderKind, UpstreamProvider};
+
+/// Model name served by the built-in local embedding model
+pub const LOCAL_EMBEDDING_MODEL: &str = "conduit-local-embed";
+
+/// Vectors for a batch of inputs, in input order
+#[derive(Debug, Clone)]
+pub struct Embeddings {
+    /// Model that produced the vectors
+    pub model: String,
+    pub vectors: Vec<Vec<f32>>,
+    /// Number of input tokens the model processed
+    pub prompt_tokens: u32,
+}
+
+/// A source of text embeddings
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    /// Name used in logs
+    fn name(&self) -> &str;
+
+    /// Whether this provider serves the given model
+    fn supports(&self, model: &str) -> bool;
+
+    /// Embed a batch of texts, returning one vector per input
+    async fn embed(&self, model: &str, input: &[String]) -> Result<Embeddings, ProviderError>;
+}
+
+#[derive(Serialize)]
+struct UpstreamEmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[async_trait]
+impl EmbeddingProvider for UpstreamProvider {
+    fn name(&self) -> &str {
+        self.owned_by()
+    }
+
+    // The upstream decides which models it knows about
+    fn supports(&self, _model: &str) -> bool {
+        true
+    }
+
+    async fn embed(&self, model: &str, input: &[String]) -> Result<Embeddings, ProviderError> {
+        info!("[PROVIDER] Forwarding {} embedding inputs for model {} to {}", input.len(), model, self.config().base_url);
+
+        self.guarded(async {
+            let embeddings = match self.config().kind {
+                ProviderKind::OpenAi => {
+                    let response = Self::send(self.request("embeddings").json(&UpstreamEmbeddingRequest { model, input })).await?;
+                    let mut response = response.json::<EmbeddingResponse>().await?;
+                    response.data.sort_by_key(|data| data.index);
+                    Embeddings {
+                        model: response.model,
+                        vectors: response.data.into_iter().map(|data| data.embedding).collect(),
+                        prompt_tokens: response.usage.prompt_tokens.max(0) as u32,
+                    }
+                }
+                ProviderKind::Ollama => {
+                    let response = Self::send(self.request("api/embed").json(&ollama::EmbedRequest { model, input })).await?;
+                    let response = response.json::<ollama::EmbedResponse>().await?;
+                    Embeddings {
+                        model: response.model,
+                        vectors: response.embeddings,
+                        prompt_tokens: response.prompt_eval_count,
+                    }
+                }
+            };
+
+            if embeddings.vectors.len() != input.len() {
+                return Err(ProviderError::InvalidResponse(format!(
+                    "expected {} embeddings, got {}",
+                    input.len(),
+                    embeddings.vectors.len()
+                )));
+            }
+            Ok(embeddings)
+        }).await
+    }
+}
+
+/// Built-in embedding model that runs without a network or model download
+///
+/// Words and their character trigrams are hashed into a fixed number of
+/// signed buckets and the result is L2-normalized, so texts sharing
+/// vocabulary (including inflections and typos) get a high cosine
+/// similarity. It captures lexical rather than semantic similarity.
+#[derive(Debug, Clone)]
+pub struct LocalEmbedder {
+    dimensions: usize,
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(|token| token.to_lowercase())
+        .collect()
+}
+
+impl LocalEmbedder {
+    pub fn new(dimensions: usize) -> Self {
+        Self { dimensions: dimensions.max(1) }
+    }
+
+    pub fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    fn add_feature(&self, vector: &mut [f32], feature: &str, weight: f32) {
+        let hash = fnv1a(feature.as_bytes());
+        let bucket = (hash % self.dimensions as u64) as usize;
+        let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+        vector[bucket] += sign * weight;
+    }
+
+    /// Embed a single text, returning the vector and the number of tokens read
+    pub fn embed_text(&self, text: &str) -> (Vec<f32>, u32) {
+        let mut vector = vec![0.0; self.dimensions];
+        let tokens = tokenize(text);
+
+        for token in &tokens {
+            self.add_feature(&mut vector, token, 1.0);
+
+            let chars: Vec<char> = format!(" {} ", token).chars().collect();
+            for trigram in chars.windows(3) {
+                self.add_feature(&mut vector, &trigram.iter().collect::<String>(), 0.5);
+            }
+        }
+
+        let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            vector.iter_mut().for_each(|v| *v /= norm);
+        }
+
+        (vector, tokens.len() as u32)
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for LocalEmbedder {
+    fn name(&self) -> &str {
+        "local"
+    }
+
+    fn supports(&self, model: &str) -> bool {
+        model == LOCAL_EMBEDDING_MODEL
+    }
+
+    async fn embed(&self, _model: &str, input: &[String]) -> Result<Embeddings, ProviderError> {
+        let mut vectors = Vec::with_capacity(input.len());
+        let mut prompt_tokens = 0;
+        for text in input {
+            let (vector, tokens) = self.embed_text(text);
+            vectors.push(vector);
+            prompt_tokens += tokens;
+        }
+
+        Ok(Embeddings {
+            model: LOCAL_EMBEDDING_MODEL.to_string(),
+            vectors,
+            prompt_tokens,
+        })
+    }
+}
+
+/// Which provider handles embedding models that are not served locally
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbeddingBackend {
+    /// Forward to the upstream provider, falling back to the local model when none is configured
+    Upstream,
+    /// Always use the local model
+    Local,
+}
+
+impl std::str::FromStr for EmbeddingBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "upstream" => Ok(Self::Upstream),
+            "local" => Ok(Self::Local),
+            other => Err(format!("Unknown embedding provider: {}", other)),
+        }
+    }
+}
+
+/// Server-wide embedding settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmbeddingConfig {
+    pub backend: EmbeddingBackend,
+    /// Vector size of the local model
+    pub local_dimensions: usize,
+    /// Model used to embed stored memories
+    pub model: String,
+    /// Seconds between checks for memories that need (re-)embedding; `0` disables the background job
+    pub sync_interval_secs: u64,
+}
+
+impl Default for EmbeddingConfig {
+    fn default() -> Self {
+        Self {
+            backend: EmbeddingBackend::Upstream,
+            local_dimensions: 384,
+            model: LOCAL_EMBEDDING_MODEL.to_string(),
+            sync_interval_secs: 60,
+        }
+    }
+}
+
+impl EmbeddingConfig {
+    /// Read embedding settings from `CONDUIT_EMBEDDING_PROVIDER`,
+    /// `CONDUIT_LOCAL_EMBEDDING_DIMENSIONS`, `CONDUIT_EMBEDDING_MODEL` and
+    /// `CONDUIT_EMBEDDING_SYNC_INTERVAL`, falling back to the defaults
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        config.apply_env();
+        config
+    }
+
+    /// Override settings with the embedding variables that are set
+    pub fn apply_env(&mut self) {
+        if let Some(backend) = std::env::var("CONDUIT_EMBEDDING_PROVIDER").ok().and_then(|v| v.parse().ok()) {
+            self.backend = backend;
+        }
+        if let Some(dimensions) = std::env::var("CONDUIT_LOCAL_EMBEDDING_DIMENSIONS").ok().and_then(|v| v.parse().ok()) {
+            self.local_dimensions = dimensions;
+        }
+        if let Some(model) = std::env::var("CONDUIT_EMBEDDING_MODEL").ok().filter(|m| !m.is_empty()) {
+            self.model = model;
+        }
+        if let Some(interval) = std::env::var("CONDUIT_EMBEDDING_SYNC_INTERVAL").ok().and_then(|v| v.parse().ok()) {
+            self.sync_interval_secs = interval;
+        }
+    }
+}
+
+/// Picks the embedding provider for a request's model
+pub struct EmbeddingRouter {
+    local: Arc<LocalEmbedder>,
+    upstream: Option<Arc<UpstreamProvider>>,
+    models: Arc<ModelRegistry>,
+    backend: EmbeddingBackend,
+    model: String,
+}
+
+impl EmbeddingRouter {
+    pub fn new(config: &EmbeddingConfig, upstream: Option<Arc<UpstreamProvider>>, models: Arc<ModelRegistry>) -> Self {
+        Self {
+            local: Arc::new(LocalEmbedder::new(config.local_dimensions)),
+            upstream,
+            models,
+            backend: config.backend,
+            model: config.model.clone(),
+        }
+    }
+
+    /// Model used to embed stored memories
+    pub fn memory_model(&self) -> &str {
+        &self.model
+    }
+
+    /// Vector length of the local model
+    pub fn local_dimensions(&self) -> usize {
+        self.local.dimensions()
+    }
+
+    /// The provider serving `model`, and the name it knows the model by
+    ///
+    /// The local model is used when the server is configured for local
+    /// embeddings, for models registered with the `local` provider, and when
+    /// no provider serves the model. Models that are not registered go to
+    /// the upstream.
+    pub fn select(&self, model: &str) -> (Arc<dyn EmbeddingProvider>, String) {
+        if self.backend == EmbeddingBackend::Local {
+            return (self.local.clone(), model.to_string());
+        }
+        let (provider, target) = match self.models.lookup(model) {
+            Some(route) => (route.provider, route.target),
+            None => (self.upstream.clone(), model.to_string()),
+        };
+        match provider {
+            Some(provider) => (provider, target),
+            None => (self.local.clone(), target),
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Number of recent requests used to compute the error rate
+const WINDOW_SIZE: usize = 50;
+
+/// Seconds a half-open probe may take before another request probes instead,
+/// in case the first one was cancelled and never reports back
+const PROBE_TIMEOUT_SECS: i64 = 60;
+
+/// State of the circuit breaker guarding an upstream provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// Requests are forwarded normally
+    Closed,
+    /// The upstream looks down; requests fail fast until the cooldown ends
+    Open,
+    /// The cooldown ended; a single probe request decides whether the circuit
+    /// closes again, while the others keep failing fast
+    HalfOpen,
+}
+
+/// Health of the upstream provider, reported by the diagnostics endpoint
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ProviderHealth {
+    pub base_url: String,
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+    pub total_requests: u64,
+    pub total_failures: u64,
+    /// Share of failed requests among the most recent ones
+    pub error_rate: f32,
+    pub last_error: Option<String>,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub last_failure_at: Option<DateTime<Utc>>,
+    /// When an open circuit lets the next request through
+    pub retry_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug)]
+struct BreakerState {
+    state: CircuitState,
+    open_until: Option<DateTime<Utc>>,
+    /// While a probe is in flight, when another request may probe instead
+    probe_until: Option<DateTime<Utc>>,
+    consecutive_failures: u32,
+    total_requests: u64,
+    total_failures: u64,
+    recent: VecDeque<bool>,
+    last_error: Option<String>,
+    last_success_at: Option<DateTime<Utc>>,
+    last_failure_at: Option<DateTime<Utc>>,
+}
+
+/// Trips after a number of consecutive upstream failures and fails fast for a
+/// cooldown period, then lets a request through to probe for recovery
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    inner: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cooldown_secs: u64) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cooldown: Duration::seconds(cooldown_secs as i64),
+            inner: Mutex::new(BreakerState {
+                state: CircuitState::Closed,
+                open_until: None,
+                probe_until: None,
+                consecutive_failures: 0,
+                total_requests: 0,
+                total_failures: 0,
+                recent: VecDeque::with_capacity(WINDOW_SIZE),
+                last_error: None,
+                last_success_at: None,
+                last_failure_at: None,
+            }),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BreakerState> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Check whether a request may be sent
+    ///
+    /// Returns the time the circuit will let requests through again if it is open,
+    /// or half-open with another request probing the upstream.
+    pub fn check(&self) -> Result<(), DateTime<Utc>> {
+        let mut inner = self.lock();
+        let now = Utc::now();
+        match (inner.state, inner.open_until) {
+            (CircuitState::Closed, _) => Ok(()),
+            (CircuitState::Open, Some(until)) if now < until => Err(until),
+            _ => match inner.probe_until {
+                Some(until) if now < until => Err(until),
+                _ => {
+                    inner.state = CircuitState::HalfOpen;
+                    inner.probe_until = Some(now + Duration::seconds(PROBE_TIMEOUT_SECS));
+                    Ok(())
+                }
+            },
+        }
+    }
+
+    fn record(inner: &mut BreakerState, success: bool) {
+        inner.total_requests += 1;
+        if inner.recent.len() == WINDOW_SIZE {
+            inner.recent.pop_front();
+        }
+        inner.recent.push_back(success);
+    }
+
+    /// Record a request the upstream answered
+    pub fn record_success(&self) {
+        let mut inner = self.lock();
+        Self::record(&mut inner, true);
+        inner.consecutive_failures = 0;
+        inner.state = CircuitState::Closed;
+        inner.open_until = None;
+        inner.probe_until = None;
+        inner.last_success_at = Some(Utc::now());
+    }
+
+    /// Record a failed request, opening the circuit once the threshold is reached
+    pub fn record_failure(&self, error: &str) {
+        let mut inner = self.lock();
+        let now = Utc::now();
+        Self::record(&mut inner, false);
+        inner.total_failures += 1;
+        inner.consecutive_failures += 1;
+        inner.last_error = Some(error.to_string());
+        inner.last_failure_at = Some(now);
+
+        if inner.state == CircuitState::HalfOpen || inner.consecutive_failures >= self.failure_threshold {
+            inner.state = CircuitState::Open;
+            inner.open_until = Some(now + self.cooldown);
+            inner.probe_until = None;
+        }
+    }
+
+    pub fn health(&self, base_url: &str) -> ProviderHealth {
+        let inner = self.lock();
+        let failures = inner.recent.iter().filter(|success| !**success).count();
+
+        ProviderHealth {
+            base_url: base_url.to_string(),
+            state: inner.state,
+            consecutive_failures: inner.consecutive_failures,
+            total_requests: inner.total_requests,
+            total_failures: inner.total_failures,
+            error_rate: if inner.recent.is_empty() { 0.0 } else { failures as f32 / inner.recent.len() as f32 },
+            last_error: inner.last_error.clone(),
+            last_success_at: inner.last_success_at,
+            last_failure_at: inner.last_failure_at,
+            retry_at: inner.open_until.filter(|_| inner.state == CircuitState::Open),
+        }
+    }
+}
@@ -0,0 +1,355 @@
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use futures::stream::{BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::api::openai::{ChatCompletionChunk, ChatCompletionRequest, ChatCompletionResponse, ModelCapabilities, ModelList};
+
+mod embedding;
+mod health;
+mod ollama;
+mod registry;
+mod stream;
+pub mod tokenizer;
+
+pub use embedding::{
+    EmbeddingBackend, EmbeddingConfig, EmbeddingProvider, EmbeddingRouter, Embeddings, LocalEmbedder,
+    LOCAL_EMBEDDING_MODEL,
+};
+pub use health::{CircuitBreaker, CircuitState, ProviderHealth};
+pub use ollama::DEFAULT_OLLAMA_HOST;
+pub use registry::{ModelRegistry, ModelRoute, RegisteredModel, DEFAULT_PROVIDER, LOCAL_PROVIDER};
+
+/// Chunks of a streamed chat completion
+pub type ChatCompletionStream = BoxStream<'static, Result<ChatCompletionChunk, ProviderError>>;
+
+#[derive(Debug, Error)]
+pub enum ProviderError {
+    #[error("Upstream request failed: {0}")]
+    Request(#[from] reqwest::Error),
+
+    #[error("Upstream returned {status}: {body}")]
+    Upstream { status: u16, body: String },
+
+    #[error("Unexpected upstream response: {0}")]
+    InvalidResponse(String),
+
+    #[error("Upstream provider is unavailable after repeated failures, retrying at {retry_at}")]
+    CircuitOpen { retry_at: DateTime<Utc> },
+}
+
+impl ProviderError {
+    // Errors that suggest the upstream is down or overloaded, as opposed to a bad request
+    fn is_upstream_failure(&self) -> bool {
+        match self {
+            ProviderError::Request(_) | ProviderError::InvalidResponse(_) => true,
+            ProviderError::Upstream { status, .. } => *status >= 500 || *status == 429,
+            ProviderError::CircuitOpen { .. } => false,
+        }
+    }
+}
+
+/// API spoken by the upstream provider
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    /// OpenAI-compatible chat completions API
+    #[default]
+    OpenAi,
+    /// Native Ollama API (`/api/chat`, `/api/embed`, `/api/tags`)
+    Ollama,
+}
+
+fn default_failure_threshold() -> u32 {
+    5
+}
+
+fn default_cooldown_secs() -> u64 {
+    30
+}
+
+fn default_timeout_secs() -> u64 {
+    120
+}
+
+/// Configuration for an OpenAI-compatible upstream LLM provider.
+///
+/// Any server that speaks the OpenAI chat completions protocol can be used,
+/// e.g. OpenAI itself, a local Ollama instance (`http://localhost:11434/v1`)
+/// or a llama.cpp server. Ollama can also be used through its native API,
+/// see [`ProviderConfig::ollama`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderConfig {
+    /// Base URL of the upstream API, including the version prefix (e.g. `https://api.openai.com/v1`);
+    /// for Ollama, the server address (e.g. `http://localhost:11434`)
+    pub base_url: String,
+    #[serde(default)]
+    pub kind: ProviderKind,
+    /// Optional API key sent as a bearer token
+    pub api_key: Option<String>,
+    /// Request timeout in seconds
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Consecutive failures after which requests fail fast
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+    /// Seconds to fail fast before trying the upstream again
+    #[serde(default = "default_cooldown_secs")]
+    pub cooldown_secs: u64,
+    /// Models to list in `/v1/models`, with their capabilities
+    #[serde(default)]
+    pub models: Vec<ModelConfig>,
+}
+
+/// A model served by the provider, from a `[[provider.models]]` section
+///
+/// Declared models are listed in `/v1/models` after those of `[[models]]`
+/// sections, followed by any other models the upstream reports.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelConfig {
+    pub id: String,
+    /// Maximum number of prompt and completion tokens
+    pub context_length: Option<u32>,
+    /// Vector length, for embedding models
+    pub embedding_dimensions: Option<usize>,
+    pub capabilities: Option<ModelCapabilities>,
+}
+
+impl ProviderConfig {
+    pub fn new(base_url: impl Into<String>, api_key: Option<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+            kind: ProviderKind::OpenAi,
+            api_key,
+            timeout_secs: default_timeout_secs(),
+            failure_threshold: default_failure_threshold(),
+            cooldown_secs: default_cooldown_secs(),
+            models: Vec::new(),
+        }
+    }
+
+    /// Use a local Ollama server through its native API
+    ///
+    /// `host` may omit the scheme, e.g. `localhost:11434`.
+    pub fn ollama(host: impl Into<String>) -> Self {
+        let host = host.into();
+        let base_url = if host.contains("://") { host } else { format!("http://{}", host) };
+
+        Self {
+            kind: ProviderKind::Ollama,
+            // Local models can take a while to load on the first request
+            timeout_secs: 300,
+            ..Self::new(base_url.trim_end_matches('/'), None)
+        }
+    }
+
+    /// Read the provider configuration from the environment.
+    ///
+    /// `CONDUIT_UPSTREAM_URL` selects an OpenAI-compatible provider and
+    /// `CONDUIT_OLLAMA_HOST` a native Ollama server. Returns `None` when neither
+    /// is set, in which case the server runs in offline mode.
+    pub fn from_env() -> Option<Self> {
+        let base_url = std::env::var("CONDUIT_UPSTREAM_URL").ok().filter(|url| !url.is_empty());
+        let ollama_host = std::env::var("CONDUIT_OLLAMA_HOST").ok().filter(|host| !host.is_empty());
+        let mut config = match (base_url, ollama_host) {
+            (Some(base_url), _) => Self::new(base_url, None),
+            (None, Some(host)) => Self::ollama(host),
+            (None, None) => return None,
+        };
+        config.apply_env();
+        Some(config)
+    }
+
+    /// Override the key, timeout and failure settings with the
+    /// `CONDUIT_UPSTREAM_*` variables that are set
+    pub fn apply_env(&mut self) {
+        if let Some(api_key) = std::env::var("CONDUIT_UPSTREAM_API_KEY").ok().filter(|key| !key.is_empty()) {
+            self.api_key = Some(api_key);
+        }
+        if let Some(timeout) = std::env::var("CONDUIT_UPSTREAM_TIMEOUT").ok().and_then(|t| t.parse().ok()) {
+            self.timeout_secs = timeout;
+        }
+        if let Some(threshold) = std::env::var("CONDUIT_UPSTREAM_FAILURE_THRESHOLD").ok().and_then(|t| t.parse().ok()) {
+            self.failure_threshold = threshold;
+        }
+        if let Some(cooldown) = std::env::var("CONDUIT_UPSTREAM_COOLDOWN").ok().and_then(|c| c.parse().ok()) {
+            self.cooldown_secs = cooldown;
+        }
+    }
+}
+
+/// Client for an OpenAI-compatible upstream provider
+pub struct UpstreamProvider {
+    config: ProviderConfig,
+    client: reqwest::Client,
+    breaker: CircuitBreaker,
+}
+
+impl UpstreamProvider {
+    pub fn new(config: ProviderConfig) -> Result<Self, ProviderError> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .build()?;
+
+        let breaker = CircuitBreaker::new(config.failure_threshold, config.cooldown_secs);
+
+        Ok(Self { config, client, breaker })
+    }
+
+    pub fn config(&self) -> &ProviderConfig {
+        &self.config
+    }
+
+    /// Owner reported for the upstream's models in `/v1/models`
+    pub fn owned_by(&self) -> &'static str {
+        match self.config.kind {
+            ProviderKind::OpenAi => "upstream",
+            ProviderKind::Ollama => "ollama",
+        }
+    }
+
+    /// Error rates and circuit breaker state of the upstream
+    pub fn health(&self) -> ProviderHealth {
+        self.breaker.health(&self.config.base_url)
+    }
+
+    // Run an upstream call through the circuit breaker
+    async fn guarded<T>(&self, call: impl std::future::Future<Output = Result<T, ProviderError>>) -> Result<T, ProviderError> {
+        if let Err(retry_at) = self.breaker.check() {
+            return Err(ProviderError::CircuitOpen { retry_at });
+        }
+
+        let result = call.await;
+        match &result {
+            Err(err) if err.is_upstream_failure() => {
+                warn!("[PROVIDER] Upstream request to {} failed: {}", self.config.base_url, err);
+                self.breaker.record_failure(&err.to_string());
+            }
+            _ => self.breaker.record_success(),
+        }
+        result
+    }
+
+    fn endpoint(&self, path: &str) -> String {
+        format!("{}/{}", self.config.base_url.trim_end_matches('/'), path.trim_start_matches('/'))
+    }
+
+    fn authorize(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.config.api_key {
+            Some(key) => builder.bearer_auth(key),
+            None => builder,
+        }
+    }
+
+    fn request(&self, path: &str) -> reqwest::RequestBuilder {
+        self.authorize(self.client.post(self.endpoint(path)))
+    }
+
+    // Send a request, turning error statuses into `ProviderError::Upstream`
+    async fn send(builder: reqwest::RequestBuilder) -> Result<reqwest::Response, ProviderError> {
+        let response = builder.send().await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(ProviderError::Upstream { status: status.as_u16(), body });
+        }
+        Ok(response)
+    }
+
+    /// Forward a chat completion request to the upstream provider
+    pub async fn chat_completion(&self, req: &ChatCompletionRequest) -> Result<ChatCompletionResponse, ProviderError> {
+        info!("[PROVIDER] Forwarding chat completion for model {} to {}", req.model, self.config.base_url);
+
+        self.guarded(async {
+            match self.config.kind {
+                ProviderKind::OpenAi => {
+                    let response = Self::send(self.request("chat/completions").json(req)).await?;
+                    Ok(response.json::<ChatCompletionResponse>().await?)
+                }
+                ProviderKind::Ollama => {
+                    let body = ollama::ChatRequest::new(req, false);
+                    let response = Self::send(self.request("api/chat").json(&body)).await?;
+                    Ok(response.json::<ollama::ChatResponse>().await?.into_completion())
+                }
+            }
+        }).await
+    }
+
+    /// Forward a chat completion request and stream the response as it is generated
+    ///
+    /// The circuit breaker only sees whether the stream could be opened; errors
+    /// part-way through are returned as stream items.
+    pub async fn chat_completion_stream(&self, req: &ChatCompletionRequest) -> Result<ChatCompletionStream, ProviderError> {
+        info!("[PROVIDER] Streaming chat completion for model {} from {}", req.model, self.config.base_url);
+
+        let kind = self.config.kind;
+        let response = self.guarded(async {
+            match kind {
+                ProviderKind::OpenAi => {
+                    let mut req = req.clone();
+                    req.stream = Some(true);
+                    Self::send(self.request("chat/completions").json(&req)).await
+                }
+                ProviderKind::Ollama => {
+                    Self::send(self.request("api/chat").json(&ollama::ChatRequest::new(req, true))).await
+                }
+            }
+        }).await?;
+
+        let lines = stream::lines(response);
+        let chunks = match kind {
+            // Server-sent events: `data: {chunk}` lines, ending with `data: [DONE]`
+            ProviderKind::OpenAi => lines
+                .filter_map(|line| async move {
+                    match line {
+                        Ok(line) => line.strip_prefix("data:").map(|data| Ok(data.trim().to_string())),
+                        Err(e) => Some(Err(e)),
+                    }
+                })
+                .take_while(|data| futures::future::ready(!matches!(data, Ok(data) if data == "[DONE]")))
+                .map(|data| {
+                    let data = data?;
+                    serde_json::from_str::<ChatCompletionChunk>(&data)
+                        .map_err(|e| ProviderError::InvalidResponse(format!("{}: {}", e, data)))
+                })
+                .boxed(),
+            // Newline-delimited JSON, one object per generated piece
+            ProviderKind::Ollama => {
+                let id = format!("chatcmpl-{}", Uuid::new_v4());
+                lines
+                    .filter(|line| futures::future::ready(!matches!(line, Ok(line) if line.trim().is_empty())))
+                    .map(move |line| {
+                        let line = line?;
+                        serde_json::from_str::<ollama::ChatResponse>(&line)
+                            .map(|response| response.into_chunk(&id))
+                            .map_err(|e| ProviderError::InvalidResponse(format!("{}: {}", e, line)))
+                    })
+                    .boxed()
+            }
+        };
+
+        Ok(chunks)
+    }
+
+    /// Names of the models the upstream serves
+    pub async fn list_models(&self) -> Result<Vec<String>, ProviderError> {
+        self.guarded(async {
+            match self.config.kind {
+                ProviderKind::OpenAi => {
+                    let response = Self::send(self.authorize(self.client.get(self.endpoint("models")))).await?;
+                    let models = response.json::<ModelList>().await?;
+                    Ok(models.data.into_iter().map(|model| model.id).collect())
+                }
+                ProviderKind::Ollama => {
+                    let response = Self::send(self.client.get(self.endpoint("api/tags"))).await?;
+                    let tags = response.json::<ollama::TagsResponse>().await?;
+                    Ok(tags.models.into_iter().map(|model| model.name).collect())
+                }
+            }
+        }).await
+    }
+}
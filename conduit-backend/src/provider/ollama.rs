@@ -0,0 +1,220 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::api::openai::{
+    ChatCompletionChoice, ChatCompletionChunk, ChatCompletionChunkChoice, ChatCompletionRequest,
+    ChatCompletionResponse, ChatCompletionUsage, ChatDelta, ChatMessage, ResponseFormat, Tool,
+};
+
+/// Default address of a local Ollama server
+pub const DEFAULT_OLLAMA_HOST: &str = "http://localhost:11434";
+
+// Native Ollama API types, mapped to the OpenAI-compatible types used by the server
+
+#[derive(Debug, Serialize)]
+pub(super) struct ChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<Message<'a>>,
+    stream: bool,
+    /// `"json"`, or the schema the reply must match
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<Value>,
+    /// Ollama takes tools in the OpenAI format
+    #[serde(skip_serializing_if = "<[Tool]>::is_empty")]
+    tools: &'a [Tool],
+    #[serde(skip_serializing_if = "Options::is_empty")]
+    options: Options,
+}
+
+#[derive(Debug, Serialize)]
+struct Message<'a> {
+    role: &'a str,
+    content: String,
+    /// Base64 data of the message's images
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    images: Vec<&'a str>,
+    /// Calls asked for by the assistant, with their arguments as objects
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<Value>,
+}
+
+impl<'a> Message<'a> {
+    fn new(message: &'a ChatMessage) -> Self {
+        // Ollama only takes inlined images, not links
+        let images = message.content.images()
+            .filter_map(|image| match image.data() {
+                Some((_, data)) => Some(data),
+                None => {
+                    warn!("[PROVIDER] Ollama does not fetch linked images, leaving out {}", image.url);
+                    None
+                }
+            })
+            .collect();
+        let tool_calls = message.tool_calls.iter()
+            .map(|call| {
+                let arguments = serde_json::from_str::<Value>(&call.function.arguments).unwrap_or_else(|_| Value::Object(Default::default()));
+                serde_json::json!({ "function": { "name": call.function.name, "arguments": arguments } })
+            })
+            .collect();
+        Self { role: &message.role, content: message.content.text(), images, tool_calls }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+struct Options {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    /// Ollama's name for `max_tokens`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<i32>,
+}
+
+impl Options {
+    fn is_empty(&self) -> bool {
+        self.temperature.is_none() && self.num_predict.is_none()
+    }
+}
+
+impl<'a> ChatRequest<'a> {
+    pub(super) fn new(req: &'a ChatCompletionRequest, stream: bool) -> Self {
+        Self {
+            model: &req.model,
+            messages: req.messages.iter().map(Message::new).collect(),
+            stream,
+            format: match &req.response_format {
+                Some(ResponseFormat::JsonObject) => Some(Value::from("json")),
+                Some(ResponseFormat::JsonSchema { json_schema }) => {
+                    Some(json_schema.schema.clone().unwrap_or_else(|| Value::from("json")))
+                }
+                Some(ResponseFormat::Text) | None => None,
+            },
+            tools: &req.tools,
+            options: Options {
+                temperature: req.temperature,
+                num_predict: req.max_tokens,
+            },
+        }
+    }
+}
+
+/// A response from `/api/chat`, or one line of a streamed response
+#[derive(Debug, Deserialize)]
+pub(super) struct ChatResponse {
+    model: String,
+    #[serde(default)]
+    message: Option<ChatMessage>,
+    #[serde(default)]
+    done: bool,
+    #[serde(default)]
+    done_reason: Option<String>,
+    #[serde(default)]
+    prompt_eval_count: i32,
+    #[serde(default)]
+    eval_count: i32,
+}
+
+impl ChatResponse {
+    // Ollama leaves out the IDs that tool messages answer calls by
+    fn number_tool_calls(&mut self) {
+        for call in self.message.iter_mut().flat_map(|message| message.tool_calls.iter_mut()) {
+            if call.id.is_empty() {
+                call.id = format!("call_{}", Uuid::new_v4().simple());
+            }
+        }
+    }
+
+    fn finish_reason(&mut self) -> String {
+        let calls_tools = self.message.as_ref().is_some_and(|message| !message.tool_calls.is_empty());
+        match self.done_reason.take() {
+            Some(reason) if reason != "stop" || !calls_tools => reason,
+            _ if calls_tools => "tool_calls".to_string(),
+            _ => "stop".to_string(),
+        }
+    }
+
+    pub(super) fn into_completion(mut self) -> ChatCompletionResponse {
+        self.number_tool_calls();
+        let finish_reason = self.finish_reason();
+        ChatCompletionResponse {
+            id: format!("chatcmpl-{}", Uuid::new_v4()),
+            object: "chat.completion".to_string(),
+            created: Utc::now().timestamp(),
+            model: self.model,
+            choices: vec![ChatCompletionChoice {
+                index: 0,
+                message: self.message.unwrap_or(ChatMessage {
+                    role: "assistant".to_string(),
+                    content: String::new().into(),
+                    ..Default::default()
+                }),
+                finish_reason,
+            }],
+            usage: ChatCompletionUsage {
+                prompt_tokens: self.prompt_eval_count,
+                completion_tokens: self.eval_count,
+                total_tokens: self.prompt_eval_count + self.eval_count,
+            },
+        }
+    }
+
+    /// Convert a streamed line into a chunk; all chunks of a stream share `id`
+    pub(super) fn into_chunk(mut self, id: &str) -> ChatCompletionChunk {
+        self.number_tool_calls();
+        let finish_reason = match self.done {
+            true => Some(self.finish_reason()),
+            false => None,
+        };
+        // Ollama sends each call whole, so each is one part with its index
+        let tool_calls = self.message.as_ref()
+            .filter(|message| !message.tool_calls.is_empty())
+            .map(|message| {
+                let calls = message.tool_calls.iter().enumerate()
+                    .map(|(index, call)| serde_json::json!({ "index": index, "id": call.id, "type": call.kind, "function": call.function }))
+                    .collect();
+                Value::Array(calls)
+            });
+
+        ChatCompletionChunk {
+            id: id.to_string(),
+            object: "chat.completion.chunk".to_string(),
+            created: Utc::now().timestamp(),
+            model: self.model,
+            choices: vec![ChatCompletionChunkChoice {
+                index: 0,
+                delta: ChatDelta {
+                    role: self.message.as_ref().map(|message| message.role.clone()),
+                    content: self.message.map(|message| message.content.text()).filter(|content| !content.is_empty()),
+                    tool_calls,
+                },
+                finish_reason,
+            }],
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct EmbedRequest<'a> {
+    pub model: &'a str,
+    pub input: &'a [String],
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct EmbedResponse {
+    pub model: String,
+    pub embeddings: Vec<Vec<f32>>,
+    #[serde(default)]
+    pub prompt_eval_count: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct TagsResponse {
+    pub models: Vec<TagModel>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct TagModel {
+    pub name: String,
+}
@@ -0,0 +1,324 @@
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::api::openai::{ChatCompletionRequest, Model, ModelCapabilities};
+use super::{ModelConfig, ProviderConfig, ProviderKind, UpstreamProvider, LOCAL_EMBEDDING_MODEL};
+
+/// Provider name of models served by Conduit itself: offline chat and the local embedding model
+pub const LOCAL_PROVIDER: &str = "local";
+
+/// Provider name of the `[provider]` section, used by models that name none
+pub const DEFAULT_PROVIDER: &str = "upstream";
+
+/// How long the upstream's model list is reused before asking again
+const UPSTREAM_MODELS_TTL: Duration = Duration::from_secs(60);
+
+fn default_provider() -> String {
+    DEFAULT_PROVIDER.to_string()
+}
+
+/// A model clients can ask for, from a `[[models]]` section
+///
+/// ```toml
+/// [providers.openai]
+/// base_url = "https://api.openai.com/v1"
+/// api_key = "sk-..."
+///
+/// [[models]]
+/// id = "gpt-4o"
+/// provider = "openai"
+/// aliases = ["default"]
+/// temperature = 0.3
+///
+/// [[models]]
+/// id = "llama3"
+/// upstream_id = "llama3.1:8b"
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegisteredModel {
+    pub id: String,
+    /// Other names the model can be requested by
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// `upstream` for the `[provider]` section, `local` for Conduit's built-in
+    /// models, or the name of a `[providers.<name>]` section
+    #[serde(default = "default_provider")]
+    pub provider: String,
+    /// Name the provider knows the model by, when it differs from `id`
+    pub upstream_id: Option<String>,
+    /// Maximum number of prompt and completion tokens
+    pub context_length: Option<u32>,
+    /// Vector length, for embedding models
+    pub embedding_dimensions: Option<usize>,
+    pub capabilities: Option<ModelCapabilities>,
+    /// Temperature used when a chat request sets none
+    pub temperature: Option<f32>,
+    /// Completion token limit used when a chat request sets none
+    pub max_tokens: Option<i32>,
+}
+
+impl RegisteredModel {
+    fn local(id: &str, capabilities: ModelCapabilities, embedding_dimensions: Option<usize>) -> Self {
+        Self {
+            id: id.to_string(),
+            aliases: Vec::new(),
+            provider: LOCAL_PROVIDER.to_string(),
+            upstream_id: None,
+            context_length: None,
+            embedding_dimensions,
+            capabilities: Some(capabilities),
+            temperature: None,
+            max_tokens: None,
+        }
+    }
+
+    // A model declared in `[[provider.models]]`, served by the default provider
+    fn declared(config: &ModelConfig) -> Self {
+        Self {
+            id: config.id.clone(),
+            aliases: Vec::new(),
+            provider: DEFAULT_PROVIDER.to_string(),
+            upstream_id: None,
+            context_length: config.context_length,
+            embedding_dimensions: config.embedding_dimensions,
+            capabilities: config.capabilities,
+            temperature: None,
+            max_tokens: None,
+        }
+    }
+
+    fn answers_to(&self, id: &str) -> bool {
+        self.id == id || self.aliases.iter().any(|alias| alias == id)
+    }
+}
+
+/// Where requests for a model go, and the options they get
+#[derive(Clone)]
+pub struct ModelRoute {
+    /// Model the client asked for, with aliases resolved
+    pub id: String,
+    /// Name sent to the provider
+    pub target: String,
+    /// Provider serving the model; `None` for models Conduit answers itself
+    pub provider: Option<Arc<UpstreamProvider>>,
+    /// Declared capabilities; `None` when the model was not declared
+    pub capabilities: Option<ModelCapabilities>,
+    temperature: Option<f32>,
+    max_tokens: Option<i32>,
+}
+
+impl ModelRoute {
+    fn new(model: &RegisteredModel, provider: Option<Arc<UpstreamProvider>>) -> Self {
+        Self {
+            id: model.id.clone(),
+            target: model.upstream_id.clone().unwrap_or_else(|| model.id.clone()),
+            provider,
+            capabilities: model.capabilities,
+            temperature: model.temperature,
+            max_tokens: model.max_tokens,
+        }
+    }
+
+    // A model the upstream serves without being declared
+    fn undeclared(id: &str, provider: Arc<UpstreamProvider>) -> Self {
+        Self {
+            id: id.to_string(),
+            target: id.to_string(),
+            provider: Some(provider),
+            capabilities: None,
+            temperature: None,
+            max_tokens: None,
+        }
+    }
+
+    /// Point a chat request at the provider's name for the model and fill in
+    /// the model's default options
+    pub fn apply(&self, req: &mut ChatCompletionRequest) {
+        req.model = self.target.clone();
+        if req.temperature.is_none() {
+            req.temperature = self.temperature;
+        }
+        if req.max_tokens.is_none() {
+            req.max_tokens = self.max_tokens;
+        }
+    }
+
+    /// Whether the model serves chat completions, which all do unless declared without `chat`
+    pub fn supports_chat(&self) -> bool {
+        self.capabilities.is_none_or(|capabilities| capabilities.chat)
+    }
+
+    /// Whether the model serves embeddings, which all do unless declared without `embeddings`
+    pub fn supports_embeddings(&self) -> bool {
+        self.capabilities.is_none_or(|capabilities| capabilities.embeddings)
+    }
+
+    /// Whether the model accepts images, which all do unless declared without `vision`
+    pub fn supports_images(&self) -> bool {
+        self.capabilities.is_none_or(|capabilities| capabilities.vision)
+    }
+
+    /// Whether the provider enforces `response_format` for the model
+    ///
+    /// Ollama always does, as do models served through the OpenAI API unless
+    /// they are declared without the `json_mode` capability.
+    pub fn supports_response_format(&self) -> bool {
+        self.provider.as_ref().is_some_and(|provider| provider.config().kind == ProviderKind::Ollama)
+            || self.capabilities.is_none_or(|capabilities| capabilities.json_mode)
+    }
+}
+
+/// The models clients can ask for, and the provider serving each
+///
+/// Models come from `[[models]]` sections, then from `[[provider.models]]`.
+/// Other models the default provider reports are served too, without
+/// metadata. In offline mode with no models configured, `gpt-3.5-turbo` and
+/// `text-embedding-ada-002` are answered locally. The local embedding model is
+/// always available.
+pub struct ModelRegistry {
+    models: Vec<(RegisteredModel, Option<Arc<UpstreamProvider>>)>,
+    default: Option<Arc<UpstreamProvider>>,
+    // Models the default provider reported, and when
+    listed: Mutex<Option<(Instant, Vec<String>)>>,
+}
+
+impl ModelRegistry {
+    /// Check the configured models and connect the providers they name
+    ///
+    /// Fails on a model naming an unknown provider, and on names used by more
+    /// than one model.
+    pub fn new(
+        configured: &[RegisteredModel],
+        providers: &BTreeMap<String, ProviderConfig>,
+        default: Option<Arc<UpstreamProvider>>,
+        local_dimensions: usize,
+    ) -> Result<Self, String> {
+        let mut named = BTreeMap::new();
+        for (name, config) in providers {
+            if name == LOCAL_PROVIDER || name == DEFAULT_PROVIDER {
+                return Err(format!("Provider name '{}' is reserved, use another name for [providers.{}]", name, name));
+            }
+            let provider = UpstreamProvider::new(config.clone())
+                .map_err(|e| format!("Failed to create provider '{}': {}", name, e))?;
+            info!("Using provider '{}' at {}", name, config.base_url);
+            named.insert(name.clone(), Arc::new(provider));
+        }
+
+        let mut entries: Vec<RegisteredModel> = configured.to_vec();
+        // Models only declared for their metadata are served by the default provider
+        if let Some(provider) = &default {
+            for declared in &provider.config().models {
+                if !entries.iter().any(|model| model.answers_to(&declared.id)) {
+                    entries.push(RegisteredModel::declared(declared));
+                }
+            }
+        }
+        if default.is_none() && entries.is_empty() {
+            let chat = ModelCapabilities { chat: true, streaming: true, json_mode: true, ..ModelCapabilities::default() };
+            entries.push(RegisteredModel::local("gpt-3.5-turbo", chat, None));
+            entries.push(RegisteredModel::local(
+                "text-embedding-ada-002",
+                ModelCapabilities { embeddings: true, ..ModelCapabilities::default() },
+                Some(local_dimensions),
+            ));
+        }
+        if !entries.iter().any(|model| model.answers_to(LOCAL_EMBEDDING_MODEL)) {
+            entries.push(RegisteredModel::local(
+                LOCAL_EMBEDDING_MODEL,
+                ModelCapabilities { embeddings: true, ..ModelCapabilities::default() },
+                Some(local_dimensions),
+            ));
+        }
+
+        let mut names = HashSet::new();
+        let mut models = Vec::with_capacity(entries.len());
+        for model in entries {
+            for name in std::iter::once(&model.id).chain(&model.aliases) {
+                if !names.insert(name.clone()) {
+                    return Err(format!("Model name '{}' is used by more than one model", name));
+                }
+            }
+            // Without a default provider, its models are answered in offline mode
+            let provider = match model.provider.as_str() {
+                LOCAL_PROVIDER => None,
+                DEFAULT_PROVIDER => default.clone(),
+                name => Some(named.get(name).cloned().ok_or_else(|| {
+                    format!("Model '{}' uses provider '{}', which has no [providers.{}] section", model.id, name, name)
+                })?),
+            };
+            models.push((model, provider));
+        }
+
+        Ok(Self { models, default, listed: Mutex::new(None) })
+    }
+
+    /// The route of a configured model or alias
+    pub fn lookup(&self, id: &str) -> Option<ModelRoute> {
+        self.models.iter()
+            .find(|(model, _)| model.answers_to(id))
+            .map(|(model, provider)| ModelRoute::new(model, provider.clone()))
+    }
+
+    /// The route of any model clients can ask for, or `None` for an unknown model
+    ///
+    /// Models that are not configured go to the default provider when it
+    /// reports them. If it cannot be asked, they are passed on for it to decide.
+    pub async fn resolve(&self, id: &str) -> Option<ModelRoute> {
+        if let Some(route) = self.lookup(id) {
+            return Some(route);
+        }
+        let provider = self.default.clone()?;
+        match self.upstream_models().await {
+            Some(ids) if !ids.iter().any(|listed| listed == id) => None,
+            _ => Some(ModelRoute::undeclared(id, provider)),
+        }
+    }
+
+    /// Every model clients can ask for, configured models first
+    pub async fn list(&self) -> Vec<Model> {
+        let mut models: Vec<Model> = self.models.iter()
+            .map(|(model, provider)| Model {
+                context_length: model.context_length,
+                embedding_dimensions: model.embedding_dimensions,
+                capabilities: model.capabilities,
+                aliases: model.aliases.clone(),
+                ..Model::new(model.id.clone(), provider.as_ref().map_or("conduit", |provider| provider.owned_by()))
+            })
+            .collect();
+
+        // Add the other models the upstream serves, so clients can pick one by name
+        if let (Some(provider), Some(ids)) = (&self.default, self.upstream_models().await) {
+            for id in ids {
+                if self.lookup(&id).is_none() {
+                    models.push(Model::new(id, provider.owned_by()));
+                }
+            }
+        }
+        models
+    }
+
+    // Names the default provider serves, asked at most once a minute;
+    // `None` when there is no default provider or it could not be asked
+    async fn upstream_models(&self) -> Option<Vec<String>> {
+        let provider = self.default.as_ref()?;
+        if let Some((at, ids)) = &*self.listed.lock().unwrap_or_else(|e| e.into_inner()) {
+            if at.elapsed() < UPSTREAM_MODELS_TTL {
+                return Some(ids.clone());
+            }
+        }
+
+        match provider.list_models().await {
+            Ok(ids) => {
+                *self.listed.lock().unwrap_or_else(|e| e.into_inner()) = Some((Instant::now(), ids.clone()));
+                Some(ids)
+            }
+            Err(err) => {
+                error!("Failed to list upstream models: {:?}", err);
+                None
+            }
+        }
+    }
+}
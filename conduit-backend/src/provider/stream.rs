@@ -0,0 +1,37 @@
+use futures::{Stream, StreamExt};
+
+use super::ProviderError;
+
+/// Split a streaming response body into lines, without their line endings
+///
+/// Used for both server-sent events and newline-delimited JSON.
+pub(super) fn lines(response: reqwest::Response) -> impl Stream<Item = Result<String, ProviderError>> + Send + 'static {
+    let body = response.bytes_stream().boxed();
+
+    futures::stream::unfold((body, Vec::<u8>::new(), false), |(mut body, mut buffer, mut done)| async move {
+        loop {
+            if let Some(end) = buffer.iter().position(|byte| *byte == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line).trim_end().to_string();
+                return Some((Ok(line), (body, buffer, done)));
+            }
+            if done {
+                if buffer.is_empty() {
+                    return None;
+                }
+                let line = String::from_utf8_lossy(&buffer).trim_end().to_string();
+                buffer.clear();
+                return Some((Ok(line), (body, buffer, done)));
+            }
+
+            match body.next().await {
+                Some(Ok(bytes)) => buffer.extend_from_slice(&bytes),
+                Some(Err(e)) => {
+                    buffer.clear();
+                    return Some((Err(ProviderError::Request(e)), (body, buffer, true)));
+                }
+                None => done = true,
+            }
+        }
+    })
+}
@@ -0,0 +1,48 @@
+use tiktoken_rs::tokenizer::{get_tokenizer, Tokenizer};
+
+use crate::api::openai::{ChatCompletionUsage, ChatMessage};
+
+/// Tokens every message adds for its role and separators, as OpenAI counts them
+const TOKENS_PER_MESSAGE: usize = 3;
+
+/// Tokens priming the reply after the last message
+const REPLY_PRIMING_TOKENS: usize = 3;
+
+/// Number of tokens `text` takes for `model`
+///
+/// Models OpenAI does not document are counted with the `cl100k_base`
+/// encoding, which is close enough for most open models.
+pub fn count_tokens(model: &str, text: &str) -> usize {
+    if text.is_empty() {
+        return 0;
+    }
+    let bpe = match get_tokenizer(model) {
+        Some(Tokenizer::O200kBase) => tiktoken_rs::o200k_base_singleton(),
+        _ => tiktoken_rs::cl100k_base_singleton(),
+    };
+    let bpe = bpe.lock();
+    bpe.encode_with_special_tokens(text).len()
+}
+
+/// Number of tokens the messages of a chat request take as a prompt
+pub fn count_prompt_tokens(model: &str, messages: &[ChatMessage]) -> usize {
+    let mut tokens = REPLY_PRIMING_TOKENS;
+    for message in messages {
+        tokens += TOKENS_PER_MESSAGE;
+        tokens += count_tokens(model, &message.role);
+        tokens += count_tokens(model, &message.content.text());
+        for call in &message.tool_calls {
+            tokens += count_tokens(model, &call.function.name);
+            tokens += count_tokens(model, &call.function.arguments);
+        }
+    }
+    tokens
+}
+
+/// Usage of a chat request answered with `reply`, for replies whose provider
+/// reports none
+pub fn count_usage(model: &str, messages: &[ChatMessage], reply: &str) -> ChatCompletionUsage {
+    let prompt_tokens = count_prompt_tokens(model, messages) as i32;
+    let completion_tokens = count_tokens(model, reply) as i32;
+    ChatCompletionUsage { prompt_tokens, completion_tokens, total_tokens: prompt_tokens + completion_tokens }
+}
@@ -0,0 +1,197 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::api::openai::ChatMessage;
+use crate::memory::{CancelToken, ImportanceConfig, Memory, MemoryAccess, MemoryError, MemoryStore, Principal};
+
+pub use conduit_types::openai::{RagOptions, RetrievalMode};
+
+/// Server-wide retrieval settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RagConfig {
+    /// Maximum number of memories to inject
+    pub top_k: usize,
+    /// Approximate token budget for the injected context
+    pub max_context_tokens: usize,
+    pub mode: RetrievalMode,
+}
+
+impl Default for RagConfig {
+    fn default() -> Self {
+        Self {
+            top_k: 5,
+            max_context_tokens: 2000,
+            mode: RetrievalMode::Keyword,
+        }
+    }
+}
+
+impl RagConfig {
+    /// Read retrieval settings from `CONDUIT_RAG_TOP_K`, `CONDUIT_RAG_MAX_CONTEXT_TOKENS`
+    /// and `CONDUIT_RAG_MODE`, falling back to the defaults
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        config.apply_env();
+        config
+    }
+
+    /// Override settings with the `CONDUIT_RAG_*` variables that are set
+    pub fn apply_env(&mut self) {
+        if let Some(top_k) = std::env::var("CONDUIT_RAG_TOP_K").ok().and_then(|v| v.parse().ok()) {
+            self.top_k = top_k;
+        }
+        if let Some(max_tokens) = std::env::var("CONDUIT_RAG_MAX_CONTEXT_TOKENS").ok().and_then(|v| v.parse().ok()) {
+            self.max_context_tokens = max_tokens;
+        }
+        if let Some(mode) = std::env::var("CONDUIT_RAG_MODE").ok().and_then(|v| v.parse().ok()) {
+            self.mode = mode;
+        }
+    }
+
+    /// Apply per-request overrides on top of the server settings
+    pub fn with_overrides(&self, options: &RagOptions) -> Self {
+        Self {
+            top_k: options.top_k.unwrap_or(self.top_k),
+            max_context_tokens: options.max_context_tokens.unwrap_or(self.max_context_tokens),
+            mode: options.retrieval.unwrap_or(self.mode),
+        }
+    }
+}
+
+/// A memory selected for the chat context
+#[derive(Debug, Clone, Serialize)]
+pub struct RetrievedMemory {
+    pub memory: Memory,
+    pub score: f32,
+}
+
+// Rough token estimate, consistent with the embeddings usage estimate
+fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(4)
+}
+
+fn query_terms(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| term.len() >= 3)
+        .map(|term| term.to_lowercase())
+        .collect();
+    terms.sort();
+    terms.dedup();
+    terms
+}
+
+fn keyword_score(memory: &Memory, terms: &[String]) -> f32 {
+    let title = memory.title.to_lowercase();
+    let content = memory.content.to_lowercase();
+
+    terms.iter()
+        .map(|term| {
+            let mut score = 0.0;
+            if title.contains(term.as_str()) {
+                score += 3.0;
+            }
+            if memory.tags.iter().any(|tag| tag.to_lowercase() == *term) {
+                score += 2.0;
+            }
+            // Cap content matches so long notes don't dominate
+            score += content.matches(term.as_str()).count().min(5) as f32 * 0.5;
+            score
+        })
+        .sum()
+}
+
+/// Retrieve the memories most relevant to `query` that `principal` can read, stopping early once `cancel` is set
+///
+/// Keyword scores are weighted by each memory's importance, so that stale or
+/// minor memories give way to key ones that match as well.
+pub fn retrieve(
+    store: &MemoryStore,
+    query: &str,
+    config: &RagConfig,
+    importance: &ImportanceConfig,
+    principal: Option<&Principal>,
+    cancel: &CancelToken,
+) -> Result<Vec<RetrievedMemory>, MemoryError> {
+    if config.mode == RetrievalMode::Off || config.top_k == 0 {
+        return Ok(Vec::new());
+    }
+
+    let terms = query_terms(query);
+    if terms.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let now = Utc::now();
+    let mut scored: Vec<RetrievedMemory> = store.list_cancellable(cancel)?
+        .into_iter()
+        .filter(|memory| memory.is_visible_to(principal))
+        .map(|memory| {
+            let score = keyword_score(&memory, &terms) * importance.weight(&memory, now);
+            RetrievedMemory { memory, score }
+        })
+        .filter(|retrieved| retrieved.score > 0.0)
+        .collect();
+
+    scored.sort_by(|a, b| b.score.total_cmp(&a.score));
+    scored.truncate(config.top_k);
+
+    Ok(scored)
+}
+
+/// Build a system message carrying the retrieved memories, trimmed to the token budget
+///
+/// Returns `None` if there is nothing to inject.
+pub fn context_message(retrieved: &[RetrievedMemory], max_context_tokens: usize) -> Option<ChatMessage> {
+    let mut context = String::from(
+        "The following memories from the user's knowledge base may be relevant. \
+         Use them when they help answer the user, and cite their titles.\n",
+    );
+    let mut budget = max_context_tokens.saturating_sub(estimate_tokens(&context));
+    let mut included = 0;
+
+    for item in retrieved {
+        let entry = format!(
+            "\n### {} (id: {}, tags: {})\n{}\n",
+            item.memory.title,
+            item.memory.id,
+            item.memory.tags.join(", "),
+            item.memory.content.trim()
+        );
+
+        let tokens = estimate_tokens(&entry);
+        if tokens > budget {
+            // Fit a truncated version of the entry if there is meaningful room left
+            if budget > 50 {
+                let truncated: String = entry.chars().take(budget * 4).collect();
+                context.push_str(&truncated);
+                context.push_str("\n[truncated]\n");
+                included += 1;
+            }
+            break;
+        }
+
+        budget -= tokens;
+        context.push_str(&entry);
+        included += 1;
+    }
+
+    if included == 0 {
+        return None;
+    }
+
+    Some(ChatMessage {
+        role: "system".to_string(),
+        content: context.into(),
+        ..Default::default()
+    })
+}
+
+/// Insert the context message after any leading system messages
+pub fn inject_context(messages: &mut Vec<ChatMessage>, context: ChatMessage) {
+    let position = messages.iter()
+        .take_while(|message| message.role == "system")
+        .count();
+    messages.insert(position, context);
+}
@@ -0,0 +1,29 @@
+//! Small helpers shared by the memory store, the API and the providers
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// 64-bit FNV-1a hash, which unlike the std hasher is stable across Rust
+/// releases and platforms, so it can be stored or used to derive IDs
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Replace the file at `path` with `contents`
+///
+/// The contents go to `<path>.tmp` first and are then renamed over the file,
+/// so a crash never leaves a truncated file and readers never see a partial one.
+pub(crate) fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let mut staging = path.as_os_str().to_owned();
+    staging.push(".tmp");
+    let staging = PathBuf::from(staging);
+
+    if let Err(e) = fs::write(&staging, contents) {
+        let _ = fs::remove_file(&staging);
+        return Err(e);
+    }
+    fs::rename(&staging, path)
+}
@@ -0,0 +1,23 @@
+//! Attachment names are listed in the memory's frontmatter, so names that
+//! would not read back the same are refused.
+
+use conduit_backend::memory::{init_store_dir, Memory, MemoryError, MemoryStore};
+use tempfile::TempDir;
+
+#[test]
+fn names_that_break_the_frontmatter_list_are_refused() {
+    let dir = TempDir::new().unwrap();
+    init_store_dir(dir.path()).unwrap();
+    let store = MemoryStore::new(dir.path());
+    let memory = Memory::new("Receipts".to_string(), "Scans from the trip".to_string(), Vec::new());
+    store.save(&memory).unwrap();
+
+    for name in ["a, b.txt", "[draft].txt", "notes].txt", " padded.txt", "padded.txt "] {
+        let result = store.add_attachment(&memory.id, name, b"data");
+        assert!(matches!(result, Err(MemoryError::InvalidAttachmentName(_))), "{:?} was accepted", name);
+    }
+
+    store.add_attachment(&memory.id, "receipt (1).txt", b"data").unwrap();
+    let names: Vec<_> = store.list_attachments(&memory.id).unwrap().into_iter().map(|a| a.name).collect();
+    assert_eq!(names, vec!["receipt (1).txt"]);
+}
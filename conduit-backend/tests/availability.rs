@@ -0,0 +1,29 @@
+//! A store directory is told apart from an empty folder by its marker, so an
+//! unmounted drive is reported instead of being used as an empty store.
+
+use conduit_backend::memory::{init_store_dir, MemoryStore};
+use tempfile::TempDir;
+
+#[test]
+fn an_existing_empty_folder_is_not_taken_for_a_store() {
+    let dir = TempDir::new().unwrap();
+    let store = MemoryStore::new(dir.path());
+    assert!(!store.is_available());
+    assert!(!dir.path().join(".conduit-store").exists());
+
+    // Once set up as a store, the same folder is used
+    init_store_dir(dir.path()).unwrap();
+    assert!(store.is_available());
+}
+
+#[test]
+fn a_new_folder_and_an_unmarked_store_are_marked() {
+    let dir = TempDir::new().unwrap();
+    assert!(MemoryStore::new(dir.path().join("new")).is_available());
+
+    // Stores from before the marker existed already hold memories
+    let old = dir.path().join("old");
+    std::fs::create_dir(&old).unwrap();
+    std::fs::write(old.join("kept.md"), "---\ntitle: Kept\n---\nFrom an older version").unwrap();
+    assert!(MemoryStore::new(&old).is_available());
+}
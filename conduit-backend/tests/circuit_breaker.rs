@@ -0,0 +1,26 @@
+//! After the cooldown, an open circuit lets one request probe the upstream
+//! while the others keep failing fast.
+
+use conduit_backend::provider::{CircuitBreaker, CircuitState};
+
+#[test]
+fn half_open_circuit_lets_a_single_probe_through() {
+    let breaker = CircuitBreaker::new(1, 0);
+    breaker.record_failure("connection refused");
+    assert_eq!(breaker.health("http://upstream").state, CircuitState::Open);
+
+    // The cooldown is over, so one request probes and the rest wait for it
+    assert!(breaker.check().is_ok());
+    assert_eq!(breaker.health("http://upstream").state, CircuitState::HalfOpen);
+    assert!(breaker.check().is_err());
+    assert!(breaker.check().is_err());
+
+    // A failed probe opens the circuit again, and the next probe closes it
+    breaker.record_failure("connection refused");
+    assert_eq!(breaker.health("http://upstream").state, CircuitState::Open);
+    assert!(breaker.check().is_ok());
+    breaker.record_success();
+    assert_eq!(breaker.health("http://upstream").state, CircuitState::Closed);
+    assert!(breaker.check().is_ok());
+    assert!(breaker.check().is_ok());
+}
@@ -0,0 +1,61 @@
+//! Titles sort in the alphabetical order of the configured locale, and
+//! search ignores case and accents unless told to keep accents apart.
+
+use conduit_backend::memory::{init_store_dir, Collation, ListOptions, Memory, MemoryStore, SortField, SortOrder, StoreOptions};
+use tempfile::TempDir;
+
+// A store with the given collation, holding a memory for each title
+fn store_with(collation: Collation, titles: &[&str]) -> (MemoryStore, TempDir) {
+    let dir = TempDir::new().unwrap();
+    init_store_dir(dir.path()).unwrap();
+    let options = StoreOptions { collation, ..StoreOptions::default() };
+    let store = MemoryStore::with_options(dir.path(), options);
+    for title in titles {
+        let memory = Memory::new(title.to_string(), format!("Notes on {}", title), Vec::new());
+        store.save(&memory).unwrap();
+    }
+    (store, dir)
+}
+
+fn sorted_titles(store: &MemoryStore) -> Vec<String> {
+    let options = ListOptions { sort: Some(SortField::Title), order: Some(SortOrder::Asc), ..ListOptions::default() };
+    store.list_paged(&options).unwrap().items.into_iter().map(|memory| memory.title).collect()
+}
+
+const TITLES: &[&str] = &["Zebra", "Öl", "apple", "Äpple", "Banana"];
+
+#[test]
+fn titles_follow_the_default_alphabet() {
+    let (store, _dir) = store_with(Collation::default(), TITLES);
+    assert_eq!(sorted_titles(&store), ["apple", "Äpple", "Banana", "Öl", "Zebra"]);
+}
+
+#[test]
+fn titles_follow_the_configured_locale() {
+    // Swedish puts Ä and Ö after Z
+    let collation = Collation { locale: Some("sv".to_string()), ..Collation::default() };
+    let (store, _dir) = store_with(collation, TITLES);
+    assert_eq!(sorted_titles(&store), ["apple", "Banana", "Zebra", "Äpple", "Öl"]);
+}
+
+#[test]
+fn search_ignores_case_and_accents() {
+    let (store, _dir) = store_with(Collation::default(), &["Café au lait", "Crème brûlée", "Tea"]);
+
+    let titles = |query: &str| -> Vec<String> {
+        store.search(query).unwrap().into_iter().map(|memory| memory.title).collect()
+    };
+    assert_eq!(titles("cafe"), ["Café au lait"]);
+    assert_eq!(titles("CAFÉ"), ["Café au lait"]);
+    assert_eq!(titles("title:creme"), ["Crème brûlée"]);
+    assert_eq!(titles("brulee"), ["Crème brûlée"]);
+}
+
+#[test]
+fn accents_can_be_kept_apart() {
+    let collation = Collation { ignore_accents: false, ..Collation::default() };
+    let (store, _dir) = store_with(collation, &["Café au lait"]);
+
+    assert!(store.search("cafe").unwrap().is_empty());
+    assert_eq!(store.search("CAFÉ").unwrap().len(), 1);
+}
@@ -0,0 +1,104 @@
+//! Collections with a retention period move members that have not changed
+//! for that long to the trash.
+
+use chrono::{Duration, Utc};
+use conduit_backend::memory::{
+    init_store_dir, CollectionDefaults, Memory, MemoryError, MemoryStore, StoreOptions, MAX_RETENTION_DAYS,
+};
+use std::fs::File;
+use std::time::SystemTime;
+use tempfile::TempDir;
+
+fn store() -> (TempDir, MemoryStore) {
+    let dir = TempDir::new().unwrap();
+    init_store_dir(dir.path()).unwrap();
+    let store = MemoryStore::new(dir.path());
+    (dir, store)
+}
+
+// Save a memory last changed `days` ago
+fn save_aged(store: &MemoryStore, title: &str, days: i64) -> String {
+    let mut memory = Memory::new(title.to_string(), "Notes".to_string(), Vec::new());
+    memory.updated_at = Utc::now() - Duration::days(days);
+    store.save(&memory).unwrap();
+    memory.id
+}
+
+fn retention(days: u32) -> CollectionDefaults {
+    CollectionDefaults { retention_days: Some(days), ..CollectionDefaults::default() }
+}
+
+#[test]
+fn members_older_than_the_retention_are_trashed() {
+    let (_dir, store) = store();
+    let old = save_aged(&store, "Old", 30);
+    let recent = save_aged(&store, "Recent", 2);
+    store.create_collection("Scratch".to_string(), None, vec![old.clone(), recent.clone()], retention(14)).unwrap();
+
+    assert_eq!(store.expire_collection_members().unwrap(), vec![old.clone()]);
+    assert!(store.get(&old).is_err());
+    assert!(store.get(&recent).is_ok());
+    assert_eq!(store.list_trash().unwrap()[0].memory.id, old);
+}
+
+#[test]
+fn pinned_members_are_kept() {
+    let (_dir, store) = store();
+    let pinned = save_aged(&store, "Pinned", 30);
+    store.create_collection("Scratch".to_string(), None, vec![pinned.clone()], retention(14)).unwrap();
+    let defaults = CollectionDefaults { pinned: true, ..CollectionDefaults::default() };
+    store.create_collection("Reference".to_string(), None, vec![pinned.clone()], defaults).unwrap();
+
+    assert!(store.expire_collection_members().unwrap().is_empty());
+    assert!(store.get(&pinned).is_ok());
+}
+
+#[test]
+fn read_only_members_are_kept() {
+    let dir = TempDir::new().unwrap();
+    let external = TempDir::new().unwrap();
+    init_store_dir(dir.path()).unwrap();
+    let document = external.path().join("Handbook.md");
+    std::fs::write(&document, "# Handbook\n").unwrap();
+    let month_ago = SystemTime::now() - std::time::Duration::from_secs(30 * 24 * 60 * 60);
+    File::options().write(true).open(&document).unwrap().set_modified(month_ago).unwrap();
+
+    let options = StoreOptions { external_dirs: vec![external.path().to_path_buf()], ..StoreOptions::default() };
+    let store = MemoryStore::with_options(dir.path(), options);
+    let handbook = store.list().unwrap().into_iter().find(|memory| memory.read_only).unwrap().id;
+    store.create_collection("Scratch".to_string(), None, vec![handbook.clone()], retention(14)).unwrap();
+
+    assert!(store.expire_collection_members().unwrap().is_empty());
+    assert!(store.get(&handbook).is_ok());
+}
+
+#[test]
+fn retention_is_bounded() {
+    let (_dir, store) = store();
+    for days in [0, MAX_RETENTION_DAYS + 1, u32::MAX] {
+        let result = store.create_collection("Scratch".to_string(), None, Vec::new(), retention(days));
+        assert!(matches!(result, Err(MemoryError::InvalidCollection(_))), "{} days accepted", days);
+    }
+
+    let old = save_aged(&store, "Old", 30);
+    store.create_collection("Archive".to_string(), None, vec![old.clone()], retention(MAX_RETENTION_DAYS)).unwrap();
+    assert!(store.expire_collection_members().unwrap().is_empty());
+    assert!(store.get(&old).is_ok());
+}
+
+#[test]
+fn an_unbounded_retention_on_disk_does_not_stop_the_store_opening() {
+    let (dir, store) = store();
+    let old = save_aged(&store, "Old", 30);
+    store.create_collection("Archive".to_string(), None, vec![old.clone()], retention(14)).unwrap();
+
+    // Written by a version that did not bound the retention
+    let path = dir.path().join(".collections.json");
+    let collections = std::fs::read_to_string(&path).unwrap()
+        .replace("\"retention_days\": 14", &format!("\"retention_days\": {}", u32::MAX));
+    std::fs::write(&path, collections).unwrap();
+
+    let reopened = MemoryStore::new(dir.path());
+    assert!(reopened.expire_collection_members().unwrap().is_empty());
+    assert!(reopened.get(&old).is_ok());
+}
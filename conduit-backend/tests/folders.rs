@@ -0,0 +1,27 @@
+//! In a recursive store a memory's collection is the folder its file lives
+//! in, and moving it leaves a single file behind.
+
+use conduit_backend::memory::{init_store_dir, Memory, MemoryStore, StoreOptions};
+use tempfile::TempDir;
+
+#[test]
+fn moving_a_memory_between_folders_keeps_one_file() {
+    let dir = TempDir::new().unwrap();
+    init_store_dir(dir.path()).unwrap();
+    let store = MemoryStore::with_options(dir.path(), StoreOptions { recursive: true, ..StoreOptions::default() });
+    let mut memory = Memory::new("Roadmap".to_string(), "Ship the importer".to_string(), Vec::new());
+    store.save(&memory).unwrap();
+    let file = format!("{}.md", memory.id);
+
+    for folder in [Some("Projects/2026"), Some("Archive"), None] {
+        memory.collection = folder.map(str::to_string);
+        store.save(&memory).unwrap();
+
+        let expected = folder.map_or_else(|| dir.path().to_path_buf(), |folder| dir.path().join(folder)).join(&file);
+        assert!(expected.is_file(), "{} missing", expected.display());
+        for other in [dir.path().join(&file), dir.path().join("Projects/2026").join(&file), dir.path().join("Archive").join(&file)] {
+            assert!(other == expected || !other.exists(), "{} left behind", other.display());
+        }
+        assert_eq!(store.get(&memory.id).unwrap().collection.as_deref(), folder);
+    }
+}
@@ -0,0 +1,46 @@
+//! IDs stay unique under every ID scheme, even for things created within the
+//! same minute.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use conduit_backend::memory::{init_store_dir, CollectionDefaults, IdScheme, MemoryStore, StoreOptions};
+use tempfile::TempDir;
+
+fn timestamp_store() -> (MemoryStore, TempDir) {
+    let dir = TempDir::new().unwrap();
+    init_store_dir(dir.path()).unwrap();
+    let options = StoreOptions { id_scheme: IdScheme::Timestamp, ..StoreOptions::default() };
+    (MemoryStore::with_options(dir.path(), options), dir)
+}
+
+#[test]
+fn collections_created_together_get_their_own_ids() {
+    let (store, _dir) = timestamp_store();
+
+    let ids: HashSet<_> = (0..5)
+        .map(|i| store.create_collection(format!("List {}", i), None, Vec::new(), CollectionDefaults::default()).unwrap().id)
+        .collect();
+    assert_eq!(ids.len(), 5);
+    assert_eq!(store.list_collections().unwrap().len(), 5);
+}
+
+#[test]
+fn memories_created_at_once_never_share_a_timestamp_id() {
+    let (store, _dir) = timestamp_store();
+    let store = Arc::new(store);
+
+    // Every ID is picked before any memory is saved, as racing requests would
+    let memories: Vec<_> = (0..8)
+        .map(|i| store.new_memory(format!("Note {}", i), "Same minute".to_string(), Vec::new()).unwrap())
+        .collect();
+    let writers: Vec<_> = memories.into_iter().map(|memory| {
+        let store = store.clone();
+        std::thread::spawn(move || store.save(&memory).unwrap())
+    }).collect();
+    for writer in writers {
+        writer.join().unwrap();
+    }
+
+    assert_eq!(store.list().unwrap().len(), 8);
+}
@@ -0,0 +1,64 @@
+//! Memories are cleaned up by the store's preprocessing chain each time they
+//! are saved.
+
+use conduit_backend::memory::{init_store_dir, Memory, MemoryStore, PreprocessConfig, PreprocessStep, Replacement, StoreOptions};
+use tempfile::TempDir;
+
+fn store_with(preprocess: PreprocessConfig) -> (MemoryStore, TempDir) {
+    let dir = TempDir::new().unwrap();
+    init_store_dir(dir.path()).unwrap();
+    let options = StoreOptions { preprocess, ..StoreOptions::default() };
+    (MemoryStore::with_options(dir.path(), options), dir)
+}
+
+fn saved(store: &MemoryStore, title: &str, content: &str) -> Memory {
+    let memory = Memory::new(title.to_string(), content.to_string(), Vec::new());
+    store.save(&memory).unwrap();
+    store.get(&memory.id).unwrap()
+}
+
+#[test]
+fn built_in_steps_clean_up_captured_text() {
+    let (store, _dir) = store_with(PreprocessConfig {
+        steps: vec![PreprocessStep::StripTrackingParams, PreprocessStep::NormalizeWhitespace, PreprocessStep::StraightenQuotes],
+        tracking_params: vec!["ref".to_string()],
+        ..PreprocessConfig::default()
+    });
+
+    let memory = saved(
+        &store,
+        "  \u{201C}Reading\u{201D}   list ",
+        "See https://example.com/post?id=7&utm_source=mail&fbclid=abc#intro \r\n\n\n\nand [this](https://example.com/?ref=feed).\n\n```\nlet s = \u{2018}x\u{2019};   \n\n\n```\n\n",
+    );
+    assert_eq!(memory.title, "\"Reading\" list");
+    assert_eq!(
+        memory.content,
+        "See https://example.com/post?id=7#intro\n\nand [this](https://example.com/).\n\n```\nlet s = \u{2018}x\u{2019};   \n\n\n```",
+    );
+}
+
+#[test]
+fn replacements_run_after_the_steps_and_again_on_each_save() {
+    let (store, _dir) = store_with(PreprocessConfig {
+        replacements: vec![Replacement {
+            // Tickets already linked are left alone, so saving again changes nothing
+            pattern: r"(^|[^\[])TICKET-(\d+)\b".to_string(),
+            replacement: "${1}[TICKET-${2}](https://tickets.example.com/${2})".to_string(),
+        }],
+        ..PreprocessConfig::default()
+    });
+
+    let mut memory = saved(&store, "Bug", "Fixed in TICKET-42");
+    assert_eq!(memory.content, "Fixed in [TICKET-42](https://tickets.example.com/42)");
+
+    store.save(&memory).unwrap();
+    memory = store.get(&memory.id).unwrap();
+    assert_eq!(memory.content, "Fixed in [TICKET-42](https://tickets.example.com/42)");
+}
+
+#[test]
+fn stores_without_a_chain_keep_content_as_written() {
+    let (store, _dir) = store_with(PreprocessConfig::default());
+    let memory = saved(&store, "Link", "https://example.com/?utm_source=x  \n\n\n\nend");
+    assert_eq!(memory.content, "https://example.com/?utm_source=x  \n\n\n\nend");
+}
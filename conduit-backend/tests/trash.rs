@@ -0,0 +1,30 @@
+//! Deleted memories wait in the trash, and deleting never replaces what is
+//! already there.
+
+use conduit_backend::memory::{init_store_dir, Memory, MemoryError, MemoryStore};
+use tempfile::TempDir;
+
+#[test]
+fn deleting_a_recreated_memory_keeps_the_trashed_one() {
+    let dir = TempDir::new().unwrap();
+    init_store_dir(dir.path()).unwrap();
+    let store = MemoryStore::new(dir.path());
+    let first = Memory::new("Plan".to_string(), "First draft".to_string(), Vec::new());
+    store.save(&first).unwrap();
+    store.delete(&first.id).unwrap();
+
+    let mut second = Memory::new("Plan".to_string(), "Second draft".to_string(), Vec::new());
+    second.id = first.id.clone();
+    store.save(&second).unwrap();
+    assert!(matches!(store.delete(&second.id), Err(MemoryError::AlreadyExists(_))));
+
+    assert_eq!(store.get(&first.id).unwrap().content, "Second draft");
+    let trash = store.list_trash().unwrap();
+    assert_eq!(trash.len(), 1);
+    assert_eq!(trash[0].memory.content, "First draft");
+
+    // Once the trashed one is purged, the new one can be deleted
+    store.purge(&first.id).unwrap();
+    store.delete(&second.id).unwrap();
+    assert_eq!(store.list_trash().unwrap()[0].memory.content, "Second draft");
+}
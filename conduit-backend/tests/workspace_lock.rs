@@ -0,0 +1,32 @@
+//! Workspace passphrases are PBKDF2-HMAC-SHA256 hashes, and hashes with too
+//! few rounds are refused.
+
+use conduit_backend::memory::{init_store_dir, MemoryError, MemoryStore};
+use tempfile::TempDir;
+
+const SALT: &str = "000102030405060708090a0b0c0d0e0f";
+
+// A store whose lock file holds `hash` of "correct horse" with `rounds`
+fn locked_store(rounds: u32, hash: &str) -> (TempDir, MemoryStore) {
+    let dir = TempDir::new().unwrap();
+    init_store_dir(dir.path()).unwrap();
+    let lock = format!(r#"{{"rounds": {}, "salt": "{}", "hash": "{}"}}"#, rounds, SALT, hash);
+    std::fs::write(dir.path().join(".workspace-lock.json"), lock).unwrap();
+    let store = MemoryStore::new(dir.path());
+    (dir, store)
+}
+
+#[test]
+fn passphrases_hashed_before_still_unlock() {
+    let (_dir, store) = locked_store(100_000, "57f2c2f0739748d516419b062a884666323c583ea4ae165504a81f7b53c62a09");
+    let lock = store.workspace_lock();
+    assert!(lock.is_locked());
+    assert!(matches!(lock.unlock("wrong horse"), Err(MemoryError::WrongPassphrase)));
+    assert!(!lock.unlock("correct horse").unwrap().locked);
+}
+
+#[test]
+fn hashes_with_too_few_rounds_never_unlock() {
+    let (_dir, store) = locked_store(1, "c5199f52d095f03bb27ace7b385711999c67ec4aacd0f298a6da36ce47520daf");
+    assert!(matches!(store.workspace_lock().unlock("correct horse"), Err(MemoryError::WrongPassphrase)));
+}
@@ -0,0 +1,56 @@
+//! Imported zips may hold further zips, as Notion splits large exports, but
+//! only a few levels deep, and only up to a size that fits in memory.
+
+use std::io::{Cursor, Write};
+
+use conduit_backend::import::{read_bytes, ImportFormat};
+use conduit_backend::memory::{init_store_dir, ImportJob, MemoryError, MemoryStore};
+use tempfile::TempDir;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+fn zip_of(name: &str, data: &[u8]) -> Vec<u8> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file(name, SimpleFileOptions::default()).unwrap();
+    zip.write_all(data).unwrap();
+    zip.finish().unwrap().into_inner()
+}
+
+// A Notion page inside `depth` further zips
+fn nested_export(depth: usize) -> Vec<u8> {
+    let mut data = zip_of("Page 0123456789abcdef0123456789abcdef.md", b"# Page\n\nHello");
+    for level in 0..depth {
+        data = zip_of(&format!("Part-{}.zip", level), &data);
+    }
+    data
+}
+
+#[test]
+fn zips_nested_too_deep_are_refused() {
+    assert_eq!(read_bytes(ImportFormat::Notion, &nested_export(2), None).unwrap().len(), 1);
+
+    let result = read_bytes(ImportFormat::Notion, &nested_export(3), None);
+    assert!(matches!(result, Err(MemoryError::InvalidFormat(message)) if message.contains("nested")));
+}
+
+#[test]
+fn zips_unpacking_to_too_much_are_refused() {
+    // One compressed megabyte, copied into 1025 attachments that unpack to just over a gigabyte
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file("attachments/note/0.txt", options).unwrap();
+    zip.write_all(&vec![b'a'; 1 << 20]).unwrap();
+    let mut source = ZipArchive::new(zip.finish().unwrap()).unwrap();
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for i in 0..1025 {
+        zip.raw_copy_file_rename(source.by_index(0).unwrap(), format!("attachments/note/{}.txt", i)).unwrap();
+    }
+    let data = zip.finish().unwrap().into_inner();
+
+    let dir = TempDir::new().unwrap();
+    init_store_dir(dir.path()).unwrap();
+    let store = MemoryStore::new(dir.path());
+    let result = store.import_zip(Cursor::new(data), &ImportJob::default());
+    assert!(matches!(result, Err(MemoryError::InvalidFormat(message)) if message.contains("unpacks")));
+    assert!(store.list().unwrap().is_empty());
+}
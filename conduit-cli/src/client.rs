@@ -0,0 +1,101 @@
+use conduit_backend::memory::{ExportReport, ListOptions, Memory};
+use conduit_backend::ConduitBackend;
+use conduit_client::types::requests::{CreateMemoryRequest, ListMemoriesQuery, SearchMemoriesRequest};
+use conduit_client::ClientError;
+
+/// Where the CLI reads and writes memories
+pub enum Client {
+    /// The memory store on this machine, opened through the backend library
+    Local(Box<ConduitBackend>),
+    /// A running Conduit server
+    Remote(conduit_client::Client),
+}
+
+// A message for a failed request, pointing at the API key when it was refused
+fn remote_error(err: ClientError) -> String {
+    match err.status() {
+        Some(status) if status.as_u16() == 401 => "The server requires an API key; pass --api-key or set CONDUIT_API_KEY".to_string(),
+        _ => err.to_string(),
+    }
+}
+
+impl Client {
+    /// A client for the server at `url`, sending `api_key` if given
+    pub fn remote(url: &str, api_key: Option<String>) -> Result<Self, String> {
+        let client = conduit_client::Client::new(url).map_err(remote_error)?;
+        Ok(Self::Remote(match api_key {
+            Some(key) => client.with_api_key(key),
+            None => client,
+        }))
+    }
+
+    /// Create a memory and return it
+    pub async fn create(&self, title: &str, content: &str, tags: &[String]) -> Result<Memory, String> {
+        match self {
+            Self::Local(backend) => {
+                let id = backend.create_memory(title.to_string(), content.to_string(), tags.to_vec())?;
+                backend.get_memory(&id)
+            }
+            Self::Remote(remote) => {
+                let request = CreateMemoryRequest {
+                    title: title.to_string(),
+                    content: content.to_string(),
+                    tags: tags.to_vec(),
+                    ..CreateMemoryRequest::default()
+                };
+                remote.create_memory(&request).await.map_err(remote_error)
+            }
+        }
+    }
+
+    /// The most recently updated memories, optionally only those with `tag`
+    pub async fn list(&self, tag: Option<&str>, limit: Option<usize>) -> Result<Vec<Memory>, String> {
+        let query = ListMemoriesQuery { limit, tag: tag.map(str::to_string), ..ListMemoriesQuery::default() };
+        match self {
+            Self::Local(backend) => Ok(backend.list_memories_paged(&ListOptions::from(query))?.items),
+            Self::Remote(remote) => remote.list_memories(&query).await.map_err(remote_error),
+        }
+    }
+
+    /// Memories matching `query`, written in the search language
+    pub async fn search(&self, query: &str, tag: Option<&str>) -> Result<Vec<Memory>, String> {
+        match self {
+            Self::Local(backend) => {
+                let memories = backend.search_memories(query)?;
+                Ok(match tag {
+                    Some(tag) => memories.into_iter()
+                        .filter(|memory| memory.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+                        .collect(),
+                    None => memories,
+                })
+            }
+            Self::Remote(remote) => {
+                let request = SearchMemoriesRequest { query: query.to_string(), tag: tag.map(str::to_string), ..SearchMemoriesRequest::default() };
+                remote.search_memories(&request).await.map_err(remote_error)
+            }
+        }
+    }
+
+    pub async fn get(&self, id: &str) -> Result<Memory, String> {
+        match self {
+            Self::Local(backend) => backend.get_memory(id),
+            Self::Remote(remote) => remote.get_memory(id).await.map_err(remote_error),
+        }
+    }
+
+    /// Move a memory to the trash
+    pub async fn delete(&self, id: &str) -> Result<(), String> {
+        match self {
+            Self::Local(backend) => backend.delete_memory(id),
+            Self::Remote(remote) => remote.delete_memory(id).await.map_err(remote_error),
+        }
+    }
+
+    /// Run a named export profile; a remote server writes the export on its own disk
+    pub async fn export(&self, profile: &str) -> Result<ExportReport, String> {
+        match self {
+            Self::Local(backend) => backend.run_export_profile(profile),
+            Self::Remote(remote) => remote.run_export_profile(profile).await.map_err(remote_error),
+        }
+    }
+}
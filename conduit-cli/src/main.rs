@@ -0,0 +1,198 @@
+//! `conduit`, a command-line client for Conduit memories
+//!
+//! Commands open the memory store directly through the backend library, or
+//! talk to a running server with `--remote`, so notes can be captured from a
+//! terminal without the desktop app.
+
+mod client;
+mod output;
+
+use std::io::{IsTerminal, Read};
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use clap::{Parser, Subcommand};
+use conduit_backend::config::ConduitConfig;
+use conduit_backend::ConduitBackend;
+
+use client::Client;
+use output::Format;
+
+#[derive(Debug, Parser)]
+#[command(name = "conduit", version, about = "Capture, find and manage Conduit memories from the terminal")]
+struct Cli {
+    /// Memory store directory; defaults to `CONDUIT_MEMORY_PATH` or the configured store
+    #[arg(long, global = true, conflicts_with = "remote")]
+    store: Option<PathBuf>,
+
+    /// URL of a running Conduit server to use instead of the local store, such as `http://127.0.0.1:3000`
+    #[arg(long, global = true, env = "CONDUIT_REMOTE")]
+    remote: Option<String>,
+
+    /// API key for the server given with `--remote`
+    #[arg(long, global = true, env = "CONDUIT_API_KEY", hide_env_values = true)]
+    api_key: Option<String>,
+
+    /// How to print results
+    #[arg(long, global = true, value_enum, default_value_t = Format::Table)]
+    format: Format,
+
+    /// Shorthand for `--format json`
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Create a memory from the given text, or from standard input
+    New {
+        /// Content of the memory; read from standard input when left out
+        content: Vec<String>,
+        /// Title; generated from the first heading or sentence when left out
+        #[arg(short, long)]
+        title: Option<String>,
+        /// Tag to add; repeat for several
+        #[arg(short = 'g', long = "tag")]
+        tags: Vec<String>,
+    },
+    /// List the most recently updated memories
+    List {
+        /// Only memories with this tag
+        #[arg(long)]
+        tag: Option<String>,
+        /// Most memories to list
+        #[arg(short = 'n', long, default_value_t = 50)]
+        limit: usize,
+    },
+    /// Find memories matching a query, such as `tag:rust -tag:draft deploy`
+    Search {
+        #[arg(required = true, allow_hyphen_values = true)]
+        query: Vec<String>,
+        /// Only memories with this tag
+        #[arg(long)]
+        tag: Option<String>,
+    },
+    /// Print a memory
+    Show {
+        id: String,
+    },
+    /// Move a memory to the trash
+    Delete {
+        id: String,
+    },
+    /// Run a named export profile from the config file
+    Export {
+        profile: String,
+    },
+    /// Run the API server on the local store until interrupted
+    Serve {
+        /// Port to listen on; defaults to the configured address
+        #[arg(short, long)]
+        port: Option<u16>,
+        /// Address to bind to; defaults to the configured address
+        #[arg(long)]
+        host: Option<IpAddr>,
+    },
+}
+
+// The local backend, with the store from `--store` if given
+fn local_backend(store: Option<PathBuf>) -> Result<ConduitBackend, String> {
+    let mut config = ConduitConfig::load()?;
+    if let Some(store) = store {
+        config.storage.path = Some(store);
+    }
+    ConduitBackend::from_settings(config)
+}
+
+// Content given as arguments, or piped in
+fn read_content(words: Vec<String>) -> Result<String, String> {
+    if !words.is_empty() {
+        return Ok(words.join(" "));
+    }
+    let mut stdin = std::io::stdin();
+    if stdin.is_terminal() {
+        return Err("Give the content as arguments or pipe it in".to_string());
+    }
+    let mut content = String::new();
+    stdin.read_to_string(&mut content).map_err(|e| format!("Failed to read standard input: {}", e))?;
+    Ok(content)
+}
+
+async fn serve(backend: ConduitBackend, port: Option<u16>, host: Option<IpAddr>) -> Result<(), String> {
+    let mut addr: SocketAddr = backend.listen_addr();
+    if let Some(port) = port {
+        addr.set_port(port);
+    }
+    if let Some(host) = host {
+        addr.set_ip(host);
+    }
+
+    let mut server = backend.start_server(addr).await?;
+    eprintln!("Conduit is listening on http://{}", server.local_addr());
+    tokio::signal::ctrl_c().await.map_err(|e| format!("Failed to wait for Ctrl+C: {}", e))?;
+    eprintln!("Shutting down");
+    server.shutdown();
+    server.wait().await
+}
+
+async fn run(cli: Cli) -> Result<(), String> {
+    let format = if cli.json { Format::Json } else { cli.format };
+
+    if let Command::Serve { port, host } = cli.command {
+        if cli.remote.is_some() {
+            return Err("`serve` runs a server on the local store and cannot be used with --remote".to_string());
+        }
+        return serve(local_backend(cli.store)?, port, host).await;
+    }
+
+    let client = match cli.remote {
+        Some(url) => Client::remote(&url, cli.api_key)?,
+        None => Client::Local(Box::new(local_backend(cli.store)?)),
+    };
+
+    match cli.command {
+        Command::New { content, title, tags } => {
+            let content = read_content(content)?;
+            let memory = client.create(title.as_deref().unwrap_or_default(), &content, &tags).await?;
+            match format {
+                Format::Json => output::print_memory(&memory, format),
+                Format::Table => {
+                    println!("Created {} ({})", memory.title, memory.id);
+                    Ok(())
+                }
+            }
+        }
+        Command::List { tag, limit } => output::print_memories(&client.list(tag.as_deref(), Some(limit)).await?, format),
+        Command::Search { query, tag } => {
+            let memories = client.search(&query.join(" "), tag.as_deref()).await?;
+            output::print_memories(&memories, format)
+        }
+        Command::Show { id } => output::print_memory(&client.get(&id).await?, format),
+        Command::Delete { id } => {
+            client.delete(&id).await?;
+            if format == Format::Table {
+                println!("Moved {} to the trash", id);
+            }
+            Ok(())
+        }
+        Command::Export { profile } => {
+            let report = client.export(&profile).await?;
+            output::print_export(&report, format)
+        }
+        Command::Serve { .. } => unreachable!("handled above"),
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("conduit: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use std::io::{self, Write};
+use chrono::Local;
+use conduit_backend::memory::{ExportReport, Memory};
+use serde::Serialize;
+
+/// Widest title shown in a table, in characters
+const MAX_TITLE_CHARS: usize = 48;
+
+/// How results are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// Aligned columns for reading in a terminal
+    Table,
+    /// JSON for scripts
+    Json,
+}
+
+// A closed pipe, as with `conduit list | head`, is not an error
+fn write_error(err: io::Error) -> Result<(), String> {
+    match err.kind() {
+        io::ErrorKind::BrokenPipe => Ok(()),
+        _ => Err(format!("Failed to write output: {}", err)),
+    }
+}
+
+fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    writeln!(io::stdout().lock(), "{}", json).or_else(write_error)
+}
+
+// Shorten to `max` characters, marking the cut with an ellipsis
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut cut: String = text.chars().take(max - 1).collect();
+    cut.push('…');
+    cut
+}
+
+/// Print memories as a table of their ID, title, tags and last update, or as a JSON array
+pub fn print_memories(memories: &[Memory], format: Format) -> Result<(), String> {
+    if format == Format::Json {
+        return print_json(memories);
+    }
+    let mut out = io::stdout().lock();
+    if memories.is_empty() {
+        return writeln!(out, "No memories found").or_else(write_error);
+    }
+
+    let rows: Vec<[String; 4]> = memories.iter()
+        .map(|memory| [
+            memory.id.clone(),
+            truncate(&memory.title, MAX_TITLE_CHARS),
+            memory.tags.iter().filter(|tag| !tag.is_empty()).cloned().collect::<Vec<_>>().join(", "),
+            memory.updated_at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string(),
+        ])
+        .collect();
+    let header = ["ID", "TITLE", "TAGS", "UPDATED"].map(str::to_string);
+
+    let mut widths = [0; 4];
+    for row in std::iter::once(&header).chain(&rows) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    for row in std::iter::once(&header).chain(&rows) {
+        let line: Vec<String> = row.iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        if let Err(err) = writeln!(out, "{}", line.join("  ").trim_end()) {
+            return write_error(err);
+        }
+    }
+    Ok(())
+}
+
+/// Print a memory with its metadata and content, or as a JSON object
+pub fn print_memory(memory: &Memory, format: Format) -> Result<(), String> {
+    if format == Format::Json {
+        return print_json(memory);
+    }
+    write_memory(&mut io::stdout().lock(), memory).or_else(write_error)
+}
+
+/// Print where an export was written, or the report as a JSON object
+pub fn print_export(report: &ExportReport, format: Format) -> Result<(), String> {
+    if format == Format::Json {
+        return print_json(report);
+    }
+    writeln!(io::stdout().lock(), "Exported {} memories to {}", report.memories, report.path.display()).or_else(write_error)
+}
+
+fn write_memory(out: &mut impl Write, memory: &Memory) -> io::Result<()> {
+    writeln!(out, "{}", memory.title)?;
+    writeln!(out, "id:      {}", memory.id)?;
+    let tags: Vec<&str> = memory.tags.iter().map(String::as_str).filter(|tag| !tag.is_empty()).collect();
+    if !tags.is_empty() {
+        writeln!(out, "tags:    {}", tags.join(", "))?;
+    }
+    writeln!(out, "created: {}", memory.created_at.with_timezone(&Local).format("%Y-%m-%d %H:%M"))?;
+    writeln!(out, "updated: {}", memory.updated_at.with_timezone(&Local).format("%Y-%m-%d %H:%M"))?;
+    if let Some(remind_at) = memory.remind_at {
+        writeln!(out, "remind:  {}", remind_at.with_timezone(&Local).format("%Y-%m-%d %H:%M"))?;
+    }
+    if !memory.attachments.is_empty() {
+        writeln!(out, "files:   {}", memory.attachments.join(", "))?;
+    }
+    if !memory.content.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "{}", memory.content.trim_end())?;
+    }
+    Ok(())
+}
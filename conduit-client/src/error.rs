@@ -0,0 +1,45 @@
+use conduit_types::OpenAiErrorResponse;
+use reqwest::StatusCode;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("Invalid server URL: {0}")]
+    InvalidUrl(String),
+    
+    /// The server could not be reached, or its response could not be read
+    #[error("Request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    
+    /// The server answered with an error status
+    #[error("Server returned {status}{}", if .message.is_empty() { String::new() } else { format!(": {}", .message) })]
+    Status {
+        status: StatusCode,
+        /// The error message from the response body, if there was one
+        message: String,
+    },
+}
+
+impl ClientError {
+    /// The status the server answered with, if it answered with an error
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Self::Status { status, .. } => Some(*status),
+            Self::Http(err) => err.status(),
+            Self::InvalidUrl(_) => None,
+        }
+    }
+    
+    /// Whether the memory or collection asked for does not exist
+    pub fn is_not_found(&self) -> bool {
+        self.status() == Some(StatusCode::NOT_FOUND)
+    }
+}
+
+// The message of an error response: an OpenAI-style `{"error": {"message"}}`
+// object, or plain text from servers older than the shared error shape
+pub(crate) fn error_message(body: &str) -> String {
+    serde_json::from_str::<OpenAiErrorResponse>(body)
+        .map(|response| response.error.message)
+        .unwrap_or_else(|_| body.trim().to_string())
+}
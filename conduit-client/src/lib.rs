@@ -0,0 +1,269 @@
+//! Typed async client for the Conduit HTTP API
+//!
+//! Requests and responses use the structs from `conduit-types`, the same ones
+//! the server reads and writes, so the client cannot drift from the API.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use conduit_client::Client;
+//! use conduit_client::types::requests::{CreateMemoryRequest, SearchMemoriesRequest};
+//!
+//! # async fn example() -> Result<(), conduit_client::ClientError> {
+//! let client = Client::new("http://127.0.0.1:3000")?.with_api_key("secret");
+//!
+//! let memory = client.create_memory(&CreateMemoryRequest {
+//!     content: "Renew the TLS certificate before June".to_string(),
+//!     tags: vec!["ops".to_string()],
+//!     ..CreateMemoryRequest::default()
+//! }).await?;
+//!
+//! let found = client.search_memories(&SearchMemoriesRequest {
+//!     query: "certificate".to_string(),
+//!     ..SearchMemoriesRequest::default()
+//! }).await?;
+//! assert!(found.iter().any(|m| m.id == memory.id));
+//! # Ok(())
+//! # }
+//! ```
+
+mod error;
+
+use reqwest::{Method, RequestBuilder, Response, Url};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use conduit_types::conversations::{
+    Conversation, ConversationInfo, ConversationToMemoryRequest, CreateConversationRequest,
+};
+use conduit_types::openai::{ChatCompletionRequest, ChatCompletionResponse, EmbeddingRequest, EmbeddingResponse, Model, ModelList};
+use conduit_types::requests::{
+    CollectionMembersRequest, CreateCollectionRequest, CreateMemoryRequest, ListMemoriesQuery, SearchMemoriesRequest,
+    SetImportanceRequest,
+};
+use conduit_types::sessions::{CreateSessionMemoryRequest, SessionInfo};
+use conduit_types::{Collection, CollectionUpdate, ExportReport, Memory};
+
+pub use conduit_types as types;
+pub use error::ClientError;
+
+/// A Conduit server reached over its HTTP API
+#[derive(Debug, Clone)]
+pub struct Client {
+    base_url: Url,
+    api_key: Option<String>,
+    http: reqwest::Client,
+}
+
+impl Client {
+    /// Create a client for the server at `base_url`, such as `http://127.0.0.1:3000`
+    ///
+    /// The URL may include a path when the API is mounted under a prefix.
+    pub fn new(base_url: &str) -> Result<Self, ClientError> {
+        let base_url = Url::parse(base_url).map_err(|e| ClientError::InvalidUrl(format!("{}: {}", base_url, e)))?;
+        if base_url.cannot_be_a_base() {
+            return Err(ClientError::InvalidUrl(base_url.to_string()));
+        }
+        Ok(Self {
+            base_url,
+            api_key: None,
+            http: reqwest::Client::new(),
+        })
+    }
+
+    /// Send `key` as a bearer token with every request
+    pub fn with_api_key(mut self, key: impl Into<String>) -> Self {
+        self.api_key = Some(key.into());
+        self
+    }
+
+    /// Send requests with a configured HTTP client, e.g. one with timeouts or a proxy
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// The server URL requests are sent to
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    // The URL of a route, given as path segments that are escaped as needed
+    fn url(&self, segments: &[&str]) -> Url {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            .expect("checked in Client::new")
+            .pop_if_empty()
+            .extend(segments);
+        url
+    }
+
+    fn request(&self, method: Method, segments: &[&str]) -> RequestBuilder {
+        let request = self.http.request(method, self.url(segments));
+        match &self.api_key {
+            Some(key) => request.bearer_auth(key),
+            None => request,
+        }
+    }
+
+    // Send a request, turning error statuses into `ClientError::Status`
+    async fn send(&self, request: RequestBuilder) -> Result<Response, ClientError> {
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let body = response.text().await.unwrap_or_default();
+        Err(ClientError::Status { status, message: error::error_message(&body) })
+    }
+
+    async fn json<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, ClientError> {
+        Ok(self.send(request).await?.json().await?)
+    }
+
+    async fn get<T: DeserializeOwned>(&self, segments: &[&str]) -> Result<T, ClientError> {
+        self.json(self.request(Method::GET, segments)).await
+    }
+
+    async fn post<B: Serialize + ?Sized, T: DeserializeOwned>(&self, segments: &[&str], body: &B) -> Result<T, ClientError> {
+        self.json(self.request(Method::POST, segments).json(body)).await
+    }
+
+    async fn delete(&self, segments: &[&str]) -> Result<(), ClientError> {
+        self.send(self.request(Method::DELETE, segments)).await.map(|_| ())
+    }
+
+    /// Create a memory and return it as stored
+    pub async fn create_memory(&self, request: &CreateMemoryRequest) -> Result<Memory, ClientError> {
+        self.post(&["api", "memories"], request).await
+    }
+
+    pub async fn get_memory(&self, id: &str) -> Result<Memory, ClientError> {
+        self.get(&["api", "memories", id]).await
+    }
+
+    /// List memories, most recently updated first unless sorted otherwise
+    pub async fn list_memories(&self, query: &ListMemoriesQuery) -> Result<Vec<Memory>, ClientError> {
+        self.json(self.request(Method::GET, &["api", "memories"]).query(query)).await
+    }
+
+    /// Memories whose title, content or tags contain the query
+    pub async fn search_memories(&self, request: &SearchMemoriesRequest) -> Result<Vec<Memory>, ClientError> {
+        self.post(&["api", "memories", "search"], request).await
+    }
+
+    /// Move a memory to the trash
+    pub async fn delete_memory(&self, id: &str) -> Result<(), ClientError> {
+        self.delete(&["api", "memories", id]).await
+    }
+
+    /// Record that a memory was read, and return it
+    pub async fn mark_memory_viewed(&self, id: &str) -> Result<Memory, ClientError> {
+        self.json(self.request(Method::POST, &["api", "memories", id, "viewed"])).await
+    }
+
+    /// Set how much a memory matters, from 0 to 1, or clear it with `None`
+    pub async fn set_memory_importance(&self, id: &str, importance: Option<f32>) -> Result<Memory, ClientError> {
+        let request = SetImportanceRequest { importance };
+        self.json(self.request(Method::PUT, &["api", "memories", id, "importance"]).json(&request)).await
+    }
+
+    /// Memories never viewed or changed since they were, oldest first
+    pub async fn list_unread_memories(&self) -> Result<Vec<Memory>, ClientError> {
+        self.get(&["api", "memories", "unread"]).await
+    }
+
+    /// Add a scratch memory to an agent session, starting the session if needed
+    ///
+    /// Session memories are not saved to the store and are dropped when the
+    /// session ends or expires.
+    pub async fn create_session_memory(&self, session_id: &str, request: &CreateSessionMemoryRequest) -> Result<Memory, ClientError> {
+        self.post(&["api", "sessions", session_id, "memories"], request).await
+    }
+
+    /// Scratch memories of a session, oldest first
+    pub async fn list_session_memories(&self, session_id: &str) -> Result<Vec<Memory>, ClientError> {
+        self.get(&["api", "sessions", session_id, "memories"]).await
+    }
+
+    /// End a session, dropping its scratch memories
+    pub async fn end_session(&self, session_id: &str) -> Result<SessionInfo, ClientError> {
+        self.json(self.request(Method::DELETE, &["api", "sessions", session_id])).await
+    }
+
+    /// Conversations kept in the store, most recently updated first
+    pub async fn list_conversations(&self) -> Result<Vec<ConversationInfo>, ClientError> {
+        self.get(&["api", "conversations"]).await
+    }
+
+    pub async fn get_conversation(&self, id: &str) -> Result<Conversation, ClientError> {
+        self.get(&["api", "conversations", id]).await
+    }
+
+    pub async fn create_conversation(&self, request: &CreateConversationRequest) -> Result<Conversation, ClientError> {
+        self.post(&["api", "conversations"], request).await
+    }
+
+    /// Save a conversation as a memory holding its transcript
+    pub async fn conversation_to_memory(&self, id: &str, request: &ConversationToMemoryRequest) -> Result<Memory, ClientError> {
+        self.post(&["api", "conversations", id, "memory"], request).await
+    }
+
+    pub async fn list_collections(&self) -> Result<Vec<Collection>, ClientError> {
+        self.get(&["api", "collections"]).await
+    }
+
+    pub async fn get_collection(&self, id: &str) -> Result<Collection, ClientError> {
+        self.get(&["api", "collections", id]).await
+    }
+
+    pub async fn create_collection(&self, request: &CreateCollectionRequest) -> Result<Collection, ClientError> {
+        self.post(&["api", "collections"], request).await
+    }
+
+    /// Rename a collection or change its description
+    pub async fn update_collection(&self, id: &str, update: &CollectionUpdate) -> Result<Collection, ClientError> {
+        self.json(self.request(Method::PUT, &["api", "collections", id]).json(update)).await
+    }
+
+    /// Delete a collection; its memories are kept
+    pub async fn delete_collection(&self, id: &str) -> Result<(), ClientError> {
+        self.delete(&["api", "collections", id]).await
+    }
+
+    pub async fn add_to_collection(&self, id: &str, memory_ids: Vec<String>) -> Result<Collection, ClientError> {
+        self.post(&["api", "collections", id, "memories"], &CollectionMembersRequest { memory_ids }).await
+    }
+
+    pub async fn remove_from_collection(&self, id: &str, memory_id: &str) -> Result<Collection, ClientError> {
+        self.json(self.request(Method::DELETE, &["api", "collections", id, "memories", memory_id])).await
+    }
+
+    /// Run a named export profile from the server's config, writing on the server
+    pub async fn run_export_profile(&self, name: &str) -> Result<ExportReport, ClientError> {
+        self.json(self.request(Method::POST, &["api", "export", "profiles", name, "run"])).await
+    }
+
+    /// Models available for chat completions, from `GET /v1/models`
+    pub async fn list_models(&self) -> Result<ModelList, ClientError> {
+        self.get(&["v1", "models"]).await
+    }
+
+    /// One model with its capabilities, from `GET /v1/models/{id}`
+    pub async fn get_model(&self, id: &str) -> Result<Model, ClientError> {
+        self.get(&["v1", "models", id]).await
+    }
+
+    /// A chat completion with relevant memories in its context, from `POST /v1/chat/completions`
+    ///
+    /// The response is always returned whole; `stream` is ignored.
+    pub async fn chat_completion(&self, request: &ChatCompletionRequest) -> Result<ChatCompletionResponse, ClientError> {
+        let request = ChatCompletionRequest { stream: None, ..request.clone() };
+        self.post(&["v1", "chat", "completions"], &request).await
+    }
+
+    /// Embeddings of the input texts, from `POST /v1/embeddings`
+    pub async fn create_embeddings(&self, request: &EmbeddingRequest) -> Result<EmbeddingResponse, ClientError> {
+        self.post(&["v1", "embeddings"], request).await
+    }
+}
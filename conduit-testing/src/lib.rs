@@ -0,0 +1,206 @@
+//! Runs the Conduit backend on a temporary store for integration tests
+//!
+//! [`TestServer`] serves the real API routes over HTTP on a free local port,
+//! with a fresh store in a temporary directory that is removed when the server
+//! is dropped. Tests talk to it with the typed [`Client`], plain HTTP, or the
+//! [`ConduitBackend`] commands the desktop app uses, all reaching the same store.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use conduit_testing::TestServer;
+//! use conduit_testing::types::requests::SearchMemoriesRequest;
+//!
+//! # async fn example() {
+//! let server = TestServer::start().await;
+//! let memory = server.seed("Deploy checklist", "Tag the release, then deploy", &["ops"]).await;
+//!
+//! let found = server.client().search_memories(&SearchMemoriesRequest {
+//!     query: "deploy".to_string(),
+//!     ..SearchMemoriesRequest::default()
+//! }).await.unwrap();
+//! assert_eq!(found[0].id, memory.id);
+//! # }
+//! ```
+
+use std::net::SocketAddr;
+use std::path::Path;
+
+use conduit_backend::config::ConduitConfig;
+use conduit_backend::memory::{init_store_dir, seed_demo_memories, Memory, MemoryStore};
+use conduit_backend::ConduitBackend;
+use conduit_client::types::requests::CreateMemoryRequest;
+use tempfile::TempDir;
+use tokio::task::JoinHandle;
+
+pub use conduit_client::{types, Client, ClientError};
+
+/// Sets up a [`TestServer`] with settings other than the defaults
+///
+/// Settings start from [`ConduitConfig::default`], not from the environment or
+/// a config file, so tests behave the same on every machine.
+#[derive(Default)]
+pub struct TestServerBuilder {
+    config: ConduitConfig,
+}
+
+impl TestServerBuilder {
+    /// Change any setting; the store path is always a new temporary directory
+    pub fn config(mut self, change: impl FnOnce(&mut ConduitConfig)) -> Self {
+        change(&mut self.config);
+        self
+    }
+
+    /// Require this API key, which the server's [`Client`] sends
+    pub fn api_key(self, key: impl Into<String>) -> Self {
+        let key = key.into();
+        self.config(|config| config.auth.api_keys.push(key))
+    }
+
+    /// Turn on password accounts, with an `admin` account logging in with
+    /// `admin_password`; each account's store lives under the temporary store
+    pub fn accounts(self, admin_password: impl Into<String>) -> Self {
+        let admin_password = admin_password.into();
+        self.config(|config| {
+            config.accounts.enabled = true;
+            config.accounts.admin_password = Some(admin_password);
+        })
+    }
+
+    /// Create the store and start serving it
+    ///
+    /// Panics when the server cannot start, which fails the test using it.
+    pub async fn start(mut self) -> TestServer {
+        let dir = TempDir::new().expect("failed to create a temporary store directory");
+        init_store_dir(dir.path()).expect("failed to set up the temporary store");
+        self.config.storage.path = Some(dir.path().to_path_buf());
+        let api_key = self.config.auth.api_keys.first().cloned();
+
+        let backend = ConduitBackend::from_settings(self.config).expect("failed to open the test store");
+        let app = backend.router().expect("failed to set up the API routes");
+        let listener = tokio::net::TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .await
+            .expect("failed to bind a local port");
+        let addr = listener.local_addr().expect("failed to read the bound address");
+        let task = tokio::spawn(async move {
+            axum::serve(listener, app).await.expect("test server failed");
+        });
+
+        let base_url = format!("http://{}", addr);
+        let client = Client::new(&base_url).expect("the server address is a valid URL");
+        let client = match api_key {
+            Some(key) => client.with_api_key(key),
+            None => client,
+        };
+
+        TestServer { backend, client, http: reqwest::Client::new(), addr, base_url, task, dir }
+    }
+}
+
+/// The backend serving a temporary store on a free local port
+///
+/// Dropping the server stops it and removes the store.
+pub struct TestServer {
+    backend: ConduitBackend,
+    client: Client,
+    http: reqwest::Client,
+    addr: SocketAddr,
+    base_url: String,
+    task: JoinHandle<()>,
+    dir: TempDir,
+}
+
+impl TestServer {
+    /// Start a server with the default settings and an open API
+    pub async fn start() -> Self {
+        Self::builder().start().await
+    }
+
+    pub fn builder() -> TestServerBuilder {
+        TestServerBuilder::default()
+    }
+
+    /// Address the server listens on
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Base URL of the server, e.g. `http://127.0.0.1:41234`
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Full URL of a path on the server, e.g. `url("/api/memories")`
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    /// Typed client for the server, sending the API key if one is required
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Plain HTTP client, for routes the typed client does not cover or
+    /// requests it would not send; no API key is added
+    pub fn http(&self) -> &reqwest::Client {
+        &self.http
+    }
+
+    /// The backend behind the server, as the desktop app's commands use it
+    pub fn backend(&self) -> &ConduitBackend {
+        &self.backend
+    }
+
+    /// The store behind the server
+    pub fn store(&self) -> std::sync::Arc<MemoryStore> {
+        self.backend.memory_store().expect("the test server always uses the file store")
+    }
+
+    /// Directory of the temporary store
+    pub fn store_path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Create a memory through the API, as a client would
+    pub async fn seed(&self, title: &str, content: &str, tags: &[&str]) -> Memory {
+        self.seed_request(&CreateMemoryRequest {
+            title: title.to_string(),
+            content: content.to_string(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            ..CreateMemoryRequest::default()
+        }).await
+    }
+
+    /// Create a memory through the API from a full request
+    ///
+    /// Panics when the server refuses it, which fails the test using it.
+    pub async fn seed_request(&self, request: &CreateMemoryRequest) -> Memory {
+        match self.client.create_memory(request).await {
+            Ok(memory) => memory,
+            Err(err) => panic!("failed to seed memory {:?}: {}", request.title, err),
+        }
+    }
+
+    /// Create memories through the API, in order
+    pub async fn seed_all(&self, memories: &[(&str, &str, &[&str])]) -> Vec<Memory> {
+        let mut seeded = Vec::with_capacity(memories.len());
+        for (title, content, tags) in memories {
+            seeded.push(self.seed(title, content, tags).await);
+        }
+        seeded
+    }
+
+    /// Fill the store with the generated sample memories of demo mode
+    ///
+    /// The same seed always produces the same memories. Returns how many were created.
+    pub fn seed_demo(&self, seed: u64) -> usize {
+        seed_demo_memories(self.store().as_ref(), seed).expect("failed to create the sample memories")
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        // Dropping the routes ends the background tasks holding the server state
+        self.task.abort();
+    }
+}
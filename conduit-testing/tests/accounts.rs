@@ -0,0 +1,162 @@
+//! Accounts of a multi-user server log in with a password and each keep
+//! their memories in a store of their own; only admins manage accounts.
+
+use conduit_testing::TestServer;
+use serde_json::{json, Value};
+
+async fn login(server: &TestServer, name: &str, password: &str) -> reqwest::Response {
+    server.http().post(server.url("/api/auth/login"))
+        .json(&json!({ "name": name, "password": password }))
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn token(server: &TestServer, name: &str, password: &str) -> String {
+    let response = login(server, name, password).await;
+    assert_eq!(response.status(), 200);
+    let session: Value = response.json().await.unwrap();
+    session["token"].as_str().unwrap().to_string()
+}
+
+async fn create_account(server: &TestServer, token: &str, name: &str, password: &str) -> reqwest::Response {
+    server.http().post(server.url("/api/accounts"))
+        .bearer_auth(token)
+        .json(&json!({ "name": name, "password": password }))
+        .send()
+        .await
+        .unwrap()
+}
+
+async fn titles(server: &TestServer, token: &str) -> Vec<String> {
+    let response = server.http().get(server.url("/api/memories")).bearer_auth(token).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    let listed: Vec<Value> = response.json().await.unwrap();
+    listed.iter().filter_map(|memory| memory["title"].as_str().map(str::to_string)).collect()
+}
+
+#[tokio::test]
+async fn accounts_only_see_their_own_memories() {
+    let server = TestServer::builder().accounts("admin-password").start().await;
+    let admin = token(&server, "admin", "admin-password").await;
+    for name in ["alice", "bob"] {
+        let response = create_account(&server, &admin, name, &format!("{}-password", name)).await;
+        assert_eq!(response.status(), 201);
+    }
+    let alice = token(&server, "alice", "alice-password").await;
+    let bob = token(&server, "bob", "bob-password").await;
+
+    let response = server.http().post(server.url("/api/memories"))
+        .bearer_auth(&alice)
+        .json(&json!({ "title": "Alice's plans", "content": "Private", "tags": [] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+
+    assert_eq!(titles(&server, &alice).await, ["Alice's plans"]);
+    assert!(titles(&server, &bob).await.is_empty());
+    assert!(titles(&server, &admin).await.is_empty());
+    assert!(server.store_path().join(".accounts").join("alice").is_dir());
+
+    // Without a session nothing is served
+    let response = server.http().get(server.url("/api/memories")).send().await.unwrap();
+    assert_eq!(response.status(), 401);
+}
+
+#[tokio::test]
+async fn only_admins_manage_accounts() {
+    let server = TestServer::builder().accounts("admin-password").start().await;
+    let admin = token(&server, "admin", "admin-password").await;
+    assert_eq!(create_account(&server, &admin, "carol", "carol-password").await.status(), 201);
+    assert_eq!(create_account(&server, &admin, "carol", "carol-password").await.status(), 409);
+    assert_eq!(create_account(&server, &admin, "dave", "short").await.status(), 400);
+
+    let carol = token(&server, "carol", "carol-password").await;
+    assert_eq!(create_account(&server, &carol, "eve", "eve-password").await.status(), 403);
+    let response = server.http().get(server.url("/api/accounts")).bearer_auth(&carol).send().await.unwrap();
+    assert_eq!(response.status(), 403);
+
+    // The last admin cannot be removed
+    let response = server.http().delete(server.url("/api/accounts/admin")).bearer_auth(&admin).send().await.unwrap();
+    assert_eq!(response.status(), 409);
+
+    // Disabling an account ends its sessions and stops it logging in
+    let response = server.http().patch(server.url("/api/accounts/carol"))
+        .bearer_auth(&admin)
+        .json(&json!({ "disabled": true }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let response = server.http().get(server.url("/api/auth/me")).bearer_auth(&carol).send().await.unwrap();
+    assert_eq!(response.status(), 401);
+    assert_eq!(login(&server, "carol", "carol-password").await.status(), 403);
+}
+
+#[tokio::test]
+async fn changing_the_password_ends_other_sessions() {
+    let server = TestServer::builder().accounts("admin-password").start().await;
+    let first = token(&server, "admin", "admin-password").await;
+    let response = server.http().put(server.url("/api/auth/password"))
+        .bearer_auth(&first)
+        .json(&json!({ "current_password": "admin-password", "new_password": "new-admin-password" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let session: Value = response.json().await.unwrap();
+    let second = session["token"].as_str().unwrap();
+
+    let response = server.http().get(server.url("/api/auth/me")).bearer_auth(&first).send().await.unwrap();
+    assert_eq!(response.status(), 401);
+    let response = server.http().get(server.url("/api/auth/me")).bearer_auth(second).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(login(&server, "admin", "admin-password").await.status(), 401);
+}
+
+#[tokio::test]
+async fn a_recreated_account_starts_empty() {
+    let server = TestServer::builder().accounts("admin-password").start().await;
+    let admin = token(&server, "admin", "admin-password").await;
+    assert_eq!(create_account(&server, &admin, "frank", "frank-password").await.status(), 201);
+    let frank = token(&server, "frank", "frank-password").await;
+    let response = server.http().post(server.url("/api/memories"))
+        .bearer_auth(&frank)
+        .json(&json!({ "title": "Frank's notes", "content": "Private", "tags": [] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+
+    let response = server.http().delete(server.url("/api/accounts/frank")).bearer_auth(&admin).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(create_account(&server, &admin, "frank", "other-password").await.status(), 201);
+    let frank = token(&server, "frank", "other-password").await;
+    assert!(titles(&server, &frank).await.is_empty());
+
+    // The removed account's store is kept aside
+    let deleted = server.store_path().join(".accounts").join(".deleted");
+    assert_eq!(std::fs::read_dir(deleted).unwrap().count(), 1);
+}
+
+#[tokio::test]
+async fn repeated_failed_logins_have_to_wait() {
+    let server = TestServer::builder().accounts("admin-password").start().await;
+    for _ in 0..5 {
+        assert_eq!(login(&server, "admin", "wrong-password").await.status(), 401);
+    }
+    let response = login(&server, "admin", "admin-password").await;
+    assert_eq!(response.status(), 429);
+    assert_eq!(response.headers()["retry-after"], "1");
+
+    // Unknown names are throttled the same way, and other names are not held back
+    for _ in 0..5 {
+        assert_eq!(login(&server, "mallory", "guess-password").await.status(), 401);
+    }
+    assert_eq!(login(&server, "mallory", "guess-password").await.status(), 429);
+
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    assert_eq!(login(&server, "admin", "admin-password").await.status(), 200);
+    assert_eq!(login(&server, "admin", "wrong-password").await.status(), 401);
+}
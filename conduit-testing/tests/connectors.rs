@@ -0,0 +1,65 @@
+//! Listing connectors never shows their credentials, and with accounts only
+//! admins may list them.
+
+use conduit_backend::api::connectors::{ConnectorConfig, ConnectorKind};
+use conduit_testing::TestServer;
+use serde_json::{json, Value};
+
+const WEBHOOK_URL: &str = "https://hooks.slack.com/services/T0000/B0000/secret-part";
+
+fn slack_webhook() -> ConnectorConfig {
+    ConnectorConfig {
+        name: "team".to_string(),
+        kind: ConnectorKind::Slack,
+        url: Some(WEBHOOK_URL.to_string()),
+        ..ConnectorConfig::default()
+    }
+}
+
+#[tokio::test]
+async fn webhook_urls_are_listed_without_their_path() {
+    let server = TestServer::builder()
+        .config(|config| config.connectors.endpoints.push(slack_webhook()))
+        .start()
+        .await;
+
+    let response = server.http().get(server.url("/api/connectors")).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    let body = response.text().await.unwrap();
+    assert!(!body.contains("secret-part"));
+    let listed: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(listed["connectors"][0]["url"], "https://hooks.slack.com");
+    assert_eq!(listed["connectors"][0]["authenticated"], true);
+}
+
+#[tokio::test]
+async fn only_admins_list_connectors() {
+    let server = TestServer::builder()
+        .accounts("admin-password")
+        .config(|config| config.connectors.endpoints.push(slack_webhook()))
+        .start()
+        .await;
+    let login = |name: &'static str, password: &'static str| {
+        server.http().post(server.url("/api/auth/login"))
+            .json(&json!({ "name": name, "password": password }))
+            .send()
+    };
+    let session: Value = login("admin", "admin-password").await.unwrap().json().await.unwrap();
+    let admin = session["token"].as_str().unwrap().to_string();
+    let response = server.http().post(server.url("/api/accounts"))
+        .bearer_auth(&admin)
+        .json(&json!({ "name": "carol", "password": "carol-password" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    let session: Value = login("carol", "carol-password").await.unwrap().json().await.unwrap();
+    let carol = session["token"].as_str().unwrap().to_string();
+
+    for path in ["/api/connectors", "/api/connectors/deliveries"] {
+        let response = server.http().get(server.url(path)).bearer_auth(&carol).send().await.unwrap();
+        assert_eq!(response.status(), 403, "{}", path);
+        let response = server.http().get(server.url(path)).bearer_auth(&admin).send().await.unwrap();
+        assert_eq!(response.status(), 200, "{}", path);
+    }
+}
@@ -0,0 +1,73 @@
+//! Every route answers errors with the same JSON object, carrying the ID of
+//! the request it failed.
+
+use conduit_testing::TestServer;
+use serde_json::Value;
+
+// Status, request ID header and error object of a response
+async fn error_of(response: reqwest::Response) -> (u16, Option<String>, Value) {
+    let status = response.status().as_u16();
+    let request_id = response.headers()
+        .get("x-request-id")
+        .map(|value| value.to_str().unwrap().to_string());
+    let body: Value = response.json().await.unwrap();
+    (status, request_id, body["error"].clone())
+}
+
+#[tokio::test]
+async fn missing_memory_is_a_json_404() {
+    let server = TestServer::start().await;
+
+    for path in ["/api/memories/nope", "/v1/memories/nope"] {
+        let response = server.http().get(server.url(path)).send().await.unwrap();
+        let (status, request_id, error) = error_of(response).await;
+        assert_eq!(status, 404, "{}", path);
+        assert_eq!(error["type"], "invalid_request_error");
+        assert_eq!(error["code"], "not_found");
+        assert!(request_id.is_some());
+        assert_eq!(error["request_id"].as_str(), request_id.as_deref());
+    }
+}
+
+#[tokio::test]
+async fn client_request_id_is_echoed() {
+    let server = TestServer::start().await;
+
+    let response = server.http().get(server.url("/api/memories/nope"))
+        .header("x-request-id", "trace-42")
+        .send()
+        .await
+        .unwrap();
+    let (_, request_id, error) = error_of(response).await;
+    assert_eq!(request_id.as_deref(), Some("trace-42"));
+    assert_eq!(error["request_id"], "trace-42");
+}
+
+#[tokio::test]
+async fn unknown_route_and_bad_query_use_the_same_shape() {
+    let server = TestServer::start().await;
+
+    let response = server.http().get(server.url("/api/no-such-route")).send().await.unwrap();
+    let (status, _, error) = error_of(response).await;
+    assert_eq!(status, 404);
+    assert!(error["message"].is_string());
+    assert!(error["request_id"].is_string());
+
+    let response = server.http().get(server.url("/api/memories?limit=many")).send().await.unwrap();
+    let (status, _, error) = error_of(response).await;
+    assert_eq!(status, 400);
+    assert_eq!(error["type"], "invalid_request_error");
+}
+
+#[tokio::test]
+async fn missing_api_key_is_a_json_401() {
+    let server = TestServer::builder().api_key("secret").start().await;
+
+    let response = server.http().get(server.url("/api/memories")).send().await.unwrap();
+    assert_eq!(response.headers()["www-authenticate"], "Bearer");
+    let (status, _, error) = error_of(response).await;
+    assert_eq!(status, 401);
+    assert_eq!(error["type"], "authentication_error");
+    assert_eq!(error["code"], "invalid_api_key");
+    assert!(error["request_id"].is_string());
+}
@@ -0,0 +1,70 @@
+//! The harness serves the real routes, and every way of reaching the server
+//! sees the same store.
+
+use conduit_testing::types::requests::{ListMemoriesQuery, SearchMemoriesRequest};
+use conduit_testing::TestServer;
+
+#[tokio::test]
+async fn seeded_memories_are_served_over_http() {
+    let server = TestServer::start().await;
+    let seeded = server.seed_all(&[
+        ("Standup", "Ship the release on Friday", &["team"]),
+        ("Groceries", "Oat milk and coffee beans", &[]),
+    ]).await;
+
+    let listed = server.client().list_memories(&ListMemoriesQuery::default()).await.unwrap();
+    assert_eq!(listed.len(), 2);
+
+    let found = server.client().search_memories(&SearchMemoriesRequest {
+        query: "release".to_string(),
+        ..SearchMemoriesRequest::default()
+    }).await.unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].id, seeded[0].id);
+    assert_eq!(found[0].tags, vec!["team".to_string()]);
+
+    let response = server.http().get(server.url(&format!("/api/memories/{}", seeded[1].id))).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+}
+
+#[tokio::test]
+async fn seeded_memories_are_seen_by_commands() {
+    let server = TestServer::start().await;
+    let seeded = server.seed("Release checklist", "Bump the version", &["ops"]).await;
+
+    let memory = server.backend().get_memory(&seeded.id).unwrap();
+    assert_eq!(memory.content, "Bump the version");
+    assert!(server.store_path().read_dir().unwrap().flatten().any(|entry| entry.file_name().to_string_lossy().ends_with(".md")));
+}
+
+#[tokio::test]
+async fn api_key_is_required_and_sent_by_the_client() {
+    let server = TestServer::builder().api_key("test-key").start().await;
+
+    let response = server.http().get(server.url("/api/memories")).send().await.unwrap();
+    assert_eq!(response.status(), 401);
+
+    server.seed("Private", "Only for key holders", &[]).await;
+    let listed = server.client().list_memories(&ListMemoriesQuery::default()).await.unwrap();
+    assert_eq!(listed.len(), 1);
+}
+
+#[tokio::test]
+async fn demo_memories_can_be_seeded() {
+    let server = TestServer::start().await;
+    let count = server.seed_demo(7);
+    assert!(count > 0);
+
+    let listed = server.client().list_memories(&ListMemoriesQuery { limit: Some(1000), ..ListMemoriesQuery::default() }).await.unwrap();
+    assert_eq!(listed.len(), count);
+}
+
+#[tokio::test]
+async fn store_is_removed_when_the_server_is_dropped() {
+    let server = TestServer::start().await;
+    server.seed("Scratch", "Gone after the test", &[]).await;
+    let path = server.store_path().to_path_buf();
+
+    drop(server);
+    assert!(!path.exists());
+}
@@ -0,0 +1,105 @@
+//! A write that has returned is seen by every later read: a memory created,
+//! or an attachment stored, shows up in the next list and search, even while
+//! other writes are racing it and before any background job has run.
+
+use conduit_backend::memory::Memory;
+use conduit_testing::TestServer;
+use serde_json::{json, Value};
+
+async fn create(server: &TestServer, title: &str, content: &str) -> Memory {
+    let response = server.http().post(server.url("/api/memories"))
+        .json(&json!({ "title": title, "content": content, "tags": [] }))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success(), "create failed: {}", response.status());
+    response.json().await.unwrap()
+}
+
+async fn list_ids(server: &TestServer) -> Vec<String> {
+    let listed: Vec<Value> = server.http().get(server.url("/api/memories?limit=1000")).send().await.unwrap().json().await.unwrap();
+    listed.iter().filter_map(|memory| memory["id"].as_str().map(str::to_string)).collect()
+}
+
+async fn search_ids(server: &TestServer, query: &str) -> Vec<String> {
+    let response = server.http().post(server.url("/api/memories/search"))
+        .json(&json!({ "query": query }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let found: Vec<Value> = response.json().await.unwrap();
+    found.iter().filter_map(|memory| memory["id"].as_str().map(str::to_string)).collect()
+}
+
+// Upload one file as `multipart/form-data`
+async fn upload(server: &TestServer, id: &str, name: &str, data: &str) {
+    let boundary = "conduit-test-boundary";
+    let body = format!(
+        "--{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{name}\"\r\nContent-Type: text/plain\r\n\r\n{data}\r\n--{b}--\r\n",
+        b = boundary, name = name, data = data,
+    );
+    let response = server.http().post(server.url(&format!("/api/memories/{}/attachments", id)))
+        .header("content-type", format!("multipart/form-data; boundary={}", boundary))
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+}
+
+#[tokio::test]
+async fn created_memory_is_listed_and_searched_right_away() {
+    let server = TestServer::start().await;
+
+    for i in 0..10 {
+        let word = format!("quokka{}", i);
+        let created = create(&server, &format!("Note {}", i), &format!("Spotted a {} today", word)).await;
+
+        assert!(list_ids(&server).await.contains(&created.id), "note {} missing from the list", i);
+        assert_eq!(search_ids(&server, &word).await, vec![created.id]);
+    }
+}
+
+#[tokio::test]
+async fn racing_writers_each_read_their_own_write() {
+    let server = std::sync::Arc::new(TestServer::start().await);
+
+    let writers: Vec<_> = (0..8).map(|i| {
+        let server = server.clone();
+        tokio::spawn(async move {
+            let word = format!("pangolin{}", i);
+            let created = create(&server, &format!("Writer {}", i), &format!("Wrote about {}", word)).await;
+            assert!(list_ids(&server).await.contains(&created.id), "writer {} missed its memory in the list", i);
+            assert!(search_ids(&server, &word).await.contains(&created.id), "writer {} missed its memory in search", i);
+        })
+    }).collect();
+    for writer in writers {
+        writer.await.unwrap();
+    }
+
+    assert_eq!(list_ids(&server).await.len(), 8);
+}
+
+#[tokio::test]
+async fn uploaded_attachment_text_is_searched_right_away() {
+    let server = TestServer::start().await;
+    let created = create(&server, "Trip", "Receipts from the trip").await;
+
+    // The background extraction only runs once a minute, so a search racing it would miss the text
+    upload(&server, &created.id, "receipt.txt", "Dinner at the axolotl bistro").await;
+
+    assert_eq!(search_ids(&server, "axolotl").await, vec![created.id]);
+}
+
+#[tokio::test]
+async fn attachment_stored_by_command_is_searched_right_away() {
+    let server = TestServer::start().await;
+    let id = server.backend().create_memory("Manual".to_string(), "Printer setup".to_string(), Vec::new()).unwrap();
+
+    server.backend().add_attachment(&id, "manual.txt", b"Reset the narwhal toner drum").unwrap();
+
+    assert_eq!(search_ids(&server, "narwhal").await, vec![id.clone()]);
+    let found = server.backend().search_memories("narwhal").unwrap();
+    assert!(found.iter().any(|memory| memory.id == id));
+}
@@ -0,0 +1,24 @@
+//! First-run setup can pick the store, but once the served store is in use,
+//! setup no longer moves it.
+
+use conduit_testing::TestServer;
+use serde_json::{json, Value};
+
+#[tokio::test]
+async fn store_path_is_refused_after_first_run() {
+    let server = TestServer::start().await;
+    server.seed("Existing note", "The store is in use", &[]).await;
+    let other = tempfile::TempDir::new().unwrap();
+
+    let response = server.http().post(server.url("/api/setup"))
+        .json(&json!({ "store_path": other.path(), "starter_templates": [] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 409);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "already_set_up");
+
+    // Nothing was set up in the other folder
+    assert_eq!(std::fs::read_dir(other.path()).unwrap().count(), 0);
+}
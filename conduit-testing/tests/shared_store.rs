@@ -0,0 +1,91 @@
+//! The desktop app's commands call `ConduitBackend` directly while its API
+//! server answers HTTP clients. These tests check that both go through the
+//! same store, so a change made on one side is seen by the other.
+
+use conduit_backend::memory::{Memory, MemoryEventKind};
+use conduit_testing::TestServer;
+use serde_json::json;
+
+async fn create_over_http(server: &TestServer, title: &str, content: &str) -> Memory {
+    let response = server.http().post(server.url("/api/memories"))
+        .json(&json!({ "title": title, "content": content, "tags": ["shared"] }))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success(), "create failed: {}", response.status());
+    response.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn memory_created_by_command_is_served_over_http() {
+    let server = TestServer::start().await;
+    let id = server.backend()
+        .create_memory("Standup notes".to_string(), "Ship the release on Friday".to_string(), vec!["team".to_string()])
+        .unwrap();
+
+    let response = server.http().get(server.url(&format!("/api/memories/{}", id))).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    let memory: Memory = response.json().await.unwrap();
+    assert_eq!(memory.title, "Standup notes");
+    assert_eq!(memory.content, "Ship the release on Friday");
+
+    let listed: Vec<Memory> = server.http().get(server.url("/api/memories")).send().await.unwrap().json().await.unwrap();
+    assert!(listed.iter().any(|m| m.id == id));
+}
+
+#[tokio::test]
+async fn memory_created_over_http_is_seen_by_commands() {
+    let server = TestServer::start().await;
+    let created = create_over_http(&server, "Release checklist", "Bump the version and tag the commit").await;
+
+    let memory = server.backend().get_memory(&created.id).unwrap();
+    assert_eq!(memory.content, "Bump the version and tag the commit");
+    assert_eq!(memory.tags, vec!["shared".to_string()]);
+
+    let found = server.backend().search_memories("checklist").unwrap();
+    assert!(found.iter().any(|m| m.id == created.id));
+}
+
+#[tokio::test]
+async fn deletes_and_restores_cross_both_sides() {
+    let server = TestServer::start().await;
+    let created = create_over_http(&server, "Old idea", "Might come back to this").await;
+
+    let response = server.http().delete(server.url(&format!("/api/memories/{}", created.id))).send().await.unwrap();
+    assert!(response.status().is_success());
+    assert!(server.backend().get_memory(&created.id).is_err());
+    assert!(server.backend().list_trash().unwrap().iter().any(|t| t.memory.id == created.id));
+
+    server.backend().restore_memory(&created.id).unwrap();
+    let response = server.http().get(server.url(&format!("/api/memories/{}", created.id))).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+}
+
+#[tokio::test]
+async fn collection_changes_cross_both_sides() {
+    let server = TestServer::start().await;
+    let collection = server.backend().create_collection("Reading list", None, Vec::new()).unwrap();
+    let created = create_over_http(&server, "Paper", "Attention is all you need").await;
+
+    let response = server.http().post(server.url(&format!("/api/collections/{}/memories", collection.id)))
+        .json(&json!({ "memory_ids": [created.id] }))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+
+    let collection = server.backend().get_collection(&collection.id).unwrap();
+    assert!(collection.contains(&created.id));
+}
+
+#[tokio::test]
+async fn http_changes_reach_command_event_subscribers() {
+    let server = TestServer::start().await;
+    let mut events = server.backend().memory_events().unwrap();
+
+    let created = create_over_http(&server, "Event", "Seen by the desktop window").await;
+
+    let event = tokio::time::timeout(std::time::Duration::from_secs(5), events.recv()).await.unwrap().unwrap();
+    assert_eq!(event.kind, MemoryEventKind::Created);
+    assert_eq!(event.id, created.id);
+}
@@ -0,0 +1,133 @@
+//! Bodies that do not parse or break a rule are refused before anything is
+//! written, with an OpenAI-style error object on `/api` and `/v1` alike.
+
+use conduit_testing::TestServer;
+use serde_json::{json, Value};
+
+// Send a raw JSON body and return the status and error object
+async fn post(server: &TestServer, path: &str, body: &str) -> (u16, Value) {
+    let response = server.http().post(server.url(path))
+        .header("content-type", "application/json")
+        .body(body.to_string())
+        .send()
+        .await
+        .unwrap();
+    let status = response.status().as_u16();
+    let body: Value = response.json().await.unwrap();
+    (status, body["error"].clone())
+}
+
+async fn post_json(server: &TestServer, path: &str, body: Value) -> (u16, Value) {
+    post(server, path, &body.to_string()).await
+}
+
+async fn memory_count(server: &TestServer) -> usize {
+    let listed: Vec<Value> = server.http().get(server.url("/api/memories")).send().await.unwrap().json().await.unwrap();
+    listed.len()
+}
+
+#[tokio::test]
+async fn malformed_json_is_a_400() {
+    let server = TestServer::start().await;
+
+    for path in ["/api/memories", "/v1/memories"] {
+        let (status, error) = post(&server, path, r#"{"title": "Unclosed", "content": "#).await;
+        assert_eq!(status, 400, "{}", path);
+        assert_eq!(error["type"], "invalid_request_error");
+        assert!(error["message"].as_str().unwrap().contains("parse"), "{}: {}", path, error);
+    }
+    assert_eq!(memory_count(&server).await, 0);
+}
+
+#[tokio::test]
+async fn missing_field_is_a_422() {
+    let server = TestServer::start().await;
+
+    for path in ["/api/memories", "/v1/memories"] {
+        let (status, error) = post_json(&server, path, json!({ "title": "No content" })).await;
+        assert_eq!(status, 422, "{}", path);
+        assert_eq!(error["type"], "invalid_request_error");
+        assert!(error["message"].as_str().unwrap().contains("content"), "{}: {}", path, error);
+    }
+}
+
+#[tokio::test]
+async fn blank_title_and_content_is_refused() {
+    let server = TestServer::start().await;
+
+    for path in ["/api/memories", "/v1/memories"] {
+        let (status, error) = post_json(&server, path, json!({ "title": "  ", "content": "", "tags": [] })).await;
+        assert_eq!(status, 422, "{}", path);
+        assert_eq!(error["param"], "title");
+        assert_eq!(error["code"], "empty_title");
+    }
+    assert_eq!(memory_count(&server).await, 0);
+}
+
+#[tokio::test]
+async fn too_many_or_blank_tags_are_refused() {
+    let server = TestServer::start().await;
+    let tags: Vec<String> = (0..1000).map(|i| format!("tag{}", i)).collect();
+
+    for path in ["/api/memories", "/v1/memories"] {
+        let (status, error) = post_json(&server, path, json!({ "title": "Tagged", "content": "x", "tags": tags })).await;
+        assert_eq!(status, 422, "{}", path);
+        assert_eq!(error["param"], "tags");
+        assert_eq!(error["code"], "too_many_tags");
+
+        let (status, error) = post_json(&server, path, json!({ "title": "Tagged", "content": "x", "tags": ["ok", " "] })).await;
+        assert_eq!(status, 422, "{}", path);
+        assert_eq!(error["code"], "empty_tag");
+    }
+    assert_eq!(memory_count(&server).await, 0);
+}
+
+#[tokio::test]
+async fn oversized_title_and_content_are_refused() {
+    let server = TestServer::builder().config(|config| config.validation.max_content_bytes = 1024).start().await;
+
+    for path in ["/api/memories", "/v1/memories"] {
+        let (status, error) = post_json(&server, path, json!({ "title": "Big", "content": "x".repeat(2048), "tags": [] })).await;
+        assert_eq!(status, 422, "{}", path);
+        assert_eq!(error["param"], "content");
+        assert_eq!(error["code"], "content_too_large");
+
+        let (status, error) = post_json(&server, path, json!({ "title": "t".repeat(501), "content": "x", "tags": [] })).await;
+        assert_eq!(status, 422, "{}", path);
+        assert_eq!(error["code"], "title_too_long");
+    }
+
+    let (status, _) = post_json(&server, "/api/memories", json!({ "title": "Fits", "content": "x".repeat(1024), "tags": [] })).await;
+    assert_eq!(status, 201);
+}
+
+#[tokio::test]
+async fn invalid_ids_are_refused() {
+    let server = TestServer::start().await;
+    let memory = server.seed("Rename me", "Soon", &[]).await;
+
+    let (status, error) = post_json(&server, &format!("/api/memories/{}/rename", memory.id), json!({ "new_id": "../escape" })).await;
+    assert_eq!(status, 422);
+    assert_eq!(error["param"], "new_id");
+    assert_eq!(error["code"], "invalid_id");
+
+    let (status, error) = post_json(&server, "/api/collections", json!({ "name": "Reading", "memory_ids": [memory.id, "a:b"] })).await;
+    assert_eq!(status, 422);
+    assert_eq!(error["param"], "memory_ids");
+    assert_eq!(error["code"], "invalid_id");
+
+    let (status, error) = post_json(&server, "/api/memories", json!({ "title": "In a folder", "content": "x", "tags": [], "collection": ".hidden" })).await;
+    assert_eq!(status, 422);
+    assert_eq!(error["param"], "collection");
+}
+
+#[tokio::test]
+async fn valid_requests_still_succeed() {
+    let server = TestServer::start().await;
+
+    let (status, _) = post_json(&server, "/api/memories", json!({ "title": "", "content": "Title comes from here", "tags": ["a"] })).await;
+    assert_eq!(status, 201);
+    let (status, _) = post_json(&server, "/v1/memories", json!({ "title": "Both", "content": "set", "tags": [] })).await;
+    assert_eq!(status, 201);
+    assert_eq!(memory_count(&server).await, 2);
+}
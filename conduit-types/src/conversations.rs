@@ -0,0 +1,81 @@
+//! Chat sessions kept in the store, under `/api/conversations`
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::openai::ChatMessage;
+
+/// A chat session with its whole history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Conversation {
+    pub id: String,
+    pub title: String,
+    /// Model of the latest exchange
+    #[serde(default)]
+    pub model: Option<String>,
+    /// User the conversation belongs to; `None` for conversations started with an API key
+    #[serde(default)]
+    pub owner: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Memory the conversation was last saved as
+    #[serde(default)]
+    pub memory_id: Option<String>,
+    #[serde(default)]
+    pub messages: Vec<ChatMessage>,
+}
+
+/// A conversation in a listing, without its messages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ConversationInfo {
+    pub id: String,
+    pub title: String,
+    pub model: Option<String>,
+    pub owner: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub memory_id: Option<String>,
+    pub message_count: usize,
+}
+
+impl From<&Conversation> for ConversationInfo {
+    fn from(conversation: &Conversation) -> Self {
+        Self {
+            id: conversation.id.clone(),
+            title: conversation.title.clone(),
+            model: conversation.model.clone(),
+            owner: conversation.owner.clone(),
+            created_at: conversation.created_at,
+            updated_at: conversation.updated_at,
+            memory_id: conversation.memory_id.clone(),
+            message_count: conversation.messages.len(),
+        }
+    }
+}
+
+/// Body of `POST /api/conversations`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateConversationRequest {
+    /// Taken from the first user message when missing or blank
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    /// History to start the conversation with
+    #[serde(default)]
+    pub messages: Vec<ChatMessage>,
+}
+
+/// Body of `POST /api/conversations/:id/memory`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ConversationToMemoryRequest {
+    /// The conversation's title when missing or blank
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
@@ -0,0 +1,32 @@
+//! Exports of the store, from `/api/export` and named export profiles
+
+use std::path::PathBuf;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// How an export is written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// A directory of markdown files, with attachments under `attachments/<id>/`
+    #[default]
+    Markdown,
+    /// A zip of the markdown files and attachments with an `index.json` manifest
+    Zip,
+    /// A JSON file of memory objects, oldest first
+    Json,
+}
+
+/// Result of exporting the store
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ExportReport {
+    /// Directory or file the memories were written to
+    #[cfg_attr(feature = "openapi", schema(value_type = String))]
+    pub path: PathBuf,
+    pub memories: usize,
+    /// Point in time the export reflects; later changes are not included
+    pub snapshot_at: DateTime<Utc>,
+    pub completed_at: DateTime<Utc>,
+}
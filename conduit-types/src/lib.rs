@@ -0,0 +1,22 @@
+//! Types exchanged over the Conduit HTTP API
+//!
+//! The server deserializes requests into these types and serializes its
+//! responses from them, and clients such as `conduit-client` do the reverse,
+//! so both sides always agree on the wire format. The crate only depends on
+//! serde, chrono and uuid; enable the `openapi` feature to derive
+//! `utoipa::ToSchema` for every type.
+
+pub mod conversations;
+pub mod export;
+pub mod memory;
+pub mod openai;
+pub mod query;
+pub mod requests;
+pub mod responses;
+pub mod sessions;
+
+pub use export::{ExportFormat, ExportReport};
+pub use memory::{Collection, CollectionDefaults, CollectionUpdate, Grant, GranteeKind, Memory, Permission, Reference};
+pub use openai::{OpenAiErrorBody, OpenAiErrorResponse, OpenAiErrorType};
+pub use query::{MatchField, QueryError, SearchMatch, SearchQuery, SearchResult};
+pub use responses::ApiResponse;
@@ -0,0 +1,217 @@
+//! Memories and collections, as returned by `/api/memories` and `/api/collections`
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Memory {
+    pub id: String,
+    pub title: String,
+    pub content: String,
+    pub tags: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub last_viewed_at: Option<DateTime<Utc>>,
+    /// When the memory is due or should be brought back up, such as a date mentioned in its text
+    #[serde(default)]
+    pub remind_at: Option<DateTime<Utc>>,
+    /// How much the memory matters, from 0 to 1, boosting or demoting it in search and chat context
+    #[serde(default)]
+    pub importance: Option<f32>,
+    /// Short summary written by the upstream model, kept on one line of the frontmatter
+    #[serde(default)]
+    pub summary: Option<String>,
+    /// Folder of the memory relative to the store root, when recursive scanning is enabled
+    #[serde(default)]
+    pub collection: Option<String>,
+    /// Set for documents from external sources, which are never modified
+    #[serde(default)]
+    pub read_only: bool,
+    /// File names of attachments stored under `attachments/<id>/`
+    #[serde(default)]
+    pub attachments: Vec<String>,
+    /// Links to tickets, pull requests and documents outside the store
+    #[serde(default)]
+    pub references: Vec<Reference>,
+    /// User who created the memory in multi-user mode; unset for memories shared with everyone
+    #[serde(default)]
+    pub owner: Option<String>,
+    /// Users and groups other than the owner who can access the memory
+    #[serde(default)]
+    pub shared: Vec<Grant>,
+}
+
+impl Memory {
+    pub fn new(title: String, content: String, tags: Vec<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4().to_string(),
+            title,
+            content,
+            tags,
+            created_at: now,
+            updated_at: now,
+            last_viewed_at: None,
+            remind_at: None,
+            importance: None,
+            summary: None,
+            collection: None,
+            read_only: false,
+            attachments: Vec::new(),
+            references: Vec::new(),
+            owner: None,
+            shared: Vec::new(),
+        }
+    }
+    
+    /// Whether the memory has never been viewed, or has changed since it was last viewed
+    pub fn is_unread(&self) -> bool {
+        match self.last_viewed_at {
+            Some(viewed_at) => viewed_at < self.updated_at,
+            None => true,
+        }
+    }
+}
+
+/// A link from a memory to something outside the store, such as a ticket,
+/// pull request or document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Reference {
+    /// What the reference points at, e.g. `ticket`, `pr` or `doc`
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Local file path, for documents that are not on the web
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+impl Reference {
+    /// The URL or path the reference points at
+    pub fn target(&self) -> &str {
+        self.url.as_deref().or(self.path.as_deref()).unwrap_or_default()
+    }
+}
+
+/// Level of access to a memory; write access includes read access
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    Read,
+    Write,
+}
+
+impl Permission {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Write => "write",
+        }
+    }
+}
+
+/// Whether a grant names a single user or a group of users
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum GranteeKind {
+    User,
+    Group,
+}
+
+impl GranteeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Group => "group",
+        }
+    }
+}
+
+/// Access to a memory given to someone other than its owner
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Grant {
+    pub kind: GranteeKind,
+    /// User or group name
+    pub name: String,
+    pub permission: Permission,
+}
+
+/// A named group of memories, independent of the folder they are stored in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Collection {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub memory_ids: Vec<String>,
+    /// Settings for memories created in the collection
+    #[serde(default)]
+    pub defaults: CollectionDefaults,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Collection {
+    /// Whether a memory belongs to this collection
+    pub fn contains(&self, id: &str) -> bool {
+        self.memory_ids.iter().any(|member| member == id)
+    }
+}
+
+/// Settings a collection applies to its memories
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(default)]
+pub struct CollectionDefaults {
+    /// Content of memories created in the collection without any; `{{date}}`,
+    /// `{{time}}`, `{{weekday}}`, `{{week}}` and `{{date:<strftime>}}` are
+    /// filled in, as are `{{context.<path>}}` from the request's
+    /// `template_context` and `{{search:<name>}}` from saved searches
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// Tags added to memories created in the collection
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Days after their last change that members are moved to the trash
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retention_days: Option<u32>,
+    /// Members are never moved to the trash by the retention of another collection
+    pub pinned: bool,
+    /// Members are committed and synced in git mode; when `false` they stay on this device
+    pub sync: bool,
+}
+
+impl Default for CollectionDefaults {
+    fn default() -> Self {
+        Self {
+            template: None,
+            tags: Vec::new(),
+            retention_days: None,
+            pinned: false,
+            sync: true,
+        }
+    }
+}
+
+/// Changes to apply to a collection; unset fields are left as they are
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CollectionUpdate {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// Replaces all of the collection's defaults
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub defaults: Option<CollectionDefaults>,
+}
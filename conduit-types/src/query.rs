@@ -0,0 +1,338 @@
+//! The search query language of `POST /api/memories/search`
+//!
+//! Words match memories whose title, content or tags contain them, and all of
+//! them must match. `title:`, `content:` and `tag:` limit a word to one field,
+//! `created:` and `updated:` compare dates such as `>2024-01-01`, quotes keep
+//! a phrase together, `-` or `NOT` negates a term, `OR` matches either side
+//! and parentheses group terms:
+//!
+//! ```text
+//! title:deploy tag:rust -tag:draft "release notes" created:>=2024-01-01
+//! (tag:ops OR tag:infra) AND NOT outage
+//! ```
+
+use std::fmt;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::Memory;
+
+/// Part of a memory a text term is matched against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum TextField {
+    /// Title, content, tags or attachment text
+    Any,
+    Title,
+    Content,
+    /// A tag equal to the value, or one of its aliases
+    Tag,
+}
+
+/// Date of a memory a date term is compared with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum DateField {
+    Created,
+    Updated,
+}
+
+/// How a memory's date compares with the date of a term
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Comparison {
+    Before,
+    OnOrBefore,
+    On,
+    OnOrAfter,
+    After,
+}
+
+impl Comparison {
+    /// Whether `date` compares with `other` this way
+    pub fn holds(&self, date: NaiveDate, other: NaiveDate) -> bool {
+        match self {
+            Self::Before => date < other,
+            Self::OnOrBefore => date <= other,
+            Self::On => date == other,
+            Self::OnOrAfter => date >= other,
+            Self::After => date > other,
+        }
+    }
+}
+
+/// A parsed search query
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum SearchQuery {
+    /// Matches every memory; the query of an empty search
+    All,
+    /// A field contains the value, ignoring case; tags must equal it
+    Text { field: TextField, value: String },
+    /// A date of the memory, in UTC, compares with `date`
+    Date { field: DateField, comparison: Comparison, date: NaiveDate },
+    #[cfg_attr(feature = "openapi", schema(no_recursion))]
+    Not { query: Box<SearchQuery> },
+    #[cfg_attr(feature = "openapi", schema(no_recursion))]
+    And { queries: Vec<SearchQuery> },
+    #[cfg_attr(feature = "openapi", schema(no_recursion))]
+    Or { queries: Vec<SearchQuery> },
+}
+
+/// Part of a memory a search term was found in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum MatchField {
+    Title,
+    Content,
+    Tag,
+    /// Text extracted from an attachment
+    Attachment,
+}
+
+/// Where a search term was found in a memory
+///
+/// Offsets count characters, not bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SearchMatch {
+    pub field: MatchField,
+    /// The tag or attachment that matched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Offset of the match in the field
+    pub start: usize,
+    /// Offset just past the match in the field
+    pub end: usize,
+    /// The match with a few words around it, on one line
+    pub snippet: String,
+    /// Offset of the match in `snippet`
+    pub highlight_start: usize,
+    /// Offset just past the match in `snippet`
+    pub highlight_end: usize,
+}
+
+/// A memory found by a search, with where the query matched it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SearchResult {
+    #[serde(flatten)]
+    pub memory: Memory,
+    /// Matches of the terms that are not negated, in field order
+    pub matches: Vec<SearchMatch>,
+}
+
+/// Why a search query could not be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryError(pub String);
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    /// `-` directly in front of a term or group
+    Minus,
+    /// A term as written, quotes included
+    Word(String),
+}
+
+fn tokenize(query: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            _ => {
+                let mut word = String::new();
+                let mut quoted = false;
+                while let Some(&c) = chars.peek() {
+                    if !quoted && (c.is_whitespace() || c == '(' || c == ')') {
+                        break;
+                    }
+                    if c == '"' {
+                        quoted = !quoted;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                // A dash ahead of something negates it, while a lone one is searched for
+                match word.strip_prefix('-') {
+                    Some("") if chars.peek() == Some(&'(') => tokens.push(Token::Minus),
+                    Some(rest) if !rest.is_empty() => {
+                        tokens.push(Token::Minus);
+                        tokens.push(Token::Word(rest.to_string()));
+                    }
+                    _ => tokens.push(Token::Word(word)),
+                }
+            }
+        }
+    }
+    tokens
+}
+
+fn unquote(value: &str) -> String {
+    value.replace('"', "")
+}
+
+fn parse_date(field: &str, value: &str) -> Result<(Comparison, NaiveDate), QueryError> {
+    let (comparison, date) = [
+        (">=", Comparison::OnOrAfter),
+        ("<=", Comparison::OnOrBefore),
+        (">", Comparison::After),
+        ("<", Comparison::Before),
+        ("=", Comparison::On),
+    ]
+        .into_iter()
+        .find_map(|(prefix, comparison)| Some((comparison, value.strip_prefix(prefix)?)))
+        .unwrap_or((Comparison::On, value));
+    let date = NaiveDate::parse_from_str(&unquote(date), "%Y-%m-%d")
+        .map_err(|_| QueryError(format!("{}: needs a date such as >2024-01-01, not {}", field, value)))?;
+    Ok((comparison, date))
+}
+
+// A single term, such as `rust`, `tag:rust` or `created:>2024-01-01`
+fn term(word: &str) -> Result<SearchQuery, QueryError> {
+    if !word.starts_with('"') {
+        if let Some((field, value)) = word.split_once(':') {
+            let name = field.to_lowercase();
+            let text_field = match name.as_str() {
+                "title" => Some(TextField::Title),
+                "content" => Some(TextField::Content),
+                "tag" => Some(TextField::Tag),
+                _ => None,
+            };
+            let date_field = match name.as_str() {
+                "created" => Some(DateField::Created),
+                "updated" => Some(DateField::Updated),
+                _ => None,
+            };
+            if (text_field.is_some() || date_field.is_some()) && unquote(value).is_empty() {
+                return Err(QueryError(format!("{}: needs a value", field)));
+            }
+            if let Some(field) = text_field {
+                return Ok(SearchQuery::Text { field, value: unquote(value) });
+            }
+            if let Some(date_field) = date_field {
+                let (comparison, date) = parse_date(field, value)?;
+                return Ok(SearchQuery::Date { field: date_field, comparison, date });
+            }
+        }
+    }
+    Ok(SearchQuery::Text { field: TextField::Any, value: unquote(word) })
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(word)) if word == keyword)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<SearchQuery, QueryError> {
+        let mut queries = vec![self.and()?];
+        while self.peek_keyword("OR") {
+            self.next();
+            queries.push(self.and()?);
+        }
+        Ok(if queries.len() == 1 { queries.remove(0) } else { SearchQuery::Or { queries } })
+    }
+
+    // Terms next to each other must all match, with or without AND between them
+    fn and(&mut self) -> Result<SearchQuery, QueryError> {
+        let mut queries = Vec::new();
+        loop {
+            match self.peek() {
+                None | Some(Token::Close) => break,
+                _ if self.peek_keyword("OR") => break,
+                _ if self.peek_keyword("AND") => {
+                    self.next();
+                }
+                _ => queries.push(self.unary()?),
+            }
+        }
+        match queries.len() {
+            0 => Err(QueryError("Expected a search term".to_string())),
+            1 => Ok(queries.remove(0)),
+            _ => Ok(SearchQuery::And { queries }),
+        }
+    }
+
+    fn unary(&mut self) -> Result<SearchQuery, QueryError> {
+        match self.next() {
+            Some(Token::Minus) => Ok(SearchQuery::Not { query: Box::new(self.unary()?) }),
+            Some(Token::Word(word)) if word == "NOT" => Ok(SearchQuery::Not { query: Box::new(self.unary()?) }),
+            Some(Token::Word(word)) => term(&word),
+            Some(Token::Open) => {
+                let query = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(query),
+                    _ => Err(QueryError("Missing )".to_string())),
+                }
+            }
+            Some(Token::Close) => Err(QueryError("Unexpected )".to_string())),
+            None => Err(QueryError("Expected a search term".to_string())),
+        }
+    }
+}
+
+impl SearchQuery {
+    /// Parse a query written in the search language
+    ///
+    /// A blank query matches every memory.
+    pub fn parse(query: &str) -> Result<Self, QueryError> {
+        let mut parser = Parser { tokens: tokenize(query), position: 0 };
+        if parser.peek().is_none() {
+            return Ok(Self::All);
+        }
+        let parsed = parser.or()?;
+        match parser.peek() {
+            None => Ok(parsed),
+            Some(_) => Err(QueryError("Unexpected )".to_string())),
+        }
+    }
+}
+
+impl std::str::FromStr for SearchQuery {
+    type Err = QueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
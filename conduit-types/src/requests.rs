@@ -0,0 +1,159 @@
+//! Bodies and query parameters of the requests to `/api/memories` and `/api/collections`
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::memory::{CollectionDefaults, Grant, Reference};
+use crate::query::SearchQuery;
+
+/// Body of `POST /api/memories`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateMemoryRequest {
+    /// Generated from the content when missing or blank
+    #[serde(default)]
+    pub title: String,
+    pub content: String,
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub references: Vec<Reference>,
+    /// Set from a date in the title or content when missing and date parsing is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remind_at: Option<DateTime<Utc>>,
+    /// How much the memory matters, from 0 to 1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub importance: Option<f32>,
+    /// Collection to add the memory to, by ID; its defaults apply to the memory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collection: Option<String>,
+    /// Values for the `{{context.<path>}}` placeholders of the collection's
+    /// template, e.g. `{"git": {"branch": "main"}}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<Object>))]
+    pub template_context: Option<serde_json::Value>,
+}
+
+/// Body of `POST /api/memories/search`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SearchMemoriesRequest {
+    /// Words, phrases and filters in the search language, see [`SearchQuery`]
+    pub query: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// Only return members of this collection, or memories in this folder
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collection: Option<String>,
+    /// Also search the scratch memories of this session, listed first
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// An already parsed query, which memories must match as well as `query`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<SearchQuery>,
+}
+
+/// Body of `POST /api/memories/:id/rename`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RenameMemoryRequest {
+    pub new_id: String,
+}
+
+/// Body of `POST /api/memories/:id/revert`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RevertMemoryRequest {
+    /// Commit to bring the memory back to, as listed by its git log
+    pub commit: String,
+}
+
+/// Body of `PUT /api/memories/:id/acl`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ShareMemoryRequest {
+    /// Replaces every existing grant; empty makes an owned memory private
+    pub shared: Vec<Grant>,
+}
+
+/// Body of `PUT /api/memories/:id/importance`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SetImportanceRequest {
+    /// From 0 to 1; `null` clears it, leaving the memory to decay with age
+    pub importance: Option<f32>,
+}
+
+/// Body of `POST /api/collections`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateCollectionRequest {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub memory_ids: Vec<String>,
+    #[serde(default)]
+    pub defaults: CollectionDefaults,
+}
+
+/// Body of `POST /api/collections/:id/memories`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CollectionMembersRequest {
+    pub memory_ids: Vec<String>,
+}
+
+/// Field used to order listed memories
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum SortField {
+    #[default]
+    UpdatedAt,
+    CreatedAt,
+    Title,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// Query parameters of `GET /api/memories` and `GET /v1/memories`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct ListMemoriesQuery {
+    /// Maximum number of memories to return
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    /// Number of memories to skip
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+    /// Sort field, defaults to `updated_at`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<SortField>,
+    /// Sort order, defaults to descending
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<SortOrder>,
+    /// Only include memories with this tag (case-insensitive) or one of its aliases
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// Only include memories updated at or after this time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<DateTime<Utc>>,
+    /// Only include memories updated at or before this time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub until: Option<DateTime<Utc>>,
+    /// Only include members of the collection with this ID, or memories stored
+    /// in the folder with this name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collection: Option<String>,
+    /// Return the summary in place of the content of large memories, as set by
+    /// `large_note_chars` under `[summarize]` on the server
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub summaries: bool,
+}
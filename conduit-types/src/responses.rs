@@ -1,22 +1,8 @@
-pub mod openai;
-pub mod server;
-pub mod state;
+//! Response envelopes shared by the API and the desktop app
 
 use serde::{Deserialize, Serialize};
-use thiserror::Error;
-
-#[derive(Debug, Error)]
-pub enum ApiError {
-    #[error("Memory error: {0}")]
-    Memory(#[from] crate::memory::MemoryError),
-    
-    #[error("Invalid request: {0}")]
-    InvalidRequest(String),
-    
-    #[error("Server error: {0}")]
-    Server(String),
-}
 
+/// A payload together with an optional error message
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiResponse<T> {
     pub data: T,
@@ -0,0 +1,32 @@
+//! Scratch memories kept for the length of an agent session, under `/api/sessions`
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Body of `POST /api/sessions/:id/memories`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CreateSessionMemoryRequest {
+    /// Generated from the content when missing or blank
+    #[serde(default)]
+    pub title: String,
+    pub content: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Seconds the session is kept after this write; the server default when missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_secs: Option<u64>,
+}
+
+/// A session holding scratch memories, which are dropped when it ends or expires
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SessionInfo {
+    pub id: String,
+    /// User the session belongs to; `None` for sessions started with an API key
+    pub owner: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// When the session and its memories are dropped unless written to again
+    pub expires_at: DateTime<Utc>,
+    pub memory_count: usize,
+}
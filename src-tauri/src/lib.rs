@@ -1,5 +1,234 @@
+use std::collections::BTreeMap;
 use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use conduit_backend::api::server::ServerHandle;
+use conduit_backend::config::ConduitConfig;
 use conduit_backend::ConduitBackend;
+use conduit_types::{Collection, CollectionUpdate, Memory, Reference, SearchResult};
+use tauri::async_runtime::JoinHandle;
+use tauri::{Emitter, Manager};
+use tauri_plugin_dialog::DialogExt;
+
+/// How often the memory store is checked for disappearing, e.g. an unmounted drive
+const STORE_WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// How often the workspace lock is checked for locking after the idle timeout
+const LOCK_WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// How long a stopping API server gets to finish its requests before it is left behind
+const SHUTDOWN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// State of the API server, sent to the webview as `server-status` events
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+enum ServerStatus {
+    /// Binding its address and loading the store
+    Starting,
+    /// Serving requests at `url`
+    Ready { url: String },
+    /// Could not start, e.g. because the port is taken
+    Failed { error: String },
+    /// Stopped by `stop_api_server` or when the app exits
+    Stopped,
+}
+
+/// The backend used by every command and by the API server
+///
+/// Commands read the store from here instead of opening their own, so the
+/// app and the server never disagree about which store is in use.
+/// `set_store_path` swaps in a backend for another store.
+struct SharedBackend {
+    backend: RwLock<Arc<ConduitBackend>>,
+    server: tokio::sync::Mutex<Option<ServerHandle>>,
+    status: Mutex<ServerStatus>,
+    emitters: Mutex<Vec<JoinHandle<()>>>,
+    // Address chosen by `restart_api_server`, in place of the configured one
+    listen: Mutex<Option<SocketAddr>>,
+    read_only: bool,
+}
+
+impl SharedBackend {
+    fn new(backend: ConduitBackend, read_only: bool) -> Self {
+        Self {
+            backend: RwLock::new(Arc::new(backend)),
+            server: tokio::sync::Mutex::new(None),
+            status: Mutex::new(ServerStatus::Starting),
+            emitters: Mutex::new(Vec::new()),
+            listen: Mutex::new(None),
+            read_only,
+        }
+    }
+    
+    fn current(&self) -> Arc<ConduitBackend> {
+        self.backend.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+    
+    // The current backend, unless its workspace is locked
+    fn unlocked(&self) -> Result<Arc<ConduitBackend>, String> {
+        let backend = self.current();
+        backend.check_unlocked()?;
+        Ok(backend)
+    }
+    
+    // Forward the current store's events to the UI, stopping those of the previous store
+    fn watch(&self, app: &tauri::AppHandle) {
+        let emitters = spawn_emitters(app, &self.current());
+        let previous = std::mem::replace(&mut *self.emitters.lock().unwrap_or_else(|e| e.into_inner()), emitters);
+        for emitter in previous {
+            emitter.abort();
+        }
+    }
+    
+    // Stop a running API server, letting in-flight requests finish; returns whether one was running
+    async fn shutdown(server: &mut Option<ServerHandle>) -> bool {
+        let Some(mut running) = server.take() else {
+            return false;
+        };
+        running.shutdown();
+        match tokio::time::timeout(SHUTDOWN_TIMEOUT, running.wait()).await {
+            Ok(Ok(())) => tracing::info!("[MAIN] API server stopped"),
+            Ok(Err(e)) => tracing::warn!("[MAIN] API server stopped with an error: {}", e),
+            Err(_) => tracing::warn!("[MAIN] API server did not stop within {:?}, leaving it behind", SHUTDOWN_TIMEOUT),
+        }
+        true
+    }
+    
+    // Start the API server on the configured address, stopping the running one first
+    async fn serve(&self) -> Result<SocketAddr, String> {
+        let mut server = self.server.lock().await;
+        Self::shutdown(&mut server).await;
+        let backend = self.current();
+        let addr = self.listen.lock().unwrap_or_else(|e| e.into_inner()).unwrap_or_else(|| backend.listen_addr());
+        let mut running = backend.start_server(addr).await?;
+        running.ready().await?;
+        // The bound address, which differs from the configured one for port 0
+        let addr = running.local_addr();
+        *server = Some(running);
+        Ok(addr)
+    }
+    
+    fn status(&self) -> ServerStatus {
+        self.status.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+    
+    fn set_status(&self, app: &tauri::AppHandle, status: ServerStatus) {
+        *self.status.lock().unwrap_or_else(|e| e.into_inner()) = status.clone();
+        if let Err(e) = app.emit("server-status", status) {
+            tracing::error!("[MAIN] Failed to emit server status: {}", e);
+        }
+    }
+    
+    // Start the API server, telling the webview once it serves requests or why it could not
+    async fn start(&self, app: &tauri::AppHandle) -> Result<SocketAddr, String> {
+        self.set_status(app, ServerStatus::Starting);
+        let result = self.serve().await;
+        self.set_status(app, match &result {
+            Ok(addr) => ServerStatus::Ready { url: format!("http://{}", addr) },
+            Err(e) => ServerStatus::Failed { error: e.clone() },
+        });
+        result
+    }
+    
+    // Stop the API server, releasing its port and the store's writer lock
+    async fn stop(&self, app: &tauri::AppHandle) -> bool {
+        let stopped = Self::shutdown(&mut *self.server.lock().await).await;
+        self.set_status(app, ServerStatus::Stopped);
+        stopped
+    }
+    
+    // Switch commands, events and the API server to another backend
+    async fn replace(&self, app: &tauri::AppHandle, backend: ConduitBackend) -> Result<SocketAddr, String> {
+        *self.backend.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(backend);
+        self.watch(app);
+        let addr = self.start(app).await?;
+        tracing::info!("[MAIN] API server restarted on http://{}", addr);
+        Ok(addr)
+    }
+}
+
+// Open the backend from ~/.conduit/config.toml and CONDUIT_* variables, optionally on another store
+fn open_backend(read_only: bool, store: Option<PathBuf>) -> Result<ConduitBackend, String> {
+    let mut config = ConduitConfig::load()?;
+    config.storage.options.read_only |= read_only;
+    if let Some(store) = store {
+        // A picked folder becomes a store even when it already exists, empty
+        if !config.storage.options.read_only {
+            conduit_backend::memory::init_store_dir(&store).map_err(|e| format!("Failed to set up store {}: {}", store.display(), e))?;
+        }
+        // A chosen store replaces the `[storage]` one, so it is opened whatever workspace was active
+        config.storage.path = Some(store);
+        config.workspaces.active = None;
+    }
+    ConduitBackend::from_settings(config)
+}
+
+// Open a backend for a workspace named in the config file
+fn open_workspace(read_only: bool, name: &str) -> Result<ConduitBackend, String> {
+    let mut config = ConduitConfig::load()?;
+    config.storage.options.read_only |= read_only;
+    config.workspaces.active = Some(name.to_string());
+    ConduitBackend::from_settings(config)
+}
+
+// Emit `store-status`, `workspace-lock`, `memory-changed` and `memory-resync` events for a backend's store
+fn spawn_emitters(app: &tauri::AppHandle, backend: &ConduitBackend) -> Vec<JoinHandle<()>> {
+    let mut emitters = Vec::new();
+    
+    // Emit `store-status` whenever the memory store disappears or comes back
+    match backend.watch_store_status(STORE_WATCH_INTERVAL) {
+        Ok(mut status) => {
+            let handle = app.clone();
+            emitters.push(tauri::async_runtime::spawn(async move {
+                while status.changed().await.is_ok() {
+                    let current = status.borrow_and_update().clone();
+                    if let Err(e) = handle.emit("store-status", current) {
+                        tracing::error!("[MAIN] Failed to emit store status: {}", e);
+                    }
+                }
+            }));
+        }
+        Err(e) => tracing::warn!("[MAIN] Not watching the memory store: {}", e),
+    }
+    
+    // Emit `workspace-lock` whenever the workspace locks or unlocks, so the UI can show its lock screen
+    match backend.watch_lock_status(LOCK_WATCH_INTERVAL) {
+        Ok(mut status) => {
+            let handle = app.clone();
+            emitters.push(tauri::async_runtime::spawn(async move {
+                while status.changed().await.is_ok() {
+                    let current = status.borrow_and_update().clone();
+                    if let Err(e) = handle.emit("workspace-lock", current) {
+                        tracing::error!("[MAIN] Failed to emit workspace lock status: {}", e);
+                    }
+                }
+            }));
+        }
+        Err(e) => tracing::warn!("[MAIN] Not watching the workspace lock: {}", e),
+    }
+    
+    // Emit `memory-changed` for every change, so the UI refreshes without polling
+    match backend.memory_events() {
+        Ok(mut events) => {
+            let handle = app.clone();
+            emitters.push(tauri::async_runtime::spawn(async move {
+                loop {
+                    let emitted = match events.recv().await {
+                        Ok(event) => handle.emit("memory-changed", event),
+                        // Changes were missed, so the UI should reload everything
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(missed)) => handle.emit("memory-resync", missed),
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    };
+                    if let Err(e) = emitted {
+                        tracing::error!("[MAIN] Failed to emit memory change: {}", e);
+                    }
+                }
+            }));
+        }
+        Err(e) => tracing::warn!("[MAIN] Not watching memory changes: {}", e),
+    }
+    emitters
+}
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 #[tauri::command]
@@ -8,83 +237,620 @@ fn greet(name: &str) -> String {
 }
 
 #[tauri::command]
-async fn start_api_server(docs_path: Option<String>, port: u16) -> Result<String, String> {
+async fn start_api_server(port: u16, shared: tauri::State<'_, SharedBackend>) -> Result<String, String> {
     tracing::info!("[TAURI] Starting API server with port: {}", port);
     
-    // Initialize the backend with the provided docs_path
-    let backend = match ConduitBackend::new(docs_path) {
-        Ok(backend) => backend,
-        Err(e) => {
-            let err_msg = format!("Failed to initialize backend: {}", e);
-            tracing::error!("[TAURI] {}", err_msg);
-            return Err(err_msg);
+    // Serve the shared backend, so both servers see the same store
+    let backend = shared.current();
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    tracing::info!("[TAURI] Using shared backend and address: {}", addr);
+    
+    let started = match backend.start_server(addr).await {
+        Ok(mut server) => server.ready().await.map(|_| server.local_addr()),
+        Err(e) => Err(e),
+    };
+    match started {
+        Ok(addr) => Ok(format!("API server started on http://{}", addr)),
+        Err(e) => Err(format!("Failed to start API server: {}", e)),
+    }
+}
+
+/// Stop the API server started with the app, releasing its port and the store's writer lock
+///
+/// Commands keep working on the store. Returns whether a server was running.
+#[tauri::command]
+async fn stop_api_server(app: tauri::AppHandle, shared: tauri::State<'_, SharedBackend>) -> Result<bool, String> {
+    tracing::info!("[TAURI] Stopping API server");
+    Ok(shared.stop(&app).await)
+}
+
+/// Restart the API server started with the app, optionally on another port or store
+///
+/// A new `memory_path` is opened before the server stops and is saved to the
+/// config file, as with `set_store_path`. A new `port` keeps the configured
+/// host and lasts until the app exits. Returns the URL the restarted server
+/// is ready at.
+#[tauri::command]
+async fn restart_api_server(
+    app: tauri::AppHandle,
+    port: Option<u16>,
+    memory_path: Option<String>,
+    shared: tauri::State<'_, SharedBackend>,
+) -> Result<String, String> {
+    tracing::info!("[TAURI] Restarting API server (port: {:?}, store: {:?})", port, memory_path);
+    if let Some(port) = port {
+        let host = shared.current().listen_addr().ip();
+        *shared.listen.lock().unwrap_or_else(|e| e.into_inner()) = Some(SocketAddr::new(host, port));
+    }
+    
+    let addr = match memory_path {
+        Some(path) => {
+            // Open the new store before saving it, so a bad path leaves the config untouched
+            let path = PathBuf::from(path);
+            let backend = open_backend(shared.read_only, Some(path.clone()))?;
+            ConduitConfig::set_store_path(&path)?;
+            shared.replace(&app, backend).await?
         }
+        None => shared.start(&app).await?,
     };
+    Ok(format!("http://{}", addr))
+}
+
+/// Version, bound address and URL of the API server started with the app
+///
+/// The address is the one actually bound, so with port 0 under `[listen]` the
+/// webview learns which port the system picked.
+#[tauri::command]
+async fn get_server_info(shared: tauri::State<'_, SharedBackend>) -> Result<conduit_backend::api::server::ServerInfo, String> {
+    match shared.server.lock().await.as_ref() {
+        Some(server) => Ok(server.info()),
+        None => Err(match shared.status() {
+            ServerStatus::Failed { error } => format!("API server failed to start: {}", error),
+            ServerStatus::Stopped => "API server is stopped".to_string(),
+            _ => "API server is not running yet".to_string(),
+        }),
+    }
+}
+
+/// State of the API server started with the app
+///
+/// The webview calls this once it has loaded, since `server-status` events
+/// sent before it subscribed are lost, and then follows the events.
+#[tauri::command]
+fn server_status(shared: tauri::State<'_, SharedBackend>) -> ServerStatus {
+    shared.status()
+}
+
+/// Save `path` as the memory store and switch the app and the API server to it
+///
+/// The path is written to the `[storage]` section of the config file, so the
+/// next launch opens the same store. Returns the status of the new store.
+#[tauri::command]
+async fn set_store_path(app: tauri::AppHandle, path: String, shared: tauri::State<'_, SharedBackend>) -> Result<conduit_backend::memory::StoreStatus, String> {
+    tracing::info!("[TAURI] Switching memory store to {}", path);
+    let path = PathBuf::from(path);
     
-    let addr = SocketAddr::from(([127, 0, 0, 1], port));
-    tracing::info!("[TAURI] Created backend and address: {}", addr);
+    // Open the new store before saving it, so a bad path leaves the config untouched
+    let backend = open_backend(shared.read_only, Some(path.clone()))?;
+    ConduitConfig::set_store_path(&path)?;
     
-    match backend.start_server(addr).await {
-        Ok(_) => Ok(format!("API server started on http://{}", addr)),
-        Err(e) => Err(format!("Failed to start API server: {}", e)),
+    shared.replace(&app, backend).await?;
+    shared.current().store_status()
+}
+
+/// Workspaces in the config file, by name, and the one the app has open
+#[derive(Debug, Clone, serde::Serialize)]
+struct WorkspaceList {
+    active: String,
+    workspaces: BTreeMap<String, PathBuf>,
+}
+
+fn workspace_list() -> Result<WorkspaceList, String> {
+    let config = ConduitConfig::load()?;
+    Ok(WorkspaceList {
+        active: config.workspaces.active_name().to_string(),
+        workspaces: config.workspace_paths(),
+    })
+}
+
+/// The configured workspaces, which the webview can also address one request
+/// at a time with the `X-Conduit-Workspace` header
+#[tauri::command]
+fn list_workspaces() -> Result<WorkspaceList, String> {
+    workspace_list()
+}
+
+/// Add a workspace with its store at `path` to the config file
+///
+/// The API server is restarted so it hosts the new workspace; the app stays
+/// on the workspace it has open.
+#[tauri::command]
+async fn create_workspace(app: tauri::AppHandle, name: String, path: String, shared: tauri::State<'_, SharedBackend>) -> Result<WorkspaceList, String> {
+    tracing::info!("[TAURI] Creating workspace {} at {}", name, path);
+    ConduitConfig::add_workspace(&name, &PathBuf::from(path))?;
+    let active = workspace_list()?.active;
+    let backend = open_workspace(shared.read_only, &active)?;
+    shared.replace(&app, backend).await?;
+    workspace_list()
+}
+
+/// Open another workspace in the app and make it the API server's default
+///
+/// The choice is saved to the config file, so the next launch opens the same
+/// workspace. Returns the status of the workspace's store.
+#[tauri::command]
+async fn switch_workspace(app: tauri::AppHandle, name: String, shared: tauri::State<'_, SharedBackend>) -> Result<conduit_backend::memory::StoreStatus, String> {
+    tracing::info!("[TAURI] Switching to workspace {}", name);
+    
+    // Open the workspace before saving it, so a missing store leaves the config untouched
+    let backend = open_workspace(shared.read_only, &name)?;
+    ConduitConfig::set_active_workspace(&name)?;
+    
+    shared.replace(&app, backend).await?;
+    shared.current().store_status()
+}
+
+/// Whether the onboarding flow should be shown, because the store was never
+/// set up and holds nothing
+#[tauri::command]
+async fn needs_setup(shared: tauri::State<'_, SharedBackend>) -> Result<bool, String> {
+    shared.unlocked()?.needs_setup()
+}
+
+/// Finish onboarding: switch to the chosen store, import existing notes and
+/// create the chosen starter collections
+///
+/// The store is set up before it is saved to the config file, so a failed
+/// import leaves the app on its current store.
+#[tauri::command]
+async fn complete_setup(
+    app: tauri::AppHandle,
+    request: conduit_backend::api::setup::SetupRequest,
+    shared: tauri::State<'_, SharedBackend>,
+) -> Result<conduit_backend::api::setup::SetupReport, String> {
+    tracing::info!("[TAURI] Completing setup (store: {:?})", request.store_path);
+    let Some(path) = request.store_path.clone() else {
+        let backend = shared.unlocked()?;
+        return tauri::async_runtime::spawn_blocking(move || backend.setup(&request))
+            .await
+            .map_err(|e| format!("Setup failed: {}", e))?;
+    };
+
+    // Only onboarding chooses the store; afterwards `set_store_path` moves it
+    if !shared.current().needs_setup()? {
+        return Err("Setup already finished, so it can no longer choose another store".to_string());
     }
+    let backend = open_backend(shared.read_only, Some(path.clone()))?;
+    let (backend, report) = tauri::async_runtime::spawn_blocking(move || {
+        let report = backend.setup(&request)?;
+        Ok::<_, String>((backend, report))
+    })
+    .await
+    .map_err(|e| format!("Setup failed: {}", e))??;
+    ConduitConfig::set_store_path(&path)?;
+
+    shared.replace(&app, backend).await?;
+    Ok(report)
 }
 
 #[tauri::command]
-async fn create_memory(title: String, content: String, tags: Vec<String>, docs_path: Option<String>) -> Result<String, String> {
-    // Initialize the backend with the provided docs_path
-    let backend = ConduitBackend::new(docs_path)?;
+async fn create_memory(title: String, content: String, tags: Vec<String>, shared: tauri::State<'_, SharedBackend>) -> Result<String, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
     
     // Create the memory using the backend
     backend.create_memory(title, content, tags)
 }
 
 #[tauri::command]
-async fn get_memory(id: String, docs_path: Option<String>) -> Result<conduit_backend::memory::Memory, String> {
-    // Initialize the backend with the provided docs_path
-    let backend = ConduitBackend::new(docs_path)?;
+async fn get_memory(id: String, shared: tauri::State<'_, SharedBackend>) -> Result<Memory, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
     
     // Get the memory using the backend
     backend.get_memory(&id)
 }
 
 #[tauri::command]
-async fn list_memories(docs_path: Option<String>) -> Result<Vec<conduit_backend::memory::Memory>, String> {
-    // Initialize the backend with the provided docs_path
-    let backend = ConduitBackend::new(docs_path)?;
+async fn list_memories(shared: tauri::State<'_, SharedBackend>) -> Result<Vec<Memory>, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
     
     // List memories using the backend
     backend.list_memories()
 }
 
 #[tauri::command]
-async fn search_memories(query: String, docs_path: Option<String>) -> Result<Vec<conduit_backend::memory::Memory>, String> {
-    // Initialize the backend with the provided docs_path
-    let backend = ConduitBackend::new(docs_path)?;
+async fn search_memories(query: String, shared: tauri::State<'_, SharedBackend>) -> Result<Vec<Memory>, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
     
     // Search memories using the backend
     backend.search_memories(&query)
 }
 
 #[tauri::command]
-async fn delete_memory(id: String, docs_path: Option<String>) -> Result<(), String> {
-    // Initialize the backend with the provided docs_path
-    let backend = ConduitBackend::new(docs_path)?;
+async fn search_memory_results(query: String, shared: tauri::State<'_, SharedBackend>) -> Result<Vec<SearchResult>, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // Search memories, with snippets of where each matched
+    backend.search_memories_with_matches(&query)
+}
+
+#[tauri::command]
+async fn delete_memory(id: String, shared: tauri::State<'_, SharedBackend>) -> Result<(), String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
     
     // Delete the memory using the backend
     backend.delete_memory(&id)
 }
 
+#[tauri::command]
+async fn mark_memory_viewed(id: String, shared: tauri::State<'_, SharedBackend>) -> Result<Memory, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // Record the view using the backend
+    backend.mark_memory_viewed(&id)
+}
+
+#[tauri::command]
+async fn list_unread_memories(shared: tauri::State<'_, SharedBackend>) -> Result<Vec<Memory>, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // List unread memories using the backend
+    backend.list_unread_memories()
+}
+
+#[tauri::command]
+async fn rename_memory(id: String, new_id: String, shared: tauri::State<'_, SharedBackend>) -> Result<conduit_backend::memory::RenameResult, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // Rename the memory using the backend
+    backend.rename_memory(&id, &new_id)
+}
+
+#[tauri::command]
+async fn memory_history(id: String, shared: tauri::State<'_, SharedBackend>) -> Result<Vec<conduit_backend::memory::GitCommit>, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // Read the commits that changed the memory
+    backend.memory_history(&id)
+}
+
+#[tauri::command]
+async fn revert_memory(id: String, commit: String, shared: tauri::State<'_, SharedBackend>) -> Result<Memory, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // Revert the memory using the backend
+    backend.revert_memory(&id, &commit)
+}
+
+#[tauri::command]
+async fn list_trash(shared: tauri::State<'_, SharedBackend>) -> Result<Vec<conduit_backend::memory::TrashedMemory>, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // List the trash using the backend
+    backend.list_trash()
+}
+
+#[tauri::command]
+async fn restore_memory(id: String, shared: tauri::State<'_, SharedBackend>) -> Result<Memory, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // Restore the memory using the backend
+    backend.restore_memory(&id)
+}
+
+#[tauri::command]
+async fn purge_memory(id: String, shared: tauri::State<'_, SharedBackend>) -> Result<(), String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // Purge the memory using the backend
+    backend.purge_memory(&id)
+}
+
+#[tauri::command]
+async fn memory_links(id: String, shared: tauri::State<'_, SharedBackend>) -> Result<conduit_backend::memory::OutgoingLinks, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // Resolve the memory's links using the backend
+    backend.memory_links(&id)
+}
+
+#[tauri::command]
+async fn memory_backlinks(id: String, shared: tauri::State<'_, SharedBackend>) -> Result<conduit_backend::memory::Backlinks, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // Find backlinks using the backend
+    backend.memory_backlinks(&id)
+}
+
+#[tauri::command]
+async fn related_memories(id: String, limit: Option<usize>, shared: tauri::State<'_, SharedBackend>) -> Result<Vec<conduit_backend::memory::RelatedMemory>, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // Score the other memories against this one
+    backend.related_memories(&id, limit)
+}
+
+#[tauri::command]
+async fn memory_graph(options: Option<conduit_backend::memory::GraphOptions>, shared: tauri::State<'_, SharedBackend>) -> Result<conduit_backend::memory::Graph, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // Build the graph using the backend
+    backend.memory_graph(&options.unwrap_or_default())
+}
+
+#[tauri::command]
+async fn list_collections(shared: tauri::State<'_, SharedBackend>) -> Result<Vec<Collection>, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // List collections using the backend
+    backend.list_collections()
+}
+
+#[tauri::command]
+async fn get_collection(id: String, shared: tauri::State<'_, SharedBackend>) -> Result<Collection, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // Get the collection using the backend
+    backend.get_collection(&id)
+}
+
+#[tauri::command]
+async fn create_collection(name: String, description: Option<String>, memory_ids: Vec<String>, shared: tauri::State<'_, SharedBackend>) -> Result<Collection, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // Create the collection using the backend
+    backend.create_collection(&name, description.as_deref(), memory_ids)
+}
+
+#[tauri::command]
+async fn update_collection(id: String, update: CollectionUpdate, shared: tauri::State<'_, SharedBackend>) -> Result<Collection, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // Update the collection using the backend
+    backend.update_collection(&id, update)
+}
+
+#[tauri::command]
+async fn delete_collection(id: String, shared: tauri::State<'_, SharedBackend>) -> Result<(), String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // Delete the collection using the backend
+    backend.delete_collection(&id)
+}
+
+#[tauri::command]
+async fn add_to_collection(id: String, memory_ids: Vec<String>, shared: tauri::State<'_, SharedBackend>) -> Result<Collection, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // Add the memories using the backend
+    backend.add_to_collection(&id, &memory_ids)
+}
+
+#[tauri::command]
+async fn remove_from_collection(id: String, memory_id: String, shared: tauri::State<'_, SharedBackend>) -> Result<Collection, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // Remove the memory using the backend
+    backend.remove_from_collection(&id, &memory_id)
+}
+
+#[tauri::command]
+async fn add_attachment(id: String, name: String, data: Vec<u8>, shared: tauri::State<'_, SharedBackend>) -> Result<conduit_backend::memory::Attachment, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // Store the attachment using the backend
+    backend.add_attachment(&id, &name, &data)
+}
+
+#[tauri::command]
+async fn list_attachments(id: String, shared: tauri::State<'_, SharedBackend>) -> Result<Vec<conduit_backend::memory::Attachment>, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // List attachments using the backend
+    backend.list_attachments(&id)
+}
+
+#[tauri::command]
+async fn read_attachment(id: String, name: String, shared: tauri::State<'_, SharedBackend>) -> Result<Vec<u8>, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // Read the attachment using the backend
+    backend.read_attachment(&id, &name)
+}
+
+#[tauri::command]
+async fn remove_attachment(id: String, name: String, shared: tauri::State<'_, SharedBackend>) -> Result<(), String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // Remove the attachment using the backend
+    backend.remove_attachment(&id, &name)
+}
+
+#[tauri::command]
+async fn memory_references(id: String, shared: tauri::State<'_, SharedBackend>) -> Result<Vec<Reference>, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // List references using the backend
+    backend.memory_references(&id)
+}
+
+#[tauri::command]
+async fn set_memory_references(id: String, references: Vec<Reference>, shared: tauri::State<'_, SharedBackend>) -> Result<Memory, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // Replace the references using the backend
+    backend.set_memory_references(&id, references)
+}
+
+#[tauri::command]
+async fn set_memory_importance(id: String, importance: Option<f32>, shared: tauri::State<'_, SharedBackend>) -> Result<Memory, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // Set or clear the importance using the backend
+    backend.set_memory_importance(&id, importance)
+}
+
+#[tauri::command]
+async fn add_memory_reference(id: String, reference: Reference, shared: tauri::State<'_, SharedBackend>) -> Result<Memory, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // Add the reference using the backend
+    backend.add_memory_reference(&id, reference)
+}
+
+#[tauri::command]
+async fn remove_memory_reference(id: String, index: usize, shared: tauri::State<'_, SharedBackend>) -> Result<Memory, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // Remove the reference using the backend
+    backend.remove_memory_reference(&id, index)
+}
+
+#[tauri::command]
+async fn collect_garbage(dry_run: bool, shared: tauri::State<'_, SharedBackend>) -> Result<conduit_backend::memory::GcReport, String> {
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // Collect orphaned artifacts using the backend
+    backend.collect_garbage(dry_run)
+}
+
+#[tauri::command]
+async fn export_backup(app: tauri::AppHandle, shared: tauri::State<'_, SharedBackend>) -> Result<Option<conduit_backend::memory::ExportReport>, String> {
+    // Ask where to save the zip; cancelling the dialog exports nothing
+    let (chosen, choice) = tokio::sync::oneshot::channel();
+    app.dialog()
+        .file()
+        .set_title("Save backup")
+        .set_file_name("conduit-backup.zip")
+        .add_filter("Zip archive", &["zip"])
+        .save_file(move |path| {
+            let _ = chosen.send(path);
+        });
+    let Some(path) = choice.await.map_err(|e| e.to_string())? else {
+        return Ok(None);
+    };
+    let path = path.into_path().map_err(|e| e.to_string())?;
+    
+    // Use the backend shared with the API server
+    let backend = shared.unlocked()?;
+    
+    // Write the backup using the backend
+    backend.export_to(path).map(Some)
+}
+
+// Name of an emergency export, unique to the second
+fn emergency_export_name() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    format!("conduit-export-{}.zip", secs)
+}
+
+#[tauri::command]
+async fn emergency_export(app: tauri::AppHandle, destination: Option<String>, shared: tauri::State<'_, SharedBackend>) -> Result<Option<conduit_backend::memory::ExportReport>, String> {
+    // Export to the given folder, or ask for one; cancelling the dialog exports nothing
+    let folder = match destination {
+        Some(folder) => PathBuf::from(folder),
+        None => {
+            let (chosen, choice) = tokio::sync::oneshot::channel();
+            app.dialog()
+                .file()
+                .set_title("Choose where to save your memories")
+                .pick_folder(move |path| {
+                    let _ = chosen.send(path);
+                });
+            let Some(path) = choice.await.map_err(|e| e.to_string())? else {
+                return Ok(None);
+            };
+            path.into_path().map_err(|e| e.to_string())?
+        }
+    };
+    let path = folder.join(emergency_export_name());
+    tracing::info!("[TAURI] Emergency export to {}", path.display());
+    
+    // Read the store folder directly, so a server that failed to start or hangs does not matter
+    let backend = shared.current();
+    tokio::task::spawn_blocking(move || backend.emergency_export(path))
+        .await
+        .map_err(|e| e.to_string())?
+        .map(Some)
+}
+
+#[tauri::command]
+async fn store_status(shared: tauri::State<'_, SharedBackend>) -> Result<conduit_backend::memory::StoreStatus, String> {
+    // Use the backend shared with the API server
+    let backend = shared.current();
+    
+    // Check the store using the backend
+    backend.store_status()
+}
+
+#[tauri::command]
+async fn workspace_lock_status(shared: tauri::State<'_, SharedBackend>) -> Result<conduit_backend::memory::LockStatus, String> {
+    shared.current().lock_status()
+}
+
+#[tauri::command]
+async fn lock_workspace(shared: tauri::State<'_, SharedBackend>) -> Result<conduit_backend::memory::LockStatus, String> {
+    tracing::info!("[TAURI] Locking the workspace");
+    shared.current().lock_workspace()
+}
+
+#[tauri::command]
+async fn unlock_workspace(passphrase: String, shared: tauri::State<'_, SharedBackend>) -> Result<conduit_backend::memory::LockStatus, String> {
+    // Unlocking is only offered here, not through the API the lock guards
+    shared.current().unlock_workspace(&passphrase)
+}
+
+#[tauri::command]
+async fn set_workspace_passphrase(current: Option<String>, passphrase: Option<String>, shared: tauri::State<'_, SharedBackend>) -> Result<conduit_backend::memory::LockStatus, String> {
+    shared.current().set_lock_passphrase(current.as_deref(), passphrase.as_deref())
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     // Initialize tracing for better logging
     tracing_subscriber::fmt::init();
     
-    // Start the API server on a separate thread
-    let port = 3000;
-    
-    // Initialize the backend with default memory path
-    let backend = match ConduitBackend::new(None) {
+    // Initialize the backend from ~/.conduit/config.toml and CONDUIT_* variables;
+    // `--read-only` leaves a store that another process syncs or inspects untouched
+    let read_only = std::env::args().any(|arg| arg == "--read-only");
+    let backend = match open_backend(read_only, None) {
         Ok(backend) => backend,
         Err(e) => {
             eprintln!("Failed to initialize backend: {}", e);
@@ -92,44 +858,98 @@ pub fn run() {
         }
     };
     
-    let addr = SocketAddr::from(([0, 0, 0, 0], port)); // Use 0.0.0.0 to allow external connections
-    tracing::info!("[MAIN] Created backend and address: {}", addr);
-    
-    // Create a runtime for the API server
-    let rt = tokio::runtime::Builder::new_multi_thread()
-        .enable_all()
-        .build()
-        .expect("Failed to create Tokio runtime");
-    
-    // Start the API server in the background
-    let _server_handle = rt.spawn(async move {
-        tracing::info!("[MAIN] Starting API server on {}", addr);
-        match backend.start_server(addr).await {
-            Ok(_) => {
-                tracing::info!("[MAIN] API server started on http://{}", addr);
-                println!("API server started on http://{}", addr);
-            },
-            Err(e) => {
-                tracing::error!("[MAIN] Failed to start API server: {}", e);
-                eprintln!("Failed to start API server: {}", e);
-            },
-        }
-    });
-    
-    // Give the server a moment to start up
-    std::thread::sleep(std::time::Duration::from_secs(1));
-    
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_dialog::init())
+        .manage(SharedBackend::new(backend, read_only))
+        .setup(|app| {
+            let shared = app.state::<SharedBackend>();
+            shared.watch(app.handle());
+            
+            // Start the API server, on 0.0.0.0:3000 unless `[listen]` says otherwise, without
+            // holding up the window; the webview waits for `server-status` to be ready
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let shared = handle.state::<SharedBackend>();
+                match shared.start(&handle).await {
+                    Ok(addr) => {
+                        tracing::info!("[MAIN] API server started on http://{}", addr);
+                        println!("API server started on http://{}", addr);
+                    },
+                    Err(e) => {
+                        tracing::error!("[MAIN] Failed to start API server: {}", e);
+                        eprintln!("Failed to start API server: {}", e);
+                    },
+                }
+            });
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             greet,
             start_api_server,
+            stop_api_server,
+            restart_api_server,
+            server_status,
+            get_server_info,
+            set_store_path,
+            list_workspaces,
+            create_workspace,
+            switch_workspace,
+            needs_setup,
+            complete_setup,
             create_memory,
             get_memory,
             list_memories,
             search_memories,
-            delete_memory
+            search_memory_results,
+            delete_memory,
+            mark_memory_viewed,
+            list_unread_memories,
+            rename_memory,
+            memory_history,
+            revert_memory,
+            list_trash,
+            restore_memory,
+            purge_memory,
+            collect_garbage,
+            export_backup,
+            emergency_export,
+            store_status,
+            add_attachment,
+            list_attachments,
+            read_attachment,
+            remove_attachment,
+            memory_references,
+            set_memory_references,
+            add_memory_reference,
+            remove_memory_reference,
+            set_memory_importance,
+            memory_links,
+            memory_backlinks,
+            related_memories,
+            memory_graph,
+            list_collections,
+            get_collection,
+            create_collection,
+            update_collection,
+            delete_collection,
+            add_to_collection,
+            remove_from_collection,
+            workspace_lock_status,
+            lock_workspace,
+            unlock_workspace,
+            set_workspace_passphrase
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Stop the API server before the process ends, so in-flight requests
+            // finish and the store's writer lock is released for the next launch
+            if let tauri::RunEvent::Exit = event {
+                let shared = app.state::<SharedBackend>();
+                if tauri::async_runtime::block_on(shared.stop(app)) {
+                    tracing::info!("[MAIN] API server stopped on exit");
+                }
+            }
+        });
 }